@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS tbl_user (
+    id SERIAL  PRIMARY KEY,
+    username  varchar(100) NOT NULL,
+    password  varchar(150) NOT NULL,
+    firstname varchar(50)  NOT NULL,
+    lastname  varchar(50)  NOT NULL,
+    create_ts bigint NOT NULL DEFAULT trunc(extract(epoch from now() at time zone 'UTC-5')),
+    update_ts bigint NOT NULL DEFAULT trunc(extract(epoch from now() at time zone 'UTC-5'))
+);
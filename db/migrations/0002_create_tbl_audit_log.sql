@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS tbl_audit_log (
+    id              BIGSERIAL    PRIMARY KEY,
+    action          varchar(50)  NOT NULL,
+    actor_id        integer,
+    target_id       integer,
+    target_username varchar(100),
+    ip              varchar(64),
+    user_agent      varchar(512),
+    request_id      varchar(128),
+    changes         jsonb        NOT NULL DEFAULT '{}',
+    create_ts       timestamptz  NOT NULL DEFAULT now()
+);
+
+-- No foreign keys: entries must outlive the accounts they describe.
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON tbl_audit_log (target_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON tbl_audit_log (actor_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_action ON tbl_audit_log (action, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_create_ts ON tbl_audit_log (create_ts);
+
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'tbl_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_audit_log_append_only
+    BEFORE UPDATE OR DELETE OR TRUNCATE ON tbl_audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
@@ -0,0 +1,17 @@
+-- mfa_secret is set on enrollment and only trusted once mfa_enabled is true.
+-- mfa_last_step is the TOTP time step of the last accepted code, so a code
+-- can't be replayed within its validity window.
+ALTER TABLE tbl_user
+    ADD COLUMN IF NOT EXISTS mfa_secret    varchar(64),
+    ADD COLUMN IF NOT EXISTS mfa_enabled   boolean NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS mfa_last_step bigint;
+
+CREATE TABLE IF NOT EXISTS tbl_mfa_recovery_code (
+    id        SERIAL      PRIMARY KEY,
+    user_id   integer     NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    code_hash varchar(64) NOT NULL, -- hex SHA-256 of the normalized code
+    used_ts   timestamptz,
+    create_ts timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mfa_recovery_code_user_hash ON tbl_mfa_recovery_code (user_id, code_hash);
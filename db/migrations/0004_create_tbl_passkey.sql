@@ -0,0 +1,27 @@
+-- WebAuthn credentials. public_key is the uncompressed SEC1 P-256 point taken
+-- from the COSE key at registration; sign_count is the authenticator's
+-- counter from the last accepted assertion.
+CREATE TABLE IF NOT EXISTS tbl_passkey (
+    id            SERIAL      PRIMARY KEY,
+    user_id       integer     NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    credential_id bytea       NOT NULL,
+    public_key    bytea       NOT NULL,
+    sign_count    bigint      NOT NULL DEFAULT 0,
+    name          varchar(64) NOT NULL,
+    create_ts     timestamptz NOT NULL DEFAULT now(),
+    last_used_ts  timestamptz
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_passkey_credential_id ON tbl_passkey (credential_id);
+CREATE INDEX IF NOT EXISTS idx_passkey_user_id ON tbl_passkey (user_id);
+
+-- Outstanding ceremony challenges. A row is deleted when it is answered, so
+-- each challenge is accepted at most once; user_id is set for registration.
+CREATE TABLE IF NOT EXISTS tbl_passkey_challenge (
+    challenge varchar(64) PRIMARY KEY,
+    ceremony  varchar(16) NOT NULL,
+    user_id   integer     REFERENCES tbl_user(id) ON DELETE CASCADE,
+    expire_ts timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_passkey_challenge_expire_ts ON tbl_passkey_challenge (expire_ts);
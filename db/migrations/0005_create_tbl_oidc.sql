@@ -0,0 +1,42 @@
+-- Applications that sign users in through this service. Public clients (SPAs,
+-- native apps) have no secret and rely on PKCE alone; confidential clients
+-- store the hex SHA-256 of their secret.
+CREATE TABLE IF NOT EXISTS tbl_oidc_client (
+    id            SERIAL       PRIMARY KEY,
+    client_id     varchar(64)  NOT NULL,
+    secret_hash   varchar(64),
+    name          varchar(128) NOT NULL,
+    redirect_uris text[]       NOT NULL,
+    create_ts     timestamptz  NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_oidc_client_client_id ON tbl_oidc_client (client_id);
+
+-- Authorization codes waiting to be exchanged at the token endpoint. A row is
+-- deleted when it is redeemed, so each code works at most once.
+CREATE TABLE IF NOT EXISTS tbl_oidc_code (
+    code_hash      varchar(64)   PRIMARY KEY,
+    client_id      varchar(64)   NOT NULL REFERENCES tbl_oidc_client(client_id) ON DELETE CASCADE,
+    user_id        integer       NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    redirect_uri   varchar(2048) NOT NULL,
+    scope          varchar(256)  NOT NULL,
+    nonce          varchar(256),
+    code_challenge varchar(128)  NOT NULL, -- S256 of the client's code_verifier
+    auth_time      bigint        NOT NULL,
+    expire_ts      timestamptz   NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_oidc_code_expire_ts ON tbl_oidc_code (expire_ts);
+
+-- Refresh tokens are rotated: redeeming one deletes it and issues the next.
+CREATE TABLE IF NOT EXISTS tbl_oidc_refresh_token (
+    token_hash varchar(64)  PRIMARY KEY,
+    client_id  varchar(64)  NOT NULL REFERENCES tbl_oidc_client(client_id) ON DELETE CASCADE,
+    user_id    integer      NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    scope      varchar(256) NOT NULL,
+    auth_time  bigint       NOT NULL,
+    expire_ts  timestamptz  NOT NULL,
+    create_ts  timestamptz  NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_oidc_refresh_token_user_id ON tbl_oidc_refresh_token (user_id);
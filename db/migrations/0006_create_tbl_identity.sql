@@ -0,0 +1,27 @@
+-- External OpenID providers users may sign in with. Only the issuer and our
+-- client_id there are needed: the ID token is obtained by the application and
+-- checked against the provider's published keys.
+CREATE TABLE IF NOT EXISTS tbl_identity_provider (
+    id               SERIAL        PRIMARY KEY,
+    name             varchar(64)   NOT NULL,
+    issuer           varchar(2048) NOT NULL,
+    client_id        varchar(256)  NOT NULL,
+    jit_provisioning boolean       NOT NULL DEFAULT false, -- create an account on first login
+    create_ts        timestamptz   NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_identity_provider_name ON tbl_identity_provider (name);
+
+-- Links an account at a provider (its sub claim) to a local user.
+CREATE TABLE IF NOT EXISTS tbl_user_identity (
+    id            SERIAL       PRIMARY KEY,
+    user_id       integer      NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    provider_id   integer      NOT NULL REFERENCES tbl_identity_provider(id) ON DELETE CASCADE,
+    subject       varchar(255) NOT NULL,
+    email         varchar(320),
+    create_ts     timestamptz  NOT NULL DEFAULT now(),
+    last_login_ts timestamptz
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_identity_subject ON tbl_user_identity (provider_id, subject);
+CREATE INDEX IF NOT EXISTS idx_user_identity_user_id ON tbl_user_identity (user_id);
@@ -0,0 +1,17 @@
+-- Long-lived credentials for service callers, acting as their owner within
+-- their scopes. The key is "rck_<prefix>_<secret>": prefix finds the row and
+-- only the hex SHA-256 of the secret is stored.
+CREATE TABLE IF NOT EXISTS tbl_api_key (
+    id           SERIAL        PRIMARY KEY,
+    user_id      integer       NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    name         varchar(64)   NOT NULL,
+    prefix       varchar(16)   NOT NULL,
+    secret_hash  varchar(64)   NOT NULL,
+    scopes       varchar(32)[] NOT NULL,
+    expire_ts    timestamptz,              -- NULL for keys that don't expire
+    create_ts    timestamptz   NOT NULL DEFAULT now(),
+    last_used_ts timestamptz
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_api_key_prefix ON tbl_api_key (prefix);
+CREATE INDEX IF NOT EXISTS idx_api_key_user_id ON tbl_api_key (user_id);
@@ -0,0 +1,15 @@
+-- One row per login. Access tokens carry the session id and are refused once
+-- the row is gone, so deleting it signs that device out. The refresh token
+-- issued with the session is stored as hex SHA-256.
+CREATE TABLE IF NOT EXISTS tbl_session (
+    id                 SERIAL       PRIMARY KEY,
+    user_id            integer      NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    user_agent         varchar(512),
+    ip                 varchar(64),
+    refresh_token_hash varchar(64)  NOT NULL,
+    create_ts          timestamptz  NOT NULL DEFAULT now(),
+    last_seen_ts       timestamptz  NOT NULL DEFAULT now(),
+    expire_ts          timestamptz  NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_session_user_id ON tbl_session (user_id);
@@ -0,0 +1,7 @@
+-- Account lifecycle. Transitions are enforced by the application; only active
+-- accounts can log in. status_reason is the admin's note when disabling.
+ALTER TABLE tbl_user ADD COLUMN IF NOT EXISTS status varchar(16) NOT NULL DEFAULT 'active'
+    CHECK (status IN ('active', 'disabled', 'locked', 'pending'));
+ALTER TABLE tbl_user ADD COLUMN IF NOT EXISTS status_reason varchar(256);
+
+CREATE INDEX IF NOT EXISTS idx_user_status ON tbl_user (status);
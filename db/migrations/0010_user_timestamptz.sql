@@ -0,0 +1,30 @@
+-- create_ts and update_ts were epoch seconds defaulted with
+-- now() at time zone 'UTC-5', which is the wall clock at UTC+5 (POSIX zone
+-- names count the other way), read back as if it were UTC: 5 hours ahead.
+-- create_ts always came from that default. update_ts did too until the
+-- first update, which set it from the application clock; rows never updated
+-- still have both columns equal.
+ALTER TABLE tbl_user ALTER COLUMN create_ts DROP DEFAULT;
+ALTER TABLE tbl_user ALTER COLUMN update_ts DROP DEFAULT;
+
+ALTER TABLE tbl_user
+    ALTER COLUMN create_ts TYPE timestamptz USING to_timestamp(create_ts - 18000),
+    ALTER COLUMN update_ts TYPE timestamptz USING to_timestamp(
+        CASE WHEN update_ts = create_ts THEN update_ts - 18000 ELSE update_ts END
+    );
+
+ALTER TABLE tbl_user ALTER COLUMN create_ts SET DEFAULT now();
+ALTER TABLE tbl_user ALTER COLUMN update_ts SET DEFAULT now();
+
+-- Only changes to the account itself count, not MFA bookkeeping such as
+-- mfa_last_step, which moves on every login.
+CREATE OR REPLACE FUNCTION user_set_update_ts() RETURNS trigger AS $$
+BEGIN
+    NEW.update_ts = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_user_update_ts
+    BEFORE UPDATE OF username, password, firstname, lastname, status, status_reason ON tbl_user
+    FOR EACH ROW EXECUTE FUNCTION user_set_update_ts();
@@ -0,0 +1,14 @@
+-- Optional profile fields, kept out of tbl_user so accounts without a profile
+-- cost nothing. attributes holds deployment-specific keys; which ones are
+-- allowed is decided by the JSON Schema in profile.attributes_schema, not
+-- here. avatar_key is the blob store prefix of the current avatar's
+-- thumbnails.
+CREATE TABLE IF NOT EXISTS tbl_user_profile (
+    user_id    integer      PRIMARY KEY REFERENCES tbl_user(id) ON DELETE CASCADE,
+    phone      varchar(16),
+    locale     varchar(35),
+    timezone   varchar(64),
+    attributes jsonb        NOT NULL DEFAULT '{}',
+    avatar_key varchar(128),
+    update_ts  timestamptz  NOT NULL DEFAULT now()
+);
@@ -0,0 +1,20 @@
+-- Organizations group accounts. Each membership carries a role in that
+-- organization: admins manage its members, members can only see them. Both
+-- go with the organization or the account.
+CREATE TABLE IF NOT EXISTS tbl_org (
+    id           SERIAL       PRIMARY KEY,
+    name         varchar(64)  NOT NULL UNIQUE,
+    display_name varchar(100) NOT NULL,
+    create_ts    timestamptz  NOT NULL DEFAULT now(),
+    update_ts    timestamptz  NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS tbl_org_member (
+    org_id    integer     NOT NULL REFERENCES tbl_org(id) ON DELETE CASCADE,
+    user_id   integer     NOT NULL REFERENCES tbl_user(id) ON DELETE CASCADE,
+    role      varchar(16) NOT NULL CHECK (role IN ('admin', 'member')),
+    create_ts timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (org_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_org_member_user_id ON tbl_org_member (user_id);
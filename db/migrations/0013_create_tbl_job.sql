@@ -0,0 +1,30 @@
+-- Background jobs. Workers claim due jobs with FOR UPDATE SKIP LOCKED, so any
+-- number of instances can share the queue. A claim holds the job until
+-- locked_until; a running job past it was abandoned by a crashed worker and
+-- can be claimed again. attempts counts claims and fences out a worker whose
+-- claim was taken over. Failures are retried with backoff until max_attempts,
+-- then the job is dead and stays for inspection.
+CREATE TABLE IF NOT EXISTS tbl_job (
+    id           BIGSERIAL    PRIMARY KEY,
+    kind         varchar(64)  NOT NULL,
+    payload      jsonb        NOT NULL DEFAULT '{}',
+    status       varchar(16)  NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'done', 'dead')),
+    attempts     integer      NOT NULL DEFAULT 0,
+    max_attempts integer      NOT NULL CHECK (max_attempts > 0),
+    run_at       timestamptz  NOT NULL DEFAULT now(),
+    locked_until timestamptz,
+    last_error   text,
+    create_ts    timestamptz  NOT NULL DEFAULT now(),
+    update_ts    timestamptz  NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_job_pending ON tbl_job (run_at, id) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_job_running ON tbl_job (locked_until) WHERE status = 'running';
+CREATE INDEX IF NOT EXISTS idx_job_status ON tbl_job (status, id);
+
+-- Recurring jobs. Whichever instance advances next_run_ts enqueues the run.
+CREATE TABLE IF NOT EXISTS tbl_job_schedule (
+    name        varchar(64)  PRIMARY KEY,
+    cron        varchar(128) NOT NULL,
+    next_run_ts timestamptz  NOT NULL
+);
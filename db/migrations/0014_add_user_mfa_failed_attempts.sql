@@ -0,0 +1,4 @@
+-- Wrong codes at /api/v1/user/mfa/verify since the last accepted one. The
+-- account is locked when it reaches the limit, and an admin unlocking it
+-- starts the count over.
+ALTER TABLE tbl_user ADD COLUMN IF NOT EXISTS mfa_failed_attempts integer NOT NULL DEFAULT 0;
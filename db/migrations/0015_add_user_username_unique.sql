@@ -0,0 +1,5 @@
+-- A username names the account at login, so two accounts must never share
+-- one. The use cases check first and answer 409; this closes the race
+-- between two writes. A database that already has duplicates fails here
+-- until they are renamed.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_username ON tbl_user (username);
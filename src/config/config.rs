@@ -1,28 +1,715 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 use dotenv::dotenv;
+use serde_json::Value;
 
+use crate::pkg::cron::cron::parse_cron;
+use crate::pkg::logger::logger::LOG_LEVELS;
+use crate::pkg::telemetry::telemetry::{EXPORTERS, EXPORTER_FILE, EXPORTER_NONE, EXPORTER_OTLP};
+
+// Settings are layered: defaults, then the config file (TOML or YAML, given by
+// --config or CONFIG_FILE), then environment variables, then CLI flags.
+
+#[derive(Debug, Clone)]
 pub struct Config {
-    pub http_host: String,
-    pub http_port: String,
+    pub http: HttpConfig,
+    pub tls: TlsConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub logging: LoggingConfig,
+    pub shutdown: ShutdownConfig,
+    pub health: HealthConfig,
+    pub metrics: MetricsConfig,
+    pub telemetry: TelemetryConfig,
+    pub profile: ProfileConfig,
+    pub jobs: JobsConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub host: String,
+    pub port: u16,
+    pub listen: Vec<Listener>, // extra listeners besides host:port
+    pub workers: usize, // 0 means one per CPU
+    pub keep_alive: u64, // second, 0 disables keep-alive
+    pub backlog: u32,
+    pub max_body_size: usize, // byte
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub enabled: bool, // serve the host:port listener over TLS
+    pub cert_path: String,
+    pub key_path: String,
+    pub reload_interval: u64, // second, 0 disables certificate reload
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Listener {
+    Tcp(String),
+    Tls(String),
+    Unix(String),
+}
+
+impl Listener {
+    // Accepts `host:port` or `tcp://host:port`, `tls://host:port` and `unix:/path`.
+    pub fn parse(value: &str) -> Result<Listener, String> {
+        let value = value.trim();
+        let (scheme, address) = match value.split_once("://") {
+            Some((scheme, address)) => {
+                (scheme, address)
+            }
+            None => {
+                match value.strip_prefix("unix:") {
+                    Some(path) => ("unix", path),
+                    None => ("tcp", value),
+                }
+            }
+        };
+
+        match scheme {
+            "tcp" | "tls" => {
+                match address.rsplit_once(':').map(|(host, port)| (host, port.parse::<u16>())) {
+                    Some((host, Ok(port))) if !host.is_empty() && port != 0 => {}
+                    _ => return Err(format!("listener \"{}\" must be host:port", value)),
+                }
+
+                if scheme == "tls" {
+                    Ok(Listener::Tls(address.to_string()))
+                } else {
+                    Ok(Listener::Tcp(address.to_string()))
+                }
+            }
+            "unix" if !address.is_empty() => {
+                Ok(Listener::Unix(address.to_string()))
+            }
+            _ => {
+                Err(format!("listener \"{}\" has unsupported scheme, expected tcp, tls or unix", value))
+            }
+        }
+    }
+}
+
+impl HttpConfig {
+    // All listeners the server binds: host:port first, then http.listen.
+    pub fn listeners(&self, tls: &TlsConfig) -> Vec<Listener> {
+        let address = format!("{}:{}", self.host, self.port);
+        let mut listeners = vec![if tls.enabled { Listener::Tls(address) } else { Listener::Tcp(address) }];
+        listeners.extend(self.listen.iter().cloned());
+
+        listeners
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: Secret,
+    pub name: String,
+    pub max_conn: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub token_secret: Secret,
+    pub token_life_time: u64, // minute
+    pub admins: Vec<i32>, // ids of the accounts allowed to call the admin endpoints
+    pub mfa_issuer: String, // shown next to the account in authenticator apps and as the passkey relying party name
+    pub webauthn_rp_id: String, // domain passkeys are bound to
+    pub webauthn_origin: String, // where the browser runs the ceremonies, e.g. https://login.example.com
+    pub oidc_issuer: String, // public base URL of this service as an OpenID provider
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub level: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    pub check_timeout: u64, // millisecond, per dependency check
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    pub max_route_labels: usize, // distinct route labels before new ones are reported as "other"
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub exporter: String, // none, otlp, stdout or file
+    pub otlp_endpoint: String, // OTLP/HTTP traces endpoint
+    pub file_path: String, // used by the file exporter
+    pub service_name: String,
+    pub sample_ratio: f64, // for traces without a sampled parent
+}
+
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    pub readiness_delay: u64, // second, not-ready period before the server stops accepting
+    pub drain_timeout: u64, // second, limit for in-flight requests and background tasks together
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileConfig {
+    pub attributes_schema: String, // path of the JSON Schema for profile attributes; without it none are allowed
+    pub avatar_dir: String, // where avatar thumbnails are stored
+    pub avatar_max_size: usize, // byte, of an uploaded avatar
+}
+
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    pub workers: usize, // jobs run at once by this instance, 0 runs none
+    pub poll_interval: u64, // millisecond, between looks for due jobs when idle
+    pub lock_timeout: u64, // second, after which a running job counts as abandoned and runs again
+    pub max_attempts: i32, // before a failing job is dead-lettered
+    pub retention: u64, // day, finished jobs are kept
+    pub session_purge_schedule: String, // cron expression, deletes expired sessions
+    pub job_purge_schedule: String, // cron expression, deletes finished jobs past the retention
+}
+
+// Secret keeps credentials out of Debug output.
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Secret {
+        Secret(value)
+    }
 
-    pub db_host: String,
-    pub db_port: String,
-    pub db_user: String,
-    pub db_password: String,
-    pub db_name: String,
-    pub db_max_conn: u32,
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "\"\"")
+        } else {
+            write!(f, "\"[REDACTED]\"")
+        }
+    }
 }
 
-pub fn read_env() -> Config{
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            http: HttpConfig {
+                host: "127.0.0.1".to_string(),
+                port: 8081,
+                listen: Vec::new(),
+                workers: 0,
+                keep_alive: 5,
+                backlog: 2048,
+                max_body_size: 256 * 1024,
+            },
+            tls: TlsConfig {
+                enabled: false,
+                cert_path: String::new(),
+                key_path: String::new(),
+                reload_interval: 60,
+            },
+            database: DatabaseConfig {
+                host: "localhost".to_string(),
+                port: 5432,
+                user: "postgres".to_string(),
+                password: Secret::default(),
+                name: "postgres".to_string(),
+                max_conn: 10,
+            },
+            auth: AuthConfig {
+                token_secret: Secret::default(),
+                token_life_time: 5,
+                admins: Vec::new(),
+                mfa_issuer: "rust-clean".to_string(),
+                webauthn_rp_id: "localhost".to_string(),
+                webauthn_origin: "http://localhost:8081".to_string(),
+                oidc_issuer: "http://localhost:8081".to_string(),
+            },
+            logging: LoggingConfig {
+                level: "info".to_string(),
+            },
+            shutdown: ShutdownConfig {
+                readiness_delay: 5,
+                drain_timeout: 30,
+            },
+            health: HealthConfig {
+                check_timeout: 1000,
+            },
+            metrics: MetricsConfig {
+                max_route_labels: 100,
+            },
+            telemetry: TelemetryConfig {
+                exporter: EXPORTER_NONE.to_string(),
+                otlp_endpoint: "http://localhost:4318/v1/traces".to_string(),
+                file_path: String::new(),
+                service_name: env!("CARGO_PKG_NAME").to_string(),
+                sample_ratio: 1.0,
+            },
+            profile: ProfileConfig {
+                attributes_schema: String::new(),
+                avatar_dir: "data/avatars".to_string(),
+                avatar_max_size: 2 * 1024 * 1024,
+            },
+            jobs: JobsConfig {
+                workers: 2,
+                poll_interval: 1000,
+                lock_timeout: 300,
+                max_attempts: 5,
+                retention: 7,
+                session_purge_schedule: "*/15 * * * *".to_string(),
+                job_purge_schedule: "0 3 * * *".to_string(),
+            },
+        }
+    }
+}
+
+pub struct Setting {
+    pub key: &'static str,
+    pub env: &'static str,
+    pub secret: bool,
+}
+
+// Every setting is reachable as a file key, an environment variable and a CLI
+// flag (the env name in lowercase kebab case, e.g. --db-max-conn). Secrets can
+// also be read from a file through the `_file` / `_FILE` / `-file` variant.
+pub const SETTINGS: &[Setting] = &[
+    Setting { key: "http.host", env: "HTTP_HOST", secret: false },
+    Setting { key: "http.port", env: "HTTP_PORT", secret: false },
+    Setting { key: "http.listen", env: "HTTP_LISTEN", secret: false },
+    Setting { key: "http.workers", env: "HTTP_WORKERS", secret: false },
+    Setting { key: "http.keep_alive", env: "HTTP_KEEP_ALIVE", secret: false },
+    Setting { key: "http.backlog", env: "HTTP_BACKLOG", secret: false },
+    Setting { key: "http.max_body_size", env: "HTTP_MAX_BODY_SIZE", secret: false },
+    Setting { key: "tls.enabled", env: "TLS_ENABLED", secret: false },
+    Setting { key: "tls.cert_path", env: "TLS_CERT_PATH", secret: false },
+    Setting { key: "tls.key_path", env: "TLS_KEY_PATH", secret: false },
+    Setting { key: "tls.reload_interval", env: "TLS_RELOAD_INTERVAL", secret: false },
+    Setting { key: "database.host", env: "DB_HOST", secret: false },
+    Setting { key: "database.port", env: "DB_PORT", secret: false },
+    Setting { key: "database.user", env: "DB_USER", secret: false },
+    Setting { key: "database.password", env: "DB_PASSWORD", secret: true },
+    Setting { key: "database.name", env: "DB_NAME", secret: false },
+    Setting { key: "database.max_conn", env: "DB_MAX_CONN", secret: false },
+    Setting { key: "auth.token_secret", env: "AUTH_TOKEN_SECRET", secret: true },
+    Setting { key: "auth.token_life_time", env: "AUTH_TOKEN_LIFE_TIME", secret: false },
+    Setting { key: "auth.admins", env: "AUTH_ADMINS", secret: false },
+    Setting { key: "auth.mfa_issuer", env: "AUTH_MFA_ISSUER", secret: false },
+    Setting { key: "auth.webauthn_rp_id", env: "AUTH_WEBAUTHN_RP_ID", secret: false },
+    Setting { key: "auth.webauthn_origin", env: "AUTH_WEBAUTHN_ORIGIN", secret: false },
+    Setting { key: "auth.oidc_issuer", env: "AUTH_OIDC_ISSUER", secret: false },
+    Setting { key: "logging.level", env: "LOG_LEVEL", secret: false },
+    Setting { key: "shutdown.readiness_delay", env: "SHUTDOWN_READINESS_DELAY", secret: false },
+    Setting { key: "shutdown.drain_timeout", env: "SHUTDOWN_DRAIN_TIMEOUT", secret: false },
+    Setting { key: "health.check_timeout", env: "HEALTH_CHECK_TIMEOUT", secret: false },
+    Setting { key: "metrics.max_route_labels", env: "METRICS_MAX_ROUTE_LABELS", secret: false },
+    Setting { key: "telemetry.exporter", env: "TELEMETRY_EXPORTER", secret: false },
+    Setting { key: "telemetry.otlp_endpoint", env: "TELEMETRY_OTLP_ENDPOINT", secret: false },
+    Setting { key: "telemetry.file_path", env: "TELEMETRY_FILE_PATH", secret: false },
+    Setting { key: "telemetry.service_name", env: "TELEMETRY_SERVICE_NAME", secret: false },
+    Setting { key: "telemetry.sample_ratio", env: "TELEMETRY_SAMPLE_RATIO", secret: false },
+    Setting { key: "profile.attributes_schema", env: "PROFILE_ATTRIBUTES_SCHEMA", secret: false },
+    Setting { key: "profile.avatar_dir", env: "PROFILE_AVATAR_DIR", secret: false },
+    Setting { key: "profile.avatar_max_size", env: "PROFILE_AVATAR_MAX_SIZE", secret: false },
+    Setting { key: "jobs.workers", env: "JOBS_WORKERS", secret: false },
+    Setting { key: "jobs.poll_interval", env: "JOBS_POLL_INTERVAL", secret: false },
+    Setting { key: "jobs.lock_timeout", env: "JOBS_LOCK_TIMEOUT", secret: false },
+    Setting { key: "jobs.max_attempts", env: "JOBS_MAX_ATTEMPTS", secret: false },
+    Setting { key: "jobs.retention", env: "JOBS_RETENTION", secret: false },
+    Setting { key: "jobs.session_purge_schedule", env: "JOBS_SESSION_PURGE_SCHEDULE", secret: false },
+    Setting { key: "jobs.job_purge_schedule", env: "JOBS_JOB_PURGE_SCHEDULE", secret: false },
+];
+
+// ConfigReport collects every problem found while loading, so a broken
+// deployment is fixed in one round instead of one variable at a time.
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration ({} problem(s)):", self.problems.len())?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+pub enum LoadResult {
+    Loaded(Box<Config>),
+    Help(String),
+}
+
+pub fn read_env() -> Result<LoadResult, ConfigReport> {
     dotenv().ok();
 
-    Config {
-        http_host: std::env::var("HTTP_HOST").expect("HTTP_HOST must be set."),
-        http_port: std::env::var("HTTP_PORT").expect("HTTP_PORT must be set."),
-        db_host: std::env::var("DB_HOST").expect("DB_HOST must be set."),
-        db_port: std::env::var("DB_PORT").expect("DB_PORT must be set."),
-        db_user: std::env::var("DB_USER").expect("DB_USER must be set."),
-        db_password: std::env::var("DB_PASSWORD").expect("DB_PASSWORD must be set."),
-        db_name: std::env::var("DB_NAME").expect("DB_NAME must be set."),
-        db_max_conn: std::env::var("DB_MAX_CONN").expect("DB_MAX_CONN must be set.").trim().parse().expect("can't convert to u32"),
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env: HashMap<String, String> = std::env::vars().collect();
+
+    load_config(&args, &env)
+}
+
+pub fn load_config(args: &[String], env: &HashMap<String, String>) -> Result<LoadResult, ConfigReport> {
+    let mut cfg = Config::default();
+    let mut report = ConfigReport::default();
+
+    let flags = match parse_flags(args) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            report.problems.push(err);
+            Vec::new()
+        }
+    };
+
+    if flags.iter().any(|(name, _)| name == "help") {
+        return Ok(LoadResult::Help(usage()));
+    }
+
+    let config_file = flags.iter()
+        .rev()
+        .find(|(name, _)| name == "config")
+        .map(|(_, value)| value.clone())
+        .or_else(|| env.get("CONFIG_FILE").filter(|value| !value.is_empty()).cloned());
+
+    if let Some(path) = config_file {
+        match read_config_file(&path) {
+            Ok(values) => {
+                for (key, value) in values {
+                    apply(&mut cfg, &mut report, &format!("file {}", path), &key, &value);
+                }
+            }
+            Err(err) => {
+                report.problems.push(format!("file {}: {}", path, err));
+            }
+        }
+    }
+
+    // Empty variables count as unset, so a blank .env template keeps the defaults.
+    let env_value = |name: &str| env.get(name).filter(|value| !value.is_empty());
+
+    for setting in SETTINGS {
+        if let Some(value) = env_value(setting.env) {
+            apply(&mut cfg, &mut report, &format!("env {}", setting.env), setting.key, value);
+        }
+
+        let file_env = format!("{}_FILE", setting.env);
+        if let Some(value) = env_value(&file_env).filter(|_| setting.secret) {
+            apply(&mut cfg, &mut report, &format!("env {}", file_env), &format!("{}_file", setting.key), value);
+        }
+    }
+
+    for (name, value) in flags.iter().filter(|(name, _)| name != "config") {
+        match flag_to_key(name) {
+            Some(key) => {
+                apply(&mut cfg, &mut report, &format!("flag --{}", name), &key, value);
+            }
+            None => {
+                report.problems.push(format!("flag --{}: unknown flag", name));
+            }
+        }
+    }
+
+    validate(&cfg, &mut report);
+
+    if report.problems.is_empty() {
+        Ok(LoadResult::Loaded(Box::new(cfg)))
+    } else {
+        Err(report)
+    }
+}
+
+fn apply(cfg: &mut Config, report: &mut ConfigReport, source: &str, key: &str, value: &str) {
+    let result = match key.strip_suffix("_file") {
+        Some(base) if SETTINGS.iter().any(|s| s.key == base && s.secret) => {
+            match std::fs::read_to_string(value) {
+                Ok(secret) => {
+                    set(cfg, base, secret.trim_end_matches(['\r', '\n']))
+                }
+                Err(err) => {
+                    Err(format!("can't read secret file {}: {}", value, err))
+                }
+            }
+        }
+        _ => {
+            set(cfg, key, value)
+        }
+    };
+
+    if let Err(err) = result {
+        report.problems.push(format!("{}: {}", source, err));
+    }
+}
+
+fn set(cfg: &mut Config, key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "http.host" => cfg.http.host = value.to_string(),
+        "http.port" => cfg.http.port = parse(key, value)?,
+        "http.listen" => cfg.http.listen = parse_list(value).iter().map(|v| Listener::parse(v)).collect::<Result<_, _>>()?,
+        "http.workers" => cfg.http.workers = parse(key, value)?,
+        "http.keep_alive" => cfg.http.keep_alive = parse(key, value)?,
+        "http.backlog" => cfg.http.backlog = parse(key, value)?,
+        "http.max_body_size" => cfg.http.max_body_size = parse(key, value)?,
+        "tls.enabled" => cfg.tls.enabled = parse(key, value)?,
+        "tls.cert_path" => cfg.tls.cert_path = value.to_string(),
+        "tls.key_path" => cfg.tls.key_path = value.to_string(),
+        "tls.reload_interval" => cfg.tls.reload_interval = parse(key, value)?,
+        "database.host" => cfg.database.host = value.to_string(),
+        "database.port" => cfg.database.port = parse(key, value)?,
+        "database.user" => cfg.database.user = value.to_string(),
+        "database.password" => cfg.database.password = Secret::new(value.to_string()),
+        "database.name" => cfg.database.name = value.to_string(),
+        "database.max_conn" => cfg.database.max_conn = parse(key, value)?,
+        "auth.token_secret" => cfg.auth.token_secret = Secret::new(value.to_string()),
+        "auth.token_life_time" => cfg.auth.token_life_time = parse(key, value)?,
+        "auth.admins" => cfg.auth.admins = parse_list(value).iter().map(|v| parse(key, v)).collect::<Result<_, _>>()?,
+        "auth.mfa_issuer" => cfg.auth.mfa_issuer = value.to_string(),
+        "auth.webauthn_rp_id" => cfg.auth.webauthn_rp_id = value.trim().to_lowercase(),
+        "auth.webauthn_origin" => cfg.auth.webauthn_origin = value.trim().trim_end_matches('/').to_lowercase(),
+        "auth.oidc_issuer" => cfg.auth.oidc_issuer = value.trim().trim_end_matches('/').to_lowercase(),
+        "logging.level" => cfg.logging.level = value.trim().to_lowercase(),
+        "shutdown.readiness_delay" => cfg.shutdown.readiness_delay = parse(key, value)?,
+        "shutdown.drain_timeout" => cfg.shutdown.drain_timeout = parse(key, value)?,
+        "health.check_timeout" => cfg.health.check_timeout = parse(key, value)?,
+        "metrics.max_route_labels" => cfg.metrics.max_route_labels = parse(key, value)?,
+        "telemetry.exporter" => cfg.telemetry.exporter = value.trim().to_lowercase(),
+        "telemetry.otlp_endpoint" => cfg.telemetry.otlp_endpoint = value.to_string(),
+        "telemetry.file_path" => cfg.telemetry.file_path = value.to_string(),
+        "telemetry.service_name" => cfg.telemetry.service_name = value.to_string(),
+        "telemetry.sample_ratio" => cfg.telemetry.sample_ratio = parse(key, value)?,
+        "profile.attributes_schema" => cfg.profile.attributes_schema = value.to_string(),
+        "profile.avatar_dir" => cfg.profile.avatar_dir = value.to_string(),
+        "profile.avatar_max_size" => cfg.profile.avatar_max_size = parse(key, value)?,
+        "jobs.workers" => cfg.jobs.workers = parse(key, value)?,
+        "jobs.poll_interval" => cfg.jobs.poll_interval = parse(key, value)?,
+        "jobs.lock_timeout" => cfg.jobs.lock_timeout = parse(key, value)?,
+        "jobs.max_attempts" => cfg.jobs.max_attempts = parse(key, value)?,
+        "jobs.retention" => cfg.jobs.retention = parse(key, value)?,
+        "jobs.session_purge_schedule" => cfg.jobs.session_purge_schedule = value.trim().to_string(),
+        "jobs.job_purge_schedule" => cfg.jobs.job_purge_schedule = value.trim().to_string(),
+        _ => return Err(format!("unknown setting {}", key)),
+    }
+
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_err| format!("{} has invalid value \"{}\"", key, value))
+}
+
+// Lists come as comma separated values from env and flags, and as arrays from files.
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+fn validate(cfg: &Config, report: &mut ConfigReport) {
+    let mut check = |ok: bool, problem: &str| {
+        if !ok {
+            report.problems.push(problem.to_string());
+        }
+    };
+
+    check(!cfg.http.host.is_empty(), "http.host must not be empty");
+    check(cfg.http.port != 0, "http.port must not be 0");
+    check(cfg.http.backlog > 0, "http.backlog must be greater than 0");
+    check(cfg.http.max_body_size > 0, "http.max_body_size must be greater than 0");
+
+    let needs_tls = cfg.http.listeners(&cfg.tls).iter().any(|l| matches!(l, Listener::Tls(_)));
+    if needs_tls {
+        for (key, path) in [("tls.cert_path", &cfg.tls.cert_path), ("tls.key_path", &cfg.tls.key_path)] {
+            if path.is_empty() {
+                check(false, &format!("{} must be set when a TLS listener is configured", key));
+            } else {
+                check(Path::new(path).is_file(), &format!("{} {} is not a readable file", key, path));
+            }
+        }
+    }
+    check(!cfg.database.host.is_empty(), "database.host must not be empty");
+    check(cfg.database.port != 0, "database.port must not be 0");
+    check(!cfg.database.user.is_empty(), "database.user must not be empty");
+    check(!cfg.database.name.is_empty(), "database.name must not be empty");
+    check(cfg.database.max_conn > 0, "database.max_conn must be greater than 0");
+    check(!cfg.auth.token_secret.expose().is_empty(), "auth.token_secret must be set (AUTH_TOKEN_SECRET or AUTH_TOKEN_SECRET_FILE)");
+    check(cfg.auth.token_life_time > 0, "auth.token_life_time must be greater than 0");
+    check(!cfg.auth.mfa_issuer.is_empty() && !cfg.auth.mfa_issuer.contains(':'), "auth.mfa_issuer must be set and must not contain ':'");
+    check(valid_secure_origin(&cfg.auth.webauthn_origin), "auth.webauthn_origin must be https://host[:port], or http:// for localhost");
+    check(
+        origin_host(&cfg.auth.webauthn_origin).is_some_and(|host| host == cfg.auth.webauthn_rp_id || host.ends_with(&format!(".{}", cfg.auth.webauthn_rp_id))),
+        "auth.webauthn_rp_id must be the host of auth.webauthn_origin or a parent domain of it",
+    );
+    check(valid_secure_origin(&cfg.auth.oidc_issuer), "auth.oidc_issuer must be https://host[:port], or http:// for localhost");
+    check(cfg.health.check_timeout > 0, "health.check_timeout must be greater than 0");
+    check(cfg.metrics.max_route_labels > 0, "metrics.max_route_labels must be greater than 0");
+    check(EXPORTERS.contains(&cfg.telemetry.exporter.as_str()), "telemetry.exporter must be one of none, otlp, stdout, file");
+    check(cfg.telemetry.exporter != EXPORTER_OTLP || !cfg.telemetry.otlp_endpoint.is_empty(), "telemetry.otlp_endpoint must be set for the otlp exporter");
+    check(cfg.telemetry.exporter != EXPORTER_FILE || !cfg.telemetry.file_path.is_empty(), "telemetry.file_path must be set for the file exporter");
+    check((0.0..=1.0).contains(&cfg.telemetry.sample_ratio), "telemetry.sample_ratio must be between 0 and 1");
+    check(LOG_LEVELS.contains(&cfg.logging.level.as_str()), "logging.level must be one of error, warn, info, debug, trace");
+    check(
+        cfg.profile.attributes_schema.is_empty() || Path::new(&cfg.profile.attributes_schema).is_file(),
+        &format!("profile.attributes_schema {} is not a readable file", cfg.profile.attributes_schema),
+    );
+    check(!cfg.profile.avatar_dir.is_empty(), "profile.avatar_dir must not be empty");
+    check(cfg.profile.avatar_max_size > 0, "profile.avatar_max_size must be greater than 0");
+    check(cfg.jobs.poll_interval > 0, "jobs.poll_interval must be greater than 0");
+    check(cfg.jobs.lock_timeout > 0, "jobs.lock_timeout must be greater than 0");
+    check(cfg.jobs.max_attempts > 0, "jobs.max_attempts must be greater than 0");
+    check(cfg.jobs.retention > 0, "jobs.retention must be greater than 0");
+    for (key, schedule) in [("jobs.session_purge_schedule", &cfg.jobs.session_purge_schedule), ("jobs.job_purge_schedule", &cfg.jobs.job_purge_schedule)] {
+        match parse_cron(schedule) {
+            Ok(cron) => {
+                check(cron.next_after(chrono::Utc::now()).is_some(), &format!("{} \"{}\" never fires", key, schedule));
+            }
+            Err(err) => {
+                check(false, &format!("{}: {}", key, err));
+            }
+        }
+    }
+}
+
+// The host part of scheme://host[:port]; None when there is a path or no host.
+fn origin_host(origin: &str) -> Option<&str> {
+    let (_, rest) = origin.split_once("://")?;
+    if rest.contains('/') {
+        return None;
+    }
+
+    let host = rest.rsplit_once(':').map(|(host, _)| host).unwrap_or(rest);
+    if host.is_empty() { None } else { Some(host) }
+}
+
+// Browsers only offer WebAuthn in secure contexts, and OAuth requires TLS for
+// its endpoints; both exclude plain HTTP except on localhost.
+fn valid_secure_origin(origin: &str) -> bool {
+    match origin_host(origin) {
+        Some(host) => {
+            origin.starts_with("https://") || (origin.starts_with("http://") && host == "localhost")
+        }
+        None => {
+            false
+        }
+    }
+}
+
+// Flags are accepted as `--name value` or `--name=value`.
+fn parse_flags(args: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut flags = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let name = match arg.strip_prefix("--") {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(format!("unexpected argument \"{}\"", arg));
+            }
+        };
+
+        if name == "help" {
+            flags.push(("help".to_string(), String::new()));
+            continue;
+        }
+
+        match name.split_once('=') {
+            Some((name, value)) => {
+                flags.push((name.to_string(), value.to_string()));
+            }
+            None => {
+                match iter.next() {
+                    Some(value) => {
+                        flags.push((name.to_string(), value.clone()));
+                    }
+                    None => {
+                        return Err(format!("flag --{}: missing value", name));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(flags)
+}
+
+fn flag_name(env: &str) -> String {
+    env.to_lowercase().replace('_', "-")
+}
+
+fn flag_to_key(name: &str) -> Option<String> {
+    for setting in SETTINGS {
+        let flag = flag_name(setting.env);
+        if name == flag {
+            return Some(setting.key.to_string());
+        }
+        if setting.secret && name == format!("{}-file", flag) {
+            return Some(format!("{}_file", setting.key));
+        }
+    }
+
+    None
+}
+
+fn usage() -> String {
+    let mut res = String::from("Usage: rust-clean [--config <file.toml|file.yaml>] [--<flag> <value>]...\n\nFlags (env variable, file key):\n");
+    for setting in SETTINGS {
+        res.push_str(&format!("  --{:<24} {:<24} {}\n", flag_name(setting.env), setting.env, setting.key));
+        if setting.secret {
+            let flag = format!("{}-file", flag_name(setting.env));
+            res.push_str(&format!("  --{:<24} {:<24} {}_file\n", flag, format!("{}_FILE", setting.env), setting.key));
+        }
+    }
+
+    res
+}
+
+fn read_config_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    let value: Value = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            toml::from_str(&content).map_err(|err| err.to_string())?
+        }
+        Some("yaml") | Some("yml") => {
+            serde_yaml::from_str(&content).map_err(|err| err.to_string())?
+        }
+        _ => {
+            return Err("unsupported format, expected .toml, .yaml or .yml".to_string());
+        }
+    };
+
+    let mut values = Vec::new();
+    flatten("", &value, &mut values);
+
+    Ok(values)
+}
+
+fn flatten(prefix: &str, value: &Value, values: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, values);
+            }
+        }
+        Value::String(s) => {
+            values.push((prefix.to_string(), s.clone()));
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter()
+                .map(|item| item.as_str().map(|s| s.to_string()).unwrap_or_else(|| item.to_string()))
+                .collect();
+            values.push((prefix.to_string(), items.join(",")));
+        }
+        Value::Null => {}
+        other => {
+            values.push((prefix.to_string(), other.to_string()));
+        }
     }
 }
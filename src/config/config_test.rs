@@ -0,0 +1,292 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use crate::config::config::{load_config, Config, ConfigReport, Listener, LoadResult};
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}", uuid::Uuid::new_v4(), name));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn loaded(res: Result<LoadResult, ConfigReport>) -> Config {
+        match res {
+            Ok(LoadResult::Loaded(cfg)) => *cfg,
+            Ok(LoadResult::Help(_)) => panic!("unexpected help"),
+            Err(report) => panic!("{}", report),
+        }
+    }
+
+    fn problems(res: Result<LoadResult, ConfigReport>) -> Vec<String> {
+        match res {
+            Err(report) => report.problems,
+            Ok(_) => panic!("expected configuration problems"),
+        }
+    }
+
+    #[test]
+    fn defaults_test() {
+        let cfg = loaded(load_config(&[], &env(&[("AUTH_TOKEN_SECRET", "secret"), ("HTTP_PORT", ""), ("DB_HOST", "")])));
+
+        assert_eq!(cfg.http.host, "127.0.0.1");
+        assert_eq!(cfg.http.port, 8081);
+        assert_eq!(cfg.database.host, "localhost");
+        assert_eq!(cfg.database.port, 5432);
+        assert_eq!(cfg.database.max_conn, 10);
+        assert_eq!(cfg.auth.token_life_time, 5);
+        assert_eq!(cfg.logging.level, "info");
+    }
+
+    #[test]
+    fn layer_precedence_test() {
+        let file = temp_file("config.toml", r#"
+            [http]
+            host = "0.0.0.0"
+            port = 9000
+
+            [database]
+            host = "db.file"
+            max_conn = 20
+
+            [auth]
+            token_secret = "file-secret"
+            admins = [1, 2]
+        "#);
+
+        let cfg = loaded(load_config(
+            &args(&["--config", &file, "--db-max-conn=30"]),
+            &env(&[("HTTP_PORT", "9100"), ("DB_MAX_CONN", "25"), ("DB_NAME", "users")]),
+        ));
+
+        assert_eq!(cfg.http.host, "0.0.0.0");
+        assert_eq!(cfg.http.port, 9100);
+        assert_eq!(cfg.database.host, "db.file");
+        assert_eq!(cfg.database.name, "users");
+        assert_eq!(cfg.database.max_conn, 30);
+        assert_eq!(cfg.auth.token_secret.expose(), "file-secret");
+        assert_eq!(cfg.auth.admins, vec![1, 2]);
+    }
+
+    #[test]
+    fn yaml_file_test() {
+        let file = temp_file("config.yaml", "database:\n  port: 6432\nauth:\n  token_secret: yaml-secret\nlogging:\n  level: DEBUG\n");
+
+        let cfg = loaded(load_config(&[], &env(&[("CONFIG_FILE", &file)])));
+
+        assert_eq!(cfg.database.port, 6432);
+        assert_eq!(cfg.auth.token_secret.expose(), "yaml-secret");
+        assert_eq!(cfg.logging.level, "debug");
+    }
+
+    #[test]
+    fn secret_file_test() {
+        let password = temp_file("db-password", "s3cret\n");
+        let token = temp_file("token-secret", "token-s3cret\n");
+
+        let cfg = loaded(load_config(
+            &args(&["--auth-token-secret-file", &token]),
+            &env(&[("DB_PASSWORD_FILE", &password)]),
+        ));
+
+        assert_eq!(cfg.database.password.expose(), "s3cret");
+        assert_eq!(cfg.auth.token_secret.expose(), "token-s3cret");
+    }
+
+    #[test]
+    fn redacted_debug_test() {
+        let cfg = loaded(load_config(&[], &env(&[("AUTH_TOKEN_SECRET", "token-s3cret"), ("DB_PASSWORD", "s3cret")])));
+
+        let debug = format!("{:?}", cfg);
+        assert!(!debug.contains("s3cret"));
+        assert!(debug.contains("[REDACTED]"));
+    }
+
+    #[test]
+    fn validation_report_test() {
+        let problems = problems(load_config(
+            &args(&["--http-port", "http", "--unknown", "1"]),
+            &env(&[("DB_MAX_CONN", "0"), ("DB_PORT", "-1"), ("LOG_LEVEL", "loud"), ("DB_PASSWORD_FILE", "/nonexistent/secret")]),
+        ));
+
+        let expected = [
+            "env DB_PORT: database.port has invalid value \"-1\"",
+            "env DB_PASSWORD_FILE: can't read secret file /nonexistent/secret",
+            "flag --http-port: http.port has invalid value \"http\"",
+            "flag --unknown: unknown flag",
+            "database.max_conn must be greater than 0",
+            "auth.token_secret must be set",
+            "logging.level must be one of",
+        ];
+
+        assert_eq!(problems.len(), expected.len(), "{:?}", problems);
+        for (problem, expected) in problems.iter().zip(expected.iter()) {
+            assert!(problem.starts_with(expected), "{} does not start with {}", problem, expected);
+        }
+    }
+
+    #[test]
+    fn telemetry_validation_test() {
+        let secret = ("AUTH_TOKEN_SECRET", "s");
+
+        let cfg = loaded(load_config(&[], &env(&[secret, ("TELEMETRY_EXPORTER", "OTLP")])));
+        assert_eq!(cfg.telemetry.exporter, "otlp");
+        assert_eq!(cfg.telemetry.otlp_endpoint, "http://localhost:4318/v1/traces");
+
+        let invalid = problems(load_config(
+            &args(&["--telemetry-sample-ratio", "1.5"]),
+            &env(&[secret, ("TELEMETRY_EXPORTER", "file")]),
+        ));
+        assert_eq!(invalid, vec![
+            "telemetry.file_path must be set for the file exporter".to_string(),
+            "telemetry.sample_ratio must be between 0 and 1".to_string(),
+        ]);
+
+        let unknown = problems(load_config(&[], &env(&[secret, ("TELEMETRY_EXPORTER", "jaeger")])));
+        assert_eq!(unknown, vec!["telemetry.exporter must be one of none, otlp, stdout, file".to_string()]);
+    }
+
+    #[test]
+    fn webauthn_validation_test() {
+        let secret = ("AUTH_TOKEN_SECRET", "s");
+
+        let cfg = loaded(load_config(&[], &env(&[
+            secret, ("AUTH_WEBAUTHN_RP_ID", "Example.com"), ("AUTH_WEBAUTHN_ORIGIN", "https://login.example.com/"),
+        ])));
+        assert_eq!(cfg.auth.webauthn_rp_id, "example.com");
+        assert_eq!(cfg.auth.webauthn_origin, "https://login.example.com");
+
+        let test_cases = vec! {
+            (("example.com", "http://example.com"), vec!["auth.webauthn_origin must be https://host[:port], or http:// for localhost"]),
+            (("example.com", "https://example.com.evil.io"), vec!["auth.webauthn_rp_id must be the host of auth.webauthn_origin or a parent domain of it"]),
+            (("ample.com", "https://example.com:8443"), vec!["auth.webauthn_rp_id must be the host of auth.webauthn_origin or a parent domain of it"]),
+        };
+        for ((rp_id, origin), expected) in test_cases {
+            let res = problems(load_config(&[], &env(&[secret, ("AUTH_WEBAUTHN_RP_ID", rp_id), ("AUTH_WEBAUTHN_ORIGIN", origin)])));
+            assert_eq!(res, expected, "{} {}", rp_id, origin);
+        }
+
+        let cfg = loaded(load_config(&[], &env(&[
+            secret, ("AUTH_WEBAUTHN_RP_ID", "example.com"), ("AUTH_WEBAUTHN_ORIGIN", "https://example.com:8443"),
+        ])));
+        assert_eq!(cfg.auth.webauthn_origin, "https://example.com:8443");
+    }
+
+    #[test]
+    fn oidc_issuer_validation_test() {
+        let secret = ("AUTH_TOKEN_SECRET", "s");
+
+        let cfg = loaded(load_config(&[], &env(&[secret, ("AUTH_OIDC_ISSUER", " https://ID.example.com/ ")])));
+        assert_eq!(cfg.auth.oidc_issuer, "https://id.example.com");
+
+        for issuer in ["http://id.example.com", "https://id.example.com/realm", "id.example.com"] {
+            let res = problems(load_config(&[], &env(&[secret, ("AUTH_OIDC_ISSUER", issuer)])));
+            assert_eq!(res, vec!["auth.oidc_issuer must be https://host[:port], or http:// for localhost".to_string()], "{}", issuer);
+        }
+    }
+
+    #[test]
+    fn profile_validation_test() {
+        let secret = ("AUTH_TOKEN_SECRET", "s");
+
+        let cfg = loaded(load_config(&[], &env(&[secret])));
+        assert_eq!((cfg.profile.attributes_schema.as_str(), cfg.profile.avatar_dir.as_str()), ("", "data/avatars"));
+        assert_eq!(cfg.profile.avatar_max_size, 2 * 1024 * 1024);
+
+        let schema = temp_file("attributes.json", r#"{"type": "object"}"#);
+        let cfg = loaded(load_config(
+            &args(&["--profile-avatar-max-size", "1048576"]),
+            &env(&[secret, ("PROFILE_ATTRIBUTES_SCHEMA", &schema), ("PROFILE_AVATAR_DIR", "/var/lib/rust-clean/avatars")]),
+        ));
+        assert_eq!(cfg.profile.attributes_schema, schema);
+        assert_eq!((cfg.profile.avatar_dir.as_str(), cfg.profile.avatar_max_size), ("/var/lib/rust-clean/avatars", 1048576));
+
+        let invalid = problems(load_config(&[], &env(&[
+            secret, ("PROFILE_ATTRIBUTES_SCHEMA", "/nonexistent/attributes.json"), ("PROFILE_AVATAR_MAX_SIZE", "0"),
+        ])));
+        assert_eq!(invalid, vec![
+            "profile.attributes_schema /nonexistent/attributes.json is not a readable file".to_string(),
+            "profile.avatar_max_size must be greater than 0".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn jobs_validation_test() {
+        let secret = ("AUTH_TOKEN_SECRET", "s");
+
+        let cfg = loaded(load_config(&[], &env(&[secret])));
+        assert_eq!((cfg.jobs.workers, cfg.jobs.poll_interval, cfg.jobs.lock_timeout), (2, 1000, 300));
+        assert_eq!((cfg.jobs.max_attempts, cfg.jobs.retention), (5, 7));
+        assert_eq!((cfg.jobs.session_purge_schedule.as_str(), cfg.jobs.job_purge_schedule.as_str()), ("*/15 * * * *", "0 3 * * *"));
+
+        let cfg = loaded(load_config(
+            &args(&["--jobs-workers", "0"]),
+            &env(&[secret, ("JOBS_SESSION_PURGE_SCHEDULE", " 0 * * * * "), ("JOBS_RETENTION", "30")]),
+        ));
+        assert_eq!((cfg.jobs.workers, cfg.jobs.retention), (0, 30));
+        assert_eq!(cfg.jobs.session_purge_schedule, "0 * * * *");
+
+        let invalid = problems(load_config(&[], &env(&[
+            secret, ("JOBS_MAX_ATTEMPTS", "0"), ("JOBS_POLL_INTERVAL", "0"),
+            ("JOBS_SESSION_PURGE_SCHEDULE", "every hour"), ("JOBS_JOB_PURGE_SCHEDULE", "0 0 31 2 *"),
+        ])));
+        assert_eq!(invalid, vec![
+            "jobs.poll_interval must be greater than 0".to_string(),
+            "jobs.max_attempts must be greater than 0".to_string(),
+            "jobs.session_purge_schedule: cron expression \"every hour\" must have 5 fields".to_string(),
+            "jobs.job_purge_schedule \"0 0 31 2 *\" never fires".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn listeners_test() {
+        let cert = temp_file("cert.pem", "cert");
+        let key = temp_file("key.pem", "key");
+
+        let cfg = loaded(load_config(
+            &args(&["--http-listen", "tls://0.0.0.0:8443, unix:/tmp/rust-clean.sock", "--tls-cert-path", &cert]),
+            &env(&[("AUTH_TOKEN_SECRET", "secret"), ("HTTP_HOST", "0.0.0.0"), ("HTTP_PORT", "9000"), ("TLS_KEY_PATH", &key)]),
+        ));
+
+        assert_eq!(cfg.http.listeners(&cfg.tls), vec![
+            Listener::Tcp("0.0.0.0:9000".to_string()),
+            Listener::Tls("0.0.0.0:8443".to_string()),
+            Listener::Unix("/tmp/rust-clean.sock".to_string()),
+        ]);
+
+        let file = temp_file("config.toml", "[http]\nlisten = [\"127.0.0.1:9001\", \"tcp://[::1]:9002\"]\n[tls]\nenabled = true\n");
+        let problems = problems(load_config(&args(&["--config", &file, "--http-listen", "udp://1.2.3.4:53"]), &env(&[("AUTH_TOKEN_SECRET", "secret")])));
+        assert_eq!(problems, vec![
+            "flag --http-listen: listener \"udp://1.2.3.4:53\" has unsupported scheme, expected tcp, tls or unix".to_string(),
+            "tls.cert_path must be set when a TLS listener is configured".to_string(),
+            "tls.key_path must be set when a TLS listener is configured".to_string(),
+        ]);
+
+        let cfg = loaded(load_config(&args(&["--config", &file, "--tls-enabled", "false"]), &env(&[("AUTH_TOKEN_SECRET", "secret")])));
+        assert_eq!(cfg.http.listen, vec![
+            Listener::Tcp("127.0.0.1:9001".to_string()),
+            Listener::Tcp("[::1]:9002".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn help_test() {
+        match load_config(&args(&["--help"]), &env(&[])) {
+            Ok(LoadResult::Help(usage)) => {
+                assert!(usage.contains("--db-password-file"));
+                assert!(usage.contains("DB_MAX_CONN"));
+            }
+            _ => panic!("expected help"),
+        }
+    }
+}
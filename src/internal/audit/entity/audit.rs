@@ -0,0 +1,176 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use sqlx::types::chrono::Utc;
+use chrono::prelude::DateTime;
+use utoipa::{IntoParams, ToSchema};
+
+pub const AUDIT_USER_CREATE: &str = "user.create";
+pub const AUDIT_USER_UPDATE: &str = "user.update";
+pub const AUDIT_USER_PASSWORD_CHANGE: &str = "user.password_change";
+pub const AUDIT_USER_DELETE: &str = "user.delete";
+pub const AUDIT_LOGIN_SUCCESS: &str = "user.login_success";
+pub const AUDIT_LOGIN_FAILURE: &str = "user.login_failure";
+pub const AUDIT_LOGIN_MFA_CHALLENGE: &str = "user.login_mfa_challenge";
+pub const AUDIT_MFA_ENABLE: &str = "user.mfa_enable";
+pub const AUDIT_MFA_RESET: &str = "user.mfa_reset";
+pub const AUDIT_MFA_RECOVERY_CODE_USED: &str = "user.mfa_recovery_code_used";
+pub const AUDIT_PASSKEY_REGISTER: &str = "user.passkey_register";
+pub const AUDIT_OIDC_CLIENT_CREATE: &str = "oidc.client_create";
+pub const AUDIT_IDENTITY_LINK: &str = "user.identity_link";
+pub const AUDIT_IDENTITY_UNLINK: &str = "user.identity_unlink";
+pub const AUDIT_IDENTITY_PROVIDER_CREATE: &str = "identity_provider.create";
+pub const AUDIT_API_KEY_CREATE: &str = "user.api_key_create";
+pub const AUDIT_API_KEY_ROTATE: &str = "user.api_key_rotate";
+pub const AUDIT_API_KEY_REVOKE: &str = "user.api_key_revoke";
+pub const AUDIT_SESSION_REVOKE: &str = "user.session_revoke";
+pub const AUDIT_USER_STATUS_CHANGE: &str = "user.status_change";
+pub const AUDIT_ORG_CREATE: &str = "org.create";
+pub const AUDIT_ORG_UPDATE: &str = "org.update";
+pub const AUDIT_ORG_DELETE: &str = "org.delete";
+pub const AUDIT_ORG_MEMBER_SET: &str = "org.member_set";
+pub const AUDIT_ORG_MEMBER_REMOVE: &str = "org.member_remove";
+pub const AUDIT_ACTIONS: [&str; 25] = [
+    AUDIT_USER_CREATE, AUDIT_USER_UPDATE, AUDIT_USER_PASSWORD_CHANGE,
+    AUDIT_USER_DELETE, AUDIT_LOGIN_SUCCESS, AUDIT_LOGIN_FAILURE,
+    AUDIT_LOGIN_MFA_CHALLENGE, AUDIT_MFA_ENABLE, AUDIT_MFA_RESET, AUDIT_MFA_RECOVERY_CODE_USED,
+    AUDIT_PASSKEY_REGISTER, AUDIT_OIDC_CLIENT_CREATE, AUDIT_IDENTITY_LINK, AUDIT_IDENTITY_UNLINK,
+    AUDIT_IDENTITY_PROVIDER_CREATE, AUDIT_API_KEY_CREATE, AUDIT_API_KEY_ROTATE, AUDIT_API_KEY_REVOKE,
+    AUDIT_SESSION_REVOKE, AUDIT_USER_STATUS_CHANGE, AUDIT_ORG_CREATE, AUDIT_ORG_UPDATE, AUDIT_ORG_DELETE,
+    AUDIT_ORG_MEMBER_SET, AUDIT_ORG_MEMBER_REMOVE,
+];
+
+pub const AUDIT_DEFAULT_LIMIT: i64 = 50;
+pub const AUDIT_MAX_LIMIT: i64 = 500;
+
+// Fields that never make it into a diff, whatever the caller passes.
+const REDACTED_FIELDS: [&str; 2] = ["password", "mfa_secret"];
+
+// Who made the request and from where, filled in by the controller.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor: Option<String>, // username from the bearer token
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub action: String,
+    pub actor_id: Option<i32>,
+    pub target_id: Option<i32>,
+    pub target_username: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub changes: Value,
+}
+
+pub fn new_audit_record(ctx: &AuditContext, action: &str) -> AuditRecord {
+    AuditRecord {
+        action: action.to_string(),
+        actor_id: None,
+        target_id: None,
+        target_username: None,
+        ip: ctx.ip.clone(),
+        user_agent: ctx.user_agent.clone(),
+        request_id: ctx.request_id.clone(),
+        changes: json!({}),
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub action: String,
+    pub actor_id: Option<i32>,
+    pub target_id: Option<i32>,
+    pub target_username: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    // {"field": {"before": ..., "after": ...}} for every changed field
+    #[schema(value_type = Object)]
+    pub changes: Value,
+    pub create_ts: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// One of user.create, user.update, user.password_change, user.delete, user.login_success, user.login_failure,
+    /// user.login_mfa_challenge, user.mfa_enable, user.mfa_reset, user.mfa_recovery_code_used, user.passkey_register,
+    /// oidc.client_create, user.identity_link, user.identity_unlink, identity_provider.create, user.api_key_create,
+    /// user.api_key_rotate, user.api_key_revoke, user.session_revoke, user.status_change, org.create, org.update,
+    /// org.delete, org.member_set, org.member_remove
+    pub action: Option<String>,
+    pub actor_id: Option<i32>,
+    pub target_id: Option<i32>,
+    /// Inclusive lower bound on create_ts (RFC 3339)
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on create_ts (RFC 3339)
+    pub to: Option<DateTime<Utc>>,
+    /// Page size, 1 to 500 (default 50)
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// One page of entries, newest first; total counts every match.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AuditPage {
+    pub items: Vec<AuditEntry>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+// Compares two JSON objects field by field; Null stands for "no object", as
+// before a create or after a delete.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if REDACTED_FIELDS.contains(&key.as_str()) || changes.contains_key(key) {
+            continue;
+        }
+
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(key.clone(), json!({"before": old, "after": new}));
+        }
+    }
+
+    Value::Object(changes)
+}
+
+// verifying
+
+pub fn verify_audit_query(req: &AuditQuery) -> Result<(), String> {
+    if let Some(action) = &req.action {
+        if !AUDIT_ACTIONS.contains(&action.as_str()) {
+            return Err(format!("action must be one of {}", AUDIT_ACTIONS.join(", ")));
+        }
+    }
+
+    if let Some(limit) = req.limit {
+        if !(1..=AUDIT_MAX_LIMIT).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", AUDIT_MAX_LIMIT));
+        }
+    }
+
+    if req.offset.unwrap_or(0) < 0 {
+        return Err("offset must not be negative".to_string());
+    }
+
+    if let (Some(from), Some(to)) = (req.from, req.to) {
+        if from > to {
+            return Err("from must not be after to".to_string());
+        }
+    }
+
+    Ok(())
+}
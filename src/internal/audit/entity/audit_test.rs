@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::internal::audit::entity::audit::{diff, verify_audit_query, AuditQuery};
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    #[test]
+    fn diff_test() {
+        let test_cases = vec! {
+            TestCase {
+                input: (Value::Null, json!({"username": "JamesHolland", "password": "secret"})),
+                output: json!({"username": {"before": null, "after": "JamesHolland"}}),
+            },
+            TestCase {
+                input: (
+                    json!({"username": "JamesHolland", "firstname": "James"}),
+                    json!({"username": "JamesHolland", "firstname": "Jim"}),
+                ),
+                output: json!({"firstname": {"before": "James", "after": "Jim"}}),
+            },
+            TestCase {
+                input: (json!({"username": "JamesHolland", "password": "a"}), json!({"username": "JamesHolland", "password": "b"})),
+                output: json!({}),
+            },
+            TestCase {
+                input: (json!({"lastname": "Holland"}), Value::Null),
+                output: json!({"lastname": {"before": "Holland", "after": null}}),
+            },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(diff(&test_case.input.0, &test_case.input.1), test_case.output);
+        }
+    }
+
+    #[test]
+    fn verify_audit_query_test() {
+        let test_cases = vec! {
+            TestCase {
+                input: AuditQuery::default(),
+                output: Ok(()),
+            },
+            TestCase {
+                input: AuditQuery { action: Some("user.update".to_string()), limit: Some(500), offset: Some(0), ..Default::default() },
+                output: Ok(()),
+            },
+            TestCase {
+                input: AuditQuery { action: Some("user.rename".to_string()), ..Default::default() },
+                output: Err("action must be one of user.create, user.update, user.password_change, user.delete, user.login_success, user.login_failure, \
+                    user.login_mfa_challenge, user.mfa_enable, user.mfa_reset, user.mfa_recovery_code_used, user.passkey_register, oidc.client_create, user.identity_link, user.identity_unlink, identity_provider.create, user.api_key_create, user.api_key_rotate, user.api_key_revoke, user.session_revoke, user.status_change, org.create, org.update, org.delete, org.member_set, org.member_remove".to_string()),
+            },
+            TestCase {
+                input: AuditQuery { limit: Some(0), ..Default::default() },
+                output: Err("limit must be between 1 and 500".to_string()),
+            },
+            TestCase {
+                input: AuditQuery { offset: Some(-1), ..Default::default() },
+                output: Err("offset must not be negative".to_string()),
+            },
+            TestCase {
+                input: AuditQuery {
+                    from: Some("2024-02-01T00:00:00Z".parse().unwrap()),
+                    to: Some("2024-01-01T00:00:00Z".parse().unwrap()),
+                    ..Default::default()
+                },
+                output: Err("from must not be after to".to_string()),
+            },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_audit_query(&test_case.input), test_case.output);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use tracing::instrument;
+
+use crate::internal::audit::entity::audit::{AuditPage, AuditQuery, AuditRecord, AUDIT_DEFAULT_LIMIT};
+use crate::internal::audit::usecase::traits::{AuditUseCase, UseCase};
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+impl AuditUseCase {
+    // Appends one entry. The change it describes has already happened, so a
+    // failed write is logged rather than turned into an error for the caller.
+    #[instrument(name = "audit_use_case.record", skip_all, fields(action = %record.action))]
+    pub async fn record(&self, record: AuditRecord) {
+        if let Err(err) = self.repo.audit_insert(record).await {
+            tracing::error!(error = %err, "repo.audit_insert failed");
+        }
+    }
+}
+
+#[async_trait]
+impl UseCase for AuditUseCase {
+    #[instrument(name = "audit_use_case.audit_list", skip(self))]
+    async fn audit_list(&self, mut query: AuditQuery) -> Result<AuditPage, ErrorResponseUseCase> {
+        let limit = query.limit.unwrap_or(AUDIT_DEFAULT_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+        query.limit = Some(limit);
+        query.offset = Some(offset);
+
+        let total = match self.repo.audit_count(query.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.audit_count failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(res);
+            }
+        };
+
+        match self.repo.audit_list(query).await {
+            Ok(items) => {
+                Ok(AuditPage {
+                    items,
+                    total,
+                    limit,
+                    offset,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.audit_list failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(res)
+            }
+        }
+    }
+}
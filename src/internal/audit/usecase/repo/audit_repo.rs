@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::audit::entity::audit::{AuditEntry, AuditQuery, AuditRecord};
+use crate::internal::audit::usecase::repo::repo::AuditRepo;
+use crate::internal::audit::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+// Every filter is optional; a NULL parameter disables its condition.
+const AUDIT_FILTER: &str = "($1::varchar IS NULL OR action=$1) AND ($2::integer IS NULL OR actor_id=$2) \
+    AND ($3::integer IS NULL OR target_id=$3) AND ($4::timestamptz IS NULL OR create_ts>=$4) \
+    AND ($5::timestamptz IS NULL OR create_ts<$5)";
+
+#[async_trait]
+impl Repo for AuditRepo {
+    #[instrument(
+        name = "audit_repo.audit_insert",
+        skip_all,
+        fields(action = %record.action, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn audit_insert(&self, record: AuditRecord) -> Result<(), Error> {
+        let sql = "INSERT INTO tbl_audit_log(action, actor_id, target_id, target_username, ip, user_agent, request_id, changes) \
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8)";
+        record_statement(sql);
+
+        let query = sqlx::query(sql)
+            .bind(record.action)
+            .bind(record.actor_id)
+            .bind(record.target_id)
+            .bind(record.target_username)
+            .bind(record.ip)
+            .bind(record.user_agent)
+            .bind(record.request_id)
+            .bind(record.changes);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "audit_repo.audit_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn audit_list(&self, query: AuditQuery) -> Result<Vec<AuditEntry>, Error> {
+        let sql = format!(
+            "SELECT id, action, actor_id, target_id, target_username, ip, user_agent, request_id, changes, create_ts \
+            FROM tbl_audit_log WHERE {} ORDER BY id DESC LIMIT $6 OFFSET $7",
+            AUDIT_FILTER,
+        );
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, AuditEntry>(&sql)
+            .bind(query.action)
+            .bind(query.actor_id)
+            .bind(query.target_id)
+            .bind(query.from)
+            .bind(query.to)
+            .bind(query.limit)
+            .bind(query.offset);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "audit_repo.audit_count",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn audit_count(&self, query: AuditQuery) -> Result<i64, Error> {
+        let sql = format!("SELECT COUNT(*) FROM tbl_audit_log WHERE {}", AUDIT_FILTER);
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, (i64,)>(&sql)
+            .bind(query.action)
+            .bind(query.actor_id)
+            .bind(query.target_id)
+            .bind(query.from)
+            .bind(query.to);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data.0)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+}
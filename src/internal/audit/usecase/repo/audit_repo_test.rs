@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+    use serde_json::json;
+
+    use crate::internal::audit::entity::audit::{
+        new_audit_record, AuditContext, AuditQuery, AUDIT_LOGIN_FAILURE, AUDIT_USER_UPDATE,
+    };
+    use crate::internal::audit::usecase::repo::repo::new_audit_repo;
+    use crate::internal::audit::usecase::traits::Repo;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn audit_insert_and_list_test() {
+        let test_db = new_test_db().await;
+        let repo = new_audit_repo(web::Data::new(test_db.db.clone()));
+
+        let ctx = AuditContext {
+            actor: Some("JamesHolland".to_string()),
+            ip: Some("10.0.0.1".to_string()),
+            user_agent: Some("curl/8.0".to_string()),
+            request_id: Some("req-1".to_string()),
+        };
+        for target_id in 1..=3 {
+            let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+            record.actor_id = Some(1);
+            record.target_id = Some(target_id);
+            record.changes = json!({"firstname": {"before": "James", "after": "Jim"}});
+            repo.audit_insert(record).await.unwrap();
+        }
+        repo.audit_insert(new_audit_record(&AuditContext::default(), AUDIT_LOGIN_FAILURE)).await.unwrap();
+
+        let all = AuditQuery { limit: Some(50), offset: Some(0), ..Default::default() };
+        let entries = repo.audit_list(all.clone()).await.unwrap();
+        assert_eq!(repo.audit_count(all).await.unwrap(), 4);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].action, AUDIT_LOGIN_FAILURE);
+        assert_eq!(entries[0].actor_id, None);
+        assert_eq!(entries[1].target_id, Some(3));
+        assert_eq!(entries[1].ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(entries[1].user_agent.as_deref(), Some("curl/8.0"));
+        assert_eq!(entries[1].request_id.as_deref(), Some("req-1"));
+        assert_eq!(entries[1].changes, json!({"firstname": {"before": "James", "after": "Jim"}}));
+
+        let updates = AuditQuery {
+            action: Some(AUDIT_USER_UPDATE.to_string()),
+            actor_id: Some(1),
+            limit: Some(2),
+            offset: Some(1),
+            ..Default::default()
+        };
+        let page = repo.audit_list(updates.clone()).await.unwrap();
+        assert_eq!(page.iter().map(|e| e.target_id).collect::<Vec<_>>(), vec![Some(2), Some(1)]);
+        assert_eq!(repo.audit_count(updates).await.unwrap(), 3);
+
+        let one_target = AuditQuery { target_id: Some(2), limit: Some(50), offset: Some(0), ..Default::default() };
+        assert_eq!(repo.audit_count(one_target).await.unwrap(), 1);
+
+        let future = AuditQuery { from: Some(entries[0].create_ts + chrono::Duration::hours(1)), ..Default::default() };
+        assert_eq!(repo.audit_count(future).await.unwrap(), 0);
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn audit_append_only_test() {
+        let test_db = new_test_db().await;
+        let repo = new_audit_repo(web::Data::new(test_db.db.clone()));
+        repo.audit_insert(new_audit_record(&AuditContext::default(), AUDIT_LOGIN_FAILURE)).await.unwrap();
+
+        for sql in ["UPDATE tbl_audit_log SET action='user.create'", "DELETE FROM tbl_audit_log", "TRUNCATE tbl_audit_log"] {
+            let err = sqlx::query(sql).execute(&test_db.db).await.unwrap_err();
+            assert!(err.to_string().contains("append-only"), "{}", sql);
+        }
+
+        test_db.close().await;
+    }
+}
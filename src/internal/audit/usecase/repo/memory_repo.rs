@@ -0,0 +1,66 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::Error;
+
+use crate::internal::audit::entity::audit::{AuditEntry, AuditQuery, AuditRecord};
+use crate::internal::audit::usecase::traits::Repo;
+
+// In-memory stand-in for AuditRepo, used by tests that must not touch Postgres.
+
+#[derive(Default)]
+pub struct MemoryAuditRepo {
+    pub entries: Mutex<Vec<AuditEntry>>,
+}
+
+pub fn new_memory_audit_repo() -> MemoryAuditRepo {
+    MemoryAuditRepo::default()
+}
+
+impl MemoryAuditRepo {
+    fn matching(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter()
+            .rev()
+            .filter(|e| query.action.as_ref().is_none_or(|action| &e.action == action))
+            .filter(|e| query.actor_id.is_none_or(|id| e.actor_id == Some(id)))
+            .filter(|e| query.target_id.is_none_or(|id| e.target_id == Some(id)))
+            .filter(|e| query.from.is_none_or(|from| e.create_ts >= from))
+            .filter(|e| query.to.is_none_or(|to| e.create_ts < to))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Repo for MemoryAuditRepo {
+    async fn audit_insert(&self, record: AuditRecord) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let id = entries.len() as i64 + 1;
+
+        entries.push(AuditEntry {
+            id,
+            action: record.action,
+            actor_id: record.actor_id,
+            target_id: record.target_id,
+            target_username: record.target_username,
+            ip: record.ip,
+            user_agent: record.user_agent,
+            request_id: record.request_id,
+            changes: record.changes,
+            create_ts: Utc::now(),
+        });
+
+        Ok(())
+    }
+
+    async fn audit_list(&self, query: AuditQuery) -> Result<Vec<AuditEntry>, Error> {
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.unwrap_or(i64::MAX) as usize;
+        Ok(self.matching(&query).into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn audit_count(&self, query: AuditQuery) -> Result<i64, Error> {
+        Ok(self.matching(&query).len() as i64)
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct AuditRepo {
+    pub db: Data<Db>,
+}
+
+pub fn new_audit_repo(db: Data<Db>) -> AuditRepo {
+    AuditRepo{
+        db
+    }
+}
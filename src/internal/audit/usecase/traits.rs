@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::internal::audit::entity::audit::{AuditEntry, AuditPage, AuditQuery, AuditRecord};
+use crate::internal::controller::response;
+
+#[derive(Clone)]
+pub struct AuditUseCase {
+    pub repo: Arc<dyn Repo>,
+}
+
+pub fn new_audit_use_case(repo: Arc<dyn Repo>) -> AuditUseCase {
+    AuditUseCase {
+        repo,
+    }
+}
+
+#[async_trait]
+pub trait UseCase {
+    async fn audit_list(&self, query: AuditQuery) -> Result<AuditPage, response::ErrorResponseUseCase>;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn audit_insert(&self, record: AuditRecord) -> Result<(), sqlx::Error>;
+    // query.limit and query.offset are always set by the use case
+    async fn audit_list(&self, query: AuditQuery) -> Result<Vec<AuditEntry>, sqlx::Error>;
+    async fn audit_count(&self, query: AuditQuery) -> Result<i64, sqlx::Error>;
+}
@@ -0,0 +1,198 @@
+// API keys for service callers. Keys are managed with the owner's access
+// token only; a key can't be used to create or rotate other keys.
+pub mod api_key_routes {
+    use actix_web::{HttpRequest, Responder, web, delete, get, post};
+    use actix_web::http::StatusCode;
+    use crate::internal::user::entity::api_key::{
+        verify_api_key_create_request, ApiKeyCreateRequest, ApiKeyResponse, ApiKeySecretResponse,
+    };
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response};
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = ApiKeyCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Created key; api_key is shown only once", body = GeneralResponse<ApiKeySecretResponse>),
+            (status = 400, description = "Malformed request, unknown scope or expiry in the past", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/api-keys")]
+    pub async fn api_key_create(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let des: ApiKeyCreateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_api_key_create_request(&des, chrono::Utc::now()) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_api_key_create(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's API keys, without secrets", body = GeneralResponse<Vec<ApiKeyResponse>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/api-keys")]
+    pub async fn api_key_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.user_use_case.user_api_key_list(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        params(("id" = i32, Path, description = "API key id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "New secret for the key; the old one stops working", body = GeneralResponse<ApiKeySecretResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Caller has no such key", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/api-keys/{id}/rotate")]
+    pub async fn api_key_rotate(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_api_key_rotate(ctx, token_result.username, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        params(("id" = i32, Path, description = "API key id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Key revoked; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Caller has no such key", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/api-keys/{id}")]
+    pub async fn api_key_revoke(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_api_key_revoke(ctx, token_result.username, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_use_cases};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const API_KEYS: &str = "/api/v1/user/api-keys";
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    macro_rules! create_key {
+        ($app:expr, $scopes:expr) => {{
+            let req = test::TestRequest::post().uri(API_KEYS).insert_header(("Authorization", test_token()))
+                .set_json(json!({"name": "batch", "scopes": $scopes}));
+            let (status, body) = call!($app, req);
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            body["data"].clone()
+        }};
+    }
+
+    macro_rules! user_get {
+        ($app:expr, $key:expr) => {{
+            let req = test::TestRequest::get().uri("/api/v1/user/1/get").insert_header(("Authorization", format!("Bearer {}", $key)));
+            call!($app, req).0
+        }};
+    }
+
+    #[actix_web::test]
+    async fn api_key_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let read = create_key!(app, ["user:read"]);
+        let read_key = read["api_key"].as_str().unwrap().to_string();
+        assert!(read_key.starts_with(&format!("rck_{}_", read["prefix"].as_str().unwrap())));
+        assert_eq!(read["scopes"], json!(["user:read"]));
+
+        assert_eq!(user_get!(app, read_key), StatusCode::OK);
+        assert_eq!(user_get!(app, format!("{}x", read_key)), StatusCode::UNAUTHORIZED);
+
+        // Scopes are enforced, and keys can't manage keys.
+        let req = test::TestRequest::put().uri("/api/v1/user/update").insert_header(("Authorization", format!("Bearer {}", read_key)))
+            .set_json(json!({"id": 1, "username": "JamesHolland", "firstname": "Jim", "lastname": "Holland"}));
+        let (status, body) = call!(app, req);
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::FORBIDDEN, json!("Forbidden")));
+        let req = test::TestRequest::get().uri(API_KEYS).insert_header(("Authorization", format!("Bearer {}", read_key)));
+        assert_eq!(call!(app, req).0, StatusCode::UNAUTHORIZED);
+
+        let write = create_key!(app, ["user:write", "user:read", "user:write"]);
+        assert_eq!(write["scopes"], json!(["user:read", "user:write"]));
+        let req = test::TestRequest::put().uri("/api/v1/user/update").insert_header(("Authorization", format!("Bearer {}", write["api_key"].as_str().unwrap())))
+            .set_json(json!({"id": 1, "username": "JamesHolland", "firstname": "Jim", "lastname": "Holland"}));
+        let (status, body) = call!(app, req);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let req = test::TestRequest::post().uri(API_KEYS).insert_header(("Authorization", test_token()))
+            .set_json(json!({"name": "batch", "scopes": ["admin"]}));
+        let (status, body) = call!(app, req);
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::BAD_REQUEST, json!("unknown scope admin; expected one of user:read, user:write")));
+
+        let req = test::TestRequest::get().uri(API_KEYS).insert_header(("Authorization", test_token()));
+        let (status, body) = call!(app, req);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert!(body["data"][0].get("api_key").is_none());
+        assert!(!body["data"][0]["last_used_ts"].is_null());
+
+        // Rotating replaces the secret; the old key stops working at once.
+        let req = test::TestRequest::post().uri(&format!("{}/{}/rotate", API_KEYS, read["id"])).insert_header(("Authorization", test_token()));
+        let (status, body) = call!(app, req);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let rotated_key = body["data"]["api_key"].as_str().unwrap().to_string();
+        assert_eq!(user_get!(app, read_key), StatusCode::UNAUTHORIZED);
+        assert_eq!(user_get!(app, rotated_key), StatusCode::OK);
+
+        repos.user.api_keys.lock().unwrap()[0].expire_ts = Some(Utc::now() - Duration::seconds(1));
+        assert_eq!(user_get!(app, rotated_key), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete().uri(&format!("{}/{}", API_KEYS, write["id"])).insert_header(("Authorization", test_token()));
+        assert_eq!(call!(app, req).0, StatusCode::OK);
+        let req = test::TestRequest::delete().uri(&format!("{}/{}", API_KEYS, write["id"])).insert_header(("Authorization", test_token()));
+        let (status, body) = call!(app, req);
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("API key not found")));
+        assert_eq!(user_get!(app, write["api_key"].as_str().unwrap()), StatusCode::UNAUTHORIZED);
+
+        // The audit log never sees a secret.
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).filter(|a| a.starts_with("user.api_key")).collect();
+        assert_eq!(actions, vec!["user.api_key_create", "user.api_key_create", "user.api_key_rotate", "user.api_key_revoke"]);
+        for entry in entries.iter().filter(|e| e.action.starts_with("user.api_key")) {
+            assert_eq!((entry.actor_id, entry.target_id), (Some(1), Some(1)));
+            assert!(!entry.changes.to_string().contains("rck_"), "{}", entry.changes);
+        }
+    }
+}
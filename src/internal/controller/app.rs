@@ -0,0 +1,213 @@
+use std::time::Instant;
+use actix_web::{App, Error, web};
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONNECTION};
+use tracing::Instrument;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::UseCases;
+use crate::config::config::HttpConfig;
+use crate::internal::controller::api_key_controller::api_key_routes;
+use crate::internal::controller::audit_controller::audit_routes;
+use crate::internal::controller::job_controller::job_routes;
+use crate::internal::controller::health_controller::health_routes;
+use crate::internal::controller::identity_controller::identity_routes;
+use crate::internal::controller::logging_controller::logging_routes;
+use crate::internal::controller::metrics_controller::metrics_routes;
+use crate::internal::controller::mfa_controller::mfa_routes;
+use crate::internal::controller::oidc_controller::oidc_routes;
+use crate::internal::controller::openapi::{ApiDoc, OPENAPI_PATH, SWAGGER_UI_PATH};
+use crate::internal::controller::org_controller::org_routes;
+use crate::internal::controller::bulk_controller::bulk_routes;
+use crate::internal::controller::batch_controller::batch_routes;
+use crate::internal::controller::passkey_controller::passkey_routes;
+use crate::internal::controller::profile_controller::profile_routes;
+use crate::internal::controller::request_id::{request_id, with_request_id, REQUEST_ID_HEADER};
+use crate::internal::controller::session_controller::session_routes;
+use crate::internal::controller::user_controller::user_routes;
+use crate::pkg::shutdown::shutdown::Shutdown;
+use crate::pkg::telemetry::telemetry::{continue_trace, rename_span};
+
+// Builds the application shared by main.rs and the HTTP tests.
+pub fn new_app(use_cases: UseCases, cfg: &HttpConfig, shutdown: Shutdown) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let metrics = use_cases.metrics.clone();
+
+    App::new()
+        .app_data(web::Data::new(shutdown.clone()))
+        .app_data(web::Data::new(use_cases))
+        .app_data(web::PayloadConfig::new(cfg.max_body_size))
+        .service(health_routes::healthz)
+        .service(health_routes::readyz)
+        .service(health_routes::health)
+        .service(metrics_routes::metrics)
+        .service(logging_routes::log_level_get)
+        .service(logging_routes::log_level_set)
+        .service(SwaggerUi::new(format!("{}/{{_:.*}}", SWAGGER_UI_PATH)).url(OPENAPI_PATH, ApiDoc::openapi()))
+        // Only the API is measured; probes and scrapes would drown it out.
+        .service(
+            web::scope("")
+                .service(user_routes::user_auth)
+                .service(user_routes::user_create)
+                .service(user_routes::user_list)
+                .service(user_routes::user_me)
+                .service(user_routes::user_update_me)
+                .service(session_routes::session_list_me)
+                .service(session_routes::session_revoke_me)
+                .service(profile_routes::profile_get_me)
+                .service(profile_routes::profile_update_me)
+                .service(profile_routes::avatar_set_me)
+                .service(profile_routes::avatar_delete_me)
+                .service(user_routes::user_get)
+                .service(user_routes::user_update_by_id)
+                .service(user_routes::user_change_password)
+                .service(user_routes::user_delete)
+                .service(user_routes::user_set_status)
+                .service(bulk_routes::user_import)
+                .service(bulk_routes::user_export)
+                .service(batch_routes::user_batch)
+                .service(profile_routes::avatar_get)
+                .service(mfa_routes::mfa_enroll)
+                .service(mfa_routes::mfa_confirm)
+                .service(mfa_routes::mfa_verify)
+                .service(mfa_routes::mfa_reset)
+                .service(passkey_routes::passkey_register_begin)
+                .service(passkey_routes::passkey_register_finish)
+                .service(passkey_routes::passkey_login_begin)
+                .service(passkey_routes::passkey_login_finish)
+                .service(identity_routes::identity_login)
+                .service(identity_routes::identity_link)
+                .service(identity_routes::identity_list)
+                .service(identity_routes::identity_unlink)
+                .service(identity_routes::identity_provider_create)
+                .service(identity_routes::identity_provider_list)
+                .service(org_routes::org_create)
+                .service(org_routes::org_list)
+                .service(org_routes::org_get)
+                .service(org_routes::org_update)
+                .service(org_routes::org_delete)
+                .service(org_routes::org_member_list)
+                .service(org_routes::org_member_set)
+                .service(org_routes::org_member_remove)
+                .service(api_key_routes::api_key_create)
+                .service(api_key_routes::api_key_list)
+                .service(api_key_routes::api_key_rotate)
+                .service(api_key_routes::api_key_revoke)
+                .service(session_routes::session_list)
+                .service(session_routes::session_revoke)
+                .service(audit_routes::audit_list)
+                .service(job_routes::job_list)
+                .service(job_routes::job_get)
+                .service(job_routes::job_create)
+                .service(oidc_routes::oidc_discovery)
+                .service(oidc_routes::oidc_jwks)
+                .service(oidc_routes::oidc_authorize)
+                .service(oidc_routes::oidc_token)
+                .service(oidc_routes::oidc_userinfo)
+                .service(oidc_routes::oidc_client_create)
+                .service(oidc_routes::oidc_client_list)
+                .wrap_fn(move |req, srv| {
+                    let metrics = metrics.clone();
+                    let method = req.method().to_string();
+                    let start = Instant::now();
+                    let res = srv.call(req);
+
+                    async move {
+                        let res = res.await;
+                        let (route, status) = match &res {
+                            Ok(res) => {
+                                (res.request().match_pattern(), res.status())
+                            }
+                            Err(err) => {
+                                (None, err.as_response_error().status_code())
+                            }
+                        };
+                        let route = route.unwrap_or_else(|| "unmatched".to_string());
+                        metrics.observe_http(&method, &route, status.as_u16(), start.elapsed());
+
+                        res
+                    }
+                })
+        )
+        // While draining, ask keep-alive clients to reconnect elsewhere.
+        .wrap_fn(move |req, srv| {
+            let draining = !shutdown.is_ready();
+            let res = srv.call(req);
+
+            async move {
+                let mut res = res.await?;
+                if draining {
+                    res.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+                }
+                Ok(res)
+            }
+        })
+        // Outermost: everything below, including error bodies, sees the request id.
+        .wrap_fn(|req, srv| {
+            let id = request_id(&req);
+            let span = tracing::info_span!(
+                "request",
+                request_id = %id,
+                method = %req.method(),
+                path = %req.path(),
+                trace_id = tracing::field::Empty,
+                otel.name = %req.method(),
+                otel.kind = "server",
+                otel.status_code = tracing::field::Empty,
+                http.response.status_code = tracing::field::Empty,
+            );
+            continue_trace(&span, req.headers());
+            let method = req.method().to_string();
+            let start = Instant::now();
+            let res = span.in_scope(|| srv.call(req));
+
+            let header = HeaderValue::from_str(&id).ok();
+            let fut = async move {
+                let res = res.await;
+                match &res {
+                    Ok(res) => {
+                        let span = tracing::Span::current();
+                        if let Some(route) = res.request().match_pattern() {
+                            rename_span(&span, format!("{} {}", method, route));
+                        }
+                        span.record("http.response.status_code", res.status().as_u16());
+                        if res.status().is_server_error() {
+                            span.record("otel.status_code", "ERROR");
+                        }
+
+                        tracing::info!(
+                            status = res.status().as_u16(),
+                            latency_ms = start.elapsed().as_millis() as u64,
+                            "request completed",
+                        );
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            status = err.as_response_error().status_code().as_u16(),
+                            latency_ms = start.elapsed().as_millis() as u64,
+                            error = %err,
+                            "request failed",
+                        );
+                    }
+                }
+
+                res.map(|mut res| {
+                    if let Some(header) = header {
+                        res.headers_mut().insert(REQUEST_ID_HEADER, header);
+                    }
+                    res
+                })
+            };
+
+            with_request_id(id, fut.instrument(span))
+        })
+}
@@ -0,0 +1,77 @@
+// Read-only access to the audit log, restricted to auth.admins.
+pub mod audit_routes {
+    use actix_web::{Responder, web, get, HttpRequest};
+    use actix_web::http::StatusCode;
+    use crate::internal::audit::entity::audit::{verify_audit_query, AuditPage, AuditQuery};
+    use crate::internal::audit::usecase::traits::UseCase;
+    use crate::internal::controller::middleware::{self, AccessTokenError};
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response};
+
+    #[utoipa::path(
+        tag = "admin",
+        params(AuditQuery),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Matching entries, newest first", body = GeneralResponse<AuditPage>),
+            (status = 400, description = "Invalid filter", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/audit")]
+    pub async fn audit_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            let res = match err {
+                AccessTokenError::NotAdmin => {
+                    ErrorResponseUseCase {
+                        status_code: StatusCode::FORBIDDEN,
+                        error_msg: "Forbidden".to_string(),
+                    }
+                }
+                _ => {
+                    ErrorResponseUseCase {
+                        status_code: StatusCode::UNAUTHORIZED,
+                        error_msg: "Unauthorized".to_string(),
+                    }
+                }
+            };
+
+            return send_error_response(res);
+        }
+
+        let query = match web::Query::<AuditQuery>::from_query(req.query_string()) {
+            Ok(res) => {
+                res.into_inner()
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid query".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_audit_query(&query) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        return match use_cases.audit_use_case.audit_list(query).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
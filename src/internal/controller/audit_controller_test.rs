@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_use_cases};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const PEER: &str = "203.0.113.7:40000";
+
+    #[actix_web::test]
+    async fn audit_trail_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let requests = vec! {
+            test::TestRequest::post().uri("/api/v1/user/create")
+                .insert_header(("Authorization", test_token()))
+                .set_json(json!({"username": "JohnSmith", "password": "john123", "firstname": "John", "lastname": "Smith"})),
+            test::TestRequest::put().uri("/api/v1/user/update")
+                .insert_header(("Authorization", test_token()))
+                .set_json(json!({"id": 2, "username": "JohnSmith", "firstname": "Johnny", "lastname": "Smith"})),
+            test::TestRequest::post().uri("/api/v1/user/password-change")
+                .set_json(json!({"id": 2, "old_password": "john123", "new_password": "john456"})),
+            test::TestRequest::post().uri("/api/v1/user/auth")
+                .set_json(json!({"username": "JohnSmith", "password": "john456"})),
+            test::TestRequest::post().uri("/api/v1/user/auth")
+                .set_json(json!({"username": "JohnSmith", "password": "john123"})),
+            test::TestRequest::post().uri("/api/v1/user/auth")
+                .set_json(json!({"username": "Nobody", "password": "nobody1"})),
+            test::TestRequest::delete().uri("/api/v1/user/2/delete")
+                .insert_header(("Authorization", test_token())),
+        };
+        for request in requests {
+            let req = request
+                .peer_addr(PEER.parse().unwrap())
+                .insert_header(("User-Agent", "audit-test/1.0"))
+                .insert_header(("X-Request-Id", "req-audit"))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_ne!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec![
+            "user.create", "user.update", "user.password_change",
+            "user.login_success", "user.login_failure", "user.login_failure", "user.delete",
+        ]);
+        for entry in &entries {
+            assert_eq!(entry.ip.as_deref(), Some("203.0.113.7"));
+            assert_eq!(entry.user_agent.as_deref(), Some("audit-test/1.0"));
+            assert_eq!(entry.request_id.as_deref(), Some("req-audit"));
+            assert!(!entry.changes.to_string().contains("john"), "{}", entry.action);
+        }
+
+        let (create, update, password, success, failure, unknown, delete) =
+            (&entries[0], &entries[1], &entries[2], &entries[3], &entries[4], &entries[5], &entries[6]);
+        assert_eq!((create.actor_id, create.target_id), (Some(1), Some(2)));
+        assert_eq!(create.changes["firstname"], json!({"before": null, "after": "John"}));
+        assert!(create.changes.get("password").is_none());
+        assert_eq!((update.actor_id, update.target_id), (Some(1), Some(2)));
+        assert_eq!(update.changes, json!({"firstname": {"before": "John", "after": "Johnny"}}));
+        assert_eq!((password.actor_id, password.target_id), (Some(2), Some(2)));
+        assert_eq!(password.changes, json!({}));
+        assert_eq!((success.actor_id, success.target_id), (Some(2), Some(2)));
+        assert_eq!((failure.actor_id, failure.target_id), (None, Some(2)));
+        assert_eq!((unknown.target_id, unknown.target_username.as_deref()), (None, Some("Nobody")));
+        assert_eq!((delete.actor_id, delete.target_id), (Some(1), Some(2)));
+        assert_eq!(delete.changes["username"], json!({"before": "JohnSmith", "after": null}));
+    }
+
+    #[actix_web::test]
+    async fn audit_list_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        for password in ["james123", "wrong12", "wrong34"] {
+            let req = test::TestRequest::post().uri("/api/v1/user/auth")
+                .set_json(json!({"username": "JamesHolland", "password": password}))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/audit?action=user.login_failure&target_id=1&limit=1&offset=1")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["total"], json!(2));
+        assert_eq!(body["data"]["limit"], json!(1));
+        assert_eq!(body["data"]["offset"], json!(1));
+        let items = body["data"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], json!(2));
+        assert_eq!(items[0]["target_username"], json!("JamesHolland"));
+
+        let req = test::TestRequest::get().uri("/api/v1/audit")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(body["data"]["total"], json!(3));
+        assert_eq!(body["data"]["limit"], json!(50));
+        assert_eq!(body["data"]["items"][0]["action"], json!("user.login_failure"));
+    }
+
+    #[actix_web::test]
+    async fn audit_list_access_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let not_admin = test_not_admin_token(&repos);
+
+        let test_cases = vec! {
+            ("no token", "/api/v1/audit", None, StatusCode::UNAUTHORIZED, "Unauthorized"),
+            ("not an admin", "/api/v1/audit", Some(not_admin), StatusCode::FORBIDDEN, "Forbidden"),
+            ("bad number", "/api/v1/audit?limit=ten", Some(test_token()), StatusCode::BAD_REQUEST, "Invalid query"),
+            ("limit too big", "/api/v1/audit?limit=501", Some(test_token()), StatusCode::BAD_REQUEST, "limit must be between 1 and 500"),
+            ("bad date", "/api/v1/audit?from=yesterday", Some(test_token()), StatusCode::BAD_REQUEST, "Invalid query"),
+        };
+
+        for (name, uri, token, status, error_msg) in test_cases {
+            let mut req = test::TestRequest::get().uri(uri);
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), status, "{}", name);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["data"]["error_msg"], json!(error_msg), "{}", name);
+        }
+    }
+}
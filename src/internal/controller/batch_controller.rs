@@ -0,0 +1,91 @@
+// Several user operations for admins in one request, each with its own result.
+pub mod batch_routes {
+    use actix_web::{HttpRequest, Responder, web, post};
+    use actix_web::http::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use utoipa::ToSchema;
+    use crate::internal::user::entity::batch::{verify_batch_request, BatchRequest};
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, to_error_response, admin_error};
+
+    #[derive(Serialize, Deserialize, Debug, ToSchema)]
+    pub struct BatchItemResult {
+        pub index: usize, // of the operation in the request
+        pub success: bool,
+        // What the single route answers with: the user for create, update and
+        // status, nothing for delete.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<Object>)]
+        pub data: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub error: Option<ErrorResponse>,
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        request_body = BatchRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The result of every operation, in request order. With atomic, either all succeeded or none was applied", body = GeneralResponse<Vec<BatchItemResult>>),
+            (status = 400, description = "Malformed request, no or too many operations, or a user in more than one operation", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/batch")]
+    pub async fn user_batch(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let des: BatchRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_batch_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+        let results: Vec<BatchItemResult> = use_cases.user_use_case.user_batch(ctx, des).await
+            .into_iter()
+            .enumerate()
+            .map(|(index, res)| match res {
+                Ok(data) => {
+                    BatchItemResult { index, success: true, data: Some(data).filter(|data| !data.is_null()), error: None }
+                }
+                Err(err) => {
+                    BatchItemResult { index, success: false, data: None, error: Some(to_error_response(err)) }
+                }
+            })
+            .collect();
+
+        send_success_response(results)
+    }
+}
@@ -0,0 +1,216 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::audit::entity::audit::{AUDIT_USER_CREATE, AUDIT_USER_DELETE, AUDIT_USER_STATUS_CHANGE, AUDIT_USER_UPDATE};
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_token_config, test_use_cases, TestRepos};
+    use crate::internal::user::entity::token::generate_access_token;
+    use crate::internal::user::entity::user::UserStatus;
+    use crate::internal::user::usecase::repo::memory_repo::MemoryUser;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    macro_rules! call {
+        ($app:expr, $token:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/api/v1/user/batch")
+                .insert_header(("Authorization", $token))
+                .set_payload($body.to_string());
+            let res = test::call_service(&$app, req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    // Adds AnnaSmith (2) and MaryJones (3), who are not service admins.
+    fn add_users(repos: &TestRepos) {
+        for (id, username, firstname, lastname) in [(2, "AnnaSmith", "Anna", "Smith"), (3, "MaryJones", "Mary", "Jones")] {
+            repos.user.users.lock().unwrap().push(MemoryUser {
+                id,
+                username: username.to_string(),
+                firstname: firstname.to_string(),
+                lastname: lastname.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    fn usernames(repos: &TestRepos) -> Vec<String> {
+        repos.user.users.lock().unwrap().iter().map(|user| user.username.clone()).collect()
+    }
+
+    fn actions(repos: &TestRepos) -> Vec<(String, Option<i32>)> {
+        repos.audit.entries.lock().unwrap().iter().map(|e| (e.action.clone(), e.target_id)).collect()
+    }
+
+    // The status code and error message of every item, or 200 for a success.
+    fn items(body: &Value) -> Vec<(i64, String)> {
+        body["data"].as_array().unwrap().iter().enumerate().map(|(index, item)| {
+            assert_eq!(item["index"], json!(index));
+            match item["success"].as_bool().unwrap() {
+                true => (200, String::new()),
+                false => (item["error"]["status_code"].as_i64().unwrap(), item["error"]["error_msg"].as_str().unwrap().to_string()),
+            }
+        }).collect()
+    }
+
+    #[actix_web::test]
+    async fn user_batch_request_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let anna = format!("Bearer {}", generate_access_token(&test_token_config(), &"AnnaSmith".to_string()));
+
+        let test_cases = vec! {
+            (anna, json!({"operations": [{"op": "delete", "id": 3}]}), StatusCode::FORBIDDEN, "Forbidden"),
+            ("Bearer nope".to_string(), json!({"operations": [{"op": "delete", "id": 3}]}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (test_token(), json!({"operations": [{"op": "rename", "id": 3}]}), StatusCode::BAD_REQUEST, "Can't convert request"),
+            (test_token(), json!({"operations": []}), StatusCode::BAD_REQUEST, "operations must have 1 to 100 entries"),
+            (
+                test_token(),
+                json!({"operations": [{"op": "delete", "id": 3}, {"op": "status", "id": 3, "action": "lock"}]}),
+                StatusCode::BAD_REQUEST,
+                "User with id=3 is in more than one operation",
+            ),
+        };
+        for (token, body, status, error_msg) in test_cases {
+            let (res_status, res_body) = call!(app, token, body);
+            assert_eq!(res_status, status, "{}", body);
+            assert_eq!(res_body["data"]["error_msg"], json!(error_msg));
+        }
+
+        assert_eq!(usernames(&repos).len(), 3);
+        assert!(repos.audit.entries.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn user_batch_best_effort_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, body) = call!(app, test_token(), json!({"operations": [
+            {"op": "create", "username": "PeterBrown", "password": "password", "firstname": "Peter", "lastname": "Brown"},
+            {"op": "update", "id": 2, "username": "AnnaJones", "firstname": "Anna", "lastname": "Jones"},
+            {"op": "delete", "id": 99},
+            {"op": "status", "id": 3, "action": "lock"},
+            {"op": "create", "username": "Bob", "password": "password", "firstname": "Bob", "lastname": "Brown"},
+            {"op": "status", "id": 1, "action": "unlock"},
+        ]}));
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(items(&body), vec![
+            (200, String::new()),
+            (200, String::new()),
+            (404, "User with id=99 not found".to_string()),
+            (200, String::new()),
+            (400, "invalid username".to_string()),
+            (409, "can't unlock an account that is active".to_string()),
+        ]);
+        assert_eq!(body["data"][0]["data"]["username"], json!("PeterBrown"));
+        assert_eq!(body["data"][1]["data"]["username"], json!("AnnaJones"));
+        assert_eq!(body["data"][3]["data"]["status"], json!("locked"));
+        assert!(body["data"][2].get("data").is_none());
+
+        assert_eq!(usernames(&repos), vec!["JamesHolland", "AnnaJones", "MaryJones", "PeterBrown"]);
+        assert_eq!(actions(&repos), vec![
+            (AUDIT_USER_CREATE.to_string(), Some(4)),
+            (AUDIT_USER_UPDATE.to_string(), Some(2)),
+            (AUDIT_USER_STATUS_CHANGE.to_string(), Some(3)),
+        ]);
+    }
+
+    #[actix_web::test]
+    async fn user_batch_atomic_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        // A failing check leaves everything as it was.
+        let (status, body) = call!(app, test_token(), json!({"atomic": true, "operations": [
+            {"op": "update", "id": 2, "username": "AnnaJones", "firstname": "Anna", "lastname": "Jones"},
+            {"op": "create", "username": "MaryJones", "password": "password", "firstname": "Mary", "lastname": "Jones"},
+            {"op": "delete", "id": 3},
+        ]}));
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(items(&body), vec![
+            (409, "Not applied, operation 1 failed".to_string()),
+            (409, "User with username=MaryJones already exists".to_string()),
+            (409, "Not applied, operation 1 failed".to_string()),
+        ]);
+        assert_eq!(usernames(&repos), vec!["JamesHolland", "AnnaSmith", "MaryJones"]);
+        assert!(repos.audit.entries.lock().unwrap().is_empty());
+
+        // PeterBrown is not in the database, so every operation passes its
+        // own check; the second one to write the name is refused.
+        let test_cases = vec! {
+            json!([
+                {"op": "create", "username": "PeterBrown", "password": "password", "firstname": "Peter", "lastname": "Brown"},
+                {"op": "update", "id": 2, "username": "PeterBrown", "firstname": "Anna", "lastname": "Smith"},
+            ]),
+            json!([
+                {"op": "update", "id": 2, "username": "PeterBrown", "firstname": "Anna", "lastname": "Smith"},
+                {"op": "update", "id": 3, "username": "PeterBrown", "firstname": "Mary", "lastname": "Jones"},
+            ]),
+        };
+        for operations in test_cases {
+            let (status, body) = call!(app, test_token(), json!({"atomic": true, "operations": operations}));
+
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(items(&body), vec![
+                (409, "Not applied, operation 1 failed".to_string()),
+                (409, "User with username=PeterBrown already exists".to_string()),
+            ], "{}", operations);
+        }
+        assert_eq!(usernames(&repos), vec!["JamesHolland", "AnnaSmith", "MaryJones"]);
+        assert!(repos.audit.entries.lock().unwrap().is_empty());
+
+        // A rename onto an account outside the batch is refused as well.
+        let (status, body) = call!(app, test_token(), json!({"atomic": true, "operations": [
+            {"op": "update", "id": 2, "username": "MaryJones", "firstname": "Anna", "lastname": "Smith"},
+        ]}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(items(&body), vec![(409, "User with username=MaryJones already exists".to_string())]);
+
+        let (status, body) = call!(app, test_token(), json!({"atomic": true, "operations": [
+            {"op": "create", "username": "PeterBrown", "password": "password", "firstname": "Peter", "lastname": "Brown"},
+            {"op": "delete", "id": 2},
+            {"op": "status", "id": 3, "action": "disable", "reason": " left the company "},
+        ]}));
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(items(&body), vec![(200, String::new()); 3]);
+        assert_eq!(body["data"][0]["data"]["id"], json!(4));
+        assert_eq!(body["data"][2]["data"]["status"], json!("disabled"));
+
+        assert_eq!(usernames(&repos), vec!["JamesHolland", "MaryJones", "PeterBrown"]);
+        let users = repos.user.users.lock().unwrap().clone();
+        assert_ne!(users[2].password, "password");
+        assert_eq!((users[1].status, users[1].status_reason.as_deref()), (UserStatus::Disabled, Some("left the company")));
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        assert_eq!(actions(&repos), vec![
+            (AUDIT_USER_CREATE.to_string(), Some(4)),
+            (AUDIT_USER_DELETE.to_string(), Some(2)),
+            (AUDIT_USER_STATUS_CHANGE.to_string(), Some(3)),
+        ]);
+        assert!(entries.iter().all(|e| e.actor_id == Some(1)));
+        assert_eq!(entries[2].changes["reason"]["after"], json!("left the company"));
+
+        // Only a changed username is taken; one kept by an update is not.
+        let (status, body) = call!(app, test_token(), json!({"atomic": true, "operations": [
+            {"op": "update", "id": 4, "username": "PeterGreen", "firstname": "Peter", "lastname": "Green"},
+            {"op": "update", "id": 3, "username": "MaryJones", "firstname": "Marie", "lastname": "Jones"},
+        ]}));
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(items(&body), vec![(200, String::new()); 2]);
+        assert_eq!(usernames(&repos), vec!["JamesHolland", "MaryJones", "PeterGreen"]);
+    }
+}
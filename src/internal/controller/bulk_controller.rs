@@ -0,0 +1,294 @@
+// Bulk import and export of users for admins. Both are streamed: an import is
+// applied record by record as the body arrives, an export is written page by
+// page as it is read.
+pub mod bulk_routes {
+    use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web, get, post};
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::CONTENT_DISPOSITION;
+    use futures_util::{stream, StreamExt};
+    use crate::internal::audit::entity::audit::AuditContext;
+    use crate::internal::user::entity::bulk::{
+        bulk_format_from_content_type, export_header, export_record, parse_bulk_format, parse_csv_header,
+        parse_import_query, parse_import_record, BulkFormat, CsvColumns, ExportQuery, ImportOptions, ImportQuery,
+        ImportReport, RecordReader, EXPORT_PAGE_SIZE, IMPORT_MAX_ROWS,
+    };
+    use crate::internal::user::entity::user::{parse_user_status, UserGetResponse};
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error, bad_request};
+
+    // Where the page after this one starts; None when it was the last.
+    fn next_page(users: &[UserGetResponse]) -> Option<i32> {
+        if users.len() < EXPORT_PAGE_SIZE as usize {
+            return None;
+        }
+
+        users.last().map(|user| user.id)
+    }
+
+    struct Import<'a> {
+        use_cases: &'a crate::UseCases,
+        ctx: AuditContext,
+        options: ImportOptions,
+        format: BulkFormat,
+        columns: Option<CsvColumns>, // from the CSV header, once it was read
+        report: ImportReport,
+    }
+
+    impl Import<'_> {
+        // Rows already applied stay applied, so whatever stops the import is
+        // reported next to them rather than instead of them.
+        async fn run(mut self, mut payload: web::Payload) -> Result<ImportReport, ErrorResponseUseCase> {
+            let mut reader = RecordReader::new(self.format);
+
+            while let Some(chunk) = payload.next().await {
+                match chunk {
+                    Ok(res) => {
+                        reader.push(&res);
+                    }
+                    Err(_err) => {
+                        self.report.error = Some("the body could not be read".to_string());
+                        return Ok(self.report);
+                    }
+                }
+
+                while let Some(record) = reader.next_record() {
+                    if !self.next(record).await? {
+                        return Ok(self.report);
+                    }
+                }
+            }
+
+            if let Some(record) = reader.finish() {
+                self.next(Ok(record)).await?;
+            }
+
+            Ok(self.report)
+        }
+
+        // Takes the next record unless the import has to stop, in which case
+        // the report says why and this returns false.
+        async fn next(&mut self, record: Result<Vec<u8>, String>) -> Result<bool, ErrorResponseUseCase> {
+            let record = match record {
+                Ok(res) => {
+                    res
+                }
+                Err(err) => {
+                    self.report.error = Some(err);
+                    return Ok(false);
+                }
+            };
+
+            if self.report.is_full() {
+                self.report.error = Some(format!("an import must have at most {} rows", IMPORT_MAX_ROWS));
+                return Ok(false);
+            }
+
+            self.record(&record).await?;
+            Ok(true)
+        }
+
+        // Applies one record and adds its row to the report. A bad CSV header
+        // fails the import, which has not changed anything yet.
+        async fn record(&mut self, record: &[u8]) -> Result<(), ErrorResponseUseCase> {
+            if self.format == BulkFormat::Csv && self.columns.is_none() {
+                let columns = parse_csv_header(record).map_err(|error_msg| ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg,
+                })?;
+                self.columns = Some(columns);
+                return Ok(());
+            }
+
+            let (username, res) = match parse_import_record(self.format, self.columns.as_ref(), record) {
+                Ok(user) => {
+                    let username = Some(user.username.clone());
+                    if !self.report.first_occurrence(&user.username) {
+                        (username, Err("username is already in an earlier row".to_string()))
+                    } else {
+                        let res = self.use_cases.user_use_case.user_import_row(self.ctx.clone(), user, self.options).await;
+                        (username, res.map_err(|err| err.error_msg))
+                    }
+                }
+                Err((username, err)) => {
+                    (username, Err(err))
+                }
+            };
+            self.report.push(username, res);
+
+            Ok(())
+        }
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(ImportQuery),
+        request_body(
+            content = String,
+            content_type = "text/csv",
+            description = "A header with username, password, firstname and lastname, then one user per row; \
+            or, as application/x-ndjson, one JSON object with these fields per line",
+        ),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The result of every row. When error is set, the import stopped after the last row of the report", body = GeneralResponse<ImportReport>),
+            (status = 400, description = "Invalid query or CSV header", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 415, description = "Body is neither CSV nor NDJSON", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/import")]
+    pub async fn user_import(
+        req: HttpRequest,
+        payload: web::Payload,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let options = match web::Query::<ImportQuery>::from_query(req.query_string()) {
+            Ok(res) => {
+                match parse_import_query(&res) {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(err) => {
+                        return bad_request(err);
+                    }
+                }
+            }
+            Err(_err) => {
+                return bad_request("Invalid query".to_string());
+            }
+        };
+
+        let content_type = req.mime_type().ok().flatten().map(|mime| mime.essence_str().to_string()).unwrap_or_default();
+        let format = match bulk_format_from_content_type(&content_type) {
+            Some(res) => {
+                res
+            }
+            None => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    error_msg: "body must be text/csv or application/x-ndjson".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let import = Import {
+            use_cases: &use_cases,
+            ctx: middleware::audit_context(&req, Some(&token_result)),
+            options,
+            format,
+            columns: None,
+            report: ImportReport::new(options.dry_run),
+        };
+        return match import.run(payload).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(ExportQuery),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "All users, or those with the given status, ordered by id; as CSV with a header, or NDJSON with the fields of user_list. Passwords are not exported", content(
+                (String = "application/x-ndjson"),
+                (String = "text/csv"),
+            )),
+            (status = 400, description = "Invalid format or status", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/export")]
+    pub async fn user_export(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        let query = match web::Query::<ExportQuery>::from_query(req.query_string()) {
+            Ok(res) => {
+                res.into_inner()
+            }
+            Err(_err) => {
+                return bad_request("Invalid query".to_string());
+            }
+        };
+
+        let format = match query.format.as_deref().map(parse_bulk_format).transpose() {
+            Ok(res) => {
+                res.unwrap_or(BulkFormat::Ndjson)
+            }
+            Err(err) => {
+                return bad_request(err);
+            }
+        };
+
+        let status = match query.status.as_deref().map(parse_user_status).transpose() {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return bad_request(err);
+            }
+        };
+
+        // The first page is read before answering, so a failing database is
+        // still a 500 rather than an empty export.
+        let first = match use_cases.user_use_case.user_list_page(status, 0, EXPORT_PAGE_SIZE).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return send_error_response(err);
+            }
+        };
+
+        let mut chunk = export_header(format).unwrap_or_default();
+        chunk.extend(first.iter().map(|user| export_record(format, user)));
+        let after_id = next_page(&first);
+
+        // A later page that fails ends the body early; the client sees the
+        // connection close without a final chunk.
+        let pages = stream::unfold(after_id, move |after_id| {
+            let use_cases = use_cases.clone();
+            async move {
+                let after_id = after_id?;
+                match use_cases.user_use_case.user_list_page(status, after_id, EXPORT_PAGE_SIZE).await {
+                    Ok(users) => {
+                        let chunk: String = users.iter().map(|user| export_record(format, user)).collect();
+                        Some((Ok(web::Bytes::from(chunk)), next_page(&users)))
+                    }
+                    Err(err) => {
+                        Some((Err(actix_web::error::ErrorInternalServerError(err.error_msg)), None))
+                    }
+                }
+            }
+        });
+        let body = stream::once(async move { Ok::<_, actix_web::Error>(web::Bytes::from(chunk)) }).chain(pages);
+
+        HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((CONTENT_DISPOSITION, format!("attachment; filename=\"users.{}\"", format.extension())))
+            .streaming(body)
+    }
+}
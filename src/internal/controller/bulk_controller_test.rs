@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::audit::entity::audit::{AUDIT_USER_CREATE, AUDIT_USER_PASSWORD_CHANGE, AUDIT_USER_UPDATE};
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_token_config, test_use_cases, TestRepos};
+    use crate::internal::user::entity::bulk::{EXPORT_PAGE_SIZE, IMPORT_MAX_RECORD_LEN, IMPORT_MAX_ROWS};
+    use crate::internal::user::entity::token::generate_access_token;
+    use crate::internal::user::entity::user::UserStatus;
+    use crate::internal::user::usecase::repo::memory_repo::MemoryUser;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    macro_rules! import {
+        ($app:expr, $uri:expr, $content_type:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri($uri)
+                .insert_header(("Authorization", test_token()))
+                .insert_header(("Content-Type", $content_type))
+                .set_payload($body);
+            call!($app, req)
+        }};
+    }
+
+    // Returns the status, the headers and the body as text.
+    macro_rules! export {
+        ($app:expr, $uri:expr, $token:expr) => {{
+            let req = test::TestRequest::get().uri($uri).insert_header(("Authorization", $token));
+            let res = test::call_service(&$app, req.to_request()).await;
+            let status = res.status();
+            let headers = res.headers().clone();
+            let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+            (status, headers, body)
+        }};
+    }
+
+    // Adds AnnaSmith (2), who is not a service admin.
+    fn add_user(repos: &TestRepos) {
+        repos.user.users.lock().unwrap().push(MemoryUser {
+            id: 2,
+            username: "AnnaSmith".to_string(),
+            firstname: "Anna".to_string(),
+            lastname: "Smith".to_string(),
+            ..Default::default()
+        });
+    }
+
+    fn token(username: &str) -> String {
+        format!("Bearer {}", generate_access_token(&test_token_config(), &username.to_string()))
+    }
+
+    #[actix_web::test]
+    async fn user_import_request_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_user(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let test_cases = vec! {
+            (token("AnnaSmith"), "/api/v1/user/import", "text/csv", StatusCode::FORBIDDEN, json!("Forbidden")),
+            ("Bearer nope".to_string(), "/api/v1/user/import", "text/csv", StatusCode::UNAUTHORIZED, json!("Unauthorized")),
+            (test_token(), "/api/v1/user/import", "application/json", StatusCode::UNSUPPORTED_MEDIA_TYPE, json!("body must be text/csv or application/x-ndjson")),
+            (test_token(), "/api/v1/user/import?on_conflict=replace", "text/csv", StatusCode::BAD_REQUEST, json!("on_conflict must be one of skip, update")),
+            (test_token(), "/api/v1/user/import?dry_run=maybe", "text/csv", StatusCode::BAD_REQUEST, json!("Invalid query")),
+            (test_token(), "/api/v1/user/import", "text/csv", StatusCode::BAD_REQUEST, json!("the CSV header must have the columns username, password, firstname, lastname")),
+        };
+        for (bearer, uri, content_type, status, error_msg) in test_cases {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("Authorization", bearer))
+                .insert_header(("Content-Type", content_type))
+                .set_payload("username,firstname,lastname\nMaryJones,Mary,Jones\n");
+            let (res, body) = call!(app, req);
+            assert_eq!((res, body["data"]["error_msg"].clone()), (status, error_msg), "{} {}", uri, content_type);
+        }
+
+        assert_eq!(repos.user.users.lock().unwrap().len(), 2);
+        assert!(repos.audit.entries.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn user_import_csv_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_user(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let csv = "id,username,password,firstname,lastname\r\n\
+            ,MaryJones,mary1234,Mary,\"Jones, Jr.\"\r\n\
+            \r\n\
+            1,JamesHolland,newpass123,Jim,Holland\r\n\
+            ,Mary,mary1234,Mary,Jones\r\n\
+            ,MaryJones,mary1234,Mary,Jones\r\n\
+            ,BobJones,bob12345,Bob\r\n";
+
+        // A dry run reports what would happen and changes nothing.
+        let (status, body) = import!(app, "/api/v1/user/import?dry_run=true&on_conflict=update", "text/csv", csv);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"], json!({
+            "dry_run": true,
+            "created": 1,
+            "updated": 1,
+            "skipped": 0,
+            "failed": 3,
+            "rows": [
+                {"row": 1, "username": "MaryJones", "status": "created"},
+                {"row": 2, "username": "JamesHolland", "status": "updated"},
+                {"row": 3, "username": "Mary", "status": "failed", "error": "invalid username"},
+                {"row": 4, "username": "MaryJones", "status": "failed", "error": "username is already in an earlier row"},
+                {"row": 5, "status": "failed", "error": "row has 4 fields, the header has 5"},
+            ],
+        }));
+        assert_eq!(repos.user.users.lock().unwrap().len(), 2);
+        assert!(repos.audit.entries.lock().unwrap().is_empty());
+
+        let (status, body) = import!(app, "/api/v1/user/import", "text/csv", csv);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(
+            (body["data"]["dry_run"].clone(), body["data"]["created"].clone(), body["data"]["skipped"].clone(), body["data"]["failed"].clone()),
+            (json!(false), json!(1), json!(1), json!(3)),
+        );
+        let mary = repos.user.users.lock().unwrap().iter().find(|u| u.username == "MaryJones").cloned().unwrap();
+        assert_eq!((mary.firstname.as_str(), mary.lastname.as_str()), ("Mary", "Jones, Jr."));
+        assert_ne!(mary.password, "mary1234");
+
+        let james_password = repos.user.users.lock().unwrap()[0].password.clone();
+        let csv = "username,password,firstname,lastname\nJamesHolland,newpass123,Jim,Holland\nMaryJones,mary1234,Mary,\"Jones, Jr.\"\n";
+        let (status, body) = import!(app, "/api/v1/user/import?on_conflict=update", "text/csv", csv);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["updated"].clone(), body["data"]["failed"].clone()), (json!(2), json!(0)));
+        let james = repos.user.users.lock().unwrap()[0].clone();
+        assert_eq!(james.firstname, "Jim");
+        assert_ne!(james.password, james_password);
+
+        // Names are audited only when they change, passwords always.
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<(&str, Option<&str>)> = entries.iter().map(|e| (e.action.as_str(), e.target_username.as_deref())).collect();
+        assert_eq!(actions, vec![
+            (AUDIT_USER_CREATE, Some("MaryJones")),
+            (AUDIT_USER_UPDATE, Some("JamesHolland")),
+            (AUDIT_USER_PASSWORD_CHANGE, Some("JamesHolland")),
+            (AUDIT_USER_PASSWORD_CHANGE, Some("MaryJones")),
+        ]);
+        assert!(entries.iter().all(|e| e.actor_id == Some(1)));
+        assert_eq!(entries[1].changes, json!({"firstname": {"before": "James", "after": "Jim"}}));
+    }
+
+    #[actix_web::test]
+    async fn user_import_ndjson_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let ndjson = concat!(
+            r#"{"username": "JamesHolland", "password": "newpass123", "firstname": "Jim", "lastname": "Holland"}"#, "\n",
+            r#"{"username": "MaryJones", "password": "mary1234", "firstname": "Mary", "lastname": "Jones"}"#, "\n",
+            "\n",
+            r#"{"username": "BobJones", "password": "bob12345"}"#, "\n",
+            "not json",
+        );
+        let (status, body) = import!(app, "/api/v1/user/import", "application/x-ndjson", ndjson);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let rows: Vec<(Value, Value)> = body["data"]["rows"].as_array().unwrap().iter().map(|r| (r["username"].clone(), r["status"].clone())).collect();
+        assert_eq!(rows, vec![
+            (json!("JamesHolland"), json!("skipped")),
+            (json!("MaryJones"), json!("created")),
+            (Value::Null, json!("failed")),
+            (Value::Null, json!("failed")),
+        ]);
+        assert_eq!(body["data"]["rows"][2]["error"], json!("row must be a JSON object with username, password, firstname and lastname"));
+        assert_eq!(repos.user.users.lock().unwrap()[0].firstname, "James");
+        assert_eq!(repos.user.users.lock().unwrap().len(), 2);
+
+        // What stops an import is reported after the rows read so far.
+        let ndjson = format!("{{}}\n{}\n{{}}\n", "x".repeat(IMPORT_MAX_RECORD_LEN + 1));
+        let (status, body) = import!(app, "/api/v1/user/import?dry_run=true", "application/x-ndjson", ndjson);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(
+            (body["data"]["failed"].clone(), body["data"]["error"].clone()),
+            (json!(1), json!(format!("records must be at most {} bytes", IMPORT_MAX_RECORD_LEN))),
+        );
+
+        let ndjson = "{}\n".repeat(IMPORT_MAX_ROWS + 1);
+        let (status, body) = import!(app, "/api/v1/user/import?dry_run=true", "application/x-ndjson", ndjson);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            (body["data"]["failed"].clone(), body["data"]["error"].clone()),
+            (json!(IMPORT_MAX_ROWS), json!(format!("an import must have at most {} rows", IMPORT_MAX_ROWS))),
+        );
+    }
+
+    #[actix_web::test]
+    async fn user_export_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_user(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, headers, body) = export!(app, "/api/v1/user/export", test_token());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get("Content-Type").unwrap(), "application/x-ndjson");
+        assert_eq!(headers.get("Content-Disposition").unwrap(), "attachment; filename=\"users.ndjson\"");
+        let lines: Vec<Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], json!({
+            "id": 1,
+            "username": "JamesHolland",
+            "firstname": "James",
+            "lastname": "Holland",
+            "status": "active",
+            "create_ts": "2023-02-10T03:33:20Z",
+            "update_ts": "2023-02-10T03:33:20Z",
+        }));
+
+        let test_cases = vec! {
+            (token("AnnaSmith"), "/api/v1/user/export", StatusCode::FORBIDDEN, json!("Forbidden")),
+            ("Bearer nope".to_string(), "/api/v1/user/export", StatusCode::UNAUTHORIZED, json!("Unauthorized")),
+            (test_token(), "/api/v1/user/export?format=xml", StatusCode::BAD_REQUEST, json!("format must be one of csv, ndjson")),
+            (test_token(), "/api/v1/user/export?status=gone", StatusCode::BAD_REQUEST, json!("status must be one of active, disabled, locked, pending")),
+        };
+        for (bearer, uri, status, error_msg) in test_cases {
+            let (res, _, body) = export!(app, uri, bearer);
+            let body: Value = serde_json::from_str(&body).unwrap();
+            assert_eq!((res, body["data"]["error_msg"].clone()), (status, error_msg), "{}", uri);
+        }
+
+        {
+            let mut users = repos.user.users.lock().unwrap();
+            users[1].status = UserStatus::Disabled;
+            users[1].firstname = "Anna, Maria".to_string();
+        }
+        let (status, headers, body) = export!(app, "/api/v1/user/export?format=csv&status=disabled", test_token());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get("Content-Type").unwrap(), "text/csv");
+        assert_eq!(body, "id,username,firstname,lastname,status,create_ts,update_ts\n\
+            2,AnnaSmith,\"Anna, Maria\",Smith,disabled,1970-01-01T00:00:00Z,1970-01-01T00:00:00Z\n");
+    }
+
+    #[actix_web::test]
+    async fn user_export_pages_test() {
+        let (use_cases, repos) = test_use_cases();
+        {
+            let mut users = repos.user.users.lock().unwrap();
+            for id in 2..=(2 * EXPORT_PAGE_SIZE as i32 + 1) {
+                users.push(MemoryUser { id, username: format!("user{}", id), ..Default::default() });
+            }
+        }
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, _, body) = export!(app, "/api/v1/user/export?format=csv", test_token());
+        assert_eq!(status, StatusCode::OK);
+        let ids: Vec<i32> = body.lines().skip(1).map(|line| line.split(',').next().unwrap().parse().unwrap()).collect();
+        assert_eq!(ids, (1..=(2 * EXPORT_PAGE_SIZE as i32 + 1)).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,58 @@
+// Probe endpoints for the orchestrator. They are registered without the
+// bearer-token check so probes need no credentials.
+pub mod health_routes {
+    use actix_web::{Responder, web, get};
+    use actix_web::http::StatusCode;
+    use crate::internal::health::usecase::traits::UseCase;
+    use crate::internal::controller::response::{ErrorResponse, GeneralResponse, send_error_response, send_status_response, send_success_response};
+    use crate::internal::health::entity::health::{HealthReport, LivenessResponse};
+
+    #[utoipa::path(
+        tag = "probe",
+        responses((status = 200, description = "The process is running", body = GeneralResponse<LivenessResponse>)),
+    )]
+    #[get("/healthz")]
+    pub async fn healthz(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        send_success_response(use_cases.health_use_case.liveness().await)
+    }
+
+    #[utoipa::path(
+        tag = "probe",
+        responses(
+            (status = 200, description = "Ready to serve traffic", body = GeneralResponse<HealthReport>),
+            (status = 503, description = "A dependency is down or the server is shutting down", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/readyz")]
+    pub async fn readyz(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        return match use_cases.health_use_case.readiness().await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "probe",
+        responses(
+            (status = 200, description = "All dependencies up", body = GeneralResponse<HealthReport>),
+            (status = 503, description = "At least one dependency down", body = GeneralResponse<HealthReport>),
+        ),
+    )]
+    #[get("/health")]
+    pub async fn health(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let report = use_cases.health_use_case.health_report().await;
+        let status_code = if report.is_up() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+        send_status_response(status_code, report)
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_use_cases_with, TestRepos};
+    use crate::pkg::shutdown::shutdown::{new_shutdown, Shutdown};
+
+    async fn get(uri: &str, setup: impl Fn(&TestRepos, &Shutdown)) -> (StatusCode, Value) {
+        let shutdown = new_shutdown();
+        let (use_cases, repos) = test_use_cases_with(shutdown.clone());
+        setup(&repos, &shutdown);
+
+        let app = test::init_service(new_app(use_cases, &Config::default().http, shutdown)).await;
+        let res = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        let status = res.status();
+
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn healthz_test() {
+        let (status, body) = get("/healthz", |repos, _| *repos.health.reachable.lock().unwrap() = false).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"success": true, "data": {"status": "up"}}));
+    }
+
+    #[actix_web::test]
+    async fn readyz_test() {
+        let (status, body) = get("/readyz", |_, _| {}).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["ready"], json!(true));
+
+        let (status, body) = get("/readyz", |repos, _| *repos.health.reachable.lock().unwrap() = false).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["success"], json!(false));
+        assert_eq!(body["data"]["status_code"], json!(503));
+        assert!(body["data"]["error_msg"].as_str().unwrap().starts_with("Not ready (postgres: "));
+
+        let (status, body) = get("/readyz", |repos, _| *repos.health.migration_version.lock().unwrap() = None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["data"]["error_msg"], json!("Not ready (migrations: version 0 is behind expected 1)"));
+
+        let (status, body) = get("/readyz", |_, shutdown| shutdown.stop_accepting()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["data"]["error_msg"], json!("Shutting down"));
+    }
+
+    #[actix_web::test]
+    async fn health_test() {
+        let (status, body) = get("/health", |_, _| {}).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["success"], json!(true));
+        assert_eq!(body["data"]["status"], json!("up"));
+        assert_eq!(body["data"]["dependencies"][0]["name"], json!("postgres"));
+        assert!(body["data"]["dependencies"][0]["latency_ms"].is_number());
+        assert_eq!(body["data"]["dependencies"][1]["detail"], json!("version 1"));
+        assert_eq!(body["data"]["pool"]["size"], json!(1));
+        assert_eq!(body["data"]["pool"]["idle"], json!(1));
+        assert_eq!(body["data"]["pool"]["max"], json!(10));
+        assert!(body["data"]["pool"]["acquire_wait_ms"].is_f64());
+
+        let (status, body) = get("/health", |repos, _| *repos.health.reachable.lock().unwrap() = false).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["success"], json!(false));
+        assert_eq!(body["data"]["status"], json!("down"));
+        assert_eq!(body["data"]["ready"], json!(false));
+        assert_eq!(body["data"]["dependencies"][0]["status"], json!("down"));
+    }
+}
@@ -0,0 +1,302 @@
+// Federated login with ID tokens from external OpenID providers, linking of
+// those identities to the caller's account, and provider registration for
+// admins. The application talks to the provider; we only see its ID token.
+pub mod identity_routes {
+    use actix_web::{HttpRequest, Responder, web, delete, get, post};
+    use actix_web::http::StatusCode;
+    use crate::internal::identity::entity::identity::{
+        verify_identity_provider_create_request, verify_identity_token_request, IdentityProvider,
+        IdentityProviderCreateRequest, IdentityTokenRequest, UserIdentity,
+    };
+    use crate::internal::identity::usecase::traits::UseCase;
+    use crate::internal::user::entity::user::UserAuthResult;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error};
+
+    fn parse_token_request(body: &str) -> Result<IdentityTokenRequest, ErrorResponseUseCase> {
+        let des: IdentityTokenRequest = match serde_json::from_str(body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return Err(res);
+            }
+        };
+
+        if let Err(err) = verify_identity_token_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return Err(res);
+        }
+
+        Ok(des)
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = IdentityTokenRequest,
+        responses(
+            (status = 200, description = "Access and refresh token, or an MFA challenge when MFA is enabled", body = GeneralResponse<UserAuthResult>),
+            (status = 400, description = "Malformed request or unknown provider", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "ID token is invalid, expired or not for us", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "No account is linked to the identity", body = GeneralResponse<ErrorResponse>),
+            (status = 502, description = "Provider keys can't be fetched", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/federated/login")]
+    pub async fn identity_login(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let des = match parse_token_request(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return send_error_response(err);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, None);
+
+        return match use_cases.identity_use_case.identity_login(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = IdentityTokenRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Identity linked to the caller's account", body = GeneralResponse<UserIdentity>),
+            (status = 400, description = "Malformed request or unknown provider", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token, or invalid ID token", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Identity is linked to another account", body = GeneralResponse<ErrorResponse>),
+            (status = 502, description = "Provider keys can't be fetched", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/identities")]
+    pub async fn identity_link(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let des = match parse_token_request(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return send_error_response(err);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.identity_use_case.identity_link(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Identities linked to the caller's account", body = GeneralResponse<Vec<UserIdentity>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/identities")]
+    pub async fn identity_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.identity_use_case.identity_list(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        params(("id" = i32, Path, description = "Identity id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Identity unlinked; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Caller has no such identity", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/identities/{id}")]
+    pub async fn identity_unlink(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.identity_use_case.identity_unlink(ctx, token_result.username, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        request_body = IdentityProviderCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Provider registered", body = GeneralResponse<IdentityProvider>),
+            (status = 400, description = "Invalid name, issuer or client_id", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Provider name is taken", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/identity-providers")]
+    pub async fn identity_provider_create(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let des: IdentityProviderCreateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_identity_provider_create_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.identity_use_case.identity_provider_create(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Registered identity providers", body = GeneralResponse<Vec<IdentityProvider>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/identity-providers")]
+    pub async fn identity_provider_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        return match use_cases.identity_use_case.identity_provider_list().await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
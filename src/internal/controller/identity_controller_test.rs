@@ -0,0 +1,224 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_token_config, test_use_cases};
+    use crate::internal::identity::entity::identity::ALG_ES256;
+    use crate::internal::identity::usecase::webapi::mock_issuer::{new_mock_issuer, MOCK_CLIENT_ID, MOCK_EC_KID};
+    use crate::internal::user::entity::token::generate_access_token;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const LOGIN: &str = "/api/v1/user/federated/login";
+    const IDENTITIES: &str = "/api/v1/user/identities";
+    const PROVIDERS: &str = "/api/v1/identity-providers";
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    macro_rules! post {
+        ($app:expr, $uri:expr, $token:expr, $body:expr) => {{
+            let mut req = test::TestRequest::post().uri($uri).set_json($body);
+            if let Some(token) = $token {
+                req = req.insert_header(("Authorization", token));
+            }
+            call!($app, req)
+        }};
+    }
+
+    macro_rules! create_provider {
+        ($app:expr, $issuer:expr, $jit:expr) => {{
+            let (status, body) = post!($app, PROVIDERS, Some(test_token()), json!({
+                "name": "corp", "issuer": $issuer, "client_id": MOCK_CLIENT_ID, "jit_provisioning": $jit,
+            }));
+            assert_eq!(status, StatusCode::OK, "{}", body);
+        }};
+    }
+
+    macro_rules! login {
+        ($app:expr, $id_token:expr) => {{
+            post!($app, LOGIN, None::<String>, json!({"provider": "corp", "id_token": $id_token}))
+        }};
+    }
+
+    #[actix_web::test]
+    async fn identity_login_and_link_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mock = new_mock_issuer();
+        create_provider!(app, mock.issuer, false);
+
+        // Without a link and without JIT provisioning there is no account.
+        let (status, body) = login!(app, mock.id_token("alice", Value::Null));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::FORBIDDEN, json!("No account is linked to this identity")));
+
+        let (status, body) = post!(app, IDENTITIES, Some(test_token()), json!({"provider": "corp", "id_token": mock.id_token("alice", json!({"email": "james@example.com"}))}));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["provider"].clone(), body["data"]["subject"].clone()), (json!("corp"), json!("alice")));
+        assert_eq!(body["data"]["email"], json!("james@example.com"));
+        assert!(body["data"].get("user_id").is_none());
+        let identity_id = body["data"]["id"].as_i64().unwrap();
+
+        // Linking again is a no-op; another account can't take the identity.
+        let (status, body) = post!(app, IDENTITIES, Some(test_token()), json!({"provider": "corp", "id_token": mock.id_token("alice", Value::Null)}));
+        assert_eq!((status, body["data"]["id"].as_i64()), (StatusCode::OK, Some(identity_id)));
+        let (status, _) = post!(app, "/api/v1/user/create", Some(test_token()), json!({"username": "JohnDoe", "password": "john12345", "firstname": "John", "lastname": "Doe"}));
+        assert_eq!(status, StatusCode::OK);
+        let john = format!("Bearer {}", generate_access_token(&test_token_config(), &"JohnDoe".to_string()));
+        let (status, body) = post!(app, IDENTITIES, Some(john.clone()), json!({"provider": "corp", "id_token": mock.id_token("alice", Value::Null)}));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::CONFLICT, json!("This identity is linked to another account")));
+
+        let (status, body) = login!(app, mock.id_token("alice", Value::Null));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body["data"]["access_token"].as_str().is_some_and(|token| !token.is_empty()));
+
+        // An unknown kid refetches the keys, but not moments after the last fetch.
+        *mock.jwks.lock().unwrap() = json!({"keys": [mock.jwk(MOCK_EC_KID)]});
+        let es256 = mock.sign(ALG_ES256, Some(MOCK_EC_KID), &mock.claims("alice", Value::Null));
+        let (status, _) = login!(app, es256);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get().uri(IDENTITIES).insert_header(("Authorization", test_token()));
+        let (status, body) = call!(app, req);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert!(!body["data"][0]["last_login_ts"].is_null());
+
+        // Local TOTP still applies to federated logins.
+        repos.user.users.lock().unwrap()[0].mfa_enabled = true;
+        let (status, body) = login!(app, mock.id_token("alice", Value::Null));
+        assert_eq!((status, body["data"]["mfa_required"].clone()), (StatusCode::OK, json!(true)));
+        repos.user.users.lock().unwrap()[0].mfa_enabled = false;
+
+        let req = test::TestRequest::delete().uri(&format!("{}/{}", IDENTITIES, identity_id)).insert_header(("Authorization", john));
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let req = test::TestRequest::delete().uri(&format!("{}/{}", IDENTITIES, identity_id)).insert_header(("Authorization", test_token()));
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = login!(app, mock.id_token("alice", Value::Null));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec![
+            "identity_provider.create", "user.identity_link", "user.create", "user.login_success",
+            "user.login_mfa_challenge", "user.identity_unlink",
+        ]);
+        let unlink = entries.last().unwrap();
+        assert_eq!((unlink.actor_id, unlink.target_username.as_deref()), (Some(1), Some("JamesHolland")));
+        assert_eq!(unlink.changes, json!({"provider": {"before": "corp", "after": null}, "subject": {"before": "alice", "after": null}}));
+    }
+
+    #[actix_web::test]
+    async fn identity_jit_provisioning_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mock = new_mock_issuer();
+        create_provider!(app, mock.issuer, true);
+
+        let claims = json!({"preferred_username": "alice.walker", "given_name": "Alice", "family_name": "Walker"});
+        let (status, body) = login!(app, mock.id_token("alice", claims.clone()));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let alice = format!("Bearer {}", body["data"]["access_token"].as_str().unwrap());
+
+        // The second login finds the linked account.
+        let (status, _) = login!(app, mock.id_token("alice", claims.clone()));
+        assert_eq!(status, StatusCode::OK);
+        let users = repos.user.users.lock().unwrap().clone();
+        assert_eq!(users.len(), 2);
+        assert_eq!((users[1].username.as_str(), users[1].firstname.as_str(), users[1].lastname.as_str()), ("corp-2bd806c97f0e00af", "Alice", "Walker"));
+
+        let req = test::TestRequest::get().uri(IDENTITIES).insert_header(("Authorization", alice));
+        let (_, body) = call!(app, req);
+        assert_eq!(body["data"][0]["subject"], json!("alice"));
+
+        // The provider's username is ignored, so an IdP user can't take an
+        // admin's name.
+        let (status, body) = login!(app, mock.id_token("bob", json!({"preferred_username": "JamesHolland"})));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(repos.user.users.lock().unwrap()[2].username, "corp-81b637d8fcd2c6da");
+        let bob = format!("Bearer {}", body["data"]["access_token"].as_str().unwrap());
+        let req = test::TestRequest::get().uri("/api/v1/job").insert_header(("Authorization", bob));
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec![
+            "identity_provider.create",
+            "user.create", "user.identity_link", "user.login_success",
+            "user.login_success",
+            "user.create", "user.identity_link", "user.login_success",
+        ]);
+    }
+
+    #[actix_web::test]
+    async fn identity_login_rejected_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mock = new_mock_issuer();
+        create_provider!(app, mock.issuer, true);
+        let now = mock.claims("alice", Value::Null)["iat"].as_u64().unwrap();
+
+        let test_cases = vec! {
+            (json!({"provider": "corp"}), StatusCode::BAD_REQUEST, "Can't convert request"),
+            (json!({"provider": "", "id_token": "a.b.c"}), StatusCode::BAD_REQUEST, "provider is required"),
+            (json!({"provider": "other", "id_token": mock.id_token("alice", Value::Null)}), StatusCode::BAD_REQUEST, "Unknown identity provider"),
+            (json!({"provider": "corp", "id_token": "a.b.c"}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (json!({"provider": "corp", "id_token": mock.id_token("alice", json!({"aud": "other"}))}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (json!({"provider": "corp", "id_token": mock.id_token("alice", json!({"exp": now - 3600}))}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (json!({"provider": "corp", "id_token": mock.id_token("alice", Value::Null), "nonce": "n-0S6"}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+        };
+        for (body, status, error_msg) in test_cases {
+            let (got_status, got_body) = post!(app, LOGIN, None::<String>, body);
+            assert_eq!((got_status, got_body["data"]["error_msg"].as_str()), (status, Some(error_msg)));
+        }
+
+        // A provider that is down is reported as such.
+        let (status, _) = post!(app, PROVIDERS, Some(test_token()), json!({"name": "down", "issuer": "http://127.0.0.1:1", "client_id": MOCK_CLIENT_ID}));
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = post!(app, LOGIN, None::<String>, json!({"provider": "down", "id_token": mock.id_token("alice", Value::Null)}));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::BAD_GATEWAY, json!("Identity provider is unavailable")));
+        assert_eq!(body["data"]["status_code"], json!(502));
+
+        assert_eq!(repos.user.users.lock().unwrap().len(), 1);
+        assert!(repos.identity.identities.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn identity_provider_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        create_provider!(app, "https://idp.example.com", false);
+        let provider = json!({"id": 1, "name": "corp", "issuer": "https://idp.example.com", "client_id": MOCK_CLIENT_ID, "jit_provisioning": false});
+
+        let (status, body) = post!(app, PROVIDERS, Some(test_token()), json!({"name": "corp", "issuer": "https://idp.example.com", "client_id": "app"}));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::CONFLICT, json!("Identity provider with name=corp already exists")));
+        let (status, body) = post!(app, PROVIDERS, Some(test_token()), json!({"name": "corp2", "issuer": "http://idp.example.com", "client_id": "app"}));
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+        let john = test_not_admin_token(&repos);
+        let (status, _) = post!(app, PROVIDERS, Some(john.clone()), json!({"name": "corp2", "issuer": "https://idp.example.com", "client_id": "app"}));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = post!(app, PROVIDERS, None::<String>, json!({}));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call!(app, test::TestRequest::get().uri(PROVIDERS).insert_header(("Authorization", test_token())));
+        assert_eq!((status, body["data"].clone()), (StatusCode::OK, json!([provider])));
+        let (status, _) = call!(app, test::TestRequest::get().uri(PROVIDERS).insert_header(("Authorization", john)));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,133 @@
+// The background job queue, restricted to auth.admins: listing jobs and
+// queueing one of a kind that has a handler.
+pub mod job_routes {
+    use actix_web::{Responder, web, get, post, HttpRequest};
+    use actix_web::http::StatusCode;
+    use crate::internal::job::entity::job::{verify_job_query, Job, JobCreateRequest, JobPage, JobQuery};
+    use crate::internal::job::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error, bad_request};
+
+    #[utoipa::path(
+        tag = "admin",
+        params(JobQuery),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Matching jobs, newest first", body = GeneralResponse<JobPage>),
+            (status = 400, description = "Invalid filter", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/job")]
+    pub async fn job_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        let query = match web::Query::<JobQuery>::from_query(req.query_string()) {
+            Ok(res) => {
+                res.into_inner()
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid query".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_job_query(&query) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        return match use_cases.job_use_case.job_list(query).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i64, Path, description = "Job id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The job with its status, attempts and last error", body = GeneralResponse<Job>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Job not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/job/{id}")]
+    pub async fn job_get(
+        req: HttpRequest,
+        id: web::Path<i64>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        return match use_cases.job_use_case.job_get(id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+    #[utoipa::path(
+        tag = "admin",
+        request_body = JobCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The queued job", body = GeneralResponse<Job>),
+            (status = 400, description = "Invalid request or a kind without a handler", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/job")]
+    pub async fn job_create(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        let des: JobCreateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return bad_request("Invalid request".to_string());
+            }
+        };
+
+        return match use_cases.job_use_case.job_create(des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_use_cases};
+    use crate::internal::job::entity::job::{JobStatus, JOB_JOB_PURGE, JOB_SESSION_PURGE};
+    use crate::internal::user::entity::session::Session;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    fn session(id: i32, expire_ts: chrono::DateTime<Utc>) -> Session {
+        Session {
+            id,
+            user_id: 1,
+            user_agent: None,
+            ip: None,
+            refresh_token_hash: format!("hash-{}", id),
+            create_ts: Utc::now(),
+            last_seen_ts: Utc::now(),
+            expire_ts,
+        }
+    }
+
+    #[actix_web::test]
+    async fn job_list_test() {
+        let (use_cases, repos) = test_use_cases();
+        let job_use_case = use_cases.job_use_case.clone();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        repos.user.sessions.lock().unwrap().extend([
+            session(1, Utc::now() - chrono::Duration::minutes(1)),
+            session(2, Utc::now() + chrono::Duration::days(1)),
+        ]);
+        job_use_case.enqueue(JOB_SESSION_PURGE, json!({}), None).await.unwrap();
+        job_use_case.enqueue(JOB_JOB_PURGE, json!({}), None).await.unwrap();
+        job_use_case.enqueue("report.send", json!({"to": "ops"}), None).await.unwrap();
+        while job_use_case.run_next().await {}
+
+        let ids: Vec<i32> = repos.user.sessions.lock().unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![2]);
+        let statuses: Vec<JobStatus> = repos.job.jobs.lock().unwrap().iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Done, JobStatus::Done, JobStatus::Pending]);
+
+        let req = test::TestRequest::get().uri("/api/v1/job?status=done&limit=1&offset=1")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["total"], json!(2));
+        assert_eq!(body["data"]["limit"], json!(1));
+        assert_eq!(body["data"]["offset"], json!(1));
+        let items = body["data"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["kind"], json!(JOB_SESSION_PURGE));
+        assert_eq!(items[0]["attempts"], json!(1));
+
+        let req = test::TestRequest::get().uri("/api/v1/job/3")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["kind"], json!("report.send"));
+        assert_eq!(body["data"]["payload"], json!({"to": "ops"}));
+        assert_eq!(body["data"]["status"], json!("pending"));
+        assert_eq!(body["data"]["last_error"], json!("no handler for kind report.send"));
+    }
+
+    #[actix_web::test]
+    async fn job_access_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let not_admin = test_not_admin_token(&repos);
+
+        let test_cases = vec! {
+            ("no token", "/api/v1/job", None, StatusCode::UNAUTHORIZED, "Unauthorized"),
+            ("not an admin", "/api/v1/job", Some(not_admin.clone()), StatusCode::FORBIDDEN, "Forbidden"),
+            ("not an admin, one job", "/api/v1/job/1", Some(not_admin), StatusCode::FORBIDDEN, "Forbidden"),
+            ("bad number", "/api/v1/job?offset=first", Some(test_token()), StatusCode::BAD_REQUEST, "Invalid query"),
+            ("bad status", "/api/v1/job?status=failed", Some(test_token()), StatusCode::BAD_REQUEST, "status must be one of pending, running, done, dead"),
+            ("limit too big", "/api/v1/job?limit=501", Some(test_token()), StatusCode::BAD_REQUEST, "limit must be between 1 and 500"),
+            ("unknown job", "/api/v1/job/42", Some(test_token()), StatusCode::NOT_FOUND, "Job with id=42 not found"),
+        };
+
+        for (name, uri, token, status, error_msg) in test_cases {
+            let mut req = test::TestRequest::get().uri(uri);
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), status, "{}", name);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["data"]["error_msg"], json!(error_msg), "{}", name);
+        }
+    }
+    #[actix_web::test]
+    async fn job_create_test() {
+        let (use_cases, repos) = test_use_cases();
+        let job_use_case = use_cases.job_use_case.clone();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let not_admin = test_not_admin_token(&repos);
+
+        let test_cases = vec! {
+            ("not an admin", Some(not_admin), json!({"kind": JOB_SESSION_PURGE}), StatusCode::FORBIDDEN, "Forbidden"),
+            ("no token", None, json!({"kind": JOB_SESSION_PURGE}), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            ("unknown field", Some(test_token()), json!({"kind": JOB_SESSION_PURGE, "payload": {}}), StatusCode::BAD_REQUEST, "Invalid request"),
+            ("no handler", Some(test_token()), json!({"kind": "report.send"}), StatusCode::BAD_REQUEST, "kind must be one of job.purge_finished, session.purge_expired"),
+        };
+        for (name, token, body, status, error_msg) in test_cases {
+            let mut req = test::TestRequest::post().uri("/api/v1/job").set_json(body);
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), status, "{}", name);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["data"]["error_msg"], json!(error_msg), "{}", name);
+        }
+        assert!(repos.job.jobs.lock().unwrap().is_empty());
+
+        // Purging expired sessions now instead of at the next scheduled run.
+        repos.user.sessions.lock().unwrap().extend([
+            session(1, Utc::now() - chrono::Duration::minutes(1)),
+            session(2, Utc::now() + chrono::Duration::days(1)),
+        ]);
+        let req = test::TestRequest::post().uri("/api/v1/job")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"kind": JOB_SESSION_PURGE}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["kind"], json!(JOB_SESSION_PURGE));
+        assert_eq!(body["data"]["status"], json!("pending"));
+
+        while job_use_case.run_next().await {}
+        let ids: Vec<i32> = repos.user.sessions.lock().unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(repos.job.jobs.lock().unwrap()[0].status, JobStatus::Done);
+    }
+}
@@ -0,0 +1,81 @@
+// Lets an operator in auth.admins raise or lower the log level of a running
+// instance. The change is not persisted; a restart goes back to logging.level.
+pub mod logging_routes {
+    use actix_web::{Responder, web, get, put, HttpRequest};
+    use actix_web::http::StatusCode;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error};
+    use crate::pkg::logger::logger::LogLevel;
+
+    #[utoipa::path(
+        tag = "operations",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Current log level", body = GeneralResponse<LogLevel>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/logging/level")]
+    pub async fn log_level_get(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        send_success_response(LogLevel { level: use_cases.logger.level() })
+    }
+
+    #[utoipa::path(
+        tag = "operations",
+        request_body = LogLevel,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "New log level", body = GeneralResponse<LogLevel>),
+            (status = 400, description = "Malformed request or unknown level", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[put("/api/v1/logging/level")]
+    pub async fn log_level_set(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        let des: LogLevel = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        match use_cases.logger.set_level(&des.level) {
+            Ok(_) => {
+                tracing::warn!(level = %use_cases.logger.level(), "log level changed");
+                send_success_response(LogLevel { level: use_cases.logger.level() })
+            }
+            Err(err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: err,
+                };
+
+                send_error_response(res)
+            }
+        }
+    }
+}
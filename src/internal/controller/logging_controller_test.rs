@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_use_cases};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    #[actix_web::test]
+    async fn log_level_test() {
+        let (use_cases, repos) = test_use_cases();
+        let logger = use_cases.logger.clone();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::get().uri("/api/v1/logging/level").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::put().uri("/api/v1/logging/level").set_json(json!({"level": "debug"})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(logger.level(), "info");
+
+        // Signed in is not enough: only admins may see or change the level.
+        let not_admin = test_not_admin_token(&repos);
+        let req = test::TestRequest::get()
+            .uri("/api/v1/logging/level")
+            .insert_header(("Authorization", not_admin.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::put()
+            .uri("/api/v1/logging/level")
+            .insert_header(("Authorization", not_admin))
+            .set_json(json!({"level": "trace"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["error_msg"], json!("Forbidden"));
+        assert_eq!(logger.level(), "info");
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/logging/level")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(body, json!({"success": true, "data": {"level": "info"}}));
+
+        let req = test::TestRequest::put()
+            .uri("/api/v1/logging/level")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"level": "DEBUG"}))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(body, json!({"success": true, "data": {"level": "debug"}}));
+        assert_eq!(logger.level(), "debug");
+
+        let req = test::TestRequest::put()
+            .uri("/api/v1/logging/level")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"level": "chatty"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["error_msg"], json!("log level must be one of error, warn, info, debug, trace"));
+        assert_eq!(logger.level(), "debug");
+    }
+}
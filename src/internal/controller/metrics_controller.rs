@@ -0,0 +1,24 @@
+// Prometheus scrape endpoint. Like the probes it needs no token; keep it off
+// public listeners.
+pub mod metrics_routes {
+    use std::time::Duration;
+    use actix_web::{HttpResponse, Responder, web, get};
+    use crate::internal::health::usecase::traits::UseCase;
+
+    #[utoipa::path(
+        tag = "operations",
+        responses((status = 200, description = "Prometheus text exposition format", content_type = "text/plain", body = String)),
+    )]
+    #[get("/metrics")]
+    pub async fn metrics(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let pool = use_cases.health_use_case.pool_stats().await;
+        let acquire_wait = pool.acquire_wait_ms.map(|ms| Duration::from_secs_f64(ms / 1000.0));
+        use_cases.metrics.set_pool(pool.size, pool.idle, pool.max, acquire_wait);
+
+        HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(use_cases.metrics.encode())
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::test;
+    use serde_json::json;
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_use_cases};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    #[actix_web::test]
+    async fn metrics_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        for password in ["james123", "wrong"] {
+            let req = test::TestRequest::post()
+                .uri("/api/v1/user/auth")
+                .set_json(json!({"username": "JamesHolland", "password": password}))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/user/1/get")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        test::call_service(&app, req).await;
+        test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/plain; version=0.0.4");
+
+        let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(body.contains(r#"http_requests_total{method="POST",route="/api/v1/user/auth",status="200"} 1"#));
+        assert!(body.contains(r#"http_requests_total{method="POST",route="/api/v1/user/auth",status="401"} 1"#));
+        assert!(body.contains(r#"http_requests_total{method="GET",route="/api/v1/user/{id}/get",status="200"} 1"#));
+        assert!(!body.contains(r#"route="/healthz""#));
+        assert!(body.contains(r#"auth_login_total{result="success"} 1"#));
+        assert!(body.contains(r#"auth_login_total{result="failure"} 1"#));
+        assert!(body.contains(r#"password_hash_duration_seconds_count{op="verify"} 2"#));
+        assert!(body.contains(r#"db_pool_connections{state="max"} 10"#));
+        assert!(body.contains("db_pool_acquire_wait_seconds"));
+    }
+
+    #[actix_web::test]
+    async fn metrics_unmatched_route_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/v1/user/1/nope").to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(body.contains(r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+    }
+}
@@ -0,0 +1,219 @@
+// TOTP enrollment for the caller's own account, the second login step and
+// the admin reset.
+pub mod mfa_routes {
+    use actix_web::{Responder, web, post, HttpRequest};
+    use actix_web::http::StatusCode;
+    use crate::internal::user::entity::mfa::{
+        verify_mfa_code, verify_mfa_verify_request, MfaConfirmRequest, MfaConfirmResponse,
+        MfaEnrollResponse, MfaVerifyRequest,
+    };
+    use crate::internal::user::entity::user::UserAuthResponse;
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware::{self, AccessTokenError};
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response};
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "New secret and otpauth:// URI; MFA stays off until confirmed", body = GeneralResponse<MfaEnrollResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "MFA is already enabled", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/mfa/enroll")]
+    pub async fn mfa_enroll(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.user_use_case.user_mfa_enroll(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = MfaConfirmRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "MFA enabled; recovery codes are shown only once", body = GeneralResponse<MfaConfirmResponse>),
+            (status = 400, description = "Malformed request, invalid code or no pending enrollment", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "MFA is already enabled", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/mfa/confirm")]
+    pub async fn mfa_confirm(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let des: MfaConfirmRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_mfa_code(&des.code) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_mfa_confirm(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = MfaVerifyRequest,
+        responses(
+            (status = 200, description = "Access and refresh token", body = GeneralResponse<UserAuthResponse>),
+            (status = 400, description = "Malformed or invalid request", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Expired challenge, wrong or reused code", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/mfa/verify")]
+    pub async fn mfa_verify(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let des: MfaVerifyRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_mfa_verify_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, None);
+
+        return match use_cases.user_use_case.user_mfa_verify(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i32, Path, description = "User id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "MFA disabled and recovery codes removed; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/{id}/mfa/reset")]
+    pub async fn mfa_reset(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(AccessTokenError::NotAdmin) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::FORBIDDEN,
+                    error_msg: "Forbidden".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_mfa_reset(ctx, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
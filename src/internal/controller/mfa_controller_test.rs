@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_token_config, test_use_cases};
+    use crate::internal::user::entity::mfa::{new_totp, MFA_MAX_FAILED_ATTEMPTS, MFA_STEP};
+    use crate::internal::user::entity::token::{generate_access_token, get_time_sec};
+    use crate::internal::user::entity::user::UserStatus;
+    use crate::internal::user::usecase::repo::memory_repo::MemoryUser;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    // Runs the password step and returns the challenge token.
+    macro_rules! challenge {
+        ($app:expr) => {{
+            let req = test::TestRequest::post().uri("/api/v1/user/auth")
+                .set_json(json!({"username": "JamesHolland", "password": "james123"}))
+                .to_request();
+            let body: Value = test::read_body_json(test::call_service(&$app, req).await).await;
+            assert_eq!(body["data"]["mfa_required"], json!(true));
+            assert!(body["data"].get("access_token").is_none());
+            body["data"]["challenge_token"].as_str().unwrap().to_string()
+        }};
+    }
+
+    #[actix_web::test]
+    async fn mfa_login_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/enroll")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        let secret = body["data"]["secret"].as_str().unwrap().to_string();
+        let uri = body["data"]["provisioning_uri"].as_str().unwrap();
+        assert!(uri.starts_with("otpauth://totp/rust-clean:JamesHolland?"), "{}", uri);
+
+        // Not enabled until confirmed, so the password alone still logs in.
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_json(json!({"username": "JamesHolland", "password": "james123"}))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert!(body["data"]["access_token"].is_string());
+
+        let totp = new_totp(&secret, "rust-clean", "JamesHolland").unwrap();
+        let now = get_time_sec();
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/confirm")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"code": totp.generate(now)}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        let recovery_codes: Vec<String> = serde_json::from_value(body["data"]["recovery_codes"].clone()).unwrap();
+        assert_eq!(recovery_codes.len(), 10);
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/enroll")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+
+        // The step used to confirm can't be replayed; the next one works once.
+        let challenge_token = challenge!(app);
+        let next = totp.generate(now + MFA_STEP);
+        let test_cases = vec! {
+            (json!({"challenge_token": challenge_token, "code": totp.generate(now)}), StatusCode::UNAUTHORIZED),
+            (json!({"challenge_token": challenge_token, "code": next}), StatusCode::OK),
+            (json!({"challenge_token": challenge_token, "code": next}), StatusCode::UNAUTHORIZED),
+            (json!({"challenge_token": "bogus", "code": next}), StatusCode::UNAUTHORIZED),
+            (json!({"challenge_token": challenge_token}), StatusCode::BAD_REQUEST),
+        };
+        for (body, status) in test_cases {
+            let req = test::TestRequest::post().uri("/api/v1/user/mfa/verify").set_json(&body).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status, "{}", body);
+        }
+
+        let recovery_code = recovery_codes[0].to_lowercase();
+        for status in [StatusCode::OK, StatusCode::UNAUTHORIZED] {
+            let req = test::TestRequest::post().uri("/api/v1/user/mfa/verify")
+                .set_json(json!({"challenge_token": challenge!(app), "recovery_code": recovery_code}))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), status);
+            if status == StatusCode::OK {
+                let body: Value = test::read_body_json(res).await;
+                assert!(body["data"]["access_token"].is_string());
+            }
+        }
+
+        // A challenge token is not an access token.
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/enroll")
+            .insert_header(("Authorization", format!("Bearer {}", challenge!(app))))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+        let actions: Vec<String> = repos.audit.entries.lock().unwrap().iter().map(|e| e.action.clone()).collect();
+        assert!(actions.contains(&"user.mfa_enable".to_string()));
+        assert!(actions.contains(&"user.login_mfa_challenge".to_string()));
+        assert!(actions.contains(&"user.mfa_recovery_code_used".to_string()));
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        assert!(entries.iter().all(|e| !e.changes.to_string().contains(&secret)));
+    }
+
+    #[actix_web::test]
+    async fn mfa_reset_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/enroll")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        let totp = new_totp(body["data"]["secret"].as_str().unwrap(), "rust-clean", "JamesHolland").unwrap();
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/confirm")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"code": totp.generate(get_time_sec())}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        challenge!(app);
+
+        let not_admin = test_not_admin_token(&repos);
+        let test_cases = vec! {
+            (None, "/api/v1/user/1/mfa/reset", StatusCode::UNAUTHORIZED),
+            (Some(not_admin), "/api/v1/user/1/mfa/reset", StatusCode::FORBIDDEN),
+            (Some(test_token()), "/api/v1/user/42/mfa/reset", StatusCode::NOT_FOUND),
+            (Some(test_token()), "/api/v1/user/1/mfa/reset", StatusCode::OK),
+        };
+        for (token, uri, status) in test_cases {
+            let mut req = test::TestRequest::post().uri(uri);
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+            assert_eq!(test::call_service(&app, req.to_request()).await.status(), status, "{}", uri);
+        }
+
+        let user = repos.user.users.lock().unwrap()[0].clone();
+        assert!(!user.mfa_enabled && user.mfa_secret.is_none());
+        assert!(repos.user.recovery_codes.lock().unwrap().is_empty());
+
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_json(json!({"username": "JamesHolland", "password": "james123"}))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert!(body["data"]["access_token"].is_string());
+
+        let reset = repos.audit.entries.lock().unwrap().iter().find(|e| e.action == "user.mfa_reset").cloned().unwrap();
+        assert_eq!((reset.actor_id, reset.target_id), (Some(1), Some(1)));
+        assert_eq!(reset.changes, json!({"mfa_enabled": {"before": true, "after": false}}));
+    }
+
+    #[actix_web::test]
+    async fn mfa_lockout_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        repos.user.users.lock().unwrap().push(MemoryUser {
+            id: 2,
+            username: "JohnSmith".to_string(),
+            password: bcrypt::hash("john123", 4).unwrap(),
+            ..Default::default()
+        });
+        let john = format!("Bearer {}", generate_access_token(&test_token_config(), &"JohnSmith".to_string()));
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/enroll")
+            .insert_header(("Authorization", john.clone()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        let totp = new_totp(body["data"]["secret"].as_str().unwrap(), "rust-clean", "JohnSmith").unwrap();
+        let now = get_time_sec();
+
+        let req = test::TestRequest::post().uri("/api/v1/user/mfa/confirm")
+            .insert_header(("Authorization", john))
+            .set_json(json!({"code": totp.generate(now)}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_json(json!({"username": "JohnSmith", "password": "john123"}))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        let challenge_token = body["data"]["challenge_token"].as_str().unwrap().to_string();
+        let verify = |body: Value| test::TestRequest::post().uri("/api/v1/user/mfa/verify").set_json(body).to_request();
+        let wrong = json!({"challenge_token": challenge_token, "recovery_code": "AAAA-AAAA-AAAA-AAAA"});
+        let status = || repos.user.users.lock().unwrap()[1].status;
+
+        // An accepted code starts the count over.
+        for _ in 1..MFA_MAX_FAILED_ATTEMPTS {
+            assert_eq!(test::call_service(&app, verify(wrong.clone())).await.status(), StatusCode::UNAUTHORIZED);
+        }
+        let code = json!({"challenge_token": challenge_token, "code": totp.generate(now + MFA_STEP)});
+        assert_eq!(test::call_service(&app, verify(code)).await.status(), StatusCode::OK);
+
+        // The challenge can be replayed, but not past the limit.
+        for _ in 0..MFA_MAX_FAILED_ATTEMPTS {
+            assert_eq!(status(), UserStatus::Active);
+            assert_eq!(test::call_service(&app, verify(wrong.clone())).await.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert_eq!(status(), UserStatus::Locked);
+        let code = json!({"challenge_token": challenge_token, "code": totp.generate(now + 2 * MFA_STEP)});
+        let res = test::call_service(&app, verify(code)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["error_msg"], json!("Account is locked"));
+
+        let locks: Vec<_> = repos.audit.entries.lock().unwrap().iter().filter(|e| e.action == "user.status_change").cloned().collect();
+        assert_eq!(locks.len(), 1);
+        assert_eq!((locks[0].actor_id, locks[0].target_id), (None, Some(2)));
+        assert_eq!(locks[0].changes, json!({
+            "status": {"before": "active", "after": "locked"},
+            "reason": {"before": null, "after": "too many wrong MFA codes"},
+        }));
+
+        // Unlocking gives the full number of attempts again.
+        let req = test::TestRequest::post().uri("/api/v1/user/2/status")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"action": "unlock"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert_eq!(test::call_service(&app, verify(wrong)).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(), UserStatus::Active);
+    }
+
+    #[actix_web::test]
+    async fn mfa_confirm_validation_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let test_cases = vec! {
+            (Some(test_token()), json!({"code": "12345"}), StatusCode::BAD_REQUEST),
+            (Some(test_token()), json!({"code": "123456"}), StatusCode::BAD_REQUEST), // not enrolled
+            (None, json!({"code": "123456"}), StatusCode::UNAUTHORIZED),
+        };
+        for (token, body, status) in test_cases {
+            let mut req = test::TestRequest::post().uri("/api/v1/user/mfa/confirm").set_json(&body);
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+            assert_eq!(test::call_service(&app, req.to_request()).await.status(), status, "{}", body);
+        }
+    }
+}
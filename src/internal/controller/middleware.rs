@@ -3,27 +3,46 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use jwt::VerifyWithKey;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
-use actix_web::HttpRequest;
+use actix_web::{web, HttpRequest};
 
-use crate::internal::user::entity::token;
+use crate::internal::user::entity::api_key::{has_api_key_scope, API_KEY_MARKER};
+use crate::internal::user::entity::token::{TokenConfig, TOKEN_PURPOSE_CLAIM, TOKEN_SESSION_CLAIM, TOKEN_USER_CLAIM};
+use crate::internal::audit::entity::audit::AuditContext;
+use crate::internal::controller::request_id::current_request_id;
+use crate::internal::user::usecase::traits::UseCase;
+
+const MAX_USER_AGENT_LEN: usize = 512;
 
 #[derive(Debug, PartialEq)]
 pub enum AccessTokenError {
     TokenInvalid,
     ExpiredToken,
+    NotAdmin,
+    InsufficientScope,
 }
 
+#[allow(dead_code)]
 pub struct AccessTokenResult {
-    pub username: String,
+    pub user_id: i32,
+    pub username: String, // the account's current username, not the one in the token
     pub remaining_time: u64,
+    pub session_id: Option<i32>, // None for API keys and tokens issued before sessions
+}
+
+// What a signed token claims, before its account is looked up.
+struct AccessTokenClaims {
+    user_id: Option<i32>, // None for tokens issued before they carried it
+    username: String,
+    remaining_time: u64,
+    session_id: Option<i32>,
 }
 
 pub fn get_time_sec() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs()
 }
 
-async fn verify_access_token(access_token: &str) -> Result<AccessTokenResult, AccessTokenError> {
-    let key: Hmac<Sha256> = Hmac::new_from_slice(token::TOKEN_SECRET_KEY.as_ref()).unwrap();
+fn verify_access_token(cfg: &TokenConfig, access_token: &str) -> Result<AccessTokenClaims, AccessTokenError> {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(cfg.secret_key.as_ref()).unwrap();
     let claims: BTreeMap<String, String> = match access_token.verify_with_key(&key) {
         Ok(res) => {
             res
@@ -33,38 +52,197 @@ async fn verify_access_token(access_token: &str) -> Result<AccessTokenResult, Ac
         }
     };
 
-    let token_expire: u64 = claims["created_time"].parse().unwrap();
+    if claims.contains_key(TOKEN_PURPOSE_CLAIM) {
+        return Err(AccessTokenError::TokenInvalid);
+    }
+
+    let token_expire: u64 = match claims.get("created_time").and_then(|res| res.parse().ok()) {
+        Some(res) => {
+            res
+        },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
     if get_time_sec() > token_expire {
         return Err(AccessTokenError::ExpiredToken);
     }
 
+    let session_id = match claims.get(TOKEN_SESSION_CLAIM).map(|res| res.parse()) {
+        Some(Ok(res)) => {
+            Some(res)
+        },
+        Some(Err(_err)) => {
+            return Err(AccessTokenError::TokenInvalid);
+        },
+        None => {
+            None
+        }
+    };
+
+    let user_id = match claims.get(TOKEN_USER_CLAIM).map(|res| res.parse()) {
+        Some(Ok(res)) => {
+            Some(res)
+        },
+        Some(Err(_err)) => {
+            return Err(AccessTokenError::TokenInvalid);
+        },
+        None => {
+            None
+        }
+    };
+
+    let username = match claims.get("username") {
+        Some(res) => {
+            res.to_string()
+        },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+
     let remaining_time: u64 = token_expire - get_time_sec();
-    let response = AccessTokenResult {
-        username: claims["username"].to_string(),
+    let response = AccessTokenClaims {
+        user_id,
+        username,
         remaining_time,
+        session_id,
     };
 
     Ok(response)
 }
 
 pub async fn is_unauthorized(req: &HttpRequest) -> Result<AccessTokenResult, AccessTokenError> {
-    let token = match req.headers().get("authorization") {
+    let use_cases = match req.app_data::<web::Data<crate::UseCases>>() {
+        Some(res) => {
+            res
+        },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+
+    let token = match req.headers().get("authorization").and_then(|res| res.to_str().ok()) {
+        Some(res) => {
+            res
+        },
+
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+    let token = match token.strip_prefix("Bearer ") {
         Some(res) => {
-            res.to_str().unwrap()
+            res
         },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+
+    let claims = verify_access_token(&use_cases.user_use_case.token, token)?;
 
+    // A signed token outlives its session and its account; revoking the
+    // session or disabling or deleting the account must end it.
+    let (user_id, username) = match use_cases.user_use_case.user_token_owner(claims.user_id, &claims.username, claims.session_id).await {
+        Some(res) => {
+            res
+        },
         None => {
             return Err(AccessTokenError::TokenInvalid);
         }
     };
-    let token = &token[7..];
 
-    return match verify_access_token(token).await {
-        Ok(res) => {
-            Ok(res)
+    Ok(AccessTokenResult {
+        user_id,
+        username,
+        remaining_time: claims.remaining_time,
+        session_id: claims.session_id,
+    })
+}
+
+// Like is_unauthorized, and an API key holding scope is accepted in place of
+// the owner's access token.
+pub async fn is_authorized(req: &HttpRequest, scope: &str) -> Result<AccessTokenResult, AccessTokenError> {
+    let token = req.headers().get("authorization")
+        .and_then(|res| res.to_str().ok())
+        .and_then(|res| res.strip_prefix("Bearer "));
+    let api_key = match token {
+        Some(res) if res.starts_with(API_KEY_MARKER) => {
+            res
         },
-        Err(err) => {
-            Err(err)
+        _ => {
+            return is_unauthorized(req).await;
         }
     };
+
+    let use_cases = match req.app_data::<web::Data<crate::UseCases>>() {
+        Some(res) => {
+            res
+        },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+    let (username, key) = match use_cases.user_use_case.user_api_key_authenticate(api_key).await {
+        Some(res) => {
+            res
+        },
+        None => {
+            return Err(AccessTokenError::TokenInvalid);
+        }
+    };
+    if !has_api_key_scope(&key, scope) {
+        return Err(AccessTokenError::InsufficientScope);
+    }
+
+    let remaining_time = match key.expire_ts {
+        Some(expire_ts) => {
+            (expire_ts.timestamp() as u64).saturating_sub(get_time_sec())
+        },
+        None => {
+            u64::MAX
+        }
+    };
+
+    Ok(AccessTokenResult {
+        user_id: key.user_id,
+        username,
+        remaining_time,
+        session_id: None,
+    })
+}
+
+// Like is_unauthorized, and the token must also belong to one of auth.admins.
+pub async fn is_admin(req: &HttpRequest) -> Result<AccessTokenResult, AccessTokenError> {
+    let token_result = is_unauthorized(req).await?;
+
+    let is_admin = match req.app_data::<web::Data<crate::UseCases>>() {
+        Some(use_cases) => {
+            use_cases.user_use_case.is_admin(token_result.user_id)
+        },
+        None => {
+            false
+        }
+    };
+    if !is_admin {
+        return Err(AccessTokenError::NotAdmin);
+    }
+
+    Ok(token_result)
+}
+
+// Who is calling and from where, for the audit log. The IP is the peer
+// address; X-Forwarded-For is not trusted because clients can set it.
+pub fn audit_context(req: &HttpRequest, actor: Option<&AccessTokenResult>) -> AuditContext {
+    let user_agent = req.headers().get("user-agent")
+        .and_then(|res| res.to_str().ok())
+        .map(|res| res.chars().take(MAX_USER_AGENT_LEN).collect());
+
+    AuditContext {
+        actor: actor.map(|res| res.username.clone()),
+        ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+        user_agent,
+        request_id: current_request_id(),
+    }
 }
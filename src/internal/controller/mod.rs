@@ -1,3 +1,43 @@
+pub mod app;
+pub mod server;
 pub mod user_controller;
+pub mod health_controller;
+pub mod metrics_controller;
+pub mod logging_controller;
+pub mod audit_controller;
+pub mod mfa_controller;
+pub mod passkey_controller;
+pub mod oidc_controller;
+pub mod identity_controller;
+pub mod api_key_controller;
+pub mod session_controller;
+pub mod profile_controller;
+pub mod org_controller;
+pub mod bulk_controller;
+pub mod batch_controller;
+pub mod job_controller;
+pub mod openapi;
 pub mod response;
-pub mod middleware;
\ No newline at end of file
+pub mod middleware;
+pub mod request_id;
+pub mod user_controller_test;
+pub mod health_controller_test;
+pub mod metrics_controller_test;
+pub mod logging_controller_test;
+pub mod request_id_test;
+pub mod trace_test;
+pub mod openapi_test;
+pub mod audit_controller_test;
+pub mod mfa_controller_test;
+pub mod passkey_controller_test;
+pub mod oidc_controller_test;
+pub mod identity_controller_test;
+pub mod api_key_controller_test;
+pub mod session_controller_test;
+pub mod profile_controller_test;
+pub mod org_controller_test;
+pub mod bulk_controller_test;
+pub mod batch_controller_test;
+pub mod job_controller_test;
+#[cfg(test)]
+pub mod test_app;
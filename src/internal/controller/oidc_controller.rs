@@ -0,0 +1,302 @@
+// OpenID provider endpoints for our own applications, and client registration
+// for admins. The protocol endpoints speak plain OAuth JSON (RFC 6749) instead
+// of GeneralResponse, since off-the-shelf client libraries parse them.
+pub mod oidc_routes {
+    use actix_web::{HttpRequest, HttpResponse, Responder, web, get, post};
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::{ContentType, CACHE_CONTROL, LOCATION, PRAGMA, WWW_AUTHENTICATE};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::Serialize;
+    use crate::internal::oidc::entity::oidc::{
+        discovery, jwks, new_oidc_error, verify_oidc_client_create_request, AuthorizeRequest, OidcClientCreateRequest,
+        OidcClientResponse, OidcError, OidcErrorResponse, TokenRequest, TokenResponse, UserInfo, ERROR_INVALID_CLIENT,
+        ERROR_INVALID_REQUEST, ERROR_INVALID_TOKEN,
+    };
+    use crate::internal::oidc::usecase::traits::{ClientCredentials, UseCase};
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error};
+
+    // Token responses carry credentials and must not be cached (RFC 6749 5.1).
+    fn send_oidc_response<T: Serialize>(data: T) -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .insert_header((CACHE_CONTROL, "no-store"))
+            .insert_header((PRAGMA, "no-cache"))
+            .body(serde_json::to_string(&data).unwrap())
+    }
+
+    fn send_oidc_error(err: OidcError, challenge: &str) -> HttpResponse {
+        let response = OidcErrorResponse {
+            error: err.error.clone(),
+            error_description: err.error_description,
+        };
+
+        let mut builder = HttpResponse::build(err.status_code);
+        builder.content_type(ContentType::json()).insert_header((CACHE_CONTROL, "no-store"));
+        if err.status_code == StatusCode::UNAUTHORIZED || err.status_code == StatusCode::FORBIDDEN {
+            builder.insert_header((WWW_AUTHENTICATE, format!("{} error=\"{}\"", challenge, err.error)));
+        }
+
+        builder.body(serde_json::to_string(&response).unwrap())
+    }
+
+    // Authorization: Basic base64(client_id:client_secret); None without the
+    // header, Err when it is malformed.
+    fn basic_credentials(req: &HttpRequest) -> Result<Option<ClientCredentials>, ()> {
+        let header = match req.headers().get("authorization").and_then(|res| res.to_str().ok()) {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let encoded = header.strip_prefix("Basic ").ok_or(())?;
+        let decoded = STANDARD.decode(encoded.trim()).map_err(|_err| ())?;
+        let decoded = String::from_utf8(decoded).map_err(|_err| ())?;
+        let (client_id, client_secret) = decoded.split_once(':').ok_or(())?;
+
+        Ok(Some(ClientCredentials {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+        }))
+    }
+
+    #[utoipa::path(
+        tag = "oidc",
+        responses(
+            (status = 200, description = "OpenID Provider metadata", body = Object),
+        ),
+    )]
+    #[get("/.well-known/openid-configuration")]
+    pub async fn oidc_discovery(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        HttpResponse::Ok().content_type(ContentType::json()).body(discovery(&use_cases.oidc_use_case.cfg).to_string())
+    }
+
+    #[utoipa::path(
+        tag = "oidc",
+        responses(
+            (status = 200, description = "Public keys for ID and access tokens", body = Object),
+        ),
+    )]
+    #[get("/api/oidc/jwks")]
+    pub async fn oidc_jwks(
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        HttpResponse::Ok().content_type(ContentType::json()).body(jwks(&use_cases.oidc_use_case.cfg).to_string())
+    }
+
+    #[utoipa::path(
+        tag = "oidc",
+        params(AuthorizeRequest),
+        security((), ("bearer_auth" = [])),
+        responses(
+            (status = 302, description = "To redirect_uri with code and state, or with error and error_description; login_required without a valid bearer token"),
+            (status = 400, description = "Unknown client or redirect_uri", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/oidc/authorize")]
+    pub async fn oidc_authorize(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        // There is no login page here: the caller presents the user's access
+        // token, and without one the client is told to log the user in.
+        let username = middleware::is_unauthorized(&req).await.ok().map(|res| res.username);
+
+        let query = match web::Query::<AuthorizeRequest>::from_query(req.query_string()) {
+            Ok(res) => {
+                res.into_inner()
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid query".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.oidc_use_case.oidc_authorize(username, query).await {
+            Ok(res) => {
+                HttpResponse::Found().insert_header((LOCATION, res)).finish()
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "oidc",
+        request_body(content = TokenRequest, content_type = "application/x-www-form-urlencoded"),
+        responses(
+            (status = 200, description = "Tokens", body = TokenResponse),
+            (status = 400, description = "Invalid request or grant", body = OidcErrorResponse),
+            (status = 401, description = "Client authentication failed", body = OidcErrorResponse),
+        ),
+    )]
+    #[post("/api/oidc/token")]
+    pub async fn oidc_token(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let basic = match basic_credentials(&req) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let err = new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_CLIENT, "Malformed Authorization header");
+                return send_oidc_error(err, "Basic");
+            }
+        };
+
+        let des: TokenRequest = match serde_urlencoded::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let err = new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_REQUEST, "Can't convert request");
+                return send_oidc_error(err, "Basic");
+            }
+        };
+
+        return match use_cases.oidc_use_case.oidc_token(des, basic).await {
+            Ok(res) => {
+                send_oidc_response(res)
+            }
+            Err(err) => {
+                send_oidc_error(err, "Basic")
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "oidc",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Claims about the user", body = UserInfo),
+            (status = 401, description = "Missing, invalid or expired access token", body = OidcErrorResponse),
+            (status = 403, description = "The access token lacks the openid scope", body = OidcErrorResponse),
+        ),
+    )]
+    #[get("/api/oidc/userinfo")]
+    pub async fn oidc_userinfo(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token = match req.headers().get("authorization").and_then(|res| res.to_str().ok()).and_then(|res| res.strip_prefix("Bearer ")) {
+            Some(res) => {
+                res.to_string()
+            }
+            None => {
+                let err = new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_TOKEN, "A bearer access token is required");
+                return send_oidc_error(err, "Bearer");
+            }
+        };
+
+        return match use_cases.oidc_use_case.oidc_userinfo(token).await {
+            Ok(res) => {
+                send_oidc_response(res)
+            }
+            Err(err) => {
+                send_oidc_error(err, "Bearer")
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        request_body = OidcClientCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Client; client_secret is shown only here", body = GeneralResponse<OidcClientResponse>),
+            (status = 400, description = "Invalid name or redirect_uris", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/oidc/clients")]
+    pub async fn oidc_client_create(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let des: OidcClientCreateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_oidc_client_create_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.oidc_use_case.oidc_client_create(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Registered clients, without secrets", body = GeneralResponse<Vec<OidcClientResponse>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/oidc/clients")]
+    pub async fn oidc_client_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_admin(&req).await {
+            return admin_error(err);
+        }
+
+        return match use_cases.oidc_use_case.oidc_client_list().await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,391 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_not_admin_token, test_token, test_token_config, test_use_cases, TEST_ISSUER};
+    use crate::internal::oidc::entity::oidc::{pkce_challenge, verify_id_token};
+    use crate::internal::user::entity::token::generate_access_token;
+    use crate::internal::user::entity::user::UserStatus;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const REDIRECT_URI: &str = "https://app.example.com/cb";
+    const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    // Sends the request and returns the status, the named header and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr, $header:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let header = res.headers().get($header).map(|h| h.to_str().unwrap().to_string()).unwrap_or_default();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, header, body)
+        }};
+    }
+
+    macro_rules! create_client {
+        ($app:expr, $confidential:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/api/v1/oidc/clients")
+                .insert_header(("Authorization", test_token()))
+                .set_json(json!({"name": "Wiki", "redirect_uris": [REDIRECT_URI], "confidential": $confidential}));
+            let (status, _, body) = call!($app, req, "location");
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            body["data"].clone()
+        }};
+    }
+
+    // Runs /authorize for JamesHolland and returns the redirect.
+    macro_rules! authorize {
+        ($app:expr, $params:expr) => {{
+            let query = serde_urlencoded::to_string($params).unwrap();
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/oidc/authorize?{}", query))
+                .insert_header(("Authorization", test_token()));
+            let (status, location, body) = call!($app, req, "location");
+            assert_eq!(status, StatusCode::FOUND, "{}", body);
+            location
+        }};
+    }
+
+    macro_rules! token {
+        ($app:expr, $params:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/api/oidc/token")
+                .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+                .set_payload(serde_urlencoded::to_string($params).unwrap());
+            call!($app, req, "www-authenticate")
+        }};
+    }
+
+    macro_rules! userinfo {
+        ($app:expr, $token:expr) => {{
+            let req = test::TestRequest::get()
+                .uri("/api/oidc/userinfo")
+                .insert_header(("Authorization", format!("Bearer {}", $token)));
+            call!($app, req, "www-authenticate")
+        }};
+    }
+
+    fn query_param(location: &str, name: &str) -> Option<String> {
+        let query = location.split_once('?')?.1;
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query).ok()?;
+        params.into_iter().find(|(key, _)| key == name).map(|(_, value)| value)
+    }
+
+    fn authorize_params<'a>(client_id: &'a str, challenge: &'a str) -> Vec<(&'a str, &'a str)> {
+        vec![
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", "openid profile"),
+            ("state", "af0ifjsldkj"),
+            ("nonce", "n-0S6_WzA2Mj"),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256"),
+        ]
+    }
+
+    #[actix_web::test]
+    async fn oidc_discovery_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let key_id = use_cases.oidc_use_case.cfg.key_id.clone();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, _, body) = call!(app, test::TestRequest::get().uri("/.well-known/openid-configuration"), "location");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["issuer"], json!(TEST_ISSUER));
+        assert_eq!(body["authorization_endpoint"], json!("http://localhost:8081/api/oidc/authorize"));
+        assert_eq!(body["token_endpoint"], json!("http://localhost:8081/api/oidc/token"));
+        assert_eq!(body["jwks_uri"], json!("http://localhost:8081/api/oidc/jwks"));
+        assert_eq!(body["code_challenge_methods_supported"], json!(["S256"]));
+
+        let (status, _, body) = call!(app, test::TestRequest::get().uri("/api/oidc/jwks"), "location");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["keys"][0]["kid"], json!(key_id));
+    }
+
+    #[actix_web::test]
+    async fn oidc_authorization_code_flow_test() {
+        let (use_cases, repos) = test_use_cases();
+        let cfg = use_cases.oidc_use_case.cfg.clone();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let client = create_client!(app, false);
+        let client_id = client["client_id"].as_str().unwrap().to_string();
+        assert_eq!((client["client_secret"].clone(), client["confidential"].clone()), (Value::Null, json!(false)));
+
+        let challenge = pkce_challenge(CODE_VERIFIER);
+        let location = authorize!(app, &authorize_params(&client_id, &challenge));
+        assert!(location.starts_with("https://app.example.com/cb?code="), "{}", location);
+        assert_eq!(query_param(&location, "state").as_deref(), Some("af0ifjsldkj"));
+        let code = query_param(&location, "code").unwrap();
+
+        let exchange = [
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", CODE_VERIFIER),
+            ("client_id", client_id.as_str()),
+        ];
+        let req = test::TestRequest::post()
+            .uri("/api/oidc/token")
+            .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+            .set_payload(serde_urlencoded::to_string(exchange).unwrap());
+        let (status, cache_control, tokens) = call!(app, req, "cache-control");
+        assert_eq!(status, StatusCode::OK, "{}", tokens);
+        assert_eq!(cache_control, "no-store");
+        assert_eq!((tokens["token_type"].clone(), tokens["scope"].clone(), tokens["expires_in"].clone()), (json!("Bearer"), json!("openid profile"), json!(300)));
+
+        let id_token = verify_id_token(&cfg, tokens["id_token"].as_str().unwrap()).unwrap();
+        assert_eq!((id_token["sub"].clone(), id_token["aud"].clone()), (json!("1"), json!(client_id)));
+        assert_eq!((id_token["nonce"].clone(), id_token["preferred_username"].clone()), (json!("n-0S6_WzA2Mj"), json!("JamesHolland")));
+
+        let (status, _, info) = userinfo!(app, tokens["access_token"].as_str().unwrap());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(info, json!({
+            "sub": "1", "preferred_username": "JamesHolland", "name": "James Holland",
+            "given_name": "James", "family_name": "Holland", "updated_at": 1676000000,
+        }));
+
+        // Codes work once.
+        let (status, _, body) = token!(app, exchange);
+        assert_eq!((status, body["error"].clone()), (StatusCode::BAD_REQUEST, json!("invalid_grant")));
+
+        // Refresh tokens rotate and can narrow the scope.
+        let refresh_token = tokens["refresh_token"].as_str().unwrap().to_string();
+        let refresh = [("grant_type", "refresh_token"), ("refresh_token", refresh_token.as_str()), ("scope", "openid"), ("client_id", client_id.as_str())];
+        let (status, _, refreshed) = token!(app, refresh);
+        assert_eq!(status, StatusCode::OK, "{}", refreshed);
+        assert_eq!(refreshed["scope"], json!("openid"));
+        assert_ne!(refreshed["refresh_token"], tokens["refresh_token"]);
+        let (_, _, info) = userinfo!(app, refreshed["access_token"].as_str().unwrap());
+        assert_eq!(info, json!({"sub": "1"}));
+
+        let (status, _, body) = token!(app, refresh);
+        assert_eq!((status, body["error"].clone()), (StatusCode::BAD_REQUEST, json!("invalid_grant")));
+        let widen = [("grant_type", "refresh_token"), ("refresh_token", refreshed["refresh_token"].as_str().unwrap()), ("scope", "openid profile"), ("client_id", client_id.as_str())];
+        let (status, _, body) = token!(app, widen);
+        assert_eq!((status, body["error"].clone()), (StatusCode::BAD_REQUEST, json!("invalid_scope")));
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        assert_eq!(entries[0].action, "oidc.client_create");
+        assert_eq!(entries[0].actor_id, Some(1));
+        assert_eq!(entries[0].changes["client_id"], json!({"before": null, "after": client_id}));
+    }
+
+    #[actix_web::test]
+    async fn oidc_inactive_account_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let client = create_client!(app, false);
+        let client_id = client["client_id"].as_str().unwrap().to_string();
+        let challenge = pkce_challenge(CODE_VERIFIER);
+        let exchange = |code: String| vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI.to_string()),
+            ("code_verifier", CODE_VERIFIER.to_string()),
+            ("client_id", client_id.clone()),
+        ];
+
+        let code = query_param(&authorize!(app, &authorize_params(&client_id, &challenge)), "code").unwrap();
+        let (status, _, tokens) = token!(app, exchange(code));
+        assert_eq!(status, StatusCode::OK, "{}", tokens);
+        let access_token = tokens["access_token"].as_str().unwrap();
+        let refresh = [("grant_type", "refresh_token"), ("refresh_token", tokens["refresh_token"].as_str().unwrap()), ("client_id", client_id.as_str())];
+        // Issued before the account is disabled, exchanged after.
+        let pending_code = query_param(&authorize!(app, &authorize_params(&client_id, &challenge)), "code").unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/user/1/status")
+            .insert_header(("Authorization", test_token()))
+            .set_json(json!({"action": "disable", "reason": "left the company"}));
+        let (status, _, body) = call!(app, req, "location");
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(repos.oidc.refresh_tokens.lock().unwrap().is_empty());
+
+        let (status, _, body) = userinfo!(app, access_token);
+        assert_eq!((status, body["error"].clone()), (StatusCode::UNAUTHORIZED, json!("invalid_token")));
+        let (status, _, body) = token!(app, exchange(pending_code));
+        assert_eq!((status, body["error"].clone(), body["error_description"].clone()), (StatusCode::BAD_REQUEST, json!("invalid_grant"), json!("Account is disabled")));
+        let (status, _, body) = token!(app, refresh);
+        assert_eq!((status, body["error"].clone()), (StatusCode::BAD_REQUEST, json!("invalid_grant")));
+
+        // Revoked, so activating the account again doesn't revive the token.
+        repos.user.users.lock().unwrap()[0].status = UserStatus::Active;
+        let (status, _, body) = token!(app, refresh);
+        assert_eq!((status, body["error_description"].clone()), (StatusCode::BAD_REQUEST, json!("Unknown, used or expired refresh_token")));
+        let (status, _, _) = userinfo!(app, access_token);
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn oidc_authorize_rejected_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let client = create_client!(app, false);
+        let client_id = client["client_id"].as_str().unwrap();
+        let challenge = pkce_challenge(CODE_VERIFIER);
+
+        let with = |name: &'static str, value: Option<&'static str>| {
+            let mut params: Vec<(&str, &str)> = authorize_params(client_id, &challenge).into_iter().filter(|(key, _)| *key != name).collect();
+            if let Some(value) = value {
+                params.push((name, value));
+            }
+            serde_urlencoded::to_string(params).unwrap()
+        };
+
+        // Not redirected: the client or redirect_uri can't be trusted.
+        let test_cases = vec! {
+            (with("client_id", None), "client_id is required"),
+            (with("client_id", Some("unknown")), "Unknown client"),
+            (with("redirect_uri", Some("https://evil.example.com/cb")), "redirect_uri is not registered for this client"),
+            (with("redirect_uri", None), "redirect_uri is required"),
+        };
+        for (query, error_msg) in test_cases {
+            let req = test::TestRequest::get().uri(&format!("/api/oidc/authorize?{}", query)).insert_header(("Authorization", test_token()));
+            let (status, _, body) = call!(app, req, "location");
+            assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::BAD_REQUEST, json!(error_msg)), "{}", query);
+        }
+
+        // Redirected back to the client with an error.
+        let test_cases = vec! {
+            (with("response_type", Some("token")), "unsupported_response_type"),
+            (with("scope", Some("profile")), "invalid_scope"),
+            (with("code_challenge", None), "invalid_request"),
+            (with("code_challenge_method", Some("plain")), "invalid_request"),
+        };
+        for (query, error) in test_cases {
+            let req = test::TestRequest::get().uri(&format!("/api/oidc/authorize?{}", query)).insert_header(("Authorization", test_token()));
+            let (status, location, _) = call!(app, req, "location");
+            assert_eq!(status, StatusCode::FOUND);
+            assert_eq!(query_param(&location, "error").as_deref(), Some(error), "{}", query);
+            assert_eq!(query_param(&location, "state").as_deref(), Some("af0ifjsldkj"));
+        }
+
+        let req = test::TestRequest::get().uri(&format!("/api/oidc/authorize?{}", with("state", None)));
+        let (status, location, _) = call!(app, req, "location");
+        assert_eq!(status, StatusCode::FOUND);
+        assert_eq!(query_param(&location, "error").as_deref(), Some("login_required"));
+        assert_eq!(query_param(&location, "state"), None);
+        assert!(repos.oidc.codes.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn oidc_token_rejected_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let public = create_client!(app, false);
+        let public_id = public["client_id"].as_str().unwrap().to_string();
+        let confidential = create_client!(app, true);
+        let confidential_id = confidential["client_id"].as_str().unwrap().to_string();
+        let secret = confidential["client_secret"].as_str().unwrap().to_string();
+        let challenge = pkce_challenge(CODE_VERIFIER);
+
+        // Confidential clients act for themselves; their tokens carry no user.
+        let basic = format!("Basic {}", STANDARD.encode(format!("{}:{}", confidential_id, secret)));
+        let req = test::TestRequest::post()
+            .uri("/api/oidc/token")
+            .insert_header(("Authorization", basic))
+            .set_payload("grant_type=client_credentials");
+        let (status, _, body) = call!(app, req, "location");
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body.get("refresh_token"), body.get("id_token")), (None, None));
+        let (status, challenge_header, body) = userinfo!(app, body["access_token"].as_str().unwrap());
+        assert_eq!((status, body["error"].clone()), (StatusCode::FORBIDDEN, json!("insufficient_scope")));
+        assert_eq!(challenge_header, "Bearer error=\"insufficient_scope\"");
+
+        let mut codes = Vec::new();
+        for _ in 0..3 {
+            let location = authorize!(app, &authorize_params(&public_id, &challenge));
+            codes.push(query_param(&location, "code").unwrap());
+        }
+        let wrong_verifier = "x".repeat(43);
+
+        let test_cases = vec! {
+            (vec![("grant_type", "client_credentials"), ("client_id", confidential_id.as_str()), ("client_secret", "wrong")], StatusCode::UNAUTHORIZED, "invalid_client"),
+            (vec![("grant_type", "client_credentials"), ("client_id", confidential_id.as_str())], StatusCode::UNAUTHORIZED, "invalid_client"),
+            (vec![("grant_type", "client_credentials"), ("client_id", "unknown")], StatusCode::UNAUTHORIZED, "invalid_client"),
+            (vec![("grant_type", "client_credentials")], StatusCode::UNAUTHORIZED, "invalid_client"),
+            (vec![("grant_type", "client_credentials"), ("client_id", public_id.as_str())], StatusCode::BAD_REQUEST, "unauthorized_client"),
+            (vec![("grant_type", "password"), ("client_id", public_id.as_str())], StatusCode::BAD_REQUEST, "unsupported_grant_type"),
+            (vec![("client_id", public_id.as_str())], StatusCode::BAD_REQUEST, "invalid_request"),
+            (
+                vec![("grant_type", "authorization_code"), ("code", codes[0].as_str()), ("redirect_uri", REDIRECT_URI), ("client_id", public_id.as_str())],
+                StatusCode::BAD_REQUEST, "invalid_request",
+            ),
+            (
+                vec![("grant_type", "authorization_code"), ("code", codes[0].as_str()), ("redirect_uri", REDIRECT_URI), ("code_verifier", wrong_verifier.as_str()), ("client_id", public_id.as_str())],
+                StatusCode::BAD_REQUEST, "invalid_grant",
+            ),
+            // The failed attempt above spent the code.
+            (
+                vec![("grant_type", "authorization_code"), ("code", codes[0].as_str()), ("redirect_uri", REDIRECT_URI), ("code_verifier", CODE_VERIFIER), ("client_id", public_id.as_str())],
+                StatusCode::BAD_REQUEST, "invalid_grant",
+            ),
+            (
+                vec![("grant_type", "authorization_code"), ("code", codes[1].as_str()), ("redirect_uri", "https://app.example.com/other"), ("code_verifier", CODE_VERIFIER), ("client_id", public_id.as_str())],
+                StatusCode::BAD_REQUEST, "invalid_grant",
+            ),
+            (
+                vec![("grant_type", "authorization_code"), ("code", codes[2].as_str()), ("redirect_uri", REDIRECT_URI), ("code_verifier", CODE_VERIFIER), ("client_id", confidential_id.as_str()), ("client_secret", secret.as_str())],
+                StatusCode::BAD_REQUEST, "invalid_grant",
+            ),
+            (vec![("grant_type", "refresh_token"), ("refresh_token", "unknown"), ("client_id", public_id.as_str())], StatusCode::BAD_REQUEST, "invalid_grant"),
+        };
+        for (params, status, error) in test_cases {
+            let (res, challenge_header, body) = token!(app, &params);
+            assert_eq!((res, body["error"].clone()), (status, json!(error)), "{:?}", params);
+            if status == StatusCode::UNAUTHORIZED {
+                assert_eq!(challenge_header, "Basic error=\"invalid_client\"");
+            }
+        }
+
+        // First-party tokens are not OIDC access tokens.
+        let (status, _, body) = userinfo!(app, generate_access_token(&test_token_config(), &"JamesHolland".to_string()));
+        assert_eq!((status, body["error"].clone()), (StatusCode::UNAUTHORIZED, json!("invalid_token")));
+        let (status, _, _) = call!(app, test::TestRequest::get().uri("/api/oidc/userinfo"), "location");
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn oidc_clients_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let confidential = create_client!(app, true);
+        assert_eq!(confidential["confidential"], json!(true));
+        assert!(confidential["client_secret"].is_string());
+
+        let req = test::TestRequest::get().uri("/api/v1/oidc/clients").insert_header(("Authorization", test_token()));
+        let (status, _, body) = call!(app, req, "location");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], json!([{
+            "client_id": confidential["client_id"], "name": "Wiki", "redirect_uris": [REDIRECT_URI], "confidential": true,
+        }]));
+
+        let not_admin = test_not_admin_token(&repos);
+        let test_cases = vec! {
+            (test::TestRequest::get().uri("/api/v1/oidc/clients"), StatusCode::UNAUTHORIZED),
+            (test::TestRequest::get().uri("/api/v1/oidc/clients").insert_header(("Authorization", not_admin.clone())), StatusCode::FORBIDDEN),
+            (test::TestRequest::post().uri("/api/v1/oidc/clients").insert_header(("Authorization", not_admin)).set_json(json!({})), StatusCode::FORBIDDEN),
+            (test::TestRequest::post().uri("/api/v1/oidc/clients").insert_header(("Authorization", test_token())).set_json(json!({"name": "Wiki"})), StatusCode::BAD_REQUEST),
+            (
+                test::TestRequest::post().uri("/api/v1/oidc/clients").insert_header(("Authorization", test_token()))
+                    .set_json(json!({"name": "Wiki", "redirect_uris": ["http://wiki.example.com/cb"]})),
+                StatusCode::BAD_REQUEST,
+            ),
+        };
+        for (req, status) in test_cases {
+            let (res, _, body) = call!(app, req, "location");
+            assert_eq!(res, status, "{}", body);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::internal::controller::{
+    api_key_controller, audit_controller, batch_controller, bulk_controller, health_controller, identity_controller, job_controller, logging_controller, metrics_controller, mfa_controller, oidc_controller,
+    org_controller, passkey_controller, profile_controller, session_controller, user_controller,
+};
+
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+pub const SWAGGER_UI_PATH: &str = "/api/docs";
+
+// The document is generated from the #[utoipa::path] annotations next to each
+// route; docs/openapi.json is a checked-in copy kept in sync by openapi_test.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "rust-clean", description = "User accounts and authentication"),
+    paths(
+        user_controller::user_routes::user_auth,
+        user_controller::user_routes::user_create,
+        user_controller::user_routes::user_list,
+        user_controller::user_routes::user_me,
+        user_controller::user_routes::user_update_me,
+        user_controller::user_routes::user_get,
+        user_controller::user_routes::user_update_by_id,
+        user_controller::user_routes::user_change_password,
+        user_controller::user_routes::user_delete,
+        user_controller::user_routes::user_set_status,
+        bulk_controller::bulk_routes::user_import,
+        bulk_controller::bulk_routes::user_export,
+        batch_controller::batch_routes::user_batch,
+        profile_controller::profile_routes::profile_get_me,
+        profile_controller::profile_routes::profile_update_me,
+        profile_controller::profile_routes::avatar_set_me,
+        profile_controller::profile_routes::avatar_delete_me,
+        profile_controller::profile_routes::avatar_get,
+        mfa_controller::mfa_routes::mfa_enroll,
+        mfa_controller::mfa_routes::mfa_confirm,
+        mfa_controller::mfa_routes::mfa_verify,
+        mfa_controller::mfa_routes::mfa_reset,
+        passkey_controller::passkey_routes::passkey_register_begin,
+        passkey_controller::passkey_routes::passkey_register_finish,
+        passkey_controller::passkey_routes::passkey_login_begin,
+        passkey_controller::passkey_routes::passkey_login_finish,
+        identity_controller::identity_routes::identity_login,
+        identity_controller::identity_routes::identity_link,
+        identity_controller::identity_routes::identity_list,
+        identity_controller::identity_routes::identity_unlink,
+        identity_controller::identity_routes::identity_provider_create,
+        identity_controller::identity_routes::identity_provider_list,
+        org_controller::org_routes::org_create,
+        org_controller::org_routes::org_list,
+        org_controller::org_routes::org_get,
+        org_controller::org_routes::org_update,
+        org_controller::org_routes::org_delete,
+        org_controller::org_routes::org_member_list,
+        org_controller::org_routes::org_member_set,
+        org_controller::org_routes::org_member_remove,
+        api_key_controller::api_key_routes::api_key_create,
+        api_key_controller::api_key_routes::api_key_list,
+        api_key_controller::api_key_routes::api_key_rotate,
+        api_key_controller::api_key_routes::api_key_revoke,
+        session_controller::session_routes::session_list_me,
+        session_controller::session_routes::session_revoke_me,
+        session_controller::session_routes::session_list,
+        session_controller::session_routes::session_revoke,
+        audit_controller::audit_routes::audit_list,
+        job_controller::job_routes::job_list,
+        job_controller::job_routes::job_get,
+        job_controller::job_routes::job_create,
+        oidc_controller::oidc_routes::oidc_discovery,
+        oidc_controller::oidc_routes::oidc_jwks,
+        oidc_controller::oidc_routes::oidc_authorize,
+        oidc_controller::oidc_routes::oidc_token,
+        oidc_controller::oidc_routes::oidc_userinfo,
+        oidc_controller::oidc_routes::oidc_client_create,
+        oidc_controller::oidc_routes::oidc_client_list,
+        health_controller::health_routes::healthz,
+        health_controller::health_routes::readyz,
+        health_controller::health_routes::health,
+        metrics_controller::metrics_routes::metrics,
+        logging_controller::logging_routes::log_level_get,
+        logging_controller::logging_routes::log_level_set,
+    ),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "user", description = "Accounts and tokens"),
+        (name = "org", description = "Organizations and their members"),
+        (name = "oidc", description = "OpenID Connect provider for our applications"),
+        (name = "probe", description = "Liveness and readiness for the orchestrator"),
+        (name = "operations", description = "Metrics and runtime settings"),
+        (name = "admin", description = "Restricted to auth.admins"),
+    ),
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
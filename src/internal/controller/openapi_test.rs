@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::Value;
+    use utoipa::OpenApi;
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::openapi::ApiDoc;
+    use crate::internal::controller::test_app::test_use_cases;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const SNAPSHOT: &str = "docs/openapi.json";
+    const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+    fn snapshot_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT)
+    }
+
+    fn spec_operations(spec: &Value) -> BTreeSet<(String, String)> {
+        let mut operations = BTreeSet::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in METHODS {
+                if item.get(method).is_some() {
+                    operations.insert((method.to_string(), path.clone()));
+                }
+            }
+        }
+        operations
+    }
+
+    // Every #[get("...")]-style route macro in the controllers.
+    fn code_operations() -> BTreeSet<(String, String)> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/internal/controller");
+        let mut operations = BTreeSet::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let source = fs::read_to_string(entry.unwrap().path()).unwrap();
+            for line in source.lines().map(str::trim) {
+                for method in METHODS {
+                    let prefix = format!("#[{}(\"", method);
+                    if let Some(rest) = line.strip_prefix(&prefix) {
+                        let path = rest.split('"').next().unwrap();
+                        operations.insert((method.to_string(), path.to_string()));
+                    }
+                }
+            }
+        }
+        operations
+    }
+
+    #[actix_web::test]
+    async fn openapi_snapshot_test() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+        if std::env::var("UPDATE_OPENAPI").is_ok() {
+            fs::write(snapshot_path(), &generated).unwrap();
+        }
+
+        let checked_in = fs::read_to_string(snapshot_path()).unwrap_or_default();
+        assert!(
+            generated == checked_in,
+            "{} is out of date, regenerate it with `UPDATE_OPENAPI=1 cargo test openapi_snapshot_test`",
+            SNAPSHOT
+        );
+    }
+
+    #[actix_web::test]
+    async fn openapi_covers_routes_test() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let documented = spec_operations(&spec);
+        let routed = code_operations();
+
+        let undocumented: Vec<_> = routed.difference(&documented).collect();
+        assert!(undocumented.is_empty(), "routes missing from ApiDoc: {:?}", undocumented);
+        let stale: Vec<_> = documented.difference(&routed).collect();
+        assert!(stale.is_empty(), "ApiDoc lists routes that no longer exist: {:?}", stale);
+
+        let schemes = &spec["components"]["securitySchemes"]["bearer_auth"];
+        assert_eq!(schemes["scheme"], "bearer");
+        for name in ["GeneralResponse_UserGetResponse", "ErrorResponse", "UserCreateRequest"] {
+            assert!(spec["components"]["schemas"].get(name).is_some(), "missing schema {}", name);
+        }
+    }
+
+    #[actix_web::test]
+    async fn openapi_served_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/openapi.json").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body, serde_json::to_value(ApiDoc::openapi()).unwrap());
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/docs/").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(body.contains("swagger-ui"));
+    }
+}
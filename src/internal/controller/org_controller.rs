@@ -0,0 +1,367 @@
+// Organizations and their members. Service admins create and delete
+// organizations; an organization's admins manage its members. Organizations
+// the caller may not see answer 404.
+pub mod org_routes {
+    use actix_web::{HttpRequest, Responder, web, delete, get, patch, post, put};
+    use crate::internal::org::entity::org::{
+        verify_org_create_request, verify_org_member_request, verify_org_update_request, Org, OrgCreateRequest,
+        OrgMember, OrgMemberRequest, OrgUpdateRequest,
+    };
+    use crate::internal::org::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, GeneralResponse, send_error_response, send_success_response, unauthorized, admin_error, bad_request};
+
+    #[utoipa::path(
+        tag = "admin",
+        request_body = OrgCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Organization created, without members", body = GeneralResponse<Org>),
+            (status = 400, description = "Invalid name or display_name", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Organization name is taken", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/org")]
+    pub async fn org_create(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let des: OrgCreateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return bad_request("Invalid request".to_string());
+            }
+        };
+
+        if let Err(err) = verify_org_create_request(&des) {
+            return bad_request(err);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.org_use_case.org_create(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's organizations, or all of them for admins, ordered by id", body = GeneralResponse<Vec<Org>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/org")]
+    pub async fn org_list(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        return match use_cases.org_use_case.org_list(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        params(("id" = i32, Path, description = "Organization id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The organization", body = GeneralResponse<Org>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization, or the caller is not a member", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/org/{id}")]
+    pub async fn org_get(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        return match use_cases.org_use_case.org_get(token_result.username, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        params(("id" = i32, Path, description = "Organization id")),
+        request_body = OrgUpdateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The updated organization", body = GeneralResponse<Org>),
+            (status = 400, description = "Malformed request, invalid name or display_name, or another field", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin of the organization", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization, or the caller is not a member", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Organization name is taken", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[patch("/api/v1/org/{id}")]
+    pub async fn org_update(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        let des: OrgUpdateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let error_msg = match err.to_string().starts_with("unknown field") {
+                    true => "only name and display_name can be changed".to_string(),
+                    false => "Invalid request".to_string(),
+                };
+
+                return bad_request(error_msg);
+            }
+        };
+
+        if let Err(err) = verify_org_update_request(&des) {
+            return bad_request(err);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.org_use_case.org_update(ctx, token_result.username, id.into_inner(), des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i32, Path, description = "Organization id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Organization and its memberships deleted; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/org/{id}")]
+    pub async fn org_delete(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.org_use_case.org_delete(ctx, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        params(("id" = i32, Path, description = "Organization id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Members with their roles, ordered by user id", body = GeneralResponse<Vec<OrgMember>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization, or the caller is not a member", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/org/{id}/users")]
+    pub async fn org_member_list(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        return match use_cases.org_use_case.org_member_list(token_result.username, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        params(
+            ("id" = i32, Path, description = "Organization id"),
+            ("user_id" = i32, Path, description = "User id"),
+        ),
+        request_body = OrgMemberRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The membership with its role", body = GeneralResponse<OrgMember>),
+            (status = 400, description = "Malformed request or unknown role", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin of the organization", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization or user, or the caller is not a member", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "The change would leave the organization without an admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[put("/api/v1/org/{id}/users/{user_id}")]
+    pub async fn org_member_set(
+        req: HttpRequest,
+        path: web::Path<(i32, i32)>,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        let des: OrgMemberRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return bad_request("Invalid request".to_string());
+            }
+        };
+
+        if let Err(err) = verify_org_member_request(&des) {
+            return bad_request(err);
+        }
+
+        let (id, user_id) = path.into_inner();
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.org_use_case.org_member_set(ctx, token_result.username, id, user_id, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "org",
+        params(
+            ("id" = i32, Path, description = "Organization id"),
+            ("user_id" = i32, Path, description = "User id"),
+        ),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Member removed; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is neither an admin of the organization nor the member", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "No such organization or member, or the caller is not a member", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "The member is the organization's only admin", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/org/{id}/users/{user_id}")]
+    pub async fn org_member_remove(
+        req: HttpRequest,
+        path: web::Path<(i32, i32)>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return unauthorized();
+            }
+        };
+
+        let (id, user_id) = path.into_inner();
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.org_use_case.org_member_remove(ctx, token_result.username, id, user_id).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use bcrypt::hash;
+    use hmac::{Hmac, Mac};
+    use jwt::VerifyWithKey;
+    use serde_json::{json, Value};
+    use sha2::Sha256;
+
+    use crate::config::config::Config;
+    use crate::internal::audit::entity::audit::{AUDIT_ORG_CREATE, AUDIT_ORG_MEMBER_REMOVE, AUDIT_ORG_MEMBER_SET};
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_token_config, test_use_cases, TestRepos, TEST_SECRET_KEY};
+    use crate::internal::user::entity::token::{generate_access_token, TOKEN_ORGS_CLAIM};
+    use crate::internal::user::usecase::repo::memory_repo::MemoryUser;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    macro_rules! get {
+        ($app:expr, $uri:expr, $token:expr) => {{
+            call!($app, test::TestRequest::get().uri($uri).insert_header(("Authorization", $token)))
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $req:expr, $uri:expr, $token:expr, $body:expr) => {{
+            call!($app, $req.uri($uri).insert_header(("Authorization", $token)).set_json($body))
+        }};
+    }
+
+    macro_rules! delete {
+        ($app:expr, $uri:expr, $token:expr) => {{
+            call!($app, test::TestRequest::delete().uri($uri).insert_header(("Authorization", $token)))
+        }};
+    }
+
+    // Adds AnnaSmith (2), BobJones (3) and CarlWhite (4), who are not service
+    // admins, with the password "secret123".
+    fn add_users(repos: &TestRepos) {
+        let password = hash("secret123", 4).unwrap();
+        let mut users = repos.user.users.lock().unwrap();
+        for (id, username) in [(2, "AnnaSmith"), (3, "BobJones"), (4, "CarlWhite")] {
+            users.push(MemoryUser {
+                id,
+                username: username.to_string(),
+                password: password.clone(),
+                firstname: username[..username.len() - 5].to_string(),
+                lastname: username[username.len() - 5..].to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    fn token(username: &str) -> String {
+        format!("Bearer {}", generate_access_token(&test_token_config(), &username.to_string()))
+    }
+
+    #[actix_web::test]
+    async fn org_admin_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, body) = send!(app, test::TestRequest::post(), "/api/v1/org", test_token(), json!({"name": "research", "display_name": "Research"}));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["id"].clone(), body["data"]["name"].clone()), (json!(1), json!("research")));
+
+        let test_cases = vec! {
+            (test_token(), json!({"name": "research", "display_name": "Again"}), StatusCode::CONFLICT, json!("Organization with name=research already exists")),
+            (test_token(), json!({"name": "R&D", "display_name": "Research"}), StatusCode::BAD_REQUEST, json!("name must be 1 to 64 characters of a-z, 0-9 and -")),
+            (test_token(), json!({"name": "sales"}), StatusCode::BAD_REQUEST, json!("Invalid request")),
+            (token("AnnaSmith"), json!({"name": "sales", "display_name": "Sales"}), StatusCode::FORBIDDEN, json!("Forbidden")),
+            ("Bearer nope".to_string(), json!({"name": "sales", "display_name": "Sales"}), StatusCode::UNAUTHORIZED, json!("Unauthorized")),
+        };
+        for (bearer, req, status, error_msg) in test_cases {
+            let (res, body) = send!(app, test::TestRequest::post(), "/api/v1/org", bearer, req.clone());
+            assert_eq!((res, body["data"]["error_msg"].clone()), (status, error_msg), "{}", req);
+        }
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].action.as_str(), entries[0].actor_id), (AUDIT_ORG_CREATE, Some(1)));
+        assert_eq!(entries[0].changes["name"], json!({"before": null, "after": "research"}));
+
+        // Service admins see every organization; others only their own.
+        let (status, body) = get!(app, "/api/v1/org", test_token());
+        assert_eq!((status, body["data"].as_array().unwrap().len()), (StatusCode::OK, 1));
+        let (status, body) = get!(app, "/api/v1/org", token("AnnaSmith"));
+        assert_eq!((status, body["data"].clone()), (StatusCode::OK, json!([])));
+        let (status, _) = get!(app, "/api/v1/org/1", test_token());
+        assert_eq!(status, StatusCode::OK);
+
+        // Only service admins delete organizations, not even their own admins.
+        let (status, _) = send!(app, test::TestRequest::put(), "/api/v1/org/1/users/2", test_token(), json!({"role": "admin"}));
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = delete!(app, "/api/v1/org/1", token("AnnaSmith"));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = delete!(app, "/api/v1/org/1", test_token());
+        assert_eq!(status, StatusCode::OK);
+        assert!(repos.org.members.lock().unwrap().is_empty());
+        let (status, body) = delete!(app, "/api/v1/org/1", test_token());
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("Organization with id=1 not found")));
+    }
+
+    #[actix_web::test]
+    async fn org_member_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        for name in ["research", "sales"] {
+            let (status, _) = send!(app, test::TestRequest::post(), "/api/v1/org", test_token(), json!({"name": name, "display_name": name}));
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        // The service admin makes Anna the admin of research and sales.
+        for org in [1, 2] {
+            let (status, body) = send!(app, test::TestRequest::put(), &format!("/api/v1/org/{}/users/2", org), test_token(), json!({"role": "admin"}));
+            assert_eq!(status, StatusCode::OK, "{}", body);
+        }
+        let (status, body) = send!(app, test::TestRequest::put(), "/api/v1/org/1/users/3", token("AnnaSmith"), json!({"role": "member"}));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["username"].clone(), body["data"]["role"].clone()), (json!("BobJones"), json!("member")));
+
+        let (status, body) = get!(app, "/api/v1/org/1/users", token("BobJones"));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let members: Vec<(Value, Value)> = body["data"].as_array().unwrap().iter().map(|m| (m["user_id"].clone(), m["role"].clone())).collect();
+        assert_eq!(members, vec![(json!(2), json!("admin")), (json!(3), json!("member"))]);
+        let (status, body) = get!(app, "/api/v1/org", token("BobJones"));
+        assert_eq!((status, body["data"][0]["name"].clone(), body["data"].as_array().unwrap().len()), (StatusCode::OK, json!("research"), 1));
+
+        let test_cases = vec! {
+            // Members can look but not change anything.
+            (test::TestRequest::put(), "/api/v1/org/1/users/4", token("BobJones"), json!({"role": "member"}), StatusCode::FORBIDDEN),
+            (test::TestRequest::patch(), "/api/v1/org/1", token("BobJones"), json!({"display_name": "Bob's"}), StatusCode::FORBIDDEN),
+            // Outsiders can't tell the organization exists.
+            (test::TestRequest::put(), "/api/v1/org/2/users/4", token("BobJones"), json!({"role": "member"}), StatusCode::NOT_FOUND),
+            (test::TestRequest::patch(), "/api/v1/org/1", token("CarlWhite"), json!({"display_name": "Carl's"}), StatusCode::NOT_FOUND),
+            (test::TestRequest::put(), "/api/v1/org/9/users/4", token("AnnaSmith"), json!({"role": "member"}), StatusCode::NOT_FOUND),
+            (test::TestRequest::put(), "/api/v1/org/1/users/9", token("AnnaSmith"), json!({"role": "member"}), StatusCode::NOT_FOUND),
+            (test::TestRequest::put(), "/api/v1/org/1/users/4", token("AnnaSmith"), json!({"role": "owner"}), StatusCode::BAD_REQUEST),
+            (test::TestRequest::patch(), "/api/v1/org/1", token("AnnaSmith"), json!({"id": 7}), StatusCode::BAD_REQUEST),
+            (test::TestRequest::patch(), "/api/v1/org/1", token("AnnaSmith"), json!({"name": "sales"}), StatusCode::CONFLICT),
+            // Anna is the only admin of research.
+            (test::TestRequest::put(), "/api/v1/org/1/users/2", token("AnnaSmith"), json!({"role": "member"}), StatusCode::CONFLICT),
+        };
+        for (req, uri, bearer, body, status) in test_cases {
+            let (res, res_body) = send!(app, req, uri, bearer, body.clone());
+            assert_eq!(res, status, "{} {} {}", uri, body, res_body);
+        }
+        let (status, body) = get!(app, "/api/v1/org/1", token("CarlWhite"));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("Organization with id=1 not found")));
+        let (status, _) = get!(app, "/api/v1/org/1/users", token("CarlWhite"));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send!(app, test::TestRequest::patch(), "/api/v1/org/1", token("AnnaSmith"), json!({"display_name": "R&D"}));
+        assert_eq!((status, body["data"]["display_name"].clone(), body["data"]["name"].clone()), (StatusCode::OK, json!("R&D"), json!("research")));
+
+        // Bob can't remove Anna, but can leave; Anna can't leave as the only admin.
+        let (status, _) = delete!(app, "/api/v1/org/1/users/2", token("BobJones"));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = delete!(app, "/api/v1/org/1/users/2", token("AnnaSmith"));
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::CONFLICT, json!("An organization must keep at least one admin")));
+        let (status, _) = delete!(app, "/api/v1/org/1/users/3", token("BobJones"));
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, "/api/v1/org/1", token("BobJones"));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // With a second admin, Anna can step down.
+        let (status, _) = send!(app, test::TestRequest::put(), "/api/v1/org/1/users/4", token("AnnaSmith"), json!({"role": "admin"}));
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = send!(app, test::TestRequest::put(), "/api/v1/org/1/users/2", token("AnnaSmith"), json!({"role": "member"}));
+        assert_eq!((status, body["data"]["role"].clone()), (StatusCode::OK, json!("member")));
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let removed = entries.iter().find(|e| e.action == AUDIT_ORG_MEMBER_REMOVE).unwrap();
+        assert_eq!((removed.actor_id, removed.target_id, removed.target_username.as_deref()), (Some(3), Some(3), Some("BobJones")));
+        let demoted = entries.iter().rev().find(|e| e.action == AUDIT_ORG_MEMBER_SET).unwrap();
+        assert_eq!((demoted.actor_id, demoted.target_id), (Some(2), Some(2)));
+        assert_eq!(demoted.changes["role"], json!({"before": "admin", "after": "member"}));
+    }
+
+    #[actix_web::test]
+    async fn org_token_claim_test() {
+        let (use_cases, repos) = test_use_cases();
+        add_users(&repos);
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let claims = |body: &Value| -> BTreeMap<String, String> {
+            let key: Hmac<Sha256> = Hmac::new_from_slice(TEST_SECRET_KEY.as_bytes()).unwrap();
+            body["data"]["access_token"].as_str().unwrap().verify_with_key(&key).unwrap()
+        };
+        let login = json!({"username": "AnnaSmith", "password": "secret123"});
+
+        let (status, body) = call!(app, test::TestRequest::post().uri("/api/v1/user/auth").set_json(login.clone()));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(!claims(&body).contains_key(TOKEN_ORGS_CLAIM));
+
+        for (id, name, role) in [(1, "research", "member"), (2, "sales", "admin")] {
+            send!(app, test::TestRequest::post(), "/api/v1/org", test_token(), json!({"name": name, "display_name": name}));
+            let (status, _) = send!(app, test::TestRequest::put(), &format!("/api/v1/org/{}/users/2", id), test_token(), json!({"role": role}));
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let (status, body) = call!(app, test::TestRequest::post().uri("/api/v1/user/auth").set_json(login));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(claims(&body).get(TOKEN_ORGS_CLAIM).map(String::as_str), Some("1:member,2:admin"));
+
+        // The token carries a snapshot; access follows the current memberships.
+        let anna = format!("Bearer {}", body["data"]["access_token"].as_str().unwrap());
+        let (status, _) = delete!(app, "/api/v1/org/1/users/2", test_token());
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, "/api/v1/org/1", anna);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
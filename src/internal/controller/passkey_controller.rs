@@ -0,0 +1,215 @@
+// WebAuthn registration for the caller's own account and passwordless login.
+// Each ceremony is a begin call that returns options with a fresh challenge
+// and a finish call that takes the authenticator's response.
+pub mod passkey_routes {
+    use actix_web::{Responder, web, post, HttpRequest};
+    use actix_web::http::StatusCode;
+    use crate::internal::user::entity::passkey::{
+        verify_passkey_login_request, verify_passkey_register_request, PasskeyLoginBeginRequest,
+        PasskeyLoginOptions, PasskeyLoginRequest, PasskeyRegisterOptions, PasskeyRegisterRequest,
+        PasskeyResponse,
+    };
+    use crate::internal::user::entity::user::UserAuthResponse;
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response};
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Options for navigator.credentials.create()", body = GeneralResponse<PasskeyRegisterOptions>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/passkey/register/begin")]
+    pub async fn passkey_register_begin(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.user_use_case.user_passkey_register_begin(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = PasskeyRegisterRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Passkey stored", body = GeneralResponse<PasskeyResponse>),
+            (status = 400, description = "Malformed response, wrong origin or relying party, or unknown challenge", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Passkey is already registered", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/passkey/register/finish")]
+    pub async fn passkey_register_finish(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let des: PasskeyRegisterRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_passkey_register_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_passkey_register_finish(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = PasskeyLoginBeginRequest,
+        responses(
+            (status = 200, description = "Options for navigator.credentials.get()", body = GeneralResponse<PasskeyLoginOptions>),
+            (status = 400, description = "Malformed request", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/passkey/login/begin")]
+    pub async fn passkey_login_begin(
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        // The body is optional; without a username the browser offers every
+        // discoverable passkey it holds for this site.
+        let des: PasskeyLoginBeginRequest = match body.trim().is_empty() {
+            true => {
+                PasskeyLoginBeginRequest::default()
+            }
+            false => {
+                match serde_json::from_str(&body) {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(_err) => {
+                        let res = ErrorResponseUseCase {
+                            status_code: StatusCode::BAD_REQUEST,
+                            error_msg: "Can't convert request".to_string(),
+                        };
+
+                        return send_error_response(res);
+                    }
+                }
+            }
+        };
+
+        return match use_cases.user_use_case.user_passkey_login_begin(des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = PasskeyLoginRequest,
+        responses(
+            (status = 200, description = "Access and refresh token", body = GeneralResponse<UserAuthResponse>),
+            (status = 400, description = "Malformed request", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Unknown credential or challenge, bad signature or counter", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/passkey/login/finish")]
+    pub async fn passkey_login_finish(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let des: PasskeyLoginRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Can't convert request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_passkey_login_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, None);
+
+        return match use_cases.user_use_case.user_passkey_login_finish(ctx, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
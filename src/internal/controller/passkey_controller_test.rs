@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_use_cases, TEST_ORIGIN, TEST_RP_ID};
+    use crate::internal::user::entity::passkey::user_handle;
+    use crate::internal::user::entity::soft_authenticator::new_soft_authenticator;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    // Sends a POST and returns the status and the JSON body.
+    macro_rules! post {
+        ($app:expr, $uri:expr, $token:expr, $body:expr) => {{
+            let mut req = test::TestRequest::post().uri($uri).set_json($body);
+            if let Some(token) = $token {
+                req = req.insert_header(("Authorization", token));
+            }
+            let res = test::call_service(&$app, req.to_request()).await;
+            let status = res.status();
+            let body: Value = test::read_body_json(res).await;
+            (status, body)
+        }};
+    }
+
+    macro_rules! challenge {
+        ($app:expr, $uri:expr, $token:expr, $body:expr) => {{
+            let (status, body) = post!($app, $uri, $token, $body);
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            body["data"]["challenge"].as_str().unwrap().to_string()
+        }};
+    }
+
+    const REGISTER_BEGIN: &str = "/api/v1/user/passkey/register/begin";
+    const REGISTER_FINISH: &str = "/api/v1/user/passkey/register/finish";
+    const LOGIN_BEGIN: &str = "/api/v1/user/passkey/login/begin";
+    const LOGIN_FINISH: &str = "/api/v1/user/passkey/login/finish";
+
+    #[actix_web::test]
+    async fn passkey_register_and_login_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mut authenticator = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+
+        let (status, options) = post!(app, REGISTER_BEGIN, Some(test_token()), json!({}));
+        assert_eq!(status, StatusCode::OK);
+        let options = &options["data"];
+        assert_eq!(options["rp"], json!({"id": "localhost", "name": "rust-clean"}));
+        assert_eq!(options["user"], json!({"id": user_handle(1), "name": "JamesHolland", "displayName": "James Holland"}));
+        assert_eq!(options["pubKeyCredParams"], json!([{"type": "public-key", "alg": -7}]));
+        assert_eq!(options["attestation"], json!("none"));
+        assert_eq!(options["excludeCredentials"], json!([]));
+
+        let mut credential = authenticator.register(options["challenge"].as_str().unwrap());
+        credential["name"] = json!("Laptop");
+        let (status, body) = post!(app, REGISTER_FINISH, Some(test_token()), &credential);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"], json!({"id": 1, "name": "Laptop"}));
+
+        // The challenge is spent, and the same credential can't be added twice.
+        let (status, body) = post!(app, REGISTER_FINISH, Some(test_token()), &credential);
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::BAD_REQUEST, json!("Unknown or expired challenge")));
+        let (status, options) = post!(app, REGISTER_BEGIN, Some(test_token()), json!({}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(options["data"]["excludeCredentials"][0]["id"], credential["rawId"]);
+        let (status, _) = post!(app, REGISTER_FINISH, Some(test_token()), authenticator.register(options["data"]["challenge"].as_str().unwrap()));
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, options) = post!(app, LOGIN_BEGIN, None::<String>, json!({"username": "JamesHolland"}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(options["data"]["rpId"], json!("localhost"));
+        assert_eq!(options["data"]["allowCredentials"][0]["id"], credential["rawId"]);
+
+        let assertion = authenticator.login(options["data"]["challenge"].as_str().unwrap(), Some(user_handle(1)));
+        let (status, body) = post!(app, LOGIN_FINISH, None::<String>, &assertion);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body["data"]["access_token"].is_string());
+        assert_eq!(repos.user.passkeys.lock().unwrap()[0].sign_count, 1);
+
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, &assertion);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Discoverable login: no username, no allow list.
+        let (status, options) = post!(app, LOGIN_BEGIN, None::<String>, json!({}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(options["data"]["allowCredentials"], json!([]));
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(options["data"]["challenge"].as_str().unwrap(), None));
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, options) = post!(app, LOGIN_BEGIN, None::<String>, json!({"username": "Nobody"}));
+        assert_eq!(options["data"]["allowCredentials"], json!([]));
+
+        // The replayed assertion fails on its challenge before the account is known.
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec!["user.passkey_register", "user.login_success", "user.login_success"]);
+        assert_eq!(entries[0].changes, json!({"passkey": {"before": null, "after": "Laptop"}}));
+        assert_eq!((entries[1].actor_id, entries[1].target_id), (Some(1), Some(1)));
+    }
+
+    #[actix_web::test]
+    async fn passkey_login_rejected_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mut authenticator = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+
+        let challenge = challenge!(app, REGISTER_BEGIN, Some(test_token()), json!({}));
+        let (status, _) = post!(app, REGISTER_FINISH, Some(test_token()), authenticator.register(&challenge));
+        assert_eq!(status, StatusCode::OK);
+
+        let mut phished = new_soft_authenticator(TEST_RP_ID, "https://login.evil.example");
+        phished.key = authenticator.key.clone();
+        phished.credential_id = authenticator.credential_id.clone();
+        let mut other_rp = new_soft_authenticator("evil.example", TEST_ORIGIN);
+        other_rp.key = authenticator.key.clone();
+        other_rp.credential_id = authenticator.credential_id.clone();
+        let mut forged = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+        forged.credential_id = authenticator.credential_id.clone();
+        let unknown = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+
+        let test_cases = vec! {
+            ("phished origin", phished.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None)),
+            ("other relying party", other_rp.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None)),
+            ("wrong key", forged.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None)),
+            ("wrong user handle", authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), Some(user_handle(2)))),
+            ("unknown credential", { let mut a = unknown; a.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None) }),
+            ("unknown challenge", authenticator.login("not-a-challenge", None)),
+        };
+        for (name, assertion) in test_cases {
+            let (status, _) = post!(app, LOGIN_FINISH, None::<String>, &assertion);
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", name);
+        }
+
+        // A counter that goes back points at a cloned authenticator.
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None));
+        assert_eq!(status, StatusCode::OK);
+        authenticator.sign_count = 1;
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // With TOTP enabled, only a user-verified assertion replaces it.
+        repos.user.users.lock().unwrap()[0].mfa_enabled = true;
+        authenticator.sign_count = 100;
+        authenticator.flags = 0x01;
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        authenticator.flags = 0x01 | 0x04;
+        let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None));
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn passkey_without_counter_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let mut authenticator = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+        authenticator.counter = false;
+
+        let challenge = challenge!(app, REGISTER_BEGIN, Some(test_token()), json!({}));
+        let (status, _) = post!(app, REGISTER_FINISH, Some(test_token()), authenticator.register(&challenge));
+        assert_eq!(status, StatusCode::OK);
+
+        for _ in 0..2 {
+            let (status, _) = post!(app, LOGIN_FINISH, None::<String>, authenticator.login(&challenge!(app, LOGIN_BEGIN, None::<String>, json!({})), None));
+            assert_eq!(status, StatusCode::OK);
+        }
+    }
+
+    #[actix_web::test]
+    async fn passkey_request_validation_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+        let authenticator = new_soft_authenticator(TEST_RP_ID, TEST_ORIGIN);
+
+        let mut wrong_type = authenticator.register("abc");
+        wrong_type["type"] = json!("password");
+        let mut long_name = authenticator.register("abc");
+        long_name["name"] = json!("x".repeat(65));
+
+        let test_cases = vec! {
+            (REGISTER_BEGIN, None, json!({}), StatusCode::UNAUTHORIZED),
+            (REGISTER_FINISH, None, authenticator.register("abc"), StatusCode::UNAUTHORIZED),
+            (REGISTER_FINISH, Some(test_token()), wrong_type, StatusCode::BAD_REQUEST),
+            (REGISTER_FINISH, Some(test_token()), long_name, StatusCode::BAD_REQUEST),
+            (REGISTER_FINISH, Some(test_token()), json!({"id": "x"}), StatusCode::BAD_REQUEST),
+            (LOGIN_FINISH, None, json!({"id": "x", "rawId": "x", "type": "public-key", "response": {}}), StatusCode::BAD_REQUEST),
+        };
+        for (uri, token, body, status) in test_cases {
+            let (res, body) = post!(app, uri, token, &body);
+            assert_eq!(res, status, "{} {}", uri, body);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// The caller's profile and avatar under /me. Avatars are served as PNG
+// thumbnails to anyone with a token, since they appear next to other users.
+pub mod profile_routes {
+    use actix_multipart::Multipart;
+    use actix_web::{HttpRequest, HttpResponse, Responder, web, delete, get, patch, put};
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, X_CONTENT_TYPE_OPTIONS};
+    use futures_util::StreamExt;
+    use crate::internal::user::entity::api_key::{SCOPE_USER_READ, SCOPE_USER_WRITE};
+    use crate::internal::user::entity::profile::{
+        verify_profile_update_request, AvatarUpload, ProfileResponse, ProfileUpdateRequest, AVATAR_CONTENT_TYPE,
+        AVATAR_UPLOAD_TYPES, AVATAR_FIELD,
+    };
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, scope_error, bad_request};
+
+    // The avatar field of the form, read up to max_size bytes so an oversized
+    // upload is refused without being buffered.
+    async fn read_avatar(mut form: Multipart, max_size: usize) -> Result<Vec<u8>, ErrorResponseUseCase> {
+        let mut avatar: Option<Vec<u8>> = None;
+
+        while let Some(field) = form.next().await {
+            let mut field = match field {
+                Ok(res) => {
+                    res
+                }
+                Err(_err) => {
+                    return Err(ErrorResponseUseCase {
+                        status_code: StatusCode::BAD_REQUEST,
+                        error_msg: "Invalid request".to_string(),
+                    });
+                }
+            };
+
+            if field.name() != Some(AVATAR_FIELD) || avatar.is_some() {
+                return Err(ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "the form must have a single avatar field".to_string(),
+                });
+            }
+
+            let declared = field.content_type().map(|mime| mime.essence_str().to_string()).unwrap_or_default();
+            if !AVATAR_UPLOAD_TYPES.contains(&declared.as_str()) {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    error_msg: "avatar must be a PNG, JPEG or WebP image".to_string(),
+                };
+
+                return Err(res);
+            }
+
+            let mut data = Vec::new();
+            while let Some(chunk) = field.next().await {
+                let chunk = match chunk {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(_err) => {
+                        return Err(ErrorResponseUseCase {
+                            status_code: StatusCode::BAD_REQUEST,
+                            error_msg: "Invalid request".to_string(),
+                        });
+                    }
+                };
+
+                if data.len() + chunk.len() > max_size {
+                    let res = ErrorResponseUseCase {
+                        status_code: StatusCode::PAYLOAD_TOO_LARGE,
+                        error_msg: format!("avatar must be at most {} bytes", max_size),
+                    };
+
+                    return Err(res);
+                }
+                data.extend_from_slice(&chunk);
+            }
+
+            avatar = Some(data);
+        }
+
+        match avatar {
+            Some(res) => {
+                Ok(res)
+            }
+            None => {
+                Err(ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "the form must have a single avatar field".to_string(),
+                })
+            }
+        }
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's profile; empty until first changed", body = GeneralResponse<ProfileResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/me/profile")]
+    pub async fn profile_get_me(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_READ).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        return match use_cases.user_use_case.user_profile_get(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = ProfileUpdateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's updated profile", body = GeneralResponse<ProfileResponse>),
+            (status = 400, description = "Malformed request, invalid field or attributes not allowed by the schema", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[patch("/api/v1/user/me/profile")]
+    pub async fn profile_update_me(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        let des: ProfileUpdateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let error_msg = match err.to_string().starts_with("unknown field") {
+                    true => "only phone, locale, timezone and attributes can be changed",
+                    false => "Invalid request",
+                };
+
+                return bad_request(error_msg.to_string());
+            }
+        };
+
+        if let Err(err) = verify_profile_update_request(&des) {
+            return bad_request(err);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_profile_update(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body(content = AvatarUpload, content_type = "multipart/form-data"),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's profile with the new avatar", body = GeneralResponse<ProfileResponse>),
+            (status = 400, description = "Malformed form or an image that can't be decoded", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+            (status = 413, description = "Avatar larger than profile.avatar_max_size", body = GeneralResponse<ErrorResponse>),
+            (status = 415, description = "Avatar is not a PNG, JPEG or WebP image", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[put("/api/v1/user/me/avatar")]
+    pub async fn avatar_set_me(
+        req: HttpRequest,
+        form: Multipart,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        let data = match read_avatar(form, use_cases.user_use_case.profile.avatar_max_size).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return send_error_response(err);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_avatar_set(ctx, token_result.username, data).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Avatar removed; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Caller has no avatar", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/me/avatar")]
+    pub async fn avatar_delete_me(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_avatar_delete(ctx, token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        params(
+            ("id" = i32, Path, description = "User id"),
+            ("size" = u32, Path, description = "Thumbnail size in pixels: 64, 256 or 512"),
+        ),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Square PNG thumbnail", content_type = "image/png", body = String),
+            (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+            (status = 400, description = "Unknown size", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User has no avatar", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/{id}/avatar/{size}")]
+    pub async fn avatar_get(
+        req: HttpRequest,
+        path: web::Path<(i32, u32)>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        if let Err(err) = middleware::is_authorized(&req, SCOPE_USER_READ).await {
+            return scope_error(err);
+        }
+
+        let (id, size) = path.into_inner();
+        let (version, data) = match use_cases.user_use_case.user_avatar_get(id, size).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return send_error_response(err);
+            }
+        };
+
+        // Revalidated on every use, so a replaced avatar shows up at once.
+        let etag = format!("\"{}\"", version);
+        let unchanged = req.headers().get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()) == Some(etag.as_str());
+        if unchanged {
+            return HttpResponse::NotModified()
+                .insert_header((ETAG, etag))
+                .insert_header((CACHE_CONTROL, "private, no-cache"))
+                .finish();
+        }
+
+        HttpResponse::Ok()
+            .content_type(AVATAR_CONTENT_TYPE)
+            .insert_header((ETAG, etag))
+            .insert_header((CACHE_CONTROL, "private, no-cache"))
+            .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .body(data)
+    }
+}
@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH, X_CONTENT_TYPE_OPTIONS};
+    use actix_web::test;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::audit::entity::audit::AUDIT_USER_UPDATE;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_use_cases, TEST_AVATAR_MAX_SIZE};
+    use crate::internal::user::entity::profile::AVATAR_SIZES;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const ME_PROFILE: &str = "/api/v1/user/me/profile";
+    const ME_AVATAR: &str = "/api/v1/user/me/avatar";
+    const BOUNDARY: &str = "test-boundary";
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    macro_rules! patch {
+        ($app:expr, $body:expr) => {{
+            call!($app, test::TestRequest::patch().uri(ME_PROFILE).insert_header(("Authorization", test_token())).set_json($body))
+        }};
+    }
+
+    // Uploads the parts as multipart/form-data.
+    macro_rules! upload {
+        ($app:expr, $parts:expr) => {{
+            let req = test::TestRequest::put().uri(ME_AVATAR)
+                .insert_header(("Authorization", test_token()))
+                .insert_header((CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY)))
+                .set_payload(form($parts));
+            call!($app, req)
+        }};
+    }
+
+    // (field name, content type, data) per part.
+    fn form(parts: &[(&str, &str, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, content_type, data) in parts {
+            body.extend_from_slice(format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"upload\"\r\nContent-Type: {}\r\n\r\n",
+                BOUNDARY, name, content_type,
+            ).as_bytes());
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        body
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height)).write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+        data
+    }
+
+    #[actix_web::test]
+    async fn profile_me_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::get().uri(ME_PROFILE).insert_header(("Authorization", test_token()));
+        let (status, body) = call!(app, req);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"], json!({
+            "phone": null, "locale": null, "timezone": null, "attributes": {}, "avatars": [], "update_ts": null,
+        }));
+
+        let (status, body) = patch!(app, json!({
+            "phone": "+4915112345678",
+            "locale": "de-DE",
+            "timezone": "US/Pacific",
+            "attributes": {"department": "R&D", "floor": 3},
+        }));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["phone"].clone(), body["data"]["timezone"].clone()), (json!("+4915112345678"), json!("US/Pacific")));
+        assert_eq!(body["data"]["attributes"], json!({"department": "R&D", "floor": 3}));
+        assert!(body["data"]["update_ts"].is_string());
+
+        // Left out fields are kept, null clears one or removes an attribute.
+        let (status, body) = patch!(app, json!({"phone": null, "attributes": {"floor": null}}));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!((body["data"]["phone"].clone(), body["data"]["locale"].clone()), (Value::Null, json!("de-DE")));
+        assert_eq!(body["data"]["attributes"], json!({"department": "R&D"}));
+
+        let records = repos.audit.entries.lock().unwrap().clone();
+        let record = records.iter().rev().find(|record| record.action == AUDIT_USER_UPDATE).unwrap();
+        assert_eq!((record.actor_id, record.target_id), (Some(1), Some(1)));
+        assert_eq!(record.changes, json!({
+            "phone": {"before": "+4915112345678", "after": null},
+            "attributes": {"before": {"department": "R&D", "floor": 3}, "after": {"department": "R&D"}},
+        }));
+
+        let test_cases = vec! {
+            (json!({}), "phone, locale, timezone or attributes is required"),
+            (json!({"username": "root"}), "only phone, locale, timezone and attributes can be changed"),
+            (json!({"phone": "0151 1234567"}), "phone must be in E.164 format, e.g. +4915112345678"),
+            (json!({"timezone": "Europe/Atlantis"}), "timezone must be an IANA time zone such as Europe/Berlin"),
+            (json!({"attributes": {"badge": "B-17"}}), "attributes: Additional properties are not allowed ('badge' was unexpected)"),
+            (json!({"attributes": {"floor": -1}}), "attributes/floor: -1 is less than the minimum of 0"),
+        };
+        for (input, output) in test_cases {
+            let (status, body) = patch!(app, input.clone());
+            assert_eq!((status, body["data"]["error_msg"].as_str()), (StatusCode::BAD_REQUEST, Some(output)), "{}", input);
+        }
+
+        let req = test::TestRequest::get().uri(ME_PROFILE);
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn avatar_me_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, body) = upload!(app, &[("avatar", "image/png", png(40, 30).as_slice())]);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let avatars = body["data"]["avatars"].as_array().unwrap().clone();
+        assert_eq!(avatars.len(), AVATAR_SIZES.len());
+        assert_eq!(repos.blobs.blobs.lock().unwrap().len(), AVATAR_SIZES.len());
+
+        let url = avatars[0]["url"].as_str().unwrap();
+        assert!(url.starts_with(&format!("/api/v1/user/1/avatar/{}?v=", AVATAR_SIZES[0])), "{}", url);
+        let req = test::TestRequest::get().uri(url).insert_header(("Authorization", test_token())).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "image/png");
+        assert_eq!(res.headers().get(X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        let etag = res.headers().get(ETAG).unwrap().to_str().unwrap().to_string();
+        let thumbnail = image::load_from_memory_with_format(&test::read_body(res).await, ImageFormat::Png).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (AVATAR_SIZES[0], AVATAR_SIZES[0]));
+
+        let req = test::TestRequest::get().uri(url).insert_header(("Authorization", test_token())).insert_header((IF_NONE_MATCH, etag.clone()));
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+        // A new upload replaces the thumbnails and the version.
+        let (status, body) = upload!(app, &[("avatar", "image/png", png(10, 10).as_slice())]);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_ne!(body["data"]["avatars"][0]["url"].as_str().unwrap(), url);
+        assert_eq!(repos.blobs.blobs.lock().unwrap().len(), AVATAR_SIZES.len());
+        let req = test::TestRequest::get().uri(url).insert_header(("Authorization", test_token())).insert_header((IF_NONE_MATCH, etag));
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::OK);
+
+        let test_cases = vec! {
+            ("/api/v1/user/1/avatar/100", StatusCode::BAD_REQUEST),
+            ("/api/v1/user/2/avatar/64", StatusCode::NOT_FOUND),
+        };
+        for (uri, output) in test_cases {
+            let (status, _) = call!(app, test::TestRequest::get().uri(uri).insert_header(("Authorization", test_token())));
+            assert_eq!(status, output, "{}", uri);
+        }
+
+        let (status, _) = call!(app, test::TestRequest::delete().uri(ME_AVATAR).insert_header(("Authorization", test_token())));
+        assert_eq!(status, StatusCode::OK);
+        assert!(repos.blobs.blobs.lock().unwrap().is_empty());
+        let (status, _) = call!(app, test::TestRequest::get().uri(url).insert_header(("Authorization", test_token())));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call!(app, test::TestRequest::delete().uri(ME_AVATAR).insert_header(("Authorization", test_token())));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let records = repos.audit.entries.lock().unwrap().clone();
+        let changes: Vec<Value> = records.iter().filter(|record| record.action == AUDIT_USER_UPDATE).map(|record| record.changes.clone()).collect();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0]["avatar"]["before"], Value::Null);
+        assert_eq!(changes[2]["avatar"]["after"], Value::Null);
+
+        // Deleting the account deletes its avatar.
+        let (status, _) = upload!(app, &[("avatar", "image/png", png(10, 10).as_slice())]);
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call!(app, test::TestRequest::delete().uri("/api/v1/user/1/delete").insert_header(("Authorization", test_token())));
+        assert_eq!(status, StatusCode::OK);
+        assert!(repos.blobs.blobs.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn avatar_upload_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let image = png(8, 8);
+        let mut truncated = image.clone();
+        truncated.truncate(image.len() / 2);
+        let oversized = vec![0u8; TEST_AVATAR_MAX_SIZE + 1];
+
+        let test_cases = vec! {
+            (vec![("avatar", "image/gif", image.as_slice())], StatusCode::UNSUPPORTED_MEDIA_TYPE),
+            (vec![("avatar", "image/png", b"<svg/>".as_slice())], StatusCode::UNSUPPORTED_MEDIA_TYPE),
+            (vec![("avatar", "image/png", truncated.as_slice())], StatusCode::BAD_REQUEST),
+            (vec![("avatar", "image/png", oversized.as_slice())], StatusCode::PAYLOAD_TOO_LARGE),
+            (vec![("picture", "image/png", image.as_slice())], StatusCode::BAD_REQUEST),
+            (vec![("avatar", "image/png", image.as_slice()), ("avatar", "image/png", image.as_slice())], StatusCode::BAD_REQUEST),
+            (vec![], StatusCode::BAD_REQUEST),
+        };
+        for (parts, output) in test_cases {
+            let (status, body) = upload!(app, &parts);
+            assert_eq!(status, output, "{}", body);
+            assert_eq!(body["data"]["status_code"], output.as_u16(), "{}", body);
+            assert_ne!(body["data"]["error_msg"], "internal server error");
+        }
+
+        let req = test::TestRequest::put().uri(ME_AVATAR).insert_header(("Authorization", test_token())).set_payload(image.clone());
+        let (status, _) = call!(app, req);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // A failing store leaves neither thumbnails nor a profile behind.
+        *repos.blobs.broken.lock().unwrap() = true;
+        let (status, _) = upload!(app, &[("avatar", "image/png", image.as_slice())]);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(repos.user.profiles.lock().unwrap().is_empty());
+    }
+}
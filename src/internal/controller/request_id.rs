@@ -0,0 +1,42 @@
+use std::future::Future;
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::HeaderName;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// Returns the caller's X-Request-Id when it is safe to echo and log, or a new
+// UUID otherwise.
+pub fn request_id(req: &ServiceRequest) -> String {
+    let header = req.headers().get(REQUEST_ID_HEADER).and_then(|res| res.to_str().ok());
+
+    match header {
+        Some(id) if is_valid(id) => {
+            id.to_string()
+        }
+        _ => {
+            Uuid::new_v4().to_string()
+        }
+    }
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+// Runs a request with its id available to current_request_id.
+pub async fn with_request_id<F: Future>(id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(id, f).await
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
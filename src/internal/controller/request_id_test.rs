@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+    use tracing::instrument::WithSubscriber;
+    use uuid::Uuid;
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::request_id::REQUEST_ID_HEADER;
+    use crate::internal::controller::test_app::{test_token, test_use_cases};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    #[actix_web::test]
+    async fn request_id_echo_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::get().uri("/healthz").insert_header((REQUEST_ID_HEADER, "gw-1234")).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "gw-1234");
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+        let generated = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+
+        // Ids that are too long or would break a log line are replaced.
+        for bad in ["a".repeat(129), "with space".to_string(), "quote\"".to_string()] {
+            let req = test::TestRequest::get().uri("/healthz").insert_header((REQUEST_ID_HEADER, bad.as_str())).to_request();
+            let res = test::call_service(&app, req).await;
+            let id = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+            assert!(Uuid::parse_str(id).is_ok(), "{}", bad);
+        }
+    }
+
+    #[actix_web::test]
+    async fn request_id_in_error_body_test() {
+        let (use_cases, _repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/user/2/get")
+            .insert_header(("Authorization", test_token()))
+            .insert_header((REQUEST_ID_HEADER, "gw-404"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["request_id"], json!("gw-404"));
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/user/1/get")
+            .insert_header(("Authorization", test_token()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert!(body["data"].get("request_id").is_none());
+    }
+
+    #[actix_web::test]
+    async fn request_logs_test() {
+        let (use_cases, repos) = test_use_cases();
+        let dispatch = use_cases.logger.dispatch().clone();
+        *repos.user.broken.lock().unwrap() = true;
+
+        async {
+            let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+            let req = test::TestRequest::get().uri("/api/v1/user/list").insert_header((REQUEST_ID_HEADER, "gw-500")).to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["data"]["request_id"], json!("gw-500"));
+        }.with_subscriber(dispatch).await;
+
+        let lines = repos.logs.lines();
+
+        let failure = lines.iter().find(|l| l["message"] == json!("repo.user_list failed")).unwrap();
+        assert_eq!(failure["level"], json!("ERROR"));
+        assert!(failure["error"].as_str().unwrap().contains("pool timed out"));
+        assert_eq!(failure["spans"][0]["name"], json!("request"));
+        assert_eq!(failure["spans"][0]["request_id"], json!("gw-500"));
+        assert_eq!(failure["spans"][1]["name"], json!("user_use_case.user_list"));
+
+        let access = lines.iter().find(|l| l["message"] == json!("request completed")).unwrap();
+        assert_eq!(access["status"], json!(500));
+        assert_eq!(access["spans"][0]["path"], json!("/api/v1/user/list"));
+    }
+}
@@ -2,19 +2,26 @@ use actix_web::http::StatusCode;
 use actix_web::HttpResponse;
 use serde::{Serialize, Deserialize};
 use actix_web::http::header::ContentType;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::internal::controller::middleware::AccessTokenError;
+use crate::internal::controller::request_id::current_request_id;
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct GeneralResponse<T> {
     pub success: bool,
     pub data: T,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct ErrorResponse {
     pub status_code: i32,
     pub error_msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
+#[derive(Debug)]
 pub struct ErrorResponseUseCase {
     pub error_msg: String,
     pub status_code: StatusCode,
@@ -31,12 +38,27 @@ pub fn convert_status_code_to_i32(code: StatusCode) -> i32 {
         StatusCode::UNAUTHORIZED => {
             401
         },
+        StatusCode::FORBIDDEN => {
+            403
+        },
         StatusCode::NOT_FOUND => {
             404
         },
         StatusCode::CONFLICT => {
             409
         },
+        StatusCode::PAYLOAD_TOO_LARGE => {
+            413
+        },
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => {
+            415
+        },
+        StatusCode::BAD_GATEWAY => {
+            502
+        },
+        StatusCode::SERVICE_UNAVAILABLE => {
+            503
+        },
         _ => {
             500
         }
@@ -50,28 +72,45 @@ pub fn send_error_response(err: ErrorResponseUseCase) -> HttpResponse {
         data: ErrorResponse {
             status_code: convert_status_code_to_i32(err.status_code),
             error_msg: err.error_msg,
+            request_id: current_request_id(),
         }
     };
 
-    return match err.status_code {
+    match err.status_code {
         StatusCode::BAD_REQUEST => {
             HttpResponse::BadRequest().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
         },
         StatusCode::UNAUTHORIZED => {
             HttpResponse::Unauthorized().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
         }
+        StatusCode::FORBIDDEN => {
+            HttpResponse::Forbidden().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+        }
         StatusCode::NOT_FOUND => {
             HttpResponse::NotFound().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
         },
         StatusCode::CONFLICT => {
             HttpResponse::Conflict().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
         }
+        StatusCode::PAYLOAD_TOO_LARGE => {
+            HttpResponse::PayloadTooLarge().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+        }
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => {
+            HttpResponse::UnsupportedMediaType().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+        }
+        StatusCode::BAD_GATEWAY => {
+            HttpResponse::BadGateway().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+        }
+        StatusCode::SERVICE_UNAVAILABLE => {
+            HttpResponse::ServiceUnavailable().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+        }
         _ => {
             let response: GeneralResponse<ErrorResponse> = GeneralResponse {
                 success: false,
                 data: ErrorResponse {
                     status_code: 500,
                     error_msg: "internal server error".to_string(),
+                    request_id: current_request_id(),
                 }
             };
             HttpResponse::InternalServerError().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
@@ -79,6 +118,68 @@ pub fn send_error_response(err: ErrorResponseUseCase) -> HttpResponse {
     }
 }
 
+pub fn unauthorized() -> HttpResponse {
+    send_error_response(ErrorResponseUseCase {
+        status_code: StatusCode::UNAUTHORIZED,
+        error_msg: "Unauthorized".to_string(),
+    })
+}
+
+pub fn bad_request(error_msg: String) -> HttpResponse {
+    send_error_response(ErrorResponseUseCase {
+        status_code: StatusCode::BAD_REQUEST,
+        error_msg,
+    })
+}
+
+// For admin-only routes: a valid token of a non-admin is refused with 403,
+// anything else that fails authentication with 401.
+pub fn admin_error(err: AccessTokenError) -> HttpResponse {
+    match err {
+        AccessTokenError::NotAdmin => {
+            send_error_response(ErrorResponseUseCase {
+                status_code: StatusCode::FORBIDDEN,
+                error_msg: "Forbidden".to_string(),
+            })
+        }
+        _ => {
+            unauthorized()
+        }
+    }
+}
+
+// For scoped routes: an API key without the scope a route needs is refused
+// with 403, anything else that fails authentication with 401.
+pub fn scope_error(err: AccessTokenError) -> HttpResponse {
+    match err {
+        AccessTokenError::InsufficientScope => {
+            send_error_response(ErrorResponseUseCase {
+                status_code: StatusCode::FORBIDDEN,
+                error_msg: "Forbidden".to_string(),
+            })
+        }
+        _ => {
+            unauthorized()
+        }
+    }
+}
+
+// The body send_error_response would answer with, for errors reported inside
+// a successful response (e.g. the items of a batch).
+pub fn to_error_response(err: ErrorResponseUseCase) -> ErrorResponse {
+    let status_code = convert_status_code_to_i32(err.status_code);
+    let error_msg = match status_code {
+        500 => "internal server error".to_string(),
+        _ => err.error_msg,
+    };
+
+    ErrorResponse {
+        status_code,
+        error_msg,
+        request_id: current_request_id(),
+    }
+}
+
 pub fn send_success_response<T: Serialize>(data: T) -> HttpResponse {
     let response: GeneralResponse<T> = GeneralResponse {
         success: true,
@@ -87,3 +188,14 @@ pub fn send_success_response<T: Serialize>(data: T) -> HttpResponse {
 
     HttpResponse::Ok().content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
 }
+
+// Like send_success_response, for payloads that are meaningful on failure too
+// (e.g. the health report): success and the status code come from the caller.
+pub fn send_status_response<T: Serialize>(status_code: StatusCode, data: T) -> HttpResponse {
+    let response: GeneralResponse<T> = GeneralResponse {
+        success: status_code.is_success(),
+        data,
+    };
+
+    HttpResponse::build(status_code).content_type(ContentType::json()).body(serde_json::to_string(&response).unwrap())
+}
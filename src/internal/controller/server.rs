@@ -0,0 +1,82 @@
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::dev::Server;
+use actix_web::http::KeepAlive;
+use actix_web::HttpServer;
+
+use crate::UseCases;
+use crate::config::config::{Config, Listener};
+use crate::internal::controller::app::new_app;
+use crate::pkg::shutdown::shutdown::Shutdown;
+use crate::pkg::tls::cert_resolver::{new_cert_resolver, new_tls_server_config, spawn_cert_reloader};
+
+// Binds every configured listener and applies the server tuning from Config.
+pub fn new_http_server(use_cases: UseCases, cfg: &Config, shutdown: Shutdown) -> io::Result<Server> {
+    let http_cfg = cfg.http.clone();
+    let app_shutdown = shutdown.clone();
+    let mut server = HttpServer::new(move || new_app(use_cases.clone(), &http_cfg, app_shutdown.clone()))
+        // signals are handled by run_until_shutdown
+        .disable_signals()
+        .shutdown_timeout(cfg.shutdown.drain_timeout)
+        .backlog(cfg.http.backlog)
+        .keep_alive(match cfg.http.keep_alive {
+            0 => KeepAlive::Disabled,
+            sec => KeepAlive::Timeout(Duration::from_secs(sec)),
+        });
+
+    if cfg.http.workers > 0 {
+        server = server.workers(cfg.http.workers);
+    }
+
+    let listeners = cfg.http.listeners(&cfg.tls);
+    let tls_config = if listeners.iter().any(|l| matches!(l, Listener::Tls(_))) {
+        let resolver = new_cert_resolver(&cfg.tls.cert_path, &cfg.tls.key_path)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let resolver = Arc::new(resolver);
+
+        if cfg.tls.reload_interval > 0 {
+            spawn_cert_reloader(resolver.clone(), Duration::from_secs(cfg.tls.reload_interval), &shutdown);
+        }
+
+        Some(new_tls_server_config(resolver))
+    } else {
+        None
+    };
+
+    for listener in listeners {
+        tracing::info!(listener = ?listener, "listening");
+
+        server = match listener {
+            Listener::Tcp(address) => {
+                server.bind(address)?
+            }
+            Listener::Tls(address) => {
+                server.bind_rustls(address, tls_config.clone().unwrap())?
+            }
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                remove_stale_socket(&path)?;
+                server.bind_uds(path)?
+            }
+            #[cfg(not(unix))]
+            Listener::Unix(path) => {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, format!("unix socket {} is not supported", path)));
+            }
+        };
+    }
+
+    Ok(server.run())
+}
+
+// A socket file left behind by a previous run would make bind fail.
+fn remove_stale_socket(path: &str) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err)
+        }
+        _ => {
+            Ok(())
+        }
+    }
+}
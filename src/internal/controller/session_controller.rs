@@ -0,0 +1,167 @@
+// Login sessions: the caller's own under /me, and any user's for admins.
+// Revoking a session signs out the access token issued with it.
+pub mod session_routes {
+    use actix_web::{HttpRequest, Responder, web, delete, get};
+    use actix_web::http::StatusCode;
+    use crate::internal::user::entity::session::SessionResponse;
+    use crate::internal::user::usecase::traits::UseCase;
+    use crate::internal::controller::middleware;
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, admin_error};
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's active sessions; current marks this token's", body = GeneralResponse<Vec<SessionResponse>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/me/sessions")]
+    pub async fn session_list_me(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.user_use_case.user_session_list(token_result.user_id, token_result.session_id).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        params(("id" = i32, Path, description = "Session id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Session revoked; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "Caller has no such session", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/me/sessions/{id}")]
+    pub async fn session_revoke_me(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_unauthorized(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_session_revoke(ctx, token_result.user_id, id.into_inner()).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i32, Path, description = "User id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The user's active sessions", body = GeneralResponse<Vec<SessionResponse>>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/{id}/sessions")]
+    pub async fn session_list(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        return match use_cases.user_use_case.user_session_list(id.into_inner(), token_result.session_id).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(
+            ("id" = i32, Path, description = "User id"),
+            ("session_id" = i32, Path, description = "Session id"),
+        ),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Session revoked; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User or session not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/{id}/sessions/{session_id}")]
+    pub async fn session_revoke(
+        req: HttpRequest,
+        path: web::Path<(i32, i32)>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return admin_error(err);
+            }
+        };
+
+        let (id, session_id) = path.into_inner();
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_session_revoke(ctx, id, session_id).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_token_config, test_use_cases};
+    use crate::internal::user::entity::token::{generate_access_token, generate_session_access_token};
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const ME_SESSIONS: &str = "/api/v1/user/me/sessions";
+
+    // Sends the request and returns the status and the JSON body.
+    macro_rules! call {
+        ($app:expr, $req:expr) => {{
+            let res = test::call_service(&$app, $req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }};
+    }
+
+    // Logs James in from the given device and returns the bearer header.
+    macro_rules! login {
+        ($app:expr, $user_agent:expr, $ip:expr) => {{
+            let req = test::TestRequest::post().uri("/api/v1/user/auth")
+                .insert_header(("User-Agent", $user_agent))
+                .peer_addr(format!("{}:40000", $ip).parse().unwrap())
+                .set_json(json!({"username": "JamesHolland", "password": "james123"}));
+            let (status, body) = call!($app, req);
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            format!("Bearer {}", body["data"]["access_token"].as_str().unwrap())
+        }};
+    }
+
+    macro_rules! get {
+        ($app:expr, $uri:expr, $token:expr) => {{
+            call!($app, test::TestRequest::get().uri($uri).insert_header(("Authorization", $token)))
+        }};
+    }
+
+    macro_rules! delete {
+        ($app:expr, $uri:expr, $token:expr) => {{
+            call!($app, test::TestRequest::delete().uri($uri).insert_header(("Authorization", $token)))
+        }};
+    }
+
+    #[actix_web::test]
+    async fn session_me_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let laptop = login!(app, "Firefox", "10.0.0.1");
+        let phone = login!(app, "Safari", "10.0.0.2");
+
+        let (status, body) = get!(app, ME_SESSIONS, laptop.clone());
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let sessions = body["data"].as_array().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!((sessions[0]["user_agent"].clone(), sessions[0]["ip"].clone(), sessions[0]["current"].clone()), (json!("Firefox"), json!("10.0.0.1"), json!(true)));
+        assert_eq!((sessions[1]["user_agent"].clone(), sessions[1]["current"].clone()), (json!("Safari"), json!(false)));
+        assert!(sessions[0].get("refresh_token_hash").is_none());
+        let phone_id = sessions[1]["id"].as_i64().unwrap();
+        let stored = repos.user.sessions.lock().unwrap().clone();
+        assert!(stored.iter().all(|session| session.refresh_token_hash.len() == 64));
+
+        // Revoking a session ends its token at once; other sessions go on.
+        let (status, _) = delete!(app, &format!("{}/{}", ME_SESSIONS, phone_id), laptop.clone());
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, ME_SESSIONS, phone.clone());
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get!(app, "/api/v1/user/1/get", phone);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = get!(app, ME_SESSIONS, laptop.clone());
+        assert_eq!((status, body["data"].as_array().unwrap().len()), (StatusCode::OK, 1));
+
+        let (status, body) = delete!(app, &format!("{}/{}", ME_SESSIONS, phone_id), laptop.clone());
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("Session not found")));
+
+        // Signing out the current session.
+        let laptop_id = repos.user.sessions.lock().unwrap()[0].id;
+        let (status, _) = delete!(app, &format!("{}/{}", ME_SESSIONS, laptop_id), laptop.clone());
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, ME_SESSIONS, laptop);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = call!(app, test::TestRequest::get().uri(ME_SESSIONS));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn session_admin_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, _) = call!(app, test::TestRequest::post().uri("/api/v1/user/create").insert_header(("Authorization", test_token()))
+            .set_json(json!({"username": "JohnDoe", "password": "john12345", "firstname": "John", "lastname": "Doe"})));
+        assert_eq!(status, StatusCode::OK);
+        let john = format!("Bearer {}", generate_access_token(&test_token_config(), &"JohnDoe".to_string()));
+        let laptop = login!(app, "Firefox", "10.0.0.1");
+
+        let (status, _) = get!(app, "/api/v1/user/1/sessions", john.clone());
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = delete!(app, "/api/v1/user/1/sessions/1", john);
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = get!(app, "/api/v1/user/1/sessions", test_token());
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        let session_id = body["data"][0]["id"].as_i64().unwrap();
+        let (status, body) = get!(app, "/api/v1/user/2/sessions", test_token());
+        assert_eq!((status, body["data"].as_array().unwrap().len()), (StatusCode::OK, 0));
+        let (status, body) = get!(app, "/api/v1/user/99/sessions", test_token());
+        assert_eq!((status, body["data"]["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("User with id=99 not found")));
+
+        // The session belongs to user 1, not user 2.
+        let (status, _) = delete!(app, &format!("/api/v1/user/2/sessions/{}", session_id), test_token());
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = delete!(app, &format!("/api/v1/user/1/sessions/{}", session_id), test_token());
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, "/api/v1/user/1/get", laptop);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let revoke = entries.iter().find(|e| e.action == "user.session_revoke").unwrap();
+        assert_eq!((revoke.actor_id, revoke.target_id, revoke.target_username.as_deref()), (Some(1), Some(1), Some("JamesHolland")));
+        assert_eq!(revoke.changes["user_agent"]["before"], json!("Firefox"));
+    }
+
+    #[actix_web::test]
+    async fn session_owner_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let (status, _) = call!(app, test::TestRequest::post().uri("/api/v1/user/create").insert_header(("Authorization", test_token()))
+            .set_json(json!({"username": "JohnDoe", "password": "john12345", "firstname": "John", "lastname": "Doe"})));
+        assert_eq!(status, StatusCode::OK);
+        let laptop = login!(app, "Firefox", "10.0.0.1");
+        let session_id = repos.user.sessions.lock().unwrap()[0].id;
+
+        // A token can't borrow another account's session.
+        let forged = format!("Bearer {}", generate_session_access_token(&test_token_config(), 2, &"JohnDoe".to_string(), session_id, ""));
+        let (status, _) = get!(app, ME_SESSIONS, forged);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get!(app, ME_SESSIONS, laptop.clone());
+        assert_eq!(status, StatusCode::OK);
+
+        // The token follows its account through a rename, not the name.
+        let (status, _) = call!(app, test::TestRequest::put().uri("/api/v1/user/update").insert_header(("Authorization", laptop.clone()))
+            .set_json(json!({"id": 1, "username": "JamesH", "firstname": "James", "lastname": "Holland"})));
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call!(app, test::TestRequest::post().uri("/api/v1/user/create").insert_header(("Authorization", laptop.clone()))
+            .set_json(json!({"username": "JamesHolland", "password": "james12345", "firstname": "James", "lastname": "Holland"})));
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get!(app, "/api/v1/user/me", laptop.clone());
+        assert_eq!((status, body["data"]["id"].clone(), body["data"]["username"].clone()), (StatusCode::OK, json!(1), json!("JamesH")));
+
+        // Nor does a token outlive its account.
+        let (status, body) = call!(app, test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_json(json!({"username": "JohnDoe", "password": "john12345"})));
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let john = format!("Bearer {}", body["data"]["access_token"].as_str().unwrap());
+        let (status, _) = delete!(app, "/api/v1/user/2/delete", laptop.clone());
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get!(app, "/api/v1/user/me", john);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::Duration;
+use bcrypt::hash;
+use chrono::{TimeZone, Utc};
+
+use crate::UseCases;
+use crate::internal::health::usecase::repo::memory_repo::{new_memory_health_repo, MemoryHealthRepo};
+use crate::internal::health::usecase::traits::new_health_use_case;
+use crate::internal::audit::usecase::repo::memory_repo::{new_memory_audit_repo, MemoryAuditRepo};
+use crate::internal::audit::usecase::traits::new_audit_use_case;
+use crate::internal::oidc::entity::oidc::new_oidc_config;
+use crate::internal::oidc::usecase::repo::memory_repo::{new_memory_oidc_repo, MemoryOidcRepo};
+use crate::internal::oidc::usecase::traits::new_oidc_use_case;
+use crate::internal::identity::usecase::repo::memory_repo::{new_memory_identity_repo, MemoryIdentityRepo};
+use crate::internal::identity::usecase::traits::new_identity_use_case;
+use crate::internal::identity::usecase::webapi::web_api::{new_issuer_web_api, ISSUER_REQUEST_TIMEOUT};
+use crate::internal::org::usecase::repo::memory_repo::{new_memory_org_repo, MemoryOrgRepo};
+use crate::internal::org::usecase::traits::new_org_use_case;
+use crate::internal::job::usecase::handlers::new_job_handlers;
+use crate::internal::job::usecase::repo::memory_repo::{new_memory_job_repo, MemoryJobRepo};
+use crate::internal::job::usecase::traits::new_job_use_case;
+use crate::config::config::Config;
+use crate::internal::user::entity::token::{generate_access_token, new_token_config, TokenConfig};
+use crate::internal::user::usecase::repo::memory_repo::{new_memory_user_repo, MemoryUser, MemoryUserRepo};
+use crate::internal::user::entity::passkey::new_webauthn_config;
+use crate::internal::user::entity::profile::new_profile_config;
+use crate::internal::user::usecase::blob::memory_blob_store::{new_memory_blob_store, MemoryBlobStore};
+use crate::internal::user::usecase::traits::new_user_use_case;
+use crate::pkg::logger::logger::new_logger;
+use crate::pkg::logger::test_writer::{new_test_writer, TestWriter};
+use crate::pkg::metrics::metrics::new_metrics;
+use crate::pkg::telemetry::telemetry::{new_json_span_exporter, Telemetry};
+use crate::pkg::shutdown::shutdown::{new_shutdown, Shutdown};
+
+// Shared fixtures for the HTTP tests: use cases over in-memory repos, seeded
+// with user 1 (JamesHolland / james123), and tokens minted with a test key.
+
+pub const TEST_SECRET_KEY: &str = "test-secret-key";
+pub const TEST_MIGRATION_VERSION: i64 = 1;
+pub const TEST_ADMIN: i32 = 1; // JamesHolland
+pub const TEST_RP_ID: &str = "localhost";
+pub const TEST_ORIGIN: &str = "http://localhost:8081";
+pub const TEST_ISSUER: &str = "http://localhost:8081";
+pub const TEST_AVATAR_MAX_SIZE: usize = 64 * 1024;
+// department and floor are the only profile attributes the tests may set.
+pub const TEST_ATTRIBUTES_SCHEMA: &str = r#"{
+    "type": "object",
+    "properties": {
+        "department": {"type": "string", "maxLength": 32},
+        "floor": {"type": "integer", "minimum": 0}
+    },
+    "additionalProperties": false
+}"#;
+
+pub struct TestRepos {
+    pub user: Arc<MemoryUserRepo>,
+    pub health: Arc<MemoryHealthRepo>,
+    pub audit: Arc<MemoryAuditRepo>,
+    pub oidc: Arc<MemoryOidcRepo>,
+    pub identity: Arc<MemoryIdentityRepo>,
+    pub org: Arc<MemoryOrgRepo>,
+    pub job: Arc<MemoryJobRepo>,
+    pub blobs: Arc<MemoryBlobStore>,
+    pub logs: TestWriter,
+    pub spans: TestWriter, // one JSON object per finished span
+}
+
+pub fn test_token_config() -> TokenConfig {
+    new_token_config(TEST_SECRET_KEY.to_string(), 5)
+}
+
+pub fn test_token() -> String {
+    format!("Bearer {}", generate_access_token(&test_token_config(), &"JamesHolland".to_string()))
+}
+
+// Adds JohnSmith, an account that is not an admin, and returns a token for it.
+pub fn test_not_admin_token(repos: &TestRepos) -> String {
+    repos.user.users.lock().unwrap().push(MemoryUser {
+        id: 2,
+        username: "JohnSmith".to_string(),
+        password: hash("john123", 4).unwrap(),
+        firstname: "John".to_string(),
+        lastname: "Smith".to_string(),
+        ..Default::default()
+    });
+
+    format!("Bearer {}", generate_access_token(&test_token_config(), &"JohnSmith".to_string()))
+}
+
+pub fn test_use_cases() -> (UseCases, TestRepos) {
+    test_use_cases_with(new_shutdown())
+}
+
+pub fn test_use_cases_with(shutdown: Shutdown) -> (UseCases, TestRepos) {
+    let user = Arc::new(new_memory_user_repo());
+    user.users.lock().unwrap().push(MemoryUser {
+        id: 1,
+        username: "JamesHolland".to_string(),
+        password: hash("james123", 4).unwrap(),
+        firstname: "James".to_string(),
+        lastname: "Holland".to_string(),
+        create_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+        update_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+        ..Default::default()
+    });
+
+    let health = Arc::new(new_memory_health_repo(TEST_MIGRATION_VERSION));
+
+    let audit = Arc::new(new_memory_audit_repo());
+    let audit_use_case = new_audit_use_case(audit.clone());
+
+    let metrics = new_metrics(100);
+    let blobs = Arc::new(new_memory_blob_store());
+    let org = Arc::new(new_memory_org_repo(user.clone()));
+    let oidc = Arc::new(new_memory_oidc_repo());
+    let user_use_case = new_user_use_case(
+        user.clone(),
+        audit_use_case.clone(),
+        test_token_config(),
+        metrics.clone(),
+        vec![TEST_ADMIN],
+        "rust-clean".to_string(),
+        new_webauthn_config(TEST_RP_ID.to_string(), "rust-clean".to_string(), TEST_ORIGIN.to_string()),
+        new_profile_config(TEST_ATTRIBUTES_SCHEMA, TEST_AVATAR_MAX_SIZE).unwrap(),
+        blobs.clone(),
+        org.clone(),
+        oidc.clone(),
+    );
+
+    let oidc_use_case = new_oidc_use_case(
+        oidc.clone(),
+        user_use_case.clone(),
+        audit_use_case.clone(),
+        new_oidc_config(TEST_ISSUER.to_string(), TEST_SECRET_KEY, 5),
+    );
+
+    // Keys come over HTTP from a mock issuer the test starts.
+    let identity = Arc::new(new_memory_identity_repo());
+    let identity_use_case = new_identity_use_case(
+        identity.clone(),
+        Arc::new(new_issuer_web_api(ISSUER_REQUEST_TIMEOUT)),
+        user_use_case.clone(),
+        audit_use_case.clone(),
+    );
+
+    let org_use_case = new_org_use_case(org.clone(), user_use_case.clone(), audit_use_case.clone());
+
+    let jobs = Config::default().jobs;
+    let job = Arc::new(new_memory_job_repo());
+    let job_use_case = new_job_use_case(
+        job.clone(),
+        new_job_handlers(&jobs, job.clone(), user_use_case.clone()),
+        jobs.max_attempts,
+        Duration::from_secs(jobs.lock_timeout),
+    );
+
+    let logs = new_test_writer();
+    let spans = new_test_writer();
+    let telemetry = Telemetry::with_exporter(new_json_span_exporter(spans.clone()));
+    let use_cases = UseCases {
+        user_use_case,
+        health_use_case: new_health_use_case(health.clone(), shutdown, Duration::from_millis(200), TEST_MIGRATION_VERSION),
+        audit_use_case,
+        oidc_use_case,
+        identity_use_case,
+        org_use_case,
+        job_use_case,
+        metrics,
+        logger: new_logger("info", logs.clone(), telemetry.tracer()).unwrap(),
+    };
+
+    (use_cases, TestRepos { user, health, audit, oidc, identity, org, job, blobs, logs, spans })
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+    use tracing::instrument::WithSubscriber;
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::test_use_cases;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    #[actix_web::test]
+    async fn traceparent_test() {
+        let (use_cases, repos) = test_use_cases();
+        let dispatch = use_cases.logger.dispatch().clone();
+
+        async {
+            let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+            let req = test::TestRequest::post()
+                .uri("/api/v1/user/auth")
+                .insert_header(("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID)))
+                .set_json(json!({"username": "JamesHolland", "password": "james123"}))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }.with_subscriber(dispatch).await;
+
+        let spans = repos.spans.lines();
+        let find = |name: &str| -> Value {
+            spans.iter().find(|s| s["name"] == json!(name)).cloned().unwrap()
+        };
+
+        let request = find("POST /api/v1/user/auth");
+        assert_eq!(request["trace_id"], json!(TRACE_ID));
+        assert_eq!(request["parent_span_id"], json!(PARENT_SPAN_ID));
+        assert_eq!(request["kind"], json!("server"));
+        assert_eq!(request["attributes"]["http.response.status_code"], json!("200"));
+
+        let use_case = find("user_use_case.user_auth");
+        assert_eq!(use_case["trace_id"], json!(TRACE_ID));
+        assert_eq!(use_case["parent_span_id"], request["span_id"]);
+        assert_eq!(use_case["attributes"]["username"], json!("JamesHolland"));
+
+        // Log lines carry the trace id so they can be joined with the trace.
+        let access = repos.logs.lines().into_iter().find(|l| l["message"] == json!("request completed")).unwrap();
+        assert_eq!(access["spans"][0]["trace_id"], json!(TRACE_ID));
+    }
+
+    #[actix_web::test]
+    async fn server_error_status_test() {
+        let (use_cases, repos) = test_use_cases();
+        let dispatch = use_cases.logger.dispatch().clone();
+        *repos.user.broken.lock().unwrap() = true;
+
+        async {
+            let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+            let req = test::TestRequest::get().uri("/api/v1/user/list").to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }.with_subscriber(dispatch).await;
+
+        let spans = repos.spans.lines();
+        let request = spans.iter().find(|s| s["name"] == json!("GET /api/v1/user/list")).unwrap();
+        assert_eq!(request["status"], json!("error"));
+        assert_ne!(request["parent_span_id"], json!(PARENT_SPAN_ID));
+    }
+}
@@ -1,14 +1,31 @@
 pub mod user_routes {
-    use actix_web::{Responder, web, post, get, put, HttpRequest};
+    use actix_web::{Responder, web, post, get, put, patch, delete, HttpRequest};
     use actix_web::http::StatusCode;
     use crate::internal::user::entity::user;
     use crate::internal::user::usecase::traits::UseCase;
-    use crate::internal::controller::response::{ErrorResponseUseCase, send_error_response, send_success_response};
+    use crate::internal::controller::response::{ErrorResponse, ErrorResponseUseCase, GeneralResponse, send_error_response, send_success_response, scope_error};
     use crate::internal::controller::middleware;
-    use crate::internal::user::entity::user::{verify_user_auth_request, verify_user_create_request};
+    use crate::internal::controller::middleware::AccessTokenError;
+    use crate::internal::user::entity::api_key::{SCOPE_USER_READ, SCOPE_USER_WRITE};
+    use crate::internal::user::entity::user::{
+        parse_user_status, verify_user_auth_request, verify_user_create_request, verify_user_me_update_request,
+        verify_user_status_request, UserAuthRequest, UserAuthResult, UserChangePasswordRequest, UserCreateRequest,
+        UserGet, UserGetResponse, UserListQuery, UserMeUpdateRequest, UserStatusRequest, UserUpdateRequest,
+        UserUpdateResponse,
+    };
 
+    #[utoipa::path(
+        tag = "user",
+        request_body = UserAuthRequest,
+        responses(
+            (status = 200, description = "Access and refresh token, or an MFA challenge when MFA is enabled", body = GeneralResponse<UserAuthResult>),
+            (status = 400, description = "Malformed or invalid request", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Unknown user or wrong password", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[post("/api/v1/user/auth")]
     pub async fn user_auth(
+        req: HttpRequest,
         body: String,
         use_cases: web::Data<crate::UseCases>,
     ) -> impl Responder {
@@ -26,9 +43,10 @@ pub mod user_routes {
             }
         };
 
-        return match verify_user_auth_request(des.clone()) {
+        match verify_user_auth_request(des.clone()) {
             Ok(_res) => {
-                match use_cases.user_use_case.user_auth(des).await {
+                let ctx = middleware::audit_context(&req, None);
+                match use_cases.user_use_case.user_auth(ctx, des).await {
                     Ok(res) => {
                         send_success_response(res)
                     }
@@ -45,11 +63,21 @@ pub mod user_routes {
 
                 send_error_response(res)
             }
-        };
+        }
     }
 
+    #[utoipa::path(
+        tag = "user",
+        request_body = UserChangePasswordRequest,
+        responses(
+            (status = 200, description = "Password changed; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 400, description = "Malformed request or wrong old password", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[post("/api/v1/user/password-change")]
     pub async fn user_change_password(
+        req: HttpRequest,
         body: String,
         use_cases: web::Data<crate::UseCases>,
     ) -> impl Responder {
@@ -67,7 +95,8 @@ pub mod user_routes {
             }
         };
 
-        return match use_cases.user_use_case.user_change_password(des).await {
+        let ctx = middleware::audit_context(&req, None);
+        return match use_cases.user_use_case.user_change_password(ctx, des).await {
             Ok(res) => {
                 send_success_response(res)
             }
@@ -77,23 +106,31 @@ pub mod user_routes {
         };
     }
 
+    #[utoipa::path(
+        tag = "user",
+        request_body = UserUpdateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Updated user", body = GeneralResponse<UserUpdateResponse>),
+            (status = 400, description = "Malformed request", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope, or another user's account and the caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Username taken", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[put("/api/v1/user/update")]
     pub async fn user_update_by_id(
         req: HttpRequest,
         body: String,
         use_cases: web::Data<crate::UseCases>,
     ) -> impl Responder {
-        let _token_result = match middleware::is_unauthorized(&req).await {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
             Ok(res) => {
                 res
             }
-            Err(_err) => {
-                let res = ErrorResponseUseCase {
-                    status_code: StatusCode::UNAUTHORIZED,
-                    error_msg: "Unauthorized".to_string(),
-                };
-
-                return send_error_response(res);
+            Err(err) => {
+                return scope_error(err);
             }
         };
 
@@ -111,7 +148,8 @@ pub mod user_routes {
             }
         };
 
-        return match use_cases.user_use_case.user_update_by_id(des).await {
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+        return match use_cases.user_use_case.user_update_by_id(ctx, des).await {
             Ok(res) => {
                 send_success_response(res)
             }
@@ -121,23 +159,30 @@ pub mod user_routes {
         };
     }
 
+    #[utoipa::path(
+        tag = "user",
+        request_body = UserCreateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "Created user", body = GeneralResponse<UserGet>),
+            (status = 400, description = "Malformed or invalid request", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "Username taken", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[post("/api/v1/user/create")]
     pub async fn user_create(
         req: HttpRequest,
         body: String,
         use_cases: web::Data<crate::UseCases>,
     ) -> impl Responder {
-        let _token_result = match middleware::is_unauthorized(&req).await {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
             Ok(res) => {
                 res
             }
-            Err(_err) => {
-                let res = ErrorResponseUseCase {
-                    status_code: StatusCode::UNAUTHORIZED,
-                    error_msg: "Unauthorized".to_string(),
-                };
-
-                return send_error_response(res);
+            Err(err) => {
+                return scope_error(err);
             }
         };
 
@@ -155,9 +200,10 @@ pub mod user_routes {
             }
         };
 
-        return match verify_user_create_request(des.clone()) {
+        match verify_user_create_request(des.clone()) {
             Ok(_res) => {
-                match use_cases.user_use_case.user_create(des).await {
+                let ctx = middleware::audit_context(&req, Some(&token_result));
+                match use_cases.user_use_case.user_create(ctx, des).await {
                     Ok(res) => {
                         send_success_response(res)
                     }
@@ -174,19 +220,174 @@ pub mod user_routes {
 
                 send_error_response(res)
             }
+        }
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's account", body = GeneralResponse<UserGetResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[get("/api/v1/user/me")]
+    pub async fn user_me(
+        req: HttpRequest,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_READ).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        return match use_cases.user_use_case.user_get_me(token_result.username).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "user",
+        request_body = UserMeUpdateRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The caller's updated account", body = GeneralResponse<UserGetResponse>),
+            (status = 400, description = "Malformed request, empty name or a field other than firstname and lastname", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[patch("/api/v1/user/me")]
+    pub async fn user_update_me(
+        req: HttpRequest,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_authorized(&req, SCOPE_USER_WRITE).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        let des: UserMeUpdateRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let error_msg = match err.to_string().starts_with("unknown field") {
+                    true => "only firstname and lastname can be changed".to_string(),
+                    false => "Invalid request".to_string(),
+                };
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg,
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_user_me_update_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_update_me(ctx, token_result.username, des).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
         };
     }
 
+    #[utoipa::path(
+        tag = "user",
+        params(("id" = i32, Path, description = "User id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "User", body = GeneralResponse<UserGetResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "API key lacks the required scope", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[get("/api/v1/user/{id}/get")]
     pub async fn user_get(
         req: HttpRequest,
         id: web::Path<i32>,
         use_cases: web::Data<crate::UseCases>,
     ) -> impl Responder {
-        let _token_result = match middleware::is_unauthorized(&req).await {
+        let _token_result = match middleware::is_authorized(&req, SCOPE_USER_READ).await {
             Ok(res) => {
                 res
             }
+            Err(err) => {
+                return scope_error(err);
+            }
+        };
+
+        let id = id.into_inner();
+
+        return match use_cases.user_use_case.user_get_by_id(id).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i32, Path, description = "User id")),
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "User deleted; data is null", body = GeneralResponse<serde_json::Value>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[delete("/api/v1/user/{id}/delete")]
+    pub async fn user_delete(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(AccessTokenError::NotAdmin) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::FORBIDDEN,
+                    error_msg: "Forbidden".to_string(),
+                };
+
+                return send_error_response(res);
+            }
             Err(_err) => {
                 let res = ErrorResponseUseCase {
                     status_code: StatusCode::UNAUTHORIZED,
@@ -197,9 +398,9 @@ pub mod user_routes {
             }
         };
 
-        let id = id.into_inner();
+        let ctx = middleware::audit_context(&req, Some(&token_result));
 
-        return match use_cases.user_use_case.user_get_by_id(id).await {
+        return match use_cases.user_use_case.user_delete_by_id(ctx, id.into_inner()).await {
             Ok(res) => {
                 send_success_response(res)
             }
@@ -209,11 +410,126 @@ pub mod user_routes {
         };
     }
 
+    #[utoipa::path(
+        tag = "user",
+        params(UserListQuery),
+        responses(
+            (status = 200, description = "All users, or those with the given status, ordered by id", body = GeneralResponse<Vec<UserGetResponse>>),
+            (status = 400, description = "Invalid status", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
     #[get("/api/v1/user/list")]
     pub async fn user_list(
+        req: HttpRequest,
         use_cases: web::Data<crate::UseCases>
     ) -> impl Responder {
-        return match use_cases.user_use_case.user_list().await {
+        let query = match web::Query::<UserListQuery>::from_query(req.query_string()) {
+            Ok(res) => {
+                res.into_inner()
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid query".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let status = match query.status.as_deref().map(parse_user_status).transpose() {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: err,
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        return match use_cases.user_use_case.user_list(status).await {
+            Ok(res) => {
+                send_success_response(res)
+            }
+            Err(err) => {
+                send_error_response(err)
+            }
+        };
+    }
+
+    #[utoipa::path(
+        tag = "admin",
+        params(("id" = i32, Path, description = "User id")),
+        request_body = UserStatusRequest,
+        security(("bearer_auth" = [])),
+        responses(
+            (status = 200, description = "The account with its new status", body = GeneralResponse<UserGetResponse>),
+            (status = 400, description = "Unknown action, or disable without a reason", body = GeneralResponse<ErrorResponse>),
+            (status = 401, description = "Missing or invalid token", body = GeneralResponse<ErrorResponse>),
+            (status = 403, description = "Caller is not an admin", body = GeneralResponse<ErrorResponse>),
+            (status = 404, description = "User not found", body = GeneralResponse<ErrorResponse>),
+            (status = 409, description = "The current status does not allow the action", body = GeneralResponse<ErrorResponse>),
+        ),
+    )]
+    #[post("/api/v1/user/{id}/status")]
+    pub async fn user_set_status(
+        req: HttpRequest,
+        id: web::Path<i32>,
+        body: String,
+        use_cases: web::Data<crate::UseCases>,
+    ) -> impl Responder {
+        let token_result = match middleware::is_admin(&req).await {
+            Ok(res) => {
+                res
+            }
+            Err(AccessTokenError::NotAdmin) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::FORBIDDEN,
+                    error_msg: "Forbidden".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    error_msg: "Unauthorized".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        let des: UserStatusRequest = match serde_json::from_str(&body) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid request".to_string(),
+                };
+
+                return send_error_response(res);
+            }
+        };
+
+        if let Err(err) = verify_user_status_request(&des) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return send_error_response(res);
+        }
+
+        let ctx = middleware::audit_context(&req, Some(&token_result));
+
+        return match use_cases.user_use_case.user_set_status(ctx, id.into_inner(), des).await {
             Ok(res) => {
                 send_success_response(res)
             }
@@ -0,0 +1,575 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+
+    use crate::config::config::Config;
+    use crate::internal::controller::app::new_app;
+    use crate::internal::controller::test_app::{test_token, test_use_cases, TEST_SECRET_KEY};
+    use crate::internal::user::entity::token::{generate_access_token, new_token_config};
+    use crate::internal::user::entity::user::UserStatus;
+    use crate::internal::user::usecase::repo::memory_repo::MemoryUser;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    struct TestCase {
+        name: &'static str,
+        request: test::TestRequest,
+        status: StatusCode,
+        error_msg: Option<&'static str>,
+    }
+
+    async fn run_test_cases(test_cases: Vec<TestCase>) {
+        for test_case in test_cases {
+            let (use_cases, _repo) = test_use_cases();
+            let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+            let res = test::call_service(&app, test_case.request.to_request()).await;
+            assert_eq!(res.status(), test_case.status, "{}", test_case.name);
+
+            let body: Value = test::read_body_json(res).await;
+            let success = test_case.status == StatusCode::OK;
+            assert_eq!(body["success"], json!(success), "{}", test_case.name);
+
+            if !success {
+                assert_eq!(body["data"]["status_code"], json!(test_case.status.as_u16()), "{}", test_case.name);
+            }
+            if let Some(error_msg) = test_case.error_msg {
+                assert_eq!(body["data"]["error_msg"], json!(error_msg), "{}", test_case.name);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn user_auth_test() {
+        run_test_cases(vec! {
+            TestCase {
+                name: "valid credentials",
+                request: test::TestRequest::post().uri("/api/v1/user/auth")
+                    .set_payload(r#"{"username":"JamesHolland","password":"james123"}"#),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "malformed json",
+                request: test::TestRequest::post().uri("/api/v1/user/auth")
+                    .set_payload(r#"{"username":"JamesHolland""#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Can't convert request"),
+            },
+            TestCase {
+                name: "invalid username",
+                request: test::TestRequest::post().uri("/api/v1/user/auth")
+                    .set_payload(r#"{"username":"","password":"james123"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("invalid username"),
+            },
+            TestCase {
+                name: "wrong password",
+                request: test::TestRequest::post().uri("/api/v1/user/auth")
+                    .set_payload(r#"{"username":"JamesHolland","password":"james1234"}"#),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "unknown user",
+                request: test::TestRequest::post().uri("/api/v1/user/auth")
+                    .set_payload(r#"{"username":"JohnSmith","password":"james123"}"#),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+        }).await;
+    }
+
+    #[actix_web::test]
+    async fn user_auth_token_test() {
+        let (use_cases, _repo) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_payload(r#"{"username":"JamesHolland","password":"james123"}"#)
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let access_token = body["data"]["access_token"].as_str().unwrap();
+
+        let req = test::TestRequest::get().uri("/api/v1/user/1/get")
+            .insert_header(("authorization", format!("Bearer {}", access_token)))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn user_change_password_test() {
+        run_test_cases(vec! {
+            TestCase {
+                name: "valid old password",
+                request: test::TestRequest::post().uri("/api/v1/user/password-change")
+                    .set_payload(r#"{"id":1,"old_password":"james123","new_password":"james456"}"#),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "malformed json",
+                request: test::TestRequest::post().uri("/api/v1/user/password-change")
+                    .set_payload(r#"{"id":"1"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Can't convert request"),
+            },
+            TestCase {
+                name: "invalid old password",
+                request: test::TestRequest::post().uri("/api/v1/user/password-change")
+                    .set_payload(r#"{"id":1,"old_password":"james000","new_password":"james456"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Invalid old password"),
+            },
+            TestCase {
+                name: "unknown user",
+                request: test::TestRequest::post().uri("/api/v1/user/password-change")
+                    .set_payload(r#"{"id":2,"old_password":"james123","new_password":"james456"}"#),
+                status: StatusCode::NOT_FOUND,
+                error_msg: Some("User with id=2 not found"),
+            },
+        }).await;
+    }
+
+    #[actix_web::test]
+    async fn user_update_by_id_test() {
+        run_test_cases(vec! {
+            TestCase {
+                name: "valid request",
+                request: test::TestRequest::put().uri("/api/v1/user/update")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"id":1,"username":"JamesH","firstname":"Jim","lastname":"Holland"}"#),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "missing token",
+                request: test::TestRequest::put().uri("/api/v1/user/update")
+                    .set_payload(r#"{"id":1,"username":"JamesH","firstname":"Jim","lastname":"Holland"}"#),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "malformed json",
+                request: test::TestRequest::put().uri("/api/v1/user/update")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload("{"),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Invalid request"),
+            },
+            TestCase {
+                name: "unknown user",
+                request: test::TestRequest::put().uri("/api/v1/user/update")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"id":2,"username":"JamesH","firstname":"Jim","lastname":"Holland"}"#),
+                status: StatusCode::NOT_FOUND,
+                error_msg: Some("User with id=2 not found"),
+            },
+        }).await;
+    }
+
+    #[actix_web::test]
+    async fn user_update_takeover_test() {
+        let (use_cases, _repo) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/create").insert_header(("authorization", test_token()))
+            .set_payload(r#"{"username":"JohnDoe","password":"john12345","firstname":"John","lastname":"Doe"}"#);
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::OK);
+        let john = format!("Bearer {}", generate_access_token(&new_token_config(TEST_SECRET_KEY.to_string(), 5), &"JohnDoe".to_string()));
+
+        let update = |token: &str, body: Value| test::TestRequest::put().uri("/api/v1/user/update")
+            .insert_header(("authorization", token.to_string())).set_json(body).to_request();
+        let test_cases = vec! {
+            ("rename the admin", json!({"id": 1, "username": "JamesH", "firstname": "James", "lastname": "Holland"}), StatusCode::FORBIDDEN, "Only admins can change other accounts"),
+            ("take the admin's name", json!({"id": 2, "username": "JamesHolland", "firstname": "John", "lastname": "Doe"}), StatusCode::CONFLICT, "User with username=JamesHolland already exists"),
+        };
+        for (name, body, status, error_msg) in test_cases {
+            let res = test::call_service(&app, update(&john, body)).await;
+            assert_eq!(res.status(), status, "{}", name);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["data"]["error_msg"], json!(error_msg), "{}", name);
+        }
+
+        // Even once the admin gave the name up, taking it grants nothing:
+        // admins are listed by id.
+        let res = test::call_service(&app, update(&test_token(), json!({"id": 1, "username": "JamesH", "firstname": "James", "lastname": "Holland"}))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, update(&john, json!({"id": 2, "username": "JamesHolland", "firstname": "John", "lastname": "Doe"}))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_payload(r#"{"username":"JamesHolland","password":"john12345"}"#);
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        let token = format!("Bearer {}", body["data"]["access_token"].as_str().unwrap());
+        let req = test::TestRequest::get().uri("/api/v1/audit").insert_header(("authorization", token));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn user_me_test() {
+        run_test_cases(vec! {
+            TestCase {
+                name: "own profile",
+                request: test::TestRequest::get().uri("/api/v1/user/me")
+                    .insert_header(("authorization", test_token())),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "missing token",
+                request: test::TestRequest::get().uri("/api/v1/user/me"),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "account gone",
+                request: test::TestRequest::get().uri("/api/v1/user/me")
+                    .insert_header(("authorization", format!("Bearer {}", generate_access_token(&new_token_config(TEST_SECRET_KEY.to_string(), 5), &"Nobody".to_string())))),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "username can't change",
+                request: test::TestRequest::patch().uri("/api/v1/user/me")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"firstname":"Jim","username":"root"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("only firstname and lastname can be changed"),
+            },
+            TestCase {
+                name: "id can't change",
+                request: test::TestRequest::patch().uri("/api/v1/user/me")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"id":2}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("only firstname and lastname can be changed"),
+            },
+            TestCase {
+                name: "empty name",
+                request: test::TestRequest::patch().uri("/api/v1/user/me")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"lastname":""}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("lastname is empty"),
+            },
+            TestCase {
+                name: "malformed json",
+                request: test::TestRequest::patch().uri("/api/v1/user/me")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload("{"),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Invalid request"),
+            },
+        }).await;
+
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::patch().uri("/api/v1/user/me")
+            .insert_header(("authorization", test_token()))
+            .set_payload(r#"{"firstname":"Jim"}"#);
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(body["data"]["id"], json!(1));
+        assert_eq!((body["data"]["username"].clone(), body["data"]["firstname"].clone(), body["data"]["lastname"].clone()), (json!("JamesHolland"), json!("Jim"), json!("Holland")));
+
+        let req = test::TestRequest::get().uri("/api/v1/user/me").insert_header(("authorization", test_token()));
+        let me: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(me["data"], body["data"]);
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].action.as_str(), entries[0].actor_id, entries[0].target_id), ("user.update", Some(1), Some(1)));
+        assert_eq!(entries[0].changes, json!({"firstname": {"before": "James", "after": "Jim"}}));
+    }
+
+    #[actix_web::test]
+    async fn user_create_test() {
+        run_test_cases(vec! {
+            TestCase {
+                name: "valid request",
+                request: test::TestRequest::post().uri("/api/v1/user/create")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"username":"JohnSmith","password":"john123","firstname":"John","lastname":"Smith"}"#),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "missing token",
+                request: test::TestRequest::post().uri("/api/v1/user/create")
+                    .set_payload(r#"{"username":"JohnSmith","password":"john123","firstname":"John","lastname":"Smith"}"#),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "malformed json",
+                request: test::TestRequest::post().uri("/api/v1/user/create")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"username":"JohnSmith"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("Invalid request"),
+            },
+            TestCase {
+                name: "empty firstname",
+                request: test::TestRequest::post().uri("/api/v1/user/create")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"username":"JohnSmith","password":"john123","firstname":"","lastname":"Smith"}"#),
+                status: StatusCode::BAD_REQUEST,
+                error_msg: Some("firstname is empty"),
+            },
+            TestCase {
+                name: "existing username",
+                request: test::TestRequest::post().uri("/api/v1/user/create")
+                    .insert_header(("authorization", test_token()))
+                    .set_payload(r#"{"username":"JamesHolland","password":"james123","firstname":"James","lastname":"Holland"}"#),
+                status: StatusCode::CONFLICT,
+                error_msg: Some("User with username=JamesHolland already exists"),
+            },
+        }).await;
+    }
+
+    #[actix_web::test]
+    async fn user_get_test() {
+        let foreign_token = generate_access_token(
+            &new_token_config("another-secret-key".to_string(), 5),
+            &"JamesHolland".to_string(),
+        );
+
+        run_test_cases(vec! {
+            TestCase {
+                name: "existing user",
+                request: test::TestRequest::get().uri("/api/v1/user/1/get")
+                    .insert_header(("authorization", test_token())),
+                status: StatusCode::OK,
+                error_msg: None,
+            },
+            TestCase {
+                name: "missing token",
+                request: test::TestRequest::get().uri("/api/v1/user/1/get"),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "token without bearer prefix",
+                request: test::TestRequest::get().uri("/api/v1/user/1/get")
+                    .insert_header(("authorization", "abc")),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "token signed with another key",
+                request: test::TestRequest::get().uri("/api/v1/user/1/get")
+                    .insert_header(("authorization", format!("Bearer {}", foreign_token))),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "expired token",
+                request: test::TestRequest::get().uri("/api/v1/user/1/get")
+                    .insert_header(("authorization", format!("Bearer {}", generate_expired_token()))),
+                status: StatusCode::UNAUTHORIZED,
+                error_msg: Some("Unauthorized"),
+            },
+            TestCase {
+                name: "unknown user",
+                request: test::TestRequest::get().uri("/api/v1/user/2/get")
+                    .insert_header(("authorization", test_token())),
+                status: StatusCode::NOT_FOUND,
+                error_msg: Some("User with id=2 not found"),
+            },
+        }).await;
+    }
+
+    #[actix_web::test]
+    async fn user_list_test() {
+        let (use_cases, repos) = test_use_cases();
+        repos.user.users.lock().unwrap().insert(0, MemoryUser {
+            id: 2,
+            username: "JohnSmith".to_string(),
+            password: String::new(),
+            firstname: "John".to_string(),
+            lastname: "Smith".to_string(),
+            create_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+            update_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+            ..Default::default()
+        });
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::get().uri("/api/v1/user/list").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["success"], json!(true));
+        assert_eq!(body["data"][0]["id"], json!(1));
+        assert_eq!(body["data"][0]["username"], json!("JamesHolland"));
+        assert_eq!(body["data"][0]["create_ts"], json!("2023-02-10T03:33:20Z"));
+        assert!(body["data"][0].get("password").is_none());
+        assert_eq!(body["data"][0]["status"], json!("active"));
+        assert_eq!(body["data"][1]["username"], json!("JohnSmith"));
+
+        repos.user.users.lock().unwrap()[0].status = UserStatus::Locked;
+        let req = test::TestRequest::get().uri("/api/v1/user/list?status=locked").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!((body["data"][0]["username"].clone(), body["data"][0]["status"].clone()), (json!("JohnSmith"), json!("locked")));
+
+        let req = test::TestRequest::get().uri("/api/v1/user/list?status=gone").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["error_msg"], json!("status must be one of active, disabled, locked, pending"));
+    }
+
+    #[actix_web::test]
+    async fn user_status_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/create").insert_header(("authorization", test_token()))
+            .set_payload(r#"{"username":"JohnDoe","password":"john12345","firstname":"John","lastname":"Doe"}"#);
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::OK);
+        let john = format!("Bearer {}", generate_access_token(&new_token_config(TEST_SECRET_KEY.to_string(), 5), &"JohnDoe".to_string()));
+        let req = test::TestRequest::post().uri("/api/v1/user/api-keys").insert_header(("authorization", john.clone()))
+            .set_payload(r#"{"name":"batch","scopes":["user:read"]}"#);
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        let api_key = format!("Bearer {}", body["data"]["api_key"].as_str().unwrap());
+
+        let status = |action: &str, reason: Option<&str>, token: String| {
+            test::TestRequest::post().uri("/api/v1/user/2/status").insert_header(("authorization", token))
+                .set_json(json!({"action": action, "reason": reason}))
+        };
+        let login = || test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_payload(r#"{"username":"JohnDoe","password":"john12345"}"#);
+        let me = |token: String| test::TestRequest::get().uri("/api/v1/user/me").insert_header(("authorization", token));
+        macro_rules! call {
+            ($req:expr) => {{
+                let res = test::call_service(&app, $req.to_request()).await;
+                let status = res.status();
+                let body: Value = test::read_body_json(res).await;
+                (status, body["data"].clone())
+            }};
+        }
+
+        let (code, _) = call!(status("lock", None, john.clone()));
+        assert_eq!(code, StatusCode::FORBIDDEN);
+        let (code, data) = call!(status("disable", None, test_token()));
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::BAD_REQUEST, json!("reason is required to disable an account")));
+        let (code, data) = call!(test::TestRequest::post().uri("/api/v1/user/9/status").insert_header(("authorization", test_token())).set_json(json!({"action": "lock"})));
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::NOT_FOUND, json!("User with id=9 not found")));
+
+        let (code, data) = call!(status("disable", Some(" left the company "), test_token()));
+        assert_eq!((code, data["status"].clone()), (StatusCode::OK, json!("disabled")));
+        assert_eq!(repos.user.users.lock().unwrap()[1].status_reason.as_deref(), Some("left the company"));
+
+        // Disabled accounts can't log in, and their tokens and keys stop working.
+        let (code, data) = call!(login());
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::FORBIDDEN, json!("Account is disabled")));
+        let (code, data) = call!(test::TestRequest::post().uri("/api/v1/user/auth").set_payload(r#"{"username":"JohnDoe","password":"wrong123"}"#));
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::UNAUTHORIZED, json!("Unauthorized")));
+        assert_eq!(call!(me(john.clone())).0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call!(me(api_key.clone())).0, StatusCode::UNAUTHORIZED);
+
+        let (code, data) = call!(status("lock", None, test_token()));
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::CONFLICT, json!("can't lock an account that is disabled")));
+
+        let (code, data) = call!(status("activate", None, test_token()));
+        assert_eq!((code, data["status"].clone()), (StatusCode::OK, json!("active")));
+        assert_eq!(call!(login()).0, StatusCode::OK);
+        assert_eq!(call!(me(john.clone())).0, StatusCode::OK);
+        assert_eq!(call!(me(api_key)).0, StatusCode::OK);
+
+        let (code, _) = call!(status("lock", None, test_token()));
+        assert_eq!(code, StatusCode::OK);
+        let (code, data) = call!(login());
+        assert_eq!((code, data["error_msg"].clone()), (StatusCode::FORBIDDEN, json!("Account is locked")));
+        let (code, _) = call!(status("unlock", None, test_token()));
+        assert_eq!(code, StatusCode::OK);
+
+        let entries = repos.audit.entries.lock().unwrap().clone();
+        let changes: Vec<&Value> = entries.iter().filter(|e| e.action == "user.status_change").map(|e| &e.changes).collect();
+        assert_eq!(changes.len(), 4);
+        assert_eq!(*changes[0], json!({"status": {"before": "active", "after": "disabled"}, "reason": {"before": null, "after": "left the company"}}));
+        let disable = entries.iter().find(|e| e.action == "user.status_change").unwrap();
+        assert_eq!((disable.actor_id, disable.target_id, disable.target_username.as_deref()), (Some(1), Some(2), Some("JohnDoe")));
+        let refused = entries.iter().filter(|e| e.action == "user.login_failure" && e.target_id == Some(2)).count();
+        assert_eq!(refused, 3);
+    }
+
+    #[actix_web::test]
+    async fn user_delete_test() {
+        let (use_cases, repos) = test_use_cases();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/create").insert_header(("authorization", test_token()))
+            .set_payload(r#"{"username":"JohnDoe","password":"john12345","firstname":"John","lastname":"Doe"}"#);
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::OK);
+        let john = format!("Bearer {}", generate_access_token(&new_token_config(TEST_SECRET_KEY.to_string(), 5), &"JohnDoe".to_string()));
+        let req = test::TestRequest::post().uri("/api/v1/user/api-keys").insert_header(("authorization", john.clone()))
+            .set_payload(r#"{"name":"sync","scopes":["user:write"]}"#);
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        let api_key = format!("Bearer {}", body["data"]["api_key"].as_str().unwrap());
+
+        let delete = |id: i32, token: String| test::TestRequest::delete().uri(&format!("/api/v1/user/{}/delete", id))
+            .insert_header(("authorization", token)).to_request();
+
+        // Deleting accounts is for admins only, whatever key a user holds.
+        let res = test::call_service(&app, delete(1, john.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["error_msg"], json!("Forbidden"));
+        assert_eq!(test::call_service(&app, delete(1, api_key)).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(repos.user.users.lock().unwrap().len(), 2);
+
+        assert_eq!(test::call_service(&app, delete(2, test_token())).await.status(), StatusCode::OK);
+        assert_eq!(test::call_service(&app, delete(2, test_token())).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(repos.user.users.lock().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn max_body_size_test() {
+        let (use_cases, _repo) = test_use_cases();
+        let mut cfg = Config::default().http;
+        cfg.max_body_size = 16;
+        let app = test::init_service(new_app(use_cases, &cfg, new_shutdown())).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/user/auth")
+            .set_payload(r#"{"username":"JamesHolland","password":"james123"}"#)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn draining_test() {
+        let (use_cases, _repo) = test_use_cases();
+        let shutdown = new_shutdown();
+        let app = test::init_service(new_app(use_cases, &Config::default().http, shutdown.clone())).await;
+
+        let req = test::TestRequest::get().uri("/api/v1/user/list").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.headers().get("connection").is_none());
+
+        shutdown.stop_accepting();
+
+        let req = test::TestRequest::get().uri("/api/v1/user/list").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("connection").unwrap(), "close");
+    }
+
+    fn generate_expired_token() -> String {
+        use std::collections::BTreeMap;
+        use hmac::{Hmac, Mac};
+        use jwt::SignWithKey;
+        use sha2::Sha256;
+
+        let key: Hmac<Sha256> = Hmac::new_from_slice(TEST_SECRET_KEY.as_ref()).unwrap();
+        let mut claims = BTreeMap::new();
+        claims.insert("username", "JamesHolland");
+        claims.insert("created_time", "1");
+
+        claims.sign_with_key(&key).unwrap()
+    }
+}
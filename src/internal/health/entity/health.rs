@@ -0,0 +1,42 @@
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+pub const STATUS_UP: &str = "up";
+pub const STATUS_DOWN: &str = "down";
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LivenessResponse {
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: String,
+    pub latency_ms: f64,
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+    pub acquire_wait_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HealthReport {
+    pub status: String,
+    pub ready: bool,
+    pub version: String,
+    pub uptime_sec: u64,
+    pub dependencies: Vec<DependencyHealth>,
+    pub pool: PoolStats,
+}
+
+impl HealthReport {
+    pub fn is_up(&self) -> bool {
+        self.status == STATUS_UP
+    }
+}
@@ -0,0 +1,130 @@
+use std::future::Future;
+use std::time::Instant;
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use tracing::instrument;
+
+use crate::internal::health::entity::health::{
+    DependencyHealth, HealthReport, LivenessResponse, PoolStats, STATUS_DOWN, STATUS_UP,
+};
+use crate::internal::health::usecase::traits::{HealthUseCase, UseCase};
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+impl HealthUseCase {
+    // Runs one dependency check bounded by check_timeout and records its latency.
+    async fn check<F>(&self, name: &str, check: F) -> DependencyHealth
+    where
+        F: Future<Output = Result<Option<String>, String>>,
+    {
+        let start = Instant::now();
+        let res = match tokio::time::timeout(self.check_timeout, check).await {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                Err(format!("timed out after {}ms", self.check_timeout.as_millis()))
+            }
+        };
+
+        let (status, detail) = match res {
+            Ok(detail) => {
+                (STATUS_UP, detail)
+            }
+            Err(err) => {
+                tracing::warn!(dependency = name, error = %err, "dependency check failed");
+                (STATUS_DOWN, Some(err))
+            }
+        };
+
+        DependencyHealth {
+            name: name.to_string(),
+            status: status.to_string(),
+            latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+            detail,
+        }
+    }
+}
+
+#[async_trait]
+impl UseCase for HealthUseCase {
+    #[instrument(name = "health_use_case.liveness", skip_all)]
+    async fn liveness(&self) -> LivenessResponse {
+        LivenessResponse {
+            status: STATUS_UP.to_string(),
+        }
+    }
+
+    #[instrument(name = "health_use_case.readiness", skip_all)]
+    async fn readiness(&self) -> Result<HealthReport, ErrorResponseUseCase> {
+        if !self.shutdown.is_ready() {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::SERVICE_UNAVAILABLE,
+                error_msg: "Shutting down".to_string(),
+            };
+
+            return Err(res);
+        }
+
+        let report = self.health_report().await;
+        if report.is_up() {
+            return Ok(report);
+        }
+
+        let failed: Vec<String> = report.dependencies.iter()
+            .filter(|d| d.status != STATUS_UP)
+            .map(|d| format!("{}: {}", d.name, d.detail.clone().unwrap_or_default()))
+            .collect();
+
+        let res = ErrorResponseUseCase {
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+            error_msg: format!("Not ready ({})", failed.join("; ")),
+        };
+
+        Err(res)
+    }
+
+    #[instrument(name = "health_use_case.health_report", skip_all)]
+    async fn health_report(&self) -> HealthReport {
+        let postgres = self.check("postgres", async {
+            self.repo.ping().await.map(|_| None).map_err(|err| err.to_string())
+        }).await;
+
+        let migrations = self.check("migrations", async {
+            match self.repo.migration_version().await {
+                Ok(Some(version)) if version >= self.expected_migration => {
+                    Ok(Some(format!("version {}", version)))
+                }
+                Ok(version) => {
+                    Err(format!("version {} is behind expected {}", version.unwrap_or(0), self.expected_migration))
+                }
+                Err(err) => {
+                    Err(err.to_string())
+                }
+            }
+        }).await;
+
+        let dependencies = vec![postgres, migrations];
+        let status = if dependencies.iter().all(|d| d.status == STATUS_UP) { STATUS_UP } else { STATUS_DOWN };
+
+        HealthReport {
+            status: status.to_string(),
+            ready: self.shutdown.is_ready() && status == STATUS_UP,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_sec: self.started.elapsed().as_secs(),
+            dependencies,
+            pool: self.pool_stats().await,
+        }
+    }
+
+    #[instrument(name = "health_use_case.pool_stats", skip_all)]
+    async fn pool_stats(&self) -> PoolStats {
+        let mut stats = self.repo.pool_stats();
+
+        let start = Instant::now();
+        if let Ok(Ok(())) = tokio::time::timeout(self.check_timeout, self.repo.acquire()).await {
+            stats.acquire_wait_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        stats
+    }
+}
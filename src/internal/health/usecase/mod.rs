@@ -0,0 +1,3 @@
+pub mod traits;
+pub mod health;
+pub mod repo;
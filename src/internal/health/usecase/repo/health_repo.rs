@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::health::entity::health::PoolStats;
+use crate::internal::health::usecase::repo::repo::HealthRepo;
+use crate::internal::health::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+#[async_trait]
+impl Repo for HealthRepo {
+    #[instrument(
+        name = "health_repo.ping",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn ping(&self) -> Result<(), Error> {
+        let sql = "SELECT 1";
+        record_statement(sql);
+
+        return match sqlx::query(sql).execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "health_repo.migration_version",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn migration_version(&self) -> Result<Option<i64>, Error> {
+        let sql = "SELECT max(version) FROM _sqlx_migrations WHERE success";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, (Option<i64>,)>(sql);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data.0)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    async fn acquire(&self) -> Result<(), Error> {
+        // The connection goes straight back to the pool when dropped.
+        self.db.acquire().await.map(|_conn| ())
+    }
+
+    fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            size: self.db.size(),
+            idle: self.db.num_idle(),
+            max: self.max_conn,
+            acquire_wait_ms: None,
+        }
+    }
+}
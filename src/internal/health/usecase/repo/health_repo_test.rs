@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+
+    use crate::internal::health::usecase::repo::repo::new_health_repo;
+    use crate::internal::health::usecase::traits::Repo;
+    use crate::pkg::postgres::connection::expected_migration_version;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn health_repo_test() {
+        let test_db = new_test_db().await;
+        let repo = new_health_repo(web::Data::new(test_db.db.clone()), 2);
+
+        repo.ping().await.unwrap();
+        repo.acquire().await.unwrap();
+        assert_eq!(repo.migration_version().await.unwrap(), Some(expected_migration_version()));
+
+        let stats = repo.pool_stats();
+        assert!(stats.size >= 1 && stats.size <= 2);
+        assert_eq!(stats.max, 2);
+
+        test_db.close().await;
+    }
+}
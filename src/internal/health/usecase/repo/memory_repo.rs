@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use sqlx::Error;
+
+use crate::internal::health::entity::health::PoolStats;
+use crate::internal::health::usecase::traits::Repo;
+
+// In-memory stand-in for HealthRepo; tests flip the fields to simulate outages.
+
+pub struct MemoryHealthRepo {
+    pub reachable: Mutex<bool>,
+    pub migration_version: Mutex<Option<i64>>,
+}
+
+pub fn new_memory_health_repo(migration_version: i64) -> MemoryHealthRepo {
+    MemoryHealthRepo {
+        reachable: Mutex::new(true),
+        migration_version: Mutex::new(Some(migration_version)),
+    }
+}
+
+#[async_trait]
+impl Repo for MemoryHealthRepo {
+    async fn ping(&self) -> Result<(), Error> {
+        if *self.reachable.lock().unwrap() {
+            Ok(())
+        } else {
+            Err(Error::PoolTimedOut)
+        }
+    }
+
+    async fn migration_version(&self) -> Result<Option<i64>, Error> {
+        if *self.reachable.lock().unwrap() {
+            Ok(*self.migration_version.lock().unwrap())
+        } else {
+            Err(Error::PoolTimedOut)
+        }
+    }
+
+    async fn acquire(&self) -> Result<(), Error> {
+        self.ping().await
+    }
+
+    fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            size: 1,
+            idle: 1,
+            max: 10,
+            acquire_wait_ms: None,
+        }
+    }
+}
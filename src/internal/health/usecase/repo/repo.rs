@@ -0,0 +1,15 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct HealthRepo {
+    pub db: Data<Db>,
+    pub max_conn: u32,
+}
+
+pub fn new_health_repo(db: Data<Db>, max_conn: u32) -> HealthRepo {
+    HealthRepo{
+        db,
+        max_conn,
+    }
+}
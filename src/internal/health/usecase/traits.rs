@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+
+use crate::internal::health::entity::health::{HealthReport, LivenessResponse, PoolStats};
+use crate::internal::controller::response;
+use crate::pkg::shutdown::shutdown::Shutdown;
+
+#[derive(Clone)]
+pub struct HealthUseCase {
+    pub repo: Arc<dyn Repo>,
+    pub shutdown: Shutdown,
+    pub check_timeout: Duration,
+    pub expected_migration: i64,
+    pub started: Instant,
+}
+
+pub fn new_health_use_case(repo: Arc<dyn Repo>, shutdown: Shutdown, check_timeout: Duration, expected_migration: i64) -> HealthUseCase {
+    HealthUseCase {
+        repo,
+        shutdown,
+        check_timeout,
+        expected_migration,
+        started: Instant::now(),
+    }
+}
+
+#[async_trait]
+pub trait UseCase {
+    async fn liveness(&self) -> LivenessResponse;
+    async fn readiness(&self) -> Result<HealthReport, response::ErrorResponseUseCase>;
+    async fn health_report(&self) -> HealthReport;
+    async fn pool_stats(&self) -> PoolStats;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn ping(&self) -> Result<(), sqlx::Error>;
+    async fn migration_version(&self) -> Result<Option<i64>, sqlx::Error>;
+    async fn acquire(&self) -> Result<(), sqlx::Error>;
+    fn pool_stats(&self) -> PoolStats;
+}
@@ -0,0 +1,360 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::prelude::DateTime;
+use chrono::Utc;
+use p256::EncodedPoint;
+use rsa::{BigUint, RsaPublicKey};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+// Federated login: users sign in with an ID token from an external OpenID
+// provider, e.g. the corporate IdP. The application runs the login at the
+// provider and hands us the ID token; we check it against the provider's
+// published keys and map (provider, sub) to a local account.
+pub const IDENTITY_PROVIDER_NAME_MAX_LEN: usize = 64;
+pub const ID_TOKEN_MAX_LEN: usize = 16 * 1024;
+pub const CLOCK_SKEW: u64 = 60; // second, allowed between us and the provider
+pub const ALG_RS256: &str = "RS256";
+pub const ALG_ES256: &str = "ES256";
+const ISSUER_MAX_LEN: usize = 2048;
+const CLIENT_ID_MAX_LEN: usize = 256;
+const SUBJECT_MAX_LEN: usize = 255;
+const EMAIL_MAX_LEN: usize = 320;
+const NAME_MAX_LEN: usize = 50;
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct IdentityProvider {
+    pub id: i32,
+    /// Short name clients pass as `provider`
+    pub name: String,
+    /// Must equal the iss claim of the provider's ID tokens
+    pub issuer: String,
+    /// Our client_id at the provider, expected in the aud claim
+    pub client_id: String,
+    /// Create an account on the first login of an unknown identity
+    pub jit_provisioning: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct IdentityProviderCreateRequest {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub jit_provisioning: bool,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct UserIdentity {
+    pub id: i32,
+    #[serde(skip)]
+    pub user_id: i32,
+    /// Name of the identity provider
+    pub provider: String,
+    /// The sub claim at the provider
+    pub subject: String,
+    pub email: Option<String>,
+    pub create_ts: DateTime<Utc>,
+    pub last_login_ts: Option<DateTime<Utc>>,
+}
+
+// An identity ready to be stored.
+#[derive(Debug, Clone)]
+pub struct UserIdentityCreate {
+    pub user_id: i32,
+    pub provider_id: i32,
+    pub subject: String,
+    pub email: Option<String>,
+}
+
+// Body of both the federated login and the link endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct IdentityTokenRequest {
+    /// Name of a registered identity provider
+    pub provider: String,
+    /// ID token the provider issued to the application
+    pub id_token: String,
+    /// The nonce the application sent to the provider, checked when given
+    pub nonce: Option<String>,
+}
+
+// The ID token claims we use. Everything but sub is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+// One entry of a JSON Web Key Set (RFC 7517). Only RSA and P-256 keys are used.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: Option<String>,
+    pub alg: Option<String>,
+    #[serde(rename = "use")]
+    pub key_use: Option<String>,
+    pub n: Option<String>,
+    pub e: Option<String>,
+    pub crv: Option<String>,
+    pub x: Option<String>,
+    pub y: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+// The part of the provider's discovery document we need.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub jwks_uri: String,
+}
+
+pub fn discovery_url(issuer: &str) -> String {
+    format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'))
+}
+
+// A JWT split into its parts; nothing about it is trusted yet.
+#[derive(Debug, Clone)]
+pub struct UnverifiedJwt {
+    pub alg: String,
+    pub kid: Option<String>,
+    message: String,
+    claims: Map<String, Value>,
+    signature: Vec<u8>,
+}
+
+pub fn parse_jwt(token: &str) -> Result<UnverifiedJwt, String> {
+    let mut parts = token.split('.');
+    let (header, claims, signature) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(claims), Some(signature), None) => {
+            (header, claims, signature)
+        }
+        _ => {
+            return Err("not a signed JWT".to_string());
+        }
+    };
+
+    let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_err| "JWT is not base64url".to_string());
+    let decoded: Value = serde_json::from_slice(&decode(header)?).map_err(|_err| "JWT header is not JSON".to_string())?;
+    let alg = decoded["alg"].as_str().unwrap_or_default();
+    if alg != ALG_RS256 && alg != ALG_ES256 {
+        return Err(format!("unsupported alg {}", alg));
+    }
+
+    Ok(UnverifiedJwt {
+        alg: alg.to_string(),
+        kid: decoded["kid"].as_str().map(|kid| kid.to_string()),
+        message: format!("{}.{}", header, claims),
+        claims: serde_json::from_slice(&decode(claims)?).map_err(|_err| "JWT claims are not a JSON object".to_string())?,
+        signature: decode(signature)?,
+    })
+}
+
+// The key the token names, or the only signing key for its algorithm when it
+// names none.
+pub fn select_key<'a>(keys: &'a [Jwk], jwt: &UnverifiedJwt) -> Option<&'a Jwk> {
+    let kty = if jwt.alg == ALG_RS256 { "RSA" } else { "EC" };
+    let mut candidates = keys.iter().filter(|key| {
+        key.kty == kty
+            && key.key_use.as_deref().is_none_or(|key_use| key_use == "sig")
+            && key.alg.as_deref().is_none_or(|alg| alg == jwt.alg)
+    });
+
+    match &jwt.kid {
+        Some(kid) => {
+            candidates.find(|key| key.kid.as_ref() == Some(kid))
+        }
+        None => {
+            match (candidates.next(), candidates.next()) {
+                (Some(key), None) => Some(key),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn verify_signature(key: &Jwk, jwt: &UnverifiedJwt) -> Result<(), String> {
+    let decode = |part: &Option<String>| part.as_ref().and_then(|part| URL_SAFE_NO_PAD.decode(part).ok()).ok_or("malformed key".to_string());
+
+    if jwt.alg == ALG_RS256 {
+        use rsa::pkcs1v15::{Signature, VerifyingKey};
+        use rsa::signature::Verifier;
+
+        let public_key = RsaPublicKey::new(BigUint::from_bytes_be(&decode(&key.n)?), BigUint::from_bytes_be(&decode(&key.e)?))
+            .map_err(|_err| "malformed key".to_string())?;
+        let signature = Signature::try_from(jwt.signature.as_slice()).map_err(|_err| "malformed signature".to_string())?;
+
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(jwt.message.as_bytes(), &signature)
+            .map_err(|_err| "bad signature".to_string())
+    } else {
+        use p256::ecdsa::{Signature, VerifyingKey};
+        use p256::ecdsa::signature::Verifier;
+
+        if key.crv.as_deref() != Some("P-256") {
+            return Err("unsupported curve".to_string());
+        }
+        let (x, y) = (decode(&key.x)?, decode(&key.y)?);
+        if x.len() != 32 || y.len() != 32 {
+            return Err("malformed key".to_string());
+        }
+        let point = EncodedPoint::from_affine_coordinates(x.as_slice().into(), y.as_slice().into(), false);
+        let public_key = VerifyingKey::from_encoded_point(&point).map_err(|_err| "malformed key".to_string())?;
+        let signature = Signature::from_slice(&jwt.signature).map_err(|_err| "malformed signature".to_string())?;
+
+        public_key.verify(jwt.message.as_bytes(), &signature).map_err(|_err| "bad signature".to_string())
+    }
+}
+
+// OIDC Core 3.1.3.7: the signature, then iss, aud, azp, exp, iat and nonce.
+pub fn verify_id_token(provider: &IdentityProvider, key: &Jwk, jwt: &UnverifiedJwt, nonce: Option<&str>, now: u64) -> Result<IdTokenClaims, String> {
+    verify_signature(key, jwt)?;
+
+    let claims = &jwt.claims;
+    let string = |name: &str| claims.get(name).and_then(|value| value.as_str()).map(|value| value.to_string());
+
+    if string("iss").as_deref() != Some(provider.issuer.as_str()) {
+        return Err("wrong issuer".to_string());
+    }
+
+    let audiences: Vec<&str> = match claims.get("aud") {
+        Some(Value::String(aud)) => vec![aud.as_str()],
+        Some(Value::Array(aud)) => aud.iter().filter_map(|aud| aud.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !audiences.contains(&provider.client_id.as_str()) {
+        return Err("wrong audience".to_string());
+    }
+    if audiences.len() > 1 && string("azp").as_deref() != Some(provider.client_id.as_str()) {
+        return Err("wrong authorized party".to_string());
+    }
+
+    match claims.get("exp").and_then(|exp| exp.as_u64()) {
+        Some(exp) if exp + CLOCK_SKEW > now => {}
+        _ => return Err("expired".to_string()),
+    }
+    match claims.get("iat").and_then(|iat| iat.as_u64()) {
+        Some(iat) if iat <= now + CLOCK_SKEW => {}
+        _ => return Err("issued in the future".to_string()),
+    }
+
+    if let Some(nonce) = nonce {
+        if string("nonce").as_deref() != Some(nonce) {
+            return Err("wrong nonce".to_string());
+        }
+    }
+
+    let sub = string("sub").unwrap_or_default();
+    if sub.is_empty() || sub.len() > SUBJECT_MAX_LEN {
+        return Err("missing or oversized sub".to_string());
+    }
+
+    Ok(IdTokenClaims {
+        sub,
+        email: string("email").filter(|email| email.len() <= EMAIL_MAX_LEN),
+        preferred_username: string("preferred_username"),
+        name: string("name"),
+        given_name: string("given_name"),
+        family_name: string("family_name"),
+    })
+}
+
+// The username of a provisioned account, derived from the subject so it is
+// unique per provider. The provider's preferred_username or email are never
+// used: an IdP user could pick one naming an admin or a reserved account.
+pub fn provisioned_username(provider: &IdentityProvider, claims: &IdTokenClaims) -> String {
+    let digest = format!("{:x}", Sha256::digest(claims.sub.as_bytes()));
+
+    format!("{}-{}", provider.name, &digest[..16])
+}
+
+// First and last name for a provisioned account, from the given and family
+// names or by splitting the full name.
+pub fn provisioned_names(claims: &IdTokenClaims, username: &str) -> (String, String) {
+    let (first, last) = match (&claims.given_name, &claims.family_name, &claims.name) {
+        (Some(given), family, _) => {
+            (given.clone(), family.clone().unwrap_or_default())
+        }
+        (None, _, Some(name)) => {
+            let (first, last) = name.trim().split_once(' ').unwrap_or((name.trim(), ""));
+            (first.to_string(), last.trim().to_string())
+        }
+        _ => {
+            (username.to_string(), String::new())
+        }
+    };
+    let truncate = |value: String| value.chars().take(NAME_MAX_LEN).collect::<String>();
+
+    (truncate(first), truncate(last))
+}
+
+// The audited fields of a link, also used for unlinking.
+pub fn audited_identity(provider: &str, subject: &str) -> Value {
+    json!({
+        "provider": provider,
+        "subject": subject,
+    })
+}
+
+// verifying
+
+// Issuers are URLs that may have a path (e.g. .../tenant/v2.0), and must be
+// HTTPS unless they point at this machine.
+fn valid_issuer(issuer: &str) -> bool {
+    if issuer.len() > ISSUER_MAX_LEN || issuer.contains(['#', '?']) || issuer.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+
+    let rest = match issuer.strip_prefix("https://").or_else(|| issuer.strip_prefix("http://")) {
+        Some(res) => {
+            res
+        }
+        None => {
+            return false;
+        }
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = authority.rsplit_once(':').map(|(host, _)| host).unwrap_or(authority);
+
+    !host.is_empty() && (issuer.starts_with("https://") || host == "localhost" || host == "127.0.0.1")
+}
+
+pub fn verify_identity_provider_create_request(req: &IdentityProviderCreateRequest) -> Result<(), String> {
+    let name_ok = !req.name.is_empty()
+        && req.name.len() <= IDENTITY_PROVIDER_NAME_MAX_LEN
+        && req.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !name_ok {
+        return Err(format!("name must be 1 to {} characters of a-z, 0-9 and -", IDENTITY_PROVIDER_NAME_MAX_LEN));
+    }
+
+    if !valid_issuer(&req.issuer) {
+        return Err("issuer must be an https URL without query or fragment, or http on localhost".to_string());
+    }
+
+    if req.client_id.trim().is_empty() || req.client_id.len() > CLIENT_ID_MAX_LEN {
+        return Err(format!("client_id must be 1 to {} characters", CLIENT_ID_MAX_LEN));
+    }
+
+    Ok(())
+}
+
+pub fn verify_identity_token_request(req: &IdentityTokenRequest) -> Result<(), String> {
+    if req.provider.is_empty() {
+        return Err("provider is required".to_string());
+    }
+
+    if req.id_token.is_empty() || req.id_token.len() > ID_TOKEN_MAX_LEN {
+        return Err(format!("id_token must be 1 to {} bytes", ID_TOKEN_MAX_LEN));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::internal::identity::entity::identity::{
+        discovery_url, parse_jwt, provisioned_names, provisioned_username, select_key, verify_id_token,
+        verify_identity_provider_create_request, verify_identity_token_request, IdTokenClaims, IdentityProvider,
+        IdentityProviderCreateRequest, IdentityTokenRequest, Jwk, ALG_ES256, ALG_RS256,
+    };
+    use crate::internal::user::entity::token::get_time_sec;
+    use crate::internal::identity::usecase::webapi::mock_issuer::{new_mock_signer, MockIssuer, MOCK_CLIENT_ID, MOCK_EC_KID, MOCK_RSA_KID};
+
+    const ISSUER: &str = "https://idp.example.com";
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn provider() -> IdentityProvider {
+        IdentityProvider {
+            id: 1,
+            name: "corp".to_string(),
+            issuer: ISSUER.to_string(),
+            client_id: MOCK_CLIENT_ID.to_string(),
+            jit_provisioning: false,
+        }
+    }
+
+    fn keys(mock: &MockIssuer) -> Vec<Jwk> {
+        vec![
+            serde_json::from_value(mock.jwk(MOCK_RSA_KID)).unwrap(),
+            serde_json::from_value(mock.jwk(MOCK_EC_KID)).unwrap(),
+        ]
+    }
+
+    fn verify(mock: &MockIssuer, token: &str, nonce: Option<&str>, now: u64) -> Result<IdTokenClaims, String> {
+        let keys = keys(mock);
+        let jwt = parse_jwt(token)?;
+        let key = select_key(&keys, &jwt).ok_or("no key".to_string())?;
+        verify_id_token(&provider(), key, &jwt, nonce, now)
+    }
+
+    #[test]
+    fn discovery_url_test() {
+        assert_eq!(discovery_url("https://idp.example.com"), "https://idp.example.com/.well-known/openid-configuration");
+        assert_eq!(discovery_url("https://idp.example.com/tenant/"), "https://idp.example.com/tenant/.well-known/openid-configuration");
+    }
+
+    #[test]
+    fn parse_jwt_test() {
+        let mock = new_mock_signer(ISSUER);
+        let jwt = parse_jwt(&mock.id_token("alice", Value::Null)).unwrap();
+        assert_eq!((jwt.alg.as_str(), jwt.kid.as_deref()), (ALG_RS256, Some(MOCK_RSA_KID)));
+
+        let test_cases = vec! {
+            TestCase { input: mock.sign("HS256", None, &mock.claims("alice", Value::Null)), output: "unsupported alg HS256" },
+            TestCase { input: mock.sign("none", None, &mock.claims("alice", Value::Null)), output: "unsupported alg none" },
+            TestCase { input: "a.b".to_string(), output: "not a signed JWT" },
+            TestCase { input: format!("{}.x", mock.id_token("alice", Value::Null)), output: "not a signed JWT" },
+            TestCase { input: "!.b.c".to_string(), output: "JWT is not base64url" },
+        };
+        for test_case in test_cases {
+            assert_eq!(parse_jwt(&test_case.input).unwrap_err(), test_case.output);
+        }
+    }
+
+    #[test]
+    fn select_key_test() {
+        let mock = new_mock_signer(ISSUER);
+        let keys = keys(&mock);
+        let claims = mock.claims("alice", Value::Null);
+
+        let by_kid = parse_jwt(&mock.sign(ALG_ES256, Some(MOCK_EC_KID), &claims)).unwrap();
+        assert_eq!(select_key(&keys, &by_kid).unwrap().kid.as_deref(), Some(MOCK_EC_KID));
+
+        // Without a kid the only key of the right type is used.
+        let without_kid = parse_jwt(&mock.sign(ALG_RS256, None, &claims)).unwrap();
+        assert_eq!(select_key(&keys, &without_kid).unwrap().kid.as_deref(), Some(MOCK_RSA_KID));
+        let two_rsa_keys = vec![keys[0].clone(), Jwk { kid: Some("rsa-2".to_string()), ..keys[0].clone() }];
+        assert!(select_key(&two_rsa_keys, &without_kid).is_none());
+
+        // A kid must also match the algorithm, and encryption keys are never used.
+        let wrong_type = parse_jwt(&mock.sign(ALG_ES256, Some(MOCK_RSA_KID), &claims)).unwrap();
+        assert!(select_key(&keys, &wrong_type).is_none());
+        let encryption = vec![Jwk { key_use: Some("enc".to_string()), ..keys[0].clone() }];
+        assert!(select_key(&encryption, &parse_jwt(&mock.id_token("alice", Value::Null)).unwrap()).is_none());
+        assert!(select_key(&keys, &parse_jwt(&mock.sign(ALG_RS256, Some("rsa-2"), &claims)).unwrap()).is_none());
+    }
+
+    #[test]
+    fn verify_id_token_test() {
+        let mock = new_mock_signer(ISSUER);
+        let other = new_mock_signer("https://other.example.com");
+        let now = get_time_sec();
+
+        let claims = verify(&mock, &mock.id_token("alice", json!({
+            "email": "alice@example.com",
+            "preferred_username": "alice",
+            "given_name": "Alice",
+            "nonce": "n-0S6",
+        })), Some("n-0S6"), now).unwrap();
+        assert_eq!(claims, IdTokenClaims {
+            sub: "alice".to_string(),
+            email: Some("alice@example.com".to_string()),
+            preferred_username: Some("alice".to_string()),
+            given_name: Some("Alice".to_string()),
+            ..Default::default()
+        });
+
+        let es256 = mock.sign(ALG_ES256, Some(MOCK_EC_KID), &mock.claims("alice", json!({"aud": [MOCK_CLIENT_ID, "other"], "azp": MOCK_CLIENT_ID})));
+        assert_eq!(verify(&mock, &es256, None, now).unwrap().sub, "alice");
+
+        let token = mock.id_token("alice", Value::Null);
+        let (message, _) = token.rsplit_once('.').unwrap();
+        let forged = mock.id_token("mallory", Value::Null);
+        let (_, forged_signature) = forged.rsplit_once('.').unwrap();
+
+        let test_cases = vec! {
+            TestCase { input: (format!("{}.{}", message, forged_signature), None), output: "bad signature" },
+            TestCase { input: (format!("{}.AAAA", es256.rsplit_once('.').unwrap().0), None), output: "malformed signature" },
+            TestCase { input: (other.id_token("alice", Value::Null), None), output: "wrong issuer" },
+            TestCase { input: (mock.id_token("alice", json!({"aud": "other"})), None), output: "wrong audience" },
+            TestCase { input: (mock.id_token("alice", json!({"aud": ["other", MOCK_CLIENT_ID]})), None), output: "wrong authorized party" },
+            TestCase { input: (mock.id_token("alice", json!({"exp": now - 61})), None), output: "expired" },
+            TestCase { input: (mock.id_token("alice", json!({"exp": Value::Null})), None), output: "expired" },
+            TestCase { input: (mock.id_token("alice", json!({"iat": now + 61})), None), output: "issued in the future" },
+            TestCase { input: (mock.id_token("alice", json!({"nonce": "other"})), Some("n-0S6")), output: "wrong nonce" },
+            TestCase { input: (mock.id_token("alice", Value::Null), Some("n-0S6")), output: "wrong nonce" },
+            TestCase { input: (mock.id_token("", Value::Null), None), output: "missing or oversized sub" },
+        };
+        for test_case in test_cases {
+            let (token, nonce) = test_case.input;
+            assert_eq!(verify(&mock, &token, nonce, now).err().as_deref(), Some(test_case.output));
+        }
+
+        // Within the allowed clock skew.
+        assert!(verify(&mock, &mock.id_token("alice", json!({"exp": now - 30, "iat": now + 30})), None, now).is_ok());
+    }
+
+    #[test]
+    fn provisioned_username_test() {
+        let derived = "corp-2bd806c97f0e00af";
+        let test_cases = vec! {
+            TestCase {
+                input: IdTokenClaims { sub: "alice".to_string(), preferred_username: Some("JamesHolland".to_string()), email: Some("alice@example.com".to_string()), ..Default::default() },
+                output: derived,
+            },
+            TestCase {
+                input: IdTokenClaims { sub: "alice".to_string(), ..Default::default() },
+                output: derived,
+            },
+            TestCase {
+                input: IdTokenClaims { sub: "bob".to_string(), preferred_username: Some("alice.w".to_string()), ..Default::default() },
+                output: "corp-81b637d8fcd2c6da",
+            },
+        };
+        for test_case in test_cases {
+            assert_eq!(provisioned_username(&provider(), &test_case.input), test_case.output);
+        }
+    }
+
+    #[test]
+    fn provisioned_names_test() {
+        let claims = |given: Option<&str>, family: Option<&str>, name: Option<&str>| IdTokenClaims {
+            sub: "alice".to_string(),
+            given_name: given.map(|s| s.to_string()),
+            family_name: family.map(|s| s.to_string()),
+            name: name.map(|s| s.to_string()),
+            ..Default::default()
+        };
+        let test_cases = vec! {
+            TestCase { input: claims(Some("Alice"), Some("Walker"), Some("Ignored")), output: ("Alice", "Walker") },
+            TestCase { input: claims(Some("Alice"), None, None), output: ("Alice", "") },
+            TestCase { input: claims(None, Some("Walker"), Some(" Alice  Mary Walker ")), output: ("Alice", "Mary Walker") },
+            TestCase { input: claims(None, None, Some("Alice")), output: ("Alice", "") },
+            TestCase { input: claims(None, None, None), output: ("alice.w", "") },
+        };
+        for test_case in test_cases {
+            let (first, last) = provisioned_names(&test_case.input, "alice.w");
+            assert_eq!((first.as_str(), last.as_str()), test_case.output);
+        }
+
+        let (first, _) = provisioned_names(&claims(Some(&"A".repeat(80)), None, None), "alice.w");
+        assert_eq!(first.len(), 50);
+    }
+
+    #[test]
+    fn verify_identity_provider_create_request_test() {
+        let req = |name: &str, issuer: &str, client_id: &str| IdentityProviderCreateRequest {
+            name: name.to_string(),
+            issuer: issuer.to_string(),
+            client_id: client_id.to_string(),
+            jit_provisioning: false,
+        };
+        let name_error = Err("name must be 1 to 64 characters of a-z, 0-9 and -".to_string());
+        let issuer_error = Err("issuer must be an https URL without query or fragment, or http on localhost".to_string());
+        let long_name = "a".repeat(65);
+        let test_cases = vec! {
+            TestCase { input: req("corp", "https://idp.example.com", "app"), output: Ok(()) },
+            TestCase { input: req("azure-ad", "https://login.example.com/tenant/v2.0", "app"), output: Ok(()) },
+            TestCase { input: req("local", "http://localhost:8080", "app"), output: Ok(()) },
+            TestCase { input: req("local", "http://127.0.0.1:8080/realms/dev", "app"), output: Ok(()) },
+            TestCase { input: req("", "https://idp.example.com", "app"), output: name_error.clone() },
+            TestCase { input: req("Corp", "https://idp.example.com", "app"), output: name_error.clone() },
+            TestCase { input: req(&long_name, "https://idp.example.com", "app"), output: name_error },
+            TestCase { input: req("corp", "http://idp.example.com", "app"), output: issuer_error.clone() },
+            TestCase { input: req("corp", "https://idp.example.com?tenant=1", "app"), output: issuer_error.clone() },
+            TestCase { input: req("corp", "https://idp.example.com#x", "app"), output: issuer_error.clone() },
+            TestCase { input: req("corp", "https://", "app"), output: issuer_error.clone() },
+            TestCase { input: req("corp", "idp.example.com", "app"), output: issuer_error },
+            TestCase { input: req("corp", "https://idp.example.com", " "), output: Err("client_id must be 1 to 256 characters".to_string()) },
+        };
+        for test_case in test_cases {
+            assert_eq!(verify_identity_provider_create_request(&test_case.input), test_case.output);
+        }
+    }
+
+    #[test]
+    fn verify_identity_token_request_test() {
+        let req = |provider: &str, id_token: String| IdentityTokenRequest {
+            provider: provider.to_string(),
+            id_token,
+            nonce: None,
+        };
+        let test_cases = vec! {
+            TestCase { input: req("corp", "a.b.c".to_string()), output: Ok(()) },
+            TestCase { input: req("", "a.b.c".to_string()), output: Err("provider is required".to_string()) },
+            TestCase { input: req("corp", String::new()), output: Err("id_token must be 1 to 16384 bytes".to_string()) },
+            TestCase { input: req("corp", "a".repeat(16385)), output: Err("id_token must be 1 to 16384 bytes".to_string()) },
+        };
+        for test_case in test_cases {
+            assert_eq!(verify_identity_token_request(&test_case.input), test_case.output);
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::internal::identity::entity::identity::{
+    audited_identity, parse_jwt, provisioned_names, provisioned_username, select_key, verify_id_token, IdTokenClaims,
+    IdentityProvider, IdentityProviderCreateRequest, IdentityTokenRequest, UserIdentity, UserIdentityCreate,
+};
+use crate::internal::identity::usecase::traits::{IdentityUseCase, UseCase};
+use crate::internal::audit::entity::audit::{
+    diff, new_audit_record, AuditContext, AUDIT_IDENTITY_LINK, AUDIT_IDENTITY_PROVIDER_CREATE, AUDIT_IDENTITY_UNLINK,
+    AUDIT_LOGIN_MFA_CHALLENGE, AUDIT_LOGIN_SUCCESS,
+};
+use crate::internal::user::entity::token::get_time_sec;
+use crate::internal::user::entity::user::{UserAuthResult, UserCreateRequest, UserGet};
+use crate::internal::user::usecase::traits::UseCase as UserUseCaseTrait;
+use crate::internal::user::usecase::user::{LOGIN_ERROR, LOGIN_FAILURE, LOGIN_MFA_CHALLENGE, LOGIN_SUCCESS};
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+fn internal_error(err: sqlx::Error, op: &str) -> ErrorResponseUseCase {
+    tracing::error!(error = %err, "repo.{} failed", op);
+    ErrorResponseUseCase {
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        error_msg: "Internal server error".to_string(),
+    }
+}
+
+impl IdentityUseCase {
+    async fn provider(&self, name: &str) -> Result<IdentityProvider, ErrorResponseUseCase> {
+        match self.repo.identity_provider_get_by_name(name.to_string()).await {
+            Ok(res) => {
+                Ok(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Unknown identity provider".to_string(),
+                };
+
+                Err(res)
+            }
+            Err(err) => {
+                Err(internal_error(err, "identity_provider_get_by_name"))
+            }
+        }
+    }
+
+    async fn account(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
+        self.users.caller(username).await
+    }
+
+    // Checks the ID token against the provider's keys. A kid we don't know
+    // may mean the provider rotated its keys, so they are fetched again once.
+    async fn verify(&self, provider: &IdentityProvider, id_token: &str, nonce: Option<&str>) -> Result<IdTokenClaims, ErrorResponseUseCase> {
+        let unauthorized = |reason: &str| {
+            tracing::debug!(provider = %provider.name, reason, "ID token rejected");
+            ErrorResponseUseCase {
+                status_code: StatusCode::UNAUTHORIZED,
+                error_msg: "Unauthorized".to_string(),
+            }
+        };
+        let unavailable = |err: String| {
+            tracing::warn!(provider = %provider.name, error = %err, "can't fetch identity provider keys");
+            ErrorResponseUseCase {
+                status_code: StatusCode::BAD_GATEWAY,
+                error_msg: "Identity provider is unavailable".to_string(),
+            }
+        };
+
+        let jwt = match parse_jwt(id_token) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(unauthorized(&err));
+            }
+        };
+
+        let mut keys = self.web_api.issuer_keys(&provider.issuer, false).await.map_err(unavailable)?;
+        if select_key(&keys, &jwt).is_none() {
+            keys = self.web_api.issuer_keys(&provider.issuer, true).await.map_err(unavailable)?;
+        }
+
+        let key = match select_key(&keys, &jwt) {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(unauthorized("unknown signing key"));
+            }
+        };
+
+        verify_id_token(provider, key, &jwt, nonce, get_time_sec()).map_err(|err| unauthorized(&err))
+    }
+
+    async fn create_identity(&self, ctx: &AuditContext, account: &UserGet, provider: &IdentityProvider, claims: &IdTokenClaims) -> Result<UserIdentity, ErrorResponseUseCase> {
+        let identity = UserIdentityCreate {
+            user_id: account.id,
+            provider_id: provider.id,
+            subject: claims.sub.clone(),
+            email: claims.email.clone(),
+        };
+
+        let res = match self.repo.identity_create(identity).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(internal_error(err, "identity_create"));
+            }
+        };
+
+        let mut record = new_audit_record(ctx, AUDIT_IDENTITY_LINK);
+        record.actor_id = Some(account.id);
+        record.target_id = Some(account.id);
+        record.target_username = Some(account.username.clone());
+        record.changes = diff(&Value::Null, &audited_identity(&provider.name, &claims.sub));
+        self.audit.record(record).await;
+
+        Ok(res)
+    }
+
+    // Just-in-time provisioning: a new account for an identity seen for the
+    // first time. Its password is random, so it can only sign in through the
+    // provider until the user sets one.
+    async fn provision(&self, ctx: &AuditContext, provider: &IdentityProvider, claims: &IdTokenClaims) -> Result<UserGet, ErrorResponseUseCase> {
+        let username = provisioned_username(provider, claims);
+        let (firstname, lastname) = provisioned_names(claims, &username);
+        let user = UserCreateRequest {
+            username,
+            password: URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>()),
+            firstname,
+            lastname,
+        };
+        let account = self.users.user_create(ctx.clone(), user).await?;
+        self.create_identity(ctx, &account, provider, claims).await?;
+
+        Ok(account)
+    }
+
+    // The federated counterpart of UserUseCase::authenticate; also returns the
+    // account once it is known, for the audit log.
+    async fn authenticate(&self, ctx: &AuditContext, req: IdentityTokenRequest) -> (Option<UserGet>, Result<UserAuthResult, ErrorResponseUseCase>) {
+        let provider = match self.provider(&req.provider).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (None, Err(err));
+            }
+        };
+
+        let claims = match self.verify(&provider, &req.id_token, req.nonce.as_deref()).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (None, Err(err));
+            }
+        };
+
+        let account = match self.repo.identity_get(provider.id, claims.sub.clone()).await {
+            Ok(identity) => {
+                if let Err(err) = self.repo.identity_touch(identity.id).await {
+                    tracing::warn!(error = %err, "repo.identity_touch failed");
+                }
+
+                match self.users.user_get_by_id(identity.user_id).await {
+                    Ok(res) => {
+                        UserGet {
+                            id: res.id,
+                            username: res.username,
+                            firstname: res.firstname,
+                            lastname: res.lastname,
+                            status: res.status,
+                        }
+                    }
+                    Err(err) => {
+                        return (None, Err(err));
+                    }
+                }
+            }
+            Err(sqlx::Error::RowNotFound) if provider.jit_provisioning => {
+                match self.provision(ctx, &provider, &claims).await {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(err) => {
+                        return (None, Err(err));
+                    }
+                }
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::FORBIDDEN,
+                    error_msg: "No account is linked to this identity".to_string(),
+                };
+
+                return (None, Err(res));
+            }
+            Err(err) => {
+                return (None, Err(internal_error(err, "identity_get")));
+            }
+        };
+
+        let res = self.users.complete_login(ctx, &account).await;
+        (Some(account), res)
+    }
+}
+
+#[async_trait]
+impl UseCase for IdentityUseCase {
+    #[instrument(name = "identity_use_case.identity_provider_create", skip_all, fields(name = %req.name))]
+    async fn identity_provider_create(&self, ctx: AuditContext, req: IdentityProviderCreateRequest) -> Result<IdentityProvider, ErrorResponseUseCase> {
+        match self.repo.identity_provider_get_by_name(req.name.clone()).await {
+            Ok(_) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: format!("Identity provider with name={} already exists", req.name),
+                };
+
+                return Err(res);
+            }
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(err) => {
+                return Err(internal_error(err, "identity_provider_get_by_name"));
+            }
+        }
+
+        let res = match self.repo.identity_provider_create(req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(internal_error(err, "identity_provider_create"));
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_IDENTITY_PROVIDER_CREATE);
+        record.actor_id = self.users.actor_id(&ctx).await;
+        let after = json!({
+            "name": res.name,
+            "issuer": res.issuer,
+            "client_id": res.client_id,
+            "jit_provisioning": res.jit_provisioning,
+        });
+        record.changes = diff(&Value::Null, &after);
+        self.audit.record(record).await;
+
+        Ok(res)
+    }
+
+    #[instrument(name = "identity_use_case.identity_provider_list", skip(self))]
+    async fn identity_provider_list(&self) -> Result<Vec<IdentityProvider>, ErrorResponseUseCase> {
+        self.repo.identity_provider_list().await.map_err(|err| internal_error(err, "identity_provider_list"))
+    }
+
+    #[instrument(name = "identity_use_case.identity_login", skip_all, fields(provider = %req.provider))]
+    async fn identity_login(&self, ctx: AuditContext, req: IdentityTokenRequest) -> Result<UserAuthResult, ErrorResponseUseCase> {
+        let (account, res) = self.authenticate(&ctx, req).await;
+
+        let (result, action) = match &res {
+            Ok(UserAuthResult::Tokens(_)) => (LOGIN_SUCCESS, Some(AUDIT_LOGIN_SUCCESS)),
+            Ok(UserAuthResult::MfaRequired(_)) => (LOGIN_MFA_CHALLENGE, Some(AUDIT_LOGIN_MFA_CHALLENGE)),
+            Err(err) if err.status_code == StatusCode::UNAUTHORIZED || err.status_code == StatusCode::FORBIDDEN => (LOGIN_FAILURE, None),
+            Err(_) => (LOGIN_ERROR, None),
+        };
+        self.users.metrics.login(result);
+
+        // Rejected tokens name no account we could attribute them to.
+        if let (Some(action), Some(account)) = (action, account) {
+            self.users.audit_login(&ctx, action, Some(account.id), account.username).await;
+        }
+
+        res
+    }
+
+    #[instrument(name = "identity_use_case.identity_link", skip_all, fields(provider = %req.provider))]
+    async fn identity_link(&self, ctx: AuditContext, username: String, req: IdentityTokenRequest) -> Result<UserIdentity, ErrorResponseUseCase> {
+        let account = self.account(username).await?;
+        let provider = self.provider(&req.provider).await?;
+        let claims = self.verify(&provider, &req.id_token, req.nonce.as_deref()).await?;
+
+        match self.repo.identity_get(provider.id, claims.sub.clone()).await {
+            Ok(res) if res.user_id == account.id => {
+                Ok(res)
+            }
+            Ok(_) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: "This identity is linked to another account".to_string(),
+                };
+
+                Err(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                self.create_identity(&ctx, &account, &provider, &claims).await
+            }
+            Err(err) => {
+                Err(internal_error(err, "identity_get"))
+            }
+        }
+    }
+
+    #[instrument(name = "identity_use_case.identity_list", skip(self))]
+    async fn identity_list(&self, username: String) -> Result<Vec<UserIdentity>, ErrorResponseUseCase> {
+        let account = self.account(username).await?;
+        self.repo.identity_list_by_user(account.id).await.map_err(|err| internal_error(err, "identity_list_by_user"))
+    }
+
+    #[instrument(name = "identity_use_case.identity_unlink", skip(self, ctx))]
+    async fn identity_unlink(&self, ctx: AuditContext, username: String, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let account = self.account(username).await?;
+
+        let identity = match self.repo.identity_delete(account.id, id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: "Identity not found".to_string(),
+                };
+
+                return Err(res);
+            }
+            Err(err) => {
+                return Err(internal_error(err, "identity_delete"));
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_IDENTITY_UNLINK);
+        record.actor_id = Some(account.id);
+        record.target_id = Some(account.id);
+        record.target_username = Some(account.username);
+        record.changes = diff(&audited_identity(&identity.provider, &identity.subject), &Value::Null);
+        self.audit.record(record).await;
+
+        Ok(())
+    }
+}
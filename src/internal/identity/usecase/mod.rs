@@ -0,0 +1,4 @@
+pub mod traits;
+pub mod identity;
+pub mod repo;
+pub mod webapi;
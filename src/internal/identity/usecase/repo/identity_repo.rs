@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::identity::entity::identity::{IdentityProvider, IdentityProviderCreateRequest, UserIdentity, UserIdentityCreate};
+use crate::internal::identity::usecase::repo::repo::IdentityRepo;
+use crate::internal::identity::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+const PROVIDER_COLUMNS: &str = "id, name, issuer, client_id, jit_provisioning";
+// Over an alias i of tbl_user_identity joined with p of tbl_identity_provider.
+const IDENTITY_COLUMNS: &str = "i.id, i.user_id, p.name AS provider, i.subject, i.email, i.create_ts, i.last_login_ts";
+
+#[async_trait]
+impl Repo for IdentityRepo {
+    #[instrument(
+        name = "identity_repo.identity_provider_create",
+        skip_all,
+        fields(name = %req.name, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_provider_create(&self, req: IdentityProviderCreateRequest) -> Result<IdentityProvider, Error> {
+        let sql = format!(
+            "INSERT INTO tbl_identity_provider(name, issuer, client_id, jit_provisioning) VALUES($1, $2, $3, $4) RETURNING {}",
+            PROVIDER_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, IdentityProvider>(&sql)
+            .bind(req.name)
+            .bind(req.issuer)
+            .bind(req.client_id)
+            .bind(req.jit_provisioning);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_provider_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_provider_list(&self) -> Result<Vec<IdentityProvider>, Error> {
+        let sql = format!("SELECT {} FROM tbl_identity_provider ORDER BY id", PROVIDER_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, IdentityProvider>(&sql);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_provider_get_by_name",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_provider_get_by_name(&self, name: String) -> Result<IdentityProvider, Error> {
+        let sql = format!("SELECT {} FROM tbl_identity_provider WHERE name=$1", PROVIDER_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, IdentityProvider>(&sql).bind(name);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_get(&self, provider_id: i32, subject: String) -> Result<UserIdentity, Error> {
+        let sql = format!(
+            "SELECT {} FROM tbl_user_identity i JOIN tbl_identity_provider p ON p.id=i.provider_id \
+            WHERE i.provider_id=$1 AND i.subject=$2",
+            IDENTITY_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, UserIdentity>(&sql).bind(provider_id).bind(subject);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_create",
+        skip_all,
+        fields(user_id = identity.user_id, provider_id = identity.provider_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_create(&self, identity: UserIdentityCreate) -> Result<UserIdentity, Error> {
+        let sql = format!(
+            "WITH i AS (INSERT INTO tbl_user_identity(user_id, provider_id, subject, email) VALUES($1, $2, $3, $4) RETURNING *) \
+            SELECT {} FROM i JOIN tbl_identity_provider p ON p.id=i.provider_id",
+            IDENTITY_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, UserIdentity>(&sql)
+            .bind(identity.user_id)
+            .bind(identity.provider_id)
+            .bind(identity.subject)
+            .bind(identity.email);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_list_by_user(&self, user_id: i32) -> Result<Vec<UserIdentity>, Error> {
+        let sql = format!(
+            "SELECT {} FROM tbl_user_identity i JOIN tbl_identity_provider p ON p.id=i.provider_id \
+            WHERE i.user_id=$1 ORDER BY i.id",
+            IDENTITY_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, UserIdentity>(&sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_delete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_delete(&self, user_id: i32, id: i32) -> Result<UserIdentity, Error> {
+        let sql = format!(
+            "WITH i AS (DELETE FROM tbl_user_identity WHERE id=$1 AND user_id=$2 RETURNING *) \
+            SELECT {} FROM i JOIN tbl_identity_provider p ON p.id=i.provider_id",
+            IDENTITY_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, UserIdentity>(&sql).bind(id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "identity_repo.identity_touch",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn identity_touch(&self, id: i32) -> Result<(), Error> {
+        let sql = "UPDATE tbl_user_identity SET last_login_ts=now() WHERE id=$1";
+        record_statement(sql);
+        let query = sqlx::query(sql).bind(id);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+    use sqlx::Error;
+
+    use crate::internal::identity::entity::identity::{IdentityProviderCreateRequest, UserIdentityCreate};
+    use crate::internal::identity::usecase::repo::repo::new_identity_repo;
+    use crate::internal::identity::usecase::traits::Repo;
+    use crate::internal::user::entity::user::UserCreateRequest;
+    use crate::internal::user::usecase::repo::repo::new_user_repo;
+    use crate::internal::user::usecase::traits::Repo as UserRepo;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    fn provider_create(name: &str, jit_provisioning: bool) -> IdentityProviderCreateRequest {
+        IdentityProviderCreateRequest {
+            name: name.to_string(),
+            issuer: format!("https://{}.example.com", name),
+            client_id: "rust-clean".to_string(),
+            jit_provisioning,
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn identity_provider_test() {
+        let test_db = new_test_db().await;
+        let repo = new_identity_repo(web::Data::new(test_db.db.clone()));
+
+        let corp = repo.identity_provider_create(provider_create("corp", true)).await.unwrap();
+        repo.identity_provider_create(provider_create("partner", false)).await.unwrap();
+        assert_eq!((corp.issuer.as_str(), corp.client_id.as_str(), corp.jit_provisioning), ("https://corp.example.com", "rust-clean", true));
+        assert!(repo.identity_provider_create(provider_create("corp", false)).await.is_err());
+
+        let providers = repo.identity_provider_list().await.unwrap();
+        assert_eq!(providers.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["corp", "partner"]);
+        assert_eq!(repo.identity_provider_get_by_name("corp".to_string()).await.unwrap(), corp);
+        assert!(matches!(repo.identity_provider_get_by_name("unknown".to_string()).await, Err(Error::RowNotFound)));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_identity_test() {
+        let test_db = new_test_db().await;
+        let repo = new_identity_repo(web::Data::new(test_db.db.clone()));
+        let users = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = users.user_create(UserCreateRequest {
+            username: "JamesHolland".to_string(),
+            password: "hashed-password".to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+        }).await.unwrap();
+        let corp = repo.identity_provider_create(provider_create("corp", false)).await.unwrap();
+        let partner = repo.identity_provider_create(provider_create("partner", false)).await.unwrap();
+
+        let identity = |provider_id: i32, subject: &str| UserIdentityCreate {
+            user_id: user.id,
+            provider_id,
+            subject: subject.to_string(),
+            email: Some("james@example.com".to_string()),
+        };
+        let created = repo.identity_create(identity(corp.id, "alice")).await.unwrap();
+        assert_eq!((created.user_id, created.provider.as_str(), created.subject.as_str()), (user.id, "corp", "alice"));
+        assert_eq!((created.email.as_deref(), created.last_login_ts), (Some("james@example.com"), None));
+        repo.identity_create(identity(partner.id, "alice")).await.unwrap();
+        // A subject is unique per provider.
+        assert!(repo.identity_create(identity(corp.id, "alice")).await.is_err());
+
+        let found = repo.identity_get(corp.id, "alice".to_string()).await.unwrap();
+        assert_eq!(found, created);
+        assert!(matches!(repo.identity_get(corp.id, "bob".to_string()).await, Err(Error::RowNotFound)));
+
+        repo.identity_touch(created.id).await.unwrap();
+        let touched = repo.identity_get(corp.id, "alice".to_string()).await.unwrap();
+        assert!(touched.last_login_ts.is_some());
+
+        let identities = repo.identity_list_by_user(user.id).await.unwrap();
+        assert_eq!(identities.iter().map(|i| i.provider.as_str()).collect::<Vec<_>>(), vec!["corp", "partner"]);
+
+        assert!(matches!(repo.identity_delete(user.id + 1, created.id).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.identity_delete(user.id, created.id).await.unwrap().subject, "alice");
+        assert!(matches!(repo.identity_delete(user.id, created.id).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.identity_list_by_user(user.id).await.unwrap().len(), 1);
+
+        // Identities go with their account.
+        users.user_delete_by_id(user.id).await.unwrap();
+        assert!(matches!(repo.identity_get(partner.id, "alice".to_string()).await, Err(Error::RowNotFound)));
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::Error;
+
+use crate::internal::identity::entity::identity::{IdentityProvider, IdentityProviderCreateRequest, UserIdentity, UserIdentityCreate};
+use crate::internal::identity::usecase::traits::Repo;
+
+// In-memory stand-in for IdentityRepo, used by tests that must not touch Postgres.
+
+#[derive(Clone)]
+pub struct MemoryIdentity {
+    pub provider_id: i32,
+    pub identity: UserIdentity,
+}
+
+#[derive(Default)]
+pub struct MemoryIdentityRepo {
+    pub providers: Mutex<Vec<IdentityProvider>>,
+    pub identities: Mutex<Vec<MemoryIdentity>>,
+}
+
+pub fn new_memory_identity_repo() -> MemoryIdentityRepo {
+    MemoryIdentityRepo::default()
+}
+
+#[async_trait]
+impl Repo for MemoryIdentityRepo {
+    async fn identity_provider_create(&self, req: IdentityProviderCreateRequest) -> Result<IdentityProvider, Error> {
+        let mut providers = self.providers.lock().unwrap();
+        let provider = IdentityProvider {
+            id: providers.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+            name: req.name,
+            issuer: req.issuer,
+            client_id: req.client_id,
+            jit_provisioning: req.jit_provisioning,
+        };
+
+        providers.push(provider.clone());
+        Ok(provider)
+    }
+
+    async fn identity_provider_list(&self) -> Result<Vec<IdentityProvider>, Error> {
+        Ok(self.providers.lock().unwrap().clone())
+    }
+
+    async fn identity_provider_get_by_name(&self, name: String) -> Result<IdentityProvider, Error> {
+        self.providers.lock().unwrap().iter().find(|p| p.name == name).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn identity_get(&self, provider_id: i32, subject: String) -> Result<UserIdentity, Error> {
+        self.identities.lock().unwrap().iter()
+            .find(|i| i.provider_id == provider_id && i.identity.subject == subject)
+            .map(|i| i.identity.clone())
+            .ok_or(Error::RowNotFound)
+    }
+
+    async fn identity_create(&self, identity: UserIdentityCreate) -> Result<UserIdentity, Error> {
+        let provider = self.providers.lock().unwrap().iter().find(|p| p.id == identity.provider_id).cloned().ok_or(Error::RowNotFound)?;
+        let mut identities = self.identities.lock().unwrap();
+        let created = UserIdentity {
+            id: identities.iter().map(|i| i.identity.id).max().unwrap_or(0) + 1,
+            user_id: identity.user_id,
+            provider: provider.name,
+            subject: identity.subject,
+            email: identity.email,
+            create_ts: Utc::now(),
+            last_login_ts: None,
+        };
+
+        identities.push(MemoryIdentity { provider_id: provider.id, identity: created.clone() });
+        Ok(created)
+    }
+
+    async fn identity_list_by_user(&self, user_id: i32) -> Result<Vec<UserIdentity>, Error> {
+        Ok(self.identities.lock().unwrap().iter().filter(|i| i.identity.user_id == user_id).map(|i| i.identity.clone()).collect())
+    }
+
+    async fn identity_delete(&self, user_id: i32, id: i32) -> Result<UserIdentity, Error> {
+        let mut identities = self.identities.lock().unwrap();
+        match identities.iter().position(|i| i.identity.id == id && i.identity.user_id == user_id) {
+            Some(index) => {
+                Ok(identities.remove(index).identity)
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn identity_touch(&self, id: i32) -> Result<(), Error> {
+        if let Some(identity) = self.identities.lock().unwrap().iter_mut().find(|i| i.identity.id == id) {
+            identity.identity.last_login_ts = Some(Utc::now());
+        }
+        Ok(())
+    }
+}
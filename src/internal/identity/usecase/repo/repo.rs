@@ -0,0 +1,13 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct IdentityRepo {
+    pub db: Data<Db>,
+}
+
+pub fn new_identity_repo(db: Data<Db>) -> IdentityRepo {
+    IdentityRepo{
+        db
+    }
+}
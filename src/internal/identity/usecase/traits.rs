@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::internal::identity::entity::identity::{
+    IdentityProvider, IdentityProviderCreateRequest, IdentityTokenRequest, Jwk, UserIdentity, UserIdentityCreate,
+};
+use crate::internal::audit::entity::audit::AuditContext;
+use crate::internal::audit::usecase::traits::AuditUseCase;
+use crate::internal::user::entity::user::UserAuthResult;
+use crate::internal::user::usecase::traits::UserUseCase;
+use crate::internal::controller::response;
+
+#[derive(Clone)]
+pub struct IdentityUseCase {
+    pub repo: Arc<dyn Repo>,
+    pub web_api: Arc<dyn WebApi>,
+    pub users: UserUseCase,
+    pub audit: AuditUseCase,
+}
+
+pub fn new_identity_use_case(repo: Arc<dyn Repo>, web_api: Arc<dyn WebApi>, users: UserUseCase, audit: AuditUseCase) -> IdentityUseCase {
+    IdentityUseCase {
+        repo,
+        web_api,
+        users,
+        audit,
+    }
+}
+
+#[async_trait]
+pub trait UseCase {
+    async fn identity_provider_create(&self, ctx: AuditContext, req: IdentityProviderCreateRequest) -> Result<IdentityProvider, response::ErrorResponseUseCase>;
+    async fn identity_provider_list(&self) -> Result<Vec<IdentityProvider>, response::ErrorResponseUseCase>;
+    // Tokens, or an MFA challenge, for the account linked to the identity;
+    // with JIT provisioning an unknown identity gets a new account.
+    async fn identity_login(&self, ctx: AuditContext, req: IdentityTokenRequest) -> Result<UserAuthResult, response::ErrorResponseUseCase>;
+    async fn identity_link(&self, ctx: AuditContext, username: String, req: IdentityTokenRequest) -> Result<UserIdentity, response::ErrorResponseUseCase>;
+    async fn identity_list(&self, username: String) -> Result<Vec<UserIdentity>, response::ErrorResponseUseCase>;
+    async fn identity_unlink(&self, ctx: AuditContext, username: String, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn identity_provider_create(&self, req: IdentityProviderCreateRequest) -> Result<IdentityProvider, sqlx::Error>;
+    async fn identity_provider_list(&self) -> Result<Vec<IdentityProvider>, sqlx::Error>;
+    async fn identity_provider_get_by_name(&self, name: String) -> Result<IdentityProvider, sqlx::Error>;
+    async fn identity_get(&self, provider_id: i32, subject: String) -> Result<UserIdentity, sqlx::Error>;
+    async fn identity_create(&self, identity: UserIdentityCreate) -> Result<UserIdentity, sqlx::Error>;
+    async fn identity_list_by_user(&self, user_id: i32) -> Result<Vec<UserIdentity>, sqlx::Error>;
+    // Deletes the user's identity and returns it; RowNotFound when the user has no such identity.
+    async fn identity_delete(&self, user_id: i32, id: i32) -> Result<UserIdentity, sqlx::Error>;
+    async fn identity_touch(&self, id: i32) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+pub trait WebApi: Send + Sync {
+    // The provider's signing keys, from discovery and its JWKS. Cached; with
+    // refresh the cache is bypassed unless it was filled moments ago.
+    async fn issuer_keys(&self, issuer: &str, refresh: bool) -> Result<Vec<Jwk>, String>;
+}
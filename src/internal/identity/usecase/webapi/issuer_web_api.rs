@@ -0,0 +1,64 @@
+use std::time::Instant;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use tracing::{instrument, Level};
+
+use crate::internal::identity::entity::identity::{discovery_url, Jwk, JwkSet, ProviderMetadata};
+use crate::internal::identity::usecase::webapi::web_api::{CachedKeys, IssuerWebApi};
+use crate::internal::identity::usecase::traits::WebApi;
+
+impl IssuerWebApi {
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let res = match self.client.get(url).send().await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(format!("GET {}: {}", url, err));
+            }
+        };
+
+        if !res.status().is_success() {
+            return Err(format!("GET {}: status {}", url, res.status()));
+        }
+
+        res.json::<T>().await.map_err(|err| format!("GET {}: {}", url, err))
+    }
+
+    fn cached(&self, issuer: &str, refresh: bool) -> Option<Vec<Jwk>> {
+        let cache = self.cache.lock().unwrap();
+        let cached = cache.get(issuer)?;
+        let age = cached.fetched_at.elapsed();
+
+        if age < self.ttl && (!refresh || age < self.min_refresh) {
+            Some(cached.keys.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl WebApi for IssuerWebApi {
+    #[instrument(name = "issuer_web_api.issuer_keys", skip(self), fields(otel.kind = "client"), err(level = Level::DEBUG))]
+    async fn issuer_keys(&self, issuer: &str, refresh: bool) -> Result<Vec<Jwk>, String> {
+        if let Some(keys) = self.cached(issuer, refresh) {
+            return Ok(keys);
+        }
+
+        let metadata: ProviderMetadata = self.get_json(&discovery_url(issuer)).await?;
+        // OIDC Discovery 4.3: the document must be about the issuer it was
+        // fetched for.
+        if metadata.issuer != issuer {
+            return Err(format!("discovery document is for issuer {}", metadata.issuer));
+        }
+
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        self.cache.lock().unwrap().insert(issuer.to_string(), CachedKeys {
+            keys: jwks.keys.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(jwks.keys)
+    }
+}
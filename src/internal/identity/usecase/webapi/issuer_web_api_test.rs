@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+    use serde_json::{json, Value};
+
+    use crate::internal::identity::entity::identity::Jwk;
+    use crate::internal::identity::usecase::traits::WebApi;
+    use crate::internal::identity::usecase::webapi::mock_issuer::{new_mock_issuer, MOCK_EC_KID, MOCK_RSA_KID};
+    use crate::internal::identity::usecase::webapi::web_api::{new_issuer_web_api, ISSUER_REQUEST_TIMEOUT};
+
+    fn kids(keys: &[Jwk]) -> Vec<&str> {
+        keys.iter().filter_map(|key| key.kid.as_deref()).collect()
+    }
+
+    #[actix_web::test]
+    async fn issuer_keys_cache_test() {
+        let mock = new_mock_issuer();
+        let web_api = new_issuer_web_api(ISSUER_REQUEST_TIMEOUT);
+
+        let keys = web_api.issuer_keys(&mock.issuer, false).await.unwrap();
+        assert_eq!(kids(&keys), vec![MOCK_RSA_KID]);
+        assert_eq!(keys[0].kty, "RSA");
+
+        // Cached, and a refresh right after a fetch is refused.
+        *mock.jwks.lock().unwrap() = json!({"keys": [mock.jwk(MOCK_RSA_KID), mock.jwk(MOCK_EC_KID)]});
+        assert_eq!(kids(&web_api.issuer_keys(&mock.issuer, false).await.unwrap()), vec![MOCK_RSA_KID]);
+        assert_eq!(kids(&web_api.issuer_keys(&mock.issuer, true).await.unwrap()), vec![MOCK_RSA_KID]);
+        assert_eq!((mock.discovery_hits.load(Ordering::SeqCst), mock.jwks_hits.load(Ordering::SeqCst)), (1, 1));
+
+        let mut web_api = new_issuer_web_api(ISSUER_REQUEST_TIMEOUT);
+        web_api.min_refresh = Duration::ZERO;
+        web_api.issuer_keys(&mock.issuer, false).await.unwrap();
+        assert_eq!(mock.jwks_hits.load(Ordering::SeqCst), 2);
+        assert_eq!(kids(&web_api.issuer_keys(&mock.issuer, true).await.unwrap()), vec![MOCK_RSA_KID, MOCK_EC_KID]);
+        assert_eq!(mock.jwks_hits.load(Ordering::SeqCst), 3);
+
+        // Expired entries are fetched again.
+        web_api.ttl = Duration::ZERO;
+        web_api.issuer_keys(&mock.issuer, false).await.unwrap();
+        assert_eq!(mock.jwks_hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[actix_web::test]
+    async fn issuer_keys_error_test() {
+        let mock = new_mock_issuer();
+        let web_api = new_issuer_web_api(ISSUER_REQUEST_TIMEOUT);
+
+        *mock.jwks.lock().unwrap() = Value::Null;
+        let err = web_api.issuer_keys(&mock.issuer, false).await.unwrap_err();
+        assert_eq!(err, format!("GET {}/jwks: status 503 Service Unavailable", mock.issuer));
+
+        // A discovery document for another issuer is not trusted.
+        *mock.discovery.lock().unwrap() = json!({"issuer": "https://evil.example.com", "jwks_uri": format!("{}/jwks", mock.issuer)});
+        let err = web_api.issuer_keys(&mock.issuer, false).await.unwrap_err();
+        assert_eq!(err, "discovery document is for issuer https://evil.example.com");
+
+        *mock.discovery.lock().unwrap() = json!({"issuer": mock.issuer});
+        let err = web_api.issuer_keys(&mock.issuer, false).await.unwrap_err();
+        assert!(err.starts_with(&format!("GET {}/.well-known/openid-configuration: ", mock.issuer)), "{}", err);
+
+        // Failures are not cached.
+        *mock.discovery.lock().unwrap() = json!({"issuer": mock.issuer, "jwks_uri": format!("{}/jwks", mock.issuer)});
+        *mock.jwks.lock().unwrap() = json!({"keys": [mock.jwk(MOCK_EC_KID)]});
+        assert_eq!(kids(&web_api.issuer_keys(&mock.issuer, false).await.unwrap()), vec![MOCK_EC_KID]);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod web_api;
+pub mod issuer_web_api;
+pub mod issuer_web_api_test;
+#[cfg(test)]
+pub mod mock_issuer;
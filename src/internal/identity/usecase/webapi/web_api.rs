@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::internal::identity::entity::identity::Jwk;
+
+pub const ISSUER_KEYS_TTL: Duration = Duration::from_secs(60 * 60);
+// Tokens naming an unknown kid trigger a refetch, at most this often, so
+// forged tokens can't make us hammer the provider.
+pub const ISSUER_KEYS_MIN_REFRESH: Duration = Duration::from_secs(60);
+pub const ISSUER_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct CachedKeys {
+    pub keys: Vec<Jwk>,
+    pub fetched_at: Instant,
+}
+
+// Fetches provider metadata and keys over HTTP and keeps the keys per issuer.
+pub struct IssuerWebApi {
+    pub client: reqwest::Client,
+    pub cache: Mutex<HashMap<String, CachedKeys>>,
+    pub ttl: Duration,
+    pub min_refresh: Duration,
+}
+
+pub fn new_issuer_web_api(timeout: Duration) -> IssuerWebApi {
+    IssuerWebApi {
+        client: reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("can't build HTTP client"),
+        cache: Mutex::new(HashMap::new()),
+        ttl: ISSUER_KEYS_TTL,
+        min_refresh: ISSUER_KEYS_MIN_REFRESH,
+    }
+}
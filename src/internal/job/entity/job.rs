@@ -0,0 +1,144 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::pkg::cron::cron::Cron;
+
+// Kinds of the built-in recurring jobs.
+pub const JOB_SESSION_PURGE: &str = "session.purge_expired";
+pub const JOB_JOB_PURGE: &str = "job.purge_finished";
+
+pub const JOB_DEFAULT_LIMIT: i64 = 50;
+pub const JOB_MAX_LIMIT: i64 = 500;
+
+// The first retry waits JOB_RETRY_BASE_DELAY, each further one twice as long,
+// up to JOB_RETRY_MAX_DELAY.
+pub const JOB_RETRY_BASE_DELAY: i64 = 10; // second
+pub const JOB_RETRY_MAX_DELAY: i64 = 3600; // second
+
+// Longer errors are cut, so a runaway message can't bloat the table.
+pub const JOB_ERROR_MAX_LEN: usize = 2048;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending, // waiting for run_at, also between retries
+    Running,
+    Done,
+    Dead, // failed max_attempts times
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Dead => "dead",
+        }
+    }
+}
+
+pub const JOB_STATUSES: [JobStatus; 4] = [JobStatus::Pending, JobStatus::Running, JobStatus::Done, JobStatus::Dead];
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    #[schema(value_type = Object)]
+    pub payload: Value,
+    pub status: JobStatus,
+    pub attempts: i32, // runs started so far
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>, // not before; for a pending job, when it runs next
+    pub locked_until: Option<DateTime<Utc>>, // while running
+    pub last_error: Option<String>,
+    pub create_ts: DateTime<Utc>,
+    pub update_ts: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobCreate {
+    pub kind: String,
+    pub payload: Value,
+    pub run_at: Option<DateTime<Utc>>, // None runs it as soon as a worker is free
+    pub max_attempts: i32,
+}
+
+// A job enqueued whenever cron fires. name identifies the schedule across
+// instances and restarts.
+#[derive(Debug, Clone)]
+pub struct JobSchedule {
+    pub name: String,
+    pub kind: String,
+    pub cron: Cron,
+}
+
+// A job queued by an admin, e.g. to purge expired sessions now rather than at
+// the next scheduled run.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct JobCreateRequest {
+    pub kind: String,
+    pub run_at: Option<DateTime<Utc>>, // None runs it as soon as a worker is free
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobQuery {
+    /// One of pending, running, done, dead
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    /// Page size, 1 to 500 (default 50)
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// One page of jobs, newest first; total counts every match.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct JobPage {
+    pub items: Vec<Job>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+// How long a job waits after its attempts-th failed run.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 32) as u32 - 1;
+    let delay = JOB_RETRY_BASE_DELAY.saturating_mul(2_i64.saturating_pow(exponent));
+
+    Duration::seconds(delay.min(JOB_RETRY_MAX_DELAY))
+}
+
+pub fn truncate_job_error(err: &str) -> String {
+    match err.char_indices().nth(JOB_ERROR_MAX_LEN) {
+        Some((end, _)) => format!("{}...", &err[..end]),
+        None => err.to_string(),
+    }
+}
+
+// verifying
+
+pub fn verify_job_query(req: &JobQuery) -> Result<(), String> {
+    if let Some(status) = &req.status {
+        if !JOB_STATUSES.iter().any(|res| res.as_str() == status) {
+            let statuses: Vec<&str> = JOB_STATUSES.iter().map(JobStatus::as_str).collect();
+            return Err(format!("status must be one of {}", statuses.join(", ")));
+        }
+    }
+
+    if let Some(limit) = req.limit {
+        if !(1..=JOB_MAX_LIMIT).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", JOB_MAX_LIMIT));
+        }
+    }
+
+    if req.offset.unwrap_or(0) < 0 {
+        return Err("offset must not be negative".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::internal::job::entity::job::{retry_delay, truncate_job_error, verify_job_query, JobQuery, JOB_ERROR_MAX_LEN};
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    #[test]
+    fn retry_delay_test() {
+        let test_cases = vec! {
+            TestCase { input: 1, output: Duration::seconds(10) },
+            TestCase { input: 2, output: Duration::seconds(20) },
+            TestCase { input: 4, output: Duration::seconds(80) },
+            TestCase { input: 9, output: Duration::seconds(2560) },
+            TestCase { input: 10, output: Duration::seconds(3600) },
+            TestCase { input: 1000, output: Duration::seconds(3600) },
+            TestCase { input: 0, output: Duration::seconds(10) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(retry_delay(test_case.input), test_case.output, "attempts {}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn truncate_job_error_test() {
+        assert_eq!(truncate_job_error("connection refused"), "connection refused");
+
+        let exact = "é".repeat(JOB_ERROR_MAX_LEN);
+        assert_eq!(truncate_job_error(&exact), exact);
+
+        let long = "é".repeat(JOB_ERROR_MAX_LEN + 1);
+        assert_eq!(truncate_job_error(&long), format!("{}...", exact));
+    }
+
+    #[test]
+    fn verify_job_query_test() {
+        let test_cases = vec! {
+            TestCase {
+                input: JobQuery::default(),
+                output: Ok(()),
+            },
+            TestCase {
+                input: JobQuery { status: Some("dead".to_string()), kind: Some("session.purge_expired".to_string()), limit: Some(500), offset: Some(0) },
+                output: Ok(()),
+            },
+            TestCase {
+                input: JobQuery { status: Some("failed".to_string()), ..Default::default() },
+                output: Err("status must be one of pending, running, done, dead".to_string()),
+            },
+            TestCase {
+                input: JobQuery { limit: Some(0), ..Default::default() },
+                output: Err("limit must be between 1 and 500".to_string()),
+            },
+            TestCase {
+                input: JobQuery { limit: Some(501), ..Default::default() },
+                output: Err("limit must be between 1 and 500".to_string()),
+            },
+            TestCase {
+                input: JobQuery { offset: Some(-1), ..Default::default() },
+                output: Err("offset must not be negative".to_string()),
+            },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_job_query(&test_case.input), test_case.output);
+        }
+    }
+}
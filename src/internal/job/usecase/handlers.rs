@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde_json::Value;
+
+use crate::config::config::JobsConfig;
+use crate::internal::job::entity::job::{JobSchedule, JOB_JOB_PURGE, JOB_SESSION_PURGE};
+use crate::internal::job::usecase::traits::{JobHandler, JobHandlers, Repo};
+use crate::internal::user::usecase::traits::UserUseCase;
+use crate::pkg::cron::cron::parse_cron;
+
+// The built-in jobs: housekeeping for tables nothing else cleans up.
+
+pub struct SessionPurgeHandler {
+    pub users: UserUseCase,
+}
+
+#[async_trait]
+impl JobHandler for SessionPurgeHandler {
+    async fn run(&self, _payload: Value) -> Result<(), String> {
+        let sessions = self.users.session_purge_expired().await.map_err(|err| err.error_msg)?;
+        tracing::info!(sessions, "purged expired sessions");
+
+        Ok(())
+    }
+}
+
+pub struct JobPurgeHandler {
+    pub repo: Arc<dyn Repo>,
+    pub retention: Duration,
+}
+
+#[async_trait]
+impl JobHandler for JobPurgeHandler {
+    async fn run(&self, _payload: Value) -> Result<(), String> {
+        let jobs = self.repo.job_purge_finished(Utc::now() - self.retention).await.map_err(|err| err.to_string())?;
+        tracing::info!(jobs, "purged finished jobs");
+
+        Ok(())
+    }
+}
+
+pub fn new_job_handlers(cfg: &JobsConfig, repo: Arc<dyn Repo>, users: UserUseCase) -> JobHandlers {
+    let mut handlers = JobHandlers::new();
+    handlers.insert(JOB_SESSION_PURGE.to_string(), Arc::new(SessionPurgeHandler { users }) as Arc<dyn JobHandler>);
+    handlers.insert(JOB_JOB_PURGE.to_string(), Arc::new(JobPurgeHandler { repo, retention: Duration::days(cfg.retention as i64) }));
+
+    handlers
+}
+
+// One schedule per built-in job, named after its kind.
+pub fn new_job_schedules(cfg: &JobsConfig) -> Result<Vec<JobSchedule>, String> {
+    let mut schedules = Vec::new();
+    for (kind, cron) in [(JOB_SESSION_PURGE, &cfg.session_purge_schedule), (JOB_JOB_PURGE, &cfg.job_purge_schedule)] {
+        schedules.push(JobSchedule {
+            name: kind.to_string(),
+            kind: kind.to_string(),
+            cron: parse_cron(cron)?,
+        });
+    }
+
+    Ok(schedules)
+}
@@ -0,0 +1,271 @@
+use std::panic::AssertUnwindSafe;
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use serde_json::{json, Value};
+use tracing::field::Empty;
+use tracing::{instrument, Span};
+
+use crate::internal::job::entity::job::{
+    retry_delay, truncate_job_error, Job, JobCreate, JobCreateRequest, JobPage, JobQuery, JobSchedule, JOB_DEFAULT_LIMIT,
+};
+use crate::internal::job::usecase::traits::{JobUseCase, UseCase};
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+impl JobUseCase {
+    // Queues a job of a kind some handler runs; run_at None runs it as soon
+    // as a worker is free.
+    #[instrument(name = "job_use_case.enqueue", skip(self, payload))]
+    pub async fn enqueue(&self, kind: &str, payload: Value, run_at: Option<DateTime<Utc>>) -> Result<Job, ErrorResponseUseCase> {
+        let job = JobCreate {
+            kind: kind.to_string(),
+            payload,
+            run_at,
+            max_attempts: self.max_attempts,
+        };
+
+        match self.repo.job_insert(job).await {
+            Ok(res) => {
+                Ok(res)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_insert failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    // Claims one due job and runs it; false when there was none, so the
+    // worker can wait before asking again.
+    #[instrument(name = "job_use_case.run_next", skip_all, fields(job.id = Empty, job.kind = Empty, job.attempt = Empty))]
+    pub async fn run_next(&self) -> bool {
+        let job = match self.repo.job_claim(self.lock_timeout).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return false;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_claim failed");
+                return false;
+            }
+        };
+
+        let span = Span::current();
+        span.record("job.id", job.id);
+        span.record("job.kind", job.kind.as_str());
+        span.record("job.attempt", job.attempts);
+
+        let res = if job.attempts > job.max_attempts {
+            // Claimed again after a worker died during the last attempt.
+            Err("the last attempt was abandoned".to_string())
+        } else {
+            self.run(&job).await
+        };
+
+        let done = match res {
+            Ok(()) => {
+                self.repo.job_complete(job.id, job.attempts).await
+            }
+            Err(err) => {
+                let retry_at = (job.attempts < job.max_attempts).then(|| Utc::now() + retry_delay(job.attempts));
+                match retry_at {
+                    Some(retry_at) => {
+                        tracing::warn!(error = %err, %retry_at, "job failed, will retry");
+                    }
+                    None => {
+                        tracing::error!(error = %err, "job failed for the last time, dead-lettered");
+                    }
+                }
+                self.repo.job_fail(job.id, job.attempts, truncate_job_error(&err), retry_at).await
+            }
+        };
+
+        match done {
+            Ok(()) => {}
+            Err(sqlx::Error::RowNotFound) => {
+                tracing::warn!("job was claimed by another worker while running");
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_complete or repo.job_fail failed");
+            }
+        }
+
+        true
+    }
+
+    // The job's handler, bounded by the lock timeout so a hung run does not
+    // overlap with the next claim. A panic counts as a failure.
+    async fn run(&self, job: &Job) -> Result<(), String> {
+        let handler = match self.handlers.get(&job.kind) {
+            Some(res) => {
+                res.clone()
+            }
+            None => {
+                return Err(format!("no handler for kind {}", job.kind));
+            }
+        };
+
+        let run = AssertUnwindSafe(handler.run(job.payload.clone())).catch_unwind();
+        match tokio::time::timeout(self.lock_timeout, run).await {
+            Ok(Ok(res)) => {
+                res
+            }
+            Ok(Err(_panic)) => {
+                Err("the handler panicked".to_string())
+            }
+            Err(_elapsed) => {
+                Err(format!("timed out after {:?}", self.lock_timeout))
+            }
+        }
+    }
+
+    // Registers the schedules; a schedule whose cron changed starts over
+    // from now.
+    pub async fn schedules_sync(&self, schedules: &[JobSchedule]) {
+        for schedule in schedules {
+            let next_run_ts = match schedule.cron.next_after(Utc::now()) {
+                Some(res) => {
+                    res
+                }
+                None => {
+                    continue;
+                }
+            };
+
+            if let Err(err) = self.repo.job_schedule_sync(schedule.name.clone(), schedule.cron.to_string(), next_run_ts).await {
+                tracing::error!(error = %err, schedule = %schedule.name, "repo.job_schedule_sync failed");
+            }
+        }
+    }
+
+    // Enqueues a job for every schedule that is due. Runs missed while no
+    // instance was up are not made up for: the schedule fires once and moves
+    // on to its next time after now.
+    pub async fn schedules_fire(&self, schedules: &[JobSchedule]) {
+        for schedule in schedules {
+            let next_run_ts = match schedule.cron.next_after(Utc::now()) {
+                Some(res) => {
+                    res
+                }
+                None => {
+                    continue;
+                }
+            };
+
+            let job = JobCreate {
+                kind: schedule.kind.clone(),
+                payload: Value::Object(Default::default()),
+                run_at: None,
+                max_attempts: self.max_attempts,
+            };
+            match self.repo.job_schedule_fire(schedule.name.clone(), next_run_ts, job).await {
+                Ok(res) => {
+                    tracing::info!(schedule = %schedule.name, job.id = res.id, "enqueued scheduled job");
+                }
+                Err(sqlx::Error::RowNotFound) => {}
+                Err(err) => {
+                    tracing::error!(error = %err, schedule = %schedule.name, "repo.job_schedule_fire failed");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UseCase for JobUseCase {
+    #[instrument(name = "job_use_case.job_list", skip(self))]
+    async fn job_list(&self, mut query: JobQuery) -> Result<JobPage, ErrorResponseUseCase> {
+        let limit = query.limit.unwrap_or(JOB_DEFAULT_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+        query.limit = Some(limit);
+        query.offset = Some(offset);
+
+        let total = match self.repo.job_count(query.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_count failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(res);
+            }
+        };
+
+        match self.repo.job_list(query).await {
+            Ok(items) => {
+                Ok(JobPage {
+                    items,
+                    total,
+                    limit,
+                    offset,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_list failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "job_use_case.job_get", skip(self))]
+    async fn job_get(&self, id: i64) -> Result<Job, ErrorResponseUseCase> {
+        match self.repo.job_get(id).await {
+            Ok(res) => {
+                Ok(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("Job with id={} not found", id),
+                };
+
+                Err(res)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.job_get failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "job_use_case.job_create", skip(self))]
+    async fn job_create(&self, req: JobCreateRequest) -> Result<Job, ErrorResponseUseCase> {
+        if !self.handlers.contains_key(&req.kind) {
+            let mut kinds: Vec<&str> = self.handlers.keys().map(String::as_str).collect();
+            kinds.sort();
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: format!("kind must be one of {}", kinds.join(", ")),
+            };
+
+            return Err(res);
+        }
+
+        let job = self.enqueue(&req.kind, json!({}), req.run_at).await?;
+        tracing::info!(job.id = job.id, job.kind = %job.kind, "enqueued job");
+
+        Ok(job)
+    }
+}
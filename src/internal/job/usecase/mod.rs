@@ -0,0 +1,6 @@
+pub mod traits;
+pub mod job;
+pub mod handlers;
+pub mod worker;
+pub mod worker_test;
+pub mod repo;
@@ -0,0 +1,284 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, Postgres};
+use sqlx::postgres::PgArguments;
+use sqlx::query::QueryAs;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::job::entity::job::{Job, JobCreate, JobQuery};
+use crate::internal::job::usecase::repo::repo::JobRepo;
+use crate::internal::job::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+const JOB_COLUMNS: &str = "id, kind, payload, status, attempts, max_attempts, run_at, locked_until, last_error, create_ts, update_ts";
+
+const JOB_INSERT: &str = "INSERT INTO tbl_job(kind, payload, run_at, max_attempts) VALUES($1, $2, COALESCE($3, now()), $4) \
+    RETURNING id, kind, payload, status, attempts, max_attempts, run_at, locked_until, last_error, create_ts, update_ts";
+
+// Every filter is optional; a NULL parameter disables its condition.
+const JOB_FILTER: &str = "($1::varchar IS NULL OR status=$1) AND ($2::varchar IS NULL OR kind=$2)";
+
+fn insert_query(job: JobCreate) -> QueryAs<'static, Postgres, Job, PgArguments> {
+    sqlx::query_as::<_, Job>(JOB_INSERT)
+        .bind(job.kind)
+        .bind(job.payload)
+        .bind(job.run_at)
+        .bind(job.max_attempts)
+}
+
+#[async_trait]
+impl Repo for JobRepo {
+    #[instrument(
+        name = "job_repo.job_insert",
+        skip_all,
+        fields(kind = %job.kind, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_insert(&self, job: JobCreate) -> Result<Job, Error> {
+        record_statement(JOB_INSERT);
+
+        return match insert_query(job).fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_claim",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_claim(&self, lock_timeout: Duration) -> Result<Job, Error> {
+        let sql = format!(
+            "UPDATE tbl_job SET status='running', attempts=attempts+1, locked_until=now()+make_interval(secs => $1), update_ts=now() \
+            WHERE id=(SELECT id FROM tbl_job WHERE (status='pending' AND run_at<=now()) OR (status='running' AND locked_until<now()) \
+            ORDER BY run_at, id LIMIT 1 FOR UPDATE SKIP LOCKED) RETURNING {}",
+            JOB_COLUMNS,
+        );
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, Job>(&sql).bind(lock_timeout.as_secs_f64());
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_complete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_complete(&self, id: i64, attempts: i32) -> Result<(), Error> {
+        let sql = "UPDATE tbl_job SET status='done', locked_until=NULL, update_ts=now() \
+        WHERE id=$1 AND status='running' AND attempts=$2 RETURNING id";
+        record_statement(sql);
+
+        let query = sqlx::query_as::<_, (i64,)>(sql)
+            .bind(id)
+            .bind(attempts);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_fail",
+        skip(self, error),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_fail(&self, id: i64, attempts: i32, error: String, retry_at: Option<DateTime<Utc>>) -> Result<(), Error> {
+        let sql = "UPDATE tbl_job SET status=CASE WHEN $4::timestamptz IS NULL THEN 'dead' ELSE 'pending' END, \
+        run_at=COALESCE($4, run_at), locked_until=NULL, last_error=$3, update_ts=now() \
+        WHERE id=$1 AND status='running' AND attempts=$2 RETURNING id";
+        record_statement(sql);
+
+        let query = sqlx::query_as::<_, (i64,)>(sql)
+            .bind(id)
+            .bind(attempts)
+            .bind(error)
+            .bind(retry_at);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_get(&self, id: i64) -> Result<Job, Error> {
+        let sql = format!("SELECT {} FROM tbl_job WHERE id=$1", JOB_COLUMNS);
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, Job>(&sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_list(&self, query: JobQuery) -> Result<Vec<Job>, Error> {
+        let sql = format!("SELECT {} FROM tbl_job WHERE {} ORDER BY id DESC LIMIT $3 OFFSET $4", JOB_COLUMNS, JOB_FILTER);
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, Job>(&sql)
+            .bind(query.status)
+            .bind(query.kind)
+            .bind(query.limit)
+            .bind(query.offset);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_count",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_count(&self, query: JobQuery) -> Result<i64, Error> {
+        let sql = format!("SELECT COUNT(*) FROM tbl_job WHERE {}", JOB_FILTER);
+        record_statement(&sql);
+
+        let query = sqlx::query_as::<_, (i64,)>(&sql)
+            .bind(query.status)
+            .bind(query.kind);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data.0)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_purge_finished",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_purge_finished(&self, before: DateTime<Utc>) -> Result<u64, Error> {
+        let sql = "DELETE FROM tbl_job WHERE status='done' AND update_ts<$1";
+        record_statement(sql);
+
+        return match sqlx::query(sql).bind(before).execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(data.rows_affected())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_schedule_sync",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_schedule_sync(&self, name: String, cron: String, next_run_ts: DateTime<Utc>) -> Result<(), Error> {
+        let sql = "INSERT INTO tbl_job_schedule(name, cron, next_run_ts) VALUES($1, $2, $3) \
+        ON CONFLICT (name) DO UPDATE SET cron=EXCLUDED.cron, next_run_ts=EXCLUDED.next_run_ts \
+        WHERE tbl_job_schedule.cron<>EXCLUDED.cron";
+        record_statement(sql);
+
+        let query = sqlx::query(sql)
+            .bind(name)
+            .bind(cron)
+            .bind(next_run_ts);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "job_repo.job_schedule_fire",
+        skip(self, job),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn job_schedule_fire(&self, name: String, next_run_ts: DateTime<Utc>, job: JobCreate) -> Result<Job, Error> {
+        let sql = "UPDATE tbl_job_schedule SET next_run_ts=$2 \
+        WHERE name=(SELECT name FROM tbl_job_schedule WHERE name=$1 AND next_run_ts<=now() FOR UPDATE SKIP LOCKED) \
+        RETURNING name";
+        record_statement(sql);
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query_as::<_, (String,)>(sql)
+            .bind(name)
+            .bind(next_run_ts)
+            .fetch_one(&mut tx)
+            .await?;
+        let data = insert_query(job).fetch_one(&mut tx).await?;
+        tx.commit().await?;
+
+        record_rows(1);
+        Ok(data)
+    }
+}
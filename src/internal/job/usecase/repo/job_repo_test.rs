@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use actix_web::web;
+    use chrono::Utc;
+    use serde_json::json;
+
+    use crate::internal::job::entity::job::{JobCreate, JobQuery, JobStatus};
+    use crate::internal::job::usecase::repo::repo::new_job_repo;
+    use crate::internal::job::usecase::traits::Repo;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    fn job(kind: &str) -> JobCreate {
+        JobCreate {
+            kind: kind.to_string(),
+            payload: json!({"kind": kind}),
+            run_at: None,
+            max_attempts: 3,
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn job_claim_test() {
+        let test_db = new_test_db().await;
+        let repo = new_job_repo(web::Data::new(test_db.db.clone()));
+        let lease = Duration::from_secs(60);
+
+        assert!(matches!(repo.job_claim(lease).await, Err(sqlx::Error::RowNotFound)));
+
+        let first = repo.job_insert(job("a")).await.unwrap();
+        assert_eq!((first.status, first.attempts, first.max_attempts), (JobStatus::Pending, 0, 3));
+        assert_eq!(first.payload, json!({"kind": "a"}));
+        let later = JobCreate { run_at: Some(Utc::now() + chrono::Duration::hours(1)), ..job("later") };
+        repo.job_insert(later).await.unwrap();
+        let second = repo.job_insert(job("b")).await.unwrap();
+
+        // A row locked by another transaction is skipped, not waited for.
+        let mut tx = test_db.db.begin().await.unwrap();
+        sqlx::query("SELECT id FROM tbl_job WHERE id=$1 FOR UPDATE").bind(first.id).execute(&mut tx).await.unwrap();
+        let claimed = repo.job_claim(lease).await.unwrap();
+        assert_eq!(claimed.id, second.id);
+        tx.rollback().await.unwrap();
+
+        let claimed = repo.job_claim(lease).await.unwrap();
+        assert_eq!((claimed.id, claimed.status, claimed.attempts), (first.id, JobStatus::Running, 1));
+        assert!(claimed.locked_until.unwrap() > Utc::now() + chrono::Duration::seconds(30));
+        assert!(matches!(repo.job_claim(lease).await, Err(sqlx::Error::RowNotFound)));
+
+        repo.job_complete(first.id, 1).await.unwrap();
+        let done = repo.job_get(first.id).await.unwrap();
+        assert_eq!((done.status, done.locked_until), (JobStatus::Done, None));
+        // Already completed: the claim is gone.
+        assert!(matches!(repo.job_complete(first.id, 1).await, Err(sqlx::Error::RowNotFound)));
+        assert!(matches!(repo.job_get(0).await, Err(sqlx::Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn job_fail_test() {
+        let test_db = new_test_db().await;
+        let repo = new_job_repo(web::Data::new(test_db.db.clone()));
+
+        let created = repo.job_insert(job("a")).await.unwrap();
+        repo.job_claim(Duration::from_secs(60)).await.unwrap();
+        let retry_at = Utc::now() - chrono::Duration::seconds(1);
+        repo.job_fail(created.id, 1, "boom".to_string(), Some(retry_at)).await.unwrap();
+        let pending = repo.job_get(created.id).await.unwrap();
+        assert_eq!((pending.status, pending.attempts, pending.last_error.as_deref()), (JobStatus::Pending, 1, Some("boom")));
+        assert_eq!(pending.run_at.timestamp_micros(), retry_at.timestamp_micros());
+
+        // The claim expires; a second worker takes the job over and the first
+        // one can no longer record its outcome.
+        repo.job_claim(Duration::ZERO).await.unwrap();
+        let reclaimed = repo.job_claim(Duration::from_secs(60)).await.unwrap();
+        assert_eq!((reclaimed.id, reclaimed.attempts), (created.id, 3));
+        assert!(matches!(repo.job_fail(created.id, 2, "late".to_string(), None).await, Err(sqlx::Error::RowNotFound)));
+
+        repo.job_fail(created.id, 3, "boom again".to_string(), None).await.unwrap();
+        let dead = repo.job_get(created.id).await.unwrap();
+        assert_eq!((dead.status, dead.last_error.as_deref(), dead.locked_until), (JobStatus::Dead, Some("boom again"), None));
+        assert!(matches!(repo.job_claim(Duration::from_secs(60)).await, Err(sqlx::Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn job_list_and_purge_test() {
+        let test_db = new_test_db().await;
+        let repo = new_job_repo(web::Data::new(test_db.db.clone()));
+
+        for kind in ["a", "b", "a"] {
+            repo.job_insert(job(kind)).await.unwrap();
+        }
+        let claimed = repo.job_claim(Duration::from_secs(60)).await.unwrap();
+        repo.job_complete(claimed.id, 1).await.unwrap();
+
+        let all = JobQuery { limit: Some(50), offset: Some(0), ..Default::default() };
+        let jobs = repo.job_list(all.clone()).await.unwrap();
+        assert_eq!(jobs.iter().map(|j| j.id).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(repo.job_count(all).await.unwrap(), 3);
+
+        let pending_a = JobQuery { status: Some("pending".to_string()), kind: Some("a".to_string()), limit: Some(1), offset: Some(0) };
+        assert_eq!(repo.job_list(pending_a.clone()).await.unwrap().iter().map(|j| j.id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(repo.job_count(pending_a).await.unwrap(), 1);
+
+        assert_eq!(repo.job_purge_finished(Utc::now() - chrono::Duration::hours(1)).await.unwrap(), 0);
+        assert_eq!(repo.job_purge_finished(Utc::now() + chrono::Duration::seconds(1)).await.unwrap(), 1);
+        assert_eq!(repo.job_count(JobQuery::default()).await.unwrap(), 2);
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn job_schedule_test() {
+        let test_db = new_test_db().await;
+        let repo = new_job_repo(web::Data::new(test_db.db.clone()));
+        let past = Utc::now() - chrono::Duration::minutes(5);
+        let future = Utc::now() + chrono::Duration::hours(1);
+        let name = "purge".to_string();
+
+        repo.job_schedule_sync(name.clone(), "* * * * *".to_string(), past).await.unwrap();
+        // Same cron from another instance: next_run_ts stays.
+        repo.job_schedule_sync(name.clone(), "* * * * *".to_string(), future).await.unwrap();
+
+        let fired = repo.job_schedule_fire(name.clone(), future, job("purge")).await.unwrap();
+        assert_eq!((fired.kind.as_str(), fired.status), ("purge", JobStatus::Pending));
+        assert!(matches!(repo.job_schedule_fire(name.clone(), future, job("purge")).await, Err(sqlx::Error::RowNotFound)));
+        assert!(matches!(repo.job_schedule_fire("unknown".to_string(), future, job("purge")).await, Err(sqlx::Error::RowNotFound)));
+
+        // A changed cron moves next_run_ts.
+        repo.job_schedule_sync(name.clone(), "0 * * * *".to_string(), past).await.unwrap();
+        repo.job_schedule_fire(name.clone(), future, job("purge")).await.unwrap();
+
+        let (cron,): (String,) = sqlx::query_as("SELECT cron FROM tbl_job_schedule WHERE name=$1").bind(&name).fetch_one(&test_db.db).await.unwrap();
+        assert_eq!(cron, "0 * * * *");
+        assert_eq!(repo.job_count(JobQuery::default()).await.unwrap(), 2);
+
+        test_db.close().await;
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+
+use crate::internal::job::entity::job::{Job, JobCreate, JobQuery, JobStatus};
+use crate::internal::job::usecase::traits::Repo;
+
+// In-memory stand-in for JobRepo, used by tests that must not touch Postgres.
+
+#[derive(Default)]
+pub struct MemoryJobRepo {
+    pub jobs: Mutex<Vec<Job>>,
+    pub schedules: Mutex<Vec<MemoryJobSchedule>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryJobSchedule {
+    pub name: String,
+    pub cron: String,
+    pub next_run_ts: DateTime<Utc>,
+}
+
+pub fn new_memory_job_repo() -> MemoryJobRepo {
+    MemoryJobRepo::default()
+}
+
+impl MemoryJobRepo {
+    fn matching(&self, query: &JobQuery) -> Vec<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter()
+            .rev()
+            .filter(|j| query.status.as_ref().is_none_or(|status| j.status.as_str() == status))
+            .filter(|j| query.kind.as_ref().is_none_or(|kind| &j.kind == kind))
+            .cloned()
+            .collect()
+    }
+
+    fn insert(jobs: &mut Vec<Job>, job: JobCreate) -> Job {
+        let now = Utc::now();
+        let job = Job {
+            id: jobs.len() as i64 + 1,
+            kind: job.kind,
+            payload: job.payload,
+            status: JobStatus::Pending,
+            attempts: 0,
+            max_attempts: job.max_attempts,
+            run_at: job.run_at.unwrap_or(now),
+            locked_until: None,
+            last_error: None,
+            create_ts: now,
+            update_ts: now,
+        };
+        jobs.push(job.clone());
+
+        job
+    }
+
+    // The claimed job, if it still is.
+    fn claimed(&self, id: i64, attempts: i32, update: impl FnOnce(&mut Job)) -> Result<(), Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.iter_mut().find(|j| j.id == id && j.status == JobStatus::Running && j.attempts == attempts) {
+            Some(job) => {
+                update(job);
+                job.locked_until = None;
+                job.update_ts = Utc::now();
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Repo for MemoryJobRepo {
+    async fn job_insert(&self, job: JobCreate) -> Result<Job, Error> {
+        Ok(MemoryJobRepo::insert(&mut self.jobs.lock().unwrap(), job))
+    }
+
+    async fn job_claim(&self, lock_timeout: Duration) -> Result<Job, Error> {
+        let now = Utc::now();
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut()
+            .filter(|j| match j.status {
+                JobStatus::Pending => j.run_at <= now,
+                JobStatus::Running => j.locked_until.is_some_and(|locked_until| locked_until < now),
+                _ => false,
+            })
+            .min_by_key(|j| (j.run_at, j.id))
+            .ok_or(Error::RowNotFound)?;
+
+        job.status = JobStatus::Running;
+        job.attempts += 1;
+        job.locked_until = Some(now + chrono::Duration::from_std(lock_timeout).unwrap());
+        job.update_ts = now;
+
+        Ok(job.clone())
+    }
+
+    async fn job_complete(&self, id: i64, attempts: i32) -> Result<(), Error> {
+        self.claimed(id, attempts, |job| job.status = JobStatus::Done)
+    }
+
+    async fn job_fail(&self, id: i64, attempts: i32, error: String, retry_at: Option<DateTime<Utc>>) -> Result<(), Error> {
+        self.claimed(id, attempts, |job| {
+            job.status = if retry_at.is_some() { JobStatus::Pending } else { JobStatus::Dead };
+            job.run_at = retry_at.unwrap_or(job.run_at);
+            job.last_error = Some(error);
+        })
+    }
+
+    async fn job_get(&self, id: i64) -> Result<Job, Error> {
+        self.jobs.lock().unwrap().iter().find(|j| j.id == id).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn job_list(&self, query: JobQuery) -> Result<Vec<Job>, Error> {
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.unwrap_or(i64::MAX) as usize;
+        Ok(self.matching(&query).into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn job_count(&self, query: JobQuery) -> Result<i64, Error> {
+        Ok(self.matching(&query).len() as i64)
+    }
+
+    async fn job_purge_finished(&self, before: DateTime<Utc>) -> Result<u64, Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let len = jobs.len();
+        jobs.retain(|j| j.status != JobStatus::Done || j.update_ts >= before);
+
+        Ok((len - jobs.len()) as u64)
+    }
+
+    async fn job_schedule_sync(&self, name: String, cron: String, next_run_ts: DateTime<Utc>) -> Result<(), Error> {
+        let mut schedules = self.schedules.lock().unwrap();
+        match schedules.iter_mut().find(|s| s.name == name) {
+            Some(schedule) => {
+                if schedule.cron != cron {
+                    schedule.cron = cron;
+                    schedule.next_run_ts = next_run_ts;
+                }
+            }
+            None => {
+                schedules.push(MemoryJobSchedule { name, cron, next_run_ts });
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn job_schedule_fire(&self, name: String, next_run_ts: DateTime<Utc>, job: JobCreate) -> Result<Job, Error> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let schedule = schedules.iter_mut()
+            .find(|s| s.name == name && s.next_run_ts <= Utc::now())
+            .ok_or(Error::RowNotFound)?;
+        schedule.next_run_ts = next_run_ts;
+
+        Ok(MemoryJobRepo::insert(&mut self.jobs.lock().unwrap(), job))
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct JobRepo {
+    pub db: Data<Db>,
+}
+
+pub fn new_job_repo(db: Data<Db>) -> JobRepo {
+    JobRepo{
+        db
+    }
+}
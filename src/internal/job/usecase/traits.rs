@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::internal::job::entity::job::{Job, JobCreate, JobCreateRequest, JobPage, JobQuery};
+use crate::internal::controller::response;
+
+// Runs the jobs of one kind. An Err is kept as the job's last_error and the
+// job is retried; handlers must therefore be safe to run more than once.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    async fn run(&self, payload: Value) -> Result<(), String>;
+}
+
+// Handlers by job kind.
+pub type JobHandlers = HashMap<String, Arc<dyn JobHandler>>;
+
+#[derive(Clone)]
+pub struct JobUseCase {
+    pub repo: Arc<dyn Repo>,
+    pub handlers: Arc<JobHandlers>,
+    pub max_attempts: i32, // of jobs enqueued here
+    pub lock_timeout: Duration, // a run taking longer is given up
+}
+
+pub fn new_job_use_case(repo: Arc<dyn Repo>, handlers: JobHandlers, max_attempts: i32, lock_timeout: Duration) -> JobUseCase {
+    JobUseCase {
+        repo,
+        handlers: Arc::new(handlers),
+        max_attempts,
+        lock_timeout,
+    }
+}
+
+#[async_trait]
+pub trait UseCase {
+    async fn job_list(&self, query: JobQuery) -> Result<JobPage, response::ErrorResponseUseCase>;
+    async fn job_get(&self, id: i64) -> Result<Job, response::ErrorResponseUseCase>;
+    // Queues a job of a kind that has a handler, with an empty payload.
+    async fn job_create(&self, req: JobCreateRequest) -> Result<Job, response::ErrorResponseUseCase>;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn job_insert(&self, job: JobCreate) -> Result<Job, sqlx::Error>;
+    // Claims the next due job, or a running one whose claim expired, until
+    // lock_timeout from now. RowNotFound when there is none.
+    async fn job_claim(&self, lock_timeout: Duration) -> Result<Job, sqlx::Error>;
+    // attempts is the job's when it was claimed; RowNotFound when the claim
+    // was lost to another worker since.
+    async fn job_complete(&self, id: i64, attempts: i32) -> Result<(), sqlx::Error>;
+    // Back to pending until retry_at, or dead without one. Same claim check
+    // as job_complete.
+    async fn job_fail(&self, id: i64, attempts: i32, error: String, retry_at: Option<DateTime<Utc>>) -> Result<(), sqlx::Error>;
+    async fn job_get(&self, id: i64) -> Result<Job, sqlx::Error>;
+    // query.limit and query.offset are always set by the use case
+    async fn job_list(&self, query: JobQuery) -> Result<Vec<Job>, sqlx::Error>;
+    async fn job_count(&self, query: JobQuery) -> Result<i64, sqlx::Error>;
+    // Deletes done jobs last updated before the given time; returns how many.
+    async fn job_purge_finished(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+    // Creates the schedule, or moves next_run_ts when its cron changed.
+    async fn job_schedule_sync(&self, name: String, cron: String, next_run_ts: DateTime<Utc>) -> Result<(), sqlx::Error>;
+    // When the schedule is due, moves it to next_run_ts and enqueues job, in
+    // one transaction. RowNotFound when it is not due or another instance is
+    // firing it.
+    async fn job_schedule_fire(&self, name: String, next_run_ts: DateTime<Utc>, job: JobCreate) -> Result<Job, sqlx::Error>;
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use crate::internal::job::entity::job::JobSchedule;
+use crate::internal::job::usecase::traits::JobUseCase;
+use crate::pkg::shutdown::shutdown::Shutdown;
+
+// Starts workers job runners and one scheduler as background tasks of
+// shutdown; with no workers this instance leaves the queue to the others.
+// A worker asks for the next job right after finishing one and waits
+// poll_interval when there was none. On shutdown a running job is finished,
+// within the drain timeout, before its worker stops.
+pub fn spawn_job_runner(use_case: JobUseCase, schedules: Vec<JobSchedule>, workers: usize, poll_interval: Duration, shutdown: &Shutdown) {
+    if workers == 0 {
+        return;
+    }
+
+    for _ in 0..workers {
+        let use_case = use_case.clone();
+        let stop = shutdown.wait();
+
+        shutdown.spawn(async move {
+            tokio::pin!(stop);
+
+            loop {
+                let pause = if use_case.run_next().await { Duration::ZERO } else { poll_interval };
+                tokio::select! {
+                    biased;
+                    _ = &mut stop => {
+                        return;
+                    }
+                    _ = tokio::time::sleep(pause) => {}
+                }
+            }
+        });
+    }
+
+    let stop = shutdown.wait();
+    shutdown.spawn(async move {
+        tokio::pin!(stop);
+        use_case.schedules_sync(&schedules).await;
+
+        loop {
+            use_case.schedules_fire(&schedules).await;
+            tokio::select! {
+                _ = &mut stop => {
+                    return;
+                }
+                _ = tokio::time::sleep(poll_interval) => {}
+            }
+        }
+    });
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    use crate::internal::job::entity::job::{JobSchedule, JobStatus};
+    use crate::internal::job::usecase::repo::memory_repo::{new_memory_job_repo, MemoryJobRepo};
+    use crate::internal::job::usecase::traits::{new_job_use_case, JobHandler, JobHandlers, JobUseCase};
+    use crate::internal::job::usecase::worker::spawn_job_runner;
+    use crate::pkg::cron::cron::parse_cron;
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    // Fails its first `failures` runs, panics on {"panic": true} and hangs on
+    // {"hang": true}.
+    #[derive(Default)]
+    struct TestHandler {
+        runs: AtomicUsize,
+        failures: usize,
+    }
+
+    #[async_trait]
+    impl JobHandler for TestHandler {
+        async fn run(&self, payload: Value) -> Result<(), String> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst);
+            if payload["panic"] == json!(true) {
+                panic!("boom");
+            }
+            if payload["hang"] == json!(true) {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            if run < self.failures {
+                return Err(format!("failure {}", run + 1));
+            }
+
+            Ok(())
+        }
+    }
+
+    fn test_job_use_case(handler: Arc<TestHandler>, max_attempts: i32) -> (JobUseCase, Arc<MemoryJobRepo>) {
+        let repo = Arc::new(new_memory_job_repo());
+        let mut handlers = JobHandlers::new();
+        handlers.insert("test".to_string(), handler as Arc<dyn JobHandler>);
+
+        (new_job_use_case(repo.clone(), handlers, max_attempts, Duration::from_millis(200)), repo)
+    }
+
+    #[actix_web::test]
+    async fn run_next_test() {
+        let handler = Arc::new(TestHandler { failures: 1, ..Default::default() });
+        let (use_case, repo) = test_job_use_case(handler.clone(), 3);
+
+        assert!(!use_case.run_next().await);
+
+        let job = use_case.enqueue("test", json!({"n": 1}), None).await.unwrap();
+        assert!(use_case.run_next().await);
+        let failed = repo.jobs.lock().unwrap()[0].clone();
+        assert_eq!(failed.status, JobStatus::Pending);
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("failure 1"));
+        assert_eq!(failed.locked_until, None);
+        assert!(failed.run_at > Utc::now() + chrono::Duration::seconds(5));
+
+        // Not due until the backoff passed.
+        assert!(!use_case.run_next().await);
+        repo.jobs.lock().unwrap()[0].run_at = Utc::now();
+        assert!(use_case.run_next().await);
+        let done = repo.jobs.lock().unwrap()[0].clone();
+        assert_eq!((done.id, done.status, done.attempts), (job.id, JobStatus::Done, 2));
+        assert_eq!(handler.runs.load(Ordering::SeqCst), 2);
+
+        assert!(!use_case.run_next().await);
+    }
+
+    #[actix_web::test]
+    async fn run_next_dead_letter_test() {
+        let handler = Arc::new(TestHandler { failures: usize::MAX, ..Default::default() });
+        let (use_case, repo) = test_job_use_case(handler, 2);
+
+        use_case.enqueue("test", json!({}), None).await.unwrap();
+        use_case.enqueue("unknown", json!({}), None).await.unwrap();
+        use_case.enqueue("test", json!({"panic": true}), None).await.unwrap();
+        use_case.enqueue("test", json!({"hang": true}), None).await.unwrap();
+        for _ in 0..2 {
+            while use_case.run_next().await {}
+            for job in repo.jobs.lock().unwrap().iter_mut() {
+                job.run_at = Utc::now();
+            }
+        }
+
+        let jobs = repo.jobs.lock().unwrap().clone();
+        let errors: Vec<Option<&str>> = jobs.iter().map(|j| j.last_error.as_deref()).collect();
+        assert_eq!(errors, vec![Some("failure 4"), Some("no handler for kind unknown"), Some("the handler panicked"), Some("timed out after 200ms")]);
+        for job in &jobs {
+            assert_eq!((job.status, job.attempts), (JobStatus::Dead, 2), "{}", job.id);
+        }
+        assert!(!use_case.run_next().await);
+    }
+
+    #[actix_web::test]
+    async fn run_next_abandoned_test() {
+        let handler = Arc::new(TestHandler::default());
+        let (use_case, repo) = test_job_use_case(handler.clone(), 2);
+        use_case.enqueue("test", json!({}), None).await.unwrap();
+
+        // A worker died during the last attempt; its claim expired.
+        {
+            let mut jobs = repo.jobs.lock().unwrap();
+            jobs[0].status = JobStatus::Running;
+            jobs[0].attempts = 2;
+            jobs[0].locked_until = Some(Utc::now() - chrono::Duration::seconds(1));
+        }
+        assert!(use_case.run_next().await);
+
+        let job = repo.jobs.lock().unwrap()[0].clone();
+        assert_eq!((job.status, job.attempts), (JobStatus::Dead, 3));
+        assert_eq!(job.last_error.as_deref(), Some("the last attempt was abandoned"));
+        assert_eq!(handler.runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_web::test]
+    async fn schedules_test() {
+        let (use_case, repo) = test_job_use_case(Arc::new(TestHandler::default()), 3);
+        let schedules = vec! {
+            JobSchedule { name: "every-minute".to_string(), kind: "test".to_string(), cron: parse_cron("* * * * *").unwrap() },
+        };
+
+        use_case.schedules_sync(&schedules).await;
+        use_case.schedules_fire(&schedules).await;
+        assert!(repo.jobs.lock().unwrap().is_empty());
+
+        // Overdue by an hour: fires once, not once per missed minute.
+        repo.schedules.lock().unwrap()[0].next_run_ts = Utc::now() - chrono::Duration::hours(1);
+        use_case.schedules_fire(&schedules).await;
+        use_case.schedules_fire(&schedules).await;
+        let jobs = repo.jobs.lock().unwrap().clone();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!((jobs[0].kind.as_str(), &jobs[0].payload, jobs[0].max_attempts), ("test", &json!({}), 3));
+        assert!(repo.schedules.lock().unwrap()[0].next_run_ts > Utc::now());
+
+        // Syncing an unchanged cron keeps the schedule; a changed one starts
+        // over from now.
+        repo.schedules.lock().unwrap()[0].next_run_ts = Utc::now() - chrono::Duration::hours(1);
+        use_case.schedules_sync(&schedules).await;
+        assert!(repo.schedules.lock().unwrap()[0].next_run_ts < Utc::now());
+        let changed = vec! {
+            JobSchedule { cron: parse_cron("0 * * * *").unwrap(), ..schedules[0].clone() },
+        };
+        use_case.schedules_sync(&changed).await;
+        let schedule = repo.schedules.lock().unwrap()[0].clone();
+        assert_eq!(schedule.cron, "0 * * * *");
+        assert!(schedule.next_run_ts > Utc::now());
+    }
+
+    #[actix_web::test]
+    async fn spawn_job_runner_test() {
+        let handler = Arc::new(TestHandler::default());
+        let (use_case, repo) = test_job_use_case(handler.clone(), 3);
+        for n in 0..5 {
+            use_case.enqueue("test", json!({"n": n}), None).await.unwrap();
+        }
+
+        let shutdown = new_shutdown();
+        spawn_job_runner(use_case.clone(), Vec::new(), 2, Duration::from_millis(10), &shutdown);
+        for _ in 0..100 {
+            if repo.jobs.lock().unwrap().iter().all(|j| j.status == JobStatus::Done) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(shutdown.drain_tasks(Duration::from_secs(5)).await);
+
+        assert!(repo.jobs.lock().unwrap().iter().all(|j| j.status == JobStatus::Done && j.attempts == 1));
+        assert_eq!(handler.runs.load(Ordering::SeqCst), 5);
+
+        // Stopped workers leave new jobs alone.
+        use_case.enqueue("test", json!({}), None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(repo.jobs.lock().unwrap()[5].status, JobStatus::Pending);
+    }
+}
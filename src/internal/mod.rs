@@ -1,2 +1,8 @@
 pub mod user;
-pub mod controller;
\ No newline at end of file
+pub mod health;
+pub mod audit;
+pub mod oidc;
+pub mod identity;
+pub mod org;
+pub mod job;
+pub mod controller;
@@ -0,0 +1,476 @@
+use actix_web::http::StatusCode;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::ecdsa::signature::{Signer, Verifier};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::internal::user::entity::token::get_time_sec;
+use crate::internal::user::entity::user::UserGetResponse;
+
+// OpenID Connect Core 1.0 for our own applications: the authorization code
+// flow with PKCE (RFC 7636, S256 only), refresh tokens and client credentials.
+// Tokens are ES256 JWTs so clients can verify them against the JWKS without
+// sharing a secret with this service.
+pub const OIDC_CODE_LIFE_TIME: u64 = 60; // second
+pub const OIDC_REFRESH_TOKEN_LIFE_TIME: u64 = 30 * 24 * 60 * 60; // second
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPES: [&str; 2] = [SCOPE_OPENID, SCOPE_PROFILE];
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_REFRESH_TOKEN: &str = "refresh_token";
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+pub const OIDC_CLIENT_NAME_MAX_LEN: usize = 128;
+pub const OIDC_MAX_REDIRECT_URIS: usize = 10;
+const REDIRECT_URI_MAX_LEN: usize = 2048;
+const TOKEN_LEN: usize = 32; // byte
+const JWT_ALG: &str = "ES256";
+const JWT_TYPE_ID_TOKEN: &str = "JWT";
+const JWT_TYPE_ACCESS_TOKEN: &str = "at+jwt"; // RFC 9068
+const SIGNING_KEY_LABEL: &[u8] = b"rust-clean oidc signing key\0";
+
+// OAuth error codes (RFC 6749 sections 4.1.2.1 and 5.2, OIDC Core 3.1.2.6)
+pub const ERROR_INVALID_REQUEST: &str = "invalid_request";
+pub const ERROR_INVALID_CLIENT: &str = "invalid_client";
+pub const ERROR_INVALID_GRANT: &str = "invalid_grant";
+pub const ERROR_UNAUTHORIZED_CLIENT: &str = "unauthorized_client";
+pub const ERROR_UNSUPPORTED_GRANT_TYPE: &str = "unsupported_grant_type";
+pub const ERROR_UNSUPPORTED_RESPONSE_TYPE: &str = "unsupported_response_type";
+pub const ERROR_INVALID_SCOPE: &str = "invalid_scope";
+pub const ERROR_INVALID_TOKEN: &str = "invalid_token";
+pub const ERROR_INSUFFICIENT_SCOPE: &str = "insufficient_scope";
+pub const ERROR_LOGIN_REQUIRED: &str = "login_required";
+pub const ERROR_SERVER_ERROR: &str = "server_error";
+
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub signing_key: SigningKey,
+    pub key_id: String,
+    pub token_life_time: u64, // second
+}
+
+// The signing key is derived from the token secret, so every replica signs
+// with the same key and rotating auth.token_secret rotates it too.
+pub fn new_oidc_config(issuer: String, token_secret: &str, token_life_time: u64) -> OidcConfig {
+    let mut seed = Sha256::new().chain_update(SIGNING_KEY_LABEL).chain_update(token_secret.as_bytes()).finalize();
+    let signing_key = loop {
+        // Fails only for a seed outside the curve order, about once in 2^32.
+        match SigningKey::from_slice(&seed) {
+            Ok(res) => {
+                break res;
+            }
+            Err(_err) => {
+                seed = Sha256::digest(seed);
+            }
+        }
+    };
+
+    let point = signing_key.verifying_key().to_encoded_point(false);
+    let key_id = URL_SAFE_NO_PAD.encode(&Sha256::digest(point.as_bytes())[..8]);
+
+    OidcConfig {
+        issuer,
+        signing_key,
+        key_id,
+        token_life_time: token_life_time * 60,
+    }
+}
+
+impl OidcConfig {
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.issuer, path)
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct OidcClient {
+    pub client_id: String,
+    pub secret_hash: Option<String>, // None for public clients
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OidcClientCreateRequest {
+    pub name: String,
+    /// Exact URIs the client may receive codes at
+    pub redirect_uris: Vec<String>,
+    /// Confidential clients get a secret and may use client_credentials;
+    /// public clients (SPAs, native apps) rely on PKCE alone
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+// A client ready to be stored.
+#[derive(Debug, Clone)]
+pub struct OidcClientCreate {
+    pub client_id: String,
+    pub secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct OidcClientResponse {
+    pub client_id: String,
+    /// Only returned when a confidential client is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+}
+
+pub fn to_client_response(client: OidcClient, client_secret: Option<String>) -> OidcClientResponse {
+    OidcClientResponse {
+        client_id: client.client_id,
+        client_secret,
+        name: client.name,
+        redirect_uris: client.redirect_uris,
+        confidential: client.secret_hash.is_some(),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuthorizeRequest {
+    /// Must be code
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    /// One of the client's registered redirect URIs
+    pub redirect_uri: Option<String>,
+    /// Space separated; must include openid. Supported: openid, profile
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    /// Copied into the ID token
+    pub nonce: Option<String>,
+    /// BASE64URL(SHA-256(code_verifier))
+    pub code_challenge: Option<String>,
+    /// Must be S256
+    pub code_challenge_method: Option<String>,
+}
+
+// An issued authorization code, stored under the hash of the code itself.
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct OidcCode {
+    pub client_id: String,
+    pub user_id: i32,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub auth_time: i64,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct OidcRefreshToken {
+    pub client_id: String,
+    pub user_id: i32,
+    pub scope: String,
+    pub auth_time: i64,
+}
+
+// application/x-www-form-urlencoded body of the token endpoint. Clients
+// authenticate with HTTP Basic or with client_id and client_secret here.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct TokenRequest {
+    /// authorization_code, refresh_token or client_credentials
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    /// Narrows the scope of a refreshed token
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+// Standard claims from the profile scope; only sub without it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+// The protocol endpoints answer with the error body OAuth clients expect
+// rather than GeneralResponse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct OidcErrorResponse {
+    pub error: String,
+    pub error_description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcError {
+    pub status_code: StatusCode,
+    pub error: String,
+    pub error_description: String,
+}
+
+pub fn new_oidc_error(status_code: StatusCode, error: &str, error_description: &str) -> OidcError {
+    OidcError {
+        status_code,
+        error: error.to_string(),
+        error_description: error_description.to_string(),
+    }
+}
+
+pub fn generate_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; TOKEN_LEN]>())
+}
+
+// Codes, refresh tokens and client secrets are stored as hex SHA-256.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+// RFC 7636 section 4.1: 43 to 128 unreserved characters.
+pub fn valid_code_verifier(code_verifier: &str) -> bool {
+    (43..=128).contains(&code_verifier.len())
+        && code_verifier.chars().all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+}
+
+// The supported scopes from a space separated list, in canonical order.
+// Unknown scopes are ignored, as OIDC Core 3.1.2.1 allows.
+pub fn normalize_scope(scope: &str) -> String {
+    let requested: Vec<&str> = scope.split_whitespace().collect();
+    SCOPES.iter().filter(|s| requested.contains(s)).copied().collect::<Vec<_>>().join(" ")
+}
+
+pub fn has_scope(scope: &str, wanted: &str) -> bool {
+    scope.split_whitespace().any(|s| s == wanted)
+}
+
+pub fn is_subset_scope(scope: &str, of: &str) -> bool {
+    scope.split_whitespace().all(|s| has_scope(of, s))
+}
+
+// Adds the parameters to the redirect URI's query, keeping any it has.
+pub fn redirect_with(redirect_uri: &str, params: &[(&str, &str)]) -> String {
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+
+    format!("{}{}{}", redirect_uri, separator, query)
+}
+
+pub fn user_info(user: &UserGetResponse, scope: &str) -> UserInfo {
+    let profile = has_scope(scope, SCOPE_PROFILE);
+
+    UserInfo {
+        sub: user.id.to_string(),
+        preferred_username: profile.then(|| user.username.clone()),
+        name: profile.then(|| format!("{} {}", user.firstname, user.lastname)),
+        given_name: profile.then(|| user.firstname.clone()),
+        family_name: profile.then(|| user.lastname.clone()),
+        updated_at: profile.then(|| user.update_ts.timestamp()),
+    }
+}
+
+fn sign_jwt(cfg: &OidcConfig, typ: &str, claims: &Value) -> String {
+    let header = json!({"alg": JWT_ALG, "typ": typ, "kid": cfg.key_id});
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string()),
+    );
+    let signature: Signature = cfg.signing_key.sign(message.as_bytes());
+
+    format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
+
+// Returns the claims of a token this service signed, if it has the expected
+// type, came from this issuer and has not expired.
+fn verify_jwt(cfg: &OidcConfig, typ: &str, token: &str) -> Option<Map<String, Value>> {
+    let mut parts = token.split('.');
+    let (header, claims, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let decoded: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+    if decoded["alg"] != JWT_ALG || decoded["typ"] != typ {
+        return None;
+    }
+
+    let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).ok()?).ok()?;
+    let key: &VerifyingKey = cfg.signing_key.verifying_key();
+    key.verify(format!("{}.{}", header, claims).as_bytes(), &signature).ok()?;
+
+    let claims: Map<String, Value> = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()?;
+    if claims.get("iss")?.as_str()? != cfg.issuer || claims.get("exp")?.as_u64()? < get_time_sec() {
+        return None;
+    }
+
+    Some(claims)
+}
+
+// RFC 9068 access token. sub is the user id, or the client_id for
+// client_credentials.
+pub fn generate_access_token(cfg: &OidcConfig, sub: &str, client_id: &str, scope: &str) -> String {
+    let now = get_time_sec();
+    let claims = json!({
+        "iss": cfg.issuer,
+        "sub": sub,
+        "aud": client_id,
+        "client_id": client_id,
+        "scope": scope,
+        "iat": now,
+        "exp": now + cfg.token_life_time,
+        "jti": generate_token(),
+    });
+
+    sign_jwt(cfg, JWT_TYPE_ACCESS_TOKEN, &claims)
+}
+
+pub struct AccessTokenClaims {
+    pub sub: String,
+    pub scope: String,
+}
+
+pub fn verify_access_token(cfg: &OidcConfig, token: &str) -> Option<AccessTokenClaims> {
+    let claims = verify_jwt(cfg, JWT_TYPE_ACCESS_TOKEN, token)?;
+
+    Some(AccessTokenClaims {
+        sub: claims.get("sub")?.as_str()?.to_string(),
+        scope: claims.get("scope")?.as_str()?.to_string(),
+    })
+}
+
+pub fn generate_id_token(cfg: &OidcConfig, user: &UserGetResponse, client_id: &str, scope: &str, nonce: Option<&str>, auth_time: i64) -> String {
+    let now = get_time_sec();
+    let mut claims = json!({
+        "iss": cfg.issuer,
+        "sub": user.id.to_string(),
+        "aud": client_id,
+        "iat": now,
+        "exp": now + cfg.token_life_time,
+        "auth_time": auth_time,
+    });
+
+    if let Some(nonce) = nonce {
+        claims["nonce"] = json!(nonce);
+    }
+    if let Value::Object(profile) = json!(user_info(user, scope)) {
+        for (key, value) in profile {
+            claims[key] = value;
+        }
+    }
+
+    sign_jwt(cfg, JWT_TYPE_ID_TOKEN, &claims)
+}
+
+#[cfg(test)]
+pub fn verify_id_token(cfg: &OidcConfig, token: &str) -> Option<Map<String, Value>> {
+    verify_jwt(cfg, JWT_TYPE_ID_TOKEN, token)
+}
+
+pub fn jwks(cfg: &OidcConfig) -> Value {
+    let point = cfg.signing_key.verifying_key().to_encoded_point(false);
+
+    json!({
+        "keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(point.x().map(|x| x.to_vec()).unwrap_or_default()),
+            "y": URL_SAFE_NO_PAD.encode(point.y().map(|y| y.to_vec()).unwrap_or_default()),
+            "use": "sig",
+            "alg": JWT_ALG,
+            "kid": cfg.key_id,
+        }],
+    })
+}
+
+pub const JWKS_PATH: &str = "/api/oidc/jwks";
+pub const AUTHORIZE_PATH: &str = "/api/oidc/authorize";
+pub const TOKEN_PATH: &str = "/api/oidc/token";
+pub const USERINFO_PATH: &str = "/api/oidc/userinfo";
+
+pub fn discovery(cfg: &OidcConfig) -> Value {
+    json!({
+        "issuer": cfg.issuer,
+        "authorization_endpoint": cfg.endpoint(AUTHORIZE_PATH),
+        "token_endpoint": cfg.endpoint(TOKEN_PATH),
+        "userinfo_endpoint": cfg.endpoint(USERINFO_PATH),
+        "jwks_uri": cfg.endpoint(JWKS_PATH),
+        "response_types_supported": ["code"],
+        "grant_types_supported": [GRANT_AUTHORIZATION_CODE, GRANT_REFRESH_TOKEN, GRANT_CLIENT_CREDENTIALS],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": [JWT_ALG],
+        "scopes_supported": SCOPES,
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "code_challenge_methods_supported": ["S256"],
+        "claims_supported": [
+            "iss", "sub", "aud", "exp", "iat", "auth_time", "nonce",
+            "preferred_username", "name", "given_name", "family_name", "updated_at",
+        ],
+    })
+}
+
+// verifying
+
+// Redirect URIs are compared exactly, so they must be absolute, without a
+// fragment, and on HTTPS unless they point back at this machine.
+fn valid_redirect_uri(uri: &str) -> bool {
+    if uri.len() > REDIRECT_URI_MAX_LEN || uri.contains('#') || uri.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+
+    let rest = match uri.strip_prefix("https://").or_else(|| uri.strip_prefix("http://")) {
+        Some(res) => {
+            res
+        }
+        None => {
+            return false;
+        }
+    };
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = authority.rsplit_once(':').map(|(host, _)| host).unwrap_or(authority);
+
+    !host.is_empty() && (uri.starts_with("https://") || host == "localhost" || host == "127.0.0.1")
+}
+
+pub fn verify_oidc_client_create_request(req: &OidcClientCreateRequest) -> Result<(), String> {
+    if req.name.trim().is_empty() || req.name.chars().count() > OIDC_CLIENT_NAME_MAX_LEN {
+        return Err(format!("name must be 1 to {} characters", OIDC_CLIENT_NAME_MAX_LEN));
+    }
+
+    if req.redirect_uris.is_empty() || req.redirect_uris.len() > OIDC_MAX_REDIRECT_URIS {
+        return Err(format!("redirect_uris must have 1 to {} entries", OIDC_MAX_REDIRECT_URIS));
+    }
+
+    if let Some(uri) = req.redirect_uris.iter().find(|uri| !valid_redirect_uri(uri)) {
+        return Err(format!("redirect_uri {} must be an absolute https URI without a fragment, or http on localhost", uri));
+    }
+
+    Ok(())
+}
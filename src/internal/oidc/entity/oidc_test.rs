@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::internal::oidc::entity::oidc::{
+        generate_access_token, generate_id_token, is_subset_scope, jwks, new_oidc_config, normalize_scope,
+        pkce_challenge, redirect_with, valid_code_verifier, verify_access_token, verify_id_token,
+        verify_oidc_client_create_request, OidcClientCreateRequest,
+    };
+    use crate::internal::user::entity::user::{UserGetResponse, UserStatus};
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn user() -> UserGetResponse {
+        UserGetResponse {
+            id: 1,
+            username: "JamesHolland".to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+            status: UserStatus::Active,
+            create_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+            update_ts: Utc.timestamp_opt(1676000100, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn signing_key_test() {
+        let cfg = new_oidc_config("https://id.example.com".to_string(), "secret", 5);
+        let same = new_oidc_config("https://id.example.com".to_string(), "secret", 5);
+        let other = new_oidc_config("https://id.example.com".to_string(), "other-secret", 5);
+        assert_eq!(cfg.key_id, same.key_id);
+        assert_ne!(cfg.key_id, other.key_id);
+        assert_eq!(cfg.token_life_time, 300);
+
+        let key = &jwks(&cfg)["keys"][0];
+        let point = cfg.signing_key.verifying_key().to_encoded_point(false);
+        assert_eq!((key["kty"].clone(), key["crv"].clone(), key["alg"].clone()), (json!("EC"), json!("P-256"), json!("ES256")));
+        assert_eq!(key["x"], json!(URL_SAFE_NO_PAD.encode(point.x().unwrap())));
+        assert_eq!(key["y"], json!(URL_SAFE_NO_PAD.encode(point.y().unwrap())));
+        assert_eq!(key["kid"], json!(cfg.key_id));
+    }
+
+    #[test]
+    fn tokens_test() {
+        let cfg = new_oidc_config("https://id.example.com".to_string(), "secret", 5);
+        let other_key = new_oidc_config("https://id.example.com".to_string(), "other-secret", 5);
+        let other_issuer = new_oidc_config("https://other.example.com".to_string(), "secret", 5);
+
+        let id_token = generate_id_token(&cfg, &user(), "client", "openid profile", Some("n-0S6"), 1676000200);
+        let claims = verify_id_token(&cfg, &id_token).unwrap();
+        assert_eq!((claims["sub"].clone(), claims["aud"].clone(), claims["nonce"].clone()), (json!("1"), json!("client"), json!("n-0S6")));
+        assert_eq!((claims["preferred_username"].clone(), claims["name"].clone()), (json!("JamesHolland"), json!("James Holland")));
+        assert_eq!((claims["auth_time"].clone(), claims["updated_at"].clone()), (json!(1676000200), json!(1676000100)));
+
+        let minimal = verify_id_token(&cfg, &generate_id_token(&cfg, &user(), "client", "openid", None, 0)).unwrap();
+        assert!(!minimal.contains_key("nonce") && !minimal.contains_key("preferred_username"));
+
+        let access_token = generate_access_token(&cfg, "1", "client", "openid");
+        let claims = verify_access_token(&cfg, &access_token).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.scope.as_str()), ("1", "openid"));
+
+        // The signature covers both parts, and an ID token is not an access token.
+        let parts: Vec<&str> = access_token.split('.').collect();
+        let forged_claims = URL_SAFE_NO_PAD.encode(json!({"iss": "https://id.example.com", "sub": "2", "scope": "openid", "exp": u64::MAX}).to_string());
+        let test_cases = vec! {
+            TestCase { input: format!("{}.{}.{}", parts[0], forged_claims, parts[2]), output: "forged claims" },
+            TestCase { input: generate_access_token(&other_key, "1", "client", "openid"), output: "other key" },
+            TestCase { input: generate_access_token(&other_issuer, "1", "client", "openid"), output: "other issuer" },
+            TestCase { input: id_token.clone(), output: "id token" },
+            TestCase { input: format!("{}.", access_token), output: "extra part" },
+            TestCase { input: "not-a-jwt".to_string(), output: "garbage" },
+        };
+        for test_case in test_cases {
+            assert!(verify_access_token(&cfg, &test_case.input).is_none(), "{}", test_case.output);
+        }
+        assert!(verify_id_token(&cfg, &access_token).is_none());
+    }
+
+    #[test]
+    fn pkce_test() {
+        // RFC 7636 appendix B
+        assert_eq!(pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let test_cases = vec! {
+            TestCase { input: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(), output: true },
+            TestCase { input: "a~.".repeat(42) + "aa", output: true },
+            TestCase { input: "a".repeat(42), output: false },
+            TestCase { input: "a".repeat(129), output: false },
+            TestCase { input: "a".repeat(42) + "+", output: false },
+        };
+        for test_case in test_cases {
+            assert_eq!(valid_code_verifier(&test_case.input), test_case.output, "{}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn scope_test() {
+        let test_cases = vec! {
+            TestCase { input: "profile openid", output: "openid profile" },
+            TestCase { input: "openid  email openid", output: "openid" },
+            TestCase { input: "email", output: "" },
+        };
+        for test_case in test_cases {
+            assert_eq!(normalize_scope(test_case.input), test_case.output);
+        }
+
+        assert!(is_subset_scope("openid", "openid profile"));
+        assert!(is_subset_scope("", "openid"));
+        assert!(!is_subset_scope("openid profile", "openid"));
+    }
+
+    #[test]
+    fn redirect_with_test() {
+        assert_eq!(redirect_with("https://app.example.com/cb", &[("code", "a b"), ("state", "x&y")]), "https://app.example.com/cb?code=a+b&state=x%26y");
+        assert_eq!(redirect_with("https://app.example.com/cb?tenant=1", &[("code", "c")]), "https://app.example.com/cb?tenant=1&code=c");
+    }
+
+    #[test]
+    fn verify_oidc_client_create_request_test() {
+        let request = |name: &str, redirect_uris: &[&str]| OidcClientCreateRequest {
+            name: name.to_string(),
+            redirect_uris: redirect_uris.iter().map(|uri| uri.to_string()).collect(),
+            confidential: false,
+        };
+
+        let test_cases = vec! {
+            TestCase { input: request("Wiki", &["https://wiki.example.com/cb", "http://localhost:3000/cb", "http://127.0.0.1/cb"]), output: Ok(()) },
+            TestCase { input: request(" ", &["https://wiki.example.com/cb"]), output: Err("name must be 1 to 128 characters".to_string()) },
+            TestCase { input: request("Wiki", &[]), output: Err("redirect_uris must have 1 to 10 entries".to_string()) },
+            TestCase {
+                input: request("Wiki", &["http://wiki.example.com/cb"]),
+                output: Err("redirect_uri http://wiki.example.com/cb must be an absolute https URI without a fragment, or http on localhost".to_string()),
+            },
+            TestCase {
+                input: request("Wiki", &["https://wiki.example.com/cb#x"]),
+                output: Err("redirect_uri https://wiki.example.com/cb#x must be an absolute https URI without a fragment, or http on localhost".to_string()),
+            },
+            TestCase {
+                input: request("Wiki", &["/cb"]),
+                output: Err("redirect_uri /cb must be an absolute https URI without a fragment, or http on localhost".to_string()),
+            },
+        };
+        for test_case in test_cases {
+            assert_eq!(verify_oidc_client_create_request(&test_case.input), test_case.output);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::internal::oidc::entity::oidc::{
+    generate_access_token, generate_id_token, generate_token, has_scope, hash_token, is_subset_scope, new_oidc_error,
+    normalize_scope, pkce_challenge, redirect_with, to_client_response, user_info, valid_code_verifier,
+    verify_access_token, AuthorizeRequest, OidcClient, OidcClientCreate, OidcClientCreateRequest, OidcClientResponse,
+    OidcCode, OidcError, OidcRefreshToken, TokenRequest, TokenResponse, UserInfo, ERROR_INSUFFICIENT_SCOPE,
+    ERROR_INVALID_CLIENT, ERROR_INVALID_GRANT, ERROR_INVALID_REQUEST, ERROR_INVALID_SCOPE, ERROR_INVALID_TOKEN,
+    ERROR_LOGIN_REQUIRED, ERROR_SERVER_ERROR, ERROR_UNAUTHORIZED_CLIENT, ERROR_UNSUPPORTED_GRANT_TYPE,
+    ERROR_UNSUPPORTED_RESPONSE_TYPE, GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, GRANT_REFRESH_TOKEN,
+    OIDC_CODE_LIFE_TIME, OIDC_REFRESH_TOKEN_LIFE_TIME, SCOPE_OPENID,
+};
+use crate::internal::oidc::usecase::traits::{ClientCredentials, OidcUseCase, UseCase};
+use crate::internal::audit::entity::audit::{diff, new_audit_record, AuditContext, AUDIT_OIDC_CLIENT_CREATE};
+use crate::internal::user::entity::token::get_time_sec;
+use crate::internal::user::usecase::traits::UseCase as UserUseCaseTrait;
+use crate::internal::user::usecase::user::login_allowed;
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+const TOKEN_TYPE_BEARER: &str = "Bearer";
+
+fn server_error() -> OidcError {
+    new_oidc_error(StatusCode::INTERNAL_SERVER_ERROR, ERROR_SERVER_ERROR, "Internal server error")
+}
+
+impl OidcUseCase {
+    // Client authentication for the token endpoint (RFC 6749 section 2.3.1).
+    // Public clients send only their client_id; confidential clients must
+    // also prove the secret.
+    async fn authenticate_client(&self, req: &TokenRequest, basic: Option<ClientCredentials>) -> Result<OidcClient, OidcError> {
+        let (client_id, client_secret) = match basic {
+            Some(res) => {
+                if req.client_secret.is_some() {
+                    return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_REQUEST, "Use only one client authentication method"));
+                }
+                (Some(res.client_id), Some(res.client_secret))
+            }
+            None => {
+                (req.client_id.clone(), req.client_secret.clone())
+            }
+        };
+
+        let client_id = match client_id {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_CLIENT, "Client authentication failed"));
+            }
+        };
+
+        let client = match self.repo.oidc_client_get(client_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_CLIENT, "Client authentication failed"));
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_client_get failed");
+                return Err(server_error());
+            }
+        };
+
+        let authenticated = match (&client.secret_hash, &client_secret) {
+            (Some(secret_hash), Some(secret)) => {
+                hash_token(secret) == *secret_hash
+            }
+            (None, None) => {
+                true
+            }
+            _ => {
+                false
+            }
+        };
+        if !authenticated {
+            return Err(new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_CLIENT, "Client authentication failed"));
+        }
+
+        Ok(client)
+    }
+
+    // Tokens for a user: an access token, an ID token when openid was
+    // granted, and the next refresh token. Refused once the account is no
+    // longer active, like a password login.
+    async fn issue_user_tokens(&self, client_id: &str, user_id: i32, scope: String, nonce: Option<&str>, auth_time: i64) -> Result<TokenResponse, OidcError> {
+        let user = match self.users.user_get_by_id(user_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) if err.status_code == StatusCode::NOT_FOUND => {
+                return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "The account no longer exists"));
+            }
+            Err(_err) => {
+                return Err(server_error());
+            }
+        };
+
+        if let Err(err) = login_allowed(user.status) {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, &err.error_msg));
+        }
+
+        let refresh_token = generate_token();
+        let stored = OidcRefreshToken {
+            client_id: client_id.to_string(),
+            user_id,
+            scope: scope.clone(),
+            auth_time,
+        };
+        if let Err(err) = self.repo.oidc_refresh_token_create(hash_token(&refresh_token), stored, OIDC_REFRESH_TOKEN_LIFE_TIME).await {
+            tracing::error!(error = %err, "repo.oidc_refresh_token_create failed");
+            return Err(server_error());
+        }
+
+        let id_token = has_scope(&scope, SCOPE_OPENID)
+            .then(|| generate_id_token(&self.cfg, &user, client_id, &scope, nonce, auth_time));
+
+        Ok(TokenResponse {
+            access_token: generate_access_token(&self.cfg, &user.id.to_string(), client_id, &scope),
+            token_type: TOKEN_TYPE_BEARER.to_string(),
+            expires_in: self.cfg.token_life_time,
+            scope,
+            refresh_token: Some(refresh_token),
+            id_token,
+        })
+    }
+
+    async fn grant_authorization_code(&self, client: OidcClient, req: TokenRequest) -> Result<TokenResponse, OidcError> {
+        let (code, code_verifier) = match (req.code, req.code_verifier) {
+            (Some(code), Some(code_verifier)) => {
+                (code, code_verifier)
+            }
+            _ => {
+                return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_REQUEST, "code and code_verifier are required"));
+            }
+        };
+
+        if !valid_code_verifier(&code_verifier) {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_REQUEST, "code_verifier must be 43 to 128 unreserved characters"));
+        }
+
+        // Taking the code spends it, so a code presented with the wrong
+        // client, redirect_uri or verifier can't be retried.
+        let stored = match self.repo.oidc_code_take(hash_token(&code)).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "Unknown, used or expired code"));
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_code_take failed");
+                return Err(server_error());
+            }
+        };
+
+        if stored.client_id != client.client_id {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "The code was issued to another client"));
+        }
+
+        if req.redirect_uri.as_deref() != Some(stored.redirect_uri.as_str()) {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "redirect_uri does not match the authorization request"));
+        }
+
+        if pkce_challenge(&code_verifier) != stored.code_challenge {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "code_verifier does not match the code_challenge"));
+        }
+
+        self.issue_user_tokens(&client.client_id, stored.user_id, stored.scope, stored.nonce.as_deref(), stored.auth_time).await
+    }
+
+    async fn grant_refresh_token(&self, client: OidcClient, req: TokenRequest) -> Result<TokenResponse, OidcError> {
+        let refresh_token = match req.refresh_token {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_REQUEST, "refresh_token is required"));
+            }
+        };
+
+        let stored = match self.repo.oidc_refresh_token_take(hash_token(&refresh_token)).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "Unknown, used or expired refresh_token"));
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_refresh_token_take failed");
+                return Err(server_error());
+            }
+        };
+
+        if stored.client_id != client.client_id {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_GRANT, "The refresh_token was issued to another client"));
+        }
+
+        let scope = match req.scope {
+            Some(requested) => {
+                if !is_subset_scope(&requested, &stored.scope) {
+                    return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_INVALID_SCOPE, "scope can't exceed the original grant"));
+                }
+                normalize_scope(&requested)
+            }
+            None => {
+                stored.scope
+            }
+        };
+
+        self.issue_user_tokens(&client.client_id, stored.user_id, scope, None, stored.auth_time).await
+    }
+
+    // The client acts for itself: no user, so no ID token, user scopes or
+    // refresh token.
+    fn grant_client_credentials(&self, client: OidcClient) -> Result<TokenResponse, OidcError> {
+        if client.secret_hash.is_none() {
+            return Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_UNAUTHORIZED_CLIENT, "Public clients can't use client_credentials"));
+        }
+
+        Ok(TokenResponse {
+            access_token: generate_access_token(&self.cfg, &client.client_id, &client.client_id, ""),
+            token_type: TOKEN_TYPE_BEARER.to_string(),
+            expires_in: self.cfg.token_life_time,
+            scope: String::new(),
+            refresh_token: None,
+            id_token: None,
+        })
+    }
+}
+
+#[async_trait]
+impl UseCase for OidcUseCase {
+    #[instrument(name = "oidc_use_case.oidc_client_create", skip_all, fields(name = %req.name))]
+    async fn oidc_client_create(&self, ctx: AuditContext, req: OidcClientCreateRequest) -> Result<OidcClientResponse, ErrorResponseUseCase> {
+        let client_secret = req.confidential.then(generate_token);
+        let client = OidcClientCreate {
+            client_id: generate_token(),
+            secret_hash: client_secret.as_deref().map(hash_token),
+            name: req.name.trim().to_string(),
+            redirect_uris: req.redirect_uris,
+        };
+
+        let res = match self.repo.oidc_client_create(client).await {
+            Ok(res) => {
+                to_client_response(res, client_secret)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_client_create failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(res);
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_OIDC_CLIENT_CREATE);
+        record.actor_id = self.users.actor_id(&ctx).await;
+        let after = json!({
+            "client_id": res.client_id,
+            "name": res.name,
+            "redirect_uris": res.redirect_uris,
+            "confidential": res.confidential,
+        });
+        record.changes = diff(&Value::Null, &after);
+        self.audit.record(record).await;
+
+        Ok(res)
+    }
+
+    #[instrument(name = "oidc_use_case.oidc_client_list", skip(self))]
+    async fn oidc_client_list(&self) -> Result<Vec<OidcClientResponse>, ErrorResponseUseCase> {
+        match self.repo.oidc_client_list().await {
+            Ok(res) => {
+                Ok(res.into_iter().map(|client| to_client_response(client, None)).collect())
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_client_list failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "oidc_use_case.oidc_authorize", skip_all, fields(client_id = ?req.client_id))]
+    async fn oidc_authorize(&self, username: Option<String>, req: AuthorizeRequest) -> Result<String, ErrorResponseUseCase> {
+        let bad_request = |error_msg: &str| ErrorResponseUseCase {
+            status_code: StatusCode::BAD_REQUEST,
+            error_msg: error_msg.to_string(),
+        };
+
+        // Until the client and redirect_uri check out, errors are shown here
+        // rather than sent to a URI an attacker may have chosen.
+        let client_id = match &req.client_id {
+            Some(res) => {
+                res.clone()
+            }
+            None => {
+                return Err(bad_request("client_id is required"));
+            }
+        };
+
+        let client = match self.repo.oidc_client_get(client_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(bad_request("Unknown client"));
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.oidc_client_get failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(res);
+            }
+        };
+
+        let redirect_uri = match &req.redirect_uri {
+            Some(res) if client.redirect_uris.contains(res) => {
+                res.clone()
+            }
+            Some(_res) => {
+                return Err(bad_request("redirect_uri is not registered for this client"));
+            }
+            None => {
+                return Err(bad_request("redirect_uri is required"));
+            }
+        };
+
+        let state = req.state.clone().unwrap_or_default();
+        let fail = |error: &str, description: &str| {
+            let mut params = vec![("error", error), ("error_description", description)];
+            if !state.is_empty() {
+                params.push(("state", &state));
+            }
+            Ok(redirect_with(&redirect_uri, &params))
+        };
+
+        if req.response_type.as_deref() != Some("code") {
+            return fail(ERROR_UNSUPPORTED_RESPONSE_TYPE, "response_type must be code");
+        }
+
+        let scope = normalize_scope(req.scope.as_deref().unwrap_or_default());
+        if !has_scope(&scope, SCOPE_OPENID) {
+            return fail(ERROR_INVALID_SCOPE, "scope must include openid");
+        }
+
+        let code_challenge = match (&req.code_challenge, req.code_challenge_method.as_deref()) {
+            (Some(res), Some("S256")) if res.len() == 43 && res.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => {
+                res.clone()
+            }
+            _ => {
+                return fail(ERROR_INVALID_REQUEST, "PKCE is required: code_challenge with code_challenge_method=S256");
+            }
+        };
+
+        let user = match username {
+            Some(username) => {
+                match self.users.user_get_by_username(username).await {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(err) if err.status_code == StatusCode::NOT_FOUND => {
+                        return fail(ERROR_LOGIN_REQUIRED, "The user is not logged in");
+                    }
+                    Err(err) => {
+                        return Err(err);
+                    }
+                }
+            }
+            None => {
+                return fail(ERROR_LOGIN_REQUIRED, "The user is not logged in");
+            }
+        };
+
+        let code = generate_token();
+        let stored = OidcCode {
+            client_id: client.client_id,
+            user_id: user.id,
+            redirect_uri: redirect_uri.clone(),
+            scope,
+            nonce: req.nonce,
+            code_challenge,
+            auth_time: get_time_sec() as i64,
+        };
+        if let Err(err) = self.repo.oidc_code_create(hash_token(&code), stored, OIDC_CODE_LIFE_TIME).await {
+            tracing::error!(error = %err, "repo.oidc_code_create failed");
+            return fail(ERROR_SERVER_ERROR, "Internal server error");
+        }
+
+        let mut params = vec![("code", code.as_str())];
+        if !state.is_empty() {
+            params.push(("state", &state));
+        }
+
+        Ok(redirect_with(&redirect_uri, &params))
+    }
+
+    #[instrument(name = "oidc_use_case.oidc_token", skip_all, fields(grant_type = %req.grant_type))]
+    async fn oidc_token(&self, req: TokenRequest, basic: Option<ClientCredentials>) -> Result<TokenResponse, OidcError> {
+        let client = self.authenticate_client(&req, basic).await?;
+
+        match req.grant_type.as_str() {
+            GRANT_AUTHORIZATION_CODE => {
+                self.grant_authorization_code(client, req).await
+            }
+            GRANT_REFRESH_TOKEN => {
+                self.grant_refresh_token(client, req).await
+            }
+            GRANT_CLIENT_CREDENTIALS => {
+                self.grant_client_credentials(client)
+            }
+            _ => {
+                Err(new_oidc_error(StatusCode::BAD_REQUEST, ERROR_UNSUPPORTED_GRANT_TYPE, "grant_type is not supported"))
+            }
+        }
+    }
+
+    #[instrument(name = "oidc_use_case.oidc_userinfo", skip_all)]
+    async fn oidc_userinfo(&self, access_token: String) -> Result<UserInfo, OidcError> {
+        let invalid_token = || new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_TOKEN, "The access token is invalid or expired");
+
+        let claims = match verify_access_token(&self.cfg, &access_token) {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(invalid_token());
+            }
+        };
+
+        if !has_scope(&claims.scope, SCOPE_OPENID) {
+            return Err(new_oidc_error(StatusCode::FORBIDDEN, ERROR_INSUFFICIENT_SCOPE, "The access token lacks the openid scope"));
+        }
+
+        let user_id: i32 = match claims.sub.parse() {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                return Err(invalid_token());
+            }
+        };
+
+        let user = match self.users.user_get_by_id(user_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) if err.status_code == StatusCode::NOT_FOUND => {
+                return Err(invalid_token());
+            }
+            Err(_err) => {
+                return Err(server_error());
+            }
+        };
+
+        // Access tokens already issued stop working with the account.
+        if let Err(err) = login_allowed(user.status) {
+            return Err(new_oidc_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_TOKEN, &err.error_msg));
+        }
+
+        Ok(user_info(&user, &claims.scope))
+    }
+}
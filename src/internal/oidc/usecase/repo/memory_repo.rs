@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use sqlx::Error;
+
+use crate::internal::oidc::entity::oidc::{OidcClient, OidcClientCreate, OidcCode, OidcRefreshToken};
+use crate::internal::oidc::usecase::traits::Repo;
+use crate::internal::user::entity::token::get_time_sec;
+
+// In-memory stand-in for OidcRepo, used by tests that must not touch Postgres.
+
+pub struct MemoryOidcCode {
+    pub code_hash: String,
+    pub code: OidcCode,
+    pub expire_ts: u64,
+}
+
+pub struct MemoryOidcRefreshToken {
+    pub token_hash: String,
+    pub token: OidcRefreshToken,
+    pub expire_ts: u64,
+}
+
+#[derive(Default)]
+pub struct MemoryOidcRepo {
+    pub clients: Mutex<Vec<OidcClient>>,
+    pub codes: Mutex<Vec<MemoryOidcCode>>,
+    pub refresh_tokens: Mutex<Vec<MemoryOidcRefreshToken>>,
+}
+
+pub fn new_memory_oidc_repo() -> MemoryOidcRepo {
+    MemoryOidcRepo::default()
+}
+
+#[async_trait]
+impl Repo for MemoryOidcRepo {
+    async fn oidc_client_create(&self, client: OidcClientCreate) -> Result<OidcClient, Error> {
+        let mut clients = self.clients.lock().unwrap();
+        let client = OidcClient {
+            client_id: client.client_id,
+            secret_hash: client.secret_hash,
+            name: client.name,
+            redirect_uris: client.redirect_uris,
+        };
+
+        clients.push(client.clone());
+        Ok(client)
+    }
+
+    async fn oidc_client_list(&self) -> Result<Vec<OidcClient>, Error> {
+        Ok(self.clients.lock().unwrap().clone())
+    }
+
+    async fn oidc_client_get(&self, client_id: String) -> Result<OidcClient, Error> {
+        self.clients.lock().unwrap().iter().find(|c| c.client_id == client_id).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn oidc_code_create(&self, code_hash: String, code: OidcCode, life_time: u64) -> Result<(), Error> {
+        let now = get_time_sec();
+        let mut codes = self.codes.lock().unwrap();
+        codes.retain(|c| c.expire_ts >= now);
+        codes.push(MemoryOidcCode { code_hash, code, expire_ts: now + life_time });
+        Ok(())
+    }
+
+    async fn oidc_code_take(&self, code_hash: String) -> Result<OidcCode, Error> {
+        let now = get_time_sec();
+        let mut codes = self.codes.lock().unwrap();
+        match codes.iter().position(|c| c.code_hash == code_hash && c.expire_ts >= now) {
+            Some(index) => {
+                Ok(codes.remove(index).code)
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn oidc_refresh_token_create(&self, token_hash: String, token: OidcRefreshToken, life_time: u64) -> Result<(), Error> {
+        let now = get_time_sec();
+        let mut tokens = self.refresh_tokens.lock().unwrap();
+        tokens.retain(|t| t.expire_ts >= now);
+        tokens.push(MemoryOidcRefreshToken { token_hash, token, expire_ts: now + life_time });
+        Ok(())
+    }
+
+    async fn oidc_refresh_token_take(&self, token_hash: String) -> Result<OidcRefreshToken, Error> {
+        let now = get_time_sec();
+        let mut tokens = self.refresh_tokens.lock().unwrap();
+        match tokens.iter().position(|t| t.token_hash == token_hash && t.expire_ts >= now) {
+            Some(index) => {
+                Ok(tokens.remove(index).token)
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn oidc_refresh_token_revoke_user(&self, user_id: i32) -> Result<u64, Error> {
+        let mut tokens = self.refresh_tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|t| t.token.user_id != user_id);
+        Ok((before - tokens.len()) as u64)
+    }
+}
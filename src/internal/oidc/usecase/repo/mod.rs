@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod repo;
+pub mod oidc_repo;
+pub mod oidc_repo_test;
+#[cfg(test)]
+pub mod memory_repo;
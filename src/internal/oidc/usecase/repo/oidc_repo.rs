@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::oidc::entity::oidc::{OidcClient, OidcClientCreate, OidcCode, OidcRefreshToken};
+use crate::internal::oidc::usecase::repo::repo::OidcRepo;
+use crate::internal::oidc::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+const CLIENT_COLUMNS: &str = "client_id, secret_hash, name, redirect_uris";
+
+#[async_trait]
+impl Repo for OidcRepo {
+    #[instrument(
+        name = "oidc_repo.oidc_client_create",
+        skip_all,
+        fields(client_id = %client.client_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_client_create(&self, client: OidcClientCreate) -> Result<OidcClient, Error> {
+        let sql = format!(
+            "INSERT INTO tbl_oidc_client(client_id, secret_hash, name, redirect_uris) VALUES($1, $2, $3, $4) RETURNING {}",
+            CLIENT_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OidcClient>(&sql)
+            .bind(client.client_id)
+            .bind(client.secret_hash)
+            .bind(client.name)
+            .bind(client.redirect_uris);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_client_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_client_list(&self) -> Result<Vec<OidcClient>, Error> {
+        let sql = format!("SELECT {} FROM tbl_oidc_client ORDER BY id", CLIENT_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OidcClient>(&sql);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_client_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_client_get(&self, client_id: String) -> Result<OidcClient, Error> {
+        let sql = format!("SELECT {} FROM tbl_oidc_client WHERE client_id=$1", CLIENT_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OidcClient>(&sql).bind(client_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_code_create",
+        skip_all,
+        fields(client_id = %code.client_id, user_id = code.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_code_create(&self, code_hash: String, code: OidcCode, life_time: u64) -> Result<(), Error> {
+        let sql = "WITH expired AS (DELETE FROM tbl_oidc_code WHERE expire_ts<now()) \
+            INSERT INTO tbl_oidc_code(code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge, auth_time, expire_ts) \
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8, now() + $9 * interval '1 second')";
+        record_statement(sql);
+        let query = sqlx::query(sql)
+            .bind(code_hash)
+            .bind(code.client_id)
+            .bind(code.user_id)
+            .bind(code.redirect_uri)
+            .bind(code.scope)
+            .bind(code.nonce)
+            .bind(code.code_challenge)
+            .bind(code.auth_time)
+            .bind(life_time as i64);
+
+        return match query.execute(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_code_take",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_code_take(&self, code_hash: String) -> Result<OidcCode, Error> {
+        let sql = "DELETE FROM tbl_oidc_code WHERE code_hash=$1 AND expire_ts>=now() \
+            RETURNING client_id, user_id, redirect_uri, scope, nonce, code_challenge, auth_time";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, OidcCode>(sql).bind(code_hash);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_refresh_token_create",
+        skip_all,
+        fields(client_id = %token.client_id, user_id = token.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_refresh_token_create(&self, token_hash: String, token: OidcRefreshToken, life_time: u64) -> Result<(), Error> {
+        let sql = "WITH expired AS (DELETE FROM tbl_oidc_refresh_token WHERE expire_ts<now()) \
+            INSERT INTO tbl_oidc_refresh_token(token_hash, client_id, user_id, scope, auth_time, expire_ts) \
+            VALUES($1, $2, $3, $4, $5, now() + $6 * interval '1 second')";
+        record_statement(sql);
+        let query = sqlx::query(sql)
+            .bind(token_hash)
+            .bind(token.client_id)
+            .bind(token.user_id)
+            .bind(token.scope)
+            .bind(token.auth_time)
+            .bind(life_time as i64);
+
+        return match query.execute(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_refresh_token_take",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_refresh_token_take(&self, token_hash: String) -> Result<OidcRefreshToken, Error> {
+        let sql = "DELETE FROM tbl_oidc_refresh_token WHERE token_hash=$1 AND expire_ts>=now() \
+            RETURNING client_id, user_id, scope, auth_time";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, OidcRefreshToken>(sql).bind(token_hash);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "oidc_repo.oidc_refresh_token_revoke_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn oidc_refresh_token_revoke_user(&self, user_id: i32) -> Result<u64, Error> {
+        let sql = "DELETE FROM tbl_oidc_refresh_token WHERE user_id=$1";
+        record_statement(sql);
+        let query = sqlx::query(sql).bind(user_id);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(data.rows_affected())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+    use sqlx::Error;
+
+    use crate::internal::oidc::entity::oidc::{OidcClientCreate, OidcCode, OidcRefreshToken};
+    use crate::internal::oidc::usecase::repo::repo::new_oidc_repo;
+    use crate::internal::oidc::usecase::traits::Repo;
+    use crate::internal::user::entity::user::UserCreateRequest;
+    use crate::internal::user::usecase::repo::repo::new_user_repo;
+    use crate::internal::user::usecase::traits::Repo as UserRepo;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    fn client_create(client_id: &str, secret_hash: Option<&str>) -> OidcClientCreate {
+        OidcClientCreate {
+            client_id: client_id.to_string(),
+            secret_hash: secret_hash.map(|s| s.to_string()),
+            name: "Wiki".to_string(),
+            redirect_uris: vec!["https://wiki.example.com/cb".to_string(), "http://localhost:3000/cb".to_string()],
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn oidc_client_test() {
+        let test_db = new_test_db().await;
+        let repo = new_oidc_repo(web::Data::new(test_db.db.clone()));
+
+        let public = repo.oidc_client_create(client_create("public", None)).await.unwrap();
+        let confidential = repo.oidc_client_create(client_create("confidential", Some("hash"))).await.unwrap();
+        assert_eq!(public.redirect_uris, vec!["https://wiki.example.com/cb", "http://localhost:3000/cb"]);
+        assert_eq!((public.secret_hash, confidential.secret_hash.as_deref()), (None, Some("hash")));
+        assert!(repo.oidc_client_create(client_create("public", None)).await.is_err());
+
+        let clients = repo.oidc_client_list().await.unwrap();
+        assert_eq!(clients.iter().map(|c| c.client_id.as_str()).collect::<Vec<_>>(), vec!["public", "confidential"]);
+        assert_eq!(repo.oidc_client_get("confidential".to_string()).await.unwrap().name, "Wiki");
+        assert!(matches!(repo.oidc_client_get("unknown".to_string()).await, Err(Error::RowNotFound)));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn oidc_code_and_refresh_token_test() {
+        let test_db = new_test_db().await;
+        let repo = new_oidc_repo(web::Data::new(test_db.db.clone()));
+        let users = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = users.user_create(UserCreateRequest {
+            username: "JamesHolland".to_string(),
+            password: "hashed-password".to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+        }).await.unwrap();
+        repo.oidc_client_create(client_create("wiki", None)).await.unwrap();
+
+        let code = OidcCode {
+            client_id: "wiki".to_string(),
+            user_id: user.id,
+            redirect_uri: "https://wiki.example.com/cb".to_string(),
+            scope: "openid profile".to_string(),
+            nonce: Some("n-0S6".to_string()),
+            code_challenge: "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_string(),
+            auth_time: 1676000000,
+        };
+
+        // Codes are single use and expire.
+        repo.oidc_code_create("a".to_string(), code.clone(), 60).await.unwrap();
+        assert_eq!(repo.oidc_code_take("a".to_string()).await.unwrap(), code);
+        assert!(matches!(repo.oidc_code_take("a".to_string()).await, Err(Error::RowNotFound)));
+        repo.oidc_code_create("b".to_string(), code.clone(), 0).await.unwrap();
+        sqlx::query("UPDATE tbl_oidc_code SET expire_ts=now() - interval '1 second' WHERE code_hash='b'")
+            .execute(&test_db.db)
+            .await
+            .unwrap();
+        assert!(matches!(repo.oidc_code_take("b".to_string()).await, Err(Error::RowNotFound)));
+
+        let token = OidcRefreshToken {
+            client_id: "wiki".to_string(),
+            user_id: user.id,
+            scope: "openid".to_string(),
+            auth_time: 1676000000,
+        };
+        repo.oidc_refresh_token_create("c".to_string(), token.clone(), 3600).await.unwrap();
+        assert_eq!(repo.oidc_refresh_token_take("c".to_string()).await.unwrap(), token);
+        assert!(matches!(repo.oidc_refresh_token_take("c".to_string()).await, Err(Error::RowNotFound)));
+
+        // Revoking takes every token of the user.
+        repo.oidc_refresh_token_create("e".to_string(), token.clone(), 3600).await.unwrap();
+        repo.oidc_refresh_token_create("f".to_string(), token.clone(), 3600).await.unwrap();
+        assert_eq!(repo.oidc_refresh_token_revoke_user(user.id).await.unwrap(), 2);
+        assert!(matches!(repo.oidc_refresh_token_take("e".to_string()).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.oidc_refresh_token_revoke_user(user.id).await.unwrap(), 0);
+
+        // Deleting the user drops whatever was issued to them.
+        repo.oidc_refresh_token_create("d".to_string(), token.clone(), 3600).await.unwrap();
+        users.user_delete_by_id(user.id).await.unwrap();
+        assert!(matches!(repo.oidc_refresh_token_take("d".to_string()).await, Err(Error::RowNotFound)));
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct OidcRepo {
+    pub db: Data<Db>,
+}
+
+pub fn new_oidc_repo(db: Data<Db>) -> OidcRepo {
+    OidcRepo{
+        db
+    }
+}
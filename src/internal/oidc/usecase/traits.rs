@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::internal::oidc::entity::oidc::{
+    AuthorizeRequest, OidcClient, OidcClientCreate, OidcClientCreateRequest, OidcClientResponse, OidcCode,
+    OidcConfig, OidcError, OidcRefreshToken, TokenRequest, TokenResponse, UserInfo,
+};
+use crate::internal::audit::entity::audit::AuditContext;
+use crate::internal::audit::usecase::traits::AuditUseCase;
+use crate::internal::user::usecase::traits::UserUseCase;
+use crate::internal::controller::response;
+
+#[derive(Clone)]
+pub struct OidcUseCase {
+    pub repo: Arc<dyn Repo>,
+    pub users: UserUseCase,
+    pub audit: AuditUseCase,
+    pub cfg: OidcConfig,
+}
+
+pub fn new_oidc_use_case(repo: Arc<dyn Repo>, users: UserUseCase, audit: AuditUseCase, cfg: OidcConfig) -> OidcUseCase {
+    OidcUseCase {
+        repo,
+        users,
+        audit,
+        cfg,
+    }
+}
+
+// Client credentials sent in an Authorization: Basic header.
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+#[async_trait]
+pub trait UseCase {
+    async fn oidc_client_create(&self, ctx: AuditContext, req: OidcClientCreateRequest) -> Result<OidcClientResponse, response::ErrorResponseUseCase>;
+    async fn oidc_client_list(&self) -> Result<Vec<OidcClientResponse>, response::ErrorResponseUseCase>;
+    // Returns where to send the browser: the client's redirect_uri with a
+    // code, or with an error once the client and redirect_uri are trusted.
+    async fn oidc_authorize(&self, username: Option<String>, req: AuthorizeRequest) -> Result<String, response::ErrorResponseUseCase>;
+    async fn oidc_token(&self, req: TokenRequest, basic: Option<ClientCredentials>) -> Result<TokenResponse, OidcError>;
+    async fn oidc_userinfo(&self, access_token: String) -> Result<UserInfo, OidcError>;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn oidc_client_create(&self, client: OidcClientCreate) -> Result<OidcClient, sqlx::Error>;
+    async fn oidc_client_list(&self) -> Result<Vec<OidcClient>, sqlx::Error>;
+    async fn oidc_client_get(&self, client_id: String) -> Result<OidcClient, sqlx::Error>;
+    // Also purges expired codes.
+    async fn oidc_code_create(&self, code_hash: String, code: OidcCode, life_time: u64) -> Result<(), sqlx::Error>;
+    // Deletes the code and returns it; RowNotFound when it is unknown or expired.
+    async fn oidc_code_take(&self, code_hash: String) -> Result<OidcCode, sqlx::Error>;
+    async fn oidc_refresh_token_create(&self, token_hash: String, token: OidcRefreshToken, life_time: u64) -> Result<(), sqlx::Error>;
+    // Deletes the token and returns it; RowNotFound when it is unknown or expired.
+    async fn oidc_refresh_token_take(&self, token_hash: String) -> Result<OidcRefreshToken, sqlx::Error>;
+    // Deletes every refresh token of the user and returns how many there were.
+    async fn oidc_refresh_token_revoke_user(&self, user_id: i32) -> Result<u64, sqlx::Error>;
+}
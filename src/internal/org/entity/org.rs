@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+// Organizations group accounts; a membership gives the account a role in the
+// organization. Admins of the service (auth.admins) manage every
+// organization, an organization's own admins only its members.
+pub const ORG_NAME_MAX_LEN: usize = 64;
+pub const ORG_DISPLAY_NAME_MAX_LEN: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum OrgRole {
+    Admin, // manages the organization and its members
+    Member,
+}
+
+impl OrgRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Admin => "admin",
+            OrgRole::Member => "member",
+        }
+    }
+}
+
+pub const ORG_ROLES: [OrgRole; 2] = [OrgRole::Admin, OrgRole::Member];
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Org {
+    pub id: i32,
+    /// Unique short name
+    pub name: String,
+    pub display_name: String,
+    pub create_ts: DateTime<Utc>,
+    pub update_ts: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OrgCreateRequest {
+    pub name: String,
+    pub display_name: String,
+}
+
+// Fields left out keep their value; any other field is refused.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OrgUpdateRequest {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct OrgMember {
+    pub user_id: i32,
+    pub username: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub role: OrgRole,
+    pub create_ts: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OrgMemberRequest {
+    pub role: String, // admin or member
+}
+
+// One of an account's memberships, as carried in its access tokens.
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct OrgMembership {
+    pub org_id: i32,
+    pub role: OrgRole,
+}
+
+pub fn parse_org_role(role: &str) -> Result<OrgRole, String> {
+    match ORG_ROLES.iter().find(|res| res.as_str() == role) {
+        Some(res) => {
+            Ok(*res)
+        }
+        None => {
+            let roles: Vec<&str> = ORG_ROLES.iter().map(OrgRole::as_str).collect();
+            Err(format!("role must be one of {}", roles.join(", ")))
+        }
+    }
+}
+
+// The value of the orgs claim of access tokens, e.g. "3:admin,7:member".
+pub fn membership_claim(memberships: &[OrgMembership]) -> String {
+    memberships.iter()
+        .map(|membership| format!("{}:{}", membership.org_id, membership.role.as_str()))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// Members see the organization and its members; service admins see all.
+pub fn can_read_org(admin: bool, role: Option<OrgRole>) -> bool {
+    admin || role.is_some()
+}
+
+pub fn can_manage_org(admin: bool, role: Option<OrgRole>) -> bool {
+    admin || role == Some(OrgRole::Admin)
+}
+
+pub fn apply_org_update(mut org: Org, req: OrgUpdateRequest) -> Org {
+    if let Some(name) = req.name {
+        org.name = name;
+    }
+    if let Some(display_name) = req.display_name {
+        org.display_name = display_name;
+    }
+
+    org
+}
+
+// The audited fields of an organization.
+pub fn audited_org(org: &Org) -> Value {
+    json!({
+        "org_id": org.id,
+        "name": org.name,
+        "display_name": org.display_name,
+    })
+}
+
+// The audited fields of a membership; the member is the audit target.
+pub fn audited_membership(org: &Org, role: OrgRole) -> Value {
+    json!({
+        "org_id": org.id,
+        "org": org.name,
+        "role": role.as_str(),
+    })
+}
+
+// verifying
+
+fn verify_name(name: &str) -> Result<(), String> {
+    let name_ok = !name.is_empty()
+        && name.len() <= ORG_NAME_MAX_LEN
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !name_ok {
+        return Err(format!("name must be 1 to {} characters of a-z, 0-9 and -", ORG_NAME_MAX_LEN));
+    }
+
+    Ok(())
+}
+
+fn verify_display_name(display_name: &str) -> Result<(), String> {
+    if display_name.trim().is_empty() || display_name.chars().count() > ORG_DISPLAY_NAME_MAX_LEN {
+        return Err(format!("display_name must be 1 to {} characters", ORG_DISPLAY_NAME_MAX_LEN));
+    }
+
+    Ok(())
+}
+
+pub fn verify_org_create_request(req: &OrgCreateRequest) -> Result<(), String> {
+    verify_name(&req.name)?;
+    verify_display_name(&req.display_name)
+}
+
+pub fn verify_org_update_request(req: &OrgUpdateRequest) -> Result<(), String> {
+    if req.name.is_none() && req.display_name.is_none() {
+        return Err("name or display_name is required".to_string());
+    }
+
+    if let Some(name) = &req.name {
+        verify_name(name)?;
+    }
+    if let Some(display_name) = &req.display_name {
+        verify_display_name(display_name)?;
+    }
+
+    Ok(())
+}
+
+pub fn verify_org_member_request(req: &OrgMemberRequest) -> Result<(), String> {
+    parse_org_role(&req.role).map(|_| ())
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::internal::org::entity::org::{
+        apply_org_update, can_manage_org, can_read_org, membership_claim, parse_org_role, verify_org_create_request,
+        verify_org_update_request, Org, OrgCreateRequest, OrgMembership, OrgRole, OrgUpdateRequest,
+    };
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn org() -> Org {
+        Org {
+            id: 3,
+            name: "research".to_string(),
+            display_name: "Research".to_string(),
+            create_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+            update_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn verify_org_create_request_test() {
+        let name_error = "name must be 1 to 64 characters of a-z, 0-9 and -".to_string();
+        let display_name_error = "display_name must be 1 to 100 characters".to_string();
+        let long_display_name = "x".repeat(101);
+        let test_cases = vec! {
+            TestCase { input: ("research", "Research & Development"), output: Ok(()) },
+            TestCase { input: ("team-42", "Team 42"), output: Ok(()) },
+            TestCase { input: ("", "Research"), output: Err(name_error.clone()) },
+            TestCase { input: ("Research", "Research"), output: Err(name_error.clone()) },
+            TestCase { input: ("r&d", "Research"), output: Err(name_error.clone()) },
+            TestCase { input: ("research", " "), output: Err(display_name_error.clone()) },
+            TestCase { input: ("research", &long_display_name), output: Err(display_name_error.clone()) },
+        };
+
+        for test_case in test_cases {
+            let req = OrgCreateRequest {
+                name: test_case.input.0.to_string(),
+                display_name: test_case.input.1.to_string(),
+            };
+            assert_eq!(verify_org_create_request(&req), test_case.output, "{:?}", test_case.input);
+        }
+
+        let long_name = OrgCreateRequest { name: "a".repeat(65), display_name: "A".to_string() };
+        assert_eq!(verify_org_create_request(&long_name), Err(name_error));
+    }
+
+    #[test]
+    fn verify_org_update_request_test() {
+        let test_cases = vec! {
+            TestCase { input: (Some("research"), None), output: Ok(()) },
+            TestCase { input: (None, Some("Research")), output: Ok(()) },
+            TestCase { input: (None, None), output: Err("name or display_name is required".to_string()) },
+            TestCase { input: (Some("R D"), None), output: Err("name must be 1 to 64 characters of a-z, 0-9 and -".to_string()) },
+            TestCase { input: (None, Some("")), output: Err("display_name must be 1 to 100 characters".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let req = OrgUpdateRequest {
+                name: test_case.input.0.map(|name| name.to_string()),
+                display_name: test_case.input.1.map(|display_name| display_name.to_string()),
+            };
+            assert_eq!(verify_org_update_request(&req), test_case.output, "{:?}", test_case.input);
+        }
+
+        assert!(serde_json::from_str::<OrgUpdateRequest>(r#"{"id": 4}"#).is_err());
+    }
+
+    #[test]
+    fn apply_org_update_test() {
+        let res = apply_org_update(org(), OrgUpdateRequest { name: None, display_name: Some("R&D".to_string()) });
+        assert_eq!((res.name.as_str(), res.display_name.as_str()), ("research", "R&D"));
+
+        let res = apply_org_update(org(), OrgUpdateRequest { name: Some("rnd".to_string()), display_name: None });
+        assert_eq!((res.name.as_str(), res.display_name.as_str()), ("rnd", "Research"));
+    }
+
+    #[test]
+    fn parse_org_role_test() {
+        let test_cases = vec! {
+            TestCase { input: "admin", output: Ok(OrgRole::Admin) },
+            TestCase { input: "member", output: Ok(OrgRole::Member) },
+            TestCase { input: "Admin", output: Err("role must be one of admin, member".to_string()) },
+            TestCase { input: "owner", output: Err("role must be one of admin, member".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(parse_org_role(test_case.input), test_case.output);
+        }
+    }
+
+    #[test]
+    fn org_access_test() {
+        let test_cases = vec! {
+            TestCase { input: (true, None), output: (true, true) },
+            TestCase { input: (false, Some(OrgRole::Admin)), output: (true, true) },
+            TestCase { input: (false, Some(OrgRole::Member)), output: (true, false) },
+            TestCase { input: (false, None), output: (false, false) },
+        };
+
+        for test_case in test_cases {
+            let (admin, role) = test_case.input;
+            assert_eq!((can_read_org(admin, role), can_manage_org(admin, role)), test_case.output, "{:?}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn membership_claim_test() {
+        assert_eq!(membership_claim(&[]), "");
+
+        let memberships = vec![
+            OrgMembership { org_id: 3, role: OrgRole::Admin },
+            OrgMembership { org_id: 7, role: OrgRole::Member },
+        ];
+        assert_eq!(membership_claim(&memberships), "3:admin,7:member");
+    }
+}
@@ -0,0 +1,3 @@
+pub mod traits;
+pub mod org;
+pub mod repo;
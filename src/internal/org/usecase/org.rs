@@ -0,0 +1,349 @@
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::internal::org::entity::org::{
+    apply_org_update, audited_membership, audited_org, can_manage_org, can_read_org, parse_org_role, Org,
+    OrgCreateRequest, OrgMember, OrgMemberRequest, OrgRole, OrgUpdateRequest,
+};
+use crate::internal::org::usecase::traits::{OrgUseCase, UseCase};
+use crate::internal::audit::entity::audit::{
+    diff, new_audit_record, AuditContext, AUDIT_ORG_CREATE, AUDIT_ORG_DELETE, AUDIT_ORG_MEMBER_REMOVE,
+    AUDIT_ORG_MEMBER_SET, AUDIT_ORG_UPDATE,
+};
+use crate::internal::user::entity::user::UserGet;
+use crate::internal::user::usecase::traits::UseCase as UserUseCaseTrait;
+use crate::internal::controller::response::ErrorResponseUseCase;
+
+fn internal_error(err: sqlx::Error, op: &str) -> ErrorResponseUseCase {
+    tracing::error!(error = %err, "repo.{} failed", op);
+    ErrorResponseUseCase {
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        error_msg: "Internal server error".to_string(),
+    }
+}
+
+fn org_not_found(id: i32) -> ErrorResponseUseCase {
+    ErrorResponseUseCase {
+        status_code: StatusCode::NOT_FOUND,
+        error_msg: format!("Organization with id={} not found", id),
+    }
+}
+
+// The caller as seen from one organization.
+struct OrgAccess {
+    org: Org,
+    caller: UserGet,
+    admin: bool, // a service admin, see auth.admins
+    role: Option<OrgRole>,
+}
+
+impl OrgAccess {
+    fn can_manage(&self) -> Result<(), ErrorResponseUseCase> {
+        if !can_manage_org(self.admin, self.role) {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::FORBIDDEN,
+                error_msg: "Only admins of the organization can change it".to_string(),
+            };
+
+            return Err(res);
+        }
+
+        Ok(())
+    }
+}
+
+impl OrgUseCase {
+    async fn caller(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
+        self.users.caller(username).await
+    }
+
+    async fn member(&self, org_id: i32, user_id: i32) -> Result<Option<OrgMember>, ErrorResponseUseCase> {
+        match self.repo.org_member_get(org_id, user_id).await {
+            Ok(res) => {
+                Ok(Some(res))
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                Ok(None)
+            }
+            Err(err) => {
+                Err(internal_error(err, "org_member_get"))
+            }
+        }
+    }
+
+    // The organization, if the caller may see it. To anyone else it does not
+    // exist, so its id tells them nothing.
+    async fn access(&self, username: String, id: i32) -> Result<OrgAccess, ErrorResponseUseCase> {
+        let caller = self.caller(username).await?;
+
+        let org = match self.repo.org_get(id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(org_not_found(id));
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_get"));
+            }
+        };
+
+        let admin = self.users.is_admin(caller.id);
+        let role = self.member(id, caller.id).await?.map(|member| member.role);
+        if !can_read_org(admin, role) {
+            return Err(org_not_found(id));
+        }
+
+        Ok(OrgAccess {
+            org,
+            caller,
+            admin,
+            role,
+        })
+    }
+
+    async fn name_available(&self, name: &str) -> Result<(), ErrorResponseUseCase> {
+        match self.repo.org_get_by_name(name.to_string()).await {
+            Ok(_) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: format!("Organization with name={} already exists", name),
+                };
+
+                Err(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                Ok(())
+            }
+            Err(err) => {
+                Err(internal_error(err, "org_get_by_name"))
+            }
+        }
+    }
+
+    // Refuses to take the admin role from the only admin of the organization,
+    // which would leave nobody but the service admins to manage it.
+    async fn keep_admin(&self, org_id: i32, member: &OrgMember) -> Result<(), ErrorResponseUseCase> {
+        if member.role != OrgRole::Admin {
+            return Ok(());
+        }
+
+        let members = match self.repo.org_member_list(org_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_member_list"));
+            }
+        };
+
+        if members.iter().filter(|m| m.role == OrgRole::Admin).count() <= 1 {
+            let res = ErrorResponseUseCase {
+                status_code: StatusCode::CONFLICT,
+                error_msg: "An organization must keep at least one admin".to_string(),
+            };
+
+            return Err(res);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UseCase for OrgUseCase {
+    #[instrument(name = "org_use_case.org_create", skip_all, fields(name = %req.name))]
+    async fn org_create(&self, ctx: AuditContext, req: OrgCreateRequest) -> Result<Org, ErrorResponseUseCase> {
+        self.name_available(&req.name).await?;
+
+        let res = match self.repo.org_create(req).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_create"));
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_ORG_CREATE);
+        record.actor_id = self.users.actor_id(&ctx).await;
+        record.changes = diff(&Value::Null, &audited_org(&res));
+        self.audit.record(record).await;
+
+        Ok(res)
+    }
+
+    #[instrument(name = "org_use_case.org_list", skip(self))]
+    async fn org_list(&self, username: String) -> Result<Vec<Org>, ErrorResponseUseCase> {
+        let caller = self.caller(username).await?;
+
+        if self.users.is_admin(caller.id) {
+            return self.repo.org_list().await.map_err(|err| internal_error(err, "org_list"));
+        }
+
+        self.repo.org_list_by_user(caller.id).await.map_err(|err| internal_error(err, "org_list_by_user"))
+    }
+
+    #[instrument(name = "org_use_case.org_get", skip(self))]
+    async fn org_get(&self, username: String, id: i32) -> Result<Org, ErrorResponseUseCase> {
+        self.access(username, id).await.map(|access| access.org)
+    }
+
+    #[instrument(name = "org_use_case.org_update", skip(self, ctx, req))]
+    async fn org_update(&self, ctx: AuditContext, username: String, id: i32, req: OrgUpdateRequest) -> Result<Org, ErrorResponseUseCase> {
+        let access = self.access(username, id).await?;
+        access.can_manage()?;
+
+        if let Some(name) = &req.name {
+            if *name != access.org.name {
+                self.name_available(name).await?;
+            }
+        }
+
+        let before = access.org.clone();
+        let res = match self.repo.org_update(apply_org_update(access.org, req)).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(org_not_found(id));
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_update"));
+            }
+        };
+
+        let changes = diff(&audited_org(&before), &audited_org(&res));
+        if changes.as_object().is_some_and(|changes| !changes.is_empty()) {
+            let mut record = new_audit_record(&ctx, AUDIT_ORG_UPDATE);
+            record.actor_id = Some(access.caller.id);
+            record.changes = changes;
+            self.audit.record(record).await;
+        }
+
+        Ok(res)
+    }
+
+    #[instrument(name = "org_use_case.org_delete", skip(self, ctx))]
+    async fn org_delete(&self, ctx: AuditContext, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let org = match self.repo.org_delete(id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return Err(org_not_found(id));
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_delete"));
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_ORG_DELETE);
+        record.actor_id = self.users.actor_id(&ctx).await;
+        record.changes = diff(&audited_org(&org), &Value::Null);
+        self.audit.record(record).await;
+
+        Ok(())
+    }
+
+    #[instrument(name = "org_use_case.org_member_list", skip(self))]
+    async fn org_member_list(&self, username: String, id: i32) -> Result<Vec<OrgMember>, ErrorResponseUseCase> {
+        self.access(username, id).await?;
+        self.repo.org_member_list(id).await.map_err(|err| internal_error(err, "org_member_list"))
+    }
+
+    #[instrument(name = "org_use_case.org_member_set", skip(self, ctx, req), fields(role = %req.role))]
+    async fn org_member_set(&self, ctx: AuditContext, username: String, id: i32, user_id: i32, req: OrgMemberRequest) -> Result<OrgMember, ErrorResponseUseCase> {
+        let role = match parse_org_role(&req.role) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(ErrorResponseUseCase { status_code: StatusCode::BAD_REQUEST, error_msg: err });
+            }
+        };
+
+        let access = self.access(username, id).await?;
+        access.can_manage()?;
+
+        self.users.user_get_by_id(user_id).await?;
+
+        let before = self.member(id, user_id).await?;
+        if let Some(member) = &before {
+            if member.role == role {
+                return Ok(member.clone());
+            }
+            self.keep_admin(id, member).await?;
+        }
+
+        let res = match self.repo.org_member_set(id, user_id, role).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_member_set"));
+            }
+        };
+
+        let before = before.map(|member| audited_membership(&access.org, member.role)).unwrap_or(Value::Null);
+        let mut record = new_audit_record(&ctx, AUDIT_ORG_MEMBER_SET);
+        record.actor_id = Some(access.caller.id);
+        record.target_id = Some(res.user_id);
+        record.target_username = Some(res.username.clone());
+        record.changes = diff(&before, &audited_membership(&access.org, res.role));
+        self.audit.record(record).await;
+
+        Ok(res)
+    }
+
+    #[instrument(name = "org_use_case.org_member_remove", skip(self, ctx))]
+    async fn org_member_remove(&self, ctx: AuditContext, username: String, id: i32, user_id: i32) -> Result<(), ErrorResponseUseCase> {
+        let access = self.access(username, id).await?;
+        if user_id != access.caller.id {
+            access.can_manage()?;
+        }
+
+        let member = match self.member(id, user_id).await? {
+            Some(res) => {
+                res
+            }
+            None => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} is not a member", user_id),
+                };
+
+                return Err(res);
+            }
+        };
+        self.keep_admin(id, &member).await?;
+
+        let res = match self.repo.org_member_delete(id, user_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} is not a member", user_id),
+                };
+
+                return Err(res);
+            }
+            Err(err) => {
+                return Err(internal_error(err, "org_member_delete"));
+            }
+        };
+
+        let mut record = new_audit_record(&ctx, AUDIT_ORG_MEMBER_REMOVE);
+        record.actor_id = Some(access.caller.id);
+        record.target_id = Some(res.user_id);
+        record.target_username = Some(res.username);
+        record.changes = diff(&audited_membership(&access.org, res.role), &Value::Null);
+        self.audit.record(record).await;
+
+        Ok(())
+    }
+}
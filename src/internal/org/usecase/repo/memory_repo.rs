@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+
+use crate::internal::org::entity::org::{Org, OrgCreateRequest, OrgMember, OrgMembership, OrgRole};
+use crate::internal::org::usecase::traits::Repo;
+use crate::internal::user::usecase::repo::memory_repo::MemoryUserRepo;
+
+// In-memory stand-in for OrgRepo, used by tests that must not touch Postgres.
+// Members are joined with the accounts of the user repo it is given.
+
+#[derive(Clone)]
+pub struct MemoryOrgMember {
+    pub org_id: i32,
+    pub user_id: i32,
+    pub role: OrgRole,
+    pub create_ts: DateTime<Utc>,
+}
+
+pub struct MemoryOrgRepo {
+    pub users: Arc<MemoryUserRepo>,
+    pub orgs: Mutex<Vec<Org>>,
+    pub members: Mutex<Vec<MemoryOrgMember>>,
+}
+
+pub fn new_memory_org_repo(users: Arc<MemoryUserRepo>) -> MemoryOrgRepo {
+    MemoryOrgRepo {
+        users,
+        orgs: Mutex::new(Vec::new()),
+        members: Mutex::new(Vec::new()),
+    }
+}
+
+impl MemoryOrgRepo {
+    fn member(&self, member: &MemoryOrgMember) -> Result<OrgMember, Error> {
+        let users = self.users.users.lock().unwrap();
+        let user = users.iter().find(|u| u.id == member.user_id).ok_or(Error::RowNotFound)?;
+
+        Ok(OrgMember {
+            user_id: user.id,
+            username: user.username.clone(),
+            firstname: user.firstname.clone(),
+            lastname: user.lastname.clone(),
+            role: member.role,
+            create_ts: member.create_ts,
+        })
+    }
+}
+
+#[async_trait]
+impl Repo for MemoryOrgRepo {
+    async fn org_create(&self, req: OrgCreateRequest) -> Result<Org, Error> {
+        let mut orgs = self.orgs.lock().unwrap();
+        let org = Org {
+            id: orgs.iter().map(|o| o.id).max().unwrap_or(0) + 1,
+            name: req.name,
+            display_name: req.display_name,
+            create_ts: Utc::now(),
+            update_ts: Utc::now(),
+        };
+
+        orgs.push(org.clone());
+        Ok(org)
+    }
+
+    async fn org_get(&self, id: i32) -> Result<Org, Error> {
+        self.orgs.lock().unwrap().iter().find(|o| o.id == id).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn org_get_by_name(&self, name: String) -> Result<Org, Error> {
+        self.orgs.lock().unwrap().iter().find(|o| o.name == name).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn org_list(&self) -> Result<Vec<Org>, Error> {
+        Ok(self.orgs.lock().unwrap().clone())
+    }
+
+    async fn org_list_by_user(&self, user_id: i32) -> Result<Vec<Org>, Error> {
+        let members = self.members.lock().unwrap();
+        Ok(self.orgs.lock().unwrap().iter()
+            .filter(|o| members.iter().any(|m| m.org_id == o.id && m.user_id == user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn org_update(&self, org: Org) -> Result<Org, Error> {
+        let mut orgs = self.orgs.lock().unwrap();
+        let stored = orgs.iter_mut().find(|o| o.id == org.id).ok_or(Error::RowNotFound)?;
+        stored.name = org.name;
+        stored.display_name = org.display_name;
+        stored.update_ts = Utc::now();
+
+        Ok(stored.clone())
+    }
+
+    async fn org_delete(&self, id: i32) -> Result<Org, Error> {
+        let mut orgs = self.orgs.lock().unwrap();
+        let index = orgs.iter().position(|o| o.id == id).ok_or(Error::RowNotFound)?;
+        self.members.lock().unwrap().retain(|m| m.org_id != id);
+
+        Ok(orgs.remove(index))
+    }
+
+    async fn org_member_list(&self, org_id: i32) -> Result<Vec<OrgMember>, Error> {
+        let mut members: Vec<MemoryOrgMember> = self.members.lock().unwrap().iter().filter(|m| m.org_id == org_id).cloned().collect();
+        members.sort_by_key(|m| m.user_id);
+
+        members.iter().map(|m| self.member(m)).collect()
+    }
+
+    async fn org_member_get(&self, org_id: i32, user_id: i32) -> Result<OrgMember, Error> {
+        let member = self.members.lock().unwrap().iter()
+            .find(|m| m.org_id == org_id && m.user_id == user_id)
+            .cloned()
+            .ok_or(Error::RowNotFound)?;
+
+        self.member(&member)
+    }
+
+    async fn org_member_set(&self, org_id: i32, user_id: i32, role: OrgRole) -> Result<OrgMember, Error> {
+        let member = {
+            let mut members = self.members.lock().unwrap();
+            match members.iter_mut().find(|m| m.org_id == org_id && m.user_id == user_id) {
+                Some(member) => {
+                    member.role = role;
+                    member.clone()
+                }
+                None => {
+                    let member = MemoryOrgMember { org_id, user_id, role, create_ts: Utc::now() };
+                    members.push(member.clone());
+                    member
+                }
+            }
+        };
+
+        self.member(&member)
+    }
+
+    async fn org_member_delete(&self, org_id: i32, user_id: i32) -> Result<OrgMember, Error> {
+        let member = {
+            let mut members = self.members.lock().unwrap();
+            let index = members.iter().position(|m| m.org_id == org_id && m.user_id == user_id).ok_or(Error::RowNotFound)?;
+            members.remove(index)
+        };
+
+        self.member(&member)
+    }
+
+    async fn org_membership_list_by_user(&self, user_id: i32) -> Result<Vec<OrgMembership>, Error> {
+        let mut memberships: Vec<OrgMembership> = self.members.lock().unwrap().iter()
+            .filter(|m| m.user_id == user_id)
+            .map(|m| OrgMembership { org_id: m.org_id, role: m.role })
+            .collect();
+        memberships.sort_by_key(|m| m.org_id);
+
+        Ok(memberships)
+    }
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
+
+use crate::internal::org::entity::org::{Org, OrgCreateRequest, OrgMember, OrgMembership, OrgRole};
+use crate::internal::org::usecase::repo::repo::OrgRepo;
+use crate::internal::org::usecase::traits::Repo;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
+
+const ORG_COLUMNS: &str = "id, name, display_name, create_ts, update_ts";
+// Over an alias m of tbl_org_member joined with u of tbl_user.
+const MEMBER_COLUMNS: &str = "m.user_id, u.username, u.firstname, u.lastname, m.role, m.create_ts";
+
+#[async_trait]
+impl Repo for OrgRepo {
+    #[instrument(
+        name = "org_repo.org_create",
+        skip_all,
+        fields(name = %req.name, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_create(&self, req: OrgCreateRequest) -> Result<Org, Error> {
+        let sql = format!("INSERT INTO tbl_org(name, display_name) VALUES($1, $2) RETURNING {}", ORG_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(req.name).bind(req.display_name);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_get(&self, id: i32) -> Result<Org, Error> {
+        let sql = format!("SELECT {} FROM tbl_org WHERE id=$1", ORG_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_get_by_name",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_get_by_name(&self, name: String) -> Result<Org, Error> {
+        let sql = format!("SELECT {} FROM tbl_org WHERE name=$1", ORG_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(name);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_list(&self) -> Result<Vec<Org>, Error> {
+        let sql = format!("SELECT {} FROM tbl_org ORDER BY id", ORG_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_list_by_user(&self, user_id: i32) -> Result<Vec<Org>, Error> {
+        let sql = format!(
+            "SELECT {} FROM tbl_org WHERE id IN (SELECT org_id FROM tbl_org_member WHERE user_id=$1) ORDER BY id",
+            ORG_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_update",
+        skip_all,
+        fields(id = org.id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_update(&self, org: Org) -> Result<Org, Error> {
+        let sql = format!(
+            "UPDATE tbl_org SET name=$1, display_name=$2, update_ts=now() WHERE id=$3 RETURNING {}",
+            ORG_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(org.name).bind(org.display_name).bind(org.id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_delete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_delete(&self, id: i32) -> Result<Org, Error> {
+        let sql = format!("DELETE FROM tbl_org WHERE id=$1 RETURNING {}", ORG_COLUMNS);
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, Org>(&sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_member_list",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_member_list(&self, org_id: i32) -> Result<Vec<OrgMember>, Error> {
+        let sql = format!(
+            "SELECT {} FROM tbl_org_member m JOIN tbl_user u ON u.id=m.user_id WHERE m.org_id=$1 ORDER BY m.user_id",
+            MEMBER_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OrgMember>(&sql).bind(org_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_member_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_member_get(&self, org_id: i32, user_id: i32) -> Result<OrgMember, Error> {
+        let sql = format!(
+            "SELECT {} FROM tbl_org_member m JOIN tbl_user u ON u.id=m.user_id WHERE m.org_id=$1 AND m.user_id=$2",
+            MEMBER_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OrgMember>(&sql).bind(org_id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_member_set",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_member_set(&self, org_id: i32, user_id: i32, role: OrgRole) -> Result<OrgMember, Error> {
+        let sql = format!(
+            "WITH m AS (INSERT INTO tbl_org_member(org_id, user_id, role) VALUES($1, $2, $3) \
+            ON CONFLICT (org_id, user_id) DO UPDATE SET role=EXCLUDED.role RETURNING *) \
+            SELECT {} FROM m JOIN tbl_user u ON u.id=m.user_id",
+            MEMBER_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OrgMember>(&sql).bind(org_id).bind(user_id).bind(role);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_member_delete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_member_delete(&self, org_id: i32, user_id: i32) -> Result<OrgMember, Error> {
+        let sql = format!(
+            "WITH m AS (DELETE FROM tbl_org_member WHERE org_id=$1 AND user_id=$2 RETURNING *) \
+            SELECT {} FROM m JOIN tbl_user u ON u.id=m.user_id",
+            MEMBER_COLUMNS,
+        );
+        record_statement(&sql);
+        let query = sqlx::query_as::<_, OrgMember>(&sql).bind(org_id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "org_repo.org_membership_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn org_membership_list_by_user(&self, user_id: i32) -> Result<Vec<OrgMembership>, Error> {
+        let sql = "SELECT org_id, role FROM tbl_org_member WHERE user_id=$1 ORDER BY org_id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, OrgMembership>(sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+    use sqlx::Error;
+
+    use crate::internal::org::entity::org::{OrgCreateRequest, OrgMembership, OrgRole};
+    use crate::internal::org::usecase::repo::repo::new_org_repo;
+    use crate::internal::org::usecase::traits::Repo;
+    use crate::internal::user::entity::user::UserCreateRequest;
+    use crate::internal::user::usecase::repo::repo::new_user_repo;
+    use crate::internal::user::usecase::traits::Repo as UserRepo;
+    use crate::pkg::postgres::test_db::new_test_db;
+
+    fn org_create(name: &str) -> OrgCreateRequest {
+        OrgCreateRequest {
+            name: name.to_string(),
+            display_name: name.to_uppercase(),
+        }
+    }
+
+    fn user_create(username: &str) -> UserCreateRequest {
+        UserCreateRequest {
+            username: username.to_string(),
+            password: "hashed-password".to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn org_test() {
+        let test_db = new_test_db().await;
+        let repo = new_org_repo(web::Data::new(test_db.db.clone()));
+
+        let research = repo.org_create(org_create("research")).await.unwrap();
+        let sales = repo.org_create(org_create("sales")).await.unwrap();
+        assert_eq!((research.name.as_str(), research.display_name.as_str()), ("research", "RESEARCH"));
+        assert!(repo.org_create(org_create("research")).await.is_err());
+
+        assert_eq!(repo.org_get(research.id).await.unwrap(), research);
+        assert_eq!(repo.org_get_by_name("sales".to_string()).await.unwrap(), sales);
+        assert!(matches!(repo.org_get(sales.id + 1).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.org_get_by_name("unknown".to_string()).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.org_list().await.unwrap().iter().map(|o| o.name.as_str()).collect::<Vec<_>>(), vec!["research", "sales"]);
+
+        let mut renamed = research.clone();
+        renamed.name = "rnd".to_string();
+        renamed.display_name = "R&D".to_string();
+        let updated = repo.org_update(renamed).await.unwrap();
+        assert_eq!((updated.name.as_str(), updated.display_name.as_str(), updated.create_ts), ("rnd", "R&D", research.create_ts));
+        assert!(updated.update_ts >= research.update_ts);
+        let mut taken = sales.clone();
+        taken.name = "rnd".to_string();
+        assert!(repo.org_update(taken).await.is_err());
+
+        assert_eq!(repo.org_delete(sales.id).await.unwrap().name, "sales");
+        assert!(matches!(repo.org_delete(sales.id).await, Err(Error::RowNotFound)));
+        let mut gone = sales.clone();
+        gone.display_name = "Gone".to_string();
+        assert!(matches!(repo.org_update(gone).await, Err(Error::RowNotFound)));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn org_member_test() {
+        let test_db = new_test_db().await;
+        let repo = new_org_repo(web::Data::new(test_db.db.clone()));
+        let users = new_user_repo(web::Data::new(test_db.db.clone()));
+        let james = users.user_create(user_create("JamesHolland")).await.unwrap();
+        let anna = users.user_create(user_create("AnnaSmith")).await.unwrap();
+        let research = repo.org_create(org_create("research")).await.unwrap();
+        let sales = repo.org_create(org_create("sales")).await.unwrap();
+
+        let member = repo.org_member_set(research.id, james.id, OrgRole::Admin).await.unwrap();
+        assert_eq!((member.user_id, member.username.as_str(), member.role), (james.id, "JamesHolland", OrgRole::Admin));
+        repo.org_member_set(research.id, anna.id, OrgRole::Member).await.unwrap();
+        repo.org_member_set(sales.id, james.id, OrgRole::Member).await.unwrap();
+        assert!(repo.org_member_set(research.id, anna.id + 1, OrgRole::Member).await.is_err());
+
+        // Setting the role again changes it in place.
+        let promoted = repo.org_member_set(research.id, anna.id, OrgRole::Admin).await.unwrap();
+        assert_eq!(promoted.role, OrgRole::Admin);
+        assert_eq!(repo.org_member_get(research.id, anna.id).await.unwrap(), promoted);
+        assert!(matches!(repo.org_member_get(sales.id, anna.id).await, Err(Error::RowNotFound)));
+
+        let members = repo.org_member_list(research.id).await.unwrap();
+        assert_eq!(members.iter().map(|m| (m.username.as_str(), m.role)).collect::<Vec<_>>(), vec![("JamesHolland", OrgRole::Admin), ("AnnaSmith", OrgRole::Admin)]);
+
+        let memberships = repo.org_membership_list_by_user(james.id).await.unwrap();
+        assert_eq!(memberships, vec![
+            OrgMembership { org_id: research.id, role: OrgRole::Admin },
+            OrgMembership { org_id: sales.id, role: OrgRole::Member },
+        ]);
+        assert_eq!(repo.org_list_by_user(anna.id).await.unwrap(), vec![research.clone()]);
+
+        assert_eq!(repo.org_member_delete(research.id, anna.id).await.unwrap().username, "AnnaSmith");
+        assert!(matches!(repo.org_member_delete(research.id, anna.id).await, Err(Error::RowNotFound)));
+        assert!(repo.org_list_by_user(anna.id).await.unwrap().is_empty());
+
+        // Memberships go with their organization and with their account.
+        repo.org_delete(sales.id).await.unwrap();
+        assert_eq!(repo.org_membership_list_by_user(james.id).await.unwrap().len(), 1);
+        users.user_delete_by_id(james.id).await.unwrap();
+        assert!(repo.org_member_list(research.id).await.unwrap().is_empty());
+    }
+}
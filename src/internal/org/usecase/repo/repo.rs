@@ -0,0 +1,13 @@
+use actix_web::web::Data;
+use crate::pkg::postgres::connection::Db;
+
+#[derive(Clone)]
+pub struct OrgRepo {
+    pub db: Data<Db>,
+}
+
+pub fn new_org_repo(db: Data<Db>) -> OrgRepo {
+    OrgRepo{
+        db
+    }
+}
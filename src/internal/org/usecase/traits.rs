@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::internal::org::entity::org::{Org, OrgCreateRequest, OrgMember, OrgMemberRequest, OrgMembership, OrgRole, OrgUpdateRequest};
+use crate::internal::audit::entity::audit::AuditContext;
+use crate::internal::audit::usecase::traits::AuditUseCase;
+use crate::internal::user::usecase::traits::UserUseCase;
+use crate::internal::controller::response;
+
+#[derive(Clone)]
+pub struct OrgUseCase {
+    pub repo: Arc<dyn Repo>,
+    pub users: UserUseCase,
+    pub audit: AuditUseCase,
+}
+
+pub fn new_org_use_case(repo: Arc<dyn Repo>, users: UserUseCase, audit: AuditUseCase) -> OrgUseCase {
+    OrgUseCase {
+        repo,
+        users,
+        audit,
+    }
+}
+
+// username is the caller's. Organizations the caller may not see are
+// reported as not found; changes need an admin of the organization or of the
+// service.
+#[async_trait]
+pub trait UseCase {
+    async fn org_create(&self, ctx: AuditContext, req: OrgCreateRequest) -> Result<Org, response::ErrorResponseUseCase>;
+    // Every organization for service admins, the caller's own for others.
+    async fn org_list(&self, username: String) -> Result<Vec<Org>, response::ErrorResponseUseCase>;
+    async fn org_get(&self, username: String, id: i32) -> Result<Org, response::ErrorResponseUseCase>;
+    async fn org_update(&self, ctx: AuditContext, username: String, id: i32, req: OrgUpdateRequest) -> Result<Org, response::ErrorResponseUseCase>;
+    async fn org_delete(&self, ctx: AuditContext, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+    async fn org_member_list(&self, username: String, id: i32) -> Result<Vec<OrgMember>, response::ErrorResponseUseCase>;
+    // Adds the account to the organization, or changes its role there.
+    async fn org_member_set(&self, ctx: AuditContext, username: String, id: i32, user_id: i32, req: OrgMemberRequest) -> Result<OrgMember, response::ErrorResponseUseCase>;
+    // Members may also remove themselves.
+    async fn org_member_remove(&self, ctx: AuditContext, username: String, id: i32, user_id: i32) -> Result<(), response::ErrorResponseUseCase>;
+}
+
+#[async_trait]
+pub trait Repo: Send + Sync {
+    async fn org_create(&self, req: OrgCreateRequest) -> Result<Org, sqlx::Error>;
+    async fn org_get(&self, id: i32) -> Result<Org, sqlx::Error>;
+    async fn org_get_by_name(&self, name: String) -> Result<Org, sqlx::Error>;
+    async fn org_list(&self) -> Result<Vec<Org>, sqlx::Error>;
+    async fn org_list_by_user(&self, user_id: i32) -> Result<Vec<Org>, sqlx::Error>;
+    // Stores name and display_name; RowNotFound when the organization is gone.
+    async fn org_update(&self, org: Org) -> Result<Org, sqlx::Error>;
+    // Deletes the organization with its memberships and returns it.
+    async fn org_delete(&self, id: i32) -> Result<Org, sqlx::Error>;
+    async fn org_member_list(&self, org_id: i32) -> Result<Vec<OrgMember>, sqlx::Error>;
+    // RowNotFound when the account is not a member.
+    async fn org_member_get(&self, org_id: i32, user_id: i32) -> Result<OrgMember, sqlx::Error>;
+    async fn org_member_set(&self, org_id: i32, user_id: i32, role: OrgRole) -> Result<OrgMember, sqlx::Error>;
+    // Deletes the membership and returns it; RowNotFound when there is none.
+    async fn org_member_delete(&self, org_id: i32, user_id: i32) -> Result<OrgMember, sqlx::Error>;
+    async fn org_membership_list_by_user(&self, user_id: i32) -> Result<Vec<OrgMembership>, sqlx::Error>;
+}
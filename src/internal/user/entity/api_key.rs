@@ -0,0 +1,134 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+// Keys look like rck_<prefix>_<secret>, so they are easy to recognise in the
+// Authorization header and in leaked-secret scanners.
+pub const API_KEY_MARKER: &str = "rck_";
+pub const API_KEY_NAME_MAX_LEN: usize = 64;
+const API_KEY_PREFIX_LEN: usize = 6; // byte, hex encoded
+const API_KEY_SECRET_LEN: usize = 32; // byte
+
+// What a key may do. Bearer tokens of the owner can do everything; a key only
+// what its scopes allow, and never manage credentials.
+pub const SCOPE_USER_READ: &str = "user:read";
+pub const SCOPE_USER_WRITE: &str = "user:write";
+pub const API_KEY_SCOPES: [&str; 2] = [SCOPE_USER_READ, SCOPE_USER_WRITE];
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String, // hex SHA-256 of the secret
+    pub scopes: Vec<String>,
+    pub expire_ts: Option<DateTime<Utc>>,
+    pub create_ts: DateTime<Utc>,
+    pub last_used_ts: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyCreate {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub expire_ts: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApiKeyCreateRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expire_ts: Option<DateTime<Utc>>, // omitted for a key that doesn't expire
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expire_ts: Option<DateTime<Utc>>,
+    pub create_ts: DateTime<Utc>,
+    pub last_used_ts: Option<DateTime<Utc>>,
+}
+
+// Returned on create and rotate; the key itself is not stored and is shown
+// only here.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ApiKeySecretResponse {
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+    pub api_key: String,
+}
+
+pub fn to_api_key_response(key: ApiKey) -> ApiKeyResponse {
+    ApiKeyResponse {
+        id: key.id,
+        name: key.name,
+        prefix: key.prefix,
+        scopes: key.scopes,
+        expire_ts: key.expire_ts,
+        create_ts: key.create_ts,
+        last_used_ts: key.last_used_ts,
+    }
+}
+
+// A new key and the prefix and secret hash to store for it.
+pub fn generate_api_key() -> (String, String, String) {
+    let prefix = hex(&rand::random::<[u8; API_KEY_PREFIX_LEN]>());
+    let secret = URL_SAFE_NO_PAD.encode(rand::random::<[u8; API_KEY_SECRET_LEN]>());
+
+    (format!("{}{}_{}", API_KEY_MARKER, prefix, secret), prefix, hash_api_key_secret(&secret))
+}
+
+// The prefix and secret of a well-formed key. The secret may itself contain
+// '_', the prefix never does.
+pub fn parse_api_key(key: &str) -> Option<(&str, &str)> {
+    let (prefix, secret) = key.strip_prefix(API_KEY_MARKER)?.split_once('_')?;
+    if prefix.len() != API_KEY_PREFIX_LEN * 2 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) || secret.is_empty() {
+        return None;
+    }
+
+    Some((prefix, secret))
+}
+
+pub fn hash_api_key_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+pub fn has_api_key_scope(key: &ApiKey, scope: &str) -> bool {
+    key.scopes.iter().any(|s| s == scope)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// verifying
+
+pub fn verify_api_key_create_request(req: &ApiKeyCreateRequest, now: DateTime<Utc>) -> Result<(), String> {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > API_KEY_NAME_MAX_LEN {
+        return Err(format!("name must be 1 to {} characters", API_KEY_NAME_MAX_LEN));
+    }
+
+    if req.scopes.is_empty() {
+        return Err("scopes must not be empty".to_string());
+    }
+    if let Some(scope) = req.scopes.iter().find(|scope| !API_KEY_SCOPES.contains(&scope.as_str())) {
+        return Err(format!("unknown scope {}; expected one of {}", scope, API_KEY_SCOPES.join(", ")));
+    }
+
+    if req.expire_ts.is_some_and(|expire_ts| expire_ts <= now) {
+        return Err("expire_ts must be in the future".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::internal::user::entity::api_key::{
+        generate_api_key, hash_api_key_secret, parse_api_key, verify_api_key_create_request, ApiKeyCreateRequest,
+        API_KEY_MARKER,
+    };
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    #[test]
+    fn generate_api_key_test() {
+        let (key, prefix, secret_hash) = generate_api_key();
+        assert!(key.starts_with(&format!("{}{}_", API_KEY_MARKER, prefix)));
+
+        let (parsed_prefix, secret) = parse_api_key(&key).unwrap();
+        assert_eq!(parsed_prefix, prefix);
+        assert_eq!(hash_api_key_secret(secret), secret_hash);
+        assert_ne!(generate_api_key().1, prefix);
+    }
+
+    #[test]
+    fn parse_api_key_test() {
+        let test_cases = vec! {
+            TestCase { input: "rck_0123456789ab_secret", output: Some(("0123456789ab", "secret")) },
+            TestCase { input: "rck_0123456789ab_sec_ret", output: Some(("0123456789ab", "sec_ret")) },
+            TestCase { input: "rck_0123456789ab_", output: None },
+            TestCase { input: "rck_0123456789_secret", output: None },
+            TestCase { input: "rck_0123456789xz_secret", output: None },
+            TestCase { input: "0123456789ab_secret", output: None },
+            TestCase { input: "eyJhbGciOiJIUzI1NiJ9.e30.sig", output: None },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(parse_api_key(test_case.input), test_case.output, "{}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn verify_api_key_create_request_test() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let request = |name: &str, scopes: &[&str], expire_ts| ApiKeyCreateRequest {
+            name: name.to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expire_ts,
+        };
+
+        let test_cases = vec! {
+            TestCase { input: request("batch", &["user:read"], None), output: Ok(()) },
+            TestCase { input: request("batch", &["user:read", "user:write"], Some(now + Duration::days(30))), output: Ok(()) },
+            TestCase { input: request("  ", &["user:read"], None), output: Err("name must be 1 to 64 characters".to_string()) },
+            TestCase { input: request(&"a".repeat(65), &["user:read"], None), output: Err("name must be 1 to 64 characters".to_string()) },
+            TestCase { input: request("batch", &[], None), output: Err("scopes must not be empty".to_string()) },
+            TestCase {
+                input: request("batch", &["user:read", "admin"], None),
+                output: Err("unknown scope admin; expected one of user:read, user:write".to_string()),
+            },
+            TestCase { input: request("batch", &["user:read"], Some(now)), output: Err("expire_ts must be in the future".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_api_key_create_request(&test_case.input, now), test_case.output);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::internal::user::entity::user::{
+    verify_user_create_request, verify_user_status_request, UserCreateRequest, UserStatus, UserStatusRequest,
+    UserUpdateRequest,
+};
+
+pub const BATCH_MAX_OPERATIONS: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Create(UserCreateRequest),
+    Update(UserUpdateRequest),
+    Delete(BatchDeleteRequest),
+    Status(BatchStatusRequest),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BatchDeleteRequest {
+    pub id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BatchStatusRequest {
+    pub id: i32,
+    pub action: String, // activate, disable, lock or unlock
+    pub reason: Option<String>, // required to disable
+}
+
+impl BatchStatusRequest {
+    pub fn to_status_request(&self) -> UserStatusRequest {
+        UserStatusRequest {
+            action: self.action.clone(),
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchRequest {
+    // All operations or none: a failure rolls back the others. Otherwise each
+    // operation stands on its own.
+    #[serde(default)]
+    pub atomic: bool,
+    pub operations: Vec<BatchOperation>,
+}
+
+impl BatchOperation {
+    // The existing account the operation changes.
+    pub fn target_id(&self) -> Option<i32> {
+        match self {
+            BatchOperation::Create(_) => None,
+            BatchOperation::Update(req) => Some(req.id),
+            BatchOperation::Delete(req) => Some(req.id),
+            BatchOperation::Status(req) => Some(req.id),
+        }
+    }
+}
+
+// Every account is touched by at most one operation, so the order of the
+// operations never matters and each can be checked on its own.
+pub fn verify_batch_request(req: &BatchRequest) -> Result<(), String> {
+    if req.operations.is_empty() || req.operations.len() > BATCH_MAX_OPERATIONS {
+        return Err(format!("operations must have 1 to {} entries", BATCH_MAX_OPERATIONS));
+    }
+
+    let mut ids = HashSet::new();
+    let mut usernames = HashSet::new();
+    for op in &req.operations {
+        if let Some(id) = op.target_id() {
+            if !ids.insert(id) {
+                return Err(format!("User with id={} is in more than one operation", id));
+            }
+        }
+        if let BatchOperation::Create(user) = op {
+            if !usernames.insert(user.username.as_str()) {
+                return Err(format!("User with username={} is created more than once", user.username));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The checks the single user routes make on their request.
+pub fn verify_batch_operation(op: &BatchOperation) -> Result<(), String> {
+    match op {
+        BatchOperation::Create(req) => verify_user_create_request(req.clone()),
+        BatchOperation::Update(_) => Ok(()),
+        BatchOperation::Delete(_) => Ok(()),
+        BatchOperation::Status(req) => verify_user_status_request(&req.to_status_request()),
+    }
+}
+
+// A checked operation of an atomic batch, as written by Repo::user_batch.
+#[derive(Debug, Clone)]
+pub enum BatchWrite {
+    Create(UserCreateRequest), // with the password hashed
+    Update(UserUpdateRequest),
+    Delete(i32),
+    // Conditional on the account still having status from.
+    Status { id: i32, from: UserStatus, to: UserStatus, reason: Option<String> },
+}
+
+#[derive(Debug)]
+pub struct BatchWriteError {
+    pub index: Option<usize>, // of the failed write; None when the transaction itself failed
+    pub err: sqlx::Error,
+}
+
+impl fmt::Display for BatchWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "write {}: {}", index, self.err),
+            None => write!(f, "transaction: {}", self.err),
+        }
+    }
+}
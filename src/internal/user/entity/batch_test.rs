@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::internal::user::entity::batch::{
+        verify_batch_operation, verify_batch_request, BatchOperation, BatchRequest, BATCH_MAX_OPERATIONS,
+    };
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn request(operations: serde_json::Value) -> BatchRequest {
+        serde_json::from_value(json!({"operations": operations})).unwrap()
+    }
+
+    #[test]
+    fn batch_request_deserialize_test() {
+        let req: BatchRequest = serde_json::from_value(json!({
+            "atomic": true,
+            "operations": [
+                {"op": "create", "username": "AnnaSmith", "password": "password", "firstname": "Anna", "lastname": "Smith"},
+                {"op": "update", "id": 2, "username": "MaryJones", "firstname": "Mary", "lastname": "Jones"},
+                {"op": "delete", "id": 3},
+                {"op": "status", "id": 4, "action": "disable", "reason": "left"},
+            ],
+        })).unwrap();
+
+        assert!(req.atomic);
+        let targets: Vec<Option<i32>> = req.operations.iter().map(BatchOperation::target_id).collect();
+        assert_eq!(targets, vec![None, Some(2), Some(3), Some(4)]);
+
+        assert!(!request(json!([{"op": "delete", "id": 3}])).atomic);
+        assert!(serde_json::from_value::<BatchRequest>(json!({"operations": [{"op": "rename", "id": 3}]})).is_err());
+        assert!(serde_json::from_value::<BatchRequest>(json!({"operations": [{"id": 3}]})).is_err());
+    }
+
+    #[test]
+    fn verify_batch_request_test() {
+        let too_many: Vec<_> = (0..=BATCH_MAX_OPERATIONS).map(|id| json!({"op": "delete", "id": id})).collect();
+        let test_cases = vec! {
+            TestCase { input: json!([{"op": "delete", "id": 3}, {"op": "status", "id": 4, "action": "lock"}]), output: Ok(()) },
+            TestCase { input: json!([]), output: Err("operations must have 1 to 100 entries".to_string()) },
+            TestCase { input: json!(too_many), output: Err("operations must have 1 to 100 entries".to_string()) },
+            TestCase {
+                input: json!([{"op": "delete", "id": 3}, {"op": "status", "id": 3, "action": "lock"}]),
+                output: Err("User with id=3 is in more than one operation".to_string()),
+            },
+            TestCase {
+                input: json!([
+                    {"op": "create", "username": "AnnaSmith", "password": "password", "firstname": "Anna", "lastname": "Smith"},
+                    {"op": "create", "username": "AnnaSmith", "password": "password", "firstname": "Anna", "lastname": "Smith"},
+                ]),
+                output: Err("User with username=AnnaSmith is created more than once".to_string()),
+            },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_batch_request(&request(test_case.input)), test_case.output);
+        }
+    }
+
+    #[test]
+    fn verify_batch_operation_test() {
+        let test_cases = vec! {
+            TestCase { input: json!({"op": "create", "username": "AnnaSmith", "password": "password", "firstname": "Anna", "lastname": "Smith"}), output: Ok(()) },
+            TestCase {
+                input: json!({"op": "create", "username": "Anna", "password": "password", "firstname": "Anna", "lastname": "Smith"}),
+                output: Err("invalid username".to_string()),
+            },
+            TestCase { input: json!({"op": "update", "id": 2, "username": "", "firstname": "", "lastname": ""}), output: Ok(()) },
+            TestCase { input: json!({"op": "status", "id": 4, "action": "disable", "reason": " "}), output: Err("reason is required to disable an account".to_string()) },
+            TestCase { input: json!({"op": "status", "id": 4, "action": "ban"}), output: Err("action must be one of activate, disable, lock, unlock".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let op: BatchOperation = serde_json::from_value(test_case.input.clone()).unwrap();
+            assert_eq!(verify_batch_operation(&op), test_case.output, "{}", test_case.input);
+        }
+    }
+}
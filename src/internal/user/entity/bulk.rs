@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::internal::user::entity::user::{verify_user_create_request, UserCreateRequest, UserGetResponse};
+
+// An import is read record by record as it arrives, so these bound what is
+// held in memory instead of the request size.
+pub const IMPORT_MAX_ROWS: usize = 10_000;
+pub const IMPORT_MAX_RECORD_LEN: usize = 64 * 1024; // byte
+pub const EXPORT_PAGE_SIZE: i64 = 500; // users read per query
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+pub const CSV_IMPORT_COLUMNS: [&str; 4] = ["username", "password", "firstname", "lastname"];
+pub const CSV_EXPORT_COLUMNS: [&str; 7] = ["id", "username", "firstname", "lastname", "status", "create_ts", "update_ts"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulkFormat {
+    Csv,
+    Ndjson, // one JSON object per line
+}
+
+impl BulkFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BulkFormat::Csv => CSV_CONTENT_TYPE,
+            BulkFormat::Ndjson => NDJSON_CONTENT_TYPE,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BulkFormat::Csv => "csv",
+            BulkFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+pub fn parse_bulk_format(format: &str) -> Result<BulkFormat, String> {
+    match format {
+        "csv" => Ok(BulkFormat::Csv),
+        "ndjson" => Ok(BulkFormat::Ndjson),
+        _ => Err("format must be one of csv, ndjson".to_string()),
+    }
+}
+
+// The format of an import body, from the essence of its Content-Type.
+pub fn bulk_format_from_content_type(content_type: &str) -> Option<BulkFormat> {
+    match content_type {
+        CSV_CONTENT_TYPE => Some(BulkFormat::Csv),
+        NDJSON_CONTENT_TYPE => Some(BulkFormat::Ndjson),
+        _ => None,
+    }
+}
+
+// What an import does with a row whose username is taken.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    Skip,
+    Update, // the row's names and password replace the account's
+}
+
+pub fn parse_conflict_policy(policy: &str) -> Result<ConflictPolicy, String> {
+    match policy {
+        "skip" => Ok(ConflictPolicy::Skip),
+        "update" => Ok(ConflictPolicy::Update),
+        _ => Err("on_conflict must be one of skip, update".to_string()),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// Report what the import would do without changing anything
+    pub dry_run: Option<bool>,
+    /// One of skip (default), update
+    pub on_conflict: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// One of csv, ndjson (default)
+    pub format: Option<String>,
+    /// One of active, disabled, locked, pending
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportOptions {
+    pub dry_run: bool,
+    pub on_conflict: ConflictPolicy,
+}
+
+pub fn parse_import_query(query: &ImportQuery) -> Result<ImportOptions, String> {
+    let on_conflict = match &query.on_conflict {
+        Some(policy) => parse_conflict_policy(policy)?,
+        None => ConflictPolicy::Skip,
+    };
+
+    Ok(ImportOptions {
+        dry_run: query.dry_run.unwrap_or(false),
+        on_conflict,
+    })
+}
+
+// What happened to a row; in a dry run, what would have.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportRowStatus {
+    Created,
+    Updated,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ImportRowResult {
+    pub row: usize, // 1 based, not counting the CSV header and blank lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub status: ImportRowStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowResult>,
+    // What stopped the import early; records after the last row were not read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    usernames: HashSet<String>, // seen so far, to catch a username given twice
+}
+
+impl ImportReport {
+    pub fn new(dry_run: bool) -> ImportReport {
+        ImportReport {
+            dry_run,
+            ..Default::default()
+        }
+    }
+
+    // The number the next row gets.
+    pub fn next_row(&self) -> usize {
+        self.rows.len() + 1
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.rows.len() >= IMPORT_MAX_ROWS
+    }
+
+    // False when the username was already given by an earlier row.
+    pub fn first_occurrence(&mut self, username: &str) -> bool {
+        self.usernames.insert(username.to_string())
+    }
+
+    pub fn push(&mut self, username: Option<String>, res: Result<ImportRowStatus, String>) {
+        let (status, error) = match res {
+            Ok(status) => (status, None),
+            Err(err) => (ImportRowStatus::Failed, Some(err)),
+        };
+
+        match status {
+            ImportRowStatus::Created => self.created += 1,
+            ImportRowStatus::Updated => self.updated += 1,
+            ImportRowStatus::Skipped => self.skipped += 1,
+            ImportRowStatus::Failed => self.failed += 1,
+        }
+
+        self.rows.push(ImportRowResult {
+            row: self.next_row(),
+            username,
+            status,
+            error,
+        });
+    }
+}
+
+// Cuts an import body into records as its chunks arrive. A record is a line,
+// except that a newline inside a quoted CSV field belongs to the field; blank
+// lines are dropped.
+pub struct RecordReader {
+    format: BulkFormat,
+    buf: Vec<u8>,
+    scanned: usize, // bytes of buf already looked at
+    quoted: bool,
+}
+
+impl RecordReader {
+    pub fn new(format: BulkFormat) -> RecordReader {
+        RecordReader {
+            format,
+            buf: Vec::new(),
+            scanned: 0,
+            quoted: false,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    // The next complete record, if there is one yet; an error for a record
+    // longer than IMPORT_MAX_RECORD_LEN, complete or not.
+    pub fn next_record(&mut self) -> Option<Result<Vec<u8>, String>> {
+        loop {
+            let mut end = None;
+            for i in self.scanned..self.buf.len() {
+                match self.buf[i] {
+                    // An escaped quote toggles twice, which leaves the state as it was.
+                    b'"' if self.format == BulkFormat::Csv => self.quoted = !self.quoted,
+                    b'\n' if !self.quoted => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let end = match end {
+                Some(res) => res,
+                None => {
+                    self.scanned = self.buf.len();
+                    if self.buf.len() > IMPORT_MAX_RECORD_LEN {
+                        return Some(Err(record_too_long()));
+                    }
+
+                    return None;
+                }
+            };
+
+            let record: Vec<u8> = self.buf.drain(..=end).collect();
+            self.scanned = 0;
+            if end > IMPORT_MAX_RECORD_LEN {
+                return Some(Err(record_too_long()));
+            }
+            if let Some(record) = trim_record(&record[..end]) {
+                return Some(Ok(record.to_vec()));
+            }
+        }
+    }
+
+    // The last record, when the body does not end with a newline. Call once
+    // next_record has nothing more.
+    pub fn finish(self) -> Option<Vec<u8>> {
+        trim_record(&self.buf).map(|record| record.to_vec())
+    }
+}
+
+fn record_too_long() -> String {
+    format!("records must be at most {} bytes", IMPORT_MAX_RECORD_LEN)
+}
+
+fn trim_record(record: &[u8]) -> Option<&[u8]> {
+    let record = record.strip_suffix(b"\r").unwrap_or(record);
+    if record.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    Some(record)
+}
+
+fn csv_fields(record: &[u8]) -> Result<csv::StringRecord, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(record);
+
+    match reader.records().next() {
+        Some(Ok(res)) => Ok(res),
+        _ => Err("row is not valid CSV".to_string()),
+    }
+}
+
+// Where the import columns are in the CSV rows; other columns are ignored, so
+// an export with a password column added can be imported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvColumns {
+    pub len: usize,
+    pub username: usize,
+    pub password: usize,
+    pub firstname: usize,
+    pub lastname: usize,
+}
+
+pub fn parse_csv_header(record: &[u8]) -> Result<CsvColumns, String> {
+    let fields = csv_fields(record)?;
+    let find = |name: &str| {
+        fields
+            .iter()
+            .position(|field| field.trim() == name)
+            .ok_or_else(|| format!("the CSV header must have the columns {}", CSV_IMPORT_COLUMNS.join(", ")))
+    };
+
+    Ok(CsvColumns {
+        len: fields.len(),
+        username: find("username")?,
+        password: find("password")?,
+        firstname: find("firstname")?,
+        lastname: find("lastname")?,
+    })
+}
+
+// A user of the import, checked with the rules of user_create. A CSV record
+// needs the columns of the header.
+pub fn parse_import_record(format: BulkFormat, columns: Option<&CsvColumns>, record: &[u8]) -> Result<UserCreateRequest, (Option<String>, String)> {
+    let req = match (format, columns) {
+        (BulkFormat::Csv, Some(columns)) => {
+            let fields = csv_fields(record).map_err(|err| (None, err))?;
+            if fields.len() != columns.len {
+                return Err((None, format!("row has {} fields, the header has {}", fields.len(), columns.len)));
+            }
+
+            UserCreateRequest {
+                username: fields[columns.username].to_string(),
+                password: fields[columns.password].to_string(),
+                firstname: fields[columns.firstname].to_string(),
+                lastname: fields[columns.lastname].to_string(),
+            }
+        }
+        (BulkFormat::Csv, None) => {
+            return Err((None, "the CSV header is missing".to_string()));
+        }
+        (BulkFormat::Ndjson, _) => {
+            match serde_json::from_slice::<UserCreateRequest>(record) {
+                Ok(res) => res,
+                // The error could quote the password, so it is not passed on.
+                Err(_err) => {
+                    return Err((None, "row must be a JSON object with username, password, firstname and lastname".to_string()));
+                }
+            }
+        }
+    };
+
+    match verify_user_create_request(req.clone()) {
+        Ok(_) => Ok(req),
+        Err(err) => Err((Some(req.username), err)),
+    }
+}
+
+// The line opening an export, if its format has one.
+pub fn export_header(format: BulkFormat) -> Option<String> {
+    match format {
+        BulkFormat::Csv => Some(csv_line(&CSV_EXPORT_COLUMNS)),
+        BulkFormat::Ndjson => None,
+    }
+}
+
+// One user of an export, with its trailing newline. NDJSON has the fields of
+// user_list; neither format has passwords.
+pub fn export_record(format: BulkFormat, user: &UserGetResponse) -> String {
+    match format {
+        BulkFormat::Csv => {
+            let id = user.id.to_string();
+            let create_ts = user.create_ts.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            let update_ts = user.update_ts.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            csv_line(&[&id, &user.username, &user.firstname, &user.lastname, user.status.as_str(), &create_ts, &update_ts])
+        }
+        BulkFormat::Ndjson => {
+            format!("{}\n", serde_json::to_string(user).unwrap())
+        }
+    }
+}
+
+fn csv_line(fields: &[&str]) -> String {
+    let mut writer = csv::WriterBuilder::new().terminator(csv::Terminator::Any(b'\n')).from_writer(Vec::new());
+    writer.write_record(fields).unwrap();
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::internal::user::entity::bulk::{
+        bulk_format_from_content_type, export_header, export_record, parse_bulk_format, parse_csv_header,
+        parse_import_query, parse_import_record, BulkFormat, ConflictPolicy, CsvColumns, ImportOptions, ImportQuery,
+        ImportReport, ImportRowStatus, RecordReader, IMPORT_MAX_RECORD_LEN, IMPORT_MAX_ROWS,
+    };
+    use crate::internal::user::entity::user::{UserGetResponse, UserStatus};
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn columns() -> CsvColumns {
+        CsvColumns { len: 4, username: 0, password: 1, firstname: 2, lastname: 3 }
+    }
+
+    fn read(format: BulkFormat, chunks: &[&str]) -> Vec<String> {
+        let mut reader = RecordReader::new(format);
+        let mut records = Vec::new();
+        for chunk in chunks {
+            reader.push(chunk.as_bytes());
+            while let Some(record) = reader.next_record() {
+                records.push(record.unwrap());
+            }
+        }
+        records.extend(reader.finish());
+
+        records.into_iter().map(|record| String::from_utf8(record).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_bulk_format_test() {
+        let test_cases = vec! {
+            TestCase { input: "csv", output: Ok(BulkFormat::Csv) },
+            TestCase { input: "ndjson", output: Ok(BulkFormat::Ndjson) },
+            TestCase { input: "json", output: Err("format must be one of csv, ndjson".to_string()) },
+            TestCase { input: "CSV", output: Err("format must be one of csv, ndjson".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(parse_bulk_format(test_case.input), test_case.output);
+        }
+
+        assert_eq!(bulk_format_from_content_type("text/csv"), Some(BulkFormat::Csv));
+        assert_eq!(bulk_format_from_content_type("application/x-ndjson"), Some(BulkFormat::Ndjson));
+        assert_eq!(bulk_format_from_content_type("application/json"), None);
+        assert_eq!((BulkFormat::Csv.content_type(), BulkFormat::Ndjson.extension()), ("text/csv", "ndjson"));
+    }
+
+    #[test]
+    fn parse_import_query_test() {
+        let test_cases = vec! {
+            TestCase { input: (None, None), output: Ok(ImportOptions { dry_run: false, on_conflict: ConflictPolicy::Skip }) },
+            TestCase { input: (Some(true), Some("update")), output: Ok(ImportOptions { dry_run: true, on_conflict: ConflictPolicy::Update }) },
+            TestCase { input: (Some(false), Some("skip")), output: Ok(ImportOptions { dry_run: false, on_conflict: ConflictPolicy::Skip }) },
+            TestCase { input: (None, Some("replace")), output: Err("on_conflict must be one of skip, update".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let query = ImportQuery {
+                dry_run: test_case.input.0,
+                on_conflict: test_case.input.1.map(|policy| policy.to_string()),
+            };
+            assert_eq!(parse_import_query(&query), test_case.output, "{:?}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn record_reader_test() {
+        let test_cases = vec! {
+            TestCase { input: (BulkFormat::Csv, vec!["a,b\nc,d\n"]), output: vec!["a,b", "c,d"] },
+            // Records split across chunks, CRLF and no final newline.
+            TestCase { input: (BulkFormat::Csv, vec!["a,", "b\r", "\nc,d"]), output: vec!["a,b", "c,d"] },
+            TestCase { input: (BulkFormat::Csv, vec!["a,b\n\n  \r\nc,d\n\n"]), output: vec!["a,b", "c,d"] },
+            TestCase { input: (BulkFormat::Csv, vec!["a,\"b\n", "c\"\"\"\nd,e\n"]), output: vec!["a,\"b\nc\"\"\"", "d,e"] },
+            // A quote means nothing outside CSV.
+            TestCase { input: (BulkFormat::Ndjson, vec!["{\"a\": \"\\\"\"}\n{}", "\n"]), output: vec!["{\"a\": \"\\\"\"}", "{}"] },
+            TestCase { input: (BulkFormat::Ndjson, vec![""]), output: vec![] },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(read(test_case.input.0, &test_case.input.1), test_case.output, "{:?}", test_case.input.1);
+        }
+
+        let too_long = Some(Err(format!("records must be at most {} bytes", IMPORT_MAX_RECORD_LEN)));
+        let mut reader = RecordReader::new(BulkFormat::Ndjson);
+        reader.push("x".repeat(IMPORT_MAX_RECORD_LEN).as_bytes());
+        assert_eq!(reader.next_record(), None);
+        reader.push(b"x");
+        assert_eq!(reader.next_record(), too_long);
+
+        // Complete records are held to the same limit, whatever the chunks.
+        let mut reader = RecordReader::new(BulkFormat::Csv);
+        reader.push(format!("a\n{}\n{}\n", "x".repeat(IMPORT_MAX_RECORD_LEN), "x".repeat(IMPORT_MAX_RECORD_LEN + 1)).as_bytes());
+        assert_eq!(reader.next_record(), Some(Ok(b"a".to_vec())));
+        assert_eq!(reader.next_record().unwrap().unwrap().len(), IMPORT_MAX_RECORD_LEN);
+        assert_eq!(reader.next_record(), too_long);
+    }
+
+    #[test]
+    fn parse_csv_header_test() {
+        let error = "the CSV header must have the columns username, password, firstname, lastname".to_string();
+        let test_cases = vec! {
+            TestCase { input: "username,password,firstname,lastname", output: Ok(columns()) },
+            TestCase {
+                input: "id, lastname ,firstname,password,username,status",
+                output: Ok(CsvColumns { len: 6, username: 4, password: 3, firstname: 2, lastname: 1 }),
+            },
+            TestCase { input: "username,firstname,lastname", output: Err(error.clone()) },
+            TestCase { input: "Username,Password,Firstname,Lastname", output: Err(error.clone()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(parse_csv_header(test_case.input.as_bytes()), test_case.output, "{}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn parse_import_record_test() {
+        let test_cases = vec! {
+            TestCase { input: (BulkFormat::Csv, "JamesHolland,password,James,Holland"), output: Ok(("JamesHolland", "James", "Holland")) },
+            TestCase { input: (BulkFormat::Csv, "AnnaSmith,password,\"Anna, Maria\",\"O\"\"Neil\""), output: Ok(("AnnaSmith", "Anna, Maria", "O\"Neil")) },
+            TestCase { input: (BulkFormat::Csv, "JamesHolland,password,James"), output: Err((None, "row has 3 fields, the header has 4".to_string())) },
+            TestCase { input: (BulkFormat::Csv, "Anna,password,Anna,Smith"), output: Err((Some("Anna".to_string()), "invalid username".to_string())) },
+            TestCase { input: (BulkFormat::Csv, "AnnaSmith,password,Anna,"), output: Err((Some("AnnaSmith".to_string()), "lastname is empty".to_string())) },
+            TestCase {
+                input: (BulkFormat::Ndjson, r#"{"username": "JamesHolland", "password": "password", "firstname": "James", "lastname": "Holland", "id": 4}"#),
+                output: Ok(("JamesHolland", "James", "Holland")),
+            },
+            TestCase {
+                input: (BulkFormat::Ndjson, r#"{"username": "JamesHolland", "password": "pass", "firstname": "James", "lastname": "Holland"}"#),
+                output: Err((Some("JamesHolland".to_string()), "invalid password".to_string())),
+            },
+            TestCase {
+                input: (BulkFormat::Ndjson, r#"{"username": "JamesHolland", "password": 12345}"#),
+                output: Err((None, "row must be a JSON object with username, password, firstname and lastname".to_string())),
+            },
+        };
+
+        for test_case in test_cases {
+            let (format, record) = test_case.input;
+            let res = parse_import_record(format, Some(&columns()), record.as_bytes());
+            let res = res.as_ref().map(|req| (req.username.as_str(), req.firstname.as_str(), req.lastname.as_str())).map_err(|err| err.clone());
+            assert_eq!(res, test_case.output, "{}", record);
+        }
+
+        assert_eq!(
+            parse_import_record(BulkFormat::Csv, None, b"JamesHolland,password,James,Holland").unwrap_err(),
+            (None, "the CSV header is missing".to_string()),
+        );
+        assert_eq!(
+            parse_import_record(BulkFormat::Csv, Some(&columns()), b"James\xffHolland,password,James,Holland").unwrap_err(),
+            (None, "row is not valid CSV".to_string()),
+        );
+    }
+
+    #[test]
+    fn import_report_test() {
+        let mut report = ImportReport::new(true);
+        assert!(report.first_occurrence("JamesHolland"));
+        report.push(Some("JamesHolland".to_string()), Ok(ImportRowStatus::Created));
+        report.push(None, Err("row is not valid CSV".to_string()));
+        assert!(!report.first_occurrence("JamesHolland"));
+        report.push(Some("AnnaSmith".to_string()), Ok(ImportRowStatus::Skipped));
+        report.push(Some("MaryJones".to_string()), Ok(ImportRowStatus::Updated));
+
+        assert_eq!(report.next_row(), 5);
+        assert!(!report.is_full());
+        assert_eq!(serde_json::to_value(&report).unwrap(), json!({
+            "dry_run": true,
+            "created": 1,
+            "updated": 1,
+            "skipped": 1,
+            "failed": 1,
+            "rows": [
+                {"row": 1, "username": "JamesHolland", "status": "created"},
+                {"row": 2, "status": "failed", "error": "row is not valid CSV"},
+                {"row": 3, "username": "AnnaSmith", "status": "skipped"},
+                {"row": 4, "username": "MaryJones", "status": "updated"},
+            ],
+        }));
+
+        let mut report = ImportReport::new(false);
+        for _ in 0..IMPORT_MAX_ROWS {
+            report.push(None, Ok(ImportRowStatus::Skipped));
+        }
+        assert!(report.is_full());
+    }
+
+    #[test]
+    fn export_record_test() {
+        let user = UserGetResponse {
+            id: 4,
+            username: "JamesHolland".to_string(),
+            firstname: "James, Jr.".to_string(),
+            lastname: "Holland".to_string(),
+            status: UserStatus::Locked,
+            create_ts: Utc.timestamp_opt(1676000000, 0).unwrap(),
+            update_ts: Utc.timestamp_opt(1676000000, 500_000_000).unwrap(),
+        };
+
+        assert_eq!(export_header(BulkFormat::Csv), Some("id,username,firstname,lastname,status,create_ts,update_ts\n".to_string()));
+        assert_eq!(export_header(BulkFormat::Ndjson), None);
+        assert_eq!(
+            export_record(BulkFormat::Csv, &user),
+            "4,JamesHolland,\"James, Jr.\",Holland,locked,2023-02-10T03:33:20Z,2023-02-10T03:33:20.500Z\n",
+        );
+
+        let line = export_record(BulkFormat::Ndjson, &user);
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&line).unwrap(), serde_json::to_value(&user).unwrap());
+    }
+}
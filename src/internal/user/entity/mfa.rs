@@ -0,0 +1,136 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+use utoipa::ToSchema;
+
+// RFC 6238 defaults, which is what authenticator apps assume.
+pub const MFA_DIGITS: usize = 6;
+pub const MFA_STEP: u64 = 30; // second
+pub const MFA_SKEW: u64 = 1; // steps accepted either side of the current one
+pub const MFA_CHALLENGE_LIFE_TIME: u64 = 300; // second
+// Wrong codes in a row before the account is locked. Without a limit a
+// challenge could be replayed until a guess hits.
+pub const MFA_MAX_FAILED_ATTEMPTS: i32 = 5;
+pub const MFA_LOCK_REASON: &str = "too many wrong MFA codes";
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUPS: usize = 4;
+const RECOVERY_CODE_GROUP_LEN: usize = 4;
+// No 0/O, 1/I/L or U, so codes survive being read out or written down.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTVWXYZ23456789";
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct UserMfa {
+    pub id: i32,
+    pub username: String,
+    pub mfa_secret: Option<String>, // base32
+    pub mfa_enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MfaEnrollResponse {
+    pub secret: String, // base32, for manual entry
+    pub provisioning_uri: String, // otpauth://totp/..., to render as a QR code
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MfaConfirmRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MfaConfirmResponse {
+    pub recovery_codes: Vec<String>, // shown once; only hashes are stored
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub challenge_token: String,
+    pub expires_in: u64, // second
+}
+
+// Second login step: the challenge from /api/v1/user/auth plus either a TOTP
+// code or one of the recovery codes.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MfaVerifyRequest {
+    pub challenge_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+pub fn generate_mfa_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns Secret::Encoded"),
+    }
+}
+
+pub fn new_totp(secret: &str, issuer: &str, username: &str) -> Result<TOTP, String> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().map_err(|err| err.to_string())?;
+
+    // Skew is applied by totp_step, which needs to know which step matched.
+    TOTP::new(Algorithm::SHA1, MFA_DIGITS, 0, MFA_STEP, secret, Some(issuer.to_string()), username.to_string())
+        .map_err(|err| err.to_string())
+}
+
+// Returns the time step the code belongs to, so the caller can refuse a step
+// that was already used.
+pub fn totp_step(totp: &TOTP, code: &str, now: u64) -> Option<i64> {
+    let current = now / MFA_STEP;
+
+    (current.saturating_sub(MFA_SKEW)..=current + MFA_SKEW)
+        .find(|step| totp.check(code, step * MFA_STEP))
+        .map(|step| step as i64)
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            (0..RECOVERY_CODE_GROUPS)
+                .map(|_| {
+                    (0..RECOVERY_CODE_GROUP_LEN)
+                        .map(|_| RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+                        .collect::<String>()
+                })
+                .collect::<Vec<String>>()
+                .join("-")
+        })
+        .collect()
+}
+
+// Codes are random and long enough that a fast hash is sufficient. Case,
+// dashes and spaces are ignored so a code can be typed the way it was read.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+// verifying
+
+pub fn verify_mfa_code(code: &str) -> Result<(), String> {
+    if code.len() != MFA_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("code must be {} digits", MFA_DIGITS));
+    }
+
+    Ok(())
+}
+
+pub fn verify_mfa_verify_request(req: &MfaVerifyRequest) -> Result<(), String> {
+    if req.challenge_token.is_empty() {
+        return Err("challenge_token is empty".to_string());
+    }
+
+    match (&req.code, &req.recovery_code) {
+        (Some(code), None) => verify_mfa_code(code),
+        (None, Some(recovery_code)) if !recovery_code.trim().is_empty() => Ok(()),
+        _ => Err("exactly one of code or recovery_code must be set".to_string()),
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::internal::user::entity::mfa::{
+        MfaVerifyRequest, RECOVERY_CODE_COUNT,
+        generate_recovery_codes, hash_recovery_code, new_totp, totp_step,
+        verify_mfa_code, verify_mfa_verify_request,
+    };
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    // RFC 6238 appendix B secret ("12345678901234567890"), truncated to 6 digits.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn totp_step_test() {
+        let totp = new_totp(RFC_SECRET, "rust-clean", "JamesHolland").unwrap();
+
+        let test_cases = vec! {
+            TestCase { input: ("287082", 59), output: Some(1) },
+            TestCase { input: ("287082", 89), output: Some(1) }, // one step late
+            TestCase { input: ("287082", 30), output: Some(1) },
+            TestCase { input: ("287082", 149), output: None }, // outside the skew window
+            TestCase { input: ("000000", 59), output: None },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(totp_step(&totp, test_case.input.0, test_case.input.1), test_case.output);
+        }
+    }
+
+    #[test]
+    fn recovery_code_test() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        for code in &codes {
+            assert_eq!(code.len(), 19);
+            assert_eq!(code.split('-').count(), 4);
+        }
+
+        let code = &codes[0];
+        assert_eq!(hash_recovery_code(code), hash_recovery_code(&code.to_lowercase()));
+        assert_eq!(hash_recovery_code(code), hash_recovery_code(&code.replace('-', " ")));
+        assert_ne!(hash_recovery_code(code), hash_recovery_code(&codes[1]));
+    }
+
+    #[test]
+    fn verify_mfa_code_test() {
+        let test_cases = vec! {
+            TestCase { input: "123456", output: Ok(()) },
+            TestCase { input: "12345", output: Err("code must be 6 digits".to_string()) },
+            TestCase { input: "12345a", output: Err("code must be 6 digits".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_mfa_code(test_case.input), test_case.output);
+        }
+    }
+
+    #[test]
+    fn verify_mfa_verify_request_test() {
+        let request = |challenge_token: &str, code: Option<&str>, recovery_code: Option<&str>| MfaVerifyRequest {
+            challenge_token: challenge_token.to_string(),
+            code: code.map(String::from),
+            recovery_code: recovery_code.map(String::from),
+        };
+
+        let test_cases = vec! {
+            TestCase { input: request("token", Some("123456"), None), output: Ok(()) },
+            TestCase { input: request("token", None, Some("ABCD-EFGH-JKMN-PQRS")), output: Ok(()) },
+            TestCase { input: request("", Some("123456"), None), output: Err("challenge_token is empty".to_string()) },
+            TestCase {
+                input: request("token", Some("123456"), Some("ABCD-EFGH-JKMN-PQRS")),
+                output: Err("exactly one of code or recovery_code must be set".to_string()),
+            },
+            TestCase {
+                input: request("token", None, None),
+                output: Err("exactly one of code or recovery_code must be set".to_string()),
+            },
+            TestCase { input: request("token", Some("12"), None), output: Err("code must be 6 digits".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_mfa_verify_request(&test_case.input), test_case.output);
+        }
+    }
+}
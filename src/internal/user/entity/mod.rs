@@ -1,3 +1,19 @@
 pub mod user;
 pub mod token;
-pub mod user_test;
\ No newline at end of file
+pub mod mfa;
+pub mod passkey;
+pub mod api_key;
+pub mod session;
+pub mod profile;
+pub mod bulk;
+pub mod batch;
+pub mod user_test;
+pub mod mfa_test;
+pub mod passkey_test;
+pub mod api_key_test;
+pub mod session_test;
+pub mod profile_test;
+pub mod bulk_test;
+pub mod batch_test;
+#[cfg(test)]
+pub mod soft_authenticator;
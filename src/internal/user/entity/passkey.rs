@@ -0,0 +1,434 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value as Cbor;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::ecdsa::signature::Verifier;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+// WebAuthn Level 2 with attestation "none" and ES256 keys, which every
+// platform and roaming authenticator supports.
+pub const PASSKEY_CHALLENGE_LIFE_TIME: u64 = 300; // second
+pub const CEREMONY_REGISTER: &str = "register";
+pub const CEREMONY_LOGIN: &str = "login";
+pub const PASSKEY_NAME_MAX_LEN: usize = 64;
+const CHALLENGE_LEN: usize = 32; // byte
+const CREDENTIAL_TYPE: &str = "public-key";
+const CLIENT_DATA_CREATE: &str = "webauthn.create";
+const CLIENT_DATA_GET: &str = "webauthn.get";
+const COSE_ALG_ES256: i64 = -7;
+
+// Authenticator data flags
+const FLAG_UP: u8 = 0x01; // user present
+const FLAG_UV: u8 = 0x04; // user verified
+const FLAG_AT: u8 = 0x40; // attested credential data included
+
+#[derive(Clone)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origin: String,
+}
+
+pub fn new_webauthn_config(rp_id: String, rp_name: String, origin: String) -> WebAuthnConfig {
+    WebAuthnConfig {
+        rp_id,
+        rp_name,
+        origin,
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Passkey {
+    pub id: i32,
+    pub user_id: i32,
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>, // uncompressed SEC1 P-256 point
+    pub sign_count: i64,
+    pub name: String,
+}
+
+// A credential that passed registration, ready to be stored.
+#[derive(Debug, Clone)]
+pub struct PasskeyCreate {
+    pub user_id: i32,
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub name: String,
+}
+
+// The options and credentials below follow the JSON shapes of the WebAuthn
+// spec (PublicKeyCredentialCreationOptionsJSON and friends), so browsers can
+// pass them to navigator.credentials as is once the binary fields are decoded.
+// Binary fields are base64url without padding.
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    pub id: String, // user handle
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CredentialParameter {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRegisterOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<CredentialParameter>,
+    pub timeout: u64, // millisecond
+    pub attestation: String,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, ToSchema)]
+pub struct PasskeyLoginBeginRequest {
+    pub username: Option<String>, // omit for discoverable credentials
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyLoginOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64, // millisecond
+    pub user_verification: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRegisterRequest {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub response: AttestationResponse,
+    pub name: Option<String>, // label shown in the passkey list, defaults to "Passkey"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyLoginRequest {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PasskeyResponse {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+    cross_origin: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    pub sign_count: u32,
+    pub credential: Option<AttestedCredential>,
+}
+
+impl AuthenticatorData {
+    pub fn user_verified(&self) -> bool {
+        self.flags & FLAG_UV != 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>, // uncompressed SEC1 P-256 point
+}
+
+pub fn generate_challenge() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; CHALLENGE_LEN]>())
+}
+
+pub fn encode(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+// Browsers differ on padding, so both forms are accepted.
+pub fn decode(data: &str, field: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD.decode(data.trim_end_matches('='))
+        .map_err(|_err| format!("{} is not valid base64url", field))
+}
+
+// The user handle is the account id; it identifies the account to the
+// authenticator and carries no personal data.
+pub fn user_handle(user_id: i32) -> String {
+    encode(&user_id.to_be_bytes())
+}
+
+pub fn credential_descriptor(passkey: &Passkey) -> CredentialDescriptor {
+    CredentialDescriptor {
+        credential_type: CREDENTIAL_TYPE.to_string(),
+        id: encode(&passkey.credential_id),
+    }
+}
+
+pub fn register_options(cfg: &WebAuthnConfig, challenge: String, user_id: i32, username: &str, display_name: &str, existing: &[Passkey]) -> PasskeyRegisterOptions {
+    PasskeyRegisterOptions {
+        challenge,
+        rp: RelyingParty {
+            id: cfg.rp_id.clone(),
+            name: cfg.rp_name.clone(),
+        },
+        user: PasskeyUser {
+            id: user_handle(user_id),
+            name: username.to_string(),
+            display_name: display_name.to_string(),
+        },
+        pub_key_cred_params: vec![CredentialParameter {
+            credential_type: CREDENTIAL_TYPE.to_string(),
+            alg: COSE_ALG_ES256,
+        }],
+        timeout: PASSKEY_CHALLENGE_LIFE_TIME * 1000,
+        attestation: "none".to_string(),
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "preferred".to_string(),
+            user_verification: "preferred".to_string(),
+        },
+        exclude_credentials: existing.iter().map(credential_descriptor).collect(),
+    }
+}
+
+pub fn login_options(cfg: &WebAuthnConfig, challenge: String, allowed: &[Passkey]) -> PasskeyLoginOptions {
+    PasskeyLoginOptions {
+        challenge,
+        rp_id: cfg.rp_id.clone(),
+        timeout: PASSKEY_CHALLENGE_LIFE_TIME * 1000,
+        user_verification: "preferred".to_string(),
+        allow_credentials: allowed.iter().map(credential_descriptor).collect(),
+    }
+}
+
+// Checks the ceremony type and origin and returns the challenge, which the
+// caller must then redeem against the stored ones.
+pub fn verify_client_data(cfg: &WebAuthnConfig, client_data_json: &[u8], ceremony: &str) -> Result<String, String> {
+    let client_data: CollectedClientData = serde_json::from_slice(client_data_json)
+        .map_err(|_err| "clientDataJSON is malformed".to_string())?;
+
+    let expected_type = if ceremony == CEREMONY_REGISTER { CLIENT_DATA_CREATE } else { CLIENT_DATA_GET };
+    if client_data.ceremony_type != expected_type {
+        return Err(format!("clientDataJSON type must be {}", expected_type));
+    }
+
+    if client_data.origin != cfg.origin || client_data.cross_origin == Some(true) {
+        return Err("clientDataJSON origin does not match".to_string());
+    }
+
+    Ok(client_data.challenge)
+}
+
+pub fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, String> {
+    if data.len() < 37 {
+        return Err("authenticator data is too short".to_string());
+    }
+
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let mut credential = None;
+    if flags & FLAG_AT != 0 {
+        // aaguid (16) | credential id length (2) | credential id | COSE key
+        let rest = &data[37..];
+        if rest.len() < 18 {
+            return Err("attested credential data is too short".to_string());
+        }
+
+        let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        let rest = &rest[18..];
+        if rest.len() < id_len {
+            return Err("attested credential data is too short".to_string());
+        }
+
+        let (credential_id, mut key) = rest.split_at(id_len);
+        let cose: Cbor = ciborium::from_reader(&mut key).map_err(|_err| "credential public key is malformed".to_string())?;
+
+        credential = Some(AttestedCredential {
+            credential_id: credential_id.to_vec(),
+            public_key: cose_to_sec1(&cose)?,
+        });
+    }
+
+    Ok(AuthenticatorData {
+        rp_id_hash: data[..32].to_vec(),
+        flags,
+        sign_count,
+        credential,
+    })
+}
+
+// Only EC2 / P-256 / ES256 keys are accepted, matching pubKeyCredParams.
+fn cose_to_sec1(cose: &Cbor) -> Result<Vec<u8>, String> {
+    let map = cose.as_map().ok_or("credential public key is malformed")?;
+    let get = |label: i64| map.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(label as i128))
+        .map(|(_, v)| v);
+    let int = |label: i64| get(label).and_then(|v| v.as_integer()).map(i128::from);
+
+    if int(1) != Some(2) || int(3) != Some(COSE_ALG_ES256 as i128) || int(-1) != Some(1) {
+        return Err("credential public key must be an ES256 P-256 key".to_string());
+    }
+
+    match (get(-2).and_then(|v| v.as_bytes()), get(-3).and_then(|v| v.as_bytes())) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => {
+            let point = [&[0x04], x.as_slice(), y.as_slice()].concat();
+            VerifyingKey::from_sec1_bytes(&point).map_err(|_err| "credential public key is not on the curve".to_string())?;
+            Ok(point)
+        }
+        _ => {
+            Err("credential public key is malformed".to_string())
+        }
+    }
+}
+
+// Shared by both ceremonies: the data must be for this relying party and
+// the user must have interacted with the authenticator.
+pub fn verify_authenticator_data(cfg: &WebAuthnConfig, auth_data: &AuthenticatorData) -> Result<(), String> {
+    if auth_data.rp_id_hash[..] != Sha256::digest(cfg.rp_id.as_bytes())[..] {
+        return Err("authenticator data is for another relying party".to_string());
+    }
+
+    if auth_data.flags & FLAG_UP == 0 {
+        return Err("user presence is required".to_string());
+    }
+
+    Ok(())
+}
+
+// Returns the new credential from an attestation object with format "none".
+pub fn verify_attestation(cfg: &WebAuthnConfig, attestation_object: &[u8]) -> Result<(AuthenticatorData, AttestedCredential), String> {
+    let object: Cbor = ciborium::from_reader(attestation_object).map_err(|_err| "attestationObject is malformed".to_string())?;
+    let map = object.as_map().ok_or("attestationObject is malformed")?;
+    let get = |key: &str| map.iter().find(|(k, _)| k.as_text() == Some(key)).map(|(_, v)| v);
+
+    if get("fmt").and_then(|v| v.as_text()) != Some("none") {
+        return Err("attestation format must be none".to_string());
+    }
+
+    let data = get("authData").and_then(|v| v.as_bytes()).ok_or("attestationObject is malformed")?;
+    let auth_data = parse_authenticator_data(data)?;
+    verify_authenticator_data(cfg, &auth_data)?;
+
+    match auth_data.credential.clone() {
+        Some(credential) => {
+            Ok((auth_data, credential))
+        }
+        None => {
+            Err("attestationObject has no credential".to_string())
+        }
+    }
+}
+
+// The signature covers authenticatorData || SHA-256(clientDataJSON).
+pub fn verify_assertion_signature(public_key: &[u8], authenticator_data: &[u8], client_data_json: &[u8], signature: &[u8]) -> Result<(), String> {
+    let key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_err| "stored public key is invalid".to_string())?;
+    let signature = Signature::from_der(signature).map_err(|_err| "signature is malformed".to_string())?;
+
+    let message = [authenticator_data, &Sha256::digest(client_data_json)[..]].concat();
+    key.verify(&message, &signature).map_err(|_err| "signature is invalid".to_string())
+}
+
+// Authenticators that don't keep a counter always report 0. Otherwise the
+// counter must grow; a step back suggests a cloned authenticator.
+pub fn sign_count_advanced(stored: i64, received: u32) -> bool {
+    (stored == 0 && received == 0) || (received as i64) > stored
+}
+
+// verifying
+
+pub fn verify_passkey_register_request(req: &PasskeyRegisterRequest) -> Result<(), String> {
+    if req.credential_type != CREDENTIAL_TYPE {
+        return Err(format!("type must be {}", CREDENTIAL_TYPE));
+    }
+
+    if req.raw_id.is_empty() || req.response.client_data_json.is_empty() || req.response.attestation_object.is_empty() {
+        return Err("rawId, clientDataJSON and attestationObject must be set".to_string());
+    }
+
+    if let Some(name) = &req.name {
+        if name.trim().is_empty() || name.chars().count() > PASSKEY_NAME_MAX_LEN {
+            return Err(format!("name must be 1 to {} characters", PASSKEY_NAME_MAX_LEN));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn verify_passkey_login_request(req: &PasskeyLoginRequest) -> Result<(), String> {
+    if req.credential_type != CREDENTIAL_TYPE {
+        return Err(format!("type must be {}", CREDENTIAL_TYPE));
+    }
+
+    if req.raw_id.is_empty() || req.response.client_data_json.is_empty()
+        || req.response.authenticator_data.is_empty() || req.response.signature.is_empty() {
+        return Err("rawId, clientDataJSON, authenticatorData and signature must be set".to_string());
+    }
+
+    Ok(())
+}
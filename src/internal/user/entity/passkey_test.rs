@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::internal::user::entity::passkey::{
+        decode, new_webauthn_config, parse_authenticator_data, sign_count_advanced,
+        verify_assertion_signature, verify_attestation, verify_client_data, WebAuthnConfig,
+        CEREMONY_LOGIN, CEREMONY_REGISTER,
+    };
+    use crate::internal::user::entity::soft_authenticator::new_soft_authenticator;
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn config() -> WebAuthnConfig {
+        new_webauthn_config("example.com".to_string(), "rust-clean".to_string(), "https://login.example.com".to_string())
+    }
+
+    #[test]
+    fn verify_client_data_test() {
+        let authenticator = new_soft_authenticator("example.com", "https://login.example.com");
+        let mut other_origin = new_soft_authenticator("example.com", "https://evil.example.net");
+        other_origin.credential_id = authenticator.credential_id.clone();
+
+        let test_cases = vec! {
+            TestCase {
+                input: (authenticator.client_data("webauthn.create", "abc"), CEREMONY_REGISTER),
+                output: Ok("abc".to_string()),
+            },
+            TestCase {
+                input: (authenticator.client_data("webauthn.get", "abc"), CEREMONY_LOGIN),
+                output: Ok("abc".to_string()),
+            },
+            TestCase {
+                input: (authenticator.client_data("webauthn.get", "abc"), CEREMONY_REGISTER),
+                output: Err("clientDataJSON type must be webauthn.create".to_string()),
+            },
+            TestCase {
+                input: (other_origin.client_data("webauthn.get", "abc"), CEREMONY_LOGIN),
+                output: Err("clientDataJSON origin does not match".to_string()),
+            },
+            TestCase {
+                input: (b"{\"type\": 1}".to_vec(), CEREMONY_LOGIN),
+                output: Err("clientDataJSON is malformed".to_string()),
+            },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_client_data(&config(), &test_case.input.0, test_case.input.1), test_case.output);
+        }
+    }
+
+    #[test]
+    fn verify_attestation_test() {
+        let authenticator = new_soft_authenticator("example.com", "https://login.example.com");
+
+        let (auth_data, credential) = verify_attestation(&config(), &authenticator.attestation_object()).unwrap();
+        assert_eq!(credential.credential_id, authenticator.credential_id);
+        assert_eq!(credential.public_key, authenticator.key.verifying_key().to_encoded_point(false).as_bytes());
+        assert_eq!(auth_data.sign_count, 0);
+        assert!(auth_data.user_verified());
+
+        let other_rp = new_soft_authenticator("evil.com", "https://login.example.com");
+        let mut not_present = new_soft_authenticator("example.com", "https://login.example.com");
+        not_present.flags = 0;
+
+        let test_cases = vec! {
+            TestCase { input: other_rp.attestation_object(), output: "authenticator data is for another relying party" },
+            TestCase { input: not_present.attestation_object(), output: "user presence is required" },
+            TestCase { input: vec![0xa0], output: "attestation format must be none" },
+            TestCase { input: b"not cbor".to_vec(), output: "attestationObject is malformed" },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(verify_attestation(&config(), &test_case.input).unwrap_err(), test_case.output);
+        }
+
+        assert_eq!(parse_authenticator_data(&[0u8; 36]).unwrap_err(), "authenticator data is too short");
+        let mut truncated = authenticator.authenticator_data(true);
+        truncated.truncate(60);
+        assert!(parse_authenticator_data(&truncated).is_err());
+    }
+
+    #[test]
+    fn verify_assertion_signature_test() {
+        let mut authenticator = new_soft_authenticator("example.com", "https://login.example.com");
+        let public_key = authenticator.key.verifying_key().to_encoded_point(false).as_bytes().to_vec();
+        let assertion = authenticator.login("abc", None);
+
+        let field = |name: &str| decode(assertion["response"][name].as_str().unwrap(), name).unwrap();
+        let (authenticator_data, client_data, signature) = (field("authenticatorData"), field("clientDataJSON"), field("signature"));
+
+        assert_eq!(verify_assertion_signature(&public_key, &authenticator_data, &client_data, &signature), Ok(()));
+
+        let other = new_soft_authenticator("example.com", "https://login.example.com");
+        let other_key = other.key.verifying_key().to_encoded_point(false).as_bytes().to_vec();
+        let mut tampered = authenticator_data.clone();
+        tampered[36] ^= 1;
+
+        let test_cases = vec! {
+            TestCase { input: (other_key, authenticator_data.clone(), signature.clone()), output: "signature is invalid" },
+            TestCase { input: (public_key.clone(), tampered, signature.clone()), output: "signature is invalid" },
+            TestCase { input: (public_key.clone(), authenticator_data.clone(), vec![1, 2, 3]), output: "signature is malformed" },
+            TestCase { input: (vec![4, 1], authenticator_data, signature), output: "stored public key is invalid" },
+        };
+
+        for test_case in test_cases {
+            let (key, data, signature) = test_case.input;
+            assert_eq!(verify_assertion_signature(&key, &data, &client_data, &signature).unwrap_err(), test_case.output);
+        }
+    }
+
+    #[test]
+    fn sign_count_advanced_test() {
+        let test_cases = vec! {
+            TestCase { input: (0, 0), output: true },
+            TestCase { input: (0, 1), output: true },
+            TestCase { input: (5, 6), output: true },
+            TestCase { input: (5, 5), output: false },
+            TestCase { input: (5, 0), output: false },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(sign_count_advanced(test_case.input.0, test_case.input.1), test_case.output, "{:?}", test_case.input);
+        }
+    }
+}
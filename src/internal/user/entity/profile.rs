@@ -0,0 +1,365 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use jsonschema::Validator;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use sqlx::types::Json;
+use utoipa::ToSchema;
+
+pub const PHONE_MAX_DIGITS: usize = 15; // E.164
+pub const PHONE_MIN_DIGITS: usize = 7;
+pub const LOCALE_MAX_LEN: usize = 35; // RFC 5646 minimum buffer size
+
+// Used when profile.attributes_schema is not set: no attribute is allowed
+// until the deployment says which ones it wants.
+pub const DEFAULT_ATTRIBUTES_SCHEMA: &str = r#"{"type": "object", "additionalProperties": false}"#;
+
+// Avatars are stored only as square PNG thumbnails of these sizes; the upload
+// itself is thrown away, with whatever metadata it carried.
+pub const AVATAR_SIZES: [u32; 3] = [64, 256, 512];
+pub const AVATAR_FIELD: &str = "avatar"; // multipart field name
+pub const AVATAR_CONTENT_TYPE: &str = "image/png";
+pub const AVATAR_UPLOAD_TYPES: [&str; 3] = ["image/png", "image/jpeg", "image/webp"]; // accepted uploads
+// Decoding limits, so a small file can't expand into a huge bitmap.
+pub const AVATAR_MAX_DIMENSION: u32 = 4096; // pixel
+pub const AVATAR_MAX_ALLOC: u64 = 128 * 1024 * 1024; // byte
+
+#[derive(Clone)]
+pub struct ProfileConfig {
+    pub attributes_schema: Arc<Validator>,
+    pub avatar_max_size: usize, // byte, of the uploaded file
+}
+
+pub fn new_profile_config(attributes_schema: &str, avatar_max_size: usize) -> Result<ProfileConfig, String> {
+    let schema: Value = match serde_json::from_str(attributes_schema) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            return Err(format!("attributes schema is not valid JSON: {}", err));
+        }
+    };
+
+    match jsonschema::validator_for(&schema) {
+        Ok(res) => {
+            Ok(ProfileConfig {
+                attributes_schema: Arc::new(res),
+                avatar_max_size,
+            })
+        }
+        Err(err) => {
+            Err(format!("attributes schema is not a valid JSON Schema: {}", err))
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct UserProfile {
+    pub user_id: i32,
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub attributes: Json<Map<String, Value>>,
+    pub avatar_key: Option<String>, // blob key prefix of the thumbnails
+    pub update_ts: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileUpdate {
+    pub user_id: i32,
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub attributes: Map<String, Value>,
+}
+
+// Fields left out of the request are kept; null clears a field, or removes
+// an attribute.
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileUpdateRequest {
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, nullable, example = "+4915112345678")]
+    pub phone: Option<Option<String>>, // E.164
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, nullable, example = "de-DE")]
+    pub locale: Option<Option<String>>, // BCP 47
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, nullable, example = "Europe/Berlin")]
+    pub timezone: Option<Option<String>>, // IANA
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Option<Map<String, Value>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ProfileResponse {
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    #[schema(value_type = Object)]
+    pub attributes: Map<String, Value>,
+    pub avatars: Vec<AvatarResponse>, // empty without an avatar
+    pub update_ts: Option<DateTime<Utc>>, // none until the profile is first changed
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AvatarResponse {
+    pub size: u32,
+    pub url: String,
+}
+
+// The multipart form of an avatar upload, for the API documentation; the
+// controller reads the parts itself.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct AvatarUpload {
+    #[schema(value_type = String, format = Binary)]
+    pub avatar: Vec<u8>, // PNG, JPEG or WebP
+}
+
+// Tells a field set to null (Some(None)) apart from one left out (None).
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub fn empty_profile(user_id: i32) -> ProfileUpdate {
+    ProfileUpdate {
+        user_id,
+        phone: None,
+        locale: None,
+        timezone: None,
+        attributes: Map::new(),
+    }
+}
+
+pub fn to_profile_update(profile: &UserProfile) -> ProfileUpdate {
+    ProfileUpdate {
+        user_id: profile.user_id,
+        phone: profile.phone.clone(),
+        locale: profile.locale.clone(),
+        timezone: profile.timezone.clone(),
+        attributes: profile.attributes.0.clone(),
+    }
+}
+
+pub fn to_profile_response(profile: Option<UserProfile>) -> ProfileResponse {
+    match profile {
+        Some(profile) => {
+            ProfileResponse {
+                avatars: avatar_urls(profile.user_id, profile.avatar_key.as_deref()),
+                phone: profile.phone,
+                locale: profile.locale,
+                timezone: profile.timezone,
+                attributes: profile.attributes.0,
+                update_ts: Some(profile.update_ts),
+            }
+        }
+        None => {
+            ProfileResponse {
+                phone: None,
+                locale: None,
+                timezone: None,
+                attributes: Map::new(),
+                avatars: Vec::new(),
+                update_ts: None,
+            }
+        }
+    }
+}
+
+// The thumbnails' URLs; the version in the query string changes with every
+// upload, so a replaced avatar gets new URLs.
+pub fn avatar_urls(user_id: i32, avatar_key: Option<&str>) -> Vec<AvatarResponse> {
+    let version = match avatar_key.and_then(|key| key.rsplit('/').next()) {
+        Some(res) => {
+            res
+        }
+        None => {
+            return Vec::new();
+        }
+    };
+
+    AVATAR_SIZES.iter().map(|size| {
+        AvatarResponse {
+            size: *size,
+            url: format!("/api/v1/user/{}/avatar/{}?v={}", user_id, size, version),
+        }
+    }).collect()
+}
+
+// avatars/{user_id}/{version}; each thumbnail is stored below it as {size}.png.
+pub fn avatar_key(user_id: i32, version: &str) -> String {
+    format!("{}/{}", avatar_user_prefix(user_id), version)
+}
+
+pub fn avatar_user_prefix(user_id: i32) -> String {
+    format!("avatars/{}", user_id)
+}
+
+pub fn avatar_blob_key(avatar_key: &str, size: u32) -> String {
+    format!("{}/{}.png", avatar_key, size)
+}
+
+// verifying
+
+pub fn verify_profile_update_request(req: &ProfileUpdateRequest) -> Result<(), String> {
+    if req.phone.is_none() && req.locale.is_none() && req.timezone.is_none() && req.attributes.is_none() {
+        return Err("phone, locale, timezone or attributes is required".to_string());
+    }
+
+    if let Some(Some(phone)) = &req.phone {
+        verify_phone(phone)?;
+    }
+
+    if let Some(Some(locale)) = &req.locale {
+        verify_locale(locale)?;
+    }
+
+    if let Some(Some(timezone)) = &req.timezone {
+        if timezone.parse::<Tz>().is_err() {
+            return Err("timezone must be an IANA time zone such as Europe/Berlin".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_phone(phone: &str) -> Result<(), String> {
+    let digits = phone.strip_prefix('+').unwrap_or_default();
+    let valid = digits.len() >= PHONE_MIN_DIGITS
+        && digits.len() <= PHONE_MAX_DIGITS
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && !digits.starts_with('0');
+
+    if !valid {
+        return Err("phone must be in E.164 format, e.g. +4915112345678".to_string());
+    }
+
+    Ok(())
+}
+
+// A language subtag followed by script, region or variant subtags, e.g. en,
+// de-DE or zh-Hant-TW. Not checked against the IANA registry.
+fn verify_locale(locale: &str) -> Result<(), String> {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+
+    let valid = locale.len() <= LOCALE_MAX_LEN
+        && (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|subtag| (2..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric()));
+
+    if !valid {
+        return Err("locale must be a BCP 47 language tag, e.g. de-DE".to_string());
+    }
+
+    Ok(())
+}
+
+// Applies the request to the stored profile; attributes are merged key by key.
+pub fn apply_profile_update(mut profile: ProfileUpdate, req: ProfileUpdateRequest) -> ProfileUpdate {
+    if let Some(phone) = req.phone {
+        profile.phone = phone;
+    }
+    if let Some(locale) = req.locale {
+        profile.locale = locale;
+    }
+    if let Some(timezone) = req.timezone {
+        profile.timezone = timezone;
+    }
+    if let Some(attributes) = req.attributes {
+        for (key, value) in attributes {
+            match value {
+                Value::Null => {
+                    profile.attributes.remove(&key);
+                }
+                value => {
+                    profile.attributes.insert(key, value);
+                }
+            }
+        }
+    }
+
+    profile
+}
+
+// Checks the merged attributes against the deployment's schema, reporting
+// the first violation with its JSON pointer.
+pub fn verify_attributes(schema: &Validator, attributes: &Map<String, Value>) -> Result<(), String> {
+    let instance = Value::Object(attributes.clone());
+    let violation = schema.iter_errors(&instance).next().map(|err| format!("attributes{}: {}", err.instance_path, err));
+
+    match violation {
+        Some(err) => {
+            Err(err)
+        }
+        None => {
+            Ok(())
+        }
+    }
+}
+
+// The format of an accepted upload, sniffed from its content.
+pub fn avatar_format(data: &[u8]) -> Option<ImageFormat> {
+    match image::guess_format(data) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => {
+            Some(format)
+        }
+        _ => {
+            None
+        }
+    }
+}
+
+// Decodes the upload, applies its EXIF orientation and renders a square,
+// center-cropped PNG for each of AVATAR_SIZES.
+pub fn render_avatar(data: &[u8], format: ImageFormat) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(AVATAR_MAX_DIMENSION);
+    limits.max_image_height = Some(AVATAR_MAX_DIMENSION);
+    limits.max_alloc = Some(AVATAR_MAX_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+
+    let mut decoder = match reader.into_decoder() {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            return Err(format!("avatar can't be decoded: {}", err));
+        }
+    };
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let mut image = match DynamicImage::from_decoder(decoder) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            return Err(format!("avatar can't be decoded: {}", err));
+        }
+    };
+    image.apply_orientation(orientation);
+
+    let mut thumbnails = Vec::new();
+    for size in AVATAR_SIZES {
+        let thumbnail = DynamicImage::ImageRgba8(image.resize_to_fill(size, size, FilterType::Lanczos3).to_rgba8());
+        let mut png = Vec::new();
+        if let Err(err) = thumbnail.write_to(&mut Cursor::new(&mut png), ImageFormat::Png) {
+            return Err(format!("can't encode avatar: {}", err));
+        }
+        thumbnails.push((size, png));
+    }
+
+    Ok(thumbnails)
+}
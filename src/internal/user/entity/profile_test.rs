@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use image::{DynamicImage, GenericImageView, ImageFormat, RgbImage};
+    use serde_json::{json, Map, Value};
+
+    use crate::internal::user::entity::profile::{
+        apply_profile_update, avatar_format, avatar_urls, empty_profile, new_profile_config, render_avatar,
+        verify_attributes, verify_profile_update_request, ProfileUpdateRequest, AVATAR_SIZES, DEFAULT_ATTRIBUTES_SCHEMA,
+    };
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn request(body: Value) -> ProfileUpdateRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    #[test]
+    fn verify_profile_update_request_test() {
+        let test_cases = vec! {
+            TestCase { input: json!({"phone": "+4915112345678"}), output: Ok(()) },
+            TestCase { input: json!({"phone": null, "locale": null, "timezone": null}), output: Ok(()) },
+            TestCase { input: json!({"locale": "zh-Hant-TW", "timezone": "America/New_York"}), output: Ok(()) },
+            TestCase { input: json!({"attributes": {}}), output: Ok(()) },
+            TestCase { input: json!({}), output: Err("phone, locale, timezone or attributes is required".to_string()) },
+            TestCase { input: json!({"phone": "015112345678"}), output: Err("phone must be in E.164 format, e.g. +4915112345678".to_string()) },
+            TestCase { input: json!({"phone": "+0123456789"}), output: Err("phone must be in E.164 format, e.g. +4915112345678".to_string()) },
+            TestCase { input: json!({"phone": "+49 151 1234"}), output: Err("phone must be in E.164 format, e.g. +4915112345678".to_string()) },
+            TestCase { input: json!({"phone": "+1234567890123456"}), output: Err("phone must be in E.164 format, e.g. +4915112345678".to_string()) },
+            TestCase { input: json!({"locale": "german"}), output: Err("locale must be a BCP 47 language tag, e.g. de-DE".to_string()) },
+            TestCase { input: json!({"locale": "de_DE"}), output: Err("locale must be a BCP 47 language tag, e.g. de-DE".to_string()) },
+            TestCase { input: json!({"timezone": "Mars/Olympus"}), output: Err("timezone must be an IANA time zone such as Europe/Berlin".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let res = verify_profile_update_request(&request(test_case.input.clone()));
+            assert_eq!(res, test_case.output, "{}", test_case.input);
+        }
+    }
+
+    #[test]
+    fn profile_update_request_test() {
+        let res = request(json!({"phone": null, "locale": "de"}));
+        assert_eq!(res.phone, Some(None));
+        assert_eq!(res.locale, Some(Some("de".to_string())));
+        assert_eq!(res.timezone, None);
+
+        assert!(serde_json::from_value::<ProfileUpdateRequest>(json!({"avatar_key": "x"})).is_err());
+    }
+
+    #[test]
+    fn apply_profile_update_test() {
+        let mut before = empty_profile(1);
+        before.phone = Some("+4915112345678".to_string());
+        before.locale = Some("de-DE".to_string());
+        before.attributes = json!({"department": "R&D", "floor": 3}).as_object().unwrap().clone();
+
+        let res = apply_profile_update(before.clone(), request(json!({
+            "phone": null,
+            "timezone": "US/Pacific",
+            "attributes": {"floor": null, "badge": "B-17"},
+        })));
+
+        assert_eq!(res.phone, None);
+        assert_eq!(res.locale, Some("de-DE".to_string()));
+        assert_eq!(res.timezone, Some("US/Pacific".to_string()));
+        assert_eq!(Value::Object(res.attributes), json!({"department": "R&D", "badge": "B-17"}));
+        assert_eq!(apply_profile_update(before.clone(), request(json!({"attributes": {}}))), before);
+    }
+
+    #[test]
+    fn verify_attributes_test() {
+        let schema = r#"{
+            "type": "object",
+            "properties": {
+                "department": {"type": "string", "maxLength": 8},
+                "floor": {"type": "integer"}
+            },
+            "additionalProperties": false
+        }"#;
+        let cfg = new_profile_config(schema, 1024).unwrap();
+
+        let test_cases = vec! {
+            TestCase { input: json!({}), output: Ok(()) },
+            TestCase { input: json!({"department": "R&D", "floor": 3}), output: Ok(()) },
+            TestCase { input: json!({"floor": "3"}), output: Err("attributes/floor: \"3\" is not of type \"integer\"".to_string()) },
+            TestCase { input: json!({"department": "Research"}), output: Ok(()) },
+            TestCase { input: json!({"department": "Marketing"}), output: Err("attributes/department: \"Marketing\" is longer than 8 characters".to_string()) },
+            TestCase { input: json!({"badge": "B-17"}), output: Err("attributes: Additional properties are not allowed ('badge' was unexpected)".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let attributes: Map<String, Value> = test_case.input.as_object().unwrap().clone();
+            let res = verify_attributes(&cfg.attributes_schema, &attributes);
+            assert_eq!(res, test_case.output, "{}", test_case.input);
+        }
+
+        let default = new_profile_config(DEFAULT_ATTRIBUTES_SCHEMA, 1024).unwrap();
+        assert!(verify_attributes(&default.attributes_schema, &Map::new()).is_ok());
+        assert!(verify_attributes(&default.attributes_schema, json!({"a": 1}).as_object().unwrap()).is_err());
+
+        assert!(new_profile_config("{", 1024).err().unwrap().starts_with("attributes schema is not valid JSON"));
+        assert!(new_profile_config(r#"{"type": 5}"#, 1024).err().unwrap().starts_with("attributes schema is not a valid JSON Schema"));
+    }
+
+    #[test]
+    fn avatar_format_test() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+
+        let test_cases = vec! {
+            TestCase { input: encode(image.clone(), ImageFormat::Png), output: Some(ImageFormat::Png) },
+            TestCase { input: encode(image.clone(), ImageFormat::Jpeg), output: Some(ImageFormat::Jpeg) },
+            TestCase { input: encode(image.clone(), ImageFormat::WebP), output: Some(ImageFormat::WebP) },
+            TestCase { input: b"GIF89a\x01\x00\x01\x00".to_vec(), output: None },
+            TestCase { input: b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>".to_vec(), output: None },
+            TestCase { input: Vec::new(), output: None },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(avatar_format(&test_case.input), test_case.output);
+        }
+    }
+
+    #[test]
+    fn render_avatar_test() {
+        // Wide image, red on the left half and blue on the right: the
+        // thumbnails keep the center and are square.
+        let image = RgbImage::from_fn(600, 200, |x, _y| {
+            if x < 300 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+        });
+        let data = encode(DynamicImage::ImageRgb8(image), ImageFormat::Png);
+
+        let res = render_avatar(&data, ImageFormat::Png).unwrap();
+        assert_eq!(res.iter().map(|(size, _)| *size).collect::<Vec<u32>>(), AVATAR_SIZES.to_vec());
+        for (size, png) in res {
+            let thumbnail = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+            assert_eq!(thumbnail.dimensions(), (size, size));
+            assert_eq!(thumbnail.get_pixel(2, size / 2).0, [255, 0, 0, 255]);
+            assert_eq!(thumbnail.get_pixel(size - 3, size / 2).0, [0, 0, 255, 255]);
+        }
+
+        let truncated = &data[..data.len() / 2];
+        assert!(render_avatar(truncated, ImageFormat::Png).unwrap_err().starts_with("avatar can't be decoded"));
+
+        let huge = encode(DynamicImage::ImageRgb8(RgbImage::new(5000, 1)), ImageFormat::Png);
+        assert!(render_avatar(&huge, ImageFormat::Png).is_err());
+    }
+
+    #[test]
+    fn avatar_urls_test() {
+        assert!(avatar_urls(7, None).is_empty());
+
+        let res = avatar_urls(7, Some("avatars/7/abc"));
+        assert_eq!(res.len(), AVATAR_SIZES.len());
+        assert_eq!(res[0].url, format!("/api/v1/user/7/avatar/{}?v=abc", AVATAR_SIZES[0]));
+    }
+}
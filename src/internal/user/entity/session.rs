@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub refresh_token_hash: String,
+    pub create_ts: DateTime<Utc>,
+    pub last_seen_ts: DateTime<Utc>,
+    pub expire_ts: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionCreate {
+    pub user_id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub refresh_token_hash: String,
+    pub expire_ts: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub create_ts: DateTime<Utc>,
+    pub last_seen_ts: DateTime<Utc>,
+    pub expire_ts: DateTime<Utc>,
+    pub current: bool, // the session of the token making the request
+}
+
+pub fn to_session_response(session: Session, current: Option<i32>) -> SessionResponse {
+    SessionResponse {
+        current: current == Some(session.id),
+        id: session.id,
+        user_agent: session.user_agent,
+        ip: session.ip,
+        create_ts: session.create_ts,
+        last_seen_ts: session.last_seen_ts,
+        expire_ts: session.expire_ts,
+    }
+}
+
+pub fn hash_refresh_token(refresh_token: &str) -> String {
+    format!("{:x}", Sha256::digest(refresh_token.as_bytes()))
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::internal::user::entity::session::{hash_refresh_token, to_session_response, Session};
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    #[test]
+    fn to_session_response_test() {
+        let create_ts = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let session = Session {
+            id: 2,
+            user_id: 1,
+            user_agent: Some("curl/8.0".to_string()),
+            ip: Some("10.0.0.1".to_string()),
+            refresh_token_hash: hash_refresh_token("refresh"),
+            create_ts,
+            last_seen_ts: create_ts,
+            expire_ts: create_ts + Duration::minutes(5),
+        };
+
+        let test_cases = vec! {
+            TestCase { input: Some(2), output: true },
+            TestCase { input: Some(3), output: false },
+            TestCase { input: None, output: false },
+        };
+
+        for test_case in test_cases {
+            let res = to_session_response(session.clone(), test_case.input);
+            assert_eq!(res.current, test_case.output);
+            assert_eq!((res.id, res.user_agent.as_deref(), res.expire_ts), (2, Some("curl/8.0"), session.expire_ts));
+        }
+
+        assert_eq!(session.refresh_token_hash.len(), 64);
+        assert_ne!(hash_refresh_token("other"), session.refresh_token_hash);
+    }
+}
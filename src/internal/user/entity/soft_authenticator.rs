@@ -0,0 +1,118 @@
+use ciborium::Value as Cbor;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::ecdsa::signature::Signer;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::internal::user::entity::passkey::encode;
+
+// A software authenticator for tests: one ES256 credential, attestation
+// "none", and a counter that grows with each assertion unless disabled.
+pub struct SoftAuthenticator {
+    pub key: SigningKey,
+    pub credential_id: Vec<u8>,
+    pub rp_id: String,
+    pub origin: String,
+    pub sign_count: u32,
+    pub counter: bool,
+    pub flags: u8,
+}
+
+pub fn new_soft_authenticator(rp_id: &str, origin: &str) -> SoftAuthenticator {
+    SoftAuthenticator {
+        key: SigningKey::from_slice(&rand::random::<[u8; 32]>()).unwrap(),
+        credential_id: rand::random::<[u8; 16]>().to_vec(),
+        rp_id: rp_id.to_string(),
+        origin: origin.to_string(),
+        sign_count: 0,
+        counter: true,
+        flags: 0x01 | 0x04, // UP | UV
+    }
+}
+
+impl SoftAuthenticator {
+    pub fn client_data(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        json!({"type": ceremony_type, "challenge": challenge, "origin": self.origin, "crossOrigin": false})
+            .to_string()
+            .into_bytes()
+    }
+
+    pub fn cose_key(&self) -> Vec<u8> {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let key = Cbor::Map(vec![
+            (Cbor::from(1), Cbor::from(2)),
+            (Cbor::from(3), Cbor::from(-7)),
+            (Cbor::from(-1), Cbor::from(1)),
+            (Cbor::from(-2), Cbor::Bytes(point.x().unwrap().to_vec())),
+            (Cbor::from(-3), Cbor::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut out = Vec::new();
+        ciborium::into_writer(&key, &mut out).unwrap();
+        out
+    }
+
+    pub fn authenticator_data(&self, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(if attested { self.flags | 0x40 } else { self.flags });
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        if attested {
+            data.extend_from_slice(&[0u8; 16]); // aaguid, zero for attestation none
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&self.cose_key());
+        }
+
+        data
+    }
+
+    pub fn attestation_object(&self) -> Vec<u8> {
+        let object = Cbor::Map(vec![
+            (Cbor::from("fmt"), Cbor::from("none")),
+            (Cbor::from("attStmt"), Cbor::Map(vec![])),
+            (Cbor::from("authData"), Cbor::Bytes(self.authenticator_data(true))),
+        ]);
+
+        let mut out = Vec::new();
+        ciborium::into_writer(&object, &mut out).unwrap();
+        out
+    }
+
+    // navigator.credentials.create() for the given challenge, as JSON.
+    pub fn register(&self, challenge: &str) -> Value {
+        json!({
+            "id": encode(&self.credential_id),
+            "rawId": encode(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": encode(&self.client_data("webauthn.create", challenge)),
+                "attestationObject": encode(&self.attestation_object()),
+            },
+        })
+    }
+
+    // navigator.credentials.get() for the given challenge, as JSON.
+    pub fn login(&mut self, challenge: &str, user_handle: Option<String>) -> Value {
+        if self.counter {
+            self.sign_count += 1;
+        }
+
+        let client_data = self.client_data("webauthn.get", challenge);
+        let authenticator_data = self.authenticator_data(false);
+        let message = [authenticator_data.as_slice(), &Sha256::digest(&client_data)[..]].concat();
+        let signature: Signature = self.key.sign(&message);
+
+        json!({
+            "id": encode(&self.credential_id),
+            "rawId": encode(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": encode(&client_data),
+                "authenticatorData": encode(&authenticator_data),
+                "signature": encode(signature.to_der().as_bytes()),
+                "userHandle": user_handle,
+            },
+        })
+    }
+}
@@ -1,25 +1,97 @@
 use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
-use jwt::{SignWithKey};
+use jwt::{SignWithKey, VerifyWithKey};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 
-pub const TOKEN_SECRET_KEY: &str = "bnurgeldiyev514";
-const TOKEN_LIFE_TIME: u64 = 5; // minute
+// Set on tokens that are not access tokens; verify_access_token rejects them.
+pub const TOKEN_PURPOSE_CLAIM: &str = "purpose";
+pub const TOKEN_PURPOSE_MFA: &str = "mfa";
+// The session an access token belongs to; see tbl_session.
+pub const TOKEN_SESSION_CLAIM: &str = "sid";
+// The account's id; the username claim goes stale when the account is renamed.
+pub const TOKEN_USER_CLAIM: &str = "uid";
+// The account's organization memberships when the token was issued, for
+// clients; the API itself checks memberships against the database.
+pub const TOKEN_ORGS_CLAIM: &str = "orgs";
+
+#[derive(Clone)]
+pub struct TokenConfig {
+    pub secret_key: String,
+    pub life_time: u64, // minute
+}
+
+pub fn new_token_config(secret_key: String, life_time: u64) -> TokenConfig {
+    TokenConfig {
+        secret_key,
+        life_time,
+    }
+}
 
 pub fn get_time_sec() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs()
 }
 
-pub fn generate_access_token(username: &String) -> String {
-    let key: Hmac<Sha256> = Hmac::new_from_slice(TOKEN_SECRET_KEY.as_ref()).unwrap();
+// A token without a session, like the ones issued before sessions existed.
+// verify_access_token still accepts them; logins no longer issue them.
+#[cfg(test)]
+pub fn generate_access_token(cfg: &TokenConfig, username: &String) -> String {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(cfg.secret_key.as_ref()).unwrap();
     let mut claims = BTreeMap::new();
-    let start = get_time_sec() + (TOKEN_LIFE_TIME * 60);
+    let start = get_time_sec() + (cfg.life_time * 60);
     let start_str = start.to_string().clone();
     claims.insert("username", username);
     claims.insert("created_time", &start_str);
 
-    let token_str = claims.sign_with_key(&key).unwrap();
+    claims.sign_with_key(&key).unwrap()
+}
+
+// An access token tied to a login session, which can be revoked before the
+// token expires. orgs is the value of the orgs claim, left out when empty.
+pub fn generate_session_access_token(cfg: &TokenConfig, user_id: i32, username: &String, session_id: i32, orgs: &str) -> String {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(cfg.secret_key.as_ref()).unwrap();
+    let mut claims = BTreeMap::new();
+    let start = (get_time_sec() + (cfg.life_time * 60)).to_string();
+    let user_id = user_id.to_string();
+    let session_id = session_id.to_string();
+    let orgs = orgs.to_string();
+    claims.insert("username", username);
+    claims.insert("created_time", &start);
+    claims.insert(TOKEN_USER_CLAIM, &user_id);
+    claims.insert(TOKEN_SESSION_CLAIM, &session_id);
+    if !orgs.is_empty() {
+        claims.insert(TOKEN_ORGS_CLAIM, &orgs);
+    }
+
+    claims.sign_with_key(&key).unwrap()
+}
+
+// Proves the password step of a login for an account with MFA enabled. It is
+// only accepted by the MFA verify endpoint.
+pub fn generate_mfa_challenge_token(cfg: &TokenConfig, username: &str, life_time: u64) -> String {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(cfg.secret_key.as_ref()).unwrap();
+    let mut claims = BTreeMap::new();
+    let expire = (get_time_sec() + life_time).to_string();
+    claims.insert("username", username);
+    claims.insert("created_time", &expire);
+    claims.insert(TOKEN_PURPOSE_CLAIM, TOKEN_PURPOSE_MFA);
+
+    claims.sign_with_key(&key).unwrap()
+}
+
+// Returns the username of a valid, unexpired challenge token.
+pub fn verify_mfa_challenge_token(cfg: &TokenConfig, token: &str) -> Option<String> {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(cfg.secret_key.as_ref()).unwrap();
+    let claims: BTreeMap<String, String> = token.verify_with_key(&key).ok()?;
+
+    if claims.get(TOKEN_PURPOSE_CLAIM).map(String::as_str) != Some(TOKEN_PURPOSE_MFA) {
+        return None;
+    }
+
+    let expire: u64 = claims.get("created_time")?.parse().ok()?;
+    if get_time_sec() > expire {
+        return None;
+    }
 
-    token_str
+    claims.get("username").cloned()
 }
@@ -1,8 +1,11 @@
-use std::time::{Duration, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
 use sqlx::types::chrono::{Utc};
 use chrono::prelude::DateTime;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::internal::user::entity::mfa::MfaChallengeResponse;
+
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     pub id: i32,
@@ -12,7 +15,7 @@ pub struct User {
     pub lastname: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UserCreateRequest {
     pub username: String,
     pub password: String,
@@ -20,12 +23,47 @@ pub struct UserCreateRequest {
     pub lastname: String,
 }
 
-#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+pub const USER_STATUS_ACTION_ACTIVATE: &str = "activate";
+pub const USER_STATUS_ACTION_DISABLE: &str = "disable";
+pub const USER_STATUS_ACTION_LOCK: &str = "lock";
+pub const USER_STATUS_ACTION_UNLOCK: &str = "unlock";
+pub const USER_STATUS_ACTIONS: [&str; 4] = [
+    USER_STATUS_ACTION_ACTIVATE, USER_STATUS_ACTION_DISABLE, USER_STATUS_ACTION_LOCK, USER_STATUS_ACTION_UNLOCK,
+];
+pub const USER_STATUS_REASON_MAX_LEN: usize = 256;
+
+// Only active accounts can log in or use their tokens and API keys.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Disabled, // by an admin, with a reason
+    Locked,
+    Pending, // created but not yet activated
+}
+
+impl UserStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Disabled => "disabled",
+            UserStatus::Locked => "locked",
+            UserStatus::Pending => "pending",
+        }
+    }
+}
+
+pub const USER_STATUSES: [UserStatus; 4] = [UserStatus::Active, UserStatus::Disabled, UserStatus::Locked, UserStatus::Pending];
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UserGet {
     pub id: i32,
     pub username: String,
     pub firstname: String,
     pub lastname: String,
+    pub status: UserStatus,
 }
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
@@ -33,44 +71,56 @@ pub struct UserGetPassword {
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UserAuthRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UserAuthResponse {
     pub access_token: String,
     pub refresh_token: String,
 }
 
+// Result of the password step: tokens, or a challenge to complete with
+// /api/v1/user/mfa/verify when the account has MFA enabled.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(untagged)]
+pub enum UserAuthResult {
+    Tokens(UserAuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
 #[derive(sqlx::FromRow)]
 pub struct UserFromDb {
     pub id: i32,
     pub username: String,
     pub firstname: String,
     pub lastname: String,
-    pub create_ts: i64,
-    pub update_ts: i64,
+    pub status: UserStatus,
+    pub create_ts: DateTime<Utc>,
+    pub update_ts: DateTime<Utc>,
 }
 
+#[allow(dead_code)]
 #[derive(sqlx::FromRow)]
 pub struct UserEmpty {
     pub id: i32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UserGetResponse {
     pub id: i32,
     pub username: String,
     pub firstname: String,
     pub lastname: String,
+    pub status: UserStatus,
     pub create_ts: DateTime<Utc>,
     pub update_ts: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UserUpdateRequest {
     pub id: i32,
     pub username: String,
@@ -78,7 +128,16 @@ pub struct UserUpdateRequest {
     pub lastname: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// The caller's own profile changes through /api/v1/user/me. Only the name can
+// change there; any other field is refused rather than ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UserMeUpdateRequest {
+    pub firstname: Option<String>,
+    pub lastname: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UserUpdateResponse {
     pub id: i32,
     pub username: String,
@@ -88,19 +147,26 @@ pub struct UserUpdateResponse {
     pub update_ts: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UserStatusRequest {
+    pub action: String, // activate, disable, lock or unlock
+    pub reason: Option<String>, // required to disable
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserListQuery {
+    /// One of active, disabled, locked, pending
+    pub status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct UserChangePasswordRequest {
     pub id: i32,
     pub old_password: String,
     pub new_password: String,
 }
 
-pub fn convert_unix_to_date(date: i64) -> DateTime<Utc> {
-    let d = UNIX_EPOCH + Duration::from_secs(date as u64);
-    let datetime = DateTime::<Utc>::from(d);
-    datetime
-}
-
 // verifying
 
 pub fn verify_user_auth_request(req: UserAuthRequest) -> Result<(), String> {
@@ -124,13 +190,73 @@ pub fn verify_user_create_request(req: UserCreateRequest) -> Result<(), String>
         return Err("invalid password".to_string());
     }
 
-    if req.firstname.len() == 0 {
+    if req.firstname.is_empty() {
         return Err("firstname is empty".to_string());
     }
 
-    if req.lastname.len() == 0 {
+    if req.lastname.is_empty() {
         return Err("lastname is empty".to_string());
     }
 
     Ok(())
 }
+
+pub fn verify_user_me_update_request(req: &UserMeUpdateRequest) -> Result<(), String> {
+    if req.firstname.is_none() && req.lastname.is_none() {
+        return Err("firstname or lastname is required".to_string());
+    }
+
+    if req.firstname.as_ref().is_some_and(|firstname| firstname.is_empty()) {
+        return Err("firstname is empty".to_string());
+    }
+
+    if req.lastname.as_ref().is_some_and(|lastname| lastname.is_empty()) {
+        return Err("lastname is empty".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn verify_user_status_request(req: &UserStatusRequest) -> Result<(), String> {
+    if !USER_STATUS_ACTIONS.contains(&req.action.as_str()) {
+        return Err(format!("action must be one of {}", USER_STATUS_ACTIONS.join(", ")));
+    }
+
+    let reason = req.reason.as_deref().map(str::trim).unwrap_or_default();
+    if req.action == USER_STATUS_ACTION_DISABLE && reason.is_empty() {
+        return Err("reason is required to disable an account".to_string());
+    }
+    if reason.chars().count() > USER_STATUS_REASON_MAX_LEN {
+        return Err(format!("reason must be at most {} characters", USER_STATUS_REASON_MAX_LEN));
+    }
+
+    Ok(())
+}
+
+pub fn parse_user_status(status: &str) -> Result<UserStatus, String> {
+    match USER_STATUSES.iter().find(|res| res.as_str() == status) {
+        Some(res) => {
+            Ok(*res)
+        }
+        None => {
+            let statuses: Vec<&str> = USER_STATUSES.iter().map(UserStatus::as_str).collect();
+            Err(format!("status must be one of {}", statuses.join(", ")))
+        }
+    }
+}
+
+// The status an action leads to from the current one. Disabling works from
+// any other status; locked accounts are unlocked, not activated.
+pub fn next_user_status(current: UserStatus, action: &str) -> Result<UserStatus, String> {
+    let next = match (current, action) {
+        (UserStatus::Pending | UserStatus::Disabled, USER_STATUS_ACTION_ACTIVATE) => UserStatus::Active,
+        (UserStatus::Active | UserStatus::Locked | UserStatus::Pending, USER_STATUS_ACTION_DISABLE) => UserStatus::Disabled,
+        (UserStatus::Active, USER_STATUS_ACTION_LOCK) => UserStatus::Locked,
+        (UserStatus::Locked, USER_STATUS_ACTION_UNLOCK) => UserStatus::Active,
+        _ => {
+            return Err(format!("can't {} an account that is {}", action, current.as_str()));
+        }
+    };
+
+    Ok(next)
+}
@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod tests {
     use crate::internal::user::entity::user::{
-        UserAuthRequest, UserCreateRequest,
-        verify_user_auth_request, verify_user_create_request,
+        UserAuthRequest, UserCreateRequest, UserMeUpdateRequest, UserStatus, UserStatusRequest,
+        next_user_status, parse_user_status, verify_user_auth_request, verify_user_create_request,
+        verify_user_me_update_request, verify_user_status_request,
     };
 
     struct TestCase<T, A> {
@@ -97,4 +98,85 @@ mod tests {
             assert_eq!(res, test_case.output)
         }
     }
+
+    #[test]
+    fn verify_user_me_update_request_test() {
+        let request = |firstname: Option<&str>, lastname: Option<&str>| UserMeUpdateRequest {
+            firstname: firstname.map(String::from),
+            lastname: lastname.map(String::from),
+        };
+
+        let test_cases = vec! {
+            TestCase { input: request(Some("Jim"), None), output: Ok(()) },
+            TestCase { input: request(None, Some("Holland")), output: Ok(()) },
+            TestCase { input: request(Some("Jim"), Some("Holland")), output: Ok(()) },
+            TestCase { input: request(None, None), output: Err("firstname or lastname is required".to_string()) },
+            TestCase { input: request(Some(""), None), output: Err("firstname is empty".to_string()) },
+            TestCase { input: request(Some("Jim"), Some("")), output: Err("lastname is empty".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let res = verify_user_me_update_request(&test_case.input);
+            assert_eq!(res, test_case.output)
+        }
+    }
+
+    #[test]
+    fn verify_user_status_request_test() {
+        let request = |action: &str, reason: Option<&str>| UserStatusRequest {
+            action: action.to_string(),
+            reason: reason.map(String::from),
+        };
+
+        let test_cases = vec! {
+            TestCase { input: request("lock", None), output: Ok(()) },
+            TestCase { input: request("disable", Some("left the company")), output: Ok(()) },
+            TestCase { input: request("delete", None), output: Err("action must be one of activate, disable, lock, unlock".to_string()) },
+            TestCase { input: request("disable", None), output: Err("reason is required to disable an account".to_string()) },
+            TestCase { input: request("disable", Some("  ")), output: Err("reason is required to disable an account".to_string()) },
+            TestCase { input: request("lock", Some(&"x".repeat(257))), output: Err("reason must be at most 256 characters".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let res = verify_user_status_request(&test_case.input);
+            assert_eq!(res, test_case.output)
+        }
+    }
+
+    #[test]
+    fn next_user_status_test() {
+        let test_cases = vec! {
+            TestCase { input: (UserStatus::Pending, "activate"), output: Ok(UserStatus::Active) },
+            TestCase { input: (UserStatus::Disabled, "activate"), output: Ok(UserStatus::Active) },
+            TestCase { input: (UserStatus::Active, "disable"), output: Ok(UserStatus::Disabled) },
+            TestCase { input: (UserStatus::Locked, "disable"), output: Ok(UserStatus::Disabled) },
+            TestCase { input: (UserStatus::Pending, "disable"), output: Ok(UserStatus::Disabled) },
+            TestCase { input: (UserStatus::Active, "lock"), output: Ok(UserStatus::Locked) },
+            TestCase { input: (UserStatus::Locked, "unlock"), output: Ok(UserStatus::Active) },
+            TestCase { input: (UserStatus::Active, "activate"), output: Err("can't activate an account that is active".to_string()) },
+            TestCase { input: (UserStatus::Locked, "activate"), output: Err("can't activate an account that is locked".to_string()) },
+            TestCase { input: (UserStatus::Disabled, "disable"), output: Err("can't disable an account that is disabled".to_string()) },
+            TestCase { input: (UserStatus::Disabled, "lock"), output: Err("can't lock an account that is disabled".to_string()) },
+            TestCase { input: (UserStatus::Active, "unlock"), output: Err("can't unlock an account that is active".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let (current, action) = test_case.input;
+            assert_eq!(next_user_status(current, action), test_case.output, "{:?} {}", current, action);
+        }
+    }
+
+    #[test]
+    fn parse_user_status_test() {
+        let test_cases = vec! {
+            TestCase { input: "active", output: Ok(UserStatus::Active) },
+            TestCase { input: "locked", output: Ok(UserStatus::Locked) },
+            TestCase { input: "Active", output: Err("status must be one of active, disabled, locked, pending".to_string()) },
+            TestCase { input: "", output: Err("status must be one of active, disabled, locked, pending".to_string()) },
+        };
+
+        for test_case in test_cases {
+            assert_eq!(parse_user_status(test_case.input), test_case.output);
+        }
+    }
 }
@@ -0,0 +1,29 @@
+use std::path::{Path, PathBuf};
+
+// Keeps blobs as files below root, one per key.
+pub struct LocalBlobStore {
+    pub root: PathBuf,
+}
+
+pub fn new_local_blob_store(root: &str) -> LocalBlobStore {
+    LocalBlobStore {
+        root: PathBuf::from(root),
+    }
+}
+
+// The path of a key below root. Keys are built by us, but are still checked
+// so that none can point outside of it.
+pub fn blob_path(root: &Path, key: &str) -> Result<PathBuf, String> {
+    let valid = key.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && segment.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+    });
+
+    if !valid {
+        return Err(format!("invalid blob key \"{}\"", key));
+    }
+
+    Ok(root.join(key))
+}
@@ -0,0 +1,82 @@
+use std::io::ErrorKind;
+use async_trait::async_trait;
+use tracing::{instrument, Level};
+use uuid::Uuid;
+
+use crate::internal::user::usecase::blob::blob::{blob_path, LocalBlobStore};
+use crate::internal::user::usecase::traits::BlobStore;
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    // Written to a temporary file first, so readers never see half a blob.
+    #[instrument(name = "local_blob_store.blob_put", skip(self, data), fields(size = data.len()), err(level = Level::DEBUG))]
+    async fn blob_put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = blob_path(&self.root, key)?;
+        if let Some(dir) = path.parent() {
+            if let Err(err) = tokio::fs::create_dir_all(dir).await {
+                return Err(format!("create {}: {}", dir.display(), err));
+            }
+        }
+
+        let temp = path.with_extension(format!("tmp-{}", Uuid::new_v4().simple()));
+        if let Err(err) = tokio::fs::write(&temp, data).await {
+            return Err(format!("write {}: {}", temp.display(), err));
+        }
+
+        match tokio::fs::rename(&temp, &path).await {
+            Ok(_) => {
+                Ok(())
+            }
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&temp).await;
+                Err(format!("rename {}: {}", path.display(), err))
+            }
+        }
+    }
+
+    #[instrument(name = "local_blob_store.blob_get", skip(self), err(level = Level::DEBUG))]
+    async fn blob_get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = blob_path(&self.root, key)?;
+
+        match tokio::fs::read(&path).await {
+            Ok(res) => {
+                Ok(Some(res))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Ok(None)
+            }
+            Err(err) => {
+                Err(format!("read {}: {}", path.display(), err))
+            }
+        }
+    }
+
+    #[instrument(name = "local_blob_store.blob_delete_prefix", skip(self), err(level = Level::DEBUG))]
+    async fn blob_delete_prefix(&self, prefix: &str) -> Result<(), String> {
+        let path = blob_path(&self.root, prefix)?;
+
+        let res = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_dir() => {
+                tokio::fs::remove_dir_all(&path).await
+            }
+            Ok(_) => {
+                tokio::fs::remove_file(&path).await
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+
+        match res {
+            Ok(_) => {
+                Ok(())
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Ok(())
+            }
+            Err(err) => {
+                Err(format!("delete {}: {}", path.display(), err))
+            }
+        }
+    }
+}
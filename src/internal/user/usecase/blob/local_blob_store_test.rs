@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use crate::internal::user::usecase::blob::blob::new_local_blob_store;
+    use crate::internal::user::usecase::traits::BlobStore;
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn temp_root() -> String {
+        std::env::temp_dir().join(format!("blobs-{}", uuid::Uuid::new_v4())).to_str().unwrap().to_string()
+    }
+
+    #[actix_web::test]
+    async fn local_blob_store_test() {
+        let root = temp_root();
+        let store = new_local_blob_store(&root);
+
+        assert_eq!(store.blob_get("avatars/1/a/64.png").await, Ok(None));
+
+        store.blob_put("avatars/1/a/64.png", b"small".to_vec()).await.unwrap();
+        store.blob_put("avatars/1/a/256.png", b"large".to_vec()).await.unwrap();
+        store.blob_put("avatars/12/b/64.png", b"other".to_vec()).await.unwrap();
+        assert_eq!(store.blob_get("avatars/1/a/64.png").await, Ok(Some(b"small".to_vec())));
+
+        store.blob_put("avatars/1/a/64.png", b"replaced".to_vec()).await.unwrap();
+        assert_eq!(store.blob_get("avatars/1/a/64.png").await, Ok(Some(b"replaced".to_vec())));
+        let files = std::fs::read_dir(format!("{}/avatars/1/a", root)).unwrap().count();
+        assert_eq!(files, 2, "no temporary files are left behind");
+
+        // The prefix is a whole path segment: avatars/1 leaves avatars/12 alone.
+        store.blob_delete_prefix("avatars/1").await.unwrap();
+        assert_eq!(store.blob_get("avatars/1/a/256.png").await, Ok(None));
+        assert_eq!(store.blob_get("avatars/12/b/64.png").await, Ok(Some(b"other".to_vec())));
+        assert_eq!(store.blob_delete_prefix("avatars/1").await, Ok(()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn blob_key_test() {
+        let root = temp_root();
+        let store = new_local_blob_store(&root);
+
+        let test_cases = vec! {
+            TestCase { input: "avatars/1/a/64.png", output: true },
+            TestCase { input: "avatars/1/../../etc/passwd", output: false },
+            TestCase { input: "/etc/passwd", output: false },
+            TestCase { input: "avatars//64.png", output: false },
+            TestCase { input: "avatars/./64.png", output: false },
+            TestCase { input: "avatars\\..\\64.png", output: false },
+            TestCase { input: "", output: false },
+        };
+
+        for test_case in test_cases {
+            let res = store.blob_put(test_case.input, Vec::new()).await;
+            assert_eq!(res.is_ok(), test_case.output, "{}", test_case.input);
+            if !test_case.output {
+                assert_eq!(res, Err(format!("invalid blob key \"{}\"", test_case.input)));
+            }
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+
+use crate::internal::user::usecase::traits::BlobStore;
+
+// In-memory stand-in for LocalBlobStore, used by the HTTP tests.
+
+#[derive(Default)]
+pub struct MemoryBlobStore {
+    pub blobs: Mutex<BTreeMap<String, Vec<u8>>>,
+    pub broken: Mutex<bool>, // every call fails as if the disk were full
+}
+
+pub fn new_memory_blob_store() -> MemoryBlobStore {
+    MemoryBlobStore::default()
+}
+
+impl MemoryBlobStore {
+    fn check(&self) -> Result<(), String> {
+        if *self.broken.lock().unwrap() {
+            return Err("no space left on device".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn blob_put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        self.check()?;
+        self.blobs.lock().unwrap().insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn blob_get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        self.check()?;
+        Ok(self.blobs.lock().unwrap().get(key).cloned())
+    }
+
+    async fn blob_delete_prefix(&self, prefix: &str) -> Result<(), String> {
+        self.check()?;
+        let dir = format!("{}/", prefix);
+        self.blobs.lock().unwrap().retain(|key, _| key != prefix && !key.starts_with(&dir));
+        Ok(())
+    }
+}
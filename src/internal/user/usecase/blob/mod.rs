@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod blob;
+pub mod local_blob_store;
+pub mod local_blob_store_test;
+#[cfg(test)]
+pub mod memory_blob_store;
@@ -1,4 +1,5 @@
 pub mod traits;
 pub mod user;
 pub mod repo;
-pub mod webapi;
\ No newline at end of file
+pub mod webapi;
+pub mod blob;
\ No newline at end of file
@@ -0,0 +1,712 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::Error;
+use sqlx::types::Json;
+
+use crate::internal::user::entity::user::{
+    UserChangePasswordRequest, UserCreateRequest, UserFromDb, UserGet,
+    UserGetPassword, UserGetResponse, UserStatus, UserUpdateRequest,
+};
+use crate::internal::user::entity::mfa::{UserMfa, MFA_LOCK_REASON};
+use crate::internal::user::entity::passkey::{Passkey, PasskeyCreate};
+use crate::internal::user::entity::api_key::{ApiKey, ApiKeyCreate};
+use crate::internal::user::entity::session::{Session, SessionCreate};
+use crate::internal::user::entity::profile::{ProfileUpdate, UserProfile};
+use crate::internal::user::entity::batch::{BatchWrite, BatchWriteError};
+use crate::internal::user::usecase::traits::Repo;
+use crate::internal::user::entity::token::get_time_sec;
+
+// In-memory stand-in for UserRepo, used by tests that must not touch Postgres.
+
+#[derive(Clone, Default)]
+pub struct MemoryUser {
+    pub id: i32,
+    pub username: String,
+    pub password: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub status: UserStatus,
+    pub status_reason: Option<String>,
+    pub create_ts: DateTime<Utc>,
+    pub update_ts: DateTime<Utc>,
+    pub mfa_secret: Option<String>,
+    pub mfa_enabled: bool,
+    pub mfa_last_step: Option<i64>,
+    pub mfa_failed_attempts: i32,
+}
+
+#[derive(Clone)]
+pub struct MemoryRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+    pub used: bool,
+}
+
+#[derive(Clone)]
+pub struct MemoryPasskeyChallenge {
+    pub challenge: String,
+    pub ceremony: String,
+    pub user_id: Option<i32>,
+    pub expire_ts: u64,
+}
+
+#[derive(Default)]
+pub struct MemoryUserRepo {
+    pub users: Mutex<Vec<MemoryUser>>,
+    pub recovery_codes: Mutex<Vec<MemoryRecoveryCode>>,
+    pub passkeys: Mutex<Vec<Passkey>>,
+    pub passkey_challenges: Mutex<Vec<MemoryPasskeyChallenge>>,
+    pub api_keys: Mutex<Vec<ApiKey>>,
+    pub sessions: Mutex<Vec<Session>>,
+    pub profiles: Mutex<Vec<UserProfile>>,
+    pub broken: Mutex<bool>, // every call fails as if the pool were exhausted
+}
+
+pub fn new_memory_user_repo() -> MemoryUserRepo {
+    MemoryUserRepo::default()
+}
+
+impl MemoryUserRepo {
+    fn check(&self) -> Result<(), Error> {
+        if *self.broken.lock().unwrap() {
+            return Err(Error::PoolTimedOut);
+        }
+
+        Ok(())
+    }
+
+    fn find<F: Fn(&MemoryUser) -> bool>(&self, f: F) -> Result<MemoryUser, Error> {
+        let users = self.users.lock().unwrap();
+        match users.iter().find(|u| f(u)) {
+            Some(user) => {
+                Ok(user.clone())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    fn update<F: FnOnce(&mut MemoryUser)>(&self, id: i32, f: F) -> Result<(), Error> {
+        let mut users = self.users.lock().unwrap();
+        match users.iter_mut().find(|u| u.id == id) {
+            Some(user) => {
+                f(user);
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+}
+
+fn to_mfa(user: MemoryUser) -> UserMfa {
+    UserMfa {
+        id: user.id,
+        username: user.username,
+        mfa_secret: user.mfa_secret,
+        mfa_enabled: user.mfa_enabled,
+    }
+}
+
+fn to_from_db(user: &MemoryUser) -> UserFromDb {
+    UserFromDb {
+        id: user.id,
+        username: user.username.clone(),
+        firstname: user.firstname.clone(),
+        lastname: user.lastname.clone(),
+        status: user.status,
+        create_ts: user.create_ts,
+        update_ts: user.update_ts,
+    }
+}
+
+fn to_response(user: MemoryUser) -> UserGetResponse {
+    UserGetResponse {
+        id: user.id,
+        username: user.username,
+        firstname: user.firstname,
+        lastname: user.lastname,
+        status: user.status,
+        create_ts: user.create_ts,
+        update_ts: user.update_ts,
+    }
+}
+
+#[async_trait]
+impl Repo for MemoryUserRepo {
+    async fn user_get_password_by_username(&self, username: String) -> Result<UserGetPassword, Error> {
+        self.check()?;
+        let user = self.find(|u| u.username == username)?;
+        Ok(UserGetPassword { password: user.password })
+    }
+
+    async fn user_get_password_by_id(&self, id: i32) -> Result<UserGetPassword, Error> {
+        self.check()?;
+        let user = self.find(|u| u.id == id)?;
+        Ok(UserGetPassword { password: user.password })
+    }
+
+    async fn user_create(&self, user: UserCreateRequest) -> Result<UserGet, Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        let now = Utc::now();
+        let id = users.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+
+        users.push(MemoryUser {
+            id,
+            username: user.username.clone(),
+            password: user.password,
+            firstname: user.firstname.clone(),
+            lastname: user.lastname.clone(),
+            create_ts: now,
+            update_ts: now,
+            ..Default::default()
+        });
+
+        Ok(UserGet {
+            id,
+            username: user.username,
+            firstname: user.firstname,
+            lastname: user.lastname,
+            status: UserStatus::Active,
+        })
+    }
+
+    async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, Error> {
+        self.check()?;
+        self.find(|u| u.id == id).map(to_response)
+    }
+
+    async fn user_get_by_username(&self, username: String) -> Result<UserGet, Error> {
+        self.check()?;
+        let user = self.find(|u| u.username == username)?;
+        Ok(UserGet {
+            id: user.id,
+            username: user.username,
+            firstname: user.firstname,
+            lastname: user.lastname,
+            status: user.status,
+        })
+    }
+
+    async fn user_list(&self, status: Option<UserStatus>) -> Result<Vec<UserGetResponse>, Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap().clone();
+        users.retain(|u| status.is_none_or(|status| u.status == status));
+        users.sort_by_key(|u| u.id);
+        Ok(users.into_iter().map(to_response).collect())
+    }
+
+    async fn user_list_page(&self, status: Option<UserStatus>, after_id: i32, limit: i64) -> Result<Vec<UserGetResponse>, Error> {
+        let mut users = self.user_list(status).await?;
+        users.retain(|u| u.id > after_id);
+        users.truncate(limit as usize);
+        Ok(users)
+    }
+
+    async fn user_update_by_id(&self, user: UserUpdateRequest) -> Result<UserFromDb, Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        match users.iter_mut().find(|u| u.id == user.id) {
+            Some(u) => {
+                u.username = user.username;
+                u.firstname = user.firstname;
+                u.lastname = user.lastname;
+                u.update_ts = Utc::now();
+
+                Ok(UserFromDb {
+                    id: u.id,
+                    username: u.username.clone(),
+                    firstname: u.firstname.clone(),
+                    lastname: u.lastname.clone(),
+                    status: u.status,
+                    create_ts: u.create_ts,
+                    update_ts: u.update_ts,
+                })
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_set_status(&self, id: i32, from: UserStatus, to: UserStatus, reason: Option<String>) -> Result<UserFromDb, Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        match users.iter_mut().find(|u| u.id == id && u.status == from) {
+            Some(u) => {
+                u.status = to;
+                u.status_reason = reason;
+                u.mfa_failed_attempts = 0;
+                u.update_ts = Utc::now();
+
+                Ok(UserFromDb {
+                    id: u.id,
+                    username: u.username.clone(),
+                    firstname: u.firstname.clone(),
+                    lastname: u.lastname.clone(),
+                    status: u.status,
+                    create_ts: u.create_ts,
+                    update_ts: u.update_ts,
+                })
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_change_password(&self, req: UserChangePasswordRequest) -> Result<(), Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        match users.iter_mut().find(|u| u.id == req.id) {
+            Some(u) => {
+                u.password = req.new_password;
+                u.update_ts = Utc::now();
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_delete_by_id(&self, id: i32) -> Result<(), Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        match users.iter().position(|u| u.id == id) {
+            Some(index) => {
+                users.remove(index);
+                self.passkeys.lock().unwrap().retain(|p| p.user_id != id);
+                self.api_keys.lock().unwrap().retain(|k| k.user_id != id);
+                self.sessions.lock().unwrap().retain(|s| s.user_id != id);
+                self.profiles.lock().unwrap().retain(|p| p.user_id != id);
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_batch(&self, writes: Vec<BatchWrite>) -> Result<Vec<UserFromDb>, BatchWriteError> {
+        self.check().map_err(|err| BatchWriteError { index: None, err })?;
+        let mut users = self.users.lock().unwrap();
+        // Written to a copy, which replaces the users only when every write succeeded.
+        let mut next = users.clone();
+        let mut rows = Vec::new();
+        let mut deleted = Vec::new();
+
+        for (index, write) in writes.into_iter().enumerate() {
+            let now = Utc::now();
+            let row = match write {
+                BatchWrite::Create(user) => {
+                    let id = next.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+                    next.push(MemoryUser {
+                        id,
+                        username: user.username,
+                        password: user.password,
+                        firstname: user.firstname,
+                        lastname: user.lastname,
+                        create_ts: now,
+                        update_ts: now,
+                        ..Default::default()
+                    });
+                    next.last().map(to_from_db)
+                }
+                BatchWrite::Update(user) => {
+                    next.iter_mut().find(|u| u.id == user.id).map(|u| {
+                        u.username = user.username;
+                        u.firstname = user.firstname;
+                        u.lastname = user.lastname;
+                        u.update_ts = now;
+                        to_from_db(u)
+                    })
+                }
+                BatchWrite::Delete(id) => {
+                    next.iter().position(|u| u.id == id).map(|position| {
+                        deleted.push(id);
+                        to_from_db(&next.remove(position))
+                    })
+                }
+                BatchWrite::Status { id, from, to, reason } => {
+                    next.iter_mut().find(|u| u.id == id && u.status == from).map(|u| {
+                        u.status = to;
+                        u.status_reason = reason;
+                        u.mfa_failed_attempts = 0;
+                        u.update_ts = now;
+                        to_from_db(u)
+                    })
+                }
+            };
+
+            match row {
+                Some(row) => {
+                    rows.push(row);
+                }
+                None => {
+                    return Err(BatchWriteError { index: Some(index), err: Error::RowNotFound });
+                }
+            }
+        }
+
+        *users = next;
+        for id in deleted {
+            self.passkeys.lock().unwrap().retain(|p| p.user_id != id);
+            self.api_keys.lock().unwrap().retain(|k| k.user_id != id);
+            self.sessions.lock().unwrap().retain(|s| s.user_id != id);
+            self.profiles.lock().unwrap().retain(|p| p.user_id != id);
+        }
+
+        Ok(rows)
+    }
+
+    async fn user_get_mfa_by_username(&self, username: String) -> Result<UserMfa, Error> {
+        self.check()?;
+        self.find(|u| u.username == username).map(to_mfa)
+    }
+
+    async fn user_get_mfa_by_id(&self, id: i32) -> Result<UserMfa, Error> {
+        self.check()?;
+        self.find(|u| u.id == id).map(to_mfa)
+    }
+
+    async fn user_mfa_set_secret(&self, id: i32, secret: String) -> Result<(), Error> {
+        self.check()?;
+        self.update(id, |u| {
+            u.mfa_secret = Some(secret);
+            u.mfa_enabled = false;
+            u.mfa_last_step = None;
+        })
+    }
+
+    async fn user_mfa_enable(&self, id: i32, step: i64, code_hashes: Vec<String>) -> Result<(), Error> {
+        self.check()?;
+        self.update(id, |u| {
+            u.mfa_enabled = true;
+            u.mfa_last_step = Some(step);
+            u.mfa_failed_attempts = 0;
+        })?;
+
+        let mut codes = self.recovery_codes.lock().unwrap();
+        codes.retain(|c| c.user_id != id);
+        codes.extend(code_hashes.into_iter().map(|code_hash| MemoryRecoveryCode { user_id: id, code_hash, used: false }));
+        Ok(())
+    }
+
+    async fn user_mfa_use_step(&self, id: i32, step: i64) -> Result<(), Error> {
+        self.check()?;
+        let mut users = self.users.lock().unwrap();
+        match users.iter_mut().find(|u| u.id == id && u.mfa_last_step.is_none_or(|last| last < step)) {
+            Some(u) => {
+                u.mfa_last_step = Some(step);
+                u.mfa_failed_attempts = 0;
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_mfa_use_recovery_code(&self, id: i32, code_hash: String) -> Result<(), Error> {
+        self.check()?;
+        let mut codes = self.recovery_codes.lock().unwrap();
+        match codes.iter_mut().find(|c| c.user_id == id && c.code_hash == code_hash && !c.used) {
+            Some(c) => {
+                c.used = true;
+                self.update(id, |u| u.mfa_failed_attempts = 0)
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn user_mfa_fail(&self, id: i32, max_attempts: i32) -> Result<bool, Error> {
+        self.check()?;
+        let mut locked = false;
+        self.update(id, |u| {
+            u.mfa_failed_attempts += 1;
+            if u.status == UserStatus::Active && u.mfa_failed_attempts >= max_attempts {
+                u.status = UserStatus::Locked;
+                u.status_reason = Some(MFA_LOCK_REASON.to_string());
+                u.update_ts = Utc::now();
+                locked = true;
+            }
+        })?;
+
+        Ok(locked)
+    }
+
+    async fn user_mfa_reset(&self, id: i32) -> Result<(), Error> {
+        self.check()?;
+        self.update(id, |u| {
+            u.mfa_secret = None;
+            u.mfa_enabled = false;
+            u.mfa_last_step = None;
+            u.mfa_failed_attempts = 0;
+        })?;
+
+        self.recovery_codes.lock().unwrap().retain(|c| c.user_id != id);
+        Ok(())
+    }
+
+    async fn passkey_challenge_create(&self, challenge: String, ceremony: String, user_id: Option<i32>, life_time: u64) -> Result<(), Error> {
+        self.check()?;
+        let now = get_time_sec();
+        let mut challenges = self.passkey_challenges.lock().unwrap();
+        challenges.retain(|c| c.expire_ts >= now);
+        challenges.push(MemoryPasskeyChallenge { challenge, ceremony, user_id, expire_ts: now + life_time });
+        Ok(())
+    }
+
+    async fn passkey_challenge_take(&self, challenge: String, ceremony: String) -> Result<Option<i32>, Error> {
+        self.check()?;
+        let now = get_time_sec();
+        let mut challenges = self.passkey_challenges.lock().unwrap();
+        match challenges.iter().position(|c| c.challenge == challenge && c.ceremony == ceremony && c.expire_ts >= now) {
+            Some(index) => {
+                Ok(challenges.remove(index).user_id)
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn passkey_list_by_user(&self, user_id: i32) -> Result<Vec<Passkey>, Error> {
+        self.check()?;
+        Ok(self.passkeys.lock().unwrap().iter().filter(|p| p.user_id == user_id).cloned().collect())
+    }
+
+    async fn passkey_get_by_credential_id(&self, credential_id: Vec<u8>) -> Result<Passkey, Error> {
+        self.check()?;
+        self.passkeys.lock().unwrap().iter().find(|p| p.credential_id == credential_id).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn passkey_create(&self, passkey: PasskeyCreate) -> Result<Passkey, Error> {
+        self.check()?;
+        let mut passkeys = self.passkeys.lock().unwrap();
+        let created = Passkey {
+            id: passkeys.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+            user_id: passkey.user_id,
+            credential_id: passkey.credential_id,
+            public_key: passkey.public_key,
+            sign_count: passkey.sign_count,
+            name: passkey.name,
+        };
+        passkeys.push(created.clone());
+        Ok(created)
+    }
+
+    async fn passkey_use(&self, id: i32, sign_count: i64) -> Result<(), Error> {
+        self.check()?;
+        let mut passkeys = self.passkeys.lock().unwrap();
+        match passkeys.iter_mut().find(|p| p.id == id && (p.sign_count < sign_count || (p.sign_count == 0 && sign_count == 0))) {
+            Some(passkey) => {
+                passkey.sign_count = sign_count;
+                Ok(())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn api_key_create(&self, key: ApiKeyCreate) -> Result<ApiKey, Error> {
+        self.check()?;
+        let mut keys = self.api_keys.lock().unwrap();
+        let created = ApiKey {
+            id: keys.iter().map(|k| k.id).max().unwrap_or(0) + 1,
+            user_id: key.user_id,
+            name: key.name,
+            prefix: key.prefix,
+            secret_hash: key.secret_hash,
+            scopes: key.scopes,
+            expire_ts: key.expire_ts,
+            create_ts: Utc::now(),
+            last_used_ts: None,
+        };
+        keys.push(created.clone());
+        Ok(created)
+    }
+
+    async fn api_key_list_by_user(&self, user_id: i32) -> Result<Vec<ApiKey>, Error> {
+        self.check()?;
+        Ok(self.api_keys.lock().unwrap().iter().filter(|k| k.user_id == user_id).cloned().collect())
+    }
+
+    async fn api_key_get_by_prefix(&self, prefix: String) -> Result<ApiKey, Error> {
+        self.check()?;
+        self.api_keys.lock().unwrap().iter().find(|k| k.prefix == prefix).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn api_key_rotate(&self, user_id: i32, id: i32, prefix: String, secret_hash: String) -> Result<ApiKey, Error> {
+        self.check()?;
+        let mut keys = self.api_keys.lock().unwrap();
+        match keys.iter_mut().find(|k| k.id == id && k.user_id == user_id) {
+            Some(key) => {
+                key.prefix = prefix;
+                key.secret_hash = secret_hash;
+                key.last_used_ts = None;
+                Ok(key.clone())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn api_key_delete(&self, user_id: i32, id: i32) -> Result<ApiKey, Error> {
+        self.check()?;
+        let mut keys = self.api_keys.lock().unwrap();
+        match keys.iter().position(|k| k.id == id && k.user_id == user_id) {
+            Some(index) => {
+                Ok(keys.remove(index))
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn api_key_touch(&self, id: i32) -> Result<(), Error> {
+        self.check()?;
+        let now = Utc::now();
+        if let Some(key) = self.api_keys.lock().unwrap().iter_mut().find(|k| k.id == id) {
+            if key.last_used_ts.is_none_or(|last_used_ts| last_used_ts < now - Duration::minutes(1)) {
+                key.last_used_ts = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn session_create(&self, session: SessionCreate) -> Result<Session, Error> {
+        self.check()?;
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Utc::now();
+        let created = Session {
+            id: sessions.iter().map(|s| s.id).max().unwrap_or(0) + 1,
+            user_id: session.user_id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            refresh_token_hash: session.refresh_token_hash,
+            create_ts: now,
+            last_seen_ts: now,
+            expire_ts: session.expire_ts,
+        };
+        sessions.push(created.clone());
+        Ok(created)
+    }
+
+    async fn session_list_by_user(&self, user_id: i32) -> Result<Vec<Session>, Error> {
+        self.check()?;
+        let now = Utc::now();
+        Ok(self.sessions.lock().unwrap().iter().filter(|s| s.user_id == user_id && s.expire_ts > now).cloned().collect())
+    }
+
+    async fn session_get(&self, id: i32) -> Result<Session, Error> {
+        self.check()?;
+        let now = Utc::now();
+        self.sessions.lock().unwrap().iter().find(|s| s.id == id && s.expire_ts > now).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn session_touch(&self, id: i32) -> Result<(), Error> {
+        self.check()?;
+        let now = Utc::now();
+        if let Some(session) = self.sessions.lock().unwrap().iter_mut().find(|s| s.id == id) {
+            if session.last_seen_ts < now - Duration::minutes(1) {
+                session.last_seen_ts = now;
+            }
+        }
+        Ok(())
+    }
+
+    async fn session_delete(&self, user_id: i32, id: i32) -> Result<Session, Error> {
+        self.check()?;
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.iter().position(|s| s.id == id && s.user_id == user_id) {
+            Some(index) => {
+                Ok(sessions.remove(index))
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn session_delete_expired(&self) -> Result<u64, Error> {
+        self.check()?;
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        let len = sessions.len();
+        sessions.retain(|s| s.expire_ts > now);
+
+        Ok((len - sessions.len()) as u64)
+    }
+
+    async fn profile_get(&self, user_id: i32) -> Result<UserProfile, Error> {
+        self.check()?;
+        match self.profiles.lock().unwrap().iter().find(|p| p.user_id == user_id) {
+            Some(profile) => {
+                Ok(profile.clone())
+            }
+            None => {
+                Err(Error::RowNotFound)
+            }
+        }
+    }
+
+    async fn profile_upsert(&self, profile: ProfileUpdate) -> Result<UserProfile, Error> {
+        self.check()?;
+        let mut profiles = self.profiles.lock().unwrap();
+        let avatar_key = match profiles.iter().position(|p| p.user_id == profile.user_id) {
+            Some(index) => {
+                profiles.remove(index).avatar_key
+            }
+            None => {
+                None
+            }
+        };
+
+        let res = UserProfile {
+            user_id: profile.user_id,
+            phone: profile.phone,
+            locale: profile.locale,
+            timezone: profile.timezone,
+            attributes: Json(profile.attributes),
+            avatar_key,
+            update_ts: Utc::now(),
+        };
+        profiles.push(res.clone());
+
+        Ok(res)
+    }
+
+    async fn profile_set_avatar(&self, user_id: i32, avatar_key: Option<String>) -> Result<Option<String>, Error> {
+        self.check()?;
+        let mut profiles = self.profiles.lock().unwrap();
+        match profiles.iter_mut().find(|p| p.user_id == user_id) {
+            Some(profile) => {
+                profile.update_ts = Utc::now();
+                Ok(std::mem::replace(&mut profile.avatar_key, avatar_key))
+            }
+            None => {
+                profiles.push(UserProfile {
+                    user_id,
+                    phone: None,
+                    locale: None,
+                    timezone: None,
+                    attributes: Json(Default::default()),
+                    avatar_key,
+                    update_ts: Utc::now(),
+                });
+                Ok(None)
+            }
+        }
+    }
+}
@@ -1,2 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod repo;
-pub mod user_repo;
\ No newline at end of file
+pub mod user_repo;
+pub mod user_repo_test;
+#[cfg(test)]
+pub mod memory_repo;
@@ -1,22 +1,39 @@
 use async_trait::async_trait;
 use sqlx::Error;
+use sqlx::types::Json;
+use tracing::{instrument, Level};
+use tracing::field::Empty;
 
 use crate::internal::user::entity::user::{
-    convert_unix_to_date, UserChangePasswordRequest, UserCreateRequest, UserEmpty,
-    UserFromDb, UserGet, UserGetPassword, UserGetResponse, UserUpdateRequest,
+    UserChangePasswordRequest, UserCreateRequest, UserEmpty,
+    UserFromDb, UserGet, UserGetPassword, UserGetResponse, UserStatus, UserUpdateRequest,
 };
+use crate::internal::user::entity::mfa::{UserMfa, MFA_LOCK_REASON};
+use crate::internal::user::entity::passkey::{Passkey, PasskeyCreate};
+use crate::internal::user::entity::api_key::{ApiKey, ApiKeyCreate};
+use crate::internal::user::entity::session::{Session, SessionCreate};
+use crate::internal::user::entity::profile::{ProfileUpdate, UserProfile};
+use crate::internal::user::entity::batch::{BatchWrite, BatchWriteError};
 use crate::internal::user::usecase::repo::repo::UserRepo;
 use crate::internal::user::usecase::traits::Repo;
-use crate::internal::user::entity::token::get_time_sec;
+use crate::pkg::postgres::trace::{record_rows, record_statement};
 
 #[async_trait]
 impl Repo for UserRepo {
+    #[instrument(
+        name = "user_repo.user_get_password_by_username",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_get_password_by_username(&self, username: String) -> Result<UserGetPassword, Error> {
         let sql = "SELECT password FROM tbl_user WHERE username=$1";
-        let query = sqlx::query_as::<_, UserGetPassword>(&sql).bind(username);
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserGetPassword>(sql).bind(username);
 
         return match query.fetch_one(&**self.db).await {
             Ok(data) => {
+                record_rows(1);
                 Ok(data)
             }
             Err(err) => {
@@ -25,12 +42,20 @@ impl Repo for UserRepo {
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_get_password_by_id",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_get_password_by_id(&self, id: i32) -> Result<UserGetPassword, Error> {
         let sql = "SELECT password FROM tbl_user WHERE id=$1";
-        let query = sqlx::query_as::<_, UserGetPassword>(&sql).bind(id);
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserGetPassword>(sql).bind(id);
 
         return match query.fetch_one(&**self.db).await {
             Ok(data) => {
+                record_rows(1);
                 Ok(data)
             }
             Err(err) => {
@@ -39,11 +64,18 @@ impl Repo for UserRepo {
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_create",
+        skip_all,
+        fields(username = %user.username, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_create(&self, user: UserCreateRequest) -> Result<UserGet, Error> {
-        let sql = "INSERT INTO tbl_user(username, password, firstname, lastname) VALUES($1, $2, $3, $4)\
-         RETURNING id, username, firstname, lastname";
+        let sql = "INSERT INTO tbl_user(username, password, firstname, lastname) VALUES($1, $2, $3, $4) \
+         RETURNING id, username, firstname, lastname, status";
+        record_statement(sql);
 
-        let query = sqlx::query_as::<_, UserGet>(&sql)
+        let query = sqlx::query_as::<_, UserGet>(sql)
             .bind(user.username)
             .bind(user.password)
             .bind(user.firstname)
@@ -51,6 +83,7 @@ impl Repo for UserRepo {
 
         return match query.fetch_one(&**self.db).await {
             Ok(data) => {
+                record_rows(1);
                 Ok(data)
             }
             Err(err) => {
@@ -59,44 +92,52 @@ impl Repo for UserRepo {
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_get_by_id",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, Error> {
-        let sql = "SELECT id, username, firstname, lastname, create_ts, update_ts FROM tbl_user WHERE id=$1";
-        let query = sqlx::query_as::<_, UserFromDb>(&sql).bind(id);
+        let sql = "SELECT id, username, firstname, lastname, status, create_ts, update_ts FROM tbl_user WHERE id=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserFromDb>(sql).bind(id);
 
-        let res = match query.fetch_one(&**self.db).await {
-            Ok(data) => {
-                Some(data)
-            }
-            Err(_err) => {
-                None
-            }
-        };
-
-        return match res {
-            Some(res) => {
+        return match query.fetch_one(&**self.db).await {
+            Ok(res) => {
+                record_rows(1);
                 let data = UserGetResponse {
                     id,
                     username: res.username,
                     firstname: res.firstname,
                     lastname: res.lastname,
-                    create_ts: convert_unix_to_date(res.create_ts),
-                    update_ts: convert_unix_to_date(res.update_ts),
+                    status: res.status,
+                    create_ts: res.create_ts,
+                    update_ts: res.update_ts,
                 };
 
                 Ok(data)
             }
-            None => {
-                Err(Error::RowNotFound)
+            Err(err) => {
+                Err(err)
             }
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_get_by_username",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_get_by_username(&self, username: String) -> Result<UserGet, Error> {
-        let sql = "SELECT id, username, firstname, lastname FROM tbl_user WHERE username=$1";
-        let query = sqlx::query_as::<_, UserGet>(&sql).bind(username);
+        let sql = "SELECT id, username, firstname, lastname, status FROM tbl_user WHERE username=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserGet>(sql).bind(username);
 
         return match query.fetch_one(&**self.db).await {
             Ok(data) => {
+                record_rows(1);
                 Ok(data)
             }
             Err(err) => {
@@ -105,21 +146,21 @@ impl Repo for UserRepo {
         };
     }
 
-    async fn user_list(&self) -> Result<Vec<UserGetResponse>, Error> {
-        let sql = "SELECT id, username, firstname, lastname, create_ts, update_ts FROM tbl_user ORDER BY id";
-        let query = sqlx::query_as::<_, UserFromDb>(&sql);
+    #[instrument(
+        name = "user_repo.user_list",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_list(&self, status: Option<UserStatus>) -> Result<Vec<UserGetResponse>, Error> {
+        let sql = "SELECT id, username, firstname, lastname, status, create_ts, update_ts FROM tbl_user \
+        WHERE ($1::varchar IS NULL OR status=$1) ORDER BY id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserFromDb>(sql).bind(status);
 
-        let users = match query.fetch_all(&**self.db).await {
+        return match query.fetch_all(&**self.db).await {
             Ok(data) => {
-                Some(data)
-            }
-            Err(_err) => {
-                None
-            }
-        };
-
-        return match users {
-            Some(data) => {
+                record_rows(data.len());
                 let mut users: Vec<UserGetResponse> = Vec::new();
 
                 for user in data {
@@ -128,8 +169,9 @@ impl Repo for UserRepo {
                         username: user.username,
                         firstname: user.firstname,
                         lastname: user.lastname,
-                        create_ts: convert_unix_to_date(user.create_ts),
-                        update_ts: convert_unix_to_date(user.update_ts),
+                        status: user.status,
+                        create_ts: user.create_ts,
+                        update_ts: user.update_ts,
                     };
 
                     users.push(u);
@@ -137,24 +179,97 @@ impl Repo for UserRepo {
 
                 Ok(users)
             }
-            None => {
-                Err(Error::RowNotFound)
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_list_page",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_list_page(&self, status: Option<UserStatus>, after_id: i32, limit: i64) -> Result<Vec<UserGetResponse>, Error> {
+        let sql = "SELECT id, username, firstname, lastname, status, create_ts, update_ts FROM tbl_user \
+        WHERE ($1::varchar IS NULL OR status=$1) AND id>$2 ORDER BY id LIMIT $3";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserFromDb>(sql)
+            .bind(status)
+            .bind(after_id)
+            .bind(limit);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                let users = data
+                    .into_iter()
+                    .map(|user| UserGetResponse {
+                        id: user.id,
+                        username: user.username,
+                        firstname: user.firstname,
+                        lastname: user.lastname,
+                        status: user.status,
+                        create_ts: user.create_ts,
+                        update_ts: user.update_ts,
+                    })
+                    .collect();
+
+                Ok(users)
+            }
+            Err(err) => {
+                Err(err)
             }
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_update_by_id",
+        skip_all,
+        fields(id = user.id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_update_by_id(&self, user: UserUpdateRequest) -> Result<UserFromDb, Error> {
-        let sql = "UPDATE tbl_user SET username=$1, firstname=$2, lastname=$3, update_ts=$4 WHERE id=$5\
-        RETURNING id, username, firstname, lastname, create_ts, update_ts";
-        let query = sqlx::query_as::<_, UserFromDb>(&sql)
+        let sql = "UPDATE tbl_user SET username=$1, firstname=$2, lastname=$3 WHERE id=$4 \
+        RETURNING id, username, firstname, lastname, status, create_ts, update_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserFromDb>(sql)
             .bind(user.username)
             .bind(user.firstname)
             .bind(user.lastname)
-            .bind(get_time_sec() as i64)
             .bind(user.id);
 
         return match query.fetch_one(&**self.db).await {
             Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_set_status",
+        skip(self, reason),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_set_status(&self, id: i32, from: UserStatus, to: UserStatus, reason: Option<String>) -> Result<UserFromDb, Error> {
+        let sql = "UPDATE tbl_user SET status=$1, status_reason=$2, mfa_failed_attempts=0 WHERE id=$3 AND status=$4 \
+        RETURNING id, username, firstname, lastname, status, create_ts, update_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserFromDb>(sql)
+            .bind(to)
+            .bind(reason)
+            .bind(id)
+            .bind(from);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
                 Ok(data)
             }
             Err(err) => {
@@ -163,16 +278,581 @@ impl Repo for UserRepo {
         };
     }
 
+    #[instrument(
+        name = "user_repo.user_change_password",
+        skip_all,
+        fields(id = req.id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
     async fn user_change_password(&self, req: UserChangePasswordRequest) -> Result<(), Error> {
-        let sql = "UPDATE tbl_user SET password=$1, update_ts=$2 WHERE id=$3 RETURNING id";
+        let sql = "UPDATE tbl_user SET password=$1 WHERE id=$2 RETURNING id";
+        record_statement(sql);
 
-        let query = sqlx::query_as::<_, UserEmpty>(&sql)
+        let query = sqlx::query_as::<_, UserEmpty>(sql)
             .bind(req.new_password)
-            .bind(get_time_sec() as i64)
             .bind(req.id);
 
         return match query.fetch_one(&**self.db).await {
             Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_delete_by_id",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_delete_by_id(&self, id: i32) -> Result<(), Error> {
+        let sql = "DELETE FROM tbl_user WHERE id=$1 RETURNING id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserEmpty>(sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_batch",
+        skip_all,
+        fields(writes = writes.len(), otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_batch(&self, writes: Vec<BatchWrite>) -> Result<Vec<UserFromDb>, BatchWriteError> {
+        let columns = "RETURNING id, username, firstname, lastname, status, create_ts, update_ts";
+        let create = format!("INSERT INTO tbl_user(username, password, firstname, lastname) VALUES($1, $2, $3, $4) {}", columns);
+        let update = format!("UPDATE tbl_user SET username=$1, firstname=$2, lastname=$3 WHERE id=$4 {}", columns);
+        let delete = format!("DELETE FROM tbl_user WHERE id=$1 {}", columns);
+        let status = format!("UPDATE tbl_user SET status=$1, status_reason=$2, mfa_failed_attempts=0 WHERE id=$3 AND status=$4 {}", columns);
+
+        let mut tx = self.db.begin().await.map_err(|err| BatchWriteError { index: None, err })?;
+        let mut rows = Vec::new();
+        for (index, write) in writes.into_iter().enumerate() {
+            let query = match write {
+                BatchWrite::Create(user) => {
+                    record_statement(&create);
+                    sqlx::query_as::<_, UserFromDb>(&create)
+                        .bind(user.username)
+                        .bind(user.password)
+                        .bind(user.firstname)
+                        .bind(user.lastname)
+                }
+                BatchWrite::Update(user) => {
+                    record_statement(&update);
+                    sqlx::query_as::<_, UserFromDb>(&update)
+                        .bind(user.username)
+                        .bind(user.firstname)
+                        .bind(user.lastname)
+                        .bind(user.id)
+                }
+                BatchWrite::Delete(id) => {
+                    record_statement(&delete);
+                    sqlx::query_as::<_, UserFromDb>(&delete).bind(id)
+                }
+                BatchWrite::Status { id, from, to, reason } => {
+                    record_statement(&status);
+                    sqlx::query_as::<_, UserFromDb>(&status)
+                        .bind(to)
+                        .bind(reason)
+                        .bind(id)
+                        .bind(from)
+                }
+            };
+
+            // Dropping the transaction on the way out rolls it back.
+            match query.fetch_one(&mut tx).await {
+                Ok(data) => {
+                    rows.push(data);
+                }
+                Err(err) => {
+                    return Err(BatchWriteError { index: Some(index), err });
+                }
+            }
+        }
+
+        tx.commit().await.map_err(|err| BatchWriteError { index: None, err })?;
+        record_rows(rows.len());
+        Ok(rows)
+    }
+
+    #[instrument(
+        name = "user_repo.user_get_mfa_by_username",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_get_mfa_by_username(&self, username: String) -> Result<UserMfa, Error> {
+        let sql = "SELECT id, username, mfa_secret, mfa_enabled FROM tbl_user WHERE username=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserMfa>(sql).bind(username);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_get_mfa_by_id",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_get_mfa_by_id(&self, id: i32) -> Result<UserMfa, Error> {
+        let sql = "SELECT id, username, mfa_secret, mfa_enabled FROM tbl_user WHERE id=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserMfa>(sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_set_secret",
+        skip_all,
+        fields(id = id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_set_secret(&self, id: i32, secret: String) -> Result<(), Error> {
+        let sql = "UPDATE tbl_user SET mfa_secret=$1, mfa_enabled=false, mfa_last_step=NULL WHERE id=$2 RETURNING id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserEmpty>(sql).bind(secret).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_enable",
+        skip(self, code_hashes),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_enable(&self, id: i32, step: i64, code_hashes: Vec<String>) -> Result<(), Error> {
+        let sql = "UPDATE tbl_user SET mfa_enabled=true, mfa_last_step=$1, mfa_failed_attempts=0 WHERE id=$2 RETURNING id";
+        record_statement(sql);
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query_as::<_, UserEmpty>(sql).bind(step).bind(id).fetch_one(&mut tx).await?;
+        sqlx::query("DELETE FROM tbl_mfa_recovery_code WHERE user_id=$1").bind(id).execute(&mut tx).await?;
+        sqlx::query("INSERT INTO tbl_mfa_recovery_code(user_id, code_hash) SELECT $1, UNNEST($2::varchar[])")
+            .bind(id)
+            .bind(&code_hashes)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        record_rows(1);
+        Ok(())
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_use_step",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_use_step(&self, id: i32, step: i64) -> Result<(), Error> {
+        // Conditional so two requests with the same code can't both succeed.
+        let sql = "UPDATE tbl_user SET mfa_last_step=$1, mfa_failed_attempts=0 WHERE id=$2 AND (mfa_last_step IS NULL OR mfa_last_step<$1) RETURNING id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserEmpty>(sql).bind(step).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_use_recovery_code",
+        skip_all,
+        fields(id = id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_use_recovery_code(&self, id: i32, code_hash: String) -> Result<(), Error> {
+        let sql = "WITH used AS (UPDATE tbl_mfa_recovery_code SET used_ts=now() \
+            WHERE user_id=$1 AND code_hash=$2 AND used_ts IS NULL RETURNING id), \
+            reset AS (UPDATE tbl_user SET mfa_failed_attempts=0 WHERE id=$1 AND EXISTS (SELECT 1 FROM used)) \
+            SELECT id FROM used";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserEmpty>(sql).bind(id).bind(code_hash);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_fail",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_fail(&self, id: i32, max_attempts: i32) -> Result<bool, Error> {
+        // The row lock serializes concurrent failures, so exactly one of them
+        // sees the account go from active to locked.
+        let sql = "WITH before AS (SELECT id, status FROM tbl_user WHERE id=$1 FOR UPDATE) \
+            UPDATE tbl_user u SET mfa_failed_attempts=u.mfa_failed_attempts+1, \
+            status=CASE WHEN before.status='active' AND u.mfa_failed_attempts+1>=$2 THEN 'locked' ELSE u.status END, \
+            status_reason=CASE WHEN before.status='active' AND u.mfa_failed_attempts+1>=$2 THEN $3 ELSE u.status_reason END \
+            FROM before WHERE u.id=before.id \
+            RETURNING before.status='active' AND u.status='locked'";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, (bool,)>(sql).bind(id).bind(max_attempts).bind(MFA_LOCK_REASON);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok((locked,)) => {
+                record_rows(1);
+                Ok(locked)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.user_mfa_reset",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn user_mfa_reset(&self, id: i32) -> Result<(), Error> {
+        let sql = "UPDATE tbl_user SET mfa_secret=NULL, mfa_enabled=false, mfa_last_step=NULL, mfa_failed_attempts=0 WHERE id=$1 RETURNING id";
+        record_statement(sql);
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query_as::<_, UserEmpty>(sql).bind(id).fetch_one(&mut tx).await?;
+        sqlx::query("DELETE FROM tbl_mfa_recovery_code WHERE user_id=$1").bind(id).execute(&mut tx).await?;
+
+        tx.commit().await?;
+        record_rows(1);
+        Ok(())
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_challenge_create",
+        skip(self, challenge),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_challenge_create(&self, challenge: String, ceremony: String, user_id: Option<i32>, life_time: u64) -> Result<(), Error> {
+        let sql = "WITH expired AS (DELETE FROM tbl_passkey_challenge WHERE expire_ts<now()) \
+            INSERT INTO tbl_passkey_challenge(challenge, ceremony, user_id, expire_ts) \
+            VALUES($1, $2, $3, now() + $4 * interval '1 second')";
+        record_statement(sql);
+        let query = sqlx::query(sql).bind(challenge).bind(ceremony).bind(user_id).bind(life_time as i64);
+
+        return match query.execute(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_challenge_take",
+        skip(self, challenge),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_challenge_take(&self, challenge: String, ceremony: String) -> Result<Option<i32>, Error> {
+        let sql = "DELETE FROM tbl_passkey_challenge WHERE challenge=$1 AND ceremony=$2 AND expire_ts>=now() RETURNING user_id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, (Option<i32>,)>(sql).bind(challenge).bind(ceremony);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data.0)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_list_by_user(&self, user_id: i32) -> Result<Vec<Passkey>, Error> {
+        let sql = "SELECT id, user_id, credential_id, public_key, sign_count, name FROM tbl_passkey WHERE user_id=$1 ORDER BY id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Passkey>(sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_get_by_credential_id",
+        skip_all,
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_get_by_credential_id(&self, credential_id: Vec<u8>) -> Result<Passkey, Error> {
+        let sql = "SELECT id, user_id, credential_id, public_key, sign_count, name FROM tbl_passkey WHERE credential_id=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Passkey>(sql).bind(credential_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_create",
+        skip_all,
+        fields(user_id = passkey.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_create(&self, passkey: PasskeyCreate) -> Result<Passkey, Error> {
+        let sql = "INSERT INTO tbl_passkey(user_id, credential_id, public_key, sign_count, name) VALUES($1, $2, $3, $4, $5) \
+            RETURNING id, user_id, credential_id, public_key, sign_count, name";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Passkey>(sql)
+            .bind(passkey.user_id)
+            .bind(passkey.credential_id)
+            .bind(passkey.public_key)
+            .bind(passkey.sign_count)
+            .bind(passkey.name);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.passkey_use",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn passkey_use(&self, id: i32, sign_count: i64) -> Result<(), Error> {
+        // Conditional so a replayed or cloned assertion loses the race.
+        let sql = "UPDATE tbl_passkey SET sign_count=$1, last_used_ts=now() \
+            WHERE id=$2 AND (sign_count<$1 OR (sign_count=0 AND $1=0)) RETURNING id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserEmpty>(sql).bind(sign_count).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(_data) => {
+                record_rows(1);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_create",
+        skip_all,
+        fields(user_id = key.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_create(&self, key: ApiKeyCreate) -> Result<ApiKey, Error> {
+        let sql = "INSERT INTO tbl_api_key(user_id, name, prefix, secret_hash, scopes, expire_ts) VALUES($1, $2, $3, $4, $5, $6) \
+            RETURNING id, user_id, name, prefix, secret_hash, scopes, expire_ts, create_ts, last_used_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, ApiKey>(sql)
+            .bind(key.user_id)
+            .bind(key.name)
+            .bind(key.prefix)
+            .bind(key.secret_hash)
+            .bind(key.scopes)
+            .bind(key.expire_ts);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_list_by_user(&self, user_id: i32) -> Result<Vec<ApiKey>, Error> {
+        let sql = "SELECT id, user_id, name, prefix, secret_hash, scopes, expire_ts, create_ts, last_used_ts \
+            FROM tbl_api_key WHERE user_id=$1 ORDER BY id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, ApiKey>(sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_get_by_prefix",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_get_by_prefix(&self, prefix: String) -> Result<ApiKey, Error> {
+        let sql = "SELECT id, user_id, name, prefix, secret_hash, scopes, expire_ts, create_ts, last_used_ts \
+            FROM tbl_api_key WHERE prefix=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, ApiKey>(sql).bind(prefix);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_rotate",
+        skip(self, prefix, secret_hash),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_rotate(&self, user_id: i32, id: i32, prefix: String, secret_hash: String) -> Result<ApiKey, Error> {
+        let sql = "UPDATE tbl_api_key SET prefix=$1, secret_hash=$2, last_used_ts=NULL WHERE id=$3 AND user_id=$4 \
+            RETURNING id, user_id, name, prefix, secret_hash, scopes, expire_ts, create_ts, last_used_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, ApiKey>(sql).bind(prefix).bind(secret_hash).bind(id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_delete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_delete(&self, user_id: i32, id: i32) -> Result<ApiKey, Error> {
+        let sql = "DELETE FROM tbl_api_key WHERE id=$1 AND user_id=$2 \
+            RETURNING id, user_id, name, prefix, secret_hash, scopes, expire_ts, create_ts, last_used_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, ApiKey>(sql).bind(id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.api_key_touch",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn api_key_touch(&self, id: i32) -> Result<(), Error> {
+        // Throttled so a busy job doesn't turn every request into a write.
+        let sql = "UPDATE tbl_api_key SET last_used_ts=now() \
+            WHERE id=$1 AND (last_used_ts IS NULL OR last_used_ts < now() - interval '1 minute')";
+        record_statement(sql);
+        let query = sqlx::query(sql).bind(id);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
                 Ok(())
             }
             Err(err) => {
@@ -180,4 +860,227 @@ impl Repo for UserRepo {
             }
         };
     }
+
+    #[instrument(
+        name = "user_repo.session_create",
+        skip_all,
+        fields(user_id = session.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_create(&self, session: SessionCreate) -> Result<Session, Error> {
+        let sql = "INSERT INTO tbl_session(user_id, user_agent, ip, refresh_token_hash, expire_ts) VALUES($1, $2, $3, $4, $5) \
+            RETURNING id, user_id, user_agent, ip, refresh_token_hash, create_ts, last_seen_ts, expire_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Session>(sql)
+            .bind(session.user_id)
+            .bind(session.user_agent)
+            .bind(session.ip)
+            .bind(session.refresh_token_hash)
+            .bind(session.expire_ts);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.session_list_by_user",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_list_by_user(&self, user_id: i32) -> Result<Vec<Session>, Error> {
+        let sql = "SELECT id, user_id, user_agent, ip, refresh_token_hash, create_ts, last_seen_ts, expire_ts \
+            FROM tbl_session WHERE user_id=$1 AND expire_ts > now() ORDER BY id";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Session>(sql).bind(user_id);
+
+        return match query.fetch_all(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.len());
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.session_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_get(&self, id: i32) -> Result<Session, Error> {
+        let sql = "SELECT id, user_id, user_agent, ip, refresh_token_hash, create_ts, last_seen_ts, expire_ts \
+            FROM tbl_session WHERE id=$1 AND expire_ts > now()";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Session>(sql).bind(id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.session_touch",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_touch(&self, id: i32) -> Result<(), Error> {
+        // Every authenticated request lands here; most of them skip the write.
+        let sql = "UPDATE tbl_session SET last_seen_ts=now() WHERE id=$1 AND last_seen_ts < now() - interval '1 minute'";
+        record_statement(sql);
+        let query = sqlx::query(sql).bind(id);
+
+        return match query.execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.session_delete",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_delete(&self, user_id: i32, id: i32) -> Result<Session, Error> {
+        let sql = "DELETE FROM tbl_session WHERE id=$1 AND user_id=$2 \
+            RETURNING id, user_id, user_agent, ip, refresh_token_hash, create_ts, last_seen_ts, expire_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, Session>(sql).bind(id).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.session_delete_expired",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn session_delete_expired(&self) -> Result<u64, Error> {
+        let sql = "DELETE FROM tbl_session WHERE expire_ts<=now()";
+        record_statement(sql);
+
+        return match sqlx::query(sql).execute(&**self.db).await {
+            Ok(data) => {
+                record_rows(data.rows_affected() as usize);
+                Ok(data.rows_affected())
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.profile_get",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn profile_get(&self, user_id: i32) -> Result<UserProfile, Error> {
+        let sql = "SELECT user_id, phone, locale, timezone, attributes, avatar_key, update_ts \
+            FROM tbl_user_profile WHERE user_id=$1";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserProfile>(sql).bind(user_id);
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.profile_upsert",
+        skip_all,
+        fields(user_id = profile.user_id, otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn profile_upsert(&self, profile: ProfileUpdate) -> Result<UserProfile, Error> {
+        let sql = "INSERT INTO tbl_user_profile (user_id, phone, locale, timezone, attributes) VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (user_id) DO UPDATE SET phone=EXCLUDED.phone, locale=EXCLUDED.locale, \
+            timezone=EXCLUDED.timezone, attributes=EXCLUDED.attributes, update_ts=now() \
+            RETURNING user_id, phone, locale, timezone, attributes, avatar_key, update_ts";
+        record_statement(sql);
+        let query = sqlx::query_as::<_, UserProfile>(sql)
+            .bind(profile.user_id)
+            .bind(profile.phone)
+            .bind(profile.locale)
+            .bind(profile.timezone)
+            .bind(Json(profile.attributes));
+
+        return match query.fetch_one(&**self.db).await {
+            Ok(data) => {
+                record_rows(1);
+                Ok(data)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        };
+    }
+
+    #[instrument(
+        name = "user_repo.profile_set_avatar",
+        skip(self),
+        fields(otel.kind = "client", db.system = "postgresql", db.statement = Empty, db.rows = Empty),
+        err(level = Level::DEBUG),
+    )]
+    async fn profile_set_avatar(&self, user_id: i32, avatar_key: Option<String>) -> Result<Option<String>, Error> {
+        // The row is created first and locked, so concurrent uploads each get
+        // back the key the other one replaced and neither leaves thumbnails
+        // behind.
+        let sql = "UPDATE tbl_user_profile SET avatar_key=$1, update_ts=now() WHERE user_id=$2";
+        record_statement(sql);
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("INSERT INTO tbl_user_profile (user_id) VALUES ($1) ON CONFLICT (user_id) DO NOTHING")
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+        let previous = sqlx::query_as::<_, (Option<String>,)>("SELECT avatar_key FROM tbl_user_profile WHERE user_id=$1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_one(&mut tx)
+            .await?;
+        sqlx::query(sql).bind(avatar_key).bind(user_id).execute(&mut tx).await?;
+
+        tx.commit().await?;
+        record_rows(1);
+        Ok(previous.0)
+    }
 }
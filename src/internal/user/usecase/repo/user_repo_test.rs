@@ -0,0 +1,564 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::web;
+    use chrono::{DateTime, Duration, Utc};
+    use serde_json::json;
+    use tracing::instrument::WithSubscriber;
+    use sqlx::Error;
+
+    use crate::internal::user::entity::api_key::ApiKeyCreate;
+    use crate::internal::user::entity::batch::{BatchWrite, BatchWriteError};
+    use crate::internal::user::entity::session::SessionCreate;
+    use crate::internal::user::entity::profile::empty_profile;
+    use crate::internal::user::entity::passkey::{PasskeyCreate, CEREMONY_LOGIN, CEREMONY_REGISTER};
+    use crate::internal::user::entity::user::{
+        UserChangePasswordRequest, UserCreateRequest, UserStatus, UserUpdateRequest,
+    };
+    use crate::internal::user::usecase::repo::repo::{new_user_repo, UserRepo};
+    use crate::internal::user::usecase::traits::Repo;
+    use crate::pkg::logger::logger::new_logger;
+    use crate::pkg::logger::test_writer::new_test_writer;
+    use crate::pkg::postgres::test_db::new_test_db;
+    use crate::pkg::telemetry::telemetry::{new_json_span_exporter, Telemetry};
+
+    fn user_create_request(username: &str) -> UserCreateRequest {
+        UserCreateRequest {
+            username: username.to_string(),
+            password: "hashed-password".to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+        }
+    }
+
+    async fn raw_timestamps(repo: &UserRepo, id: i32) -> (DateTime<Utc>, DateTime<Utc>) {
+        sqlx::query_as("SELECT create_ts, update_ts FROM tbl_user WHERE id=$1")
+            .bind(id)
+            .fetch_one(&**repo.db)
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_create_and_get_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let before = Utc::now();
+        let created = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let after = Utc::now();
+        assert!(created.id > 0);
+        assert_eq!(created.username, "JamesHolland");
+        assert_eq!(created.firstname, "James");
+        assert_eq!(created.lastname, "Holland");
+
+        let by_username = repo.user_get_by_username("JamesHolland".to_string()).await.unwrap();
+        assert_eq!(by_username.id, created.id);
+
+        let by_id = repo.user_get_by_id(created.id).await.unwrap();
+        assert_eq!(by_id.username, "JamesHolland");
+
+        // The defaults are the current time in UTC, not shifted by any offset.
+        let (create_ts, update_ts) = raw_timestamps(&repo, created.id).await;
+        assert_eq!((by_id.create_ts, by_id.update_ts), (create_ts, update_ts));
+        assert_eq!(create_ts, update_ts);
+        assert!(create_ts >= before - Duration::seconds(1) && create_ts <= after + Duration::seconds(1), "{} not in {}..{}", create_ts, before, after);
+
+        let password = repo.user_get_password_by_username("JamesHolland".to_string()).await.unwrap();
+        assert_eq!(password.password, "hashed-password");
+
+        let password = repo.user_get_password_by_id(created.id).await.unwrap();
+        assert_eq!(password.password, "hashed-password");
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_not_found_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        assert!(matches!(repo.user_get_by_id(1).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_get_by_username("nobody".to_string()).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_get_password_by_id(1).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_get_password_by_username("nobody".to_string()).await, Err(Error::RowNotFound)));
+
+        let update = UserUpdateRequest {
+            id: 1,
+            username: "nobody".to_string(),
+            firstname: "No".to_string(),
+            lastname: "Body".to_string(),
+        };
+        assert!(matches!(repo.user_update_by_id(update).await, Err(Error::RowNotFound)));
+
+        let change = UserChangePasswordRequest {
+            id: 1,
+            old_password: String::new(),
+            new_password: "hashed".to_string(),
+        };
+        assert!(matches!(repo.user_change_password(change).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_delete_by_id(1).await, Err(Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_list_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        assert!(repo.user_list(None).await.unwrap().is_empty());
+
+        let first = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let second = repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+
+        let users = repo.user_list(None).await.unwrap();
+        let ids: Vec<i32> = users.iter().map(|u| u.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_update_by_id_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let created = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let (create_ts, _) = raw_timestamps(&repo, created.id).await;
+
+        let before = Utc::now();
+        let updated = repo.user_update_by_id(UserUpdateRequest {
+            id: created.id,
+            username: "JimHolland".to_string(),
+            firstname: "Jim".to_string(),
+            lastname: "Holland".to_string(),
+        }).await.unwrap();
+
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.username, "JimHolland");
+        assert_eq!(updated.firstname, "Jim");
+        assert_eq!(updated.create_ts, create_ts);
+        assert!(updated.update_ts > create_ts);
+        assert!(updated.update_ts >= before - Duration::seconds(1) && updated.update_ts <= Utc::now() + Duration::seconds(1));
+
+        // MFA bookkeeping is not an account change.
+        repo.user_mfa_use_step(created.id, 1).await.unwrap();
+        assert_eq!(raw_timestamps(&repo, created.id).await.1, updated.update_ts);
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_change_password_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let created = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        repo.user_change_password(UserChangePasswordRequest {
+            id: created.id,
+            old_password: String::new(),
+            new_password: "new-hashed-password".to_string(),
+        }).await.unwrap();
+
+        let password = repo.user_get_password_by_id(created.id).await.unwrap();
+        assert_eq!(password.password, "new-hashed-password");
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_delete_by_id_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let first = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let second = repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+        repo.user_delete_by_id(first.id).await.unwrap();
+
+        assert!(matches!(repo.user_get_by_id(first.id).await, Err(Error::RowNotFound)));
+        let ids: Vec<i32> = repo.user_list(None).await.unwrap().iter().map(|u| u.id).collect();
+        assert_eq!(ids, vec![second.id]);
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_status_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let first = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let second = repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+        assert_eq!(first.status, UserStatus::Active);
+
+        let disabled = repo.user_set_status(second.id, UserStatus::Active, UserStatus::Disabled, Some("left".to_string())).await.unwrap();
+        assert_eq!(disabled.status, UserStatus::Disabled);
+        assert_eq!(repo.user_get_by_id(second.id).await.unwrap().status, UserStatus::Disabled);
+        assert_eq!(repo.user_get_by_username("JohnSmith".to_string()).await.unwrap().status, UserStatus::Disabled);
+        let reason: Option<String> = sqlx::query_scalar("SELECT status_reason FROM tbl_user WHERE id=$1")
+            .bind(second.id).fetch_one(&**repo.db).await.unwrap();
+        assert_eq!(reason.as_deref(), Some("left"));
+
+        // Conditional on the current status.
+        let res = repo.user_set_status(second.id, UserStatus::Active, UserStatus::Locked, None).await;
+        assert!(matches!(res, Err(Error::RowNotFound)));
+
+        let filters = vec![
+            (None, vec![first.id, second.id]),
+            (Some(UserStatus::Active), vec![first.id]),
+            (Some(UserStatus::Disabled), vec![second.id]),
+            (Some(UserStatus::Pending), vec![]),
+        ];
+
+        for (status, expected) in filters {
+            let ids: Vec<i32> = repo.user_list(status).await.unwrap().iter().map(|u| u.id).collect();
+            assert_eq!(ids, expected, "{:?}", status);
+        }
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_list_page_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+
+        let mut ids = Vec::new();
+        for username in ["JamesHolland", "JohnSmith", "AnnaSmith", "MaryJones"] {
+            ids.push(repo.user_create(user_create_request(username)).await.unwrap().id);
+        }
+        repo.user_set_status(ids[2], UserStatus::Active, UserStatus::Locked, None).await.unwrap();
+
+        let pages = vec![
+            ((None, 0, 2), vec![ids[0], ids[1]]),
+            ((None, ids[1], 2), vec![ids[2], ids[3]]),
+            ((None, ids[3], 2), vec![]),
+            ((Some(UserStatus::Active), ids[0], 5), vec![ids[1], ids[3]]),
+            ((Some(UserStatus::Locked), 0, 5), vec![ids[2]]),
+        ];
+
+        for ((status, after_id, limit), expected) in pages {
+            let page: Vec<i32> = repo.user_list_page(status, after_id, limit).await.unwrap().iter().map(|u| u.id).collect();
+            assert_eq!(page, expected, "{:?} {} {}", status, after_id, limit);
+        }
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_batch_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let james = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        let john = repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+        let anna = repo.user_create(user_create_request("AnnaSmith")).await.unwrap();
+
+        let rename = |id: i32, username: &str| BatchWrite::Update(UserUpdateRequest {
+            id,
+            username: username.to_string(),
+            firstname: "James".to_string(),
+            lastname: "Holland".to_string(),
+        });
+        let lock = |id: i32| BatchWrite::Status { id, from: UserStatus::Active, to: UserStatus::Locked, reason: None };
+
+        // A failing write rolls back the ones before it.
+        let res = repo.user_batch(vec![rename(james.id, "JamesJones"), lock(anna.id), BatchWrite::Delete(99)]).await;
+        assert!(matches!(res, Err(BatchWriteError { index: Some(2), err: Error::RowNotFound })));
+        let res = repo.user_batch(vec![rename(james.id, "JamesJones"), lock(anna.id), lock(anna.id)]).await;
+        assert!(matches!(res, Err(BatchWriteError { index: Some(2), err: Error::RowNotFound })));
+        assert_eq!(repo.user_get_by_id(james.id).await.unwrap().username, "JamesHolland");
+        assert_eq!(repo.user_get_by_id(anna.id).await.unwrap().status, UserStatus::Active);
+
+        let rows = repo.user_batch(vec![
+            BatchWrite::Create(user_create_request("MaryJones")),
+            rename(james.id, "JamesJones"),
+            BatchWrite::Delete(john.id),
+            lock(anna.id),
+        ]).await.unwrap();
+        let rows: Vec<(&str, UserStatus)> = rows.iter().map(|u| (u.username.as_str(), u.status)).collect();
+        assert_eq!(rows, vec![
+            ("MaryJones", UserStatus::Active),
+            ("JamesJones", UserStatus::Active),
+            ("JohnSmith", UserStatus::Active),
+            ("AnnaSmith", UserStatus::Locked),
+        ]);
+        assert!(matches!(repo.user_get_by_id(john.id).await, Err(Error::RowNotFound)));
+
+        // Status changes only apply to the status they were checked against.
+        let res = repo.user_batch(vec![lock(anna.id)]).await;
+        assert!(matches!(res, Err(BatchWriteError { index: Some(0), err: Error::RowNotFound })));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn user_mfa_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+
+        let mfa = repo.user_get_mfa_by_username("JamesHolland".to_string()).await.unwrap();
+        assert_eq!((mfa.id, mfa.mfa_secret, mfa.mfa_enabled), (user.id, None, false));
+
+        repo.user_mfa_set_secret(user.id, "SECRET".to_string()).await.unwrap();
+        repo.user_mfa_enable(user.id, 100, vec!["hash-a".to_string(), "hash-b".to_string()]).await.unwrap();
+        let mfa = repo.user_get_mfa_by_id(user.id).await.unwrap();
+        assert_eq!((mfa.mfa_secret.as_deref(), mfa.mfa_enabled), (Some("SECRET"), true));
+
+        // Steps only move forward and recovery codes are single use.
+        assert!(matches!(repo.user_mfa_use_step(user.id, 100).await, Err(Error::RowNotFound)));
+        repo.user_mfa_use_step(user.id, 101).await.unwrap();
+        assert!(matches!(repo.user_mfa_use_step(user.id, 101).await, Err(Error::RowNotFound)));
+        assert!(!repo.user_mfa_fail(user.id, 3).await.unwrap());
+        repo.user_mfa_use_recovery_code(user.id, "hash-a".to_string()).await.unwrap();
+        assert!(matches!(repo.user_mfa_use_recovery_code(user.id, "hash-a".to_string()).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_mfa_use_recovery_code(user.id, "hash-c".to_string()).await, Err(Error::RowNotFound)));
+        let (attempts,): (i32,) = sqlx::query_as("SELECT mfa_failed_attempts FROM tbl_user WHERE id=$1").bind(user.id).fetch_one(&test_db.db).await.unwrap();
+        assert_eq!(attempts, 0);
+
+        // Only the failure reaching the limit reports the lock; unlocking
+        // starts the count over.
+        assert!(!repo.user_mfa_fail(user.id, 2).await.unwrap());
+        assert!(repo.user_mfa_fail(user.id, 2).await.unwrap());
+        assert!(!repo.user_mfa_fail(user.id, 2).await.unwrap());
+        assert_eq!(repo.user_get_by_id(user.id).await.unwrap().status, UserStatus::Locked);
+        repo.user_set_status(user.id, UserStatus::Locked, UserStatus::Active, None).await.unwrap();
+        assert!(!repo.user_mfa_fail(user.id, 2).await.unwrap());
+        assert!(repo.user_mfa_fail(user.id, 2).await.unwrap());
+        repo.user_batch(vec![BatchWrite::Status { id: user.id, from: UserStatus::Locked, to: UserStatus::Active, reason: None }]).await.unwrap();
+        assert!(!repo.user_mfa_fail(user.id, 2).await.unwrap());
+        assert_eq!(repo.user_get_by_id(user.id).await.unwrap().status, UserStatus::Active);
+        assert!(matches!(repo.user_mfa_fail(99, 2).await, Err(Error::RowNotFound)));
+
+        repo.user_mfa_reset(user.id).await.unwrap();
+        let mfa = repo.user_get_mfa_by_id(user.id).await.unwrap();
+        assert_eq!((mfa.mfa_secret, mfa.mfa_enabled), (None, false));
+        assert!(matches!(repo.user_mfa_use_recovery_code(user.id, "hash-b".to_string()).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.user_mfa_reset(99).await, Err(Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn passkey_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+
+        // Challenges are single use, bound to their ceremony and expire.
+        repo.passkey_challenge_create("a".to_string(), CEREMONY_REGISTER.to_string(), Some(user.id), 300).await.unwrap();
+        repo.passkey_challenge_create("b".to_string(), CEREMONY_LOGIN.to_string(), None, 300).await.unwrap();
+        assert!(matches!(repo.passkey_challenge_take("a".to_string(), CEREMONY_LOGIN.to_string()).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.passkey_challenge_take("a".to_string(), CEREMONY_REGISTER.to_string()).await.unwrap(), Some(user.id));
+        assert!(matches!(repo.passkey_challenge_take("a".to_string(), CEREMONY_REGISTER.to_string()).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.passkey_challenge_take("b".to_string(), CEREMONY_LOGIN.to_string()).await.unwrap(), None);
+        sqlx::query("INSERT INTO tbl_passkey_challenge(challenge, ceremony, expire_ts) VALUES('c', 'login', now() - interval '1 second')")
+            .execute(&test_db.db)
+            .await
+            .unwrap();
+        assert!(matches!(repo.passkey_challenge_take("c".to_string(), CEREMONY_LOGIN.to_string()).await, Err(Error::RowNotFound)));
+
+        let create = |credential_id: Vec<u8>| PasskeyCreate {
+            user_id: user.id,
+            credential_id,
+            public_key: vec![4, 1, 2],
+            sign_count: 0,
+            name: "Laptop".to_string(),
+        };
+        let passkey = repo.passkey_create(create(vec![1, 2, 3])).await.unwrap();
+        assert!(repo.passkey_create(create(vec![1, 2, 3])).await.is_err());
+        assert_eq!(repo.passkey_get_by_credential_id(vec![1, 2, 3]).await.unwrap().id, passkey.id);
+        assert!(matches!(repo.passkey_get_by_credential_id(vec![9]).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.passkey_list_by_user(user.id).await.unwrap().len(), 1);
+
+        // The counter may stay at zero but otherwise has to grow.
+        repo.passkey_use(passkey.id, 0).await.unwrap();
+        repo.passkey_use(passkey.id, 5).await.unwrap();
+        assert!(matches!(repo.passkey_use(passkey.id, 5).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.passkey_use(passkey.id, 0).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.passkey_get_by_credential_id(vec![1, 2, 3]).await.unwrap().sign_count, 5);
+
+        repo.user_delete_by_id(user.id).await.unwrap();
+        assert!(repo.passkey_list_by_user(user.id).await.unwrap().is_empty());
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn api_key_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+
+        let create = |prefix: &str| ApiKeyCreate {
+            user_id: user.id,
+            name: "batch".to_string(),
+            prefix: prefix.to_string(),
+            secret_hash: "hash".to_string(),
+            scopes: vec!["user:read".to_string()],
+            expire_ts: None,
+        };
+        let key = repo.api_key_create(create("aaaaaaaaaaaa")).await.unwrap();
+        assert_eq!((key.scopes.clone(), key.expire_ts, key.last_used_ts), (vec!["user:read".to_string()], None, None));
+        assert!(repo.api_key_create(create("aaaaaaaaaaaa")).await.is_err());
+        assert_eq!(repo.api_key_get_by_prefix("aaaaaaaaaaaa".to_string()).await.unwrap(), key);
+        assert!(matches!(repo.api_key_get_by_prefix("bbbbbbbbbbbb".to_string()).await, Err(Error::RowNotFound)));
+
+        // Use is recorded at most once a minute.
+        repo.api_key_touch(key.id).await.unwrap();
+        let used = repo.api_key_get_by_prefix("aaaaaaaaaaaa".to_string()).await.unwrap().last_used_ts;
+        assert!(used.is_some());
+        repo.api_key_touch(key.id).await.unwrap();
+        assert_eq!(repo.api_key_get_by_prefix("aaaaaaaaaaaa".to_string()).await.unwrap().last_used_ts, used);
+
+        let rotated = repo.api_key_rotate(user.id, key.id, "cccccccccccc".to_string(), "other".to_string()).await.unwrap();
+        assert_eq!((rotated.id, rotated.prefix.as_str(), rotated.secret_hash.as_str(), rotated.last_used_ts), (key.id, "cccccccccccc", "other", None));
+        assert!(matches!(repo.api_key_get_by_prefix("aaaaaaaaaaaa".to_string()).await, Err(Error::RowNotFound)));
+        assert!(matches!(repo.api_key_rotate(user.id + 1, key.id, "dddddddddddd".to_string(), "x".to_string()).await, Err(Error::RowNotFound)));
+
+        assert!(matches!(repo.api_key_delete(user.id + 1, key.id).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.api_key_delete(user.id, key.id).await.unwrap().prefix, "cccccccccccc");
+        assert!(repo.api_key_list_by_user(user.id).await.unwrap().is_empty());
+
+        // Keys go with their account.
+        repo.api_key_create(create("eeeeeeeeeeee")).await.unwrap();
+        repo.user_delete_by_id(user.id).await.unwrap();
+        assert!(matches!(repo.api_key_get_by_prefix("eeeeeeeeeeee".to_string()).await, Err(Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn session_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+
+        let create = |expire_ts| SessionCreate {
+            user_id: user.id,
+            user_agent: Some("Firefox".to_string()),
+            ip: Some("10.0.0.1".to_string()),
+            refresh_token_hash: "hash".to_string(),
+            expire_ts,
+        };
+        let session = repo.session_create(create(chrono::Utc::now() + chrono::Duration::minutes(5))).await.unwrap();
+        assert_eq!((session.user_agent.as_deref(), session.ip.as_deref()), (Some("Firefox"), Some("10.0.0.1")));
+        assert_eq!(repo.session_get(session.id).await.unwrap(), session);
+
+        // Expired sessions are neither listed nor accepted.
+        let expired = repo.session_create(create(chrono::Utc::now() - chrono::Duration::seconds(1))).await.unwrap();
+        assert!(matches!(repo.session_get(expired.id).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.session_list_by_user(user.id).await.unwrap(), vec![session.clone()]);
+
+        // Activity is recorded at most once a minute.
+        repo.session_touch(session.id).await.unwrap();
+        assert_eq!(repo.session_get(session.id).await.unwrap().last_seen_ts, session.last_seen_ts);
+        sqlx::query("UPDATE tbl_session SET last_seen_ts = now() - interval '2 minutes' WHERE id=$1")
+            .bind(session.id)
+            .execute(&test_db.db)
+            .await
+            .unwrap();
+        repo.session_touch(session.id).await.unwrap();
+        assert!(repo.session_get(session.id).await.unwrap().last_seen_ts >= session.last_seen_ts);
+
+        assert!(matches!(repo.session_delete(user.id + 1, session.id).await, Err(Error::RowNotFound)));
+        assert_eq!(repo.session_delete(user.id, session.id).await.unwrap().id, session.id);
+        assert!(matches!(repo.session_get(session.id).await, Err(Error::RowNotFound)));
+
+        // Only expired sessions are purged.
+        let session = repo.session_create(create(chrono::Utc::now() + chrono::Duration::minutes(5))).await.unwrap();
+        assert_eq!(repo.session_delete_expired().await.unwrap(), 1);
+        assert_eq!(repo.session_delete_expired().await.unwrap(), 0);
+        assert_eq!(repo.session_get(session.id).await.unwrap().id, session.id);
+        repo.session_delete(user.id, session.id).await.unwrap();
+
+        // Sessions go with their account.
+        let session = repo.session_create(create(chrono::Utc::now() + chrono::Duration::minutes(5))).await.unwrap();
+        repo.user_delete_by_id(user.id).await.unwrap();
+        assert!(matches!(repo.session_get(session.id).await, Err(Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn profile_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        let user = repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+
+        assert!(matches!(repo.profile_get(user.id).await, Err(Error::RowNotFound)));
+
+        let mut update = empty_profile(user.id);
+        update.phone = Some("+4915112345678".to_string());
+        update.attributes = json!({"department": "R&D", "floor": 3}).as_object().unwrap().clone();
+        let created = repo.profile_upsert(update.clone()).await.unwrap();
+        assert_eq!((created.phone.as_deref(), created.avatar_key.as_deref()), (Some("+4915112345678"), None));
+        assert_eq!(created.attributes.0, update.attributes);
+        assert_eq!(repo.profile_get(user.id).await.unwrap(), created);
+
+        // Setting the avatar returns the one it replaced and keeps the fields.
+        assert_eq!(repo.profile_set_avatar(user.id, Some("avatars/1/a".to_string())).await.unwrap(), None);
+        assert_eq!(repo.profile_set_avatar(user.id, Some("avatars/1/b".to_string())).await.unwrap(), Some("avatars/1/a".to_string()));
+        let res = repo.profile_get(user.id).await.unwrap();
+        assert_eq!((res.phone.as_deref(), res.avatar_key.as_deref()), (Some("+4915112345678"), Some("avatars/1/b")));
+
+        // Replacing the fields keeps the avatar.
+        update.phone = None;
+        let res = repo.profile_upsert(update).await.unwrap();
+        assert_eq!((res.phone, res.avatar_key.as_deref()), (None, Some("avatars/1/b")));
+        assert!(res.update_ts >= created.update_ts);
+        assert_eq!(repo.profile_set_avatar(user.id, None).await.unwrap(), Some("avatars/1/b".to_string()));
+
+        // A profile can start with an avatar, and goes with its account.
+        let other = repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+        assert_eq!(repo.profile_set_avatar(other.id, Some("avatars/2/a".to_string())).await.unwrap(), None);
+        assert!(repo.profile_get(other.id).await.unwrap().attributes.0.is_empty());
+        repo.user_delete_by_id(other.id).await.unwrap();
+        assert!(matches!(repo.profile_get(other.id).await, Err(Error::RowNotFound)));
+
+        test_db.close().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn repo_span_test() {
+        let test_db = new_test_db().await;
+        let repo = new_user_repo(web::Data::new(test_db.db.clone()));
+        repo.user_create(user_create_request("JamesHolland")).await.unwrap();
+        repo.user_create(user_create_request("JohnSmith")).await.unwrap();
+
+        let spans = new_test_writer();
+        let telemetry = Telemetry::with_exporter(new_json_span_exporter(spans.clone()));
+        let logger = new_logger("info", new_test_writer(), telemetry.tracer()).unwrap();
+        repo.user_list(None).with_subscriber(logger.dispatch().clone()).await.unwrap();
+        let _ = repo.user_get_by_id(99).with_subscriber(logger.dispatch().clone()).await;
+
+        let spans = spans.lines();
+        let list = spans.iter().find(|s| s["name"] == json!("user_repo.user_list")).unwrap();
+        assert_eq!(list["kind"], json!("client"));
+        assert_eq!(list["attributes"]["db.system"], json!("postgresql"));
+        assert!(list["attributes"]["db.statement"].as_str().unwrap().starts_with("SELECT id, username"));
+        assert_eq!(list["attributes"]["db.rows"], json!("2"));
+
+        let get = spans.iter().find(|s| s["name"] == json!("user_repo.user_get_by_id")).unwrap();
+        assert!(get["attributes"].get("db.rows").is_none());
+        assert_eq!(get["status"], json!("error"));
+        assert!(get["events"][0]["attributes"]["exception.message"].as_str().unwrap().contains("no rows returned"));
+
+        test_db.close().await;
+    }
+}
@@ -1,42 +1,211 @@
+use std::sync::Arc;
 use async_trait::async_trait;
+use serde_json::Value;
 
 use crate::internal::user::entity::user::{
-    UserAuthRequest, UserAuthResponse, UserChangePasswordRequest, UserCreateRequest, UserFromDb,
-    UserGet, UserGetPassword, UserGetResponse, UserUpdateRequest, UserUpdateResponse
+    UserAuthRequest, UserAuthResponse, UserAuthResult, UserChangePasswordRequest, UserCreateRequest,
+    UserFromDb, UserGet, UserGetPassword, UserGetResponse, UserMeUpdateRequest, UserStatus, UserStatusRequest,
+    UserUpdateRequest, UserUpdateResponse,
 };
-use crate::internal::user::usecase::repo::repo::UserRepo;
+use crate::internal::user::entity::mfa::{
+    MfaConfirmRequest, MfaConfirmResponse, MfaEnrollResponse, MfaVerifyRequest, UserMfa,
+};
+use crate::internal::user::entity::passkey::{
+    Passkey, PasskeyCreate, PasskeyLoginBeginRequest, PasskeyLoginOptions, PasskeyLoginRequest,
+    PasskeyRegisterOptions, PasskeyRegisterRequest, PasskeyResponse, WebAuthnConfig,
+};
+use crate::internal::user::entity::api_key::{ApiKey, ApiKeyCreate, ApiKeyCreateRequest, ApiKeyResponse, ApiKeySecretResponse};
+use crate::internal::user::entity::session::{Session, SessionCreate, SessionResponse};
+use crate::internal::user::entity::profile::{ProfileConfig, ProfileResponse, ProfileUpdate, ProfileUpdateRequest, UserProfile};
+use crate::internal::user::entity::token::TokenConfig;
+use crate::internal::user::entity::bulk::{ImportOptions, ImportRowStatus};
+use crate::internal::user::entity::batch::{BatchRequest, BatchWrite, BatchWriteError};
+use crate::internal::audit::entity::audit::AuditContext;
+use crate::internal::audit::usecase::traits::AuditUseCase;
+use crate::internal::org::usecase::traits::Repo as OrgRepo;
+use crate::internal::oidc::usecase::traits::Repo as OidcRepo;
 use crate::internal::controller::response;
+use crate::pkg::metrics::metrics::Metrics;
 
 #[derive(Clone)]
 pub struct UserUseCase {
-    pub repo: UserRepo
+    pub repo: Arc<dyn Repo>,
+    pub audit: AuditUseCase,
+    pub token: TokenConfig,
+    pub metrics: Metrics,
+    pub admins: Vec<i32>, // ids of the accounts allowed to call the admin endpoints
+    pub mfa_issuer: String,
+    pub webauthn: WebAuthnConfig,
+    pub profile: ProfileConfig,
+    pub blobs: Arc<dyn BlobStore>,
+    pub orgs: Arc<dyn OrgRepo>, // memberships for the access token claims
+    pub oidc: Arc<dyn OidcRepo>, // refresh tokens revoked when an account stops being active
 }
 
-pub fn new_user_use_case(repo: UserRepo) -> UserUseCase {
+#[allow(clippy::too_many_arguments)]
+pub fn new_user_use_case(
+    repo: Arc<dyn Repo>,
+    audit: AuditUseCase,
+    token: TokenConfig,
+    metrics: Metrics,
+    admins: Vec<i32>,
+    mfa_issuer: String,
+    webauthn: WebAuthnConfig,
+    profile: ProfileConfig,
+    blobs: Arc<dyn BlobStore>,
+    orgs: Arc<dyn OrgRepo>,
+    oidc: Arc<dyn OidcRepo>,
+) -> UserUseCase {
     UserUseCase {
-        repo
+        repo,
+        audit,
+        token,
+        metrics,
+        admins,
+        mfa_issuer,
+        webauthn,
+        profile,
+        blobs,
+        orgs,
+        oidc,
     }
 }
 
 #[async_trait]
 pub trait UseCase {
-    async fn user_auth(&self, user: UserAuthRequest) -> Result<UserAuthResponse, response::ErrorResponseUseCase>;
-    async fn user_create(&self, user: UserCreateRequest) -> Result<UserGet, response::ErrorResponseUseCase>;
+    async fn user_auth(&self, ctx: AuditContext, user: UserAuthRequest) -> Result<UserAuthResult, response::ErrorResponseUseCase>;
+    async fn user_create(&self, ctx: AuditContext, user: UserCreateRequest) -> Result<UserGet, response::ErrorResponseUseCase>;
     async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, response::ErrorResponseUseCase>;
     async fn user_get_by_username(&self, username: String) -> Result<UserGet, response::ErrorResponseUseCase>;
-    async fn user_list(&self) -> Result<Vec<UserGetResponse>, response::ErrorResponseUseCase>;
-    async fn user_update_by_id(&self, user: UserUpdateRequest) -> Result<UserUpdateResponse, response::ErrorResponseUseCase>;
-    async fn user_change_password(&self, user: UserChangePasswordRequest) -> Result<(), response::ErrorResponseUseCase>;
+    async fn user_list(&self, status: Option<UserStatus>) -> Result<Vec<UserGetResponse>, response::ErrorResponseUseCase>;
+    // One verified row of an import: creates the user or, by the conflict
+    // policy, skips or updates the account with its username. A dry run only
+    // tells which.
+    async fn user_import_row(&self, ctx: AuditContext, user: UserCreateRequest, options: ImportOptions) -> Result<ImportRowStatus, response::ErrorResponseUseCase>;
+    // A page of user_list for exports, see Repo::user_list_page.
+    async fn user_list_page(&self, status: Option<UserStatus>, after_id: i32, limit: i64) -> Result<Vec<UserGetResponse>, response::ErrorResponseUseCase>;
+    async fn user_get_me(&self, username: String) -> Result<UserGetResponse, response::ErrorResponseUseCase>;
+    async fn user_update_me(&self, ctx: AuditContext, username: String, req: UserMeUpdateRequest) -> Result<UserGetResponse, response::ErrorResponseUseCase>;
+    async fn user_update_by_id(&self, ctx: AuditContext, user: UserUpdateRequest) -> Result<UserUpdateResponse, response::ErrorResponseUseCase>;
+    async fn user_change_password(&self, ctx: AuditContext, user: UserChangePasswordRequest) -> Result<(), response::ErrorResponseUseCase>;
+    async fn user_delete_by_id(&self, ctx: AuditContext, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+    // Applies the operations of a verified batch, see verify_batch_request.
+    // Each result is what the single route for the operation would return.
+    async fn user_batch(&self, ctx: AuditContext, req: BatchRequest) -> Vec<Result<Value, response::ErrorResponseUseCase>>;
+    // Applies an activate, disable, lock or unlock action; 409 when the
+    // current status does not allow it.
+    async fn user_set_status(&self, ctx: AuditContext, id: i32, req: UserStatusRequest) -> Result<UserGetResponse, response::ErrorResponseUseCase>;
+    async fn user_mfa_enroll(&self, username: String) -> Result<MfaEnrollResponse, response::ErrorResponseUseCase>;
+    async fn user_mfa_confirm(&self, ctx: AuditContext, username: String, req: MfaConfirmRequest) -> Result<MfaConfirmResponse, response::ErrorResponseUseCase>;
+    async fn user_mfa_verify(&self, ctx: AuditContext, req: MfaVerifyRequest) -> Result<UserAuthResponse, response::ErrorResponseUseCase>;
+    async fn user_mfa_reset(&self, ctx: AuditContext, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+    async fn user_passkey_register_begin(&self, username: String) -> Result<PasskeyRegisterOptions, response::ErrorResponseUseCase>;
+    async fn user_passkey_register_finish(&self, ctx: AuditContext, username: String, req: PasskeyRegisterRequest) -> Result<PasskeyResponse, response::ErrorResponseUseCase>;
+    async fn user_passkey_login_begin(&self, req: PasskeyLoginBeginRequest) -> Result<PasskeyLoginOptions, response::ErrorResponseUseCase>;
+    async fn user_passkey_login_finish(&self, ctx: AuditContext, req: PasskeyLoginRequest) -> Result<UserAuthResponse, response::ErrorResponseUseCase>;
+    async fn user_api_key_create(&self, ctx: AuditContext, username: String, req: ApiKeyCreateRequest) -> Result<ApiKeySecretResponse, response::ErrorResponseUseCase>;
+    async fn user_api_key_list(&self, username: String) -> Result<Vec<ApiKeyResponse>, response::ErrorResponseUseCase>;
+    // Replaces the key's secret; the old key stops working at once.
+    async fn user_api_key_rotate(&self, ctx: AuditContext, username: String, id: i32) -> Result<ApiKeySecretResponse, response::ErrorResponseUseCase>;
+    async fn user_api_key_revoke(&self, ctx: AuditContext, username: String, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+    // The owner's username and the key, for a known, unexpired key.
+    async fn user_api_key_authenticate(&self, api_key: &str) -> Option<(String, ApiKey)>;
+    // The user's active sessions; current marks the one of the caller's token.
+    async fn user_session_list(&self, user_id: i32, current: Option<i32>) -> Result<Vec<SessionResponse>, response::ErrorResponseUseCase>;
+    async fn user_session_revoke(&self, ctx: AuditContext, user_id: i32, id: i32) -> Result<(), response::ErrorResponseUseCase>;
+    // The id and current username of an access token's account while the
+    // token may still be used: the account exists and is active, and the
+    // session, if it has one, still exists and is the account's. Records the use.
+    async fn user_token_owner(&self, user_id: Option<i32>, username: &str, session_id: Option<i32>) -> Option<(i32, String)>;
+    async fn user_profile_get(&self, username: String) -> Result<ProfileResponse, response::ErrorResponseUseCase>;
+    // Merges the request into the profile; 400 when the attributes don't
+    // match the schema.
+    async fn user_profile_update(&self, ctx: AuditContext, username: String, req: ProfileUpdateRequest) -> Result<ProfileResponse, response::ErrorResponseUseCase>;
+    // Replaces the avatar with thumbnails of the uploaded image; 415 for
+    // anything but PNG, JPEG or WebP.
+    async fn user_avatar_set(&self, ctx: AuditContext, username: String, data: Vec<u8>) -> Result<ProfileResponse, response::ErrorResponseUseCase>;
+    async fn user_avatar_delete(&self, ctx: AuditContext, username: String) -> Result<(), response::ErrorResponseUseCase>;
+    // The PNG thumbnail and its version, for ETags.
+    async fn user_avatar_get(&self, id: i32, size: u32) -> Result<(String, Vec<u8>), response::ErrorResponseUseCase>;
 }
 
 #[async_trait]
-pub trait Repo {
+pub trait Repo: Send + Sync {
     async fn user_get_password_by_username(&self, username: String) -> Result<UserGetPassword, sqlx::Error>;
     async fn user_get_password_by_id(&self, id: i32) -> Result<UserGetPassword, sqlx::Error>;
     async fn user_create(&self, user: UserCreateRequest) -> Result<UserGet, sqlx::Error>;
     async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, sqlx::Error>;
     async fn user_get_by_username(&self, username: String) -> Result<UserGet, sqlx::Error>;
-    async fn user_list(&self) -> Result<Vec<UserGetResponse>, sqlx::Error>;
+    async fn user_list(&self, status: Option<UserStatus>) -> Result<Vec<UserGetResponse>, sqlx::Error>;
+    // Up to limit users with an id above after_id, ordered by id.
+    async fn user_list_page(&self, status: Option<UserStatus>, after_id: i32, limit: i64) -> Result<Vec<UserGetResponse>, sqlx::Error>;
     async fn user_update_by_id(&self, user: UserUpdateRequest) -> Result<UserFromDb, sqlx::Error>;
+    // RowNotFound when the user is gone or no longer has status from.
+    async fn user_set_status(&self, id: i32, from: UserStatus, to: UserStatus, reason: Option<String>) -> Result<UserFromDb, sqlx::Error>;
     async fn user_change_password(&self, req: UserChangePasswordRequest) -> Result<(), sqlx::Error>;
+    async fn user_delete_by_id(&self, id: i32) -> Result<(), sqlx::Error>;
+    // Applies the writes in order in one transaction and returns the rows they
+    // wrote, or deleted; the first failing write rolls back all of them.
+    async fn user_batch(&self, writes: Vec<BatchWrite>) -> Result<Vec<UserFromDb>, BatchWriteError>;
+    async fn user_get_mfa_by_username(&self, username: String) -> Result<UserMfa, sqlx::Error>;
+    async fn user_get_mfa_by_id(&self, id: i32) -> Result<UserMfa, sqlx::Error>;
+    // Stores a pending secret; MFA stays disabled until user_mfa_enable.
+    async fn user_mfa_set_secret(&self, id: i32, secret: String) -> Result<(), sqlx::Error>;
+    // Enables MFA and replaces the recovery codes, in one transaction.
+    async fn user_mfa_enable(&self, id: i32, step: i64, code_hashes: Vec<String>) -> Result<(), sqlx::Error>;
+    // RowNotFound when step is not newer than the last accepted one. Both
+    // use_ methods clear the failed attempts on success.
+    async fn user_mfa_use_step(&self, id: i32, step: i64) -> Result<(), sqlx::Error>;
+    // RowNotFound when no unused code has this hash.
+    async fn user_mfa_use_recovery_code(&self, id: i32, code_hash: String) -> Result<(), sqlx::Error>;
+    // Counts a wrong code and locks an active account once max_attempts are
+    // reached; true when this call locked it.
+    async fn user_mfa_fail(&self, id: i32, max_attempts: i32) -> Result<bool, sqlx::Error>;
+    async fn user_mfa_reset(&self, id: i32) -> Result<(), sqlx::Error>;
+    // Also purges expired challenges.
+    async fn passkey_challenge_create(&self, challenge: String, ceremony: String, user_id: Option<i32>, life_time: u64) -> Result<(), sqlx::Error>;
+    // Deletes the challenge and returns its user_id; RowNotFound when it is
+    // unknown, expired or for another ceremony.
+    async fn passkey_challenge_take(&self, challenge: String, ceremony: String) -> Result<Option<i32>, sqlx::Error>;
+    async fn passkey_list_by_user(&self, user_id: i32) -> Result<Vec<Passkey>, sqlx::Error>;
+    async fn passkey_get_by_credential_id(&self, credential_id: Vec<u8>) -> Result<Passkey, sqlx::Error>;
+    async fn passkey_create(&self, passkey: PasskeyCreate) -> Result<Passkey, sqlx::Error>;
+    // RowNotFound when sign_count did not advance (see sign_count_advanced).
+    async fn passkey_use(&self, id: i32, sign_count: i64) -> Result<(), sqlx::Error>;
+    async fn api_key_create(&self, key: ApiKeyCreate) -> Result<ApiKey, sqlx::Error>;
+    async fn api_key_list_by_user(&self, user_id: i32) -> Result<Vec<ApiKey>, sqlx::Error>;
+    async fn api_key_get_by_prefix(&self, prefix: String) -> Result<ApiKey, sqlx::Error>;
+    // RowNotFound when the user has no such key.
+    async fn api_key_rotate(&self, user_id: i32, id: i32, prefix: String, secret_hash: String) -> Result<ApiKey, sqlx::Error>;
+    // Deletes the user's key and returns it; RowNotFound when the user has no such key.
+    async fn api_key_delete(&self, user_id: i32, id: i32) -> Result<ApiKey, sqlx::Error>;
+    // Records a use; skipped when the last one was under a minute ago.
+    async fn api_key_touch(&self, id: i32) -> Result<(), sqlx::Error>;
+    async fn session_create(&self, session: SessionCreate) -> Result<Session, sqlx::Error>;
+    // Unexpired sessions only.
+    async fn session_list_by_user(&self, user_id: i32) -> Result<Vec<Session>, sqlx::Error>;
+    // RowNotFound when the session was revoked or has expired.
+    async fn session_get(&self, id: i32) -> Result<Session, sqlx::Error>;
+    // Records activity; skipped when the last one was under a minute ago.
+    async fn session_touch(&self, id: i32) -> Result<(), sqlx::Error>;
+    // Deletes the user's session and returns it; RowNotFound when the user has no such session.
+    async fn session_delete(&self, user_id: i32, id: i32) -> Result<Session, sqlx::Error>;
+    // Deletes every expired session; returns how many.
+    async fn session_delete_expired(&self) -> Result<u64, sqlx::Error>;
+    // RowNotFound when the user never changed their profile.
+    async fn profile_get(&self, user_id: i32) -> Result<UserProfile, sqlx::Error>;
+    // Creates the profile or replaces its fields; the avatar is kept.
+    async fn profile_upsert(&self, profile: ProfileUpdate) -> Result<UserProfile, sqlx::Error>;
+    // Sets the avatar and returns the one it replaced.
+    async fn profile_set_avatar(&self, user_id: i32, avatar_key: Option<String>) -> Result<Option<String>, sqlx::Error>;
+}
+
+// Binary objects such as avatar thumbnails, addressed by slash separated keys.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn blob_put(&self, key: &str, data: Vec<u8>) -> Result<(), String>;
+    // None when there is no blob with this key.
+    async fn blob_get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    // Deletes every blob under the prefix; nothing there is not an error.
+    async fn blob_delete_prefix(&self, prefix: &str) -> Result<(), String>;
 }
@@ -1,20 +1,147 @@
+use std::collections::HashMap;
+use std::time::Instant;
 use actix_web::http::StatusCode;
+use actix_web::web;
 use async_trait::async_trait;
-use bcrypt::{hash, verify};
+use bcrypt::{hash, verify, BcryptError};
+use serde_json::{json, Value};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::internal::user::entity::user::{
-    convert_unix_to_date, UserAuthRequest, UserAuthResponse, UserChangePasswordRequest,
-    UserCreateRequest, UserGet, UserGetResponse, UserUpdateRequest, UserUpdateResponse,
+    next_user_status, UserAuthRequest, UserAuthResponse,
+    UserAuthResult, UserChangePasswordRequest, UserCreateRequest, UserGet, UserGetResponse, UserMeUpdateRequest,
+    UserStatus, UserStatusRequest, UserUpdateRequest, UserUpdateResponse,
+};
+use crate::internal::user::entity::mfa::{
+    generate_mfa_secret, generate_recovery_codes, hash_recovery_code, new_totp, totp_step,
+    MfaChallengeResponse, MfaConfirmRequest, MfaConfirmResponse, MfaEnrollResponse, MfaVerifyRequest,
+    UserMfa, MFA_CHALLENGE_LIFE_TIME, MFA_LOCK_REASON, MFA_MAX_FAILED_ATTEMPTS,
+};
+use crate::internal::user::entity::passkey::{
+    decode, generate_challenge, login_options, parse_authenticator_data, register_options, sign_count_advanced,
+    user_handle, verify_assertion_signature, verify_attestation, verify_authenticator_data, verify_client_data,
+    PasskeyCreate, PasskeyLoginBeginRequest, PasskeyLoginOptions, PasskeyLoginRequest, PasskeyRegisterOptions,
+    PasskeyRegisterRequest, PasskeyResponse, CEREMONY_LOGIN, CEREMONY_REGISTER, PASSKEY_CHALLENGE_LIFE_TIME,
+};
+use crate::internal::user::entity::session::{hash_refresh_token, to_session_response, SessionCreate, SessionResponse};
+use crate::internal::user::entity::profile::{
+    apply_profile_update, avatar_blob_key, avatar_format, avatar_key, avatar_user_prefix, empty_profile, render_avatar,
+    to_profile_response, to_profile_update, verify_attributes, ProfileResponse, ProfileUpdateRequest, UserProfile,
+    AVATAR_SIZES,
+};
+use crate::internal::user::entity::bulk::{ConflictPolicy, ImportOptions, ImportRowStatus};
+use crate::internal::user::entity::batch::{verify_batch_operation, BatchOperation, BatchRequest, BatchWrite};
+use crate::internal::user::entity::api_key::{
+    generate_api_key, hash_api_key_secret, parse_api_key, to_api_key_response, ApiKey, ApiKeyCreate,
+    ApiKeyCreateRequest, ApiKeyResponse, ApiKeySecretResponse,
 };
 
-use crate::internal::user::usecase::traits::{Repo, UseCase, UserUseCase};
+use crate::internal::user::usecase::traits::{UseCase, UserUseCase};
+use crate::internal::audit::entity::audit::{
+    diff, new_audit_record, AuditContext, AUDIT_API_KEY_CREATE, AUDIT_API_KEY_REVOKE, AUDIT_API_KEY_ROTATE, AUDIT_LOGIN_FAILURE, AUDIT_LOGIN_MFA_CHALLENGE, AUDIT_LOGIN_SUCCESS,
+    AUDIT_MFA_ENABLE, AUDIT_MFA_RECOVERY_CODE_USED, AUDIT_MFA_RESET, AUDIT_PASSKEY_REGISTER, AUDIT_SESSION_REVOKE, AUDIT_USER_CREATE, AUDIT_USER_DELETE,
+    AUDIT_USER_PASSWORD_CHANGE, AUDIT_USER_STATUS_CHANGE, AUDIT_USER_UPDATE,
+};
 use crate::internal::user::entity::token;
+use crate::internal::org::entity::org::membership_claim;
 use crate::internal::controller::response::ErrorResponseUseCase;
 
-#[async_trait]
-impl UseCase for UserUseCase {
-    async fn user_auth(&self, user: UserAuthRequest) -> Result<UserAuthResponse, ErrorResponseUseCase> {
+pub const LOGIN_SUCCESS: &str = "success";
+pub const LOGIN_FAILURE: &str = "failure";
+pub const LOGIN_ERROR: &str = "error";
+pub const LOGIN_MFA_CHALLENGE: &str = "mfa_challenge";
+
+impl UserUseCase {
+    fn hash_password(&self, password: &str) -> Result<String, ErrorResponseUseCase> {
+        let start = Instant::now();
+        let res = hash(password, 12);
+        self.metrics.observe_password_hash("hash", start.elapsed());
+
+        match res {
+            Ok(data) => {
+                Ok(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "bcrypt hash failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    fn verify_password(&self, password: &str, hashed: &str) -> Result<bool, BcryptError> {
+        let start = Instant::now();
+        let res = verify(password, hashed);
+        self.metrics.observe_password_hash("verify", start.elapsed());
+
+        res
+    }
+
+    // Starts a session for the login, recording the client from ctx, and
+    // returns tokens bound to it that carry the account's memberships.
+    async fn issue_tokens(&self, ctx: &AuditContext, id: i32, username: &String) -> Result<UserAuthResponse, ErrorResponseUseCase> {
+        let memberships = match self.orgs.org_membership_list_by_user(id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.org_membership_list_by_user failed");
+                let res = ErrorResponseUseCase {
+                    error_msg: "Internal server error".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                };
+
+                return Err(res);
+            }
+        };
+
+        let refresh_token = Uuid::new_v4().to_string();
+        let session = SessionCreate {
+            user_id: id,
+            user_agent: ctx.user_agent.clone(),
+            ip: ctx.ip.clone(),
+            refresh_token_hash: hash_refresh_token(&refresh_token),
+            expire_ts: chrono::Utc::now() + chrono::Duration::minutes(self.token.life_time as i64),
+        };
+
+        match self.repo.session_create(session).await {
+            Ok(res) => {
+                Ok(UserAuthResponse {
+                    access_token: token::generate_session_access_token(&self.token, id, username, res.id, &membership_claim(&memberships)),
+                    refresh_token,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.session_create failed");
+                let res = ErrorResponseUseCase {
+                    error_msg: "Internal server error".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    fn totp(&self, mfa: &UserMfa) -> Result<totp_rs::TOTP, String> {
+        match &mfa.mfa_secret {
+            Some(secret) => {
+                new_totp(secret, &self.mfa_issuer, &mfa.username)
+            }
+            None => {
+                Err("no MFA secret".to_string())
+            }
+        }
+    }
+
+    // The password step of a login. Also returns the account the username
+    // belongs to, if any, so failed logins can be attributed in the audit log.
+    async fn authenticate(&self, ctx: &AuditContext, user: UserAuthRequest) -> (Option<UserGet>, Result<UserAuthResult, ErrorResponseUseCase>) {
         let user_by_username: Option<UserGet> = match self.repo.user_get_by_username(user.username.clone()).await {
             Ok(res) => {
                 Some(res)
@@ -22,12 +149,13 @@ impl UseCase for UserUseCase {
 
             Err(err) => {
                 if err.to_string() != sqlx::Error::RowNotFound.to_string() {
+                    tracing::error!(error = %err, "repo.user_get_by_username failed");
                     let res = ErrorResponseUseCase {
                         error_msg: "Error in repo.user_get_by_username".to_string(),
                         status_code: StatusCode::INTERNAL_SERVER_ERROR,
                     };
 
-                    return Err(res);
+                    return (None, Err(res));
                 }
 
                 None
@@ -35,40 +163,34 @@ impl UseCase for UserUseCase {
         };
 
         match user_by_username {
-            Some(_data) => {
+            Some(data) => {
                 let password_by_username = match self.repo.user_get_password_by_username(user.username.clone()).await {
                     Ok(data) => {
                         data
                     }
-                    Err(_err) => {
+                    Err(err) => {
+                        tracing::error!(error = %err, "repo.user_get_password_by_username failed");
                         let res = ErrorResponseUseCase {
                             error_msg: "Error in repo.user_get_password_by_username".to_string(),
                             status_code: StatusCode::INTERNAL_SERVER_ERROR,
                         };
 
-                        return Err(res);
+                        return (Some(data), Err(res));
                     }
                 };
 
-                let valid = verify(user.password.clone(), &password_by_username.password).unwrap();
+                let valid = self.verify_password(&user.password, &password_by_username.password).unwrap_or_default();
 
                 if valid {
-                    let access_token = token::generate_access_token(&user.username);
-                    let refresh_token = Uuid::new_v4().to_string();
-
-                    let res: UserAuthResponse = UserAuthResponse {
-                        access_token,
-                        refresh_token,
-                    };
-
-                    Ok(res)
+                    let res = self.complete_login(ctx, &data).await;
+                    (Some(data), res)
                 } else {
                     let res = ErrorResponseUseCase {
                         error_msg: "Unauthorized".to_string(),
                         status_code: StatusCode::UNAUTHORIZED,
                     };
 
-                    Err(res)
+                    (Some(data), Err(res))
                 }
             }
             None => {
@@ -77,164 +199,305 @@ impl UseCase for UserUseCase {
                     status_code: StatusCode::UNAUTHORIZED,
                 };
 
-                Err(res)
+                (None, Err(res))
             }
         }
     }
 
-    async fn user_create(&self, mut user: UserCreateRequest) -> Result<UserGet, ErrorResponseUseCase> {
-        let user_by_username: Option<UserGet> = match self.repo.user_get_by_username(user.username.clone()).await {
+    // The step after the first factor: refused unless the account is active,
+    // then an MFA challenge when the account has TOTP on, tokens otherwise.
+    pub async fn complete_login(&self, ctx: &AuditContext, account: &UserGet) -> Result<UserAuthResult, ErrorResponseUseCase> {
+        login_allowed(account.status)?;
+
+        let (id, username) = (account.id, &account.username);
+        let mfa = match self.repo.user_get_mfa_by_id(id).await {
             Ok(res) => {
-                Some(res)
+                res
             }
-
             Err(err) => {
-                if err.to_string() != sqlx::Error::RowNotFound.to_string() {
-                    let data = ErrorResponseUseCase {
-                        status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                        error_msg: "Internal server error".to_string(),
-                    };
-
-                    return Err(data);
-                }
+                tracing::error!(error = %err, "repo.user_get_mfa_by_id failed");
+                let res = ErrorResponseUseCase {
+                    error_msg: "Internal server error".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                };
 
-                None
+                return Err(res);
             }
         };
 
-        match user_by_username {
-            Some(_data) => {
-                let data = ErrorResponseUseCase {
-                    status_code: StatusCode::CONFLICT,
-                    error_msg: format!("User with username={} already exists", user.username.clone()),
-                };
+        if mfa.mfa_enabled {
+            let res = MfaChallengeResponse {
+                mfa_required: true,
+                challenge_token: token::generate_mfa_challenge_token(&self.token, username, MFA_CHALLENGE_LIFE_TIME),
+                expires_in: MFA_CHALLENGE_LIFE_TIME,
+            };
 
-                Err(data)
-            }
-            None => {
-                let hashed = hash(user.password.clone(), 12).unwrap();
-                user.password = hashed;
+            return Ok(UserAuthResult::MfaRequired(res));
+        }
 
-                let res = match self.repo.user_create(user).await {
-                    Ok(data) => {
-                        data
-                    }
-                    Err(_err) => {
-                        let data = ErrorResponseUseCase {
-                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                            error_msg: "Internal server error".to_string(),
-                        };
+        self.issue_tokens(ctx, id, username).await.map(UserAuthResult::Tokens)
+    }
 
-                        return Err(data);
-                    }
-                };
+    // Resolves the username from the bearer token to an account id.
+    pub async fn actor_id(&self, ctx: &AuditContext) -> Option<i32> {
+        match &ctx.actor {
+            Some(username) => self.repo.user_get_by_username(username.clone()).await.ok().map(|u| u.id),
+            None => None,
+        }
+    }
 
-                Ok(res)
-            }
+    pub async fn audit_login(&self, ctx: &AuditContext, action: &str, account: Option<i32>, username: String) {
+        let mut record = new_audit_record(ctx, action);
+        record.target_id = account;
+        record.target_username = Some(username);
+        if action != AUDIT_LOGIN_FAILURE {
+            record.actor_id = account;
         }
+        self.audit.record(record).await;
     }
 
-    async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, ErrorResponseUseCase> {
-        match self.repo.user_get_by_id(id).await {
-            Ok(data) => {
-                Ok(data)
+    // Counts a wrong MFA code. The failure that reaches the limit locks the
+    // account and is audited as a status change without an actor.
+    async fn mfa_fail(&self, ctx: &AuditContext, mfa: &UserMfa) {
+        match self.repo.user_mfa_fail(mfa.id, MFA_MAX_FAILED_ATTEMPTS).await {
+            Ok(true) => {
+                tracing::warn!(user_id = mfa.id, "account locked after too many wrong MFA codes");
+                self.revoke_oidc_tokens(mfa.id, UserStatus::Active, UserStatus::Locked).await;
+                let mut record = new_audit_record(ctx, AUDIT_USER_STATUS_CHANGE);
+                record.target_id = Some(mfa.id);
+                record.target_username = Some(mfa.username.clone());
+                record.changes = diff(
+                    &json!({"status": UserStatus::Active.as_str()}),
+                    &json!({"status": UserStatus::Locked.as_str(), "reason": MFA_LOCK_REASON}),
+                );
+                self.audit.record(record).await;
             }
+            Ok(false) => {}
             Err(err) => {
-                if err.to_string() == sqlx::Error::RowNotFound.to_string() {
-                    let res = ErrorResponseUseCase {
-                        status_code: StatusCode::NOT_FOUND,
-                        error_msg: format!("User with id={} not found", id),
-                    };
-                    return Err(res);
-                }
+                tracing::error!(error = %err, "repo.user_mfa_fail failed");
+            }
+        }
+    }
+
+    // OIDC refresh tokens are not sessions of this module, so they are
+    // revoked here when an account stops being active. The token endpoint
+    // checks the status too; this keeps a later reactivation from bringing
+    // them back.
+    async fn revoke_oidc_tokens(&self, id: i32, from: UserStatus, to: UserStatus) {
+        if from != UserStatus::Active || to == UserStatus::Active {
+            return;
+        }
+
+        if let Err(err) = self.oidc.oidc_refresh_token_revoke_user(id).await {
+            tracing::error!(error = %err, "oidc.oidc_refresh_token_revoke_user failed");
+        }
+    }
 
+    // The account behind a caller's token, for the use cases that act on its
+    // behalf. An account that may not sign in may not act either.
+    pub async fn caller(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
+        let user = match self.repo.user_get_by_username(username).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: "User not found".to_string(),
+                };
+
+                return Err(res);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
                 let res = ErrorResponseUseCase {
                     status_code: StatusCode::INTERNAL_SERVER_ERROR,
                     error_msg: "Internal server error".to_string(),
                 };
 
-                Err(res)
+                return Err(res);
             }
-        }
+        };
+
+        login_allowed(user.status)?;
+
+        Ok(user)
     }
 
-    async fn user_get_by_username(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
-        match self.repo.user_get_by_username(username.clone()).await {
-            Ok(data) => {
-                Ok(data)
+    // Refuses a username held by an account other than id (None for a new
+    // account). tbl_user has a unique index too; this gives the 409.
+    async fn username_available(&self, username: &str, id: Option<i32>) -> Result<(), ErrorResponseUseCase> {
+        match self.repo.user_get_by_username(username.to_string()).await {
+            Ok(res) if Some(res.id) != id => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: format!("User with username={} already exists", username),
+                };
+
+                Err(data)
+            }
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                Ok(())
             }
             Err(err) => {
-                let mut data = ErrorResponseUseCase {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                let data = ErrorResponseUseCase {
                     status_code: StatusCode::INTERNAL_SERVER_ERROR,
                     error_msg: "Internal server error".to_string(),
                 };
 
-                if err.to_string() == sqlx::Error::RowNotFound.to_string() {
-                    data.status_code = StatusCode::NOT_FOUND;
-                    data.error_msg = format!("User with username={} not found", username);
-                    return Err(data);
-                }
-
                 Err(data)
             }
         }
     }
 
-    async fn user_list(&self) -> Result<Vec<UserGetResponse>, ErrorResponseUseCase> {
-        match self.repo.user_list().await {
-            Ok(data) => {
-                Ok(data)
+    // Admins are listed by id, which a rename can't take over.
+    pub fn is_admin(&self, id: i32) -> bool {
+        self.admins.contains(&id)
+    }
+
+    // The passkey counterpart of authenticate. The account is known once the
+    // credential is, so failures after that point can be attributed.
+    async fn passkey_authenticate(&self, ctx: &AuditContext, req: PasskeyLoginRequest) -> (Option<UserGetResponse>, Result<UserAuthResponse, ErrorResponseUseCase>) {
+        let unauthorized = |reason: &str| {
+            tracing::debug!(reason, "passkey assertion rejected");
+            ErrorResponseUseCase {
+                status_code: StatusCode::UNAUTHORIZED,
+                error_msg: "Unauthorized".to_string(),
             }
-            Err(_err) => {
-                let res = ErrorResponseUseCase {
-                    error_msg: "Internal server error".to_string(),
-                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                };
+        };
+        let internal = |err: sqlx::Error, op: &str| {
+            tracing::error!(error = %err, "repo.{} failed", op);
+            ErrorResponseUseCase {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                error_msg: "Internal server error".to_string(),
+            }
+        };
 
-                Err(res)
+        let decoded = decode(&req.raw_id, "rawId")
+            .and_then(|raw_id| Ok((raw_id, decode(&req.response.client_data_json, "clientDataJSON")?)))
+            .and_then(|(raw_id, client_data)| Ok((raw_id, client_data, decode(&req.response.authenticator_data, "authenticatorData")?)))
+            .and_then(|(raw_id, client_data, auth_data)| Ok((raw_id, client_data, auth_data, decode(&req.response.signature, "signature")?)));
+        let (raw_id, client_data, authenticator_data, signature) = match decoded {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (None, Err(ErrorResponseUseCase { status_code: StatusCode::BAD_REQUEST, error_msg: err }));
+            }
+        };
+
+        // The challenge is redeemed before anything else is checked, so each
+        // one allows a single attempt.
+        let challenge = match verify_client_data(&self.webauthn, &client_data, CEREMONY_LOGIN) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (None, Err(unauthorized(&err)));
+            }
+        };
+
+        match self.repo.passkey_challenge_take(challenge, CEREMONY_LOGIN.to_string()).await {
+            Ok(_) => {}
+            Err(sqlx::Error::RowNotFound) => {
+                return (None, Err(unauthorized("unknown or expired challenge")));
+            }
+            Err(err) => {
+                return (None, Err(internal(err, "passkey_challenge_take")));
             }
         }
-    }
 
-    async fn user_update_by_id(&self, user: UserUpdateRequest) -> Result<UserUpdateResponse, ErrorResponseUseCase> {
-        let user_by_id = match self.repo.user_get_by_id(user.id).await {
+        let passkey = match self.repo.passkey_get_by_credential_id(raw_id).await {
             Ok(res) => {
-                Some(res)
+                res
             }
-            Err(_err) => {
-                None
+            Err(sqlx::Error::RowNotFound) => {
+                return (None, Err(unauthorized("unknown credential")));
+            }
+            Err(err) => {
+                return (None, Err(internal(err, "passkey_get_by_credential_id")));
             }
         };
 
-        match user_by_id {
-            Some(_res) => {
-                match self.repo.user_update_by_id(user).await {
-                    Ok(res) => {
-                        let response = UserUpdateResponse {
-                            id: res.id,
-                            username: res.username,
-                            firstname: res.firstname,
-                            lastname: res.lastname,
-                            create_ts: convert_unix_to_date(res.create_ts),
-                            update_ts: convert_unix_to_date(res.update_ts),
-                        };
+        let account = match self.repo.user_get_by_id(passkey.user_id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (None, Err(internal(err, "user_get_by_id")));
+            }
+        };
 
-                        Ok(response)
-                    }
-                    Err(_err) => {
-                        let data = ErrorResponseUseCase {
-                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                            error_msg: "Internal server error".to_string(),
-                        };
+        let auth_data = match parse_authenticator_data(&authenticator_data)
+            .and_then(|res| verify_authenticator_data(&self.webauthn, &res).map(|_| res))
+            .and_then(|res| verify_assertion_signature(&passkey.public_key, &authenticator_data, &client_data, &signature).map(|_| res)) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                return (Some(account), Err(unauthorized(&err)));
+            }
+        };
 
-                        return Err(data);
-                    }
-                }
+        if req.response.user_handle.as_ref().is_some_and(|handle| *handle != user_handle(passkey.user_id)) {
+            return (Some(account), Err(unauthorized("user handle does not match the credential")));
+        }
+
+        if !sign_count_advanced(passkey.sign_count, auth_data.sign_count) {
+            tracing::warn!(passkey_id = passkey.id, stored = passkey.sign_count, received = auth_data.sign_count, "passkey sign count went back, possibly a cloned authenticator");
+            return (Some(account), Err(unauthorized("sign count did not advance")));
+        }
+
+        if let Err(err) = login_allowed(account.status) {
+            return (Some(account), Err(err));
+        }
+
+        // A passkey stands in for password and TOTP together only when the
+        // authenticator verified the user (PIN or biometrics).
+        match self.repo.user_get_mfa_by_id(passkey.user_id).await {
+            Ok(res) if res.mfa_enabled && !auth_data.user_verified() => {
+                return (Some(account), Err(unauthorized("user verification is required with MFA enabled")));
             }
-            None => {
+            Ok(_) => {}
+            Err(err) => {
+                return (Some(account), Err(internal(err, "user_get_mfa_by_id")));
+            }
+        }
+
+        match self.repo.passkey_use(passkey.id, auth_data.sign_count as i64).await {
+            Ok(_) => {
+                let tokens = self.issue_tokens(ctx, account.id, &account.username).await;
+                (Some(account), tokens)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                (Some(account), Err(unauthorized("sign count did not advance")))
+            }
+            Err(err) => {
+                (Some(account), Err(internal(err, "passkey_use")))
+            }
+        }
+    }
+
+    // The account that owns, or is about to own, an API key.
+    async fn api_key_owner(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
+        match self.repo.user_get_by_username(username.clone()).await {
+            Ok(res) => {
+                Ok(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
                 let data = ErrorResponseUseCase {
                     status_code: StatusCode::NOT_FOUND,
-                    error_msg: format!("User with id={} not found", user.id),
+                    error_msg: format!("User with username={} not found", username),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
                 };
 
                 Err(data)
@@ -242,58 +505,2171 @@ impl UseCase for UserUseCase {
         }
     }
 
-    async fn user_change_password(&self, mut user: UserChangePasswordRequest) -> Result<(), ErrorResponseUseCase> {
-        let password_by_id = match self.repo.user_get_password_by_id(user.id).await {
-            Ok(data) => {
-                Some(data)
-            }
-            Err(_err) => {
-                None
+    async fn audit_api_key(&self, ctx: &AuditContext, action: &str, owner: UserGet, before: Value, after: Value) {
+        let mut record = new_audit_record(ctx, action);
+        record.actor_id = Some(owner.id);
+        record.target_id = Some(owner.id);
+        record.target_username = Some(owner.username);
+        record.changes = diff(&before, &after);
+        self.audit.record(record).await;
+    }
+
+    // The account whose sessions are listed or revoked.
+    async fn session_owner(&self, id: i32) -> Result<UserGetResponse, ErrorResponseUseCase> {
+        match self.repo.user_get_by_id(id).await {
+            Ok(res) => {
+                Ok(res)
             }
-        };
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", id),
+                };
 
-        match password_by_id {
-            Some(data) => {
-                let valid = match verify(user.old_password.clone(), &data.password) {
-                    Ok(_data) => {
-                        true
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    // The user's profile; None until they first change it.
+    async fn profile(&self, user_id: i32) -> Result<Option<UserProfile>, ErrorResponseUseCase> {
+        match self.repo.profile_get(user_id).await {
+            Ok(res) => {
+                Ok(Some(res))
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                Ok(None)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.profile_get failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    // Thumbnails no longer referenced by a profile; failing to delete them
+    // only leaves garbage behind.
+    async fn delete_avatar_blobs(&self, prefix: &str) {
+        if let Err(err) = self.blobs.blob_delete_prefix(prefix).await {
+            tracing::warn!(error = %err, prefix, "blobs.blob_delete_prefix failed");
+        }
+    }
+
+    // Sessions past expire_ts are already refused; this only reclaims their rows.
+    #[instrument(name = "user_use_case.session_purge_expired", skip_all)]
+    pub async fn session_purge_expired(&self) -> Result<u64, ErrorResponseUseCase> {
+        match self.repo.session_delete_expired().await {
+            Ok(res) => {
+                Ok(res)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.session_delete_expired failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    // One operation of a best-effort batch, through the use case method of its
+    // single route.
+    async fn batch_apply(&self, ctx: AuditContext, op: BatchOperation) -> Result<Value, ErrorResponseUseCase> {
+        if let Err(err) = verify_batch_operation(&op) {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return Err(data);
+        }
+
+        match op {
+            BatchOperation::Create(req) => {
+                self.user_create(ctx, req).await.map(|res| json!(res))
+            }
+            BatchOperation::Update(req) => {
+                self.user_update_by_id(ctx, req).await.map(|res| json!(res))
+            }
+            BatchOperation::Delete(req) => {
+                self.user_delete_by_id(ctx, req.id).await.map(|_| Value::Null)
+            }
+            BatchOperation::Status(req) => {
+                self.user_set_status(ctx, req.id, req.to_status_request()).await.map(|res| json!(res))
+            }
+        }
+    }
+
+    // The write for one operation of an atomic batch, with the account before
+    // it, after the checks its single route makes.
+    async fn batch_check(&self, op: BatchOperation) -> Result<(BatchWrite, Option<UserGetResponse>), ErrorResponseUseCase> {
+        if let Err(err) = verify_batch_operation(&op) {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return Err(data);
+        }
+
+        match op {
+            BatchOperation::Create(mut req) => {
+                match self.repo.user_get_by_username(req.username.clone()).await {
+                    Ok(_) => {
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::CONFLICT,
+                            error_msg: format!("User with username={} already exists", req.username),
+                        };
+
+                        return Err(data);
+                    }
+                    Err(sqlx::Error::RowNotFound) => {}
+                    Err(err) => {
+                        tracing::error!(error = %err, "repo.user_get_by_username failed");
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                            error_msg: "Internal server error".to_string(),
+                        };
+
+                        return Err(data);
                     }
-                    Err(_err) => {
-                        false
+                }
+
+                req.password = self.hash_password(&req.password)?;
+                Ok((BatchWrite::Create(req), None))
+            }
+            BatchOperation::Update(req) => {
+                let before = self.user_get_by_id(req.id).await?;
+                if req.username != before.username {
+                    self.username_available(&req.username, Some(req.id)).await?;
+                }
+                Ok((BatchWrite::Update(req), Some(before)))
+            }
+            BatchOperation::Delete(req) => {
+                let before = self.user_get_by_id(req.id).await?;
+                Ok((BatchWrite::Delete(req.id), Some(before)))
+            }
+            BatchOperation::Status(req) => {
+                let before = self.user_get_by_id(req.id).await?;
+                let to = match next_user_status(before.status, &req.action) {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(err) => {
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::CONFLICT,
+                            error_msg: err,
+                        };
+
+                        return Err(data);
                     }
                 };
+                let reason = req.reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
 
-                if valid {
-                    let hashed = hash(user.new_password.clone(), 12).unwrap();
-                    user.new_password = hashed;
+                Ok((BatchWrite::Status { id: req.id, from: before.status, to, reason }, Some(before)))
+            }
+        }
+    }
 
-                    return match self.repo.user_change_password(user).await {
-                        Ok(_) => {
-                            Ok(())
-                        }
-                        Err(_err) => {
-                            let data = ErrorResponseUseCase {
-                                status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                                error_msg: "Internal server error".to_string(),
-                            };
+    // Checks every operation, then writes them all in one transaction. Until
+    // the commit nothing is audited, so a rolled back batch leaves no trace.
+    async fn batch_atomic(&self, ctx: &AuditContext, ops: Vec<BatchOperation>) -> Vec<Result<Value, ErrorResponseUseCase>> {
+        let len = ops.len();
+        let mut writes = Vec::new();
+        let mut befores = Vec::new();
+        // batch_check only sees the database, not the usernames written by
+        // earlier operations of this batch, and tbl_user doesn't refuse a
+        // duplicate either. Each name a create or rename writes is kept with
+        // the id that takes it (None for a new user); an update that keeps
+        // its username writes nothing new.
+        let mut usernames: HashMap<String, Option<i32>> = HashMap::new();
+        for (index, op) in ops.into_iter().enumerate() {
+            let (write, before) = match self.batch_check(op).await {
+                Ok(res) => {
+                    res
+                }
+                Err(err) => {
+                    return rolled_back(len, Some(index), err);
+                }
+            };
 
-                            Err(data)
+            let claim = match (&write, &before) {
+                (BatchWrite::Create(req), _) => {
+                    Some((&req.username, None))
+                }
+                (BatchWrite::Update(req), Some(before)) if req.username != before.username => {
+                    Some((&req.username, Some(req.id)))
+                }
+                _ => {
+                    None
+                }
+            };
+            if let Some((username, holder)) = claim {
+                match usernames.insert(username.clone(), holder) {
+                    Some(other) if other.is_none() || other != holder => {
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::CONFLICT,
+                            error_msg: format!("User with username={} already exists", username),
+                        };
+
+                        return rolled_back(len, Some(index), data);
+                    }
+                    _ => {}
+                }
+            }
+
+            writes.push(write);
+            befores.push(before);
+        }
+
+        // Resolved before the writes, in case the batch deletes the caller.
+        let actor_id = self.actor_id(ctx).await;
+
+        let rows = match self.repo.user_batch(writes.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let failed = err.index.map(|index| &writes[index]);
+                let data = match (failed, &err.err) {
+                    (Some(BatchWrite::Status { .. }), sqlx::Error::RowNotFound) => {
+                        ErrorResponseUseCase {
+                            status_code: StatusCode::CONFLICT,
+                            error_msg: "Account status changed concurrently, try again".to_string(),
+                        }
+                    }
+                    (Some(BatchWrite::Update(UserUpdateRequest { id, .. }) | BatchWrite::Delete(id)), sqlx::Error::RowNotFound) => {
+                        ErrorResponseUseCase {
+                            status_code: StatusCode::NOT_FOUND,
+                            error_msg: format!("User with id={} not found", id),
+                        }
+                    }
+                    _ => {
+                        tracing::error!(error = %err, "repo.user_batch failed");
+                        ErrorResponseUseCase {
+                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                            error_msg: "Internal server error".to_string(),
                         }
+                    }
+                };
+
+                return rolled_back(len, err.index, data);
+            }
+        };
+
+        let mut results = Vec::new();
+        for ((write, before), res) in writes.into_iter().zip(befores).zip(rows) {
+            let before = before.map(|before| (audited_fields(&before.username, &before.firstname, &before.lastname), before.status));
+            let after = audited_fields(&res.username, &res.firstname, &res.lastname);
+
+            let (action, changes, data) = match write {
+                BatchWrite::Create(_) => {
+                    let data = UserGet {
+                        id: res.id,
+                        username: res.username.clone(),
+                        firstname: res.firstname.clone(),
+                        lastname: res.lastname.clone(),
+                        status: res.status,
                     };
-                } else {
+                    (AUDIT_USER_CREATE, diff(&Value::Null, &after), json!(data))
+                }
+                BatchWrite::Update(_) => {
+                    let data = UserUpdateResponse {
+                        id: res.id,
+                        username: res.username.clone(),
+                        firstname: res.firstname.clone(),
+                        lastname: res.lastname.clone(),
+                        create_ts: res.create_ts,
+                        update_ts: res.update_ts,
+                    };
+                    (AUDIT_USER_UPDATE, diff(&before.unwrap_or_default().0, &after), json!(data))
+                }
+                BatchWrite::Delete(id) => {
+                    self.delete_avatar_blobs(&avatar_user_prefix(id)).await;
+                    (AUDIT_USER_DELETE, diff(&after, &Value::Null), Value::Null)
+                }
+                BatchWrite::Status { reason, .. } => {
+                    let from = before.map(|before| before.1).unwrap_or_default();
+                    self.revoke_oidc_tokens(res.id, from, res.status).await;
+                    let data = UserGetResponse {
+                        id: res.id,
+                        username: res.username.clone(),
+                        firstname: res.firstname.clone(),
+                        lastname: res.lastname.clone(),
+                        status: res.status,
+                        create_ts: res.create_ts,
+                        update_ts: res.update_ts,
+                    };
+                    let changes = diff(
+                        &json!({"status": from.as_str()}),
+                        &json!({"status": res.status.as_str(), "reason": reason}),
+                    );
+                    (AUDIT_USER_STATUS_CHANGE, changes, json!(data))
+                }
+            };
+
+            let mut record = new_audit_record(ctx, action);
+            record.actor_id = actor_id;
+            record.target_id = Some(res.id);
+            record.target_username = Some(res.username);
+            record.changes = changes;
+            self.audit.record(record).await;
+
+            results.push(Ok(data));
+        }
+
+        results
+    }
+}
+
+// The results of an atomic batch that was not written: the error of the
+// operation that failed, if it is known, and a 409 for each of the others.
+fn rolled_back(len: usize, failed: Option<usize>, err: ErrorResponseUseCase) -> Vec<Result<Value, ErrorResponseUseCase>> {
+    let mut err = Some(err);
+    (0..len)
+        .map(|index| {
+            if failed.is_none_or(|failed| failed == index) {
+                if let Some(err) = err.take() {
+                    return Err(err);
+                }
+            }
+
+            let error_msg = match failed {
+                Some(failed) => format!("Not applied, operation {} failed", failed),
+                None => "Not applied, the batch failed".to_string(),
+            };
+            Err(ErrorResponseUseCase {
+                status_code: StatusCode::CONFLICT,
+                error_msg,
+            })
+        })
+        .collect()
+}
+
+// Only active accounts may finish a login. Checked after the first factor,
+// so the status is only told to someone holding the credentials. The OIDC
+// token and userinfo endpoints apply it to the account behind a grant.
+pub fn login_allowed(status: UserStatus) -> Result<(), ErrorResponseUseCase> {
+    if status == UserStatus::Active {
+        return Ok(());
+    }
+
+    let res = ErrorResponseUseCase {
+        status_code: StatusCode::FORBIDDEN,
+        error_msg: format!("Account is {}", status.as_str()),
+    };
+
+    Err(res)
+}
+
+// The fields of an API key tracked by the audit log; never the secret.
+fn audited_api_key(key: &ApiKey) -> Value {
+    json!({
+        "api_key": key.name,
+        "prefix": key.prefix,
+        "scopes": key.scopes,
+        "expire_ts": key.expire_ts,
+    })
+}
+
+// The profile fields tracked by the audit log.
+fn audited_profile(profile: &Option<UserProfile>) -> Value {
+    match profile {
+        Some(profile) => {
+            json!({
+                "phone": profile.phone,
+                "locale": profile.locale,
+                "timezone": profile.timezone,
+                "attributes": profile.attributes.0,
+                "avatar": profile.avatar_key,
+            })
+        }
+        None => {
+            json!({
+                "phone": null,
+                "locale": null,
+                "timezone": null,
+                "attributes": {},
+                "avatar": null,
+            })
+        }
+    }
+}
+
+// The account fields tracked by the audit log; passwords are never included.
+fn audited_fields(username: &str, firstname: &str, lastname: &str) -> Value {
+    json!({
+        "username": username,
+        "firstname": firstname,
+        "lastname": lastname,
+    })
+}
+
+#[async_trait]
+impl UseCase for UserUseCase {
+    #[instrument(name = "user_use_case.user_auth", skip_all, fields(username = %user.username))]
+    async fn user_auth(&self, ctx: AuditContext, user: UserAuthRequest) -> Result<UserAuthResult, ErrorResponseUseCase> {
+        let username = user.username.clone();
+        let (account, res) = self.authenticate(&ctx, user).await;
+
+        let (result, action) = match &res {
+            Ok(UserAuthResult::Tokens(_)) => (LOGIN_SUCCESS, Some(AUDIT_LOGIN_SUCCESS)),
+            Ok(UserAuthResult::MfaRequired(_)) => (LOGIN_MFA_CHALLENGE, Some(AUDIT_LOGIN_MFA_CHALLENGE)),
+            Err(err) if err.status_code == StatusCode::UNAUTHORIZED || err.status_code == StatusCode::FORBIDDEN => (LOGIN_FAILURE, Some(AUDIT_LOGIN_FAILURE)),
+            Err(_) => (LOGIN_ERROR, None),
+        };
+        self.metrics.login(result);
+
+        if let Some(action) = action {
+            self.audit_login(&ctx, action, account.map(|a| a.id), username).await;
+        }
+
+        res
+    }
+
+    #[instrument(name = "user_use_case.user_create", skip_all, fields(username = %user.username))]
+    async fn user_create(&self, ctx: AuditContext, mut user: UserCreateRequest) -> Result<UserGet, ErrorResponseUseCase> {
+        let user_by_username: Option<UserGet> = match self.repo.user_get_by_username(user.username.clone()).await {
+            Ok(res) => {
+                Some(res)
+            }
+
+            Err(err) => {
+                if err.to_string() != sqlx::Error::RowNotFound.to_string() {
+                    tracing::error!(error = %err, "repo.user_get_by_username failed");
                     let data = ErrorResponseUseCase {
-                        status_code: StatusCode::BAD_REQUEST,
-                        error_msg: "Invalid old password".to_string(),
+                        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                        error_msg: "Internal server error".to_string(),
                     };
 
-                    Err(data)
+                    return Err(data);
                 }
+
+                None
             }
-            None => {
+        };
+
+        match user_by_username {
+            Some(_data) => {
                 let data = ErrorResponseUseCase {
-                    status_code: StatusCode::NOT_FOUND,
-                    error_msg: format!("User with id={} not found", user.id),
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: format!("User with username={} already exists", user.username.clone()),
+                };
+
+                Err(data)
+            }
+            None => {
+                let hashed = self.hash_password(&user.password)?;
+                user.password = hashed;
+
+                let res = match self.repo.user_create(user).await {
+                    Ok(data) => {
+                        data
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "repo.user_create failed");
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                            error_msg: "Internal server error".to_string(),
+                        };
+
+                        return Err(data);
+                    }
+                };
+
+                let mut record = new_audit_record(&ctx, AUDIT_USER_CREATE);
+                record.actor_id = self.actor_id(&ctx).await;
+                record.target_id = Some(res.id);
+                record.target_username = Some(res.username.clone());
+                record.changes = diff(&Value::Null, &audited_fields(&res.username, &res.firstname, &res.lastname));
+                self.audit.record(record).await;
+
+                Ok(res)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_get_by_id", skip(self))]
+    async fn user_get_by_id(&self, id: i32) -> Result<UserGetResponse, ErrorResponseUseCase> {
+        match self.repo.user_get_by_id(id).await {
+            Ok(data) => {
+                Ok(data)
+            }
+            Err(err) => {
+                if err.to_string() == sqlx::Error::RowNotFound.to_string() {
+                    let res = ErrorResponseUseCase {
+                        status_code: StatusCode::NOT_FOUND,
+                        error_msg: format!("User with id={} not found", id),
+                    };
+                    return Err(res);
+                }
+
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                let res = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
                 };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_get_by_username", skip(self))]
+    async fn user_get_by_username(&self, username: String) -> Result<UserGet, ErrorResponseUseCase> {
+        match self.repo.user_get_by_username(username.clone()).await {
+            Ok(data) => {
+                Ok(data)
+            }
+            Err(err) => {
+                let mut data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                if err.to_string() == sqlx::Error::RowNotFound.to_string() {
+                    data.status_code = StatusCode::NOT_FOUND;
+                    data.error_msg = format!("User with username={} not found", username);
+                    return Err(data);
+                }
+
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_list", skip_all)]
+    async fn user_list(&self, status: Option<UserStatus>) -> Result<Vec<UserGetResponse>, ErrorResponseUseCase> {
+        match self.repo.user_list(status).await {
+            Ok(data) => {
+                Ok(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_list failed");
+                let res = ErrorResponseUseCase {
+                    error_msg: "Internal server error".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_import_row", skip_all, fields(username = %user.username, dry_run = options.dry_run))]
+    async fn user_import_row(&self, ctx: AuditContext, user: UserCreateRequest, options: ImportOptions) -> Result<ImportRowStatus, ErrorResponseUseCase> {
+        let existing = match self.repo.user_get_by_username(user.username.clone()).await {
+            Ok(res) => {
+                Some(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                None
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let before = match (existing, options.on_conflict) {
+            (None, _) if options.dry_run => {
+                return Ok(ImportRowStatus::Created);
+            }
+            (None, _) => {
+                return self.user_create(ctx, user).await.map(|_| ImportRowStatus::Created);
+            }
+            (Some(_), ConflictPolicy::Skip) => {
+                return Ok(ImportRowStatus::Skipped);
+            }
+            (Some(_), ConflictPolicy::Update) if options.dry_run => {
+                return Ok(ImportRowStatus::Updated);
+            }
+            (Some(res), ConflictPolicy::Update) => {
+                res
+            }
+        };
+
+        let update = UserUpdateRequest {
+            id: before.id,
+            username: before.username.clone(),
+            firstname: user.firstname,
+            lastname: user.lastname,
+        };
+        let res = match self.repo.user_update_by_id(update).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_update_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let actor_id = self.actor_id(&ctx).await;
+        let changes = diff(
+            &audited_fields(&before.username, &before.firstname, &before.lastname),
+            &audited_fields(&res.username, &res.firstname, &res.lastname),
+        );
+        if changes.as_object().is_some_and(|changes| !changes.is_empty()) {
+            let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+            record.actor_id = actor_id;
+            record.target_id = Some(res.id);
+            record.target_username = Some(res.username.clone());
+            record.changes = changes;
+            self.audit.record(record).await;
+        }
+
+        let password = UserChangePasswordRequest {
+            id: res.id,
+            old_password: String::new(),
+            new_password: self.hash_password(&user.password)?,
+        };
+        if let Err(err) = self.repo.user_change_password(password).await {
+            tracing::error!(error = %err, "repo.user_change_password failed");
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                error_msg: "Internal server error".to_string(),
+            };
+
+            return Err(data);
+        }
+
+        let mut record = new_audit_record(&ctx, AUDIT_USER_PASSWORD_CHANGE);
+        record.actor_id = actor_id;
+        record.target_id = Some(res.id);
+        record.target_username = Some(res.username);
+        self.audit.record(record).await;
+
+        Ok(ImportRowStatus::Updated)
+    }
+
+    #[instrument(name = "user_use_case.user_list_page", skip(self))]
+    async fn user_list_page(&self, status: Option<UserStatus>, after_id: i32, limit: i64) -> Result<Vec<UserGetResponse>, ErrorResponseUseCase> {
+        match self.repo.user_list_page(status, after_id, limit).await {
+            Ok(data) => {
+                Ok(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_list_page failed");
+                let res = ErrorResponseUseCase {
+                    error_msg: "Internal server error".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                };
+
+                Err(res)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_get_me", skip(self))]
+    async fn user_get_me(&self, username: String) -> Result<UserGetResponse, ErrorResponseUseCase> {
+        let user = self.user_get_by_username(username).await?;
+
+        self.user_get_by_id(user.id).await
+    }
+
+    #[instrument(name = "user_use_case.user_update_me", skip(self, ctx, req))]
+    async fn user_update_me(&self, ctx: AuditContext, username: String, req: UserMeUpdateRequest) -> Result<UserGetResponse, ErrorResponseUseCase> {
+        let before = self.user_get_by_username(username).await?;
+
+        let update = UserUpdateRequest {
+            id: before.id,
+            username: before.username.clone(),
+            firstname: req.firstname.unwrap_or_else(|| before.firstname.clone()),
+            lastname: req.lastname.unwrap_or_else(|| before.lastname.clone()),
+        };
+
+        match self.repo.user_update_by_id(update).await {
+            Ok(res) => {
+                let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+                record.actor_id = Some(res.id);
+                record.target_id = Some(res.id);
+                record.target_username = Some(res.username.clone());
+                record.changes = diff(
+                    &audited_fields(&before.username, &before.firstname, &before.lastname),
+                    &audited_fields(&res.username, &res.firstname, &res.lastname),
+                );
+                self.audit.record(record).await;
+
+                let response = UserGetResponse {
+                    id: res.id,
+                    username: res.username,
+                    firstname: res.firstname,
+                    lastname: res.lastname,
+                    status: res.status,
+                    create_ts: res.create_ts,
+                    update_ts: res.update_ts,
+                };
+
+                Ok(response)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_update_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_update_by_id", skip_all, fields(id = user.id))]
+    async fn user_update_by_id(&self, ctx: AuditContext, user: UserUpdateRequest) -> Result<UserUpdateResponse, ErrorResponseUseCase> {
+        let user_by_id = match self.repo.user_get_by_id(user.id).await {
+            Ok(res) => {
+                Some(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                None
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        // Anyone with user:write may change their own account; changing
+        // someone else's, their username included, is for admins.
+        let actor_id = self.actor_id(&ctx).await;
+        if actor_id != Some(user.id) && !actor_id.is_some_and(|id| self.is_admin(id)) {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::FORBIDDEN,
+                error_msg: "Only admins can change other accounts".to_string(),
+            };
+
+            return Err(data);
+        }
+
+        match user_by_id {
+            Some(before) => {
+                if user.username != before.username {
+                    self.username_available(&user.username, Some(user.id)).await?;
+                }
+
+                match self.repo.user_update_by_id(user).await {
+                    Ok(res) => {
+                        let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+                        record.actor_id = actor_id;
+                        record.target_id = Some(res.id);
+                        record.target_username = Some(res.username.clone());
+                        record.changes = diff(
+                            &audited_fields(&before.username, &before.firstname, &before.lastname),
+                            &audited_fields(&res.username, &res.firstname, &res.lastname),
+                        );
+                        self.audit.record(record).await;
+
+                        let response = UserUpdateResponse {
+                            id: res.id,
+                            username: res.username,
+                            firstname: res.firstname,
+                            lastname: res.lastname,
+                            create_ts: res.create_ts,
+                            update_ts: res.update_ts,
+                        };
+
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "repo.user_update_by_id failed");
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                            error_msg: "Internal server error".to_string(),
+                        };
+
+                        Err(data)
+                    }
+                }
+            }
+            None => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", user.id),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_change_password", skip_all, fields(id = user.id))]
+    async fn user_change_password(&self, ctx: AuditContext, mut user: UserChangePasswordRequest) -> Result<(), ErrorResponseUseCase> {
+        let password_by_id = match self.repo.user_get_password_by_id(user.id).await {
+            Ok(res) => {
+                Some(res)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                None
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_password_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        match password_by_id {
+            Some(data) => {
+                let valid = self.verify_password(&user.old_password, &data.password).unwrap_or_default();
+
+                if valid {
+                    let hashed = self.hash_password(&user.new_password)?;
+                    user.new_password = hashed;
+                    let id = user.id;
+
+                    return match self.repo.user_change_password(user).await {
+                        Ok(_) => {
+                            // Knowing the old password identifies the caller when there is no token.
+                            let mut record = new_audit_record(&ctx, AUDIT_USER_PASSWORD_CHANGE);
+                            record.actor_id = self.actor_id(&ctx).await.or(Some(id));
+                            record.target_id = Some(id);
+                            record.target_username = self.repo.user_get_by_id(id).await.ok().map(|u| u.username);
+                            self.audit.record(record).await;
+
+                            Ok(())
+                        }
+                        Err(err) => {
+                            tracing::error!(error = %err, "repo.user_change_password failed");
+                            let data = ErrorResponseUseCase {
+                                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                                error_msg: "Internal server error".to_string(),
+                            };
+
+                            Err(data)
+                        }
+                    };
+                } else {
+                    let data = ErrorResponseUseCase {
+                        status_code: StatusCode::BAD_REQUEST,
+                        error_msg: "Invalid old password".to_string(),
+                    };
+
+                    Err(data)
+                }
+            }
+            None => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", user.id),
+                };
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_delete_by_id", skip(self, ctx))]
+    async fn user_delete_by_id(&self, ctx: AuditContext, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let before = match self.repo.user_get_by_id(id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", id),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        // Resolved before the delete, in case the account deletes itself.
+        let actor_id = self.actor_id(&ctx).await;
+
+        match self.repo.user_delete_by_id(id).await {
+            Ok(_) => {
+                let mut record = new_audit_record(&ctx, AUDIT_USER_DELETE);
+                record.actor_id = actor_id;
+                record.target_id = Some(id);
+                record.target_username = Some(before.username.clone());
+                record.changes = diff(&audited_fields(&before.username, &before.firstname, &before.lastname), &Value::Null);
+                self.audit.record(record).await;
+                // The profile row went with the account; its avatar's thumbnails did not.
+                self.delete_avatar_blobs(&avatar_user_prefix(id)).await;
+
+                Ok(())
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", id),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_delete_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_batch", skip_all, fields(atomic = req.atomic, operations = req.operations.len()))]
+    async fn user_batch(&self, ctx: AuditContext, req: BatchRequest) -> Vec<Result<Value, ErrorResponseUseCase>> {
+        if req.atomic {
+            return self.batch_atomic(&ctx, req.operations).await;
+        }
+
+        let mut results = Vec::new();
+        for op in req.operations {
+            results.push(self.batch_apply(ctx.clone(), op).await);
+        }
+
+        results
+    }
+
+    #[instrument(name = "user_use_case.user_set_status", skip(self, ctx, req), fields(action = %req.action))]
+    async fn user_set_status(&self, ctx: AuditContext, id: i32, req: UserStatusRequest) -> Result<UserGetResponse, ErrorResponseUseCase> {
+        let before = self.user_get_by_id(id).await?;
+
+        let status = match next_user_status(before.status, &req.action) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: err,
+                };
+
+                return Err(data);
+            }
+        };
+
+        let reason = req.reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+
+        // Conditional on the status read above, so two admins acting at once
+        // can't both apply a transition.
+        match self.repo.user_set_status(id, before.status, status, reason.clone()).await {
+            Ok(res) => {
+                self.revoke_oidc_tokens(res.id, before.status, res.status).await;
+                let mut record = new_audit_record(&ctx, AUDIT_USER_STATUS_CHANGE);
+                record.actor_id = self.actor_id(&ctx).await;
+                record.target_id = Some(res.id);
+                record.target_username = Some(res.username.clone());
+                record.changes = diff(
+                    &json!({"status": before.status.as_str()}),
+                    &json!({"status": res.status.as_str(), "reason": reason}),
+                );
+                self.audit.record(record).await;
+
+                let response = UserGetResponse {
+                    id: res.id,
+                    username: res.username,
+                    firstname: res.firstname,
+                    lastname: res.lastname,
+                    status: res.status,
+                    create_ts: res.create_ts,
+                    update_ts: res.update_ts,
+                };
+
+                Ok(response)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: "Account status changed concurrently, try again".to_string(),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_set_status failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_mfa_enroll", skip(self))]
+    async fn user_mfa_enroll(&self, username: String) -> Result<MfaEnrollResponse, ErrorResponseUseCase> {
+        let mfa = match self.repo.user_get_mfa_by_username(username.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with username={} not found", username),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_mfa_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        if mfa.mfa_enabled {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::CONFLICT,
+                error_msg: "MFA is already enabled".to_string(),
+            };
+
+            return Err(data);
+        }
+
+        // Enrolling again before confirming replaces the pending secret.
+        let secret = generate_mfa_secret();
+        let totp = match new_totp(&secret, &self.mfa_issuer, &mfa.username) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "can't build TOTP");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        match self.repo.user_mfa_set_secret(mfa.id, secret.clone()).await {
+            Ok(_) => {
+                Ok(MfaEnrollResponse {
+                    secret,
+                    provisioning_uri: totp.get_url(),
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_mfa_set_secret failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_mfa_confirm", skip(self, ctx, req))]
+    async fn user_mfa_confirm(&self, ctx: AuditContext, username: String, req: MfaConfirmRequest) -> Result<MfaConfirmResponse, ErrorResponseUseCase> {
+        let mfa = match self.repo.user_get_mfa_by_username(username.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with username={} not found", username),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_mfa_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        if mfa.mfa_enabled {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::CONFLICT,
+                error_msg: "MFA is already enabled".to_string(),
+            };
+
+            return Err(data);
+        }
+
+        let totp = match self.totp(&mfa) {
+            Ok(res) => {
+                res
+            }
+            Err(_err) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "MFA enrollment has not been started".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let step = match totp_step(&totp, &req.code, token::get_time_sec()) {
+            Some(res) => {
+                res
+            }
+            None => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Invalid code".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let recovery_codes = generate_recovery_codes();
+        let code_hashes = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect();
+
+        match self.repo.user_mfa_enable(mfa.id, step, code_hashes).await {
+            Ok(_) => {
+                let mut record = new_audit_record(&ctx, AUDIT_MFA_ENABLE);
+                record.actor_id = self.actor_id(&ctx).await;
+                record.target_id = Some(mfa.id);
+                record.target_username = Some(mfa.username);
+                record.changes = diff(&json!({"mfa_enabled": false}), &json!({"mfa_enabled": true}));
+                self.audit.record(record).await;
+
+                Ok(MfaConfirmResponse {
+                    recovery_codes,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_mfa_enable failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_mfa_verify", skip_all)]
+    async fn user_mfa_verify(&self, ctx: AuditContext, req: MfaVerifyRequest) -> Result<UserAuthResponse, ErrorResponseUseCase> {
+        let unauthorized = ErrorResponseUseCase {
+            status_code: StatusCode::UNAUTHORIZED,
+            error_msg: "Unauthorized".to_string(),
+        };
+
+        let username = match token::verify_mfa_challenge_token(&self.token, &req.challenge_token) {
+            Some(res) => {
+                res
+            }
+            None => {
+                self.metrics.login(LOGIN_FAILURE);
+                return Err(unauthorized);
+            }
+        };
+
+        let mfa = match self.repo.user_get_mfa_by_username(username.clone()).await {
+            Ok(res) if res.mfa_enabled => {
+                res
+            }
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                // Deleted or reset since the challenge was issued.
+                self.metrics.login(LOGIN_FAILURE);
+                self.audit_login(&ctx, AUDIT_LOGIN_FAILURE, None, username).await;
+                return Err(unauthorized);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_mfa_by_username failed");
+                self.metrics.login(LOGIN_ERROR);
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        // The account may have been disabled or locked since the challenge.
+        let status = match self.repo.user_get_by_id(mfa.id).await {
+            Ok(res) => {
+                res.status
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                self.metrics.login(LOGIN_ERROR);
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+        if let Err(err) = login_allowed(status) {
+            self.metrics.login(LOGIN_FAILURE);
+            self.audit_login(&ctx, AUDIT_LOGIN_FAILURE, Some(mfa.id), mfa.username).await;
+            return Err(err);
+        }
+
+        // Both paths end in a conditional update, so a code or a recovery
+        // code is accepted at most once even under concurrent requests.
+        let res = match (&req.code, &req.recovery_code) {
+            (Some(code), _) => {
+                match self.totp(&mfa).ok().and_then(|totp| totp_step(&totp, code, token::get_time_sec())) {
+                    Some(step) => self.repo.user_mfa_use_step(mfa.id, step).await,
+                    None => Err(sqlx::Error::RowNotFound),
+                }
+            }
+            (None, Some(recovery_code)) => {
+                self.repo.user_mfa_use_recovery_code(mfa.id, hash_recovery_code(recovery_code)).await
+            }
+            (None, None) => {
+                Err(sqlx::Error::RowNotFound)
+            }
+        };
+
+        match res {
+            Ok(_) => {
+                if req.code.is_none() {
+                    let mut record = new_audit_record(&ctx, AUDIT_MFA_RECOVERY_CODE_USED);
+                    record.actor_id = Some(mfa.id);
+                    record.target_id = Some(mfa.id);
+                    record.target_username = Some(mfa.username.clone());
+                    self.audit.record(record).await;
+                }
+
+                let tokens = self.issue_tokens(&ctx, mfa.id, &mfa.username).await;
+                if tokens.is_err() {
+                    self.metrics.login(LOGIN_ERROR);
+                    return tokens;
+                }
+
+                self.metrics.login(LOGIN_SUCCESS);
+                self.audit_login(&ctx, AUDIT_LOGIN_SUCCESS, Some(mfa.id), mfa.username.clone()).await;
+
+                tokens
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                self.metrics.login(LOGIN_FAILURE);
+                self.audit_login(&ctx, AUDIT_LOGIN_FAILURE, Some(mfa.id), mfa.username.clone()).await;
+                self.mfa_fail(&ctx, &mfa).await;
+
+                Err(unauthorized)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_mfa_use failed");
+                self.metrics.login(LOGIN_ERROR);
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_mfa_reset", skip(self, ctx))]
+    async fn user_mfa_reset(&self, ctx: AuditContext, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let before = match self.repo.user_get_mfa_by_id(id).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", id),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_mfa_by_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        match self.repo.user_mfa_reset(id).await {
+            Ok(_) => {
+                let mut record = new_audit_record(&ctx, AUDIT_MFA_RESET);
+                record.actor_id = self.actor_id(&ctx).await;
+                record.target_id = Some(id);
+                record.target_username = Some(before.username);
+                record.changes = diff(&json!({"mfa_enabled": before.mfa_enabled}), &json!({"mfa_enabled": false}));
+                self.audit.record(record).await;
+
+                Ok(())
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with id={} not found", id),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_mfa_reset failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_passkey_register_begin", skip(self))]
+    async fn user_passkey_register_begin(&self, username: String) -> Result<PasskeyRegisterOptions, ErrorResponseUseCase> {
+        let user = match self.repo.user_get_by_username(username.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with username={} not found", username),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let existing = match self.repo.passkey_list_by_user(user.id).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_list_by_user failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let challenge = generate_challenge();
+        match self.repo.passkey_challenge_create(challenge.clone(), CEREMONY_REGISTER.to_string(), Some(user.id), PASSKEY_CHALLENGE_LIFE_TIME).await {
+            Ok(_) => {
+                let display_name = format!("{} {}", user.firstname, user.lastname);
+                Ok(register_options(&self.webauthn, challenge, user.id, &user.username, display_name.trim(), &existing))
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_challenge_create failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_passkey_register_finish", skip(self, ctx, req))]
+    async fn user_passkey_register_finish(&self, ctx: AuditContext, username: String, req: PasskeyRegisterRequest) -> Result<PasskeyResponse, ErrorResponseUseCase> {
+        let user = match self.repo.user_get_by_username(username.clone()).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: format!("User with username={} not found", username),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_username failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let challenge = match decode(&req.response.client_data_json, "clientDataJSON")
+            .and_then(|client_data| verify_client_data(&self.webauthn, &client_data, CEREMONY_REGISTER)) {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: err,
+                };
+
+                return Err(data);
+            }
+        };
+
+        // Registration challenges are bound to the account that asked for them.
+        match self.repo.passkey_challenge_take(challenge, CEREMONY_REGISTER.to_string()).await {
+            Ok(Some(user_id)) if user_id == user.id => {}
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: "Unknown or expired challenge".to_string(),
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_challenge_take failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        }
+
+        let verified = decode(&req.response.attestation_object, "attestationObject")
+            .and_then(|attestation| verify_attestation(&self.webauthn, &attestation))
+            .and_then(|res| {
+                if decode(&req.raw_id, "rawId")? != res.1.credential_id {
+                    return Err("rawId does not match the attested credential".to_string());
+                }
+
+                Ok(res)
+            });
+        let (auth_data, credential) = match verified {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: err,
+                };
+
+                return Err(data);
+            }
+        };
+
+        match self.repo.passkey_get_by_credential_id(credential.credential_id.clone()).await {
+            Ok(_) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::CONFLICT,
+                    error_msg: "Passkey is already registered".to_string(),
+                };
+
+                return Err(data);
+            }
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_get_by_credential_id failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        }
+
+        let passkey = PasskeyCreate {
+            user_id: user.id,
+            credential_id: credential.credential_id,
+            public_key: credential.public_key,
+            sign_count: auth_data.sign_count as i64,
+            name: req.name.map(|name| name.trim().to_string()).unwrap_or_else(|| "Passkey".to_string()),
+        };
+
+        match self.repo.passkey_create(passkey).await {
+            Ok(res) => {
+                let mut record = new_audit_record(&ctx, AUDIT_PASSKEY_REGISTER);
+                record.actor_id = Some(user.id);
+                record.target_id = Some(user.id);
+                record.target_username = Some(user.username);
+                record.changes = diff(&Value::Null, &json!({"passkey": res.name}));
+                self.audit.record(record).await;
+
+                Ok(PasskeyResponse {
+                    id: res.id,
+                    name: res.name,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_create failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_passkey_login_begin", skip_all)]
+    async fn user_passkey_login_begin(&self, req: PasskeyLoginBeginRequest) -> Result<PasskeyLoginOptions, ErrorResponseUseCase> {
+        // An unknown username gets an empty allow list rather than an error,
+        // so the endpoint doesn't reveal which accounts exist.
+        let allowed = match req.username {
+            Some(username) => {
+                let res = match self.repo.user_get_by_username(username).await {
+                    Ok(user) => self.repo.passkey_list_by_user(user.id).await,
+                    Err(sqlx::Error::RowNotFound) => Ok(Vec::new()),
+                    Err(err) => Err(err),
+                };
+
+                match res {
+                    Ok(res) => {
+                        res
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "repo.passkey_list_by_user failed");
+                        let data = ErrorResponseUseCase {
+                            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                            error_msg: "Internal server error".to_string(),
+                        };
+
+                        return Err(data);
+                    }
+                }
+            }
+            None => {
+                Vec::new()
+            }
+        };
+
+        let challenge = generate_challenge();
+        match self.repo.passkey_challenge_create(challenge.clone(), CEREMONY_LOGIN.to_string(), None, PASSKEY_CHALLENGE_LIFE_TIME).await {
+            Ok(_) => {
+                Ok(login_options(&self.webauthn, challenge, &allowed))
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.passkey_challenge_create failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_passkey_login_finish", skip_all)]
+    async fn user_passkey_login_finish(&self, ctx: AuditContext, req: PasskeyLoginRequest) -> Result<UserAuthResponse, ErrorResponseUseCase> {
+        let (account, res) = self.passkey_authenticate(&ctx, req).await;
+
+        let (result, action) = match &res {
+            Ok(_) => (LOGIN_SUCCESS, Some(AUDIT_LOGIN_SUCCESS)),
+            Err(err) if err.status_code == StatusCode::UNAUTHORIZED || err.status_code == StatusCode::FORBIDDEN => (LOGIN_FAILURE, Some(AUDIT_LOGIN_FAILURE)),
+            Err(_) => (LOGIN_ERROR, None),
+        };
+        self.metrics.login(result);
+
+        // Assertions for unknown credentials name no account, so there is
+        // nothing to attribute them to.
+        if let (Some(action), Some(account)) = (action, account) {
+            self.audit_login(&ctx, action, Some(account.id), account.username).await;
+        }
+
+        res
+    }
+
+    #[instrument(name = "user_use_case.user_api_key_create", skip(self, ctx, req))]
+    async fn user_api_key_create(&self, ctx: AuditContext, username: String, req: ApiKeyCreateRequest) -> Result<ApiKeySecretResponse, ErrorResponseUseCase> {
+        let owner = self.api_key_owner(username).await?;
+
+        let mut scopes = req.scopes;
+        scopes.sort();
+        scopes.dedup();
+        let (api_key, prefix, secret_hash) = generate_api_key();
+        let key = ApiKeyCreate {
+            user_id: owner.id,
+            name: req.name.trim().to_string(),
+            prefix,
+            secret_hash,
+            scopes,
+            expire_ts: req.expire_ts,
+        };
+
+        match self.repo.api_key_create(key).await {
+            Ok(res) => {
+                self.audit_api_key(&ctx, AUDIT_API_KEY_CREATE, owner, Value::Null, audited_api_key(&res)).await;
+
+                Ok(ApiKeySecretResponse {
+                    key: to_api_key_response(res),
+                    api_key,
+                })
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_create failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_api_key_list", skip(self))]
+    async fn user_api_key_list(&self, username: String) -> Result<Vec<ApiKeyResponse>, ErrorResponseUseCase> {
+        let owner = self.api_key_owner(username).await?;
+
+        match self.repo.api_key_list_by_user(owner.id).await {
+            Ok(res) => {
+                Ok(res.into_iter().map(to_api_key_response).collect())
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_list_by_user failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_api_key_rotate", skip(self, ctx))]
+    async fn user_api_key_rotate(&self, ctx: AuditContext, username: String, id: i32) -> Result<ApiKeySecretResponse, ErrorResponseUseCase> {
+        let owner = self.api_key_owner(username).await?;
+        let before = match self.repo.api_key_list_by_user(owner.id).await {
+            Ok(res) => {
+                res.into_iter().find(|key| key.id == id)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_list_by_user failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let (api_key, prefix, secret_hash) = generate_api_key();
+        match self.repo.api_key_rotate(owner.id, id, prefix, secret_hash).await {
+            Ok(res) => {
+                let before = before.as_ref().map(audited_api_key).unwrap_or(Value::Null);
+                self.audit_api_key(&ctx, AUDIT_API_KEY_ROTATE, owner, before, audited_api_key(&res)).await;
+
+                Ok(ApiKeySecretResponse {
+                    key: to_api_key_response(res),
+                    api_key,
+                })
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: "API key not found".to_string(),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_rotate failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_api_key_revoke", skip(self, ctx))]
+    async fn user_api_key_revoke(&self, ctx: AuditContext, username: String, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let owner = self.api_key_owner(username).await?;
+
+        match self.repo.api_key_delete(owner.id, id).await {
+            Ok(res) => {
+                self.audit_api_key(&ctx, AUDIT_API_KEY_REVOKE, owner, audited_api_key(&res), Value::Null).await;
+                Ok(())
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: "API key not found".to_string(),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_delete failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_api_key_authenticate", skip_all)]
+    async fn user_api_key_authenticate(&self, api_key: &str) -> Option<(String, ApiKey)> {
+        let (prefix, secret) = parse_api_key(api_key)?;
+
+        let key = match self.repo.api_key_get_by_prefix(prefix.to_string()).await {
+            Ok(res) => {
+                res
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                return None;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.api_key_get_by_prefix failed");
+                return None;
+            }
+        };
+
+        if hash_api_key_secret(secret) != key.secret_hash || key.expire_ts.is_some_and(|expire_ts| expire_ts <= chrono::Utc::now()) {
+            tracing::debug!(prefix, "API key rejected");
+            return None;
+        }
+
+        if let Err(err) = self.repo.api_key_touch(key.id).await {
+            tracing::warn!(error = %err, "repo.api_key_touch failed");
+        }
+
+        match self.repo.user_get_by_id(key.user_id).await {
+            Ok(res) if res.status == UserStatus::Active => {
+                Some((res.username, key))
+            }
+            Ok(res) => {
+                tracing::debug!(status = res.status.as_str(), "API key owner is not active");
+                None
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.user_get_by_id failed");
+                None
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_session_list", skip(self))]
+    async fn user_session_list(&self, user_id: i32, current: Option<i32>) -> Result<Vec<SessionResponse>, ErrorResponseUseCase> {
+        let owner = self.session_owner(user_id).await?;
+
+        match self.repo.session_list_by_user(owner.id).await {
+            Ok(res) => {
+                Ok(res.into_iter().map(|session| to_session_response(session, current)).collect())
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.session_list_by_user failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_session_revoke", skip(self, ctx))]
+    async fn user_session_revoke(&self, ctx: AuditContext, user_id: i32, id: i32) -> Result<(), ErrorResponseUseCase> {
+        let owner = self.session_owner(user_id).await?;
+
+        match self.repo.session_delete(owner.id, id).await {
+            Ok(res) => {
+                let mut record = new_audit_record(&ctx, AUDIT_SESSION_REVOKE);
+                record.actor_id = self.actor_id(&ctx).await;
+                record.target_id = Some(owner.id);
+                record.target_username = Some(owner.username);
+                record.changes = diff(&json!({"session": res.id, "user_agent": res.user_agent, "ip": res.ip}), &Value::Null);
+                self.audit.record(record).await;
+
+                Ok(())
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::NOT_FOUND,
+                    error_msg: "Session not found".to_string(),
+                };
+
+                Err(data)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.session_delete failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_token_owner", skip(self))]
+    async fn user_token_owner(&self, user_id: Option<i32>, username: &str, session_id: Option<i32>) -> Option<(i32, String)> {
+        // Tokens issued before they carried the account's id name it by
+        // username instead.
+        let user = match user_id {
+            Some(id) => {
+                self.repo.user_get_by_id(id).await.map(|res| (res.id, res.username, res.status))
+            }
+            None => {
+                self.repo.user_get_by_username(username.to_string()).await.map(|res| (res.id, res.username, res.status))
+            }
+        };
+        let (id, username) = match user {
+            Ok((_, _, status)) if status != UserStatus::Active => {
+                tracing::debug!(status = status.as_str(), "account is not active");
+                return None;
+            }
+            Ok((id, username, _)) => {
+                (id, username)
+            }
+            Err(sqlx::Error::RowNotFound) => {
+                tracing::debug!("account no longer exists");
+                return None;
+            }
+            Err(err) => {
+                // Fails closed, like the session check below.
+                tracing::error!(error = %err, "repo.user_get failed");
+                return None;
+            }
+        };
+
+        let session_id = match session_id {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Some((id, username));
+            }
+        };
+
+        match self.repo.session_get(session_id).await {
+            Ok(res) if res.user_id != id => {
+                tracing::warn!(user_id = id, "session belongs to another account");
+                return None;
+            }
+            Ok(_) => {}
+            Err(sqlx::Error::RowNotFound) => {
+                tracing::debug!("session revoked or expired");
+                return None;
+            }
+            Err(err) => {
+                // Fails closed: a revoked session must not slip through.
+                tracing::error!(error = %err, "repo.session_get failed");
+                return None;
+            }
+        }
+
+        if let Err(err) = self.repo.session_touch(session_id).await {
+            tracing::warn!(error = %err, "repo.session_touch failed");
+        }
+
+        Some((id, username))
+    }
+
+    #[instrument(name = "user_use_case.user_profile_get", skip(self))]
+    async fn user_profile_get(&self, username: String) -> Result<ProfileResponse, ErrorResponseUseCase> {
+        let user = self.user_get_by_username(username).await?;
+        let profile = self.profile(user.id).await?;
+
+        Ok(to_profile_response(profile))
+    }
+
+    #[instrument(name = "user_use_case.user_profile_update", skip(self, ctx, req))]
+    async fn user_profile_update(&self, ctx: AuditContext, username: String, req: ProfileUpdateRequest) -> Result<ProfileResponse, ErrorResponseUseCase> {
+        let user = self.user_get_by_username(username).await?;
+        let before = self.profile(user.id).await?;
+
+        let current = match &before {
+            Some(res) => {
+                to_profile_update(res)
+            }
+            None => {
+                empty_profile(user.id)
+            }
+        };
+        let update = apply_profile_update(current, req);
+
+        if let Err(err) = verify_attributes(&self.profile.attributes_schema, &update.attributes) {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: err,
+            };
+
+            return Err(data);
+        }
+
+        match self.repo.profile_upsert(update).await {
+            Ok(res) => {
+                let after = Some(res);
+                let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+                record.actor_id = Some(user.id);
+                record.target_id = Some(user.id);
+                record.target_username = Some(user.username);
+                record.changes = diff(&audited_profile(&before), &audited_profile(&after));
+                self.audit.record(record).await;
+
+                Ok(to_profile_response(after))
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.profile_upsert failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_avatar_set", skip(self, ctx, data), fields(size = data.len()))]
+    async fn user_avatar_set(&self, ctx: AuditContext, username: String, data: Vec<u8>) -> Result<ProfileResponse, ErrorResponseUseCase> {
+        let user = self.user_get_by_username(username).await?;
+
+        if data.len() > self.profile.avatar_max_size {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::PAYLOAD_TOO_LARGE,
+                error_msg: format!("avatar must be at most {} bytes", self.profile.avatar_max_size),
+            };
+
+            return Err(data);
+        }
+
+        let format = match avatar_format(&data) {
+            Some(res) => {
+                res
+            }
+            None => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    error_msg: "avatar must be a PNG, JPEG or WebP image".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        // Decoding and resizing take a while; keep them off the async workers.
+        let thumbnails = match web::block(move || render_avatar(&data, format)).await {
+            Ok(Ok(res)) => {
+                res
+            }
+            Ok(Err(err)) => {
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::BAD_REQUEST,
+                    error_msg: err,
+                };
+
+                return Err(data);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "render_avatar failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        let key = avatar_key(user.id, &Uuid::new_v4().simple().to_string());
+        for (size, png) in thumbnails {
+            if let Err(err) = self.blobs.blob_put(&avatar_blob_key(&key, size), png).await {
+                tracing::error!(error = %err, "blobs.blob_put failed");
+                self.delete_avatar_blobs(&key).await;
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        }
+
+        let previous = match self.repo.profile_set_avatar(user.id, Some(key.clone())).await {
+            Ok(res) => {
+                res
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.profile_set_avatar failed");
+                self.delete_avatar_blobs(&key).await;
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                return Err(data);
+            }
+        };
+
+        if let Some(previous) = &previous {
+            self.delete_avatar_blobs(previous).await;
+        }
+
+        let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+        record.actor_id = Some(user.id);
+        record.target_id = Some(user.id);
+        record.target_username = Some(user.username);
+        record.changes = diff(&json!({"avatar": previous}), &json!({"avatar": key}));
+        self.audit.record(record).await;
+
+        let profile = self.profile(user.id).await?;
+
+        Ok(to_profile_response(profile))
+    }
+
+    #[instrument(name = "user_use_case.user_avatar_delete", skip(self, ctx))]
+    async fn user_avatar_delete(&self, ctx: AuditContext, username: String) -> Result<(), ErrorResponseUseCase> {
+        let user = self.user_get_by_username(username).await?;
+
+        let has_avatar = self.profile(user.id).await?.is_some_and(|profile| profile.avatar_key.is_some());
+        if !has_avatar {
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::NOT_FOUND,
+                error_msg: "No avatar".to_string(),
+            };
+
+            return Err(data);
+        }
+
+        match self.repo.profile_set_avatar(user.id, None).await {
+            Ok(previous) => {
+                if let Some(previous) = &previous {
+                    self.delete_avatar_blobs(previous).await;
+                }
+
+                let mut record = new_audit_record(&ctx, AUDIT_USER_UPDATE);
+                record.actor_id = Some(user.id);
+                record.target_id = Some(user.id);
+                record.target_username = Some(user.username);
+                record.changes = diff(&json!({"avatar": previous}), &json!({"avatar": null}));
+                self.audit.record(record).await;
+
+                Ok(())
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "repo.profile_set_avatar failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
+                Err(data)
+            }
+        }
+    }
+
+    #[instrument(name = "user_use_case.user_avatar_get", skip(self))]
+    async fn user_avatar_get(&self, id: i32, size: u32) -> Result<(String, Vec<u8>), ErrorResponseUseCase> {
+        if !AVATAR_SIZES.contains(&size) {
+            let sizes: Vec<String> = AVATAR_SIZES.iter().map(|size| size.to_string()).collect();
+            let data = ErrorResponseUseCase {
+                status_code: StatusCode::BAD_REQUEST,
+                error_msg: format!("size must be one of {}", sizes.join(", ")),
+            };
+
+            return Err(data);
+        }
+
+        let not_found = ErrorResponseUseCase {
+            status_code: StatusCode::NOT_FOUND,
+            error_msg: format!("User with id={} has no avatar", id),
+        };
+
+        let key = match self.profile(id).await?.and_then(|profile| profile.avatar_key) {
+            Some(res) => {
+                res
+            }
+            None => {
+                return Err(not_found);
+            }
+        };
+
+        // None when the avatar was replaced since the profile was read.
+        match self.blobs.blob_get(&avatar_blob_key(&key, size)).await {
+            Ok(Some(res)) => {
+                let version = key.rsplit('/').next().unwrap_or_default().to_string();
+                Ok((version, res))
+            }
+            Ok(None) => {
+                Err(not_found)
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "blobs.blob_get failed");
+                let data = ErrorResponseUseCase {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    error_msg: "Internal server error".to_string(),
+                };
+
                 Err(data)
             }
         }
@@ -1,14 +1,37 @@
-#![allow(deprecated)]
-
 use std::io;
-use actix_web;
-use actix_web::{App, HttpServer, web};
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::web;
 
-use crate::config::config::read_env;
+use crate::config::config::{read_env, LoadResult};
 use crate::pkg::postgres::connection;
+use crate::internal::user::entity::passkey::new_webauthn_config;
+use crate::internal::user::entity::profile::{new_profile_config, DEFAULT_ATTRIBUTES_SCHEMA};
+use crate::internal::user::entity::token::new_token_config;
 use crate::internal::user::usecase::repo::repo::new_user_repo;
+use crate::internal::user::usecase::blob::blob::new_local_blob_store;
 use crate::internal::user::usecase::traits::{new_user_use_case, UserUseCase};
-use crate::internal::controller::user_controller::user_routes;
+use crate::internal::health::usecase::repo::repo::new_health_repo;
+use crate::internal::health::usecase::traits::{new_health_use_case, HealthUseCase};
+use crate::internal::audit::usecase::repo::repo::new_audit_repo;
+use crate::internal::audit::usecase::traits::{new_audit_use_case, AuditUseCase};
+use crate::internal::oidc::entity::oidc::new_oidc_config;
+use crate::internal::oidc::usecase::repo::repo::new_oidc_repo;
+use crate::internal::oidc::usecase::traits::{new_oidc_use_case, OidcUseCase};
+use crate::internal::identity::usecase::repo::repo::new_identity_repo;
+use crate::internal::identity::usecase::traits::{new_identity_use_case, IdentityUseCase};
+use crate::internal::identity::usecase::webapi::web_api::{new_issuer_web_api, ISSUER_REQUEST_TIMEOUT};
+use crate::internal::org::usecase::repo::repo::new_org_repo;
+use crate::internal::org::usecase::traits::{new_org_use_case, OrgUseCase};
+use crate::internal::job::usecase::handlers::{new_job_handlers, new_job_schedules};
+use crate::internal::job::usecase::repo::repo::new_job_repo;
+use crate::internal::job::usecase::traits::{new_job_use_case, JobUseCase};
+use crate::internal::job::usecase::worker::spawn_job_runner;
+use crate::internal::controller::server::new_http_server;
+use crate::pkg::logger::logger::{init_logger, Logger};
+use crate::pkg::metrics::metrics::{new_metrics, Metrics};
+use crate::pkg::telemetry::telemetry::new_telemetry;
+use crate::pkg::shutdown::shutdown::{new_shutdown, run_until_shutdown};
 
 mod config;
 mod internal;
@@ -17,41 +40,173 @@ mod pkg;
 #[derive(Clone)]
 pub struct UseCases {
     user_use_case: UserUseCase,
+    health_use_case: HealthUseCase,
+    audit_use_case: AuditUseCase,
+    oidc_use_case: OidcUseCase,
+    identity_use_case: IdentityUseCase,
+    org_use_case: OrgUseCase,
+    job_use_case: JobUseCase,
+    metrics: Metrics,
+    logger: Logger,
 }
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
-    let cfg = read_env();
+    let cfg = match read_env() {
+        Ok(LoadResult::Loaded(cfg)) => {
+            *cfg
+        }
+        Ok(LoadResult::Help(usage)) => {
+            println!("{}", usage);
+            return Ok(());
+        }
+        Err(report) => {
+            eprintln!("{}", report);
+            std::process::exit(2);
+        }
+    };
+
+    let telemetry = match new_telemetry(&cfg.telemetry) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
 
-    let db = match connection::new_pg_connection(&cfg).await {
+    let logger = match init_logger(&cfg.logging.level, telemetry.tracer()) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let attributes_schema = match cfg.profile.attributes_schema.as_str() {
+        "" => {
+            DEFAULT_ATTRIBUTES_SCHEMA.to_string()
+        }
+        path => {
+            match std::fs::read_to_string(path) {
+                Ok(res) => {
+                    res
+                }
+                Err(err) => {
+                    eprintln!("profile.attributes_schema {}: {}", path, err);
+                    std::process::exit(2);
+                }
+            }
+        }
+    };
+
+    let profile = match new_profile_config(&attributes_schema, cfg.profile.avatar_max_size) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            eprintln!("profile.attributes_schema: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let db = match connection::new_pg_connection(&cfg.database).await {
         Ok(database) => {
             database
         }
         Err(err) => {
-            panic!("Error in new_pg_connection: {}", err)
+            tracing::error!(error = %err, "can't connect to postgres");
+            std::process::exit(1);
         }
     };
 
+    if let Err(err) = connection::run_migrations(&db).await {
+        tracing::error!(error = %err, "can't run migrations");
+        std::process::exit(1);
+    }
+
     let db = web::Data::new(db);
-    let user_repo = new_user_repo(db);
-    let user_use_case = new_user_use_case(user_repo);
+    let metrics = new_metrics(cfg.metrics.max_route_labels);
+    let audit_use_case = new_audit_use_case(Arc::new(new_audit_repo(db.clone())));
+    let user_repo = new_user_repo(db.clone());
+    let org_repo = Arc::new(new_org_repo(db.clone()));
+    let oidc_repo = Arc::new(new_oidc_repo(db.clone()));
+    let user_use_case = new_user_use_case(
+        Arc::new(user_repo),
+        audit_use_case.clone(),
+        new_token_config(cfg.auth.token_secret.expose().to_string(), cfg.auth.token_life_time),
+        metrics.clone(),
+        cfg.auth.admins.clone(),
+        cfg.auth.mfa_issuer.clone(),
+        new_webauthn_config(cfg.auth.webauthn_rp_id.clone(), cfg.auth.mfa_issuer.clone(), cfg.auth.webauthn_origin.clone()),
+        profile,
+        Arc::new(new_local_blob_store(&cfg.profile.avatar_dir)),
+        org_repo.clone(),
+        oidc_repo.clone(),
+    );
+    let oidc_use_case = new_oidc_use_case(
+        oidc_repo,
+        user_use_case.clone(),
+        audit_use_case.clone(),
+        new_oidc_config(cfg.auth.oidc_issuer.clone(), cfg.auth.token_secret.expose(), cfg.auth.token_life_time),
+    );
+    let identity_use_case = new_identity_use_case(
+        Arc::new(new_identity_repo(db.clone())),
+        Arc::new(new_issuer_web_api(ISSUER_REQUEST_TIMEOUT)),
+        user_use_case.clone(),
+        audit_use_case.clone(),
+    );
+    let org_use_case = new_org_use_case(org_repo, user_use_case.clone(), audit_use_case.clone());
+
+    let job_schedules = match new_job_schedules(&cfg.jobs) {
+        Ok(res) => {
+            res
+        }
+        Err(err) => {
+            eprintln!("jobs: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let job_repo = Arc::new(new_job_repo(db.clone()));
+    let job_use_case = new_job_use_case(
+        job_repo.clone(),
+        new_job_handlers(&cfg.jobs, job_repo, user_use_case.clone()),
+        cfg.jobs.max_attempts,
+        Duration::from_secs(cfg.jobs.lock_timeout),
+    );
+
+    let shutdown = new_shutdown();
+    let health_repo = new_health_repo(db.clone(), cfg.database.max_conn);
+    let health_use_case = new_health_use_case(
+        Arc::new(health_repo),
+        shutdown.clone(),
+        Duration::from_millis(cfg.health.check_timeout),
+        connection::expected_migration_version(),
+    );
 
     let use_cases = UseCases {
-        user_use_case
-    };
-
-    println!("<--START-SERVER--> {} {}", cfg.http_host, cfg.http_port);
-    HttpServer::new(move || {
-        App::new()
-            .data(use_cases.clone())
-            .service(user_routes::user_auth)
-            .service(user_routes::user_create)
-            .service(user_routes::user_list)
-            .service(user_routes::user_get)
-            .service(user_routes::user_update_by_id)
-            .service(user_routes::user_change_password)
-    })
-        .bind((cfg.db_host, 8081))?
-        .run()
-        .await
+        user_use_case,
+        health_use_case,
+        audit_use_case,
+        oidc_use_case,
+        identity_use_case,
+        org_use_case,
+        job_use_case: job_use_case.clone(),
+        metrics,
+        logger,
+    };
+
+    tracing::info!(host = %cfg.http.host, port = cfg.http.port, "starting server");
+    let server = new_http_server(use_cases, &cfg, shutdown.clone())?;
+    spawn_job_runner(job_use_case, job_schedules, cfg.jobs.workers, Duration::from_millis(cfg.jobs.poll_interval), &shutdown);
+    let res = run_until_shutdown(server, &shutdown, &cfg.shutdown).await;
+
+    db.close().await;
+    telemetry.shutdown();
+    tracing::info!("server stopped");
+
+    res
 }
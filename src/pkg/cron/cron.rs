@@ -0,0 +1,134 @@
+use std::fmt;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+
+// How far ahead next_after looks before deciding an expression never fires,
+// e.g. for the 30th of February.
+const CRON_HORIZON_DAYS: i64 = 5 * 366;
+
+// A five-field cron expression: minute, hour, day of month, month and day of
+// week, evaluated in UTC. A field is *, a number, a range a-b, any of these
+// with a step /n, or a comma separated list of them. Sunday is 0 or 7. As in
+// Vixie cron, when both day fields are restricted a day matching either one
+// fires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool, // day of month is *
+    any_weekday: bool, // day of week is *
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+pub fn parse_cron(expr: &str) -> Result<Cron, String> {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(format!("cron expression \"{}\" must have 5 fields", expr));
+    }
+
+    let field = |index: usize, name: &str, min: u32, max: u32| {
+        parse_field(fields[index], min, max).map_err(|err| format!("cron expression \"{}\": {} {}", expr, name, err))
+    };
+    let weekdays = field(4, "day of week", 0, 7)?;
+
+    Ok(Cron {
+        expr: fields.join(" "),
+        minutes: field(0, "minute", 0, 59)?,
+        hours: field(1, "hour", 0, 23)?,
+        days: field(2, "day of month", 1, 31)?,
+        months: field(3, "month", 1, 12)?,
+        weekdays: (weekdays | weekdays >> 7) & 0x7f,
+        any_day: fields[2].starts_with('*'),
+        any_weekday: fields[4].starts_with('*'),
+    })
+}
+
+// The values of one field as a bit set.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (range, Some(step)),
+                    _ => return Err(format!("has invalid step \"{}\"", part)),
+                }
+            }
+            None => {
+                (part, None)
+            }
+        };
+
+        let value = |value: &str| match value.parse::<u32>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(format!("has invalid value \"{}\", expected {} to {}", part, min, max)),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (value(from)?, value(to)?),
+            None if step.is_some() => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if from > to {
+            return Err(format!("has invalid range \"{}\"", part));
+        }
+
+        for value in (from..=to).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl Cron {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    // The first minute strictly after time that the expression fires at;
+    // None when it never does.
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let time = time.naive_utc();
+        let mut next = time.date().and_hms_opt(time.hour(), time.minute(), 0)? + Duration::minutes(1);
+        let horizon = next + Duration::days(CRON_HORIZON_DAYS);
+
+        // Whole months, days and hours that can't match are skipped at once.
+        while next <= horizon {
+            let date = next.date();
+            if self.months & (1 << date.month()) == 0 {
+                let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(date) {
+                next = start_of_day(date.succ_opt()?)?;
+            } else if self.hours & (1 << next.hour()) == 0 {
+                next = date.and_hms_opt(next.hour(), 0, 0)? + Duration::hours(1);
+            } else if self.minutes & (1 << next.minute()) == 0 {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next.and_utc());
+            }
+        }
+
+        None
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> Option<NaiveDateTime> {
+    date.and_hms_opt(0, 0, 0)
+}
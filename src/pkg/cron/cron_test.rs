@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::pkg::cron::cron::parse_cron;
+
+    struct TestCase<T, A> {
+        input: A,
+        output: T,
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_cron_test() {
+        let test_cases = vec! {
+            TestCase { input: "*/15 * * * *", output: Ok("*/15 * * * *") },
+            TestCase { input: "  0 3   * * 1-5 ", output: Ok("0 3 * * 1-5") },
+            TestCase { input: "0,30 8-18/2 1,15 */3 7", output: Ok("0,30 8-18/2 1,15 */3 7") },
+            TestCase { input: "* * * *", output: Err("cron expression \"* * * *\" must have 5 fields".to_string()) },
+            TestCase { input: "60 * * * *", output: Err("cron expression \"60 * * * *\": minute has invalid value \"60\", expected 0 to 59".to_string()) },
+            TestCase { input: "* * 0 * *", output: Err("cron expression \"* * 0 * *\": day of month has invalid value \"0\", expected 1 to 31".to_string()) },
+            TestCase { input: "*/0 * * * *", output: Err("cron expression \"*/0 * * * *\": minute has invalid step \"*/0\"".to_string()) },
+            TestCase { input: "* 5-1 * * *", output: Err("cron expression \"* 5-1 * * *\": hour has invalid range \"5-1\"".to_string()) },
+            TestCase { input: "* * * JAN *", output: Err("cron expression \"* * * JAN *\": month has invalid value \"JAN\", expected 1 to 12".to_string()) },
+        };
+
+        for test_case in test_cases {
+            let res = parse_cron(test_case.input);
+            assert_eq!(res.as_ref().map(|cron| cron.to_string()).map_err(|err| err.clone()), test_case.output.map(|expr| expr.to_string()).map_err(|err| err.to_string()));
+        }
+    }
+
+    #[test]
+    fn next_after_test() {
+        // 2024-01-31 is a Wednesday, 2024 a leap year.
+        let test_cases = vec! {
+            TestCase { input: ("*/15 * * * *", "2024-01-31T10:07:42Z"), output: Some("2024-01-31T10:15:00Z") },
+            TestCase { input: ("*/15 * * * *", "2024-01-31T10:15:00Z"), output: Some("2024-01-31T10:30:00Z") },
+            TestCase { input: ("0 3 * * *", "2024-01-31T03:00:00Z"), output: Some("2024-02-01T03:00:00Z") },
+            TestCase { input: ("30 23 * * *", "2024-12-31T23:45:00Z"), output: Some("2025-01-01T23:30:00Z") },
+            TestCase { input: ("0 9 * * 1-5", "2024-02-02T12:00:00Z"), output: Some("2024-02-05T09:00:00Z") },
+            TestCase { input: ("0 0 * * 7", "2024-01-31T00:00:00Z"), output: Some("2024-02-04T00:00:00Z") },
+            TestCase { input: ("0 0 29 2 *", "2024-03-01T00:00:00Z"), output: Some("2028-02-29T00:00:00Z") },
+            // Either day field matches when both are restricted.
+            TestCase { input: ("0 0 13 * 5", "2024-01-31T00:00:00Z"), output: Some("2024-02-02T00:00:00Z") },
+            TestCase { input: ("0 0 1 * 5", "2024-01-31T00:00:00Z"), output: Some("2024-02-01T00:00:00Z") },
+            TestCase { input: ("0 0 30 2 *", "2024-01-31T00:00:00Z"), output: None },
+        };
+
+        for test_case in test_cases {
+            let (expr, time) = test_case.input;
+            let next = parse_cron(expr).unwrap().next_after(at(time));
+            assert_eq!(next, test_case.output.map(at), "{} after {}", expr, time);
+        }
+
+        let cron = parse_cron("* * * * *").unwrap();
+        assert_eq!(cron.next_after(Utc.timestamp_opt(1676000000, 999_000_000).unwrap()), Some(Utc.timestamp_opt(1676000040, 0).unwrap()));
+    }
+}
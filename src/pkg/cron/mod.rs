@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod cron;
+pub mod cron_test;
@@ -0,0 +1,113 @@
+use std::io;
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+use opentelemetry_sdk::trace::SdkTracer;
+use tracing::Dispatch;
+use utoipa::ToSchema;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+pub const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+// Logger writes one JSON object per line. Events carry the fields of every
+// enclosing span (request id, use case, repo call), and the level can be
+// changed at runtime through the reload handle. When a tracer is given the
+// same spans, with debug events such as failed queries, are exported to
+// OpenTelemetry independently of the log level.
+#[derive(Clone)]
+pub struct Logger {
+    dispatch: Dispatch,
+    filter: reload::Handle<EnvFilter, Registry>,
+    level: Arc<RwLock<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LogLevel {
+    pub level: String,
+}
+
+pub fn new_logger<W>(level: &str, writer: W, tracer: Option<SdkTracer>) -> Result<Logger, String>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let (level, filter) = parse_level(level)?;
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let logs = fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(true)
+        .with_writer(writer)
+        .with_filter(filter);
+
+    let traces = tracer.map(|tracer| {
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(LevelFilter::DEBUG)
+    });
+
+    let subscriber = Registry::default().with(logs).with(traces);
+
+    Ok(Logger {
+        dispatch: Dispatch::new(subscriber),
+        filter: handle,
+        level: Arc::new(RwLock::new(level)),
+    })
+}
+
+// Installs the logger process wide and routes records from the `log` crate
+// (actix, sqlx) through it.
+pub fn init_logger(level: &str, tracer: Option<SdkTracer>) -> Result<Logger, String> {
+    let logger = new_logger(level, io::stdout, tracer)?;
+
+    match logger.dispatch.clone().try_init() {
+        Ok(_) => {
+            Ok(logger)
+        }
+        Err(err) => {
+            Err(format!("can't install logger: {}", err))
+        }
+    }
+}
+
+impl Logger {
+    #[cfg(test)]
+    pub fn dispatch(&self) -> &Dispatch {
+        &self.dispatch
+    }
+
+    pub fn level(&self) -> String {
+        self.level.read().unwrap().clone()
+    }
+
+    pub fn set_level(&self, level: &str) -> Result<(), String> {
+        let (level, filter) = parse_level(level)?;
+
+        if let Err(err) = self.filter.reload(filter) {
+            return Err(format!("can't change log level: {}", err));
+        }
+        *self.level.write().unwrap() = level;
+
+        Ok(())
+    }
+}
+
+fn parse_level(level: &str) -> Result<(String, EnvFilter), String> {
+    let level = level.trim().to_lowercase();
+    if !LOG_LEVELS.contains(&level.as_str()) {
+        return Err(format!("log level must be one of {}", LOG_LEVELS.join(", ")));
+    }
+
+    match EnvFilter::try_new(&level) {
+        Ok(filter) => {
+            Ok((level, filter))
+        }
+        Err(err) => {
+            Err(err.to_string())
+        }
+    }
+}
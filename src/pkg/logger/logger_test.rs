@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tracing::{debug, info, info_span};
+
+    use crate::pkg::logger::logger::new_logger;
+    use crate::pkg::logger::test_writer::new_test_writer;
+
+    #[test]
+    fn json_lines_with_spans_test() {
+        let writer = new_test_writer();
+        let logger = new_logger("info", writer.clone(), None).unwrap();
+
+        tracing::dispatcher::with_default(logger.dispatch(), || {
+            let span = info_span!("request", request_id = "req-1");
+            let _enter = span.enter();
+            info!(status = 200, "request completed");
+            debug!("hidden at info");
+        });
+
+        let lines = writer.lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["level"], json!("INFO"));
+        assert_eq!(lines[0]["message"], json!("request completed"));
+        assert_eq!(lines[0]["status"], json!(200));
+        assert_eq!(lines[0]["spans"][0]["name"], json!("request"));
+        assert_eq!(lines[0]["spans"][0]["request_id"], json!("req-1"));
+    }
+
+    #[test]
+    fn set_level_test() {
+        let writer = new_test_writer();
+        let logger = new_logger("WARN", writer.clone(), None).unwrap();
+        assert_eq!(logger.level(), "warn");
+
+        tracing::dispatcher::with_default(logger.dispatch(), || info!("dropped"));
+        assert!(writer.lines().is_empty());
+
+        logger.set_level("debug").unwrap();
+        assert_eq!(logger.level(), "debug");
+        tracing::dispatcher::with_default(logger.dispatch(), || debug!("kept"));
+        assert_eq!(writer.lines()[0]["message"], json!("kept"));
+
+        assert!(logger.set_level("loud").is_err());
+        assert_eq!(logger.level(), "debug");
+    }
+
+    #[test]
+    fn invalid_level_test() {
+        assert!(new_logger("verbose", new_test_writer(), None).is_err());
+    }
+}
@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod logger;
+pub mod logger_test;
+#[cfg(test)]
+pub mod test_writer;
@@ -0,0 +1,43 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+// Collects log output in memory so tests can assert on the JSON lines.
+#[derive(Clone, Default)]
+pub struct TestWriter {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+pub fn new_test_writer() -> TestWriter {
+    TestWriter::default()
+}
+
+impl TestWriter {
+    pub fn lines(&self) -> Vec<Value> {
+        let buffer = self.buffer.lock().unwrap();
+        String::from_utf8_lossy(&buffer)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl io::Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for TestWriter {
+    type Writer = TestWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
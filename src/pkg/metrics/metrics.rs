@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+pub const LABEL_OTHER: &str = "other";
+
+const HTTP_METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+// Metrics owns a private registry so tests can assert on exact values; clones
+// share the same underlying collectors.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    logins: IntCounterVec,
+    password_hash: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pool_acquire_wait: Gauge,
+    routes: Arc<LabelGuard>,
+}
+
+// LabelGuard caps how many distinct values a label may take; values seen after
+// the cap is reached are reported as "other" to keep series count bounded.
+pub struct LabelGuard {
+    max: usize,
+    seen: Mutex<HashSet<String>>,
+}
+
+impl LabelGuard {
+    pub fn admit(&self, value: &str) -> String {
+        let mut seen = self.seen.lock().unwrap();
+        if seen.contains(value) {
+            return value.to_string();
+        }
+
+        if seen.len() < self.max {
+            seen.insert(value.to_string());
+            return value.to_string();
+        }
+
+        LABEL_OTHER.to_string()
+    }
+}
+
+pub fn new_metrics(max_route_labels: usize) -> Metrics {
+    let registry = Registry::new();
+
+    let http_requests = IntCounterVec::new(
+        Opts::new("http_requests_total", "HTTP requests by method, route and status"),
+        &["method", "route", "status"],
+    ).unwrap();
+    let http_duration = HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method, route and status"),
+        &["method", "route", "status"],
+    ).unwrap();
+    let logins = IntCounterVec::new(
+        Opts::new("auth_login_total", "Login attempts by result"),
+        &["result"],
+    ).unwrap();
+    let password_hash = HistogramVec::new(
+        HistogramOpts::new("password_hash_duration_seconds", "bcrypt hash and verify duration")
+            .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+        &["op"],
+    ).unwrap();
+    let pool_connections = IntGaugeVec::new(
+        Opts::new("db_pool_connections", "Postgres pool connections by state (size, idle, max)"),
+        &["state"],
+    ).unwrap();
+    let pool_acquire_wait = Gauge::new(
+        "db_pool_acquire_wait_seconds",
+        "Time to acquire a pool connection, sampled at scrape",
+    ).unwrap();
+
+    registry.register(Box::new(http_requests.clone())).unwrap();
+    registry.register(Box::new(http_duration.clone())).unwrap();
+    registry.register(Box::new(logins.clone())).unwrap();
+    registry.register(Box::new(password_hash.clone())).unwrap();
+    registry.register(Box::new(pool_connections.clone())).unwrap();
+    registry.register(Box::new(pool_acquire_wait.clone())).unwrap();
+
+    Metrics {
+        registry,
+        http_requests,
+        http_duration,
+        logins,
+        password_hash,
+        pool_connections,
+        pool_acquire_wait,
+        routes: Arc::new(LabelGuard {
+            max: max_route_labels,
+            seen: Mutex::new(HashSet::new()),
+        }),
+    }
+}
+
+impl Metrics {
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        let method = if HTTP_METHODS.contains(&method) { method } else { LABEL_OTHER };
+        let route = self.routes.admit(route);
+        let status = status.to_string();
+        let labels = [method, route.as_str(), status.as_str()];
+
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration.with_label_values(&labels).observe(duration.as_secs_f64());
+    }
+
+    pub fn login(&self, result: &str) {
+        self.logins.with_label_values(&[result]).inc();
+    }
+
+    pub fn observe_password_hash(&self, op: &str, duration: Duration) {
+        self.password_hash.with_label_values(&[op]).observe(duration.as_secs_f64());
+    }
+
+    pub fn set_pool(&self, size: u32, idle: usize, max: u32, acquire_wait: Option<Duration>) {
+        self.pool_connections.with_label_values(&["size"]).set(size as i64);
+        self.pool_connections.with_label_values(&["idle"]).set(idle as i64);
+        self.pool_connections.with_label_values(&["max"]).set(max as i64);
+
+        if let Some(wait) = acquire_wait {
+            self.pool_acquire_wait.set(wait.as_secs_f64());
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).unwrap();
+
+        String::from_utf8(buffer).unwrap()
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::pkg::metrics::metrics::{new_metrics, LABEL_OTHER};
+
+    #[test]
+    fn observe_http_test() {
+        let metrics = new_metrics(10);
+        metrics.observe_http("GET", "/api/v1/user/{id}/get", 200, Duration::from_millis(5));
+        metrics.observe_http("GET", "/api/v1/user/{id}/get", 200, Duration::from_millis(5));
+        metrics.observe_http("BREW", "/api/v1/user/list", 404, Duration::from_millis(5));
+
+        let out = metrics.encode();
+        assert!(out.contains(r#"http_requests_total{method="GET",route="/api/v1/user/{id}/get",status="200"} 2"#));
+        assert!(out.contains(r#"http_requests_total{method="other",route="/api/v1/user/list",status="404"} 1"#));
+        assert!(out.contains(r#"http_request_duration_seconds_count{method="GET",route="/api/v1/user/{id}/get",status="200"} 2"#));
+    }
+
+    #[test]
+    fn route_label_guard_test() {
+        let metrics = new_metrics(2);
+        for route in ["/a", "/b", "/c", "/d", "/a"] {
+            metrics.observe_http("GET", route, 200, Duration::from_millis(1));
+        }
+
+        let out = metrics.encode();
+        assert!(out.contains(r#"route="/a",status="200"} 2"#));
+        assert!(out.contains(r#"route="/b",status="200"} 1"#));
+        assert!(!out.contains(r#"route="/c""#));
+        assert!(out.contains(&format!(r#"route="{}",status="200"}} 2"#, LABEL_OTHER)));
+    }
+
+    #[test]
+    fn login_and_password_hash_test() {
+        let metrics = new_metrics(10);
+        metrics.login("success");
+        metrics.login("failure");
+        metrics.login("failure");
+        metrics.observe_password_hash("verify", Duration::from_millis(30));
+
+        let out = metrics.encode();
+        assert!(out.contains(r#"auth_login_total{result="success"} 1"#));
+        assert!(out.contains(r#"auth_login_total{result="failure"} 2"#));
+        assert!(out.contains(r#"password_hash_duration_seconds_bucket{op="verify",le="0.05"} 1"#));
+        assert!(out.contains(r#"password_hash_duration_seconds_bucket{op="verify",le="0.01"} 0"#));
+    }
+
+    #[test]
+    fn set_pool_test() {
+        let metrics = new_metrics(10);
+        metrics.set_pool(3, 2, 10, Some(Duration::from_millis(250)));
+        metrics.set_pool(4, 1, 10, None);
+
+        let out = metrics.encode();
+        assert!(out.contains(r#"db_pool_connections{state="size"} 4"#));
+        assert!(out.contains(r#"db_pool_connections{state="idle"} 1"#));
+        assert!(out.contains(r#"db_pool_connections{state="max"} 10"#));
+        assert!(out.contains("db_pool_acquire_wait_seconds 0.25"));
+    }
+}
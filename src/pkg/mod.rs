@@ -1 +1,7 @@
-pub mod postgres;
\ No newline at end of file
+pub mod cron;
+pub mod logger;
+pub mod metrics;
+pub mod postgres;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tls;
@@ -1,14 +1,35 @@
-use sqlx::{Pool, Postgres};
-use sqlx::postgres::PgPoolOptions;
+use log::LevelFilter;
+use sqlx::{ConnectOptions, Pool, Postgres};
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
-use crate::config::config::Config;
+use crate::config::config::DatabaseConfig;
 
 pub type Db = Pool<Postgres>;
 
-pub async fn new_pg_connection(cfg: &Config) -> Result<Db, sqlx::Error> {
-    let conn_string = format!("postgres://{}:{}@{}/{}", cfg.db_user, cfg.db_password, cfg.db_host, cfg.db_name);
+pub static MIGRATOR: Migrator = sqlx::migrate!("./db/migrations");
+
+pub async fn new_pg_connection(cfg: &DatabaseConfig) -> Result<Db, sqlx::Error> {
+    let mut options = PgConnectOptions::new()
+        .host(&cfg.host)
+        .port(cfg.port)
+        .username(&cfg.user)
+        .password(cfg.password.expose())
+        .database(&cfg.name);
+    // Every statement is logged at info by default; keep them for debug.
+    options.log_statements(LevelFilter::Debug);
+
     PgPoolOptions::new()
-        .max_connections(cfg.db_max_conn)
-        .connect(&conn_string)
+        .max_connections(cfg.max_conn)
+        .connect_with(options)
         .await
 }
+
+pub async fn run_migrations(db: &Db) -> Result<(), MigrateError> {
+    MIGRATOR.run(db).await
+}
+
+// Version of the newest migration compiled into this binary.
+pub fn expected_migration_version() -> i64 {
+    MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0)
+}
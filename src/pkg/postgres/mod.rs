@@ -1 +1,5 @@
-pub mod connection;
\ No newline at end of file
+pub mod connection;
+pub mod trace;
+#[cfg(test)]
+pub mod test_db;
+pub mod test_db_test;
@@ -0,0 +1,93 @@
+use std::str::FromStr;
+use sqlx::{Connection, Executor, PgConnection};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use uuid::Uuid;
+
+use crate::pkg::postgres::connection::{run_migrations, Db};
+
+// Postgres tests are #[ignore]d, so a plain cargo test does not report them as
+// passed without running them. Run them with TEST_DATABASE_URL pointing at a
+// server whose user may create databases:
+// TEST_DATABASE_URL=postgres://postgres@localhost:5432/postgres cargo test -- --include-ignored
+pub const TEST_DATABASE_URL: &str = "TEST_DATABASE_URL";
+
+pub struct TestDb {
+    pub db: Db,
+    _database: TestDatabase,
+}
+
+// Drops the database when it goes out of scope, also when the test panics.
+struct TestDatabase {
+    url: String,
+    name: String,
+}
+
+impl Drop for TestDatabase {
+    // Drop can't await, and the test's runtime may be unwinding, so the DROP
+    // runs on a runtime of its own in a separate thread.
+    fn drop(&mut self) {
+        let url = self.url.clone();
+        let sql = format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name);
+
+        let dropped = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            runtime.block_on(async {
+                let mut admin = PgConnection::connect(&url).await?;
+                admin.execute(sql.as_str()).await?;
+                admin.close().await
+            }).map_err(std::io::Error::other)
+        }).join();
+
+        match dropped {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                eprintln!("can't drop test database {}: {}", self.name, err);
+            }
+            Err(_panic) => {
+                eprintln!("can't drop test database {}", self.name);
+            }
+        }
+    }
+}
+
+// Creates a throwaway database with all migrations applied. Panics when
+// TEST_DATABASE_URL is not set, so an ignored test run without a database
+// fails instead of passing.
+pub async fn new_test_db() -> TestDb {
+    let url = match std::env::var(TEST_DATABASE_URL) {
+        Ok(res) => {
+            res
+        }
+        Err(_err) => {
+            panic!("{} is not set, Postgres tests need a database", TEST_DATABASE_URL);
+        }
+    };
+
+    let name = format!("test_{}", Uuid::new_v4().simple());
+    let mut admin = PgConnection::connect(&url).await.expect("can't connect to TEST_DATABASE_URL");
+    admin.execute(format!("CREATE DATABASE {}", name).as_str()).await.expect("can't create test database");
+    admin.close().await.ok();
+    let database = TestDatabase { url: url.clone(), name: name.clone() };
+
+    let options = PgConnectOptions::from_str(&url).expect("invalid TEST_DATABASE_URL").database(&name);
+    let db = PgPoolOptions::new()
+        .max_connections(2)
+        .connect_with(options)
+        .await
+        .expect("can't connect to test database");
+
+    run_migrations(&db).await.expect("can't run migrations");
+
+    TestDb {
+        db,
+        _database: database,
+    }
+}
+
+impl TestDb {
+    // Closes the pool before the database is dropped; a test that panics
+    // before getting here still has its database dropped.
+    pub async fn close(self) {
+        self.db.close().await;
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use sqlx::{Connection, PgConnection};
+
+    use crate::pkg::postgres::test_db::{new_test_db, TEST_DATABASE_URL};
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn drop_on_panic_test() {
+        let name = Arc::new(Mutex::new(String::new()));
+
+        let task_name = name.clone();
+        let res = tokio::spawn(async move {
+            let test_db = new_test_db().await;
+            let (current,): (String,) = sqlx::query_as("SELECT current_database()").fetch_one(&test_db.db).await.unwrap();
+            *task_name.lock().unwrap() = current;
+            panic!("failing test");
+        }).await;
+        assert!(res.unwrap_err().is_panic());
+
+        let name = name.lock().unwrap().clone();
+        assert!(name.starts_with("test_"));
+        let mut admin = PgConnection::connect(&std::env::var(TEST_DATABASE_URL).unwrap()).await.unwrap();
+        let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname=$1)")
+            .bind(&name)
+            .fetch_one(&mut admin)
+            .await
+            .unwrap();
+        assert!(!exists, "{} was left behind", name);
+        admin.close().await.ok();
+    }
+}
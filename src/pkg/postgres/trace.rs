@@ -0,0 +1,12 @@
+use tracing::Span;
+
+// Repo spans declare db.statement and db.rows as empty fields so the SQL and
+// its row count end up on the span (and on the exported OpenTelemetry span).
+
+pub fn record_statement(sql: &str) {
+    Span::current().record("db.statement", sql);
+}
+
+pub fn record_rows(rows: usize) {
+    Span::current().record("db.rows", rows as u64);
+}
@@ -0,0 +1,136 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use actix_web::dev::Server;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::config::config::ShutdownConfig;
+
+// Shutdown coordinates a graceful stop: readiness is flipped first so load
+// balancers stop routing, then the HTTP server drains in-flight requests, then
+// background tasks spawned through it get their shutdown signal and are awaited.
+#[derive(Clone)]
+pub struct Shutdown {
+    ready: Arc<AtomicBool>,
+    signal: Arc<watch::Sender<bool>>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+pub fn new_shutdown() -> Shutdown {
+    let (signal, _) = watch::channel(false);
+
+    Shutdown {
+        ready: Arc::new(AtomicBool::new(true)),
+        signal: Arc::new(signal),
+        tasks: Arc::new(Mutex::new(Vec::new())),
+    }
+}
+
+impl Shutdown {
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    // Resolves once shutdown has begun; background tasks select on it.
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.signal.subscribe();
+
+        async move {
+            while !*rx.borrow() {
+                if rx.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task);
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|t| !t.is_finished());
+        tasks.push(handle);
+    }
+
+    pub fn stop_accepting(&self) {
+        self.ready.store(false, Ordering::SeqCst);
+    }
+
+    // Signals background tasks and waits for them; returns false when some
+    // were still running after the timeout and had to be abandoned.
+    pub async fn drain_tasks(&self, timeout: Duration) -> bool {
+        self.signal.send_replace(true);
+
+        let tasks: Vec<JoinHandle<()>> = self.tasks.lock().unwrap().drain(..).collect();
+        let aborts: Vec<_> = tasks.iter().map(|t| t.abort_handle()).collect();
+
+        let joined = tokio::time::timeout(timeout, async {
+            for task in tasks {
+                task.await.ok();
+            }
+        }).await;
+
+        if joined.is_err() {
+            for abort in aborts {
+                abort.abort();
+            }
+            return false;
+        }
+
+        true
+    }
+}
+
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut term = signal(SignalKind::terminate()).expect("can't listen for SIGTERM");
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.ok();
+    }
+}
+
+// Runs the server until SIGTERM/SIGINT (or until it stops by itself), then
+// performs the shutdown sequence described on Shutdown.
+pub async fn run_until_shutdown(server: Server, shutdown: &Shutdown, cfg: &ShutdownConfig) -> std::io::Result<()> {
+    let handle = server.handle();
+    let mut server = actix_web::rt::spawn(server);
+
+    tokio::select! {
+        res = &mut server => {
+            shutdown.drain_tasks(Duration::from_secs(cfg.drain_timeout)).await;
+            return res.unwrap_or_else(|err| Err(std::io::Error::other(err)));
+        }
+        _ = wait_for_signal() => {}
+    }
+
+    tracing::info!(drain_timeout = cfg.drain_timeout, "shutting down, readiness off");
+    shutdown.stop_accepting();
+    tokio::time::sleep(Duration::from_secs(cfg.readiness_delay)).await;
+
+    // drain_timeout bounds the whole drain: the server is stopped with it as
+    // its shutdown_timeout, and background tasks only get what is left.
+    let drain_timeout = Duration::from_secs(cfg.drain_timeout);
+    let draining = Instant::now();
+    handle.stop(true).await;
+    let res = server.await.unwrap_or_else(|err| Err(std::io::Error::other(err)));
+
+    if !shutdown.drain_tasks(drain_timeout.saturating_sub(draining.elapsed())).await {
+        tracing::warn!(drain_timeout = cfg.drain_timeout, "background tasks did not finish in time");
+    }
+
+    res
+}
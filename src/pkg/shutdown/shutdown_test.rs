@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use crate::pkg::shutdown::shutdown::new_shutdown;
+
+    #[actix_web::test]
+    async fn drain_tasks_test() {
+        let shutdown = new_shutdown();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let wait = shutdown.wait();
+        let task_finished = finished.clone();
+        shutdown.spawn(async move {
+            wait.await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            task_finished.store(true, Ordering::SeqCst);
+        });
+
+        assert!(shutdown.is_ready());
+        shutdown.stop_accepting();
+        assert!(!shutdown.is_ready());
+        assert!(!finished.load(Ordering::SeqCst));
+
+        assert!(shutdown.drain_tasks(Duration::from_secs(5)).await);
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[actix_web::test]
+    async fn drain_tasks_timeout_test() {
+        let shutdown = new_shutdown();
+
+        shutdown.spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        assert!(!shutdown.drain_tasks(Duration::from_millis(50)).await);
+    }
+
+    #[actix_web::test]
+    async fn wait_after_shutdown_test() {
+        let shutdown = new_shutdown();
+        shutdown.drain_tasks(Duration::from_millis(10)).await;
+
+        tokio::time::timeout(Duration::from_secs(1), shutdown.wait()).await.unwrap();
+    }
+}
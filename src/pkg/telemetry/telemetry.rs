@@ -0,0 +1,204 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use actix_web::http::header::HeaderMap;
+use chrono::{DateTime, SecondsFormat, Utc};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{Status, TraceContextExt, TraceId, TracerProvider};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map, Value};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::config::config::TelemetryConfig;
+
+pub const EXPORTER_NONE: &str = "none";
+pub const EXPORTER_OTLP: &str = "otlp";
+pub const EXPORTER_STDOUT: &str = "stdout";
+pub const EXPORTER_FILE: &str = "file";
+pub const EXPORTERS: &[&str] = &[EXPORTER_NONE, EXPORTER_OTLP, EXPORTER_STDOUT, EXPORTER_FILE];
+
+const TRACER_NAME: &str = env!("CARGO_PKG_NAME");
+
+// Telemetry owns the span pipeline. Spans come from tracing (see Logger) and
+// are exported in batches; shutdown flushes whatever is still queued.
+#[derive(Clone, Default)]
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+pub fn new_telemetry(cfg: &TelemetryConfig) -> Result<Telemetry, String> {
+    let builder = SdkTracerProvider::builder()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(cfg.sample_ratio))))
+        .with_resource(Resource::builder().with_service_name(cfg.service_name.clone()).build());
+
+    let builder = match cfg.exporter.as_str() {
+        EXPORTER_NONE => {
+            return Ok(Telemetry::default());
+        }
+        EXPORTER_OTLP => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(cfg.otlp_endpoint.clone())
+                .build()
+                .map_err(|err| format!("can't create otlp exporter: {}", err))?;
+
+            builder.with_batch_exporter(exporter)
+        }
+        EXPORTER_STDOUT => {
+            builder.with_batch_exporter(new_json_span_exporter(io::stdout()))
+        }
+        EXPORTER_FILE => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&cfg.file_path)
+                .map_err(|err| format!("can't open {}: {}", cfg.file_path, err))?;
+
+            builder.with_batch_exporter(new_json_span_exporter(file))
+        }
+        exporter => {
+            return Err(format!("unknown exporter {}", exporter));
+        }
+    };
+
+    Ok(Telemetry { provider: Some(builder.build()) })
+}
+
+impl Telemetry {
+    // Exports every finished span synchronously; used by tests.
+    #[cfg(test)]
+    pub fn with_exporter<E: SpanExporter + 'static>(exporter: E) -> Telemetry {
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter).build();
+
+        Telemetry { provider: Some(provider) }
+    }
+
+    pub fn tracer(&self) -> Option<SdkTracer> {
+        self.provider.as_ref().map(|provider| provider.tracer(TRACER_NAME))
+    }
+
+    pub fn shutdown(&self) {
+        if let Some(provider) = &self.provider {
+            if let Err(err) = provider.shutdown() {
+                tracing::warn!(error = %err, "can't flush spans");
+            }
+        }
+    }
+}
+
+// Makes span a child of the caller's span when the request carries a W3C
+// traceparent header, and records the trace id so log lines can be matched
+// with traces.
+pub fn continue_trace(span: &tracing::Span, headers: &HeaderMap) {
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    if parent.span().span_context().is_valid() {
+        let _ = span.set_parent(parent);
+    }
+
+    let trace_id = span.context().span().span_context().trace_id();
+    if trace_id != TraceId::INVALID {
+        span.record("trace_id", trace_id.to_string());
+    }
+}
+
+// Renames an exported span once it has started, e.g. to the matched route;
+// recording otel.name only works before the span is entered.
+pub fn rename_span(span: &tracing::Span, name: String) {
+    span.context().span().update_name(name);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// JsonSpanExporter writes one JSON object per span, for environments without
+// a collector.
+pub struct JsonSpanExporter {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+pub fn new_json_span_exporter<W: Write + Send + 'static>(writer: W) -> JsonSpanExporter {
+    JsonSpanExporter {
+        writer: Mutex::new(Box::new(writer)),
+    }
+}
+
+impl fmt::Debug for JsonSpanExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JsonSpanExporter")
+    }
+}
+
+impl SpanExporter for JsonSpanExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut writer = self.writer.lock().unwrap();
+
+        for span in batch {
+            let line = span_to_json(&span).to_string();
+            if let Err(err) = writeln!(writer, "{}", line) {
+                return Err(OTelSdkError::InternalFailure(err.to_string()));
+            }
+        }
+
+        writer.flush().map_err(|err| OTelSdkError::InternalFailure(err.to_string()))
+    }
+}
+
+fn span_to_json(span: &SpanData) -> Value {
+    let attributes: Map<String, Value> = span.attributes.iter()
+        .map(|kv| (kv.key.to_string(), Value::String(kv.value.to_string())))
+        .collect();
+
+    let events: Vec<Value> = span.events.iter()
+        .map(|event| {
+            let attributes: Map<String, Value> = event.attributes.iter()
+                .map(|kv| (kv.key.to_string(), Value::String(kv.value.to_string())))
+                .collect();
+
+            json!({
+                "name": event.name,
+                "time": format_time(event.timestamp),
+                "attributes": attributes,
+            })
+        })
+        .collect();
+
+    let (status, description) = match &span.status {
+        Status::Unset => ("unset", None),
+        Status::Ok => ("ok", None),
+        Status::Error { description } => ("error", Some(description.to_string())),
+    };
+
+    let duration = span.end_time.duration_since(span.start_time).unwrap_or_default();
+
+    json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": span.parent_span_id.to_string(),
+        "name": span.name,
+        "kind": format!("{:?}", span.span_kind).to_lowercase(),
+        "start": format_time(span.start_time),
+        "duration_ms": duration.as_secs_f64() * 1000.0,
+        "status": status,
+        "status_description": description,
+        "attributes": attributes,
+        "events": events,
+    })
+}
+
+fn format_time(time: std::time::SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Micros, true)
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+    use serde_json::{json, Value};
+    use tracing::info_span;
+
+    use crate::config::config::Config;
+    use crate::pkg::logger::logger::new_logger;
+    use crate::pkg::logger::test_writer::{new_test_writer, TestWriter};
+    use crate::pkg::telemetry::telemetry::{
+        continue_trace, new_json_span_exporter, new_telemetry, Telemetry, EXPORTER_FILE,
+    };
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn find<'a>(spans: &'a [Value], name: &str) -> &'a Value {
+        spans.iter().find(|s| s["name"] == json!(name)).unwrap()
+    }
+
+    fn test_logger() -> (crate::pkg::logger::logger::Logger, TestWriter) {
+        let spans = new_test_writer();
+        let telemetry = Telemetry::with_exporter(new_json_span_exporter(spans.clone()));
+        let logger = new_logger("info", new_test_writer(), telemetry.tracer()).unwrap();
+
+        (logger, spans)
+    }
+
+    #[test]
+    fn export_spans_test() {
+        let (logger, spans) = test_logger();
+
+        tracing::dispatcher::with_default(logger.dispatch(), || {
+            let parent = info_span!("user_use_case.user_list");
+            let _enter = parent.enter();
+
+            let child = info_span!("user_repo.user_list", otel.kind = "client", db.statement = "SELECT 1", db.rows = 3);
+            let _child = child.enter();
+        });
+
+        let spans = spans.lines();
+        let parent = find(&spans, "user_use_case.user_list");
+        let child = find(&spans, "user_repo.user_list");
+
+        assert_eq!(child["trace_id"], parent["trace_id"]);
+        assert_eq!(child["parent_span_id"], parent["span_id"]);
+        assert_eq!(child["kind"], json!("client"));
+        assert_eq!(child["attributes"]["db.statement"], json!("SELECT 1"));
+        assert_eq!(child["attributes"]["db.rows"], json!("3"));
+    }
+
+    #[test]
+    fn continue_trace_test() {
+        let (logger, spans) = test_logger();
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static("traceparent"), HeaderValue::from_static(TRACEPARENT));
+
+        tracing::dispatcher::with_default(logger.dispatch(), || {
+            let span = info_span!("request", trace_id = tracing::field::Empty, otel.kind = "server");
+            continue_trace(&span, &headers);
+        });
+
+        let spans = spans.lines();
+        let request = find(&spans, "request");
+        assert_eq!(request["trace_id"], json!("4bf92f3577b34da6a3ce929d0e0e4736"));
+        assert_eq!(request["parent_span_id"], json!("00f067aa0ba902b7"));
+        assert_eq!(request["kind"], json!("server"));
+        assert_eq!(request["attributes"]["trace_id"], json!("4bf92f3577b34da6a3ce929d0e0e4736"));
+    }
+
+    #[test]
+    fn continue_trace_without_header_test() {
+        let (logger, spans) = test_logger();
+
+        tracing::dispatcher::with_default(logger.dispatch(), || {
+            let span = info_span!("request", trace_id = tracing::field::Empty);
+            continue_trace(&span, &HeaderMap::new());
+        });
+
+        let spans = spans.lines();
+        let request = find(&spans, "request");
+        assert_eq!(request["parent_span_id"], json!("0000000000000000"));
+        assert_eq!(request["attributes"]["trace_id"], request["trace_id"]);
+    }
+
+    #[test]
+    fn new_telemetry_test() {
+        let mut cfg = Config::default().telemetry;
+        assert!(new_telemetry(&cfg).unwrap().tracer().is_none());
+
+        let path = std::env::temp_dir().join(format!("spans-{}.json", uuid::Uuid::new_v4()));
+        cfg.exporter = EXPORTER_FILE.to_string();
+        cfg.file_path = path.to_string_lossy().to_string();
+
+        let telemetry = new_telemetry(&cfg).unwrap();
+        let logger = new_logger("info", new_test_writer(), telemetry.tracer()).unwrap();
+        tracing::dispatcher::with_default(logger.dispatch(), || {
+            let _span = info_span!("startup").entered();
+        });
+        telemetry.shutdown();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let span: Value = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(span["name"], json!("startup"));
+        std::fs::remove_file(&path).unwrap();
+
+        cfg.file_path = "/nonexistent/dir/spans.json".to_string();
+        assert!(new_telemetry(&cfg).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{any_supported_type, CertifiedKey};
+use rustls_pemfile::Item;
+
+use crate::pkg::shutdown::shutdown::Shutdown;
+
+// CertResolver serves the certificate from cert_path/key_path and swaps it in
+// place when the files change, so renewed certificates need no restart.
+pub struct CertResolver {
+    cert_path: String,
+    key_path: String,
+    state: RwLock<LoadedCert>,
+}
+
+struct LoadedCert {
+    key: Arc<CertifiedKey>,
+    pem: (Vec<u8>, Vec<u8>),
+}
+
+pub fn new_cert_resolver(cert_path: &str, key_path: &str) -> Result<CertResolver, String> {
+    let pem = read_pem(cert_path, key_path)?;
+    let key = load_certified_key(cert_path, key_path, &pem)?;
+
+    Ok(CertResolver {
+        cert_path: cert_path.to_string(),
+        key_path: key_path.to_string(),
+        state: RwLock::new(LoadedCert {
+            key: Arc::new(key),
+            pem,
+        }),
+    })
+}
+
+pub fn new_tls_server_config(resolver: Arc<CertResolver>) -> ServerConfig {
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver)
+}
+
+// Polls the certificate files every interval until shutdown; a failed reload
+// keeps serving the previous certificate.
+pub fn spawn_cert_reloader(resolver: Arc<CertResolver>, interval: Duration, shutdown: &Shutdown) {
+    let stop = shutdown.wait();
+
+    shutdown.spawn(async move {
+        tokio::pin!(stop);
+
+        loop {
+            tokio::select! {
+                _ = &mut stop => {
+                    return;
+                }
+                _ = tokio::time::sleep(interval) => {}
+            }
+
+            // Reading and parsing the files block; keep them off the async workers.
+            let reloader = resolver.clone();
+            match tokio::task::spawn_blocking(move || reloader.reload_if_changed()).await {
+                Ok(Ok(true)) => {
+                    tracing::info!(cert_path = %resolver.cert_path, "reloaded certificate");
+                }
+                Ok(Ok(false)) => {}
+                Ok(Err(err)) => {
+                    tracing::error!(cert_path = %resolver.cert_path, error = %err, "can't reload certificate");
+                }
+                Err(err) => {
+                    tracing::error!(cert_path = %resolver.cert_path, error = %err, "certificate reload task failed");
+                }
+            }
+        }
+    });
+}
+
+impl CertResolver {
+    // The files are read once and the key is built from those bytes, so what
+    // is served is always what was compared. A pair read halfway through a
+    // rotation differs from the final files and is replaced on the next poll.
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let pem = read_pem(&self.cert_path, &self.key_path)?;
+        if self.state.read().unwrap().pem == pem {
+            return Ok(false);
+        }
+
+        let key = load_certified_key(&self.cert_path, &self.key_path, &pem)?;
+        *self.state.write().unwrap() = LoadedCert {
+            key: Arc::new(key),
+            pem,
+        };
+
+        Ok(true)
+    }
+
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.state.read().unwrap().key.clone()
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn read_pem(cert_path: &str, key_path: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cert = std::fs::read(cert_path).map_err(|err| format!("can't read {}: {}", cert_path, err))?;
+    let key = std::fs::read(key_path).map_err(|err| format!("can't read {}: {}", key_path, err))?;
+
+    Ok((cert, key))
+}
+
+// Parses the PEM read by read_pem; the paths only name the files in errors.
+fn load_certified_key(cert_path: &str, key_path: &str, pem: &(Vec<u8>, Vec<u8>)) -> Result<CertifiedKey, String> {
+    let (cert, key) = pem;
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut cert.as_slice())
+        .map_err(|err| format!("can't parse {}: {}", cert_path, err))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certs.is_empty() {
+        return Err(format!("no certificate found in {}", cert_path));
+    }
+
+    let key = rustls_pemfile::read_all(&mut key.as_slice())
+        .map_err(|err| format!("can't parse {}: {}", key_path, err))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        });
+    let key = match key {
+        Some(res) => {
+            res
+        }
+        None => {
+            return Err(format!("no private key found in {}", key_path));
+        }
+    };
+
+    let signing_key = any_supported_type(&key).map_err(|_err| format!("unsupported private key in {}", key_path))?;
+
+    Ok(CertifiedKey::new(certs, signing_key))
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use rustls::Certificate;
+
+    use crate::pkg::tls::cert_resolver::new_cert_resolver;
+
+    struct TestCert {
+        cert_path: PathBuf,
+        key_path: PathBuf,
+    }
+
+    fn write_cert(dir: &Path, name: &str) -> (TestCert, Vec<u8>) {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let test_cert = TestCert {
+            cert_path: dir.join("cert.pem"),
+            key_path: dir.join("key.pem"),
+        };
+        let cert_pem = cert.serialize_pem().unwrap();
+        std::fs::write(&test_cert.cert_path, &cert_pem).unwrap();
+        std::fs::write(&test_cert.key_path, cert.serialize_private_key_pem()).unwrap();
+
+        let der = rustls_pemfile::certs(&mut cert_pem.as_bytes()).unwrap().remove(0);
+        (test_cert, der)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reload_if_changed_test() {
+        let dir = temp_dir();
+        let (paths, first_der) = write_cert(&dir, "localhost");
+        let cert_path = paths.cert_path.to_str().unwrap();
+        let key_path = paths.key_path.to_str().unwrap();
+
+        let resolver = new_cert_resolver(cert_path, key_path).unwrap();
+        assert_eq!(resolver.current().cert, vec![Certificate(first_der.clone())]);
+        assert_eq!(resolver.reload_if_changed(), Ok(false));
+
+        let (_, second_der) = write_cert(&dir, "example.com");
+        assert_eq!(resolver.reload_if_changed(), Ok(true));
+        assert_eq!(resolver.current().cert, vec![Certificate(second_der)]);
+
+        // Halfway through a rotation the new certificate is served with the
+        // old key; the new key is picked up once it lands.
+        let rotated = temp_dir();
+        let (next, third_der) = write_cert(&rotated, "example.org");
+        std::fs::copy(&next.cert_path, &paths.cert_path).unwrap();
+        assert_eq!(resolver.reload_if_changed(), Ok(true));
+        assert_eq!(resolver.current().cert, vec![Certificate(third_der.clone())]);
+        let old_key = resolver.current().key.clone();
+        std::fs::copy(&next.key_path, &paths.key_path).unwrap();
+        assert_eq!(resolver.reload_if_changed(), Ok(true));
+        assert!(!std::sync::Arc::ptr_eq(&resolver.current().key, &old_key));
+        assert_eq!(resolver.reload_if_changed(), Ok(false));
+        std::fs::remove_dir_all(rotated).unwrap();
+
+        std::fs::write(&paths.key_path, "not a key").unwrap();
+        assert!(resolver.reload_if_changed().is_err());
+        assert_eq!(resolver.current().cert, vec![Certificate(third_der)]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_files_test() {
+        let dir = temp_dir();
+        let cert_path = dir.join("cert.pem");
+        std::fs::write(&cert_path, "garbage").unwrap();
+
+        let res = new_cert_resolver(cert_path.to_str().unwrap(), "/nonexistent/key.pem");
+        assert!(res.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
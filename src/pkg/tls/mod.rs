@@ -0,0 +1,2 @@
+pub mod cert_resolver;
+pub mod cert_resolver_test;